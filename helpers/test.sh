@@ -29,3 +29,6 @@ cargo test --quiet --lib --all-features $1
 
 echo -e "\033[1;33mRunning doc tests (big)...\033[0m"
 cargo test --quiet --doc --all-features $1
+
+echo -e "\033[1;33mRunning end-to-end tests of the server...\033[0m"
+cargo test --quiet --package versatiles --features test --test e2e $1
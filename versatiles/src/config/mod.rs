@@ -0,0 +1,514 @@
+//! Configuration file for `versatiles serve`
+//!
+//! ```yaml
+//! server:
+//!   ip: 0.0.0.0
+//!   port: 8080
+//!   # add a timing breakdown to every tile response
+//!   trace: false
+//!   # enable the admin API "/api/jobs" for conversions of files in the folder "jobs"
+//!   jobs:
+//!     root: jobs
+//!     api_keys: ["admin-key"]
+//!   # cache up to 256 MB of tile responses in memory, each for at most one hour
+//!   tile_cache:
+//!     size_mb: 256
+//!     ttl_seconds: 3600
+//!
+//! # origins that may access all tile sources, defaults to "*"
+//! cors:
+//!   allowed_origins: ["https://example.org", "https://*.example.org"]
+//!
+//! tiles:
+//!   - name: osm
+//!     src: osm.versatiles
+//!     # publish subdomain URLs for legacy clients like Leaflet
+//!     tile_url: "https://{s}.tiles.example.org/tiles/osm"
+//!     subdomains: [a, b, c]
+//!     scheme: xyz
+//!     # MapLibre style used as template for "/tiles/osm/style.json"
+//!     style: osm-style.json
+//!     # only serve requests with an API key or a valid signed URL
+//!     auth:
+//!       api_keys: ["customer-a-key", "customer-b-key"]
+//!       signing_secret: "a long random string"
+//!     # translate property values of vector tiles with a CSV or JSON lookup table
+//!     lookups:
+//!       - src: kinds_de.csv
+//!         layer: place_labels
+//!         property: kind
+//!         target: kind_de
+//!   # serve each tile from the first container that has it, e.g. a fresh regional extract over an older planet
+//!   - name: planet
+//!     src: [berlin-2026-10.versatiles, planet-2026-01.versatiles]
+//!     # send the tiles as stored if the client accepts their compression, instead of recompressing them
+//!     minimal_recompression: true
+//!     # answer requests for fully transparent or featureless tiles with "204 No Content"
+//!     no_content_for_blank_tiles: true
+//!
+//! static:
+//!   - src: frontend.tar.br
+//!     prefix: /
+//! ```
+//!
+//! Responses use brotli if the client accepts it, then zstd, then gzip, and are sent uncompressed otherwise.
+//! With `minimal_recompression`, the compression of the source is kept whenever the client accepts it.
+//!
+//! API keys are sent in the header "X-API-Key" or the query parameter "key". Signed URLs have the
+//! query parameters "expires" (a Unix timestamp) and "signature", the hex encoded HMAC-SHA256 of
+//! "{path}?expires={expires}" with the signing secret, e.g. "/tiles/osm/3/4/2?expires=1767225600".
+//!
+//! Relative paths are resolved relative to the directory of the configuration file.
+//! `versatiles schema config` prints the JSON Schema of this file, e.g. for validation in an IDE.
+//!
+//! On Unix, sending SIGHUP to the server reloads this file and reopens all tile and static sources.
+//! Open connections are kept, and if the new configuration is invalid, the previous sources stay active.
+
+use crate::tools::server::{Auth, Cors, JobsOptions, PropertyLookup, TileCacheOptions, TileSourceOptions};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+use std::{path::Path, time::Duration};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::TileScheme,
+};
+use versatiles_derive::ConfigDoc;
+
+/// Configuration file for "versatiles serve"
+#[derive(ConfigDoc, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	pub server: ServerConfig,
+	/// origins that may access all tile sources
+	pub cors: CorsConfig,
+	/// tile sources, served at "/tiles/<name>/"
+	pub tiles: Vec<TileSourceConfig>,
+	/// static sources, the first hit is served
+	#[serde(rename = "static")]
+	pub static_sources: Vec<StaticSourceConfig>,
+}
+
+#[derive(ConfigDoc, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+	/// serve via socket ip, defaults to "0.0.0.0"
+	pub ip: Option<String>,
+	/// serve via port, defaults to 8080
+	pub port: Option<u16>,
+	/// use minimal recompression to reduce server response time: content is only recompressed if the client
+	/// doesn't accept its compression
+	pub minimal_recompression: Option<bool>,
+	pub disable_api: Option<bool>,
+	/// add a timing breakdown to every tile response
+	pub trace: Option<bool>,
+	/// enable the admin API "/api/jobs" for conversions on the server
+	pub jobs: Option<JobsConfig>,
+	/// cache encoded tile responses in memory
+	pub tile_cache: Option<TileCacheConfig>,
+}
+
+#[derive(ConfigDoc, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TileCacheConfig {
+	/// maximum size of the cache in megabytes
+	pub size_mb: u64,
+	/// seconds after which a cached tile is read again, by default tiles are kept until they are evicted
+	pub ttl_seconds: Option<u64>,
+}
+
+impl TileCacheConfig {
+	pub fn options(&self) -> TileCacheOptions {
+		TileCacheOptions {
+			max_bytes: self.size_mb * 1024 * 1024,
+			ttl: self.ttl_seconds.map(Duration::from_secs),
+		}
+	}
+}
+
+#[derive(ConfigDoc, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct JobsConfig {
+	/// folder with the inputs and outputs of all jobs, jobs can't access files outside of it
+	pub root: String,
+	/// API keys that are accepted in the header "X-API-Key" or the query parameter "key"
+	pub api_keys: Vec<String>,
+}
+
+impl JobsConfig {
+	pub fn options(&self) -> Result<JobsOptions> {
+		JobsOptions::new(Path::new(&self.root), &self.api_keys)
+	}
+}
+
+#[derive(ConfigDoc, Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+	/// allowed origins, may contain wildcards like "https://*.example.org", defaults to "*"
+	pub allowed_origins: Vec<String>,
+}
+
+impl Default for CorsConfig {
+	fn default() -> Self {
+		CorsConfig {
+			allowed_origins: vec![String::from("*")],
+		}
+	}
+}
+
+#[derive(ConfigDoc, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TileSourceConfig {
+	/// id used in the URL "/tiles/<name>/", defaults to the filename without extension
+	pub name: Option<String>,
+	/// filename or URL of the tile container, or a list of them: each tile is served from the first container that has it
+	pub src: TileSourcePaths,
+	/// public tile URL, e.g. "https://{s}.tiles.example.org/tiles/osm"
+	pub tile_url: Option<String>,
+	/// subdomains that replace "{s}" in the tile URL
+	#[serde(default)]
+	pub subdomains: Vec<String>,
+	/// URL scheme of the tiles, defaults to "xyz"
+	#[serde(default, deserialize_with = "deserialize_scheme")]
+	#[config_doc(values = "xyz, tms")]
+	pub scheme: TileScheme,
+	/// overrides the global CORS configuration for this source
+	pub cors: Option<CorsConfig>,
+	/// requires an API key or a signed URL for this source
+	pub auth: Option<AuthConfig>,
+	/// MapLibre style file used as template for "style.json"
+	pub style: Option<String>,
+	/// lookup tables that translate property values of vector tiles while serving
+	#[serde(default)]
+	pub lookups: Vec<LookupConfig>,
+	/// overrides "server.minimal_recompression" for this source
+	pub minimal_recompression: Option<bool>,
+	/// answer requests for blank tiles, i.e. fully transparent images or vector tiles without features,
+	/// with "204 No Content", defaults to false
+	pub no_content_for_blank_tiles: Option<bool>,
+}
+
+/// One or more filenames or URLs of tile containers, sorted by priority
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileSourcePaths(pub Vec<String>);
+
+impl TileSourcePaths {
+	/// The container with the highest priority, used e.g. for the default name
+	pub fn first(&self) -> &str {
+		&self.0[0]
+	}
+
+	pub fn get_json_schema() -> JsonValue {
+		let string = || JsonValue::from(vec![("type", "string")]);
+		JsonValue::from(vec![(
+			"anyOf",
+			JsonValue::from(vec![
+				string(),
+				JsonValue::from(vec![
+					("type", JsonValue::from("array")),
+					("items", string()),
+					("minItems", JsonValue::from(1)),
+				]),
+			]),
+		)])
+	}
+}
+
+impl<'de> Deserialize<'de> for TileSourcePaths {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Paths {
+			One(String),
+			List(Vec<String>),
+		}
+		match Paths::deserialize(deserializer)? {
+			Paths::One(path) => Ok(TileSourcePaths(vec![path])),
+			Paths::List(paths) if paths.is_empty() => Err(serde::de::Error::invalid_length(0, &"at least one source")),
+			Paths::List(paths) => Ok(TileSourcePaths(paths)),
+		}
+	}
+}
+
+#[derive(ConfigDoc, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+	/// API keys that are accepted in the header "X-API-Key" or the query parameter "key"
+	pub api_keys: Vec<String>,
+	/// secret for validating signed URLs with the query parameters "expires" and "signature"
+	pub signing_secret: Option<String>,
+}
+
+#[derive(ConfigDoc, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LookupConfig {
+	/// CSV file with a header, or JSON object, mapping old values to new values
+	pub src: String,
+	/// only translate features of this layer, defaults to all layers
+	pub layer: Option<String>,
+	/// property whose value is looked up
+	pub property: String,
+	/// property that is set to the new value, defaults to "property"
+	pub target: Option<String>,
+}
+
+#[derive(ConfigDoc, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StaticSourceConfig {
+	/// folder, tar file (.tar / .tar.gz / .tar.br) or zip file
+	pub src: String,
+	/// URL prefix, defaults to "/"
+	pub prefix: Option<String>,
+	/// overrides "server.minimal_recompression" for this source
+	pub minimal_recompression: Option<bool>,
+}
+
+fn deserialize_scheme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TileScheme, D::Error> {
+	#[derive(Deserialize)]
+	#[serde(rename_all = "lowercase")]
+	enum Scheme {
+		Xyz,
+		Tms,
+	}
+	Ok(match Scheme::deserialize(deserializer)? {
+		Scheme::Xyz => TileScheme::Xyz,
+		Scheme::Tms => TileScheme::Tms,
+	})
+}
+
+impl Config {
+	pub fn from_path(path: &Path) -> Result<Config> {
+		let text = std::fs::read_to_string(path).with_context(|| format!("reading config file {path:?}"))?;
+		let mut config = Config::from_string(&text).with_context(|| format!("parsing config file {path:?}"))?;
+		if let Some(dir) = path.parent() {
+			config.resolve_paths(dir);
+		}
+		Ok(config)
+	}
+
+	pub fn from_string(text: &str) -> Result<Config> {
+		Ok(serde_yaml_ng::from_str(text)?)
+	}
+
+	fn resolve_paths(&mut self, dir: &Path) {
+		let resolve = |src: &mut String| {
+			if !src.contains("://") {
+				*src = dir.join(&*src).to_string_lossy().to_string();
+			}
+		};
+		self.tiles.iter_mut().for_each(|t| {
+			t.src.0.iter_mut().for_each(resolve);
+			t.style.iter_mut().for_each(resolve);
+			t.lookups.iter_mut().for_each(|l| resolve(&mut l.src));
+		});
+		self.static_sources.iter_mut().for_each(|s| resolve(&mut s.src));
+		self.server.jobs.iter_mut().for_each(|j| resolve(&mut j.root));
+	}
+
+	/// CORS configuration for tile sources without their own
+	pub fn default_cors(&self) -> Result<Cors> {
+		Cors::new(&self.cors.allowed_origins)
+	}
+
+	pub fn tile_source_options(&self, tiles: &TileSourceConfig) -> Result<TileSourceOptions> {
+		Ok(TileSourceOptions {
+			tile_url: tiles.tile_url.clone(),
+			subdomains: tiles.subdomains.clone(),
+			scheme: tiles.scheme,
+			cors: match &tiles.cors {
+				Some(cors) => Cors::new(&cors.allowed_origins)?,
+				None => self.default_cors()?,
+			},
+			auth: match &tiles.auth {
+				Some(auth) => Auth::new(&auth.api_keys, auth.signing_secret.as_deref())?,
+				None => Auth::default(),
+			},
+			style_template: match &tiles.style {
+				Some(path) => {
+					let text = std::fs::read_to_string(path).with_context(|| format!("reading style {path:?}"))?;
+					Some(JsonObject::parse_str(&text).with_context(|| format!("parsing style {path:?}"))?)
+				}
+				None => None,
+			},
+			lookups: tiles
+				.lookups
+				.iter()
+				.map(|lookup| {
+					PropertyLookup::from_path(
+						Path::new(&lookup.src),
+						lookup.layer.clone(),
+						lookup.property.clone(),
+						lookup.target.clone(),
+					)
+				})
+				.collect::<Result<_>>()?,
+			minimal_recompression: tiles.minimal_recompression,
+			no_content_for_blank_tiles: tiles.no_content_for_blank_tiles.unwrap_or(false),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+
+	#[test]
+	fn test_parse() -> Result<()> {
+		let config = Config::from_string(
+			"
+server:
+  port: 8081
+  trace: true
+  jobs:
+    root: /data/jobs
+    api_keys: [admin-key]
+  tile_cache:
+    size_mb: 64
+    ttl_seconds: 60
+cors:
+  allowed_origins: [\"https://example.org\"]
+tiles:
+  - src: osm.versatiles
+  - name: legacy
+    src: https://download.example.org/legacy.versatiles
+    tile_url: \"https://{s}.example.org/tiles/legacy\"
+    subdomains: [a, b]
+    scheme: tms
+    cors:
+      allowed_origins: [\"*\"]
+    auth:
+      api_keys: [customer-a]
+    minimal_recompression: true
+    no_content_for_blank_tiles: true
+static:
+  - src: frontend.tar
+    minimal_recompression: false
+",
+		)?;
+
+		assert_eq!(config.server.port, Some(8081));
+		assert_eq!(config.server.ip, None);
+		assert_eq!(config.server.trace, Some(true));
+		assert_eq!(
+			config.server.jobs,
+			Some(JobsConfig {
+				root: String::from("/data/jobs"),
+				api_keys: vec![String::from("admin-key")],
+			})
+		);
+		assert_eq!(
+			config.server.tile_cache.as_ref().unwrap().options(),
+			TileCacheOptions {
+				max_bytes: 64 * 1024 * 1024,
+				ttl: Some(Duration::from_secs(60)),
+			}
+		);
+		assert_eq!(config.tiles.len(), 2);
+		assert_eq!(config.tiles[0].src.0, ["osm.versatiles"]);
+		assert_eq!(config.static_sources[0].src, "frontend.tar");
+		assert_eq!(config.static_sources[0].minimal_recompression, Some(false));
+
+		let options = config.tile_source_options(&config.tiles[0])?;
+		assert_eq!(
+			options,
+			TileSourceOptions {
+				cors: Cors::new(&[String::from("https://example.org")])?,
+				..Default::default()
+			}
+		);
+
+		let options = config.tile_source_options(&config.tiles[1])?;
+		assert_eq!(
+			options.tile_url.as_deref(),
+			Some("https://{s}.example.org/tiles/legacy")
+		);
+		assert_eq!(options.subdomains, ["a", "b"]);
+		assert_eq!(options.scheme, TileScheme::Tms);
+		assert_eq!(options.cors, Cors::any());
+		assert_eq!(options.auth, Auth::new(&[String::from("customer-a")], None)?);
+		assert_eq!(options.minimal_recompression, Some(true));
+		assert!(options.no_content_for_blank_tiles);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_errors() {
+		let error = |text: &str| Config::from_string(text).unwrap_err().to_string();
+		assert!(error("tilez: []").starts_with("unknown field `tilez`"));
+		assert!(error("tiles: [{src: a.mbtiles, scheme: zxy}]").starts_with("tiles[0].scheme: unknown variant `zxy`"));
+		assert!(error("tiles: [{name: a}]").starts_with("tiles[0]: missing field `src`"));
+		assert!(error("tiles: [{src: []}]").starts_with("tiles[0]: invalid length 0, expected at least one source"));
+	}
+
+	#[test]
+	fn test_json_schema() -> Result<()> {
+		let schema = Config::get_json_schema().to_object()?;
+		assert_eq!(schema.get("additionalProperties"), Some(&JsonValue::from(false)));
+		assert_eq!(schema.get("required"), None);
+
+		let properties = schema.get("properties").unwrap().as_object()?;
+		let keys: Vec<&String> = properties.iter().map(|(key, _)| key).collect();
+		assert_eq!(keys, ["cors", "server", "static", "tiles"]);
+
+		let port = properties
+			.get("server")
+			.unwrap()
+			.as_object()?
+			.get("properties")
+			.unwrap();
+		assert_eq!(
+			port.as_object()?.get("port").unwrap().stringify(),
+			"{\"description\":\"serve via port, defaults to 8080\",\"maximum\":65535,\"minimum\":0,\"type\":\"integer\"}"
+		);
+
+		let tiles = properties.get("tiles").unwrap().as_object()?;
+		assert_eq!(tiles.get_string("type")?.as_deref(), Some("array"));
+		let tile_source = tiles.get("items").unwrap().as_object()?;
+		assert_eq!(tile_source.get_string_vec("required")?, Some(vec![String::from("src")]));
+		assert_eq!(
+			tile_source.get("properties").unwrap().as_object()?.get("scheme").unwrap().stringify(),
+			"{\"description\":\"URL scheme of the tiles, defaults to \\\"xyz\\\"\",\"enum\":[\"xyz\",\"tms\"],\"type\":\"string\"}"
+		);
+		assert_eq!(
+			tile_source.get("properties").unwrap().as_object()?.get("src").unwrap().stringify(),
+			"{\"anyOf\":[{\"type\":\"string\"},{\"items\":{\"type\":\"string\"},\"minItems\":1,\"type\":\"array\"}],\"description\":\"filename or URL of the tile container, or a list of them: each tile is served from the first container that has it\"}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_from_path() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("config.yaml");
+		std::fs::write(
+			&path,
+			"server: {jobs: {root: jobs, api_keys: [admin-key]}}\ntiles: [{src: osm.versatiles, style: style.json, lookups: [{src: kinds.json, property: kind}]}, {src: \"https://example.org/osm.versatiles\"}]",
+		)?;
+		std::fs::create_dir(dir.path().join("jobs"))?;
+		std::fs::write(dir.path().join("style.json"), "{\"version\":8,\"layers\":[]}")?;
+		std::fs::write(dir.path().join("kinds.json"), "{\"city\":\"Stadt\"}")?;
+
+		let config = Config::from_path(&path)?;
+		assert_eq!(
+			config.tiles[0].src.first(),
+			dir.path().join("osm.versatiles").to_string_lossy()
+		);
+		assert_eq!(config.tiles[1].src.first(), "https://example.org/osm.versatiles");
+		assert_eq!(config.cors, CorsConfig::default());
+
+		let options = config.tile_source_options(&config.tiles[0])?;
+		assert_eq!(
+			options.style_template.unwrap().stringify(),
+			"{\"layers\":[],\"version\":8}"
+		);
+		assert_eq!(options.lookups.len(), 1);
+		assert_eq!(options.lookups[0].target, "kind");
+		assert_eq!(options.lookups[0].table.len(), 1);
+
+		let jobs = config.server.jobs.unwrap().options()?;
+		assert_eq!(jobs.root(), dir.path().join("jobs").canonicalize()?);
+
+		Ok(())
+	}
+}
@@ -5,22 +5,30 @@
 //! ## Features
 //! - **Read and Write**: Supports reading and writing various tile container formats.
 //! - **Convert**: Convert between different tile formats and compressions.
+//! - **Serve**: The tile server is available in [`server`] with the `server` feature, e.g. for integration tests.
 //!
 //! ## Supported Formats
 //! - `*.versatiles`
-//! - `*.mbtiles` (requires `full` feature)
-//! - `*.pmtiles` (requires `full` feature)
-//! - `*.tar` (requires `full` feature)
+//! - `*.pmtiles`
+//! - `*.comt`
+//! - `*.tar`
 //! - tiles stored in a local directory
+//! - `*.mbtiles` (requires `full` feature)
+//! - `*.gpkg` (requires `full` feature)
+//! - `*.vpl` pipelines (requires `full` feature)
+//!
+//! The `full` feature also adds reading containers from URLs and recompressing raster tiles.
+//! Without it, e.g. with the `minimal` feature, only the first five formats are available.
+//!
+//! ## Stability
+//! The [`prelude`] contains the stable API, which follows semantic versioning.
+//! All other modules re-export internals of the workspace crates. They require the opt-in `unstable` feature
+//! and may change in any release.
 //!
 //! ## Usage Example
 //!
 //! ```rust
-//! use versatiles::{
-//!     container::{get_reader, write_to_filename},
-//!     types::{TileFormat, TilesReaderTrait}
-//! };
-//! use std::path::Path;
+//! use versatiles::prelude::*;
 //! use anyhow::Result;
 //!
 //! #[tokio::main]
@@ -40,9 +48,19 @@
 //! }
 //! ```
 
+pub mod prelude;
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "unstable")]
 pub use versatiles_container as container;
+#[cfg(feature = "unstable")]
 pub use versatiles_core::*;
+#[cfg(feature = "unstable")]
 pub use versatiles_derive as derive;
+#[cfg(feature = "unstable")]
 pub use versatiles_geometry as geometry;
+#[cfg(feature = "unstable")]
 pub use versatiles_image as image;
+#[cfg(feature = "unstable")]
 pub use versatiles_pipeline as pipeline;
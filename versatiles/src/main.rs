@@ -3,9 +3,18 @@
 //! VersaTiles is a command-line tool for converting, probing, and serving map tiles in various formats.
 //!
 //! ## Subcommands
+//! - **Bench**: Run reproducible benchmarks and compare their results (feature `bench`).
 //! - **Convert**: Convert between different tile containers.
+//! - **Diff**: Compare the tiles of two tile containers.
+//! - **Export**: Export the features of vector tiles as NDGeoJSON.
+//! - **Merge**: Merge multiple tile containers into one.
+//! - **Outline**: Export the area covered by the tiles of each zoom level as GeoJSON.
 //! - **Probe**: Show information about a tile container.
+//! - **Run**: Run a pipeline that writes its tiles into containers.
+//! - **Schema**: Print the JSON Schema of the config file or the probe output.
 //! - **Serve**: Serve tiles via HTTP.
+//! - **Show**: Preview a single tile in the terminal.
+//! - **Verify**: Check the checksums and signature of a `*.versatiles` file.
 //!
 //! ## Usage
 //! ```sh
@@ -14,22 +23,58 @@
 //!
 //! ## Example
 //! ```sh
+//! # Benchmark this release and compare it with the results of the last one
+//! versatiles bench run --output new.json
+//! versatiles bench compare old.json new.json
+//!
 //! # Convert tiles between different formats
 //! versatiles convert --input input_file --output output_file
 //!
+//! # List the tiles that differ between two versions of a container
+//! versatiles diff --ndjson changes.ndjson old.versatiles new.versatiles
+//!
+//! # Export the buildings of the highest zoom level as NDGeoJSON
+//! versatiles export --layers buildings --output buildings.ndgeojson tile_file
+//!
+//! # Merge regional extracts into one container
+//! versatiles merge north.versatiles south.versatiles merged.versatiles
+//!
+//! # Find gaps in the tiles of zoom level 14
+//! versatiles outline --zoom 14 --output coverage.geojson tile_file
+//!
 //! # Probe information about a tile container
 //! versatiles probe --file tile_file
 //!
+//! # Run a pipeline that ends with one or more `to_container` operations
+//! versatiles run pipeline.vpl
+//!
+//! # Print the JSON Schema of the config file, e.g. for validation in an IDE
+//! versatiles schema config > versatiles-config.schema.json
+//!
 //! # Serve tiles via HTTP
 //! versatiles serve --port 8080 --dir /path/to/tiles
+//!
+//! # Preview a tile in the terminal
+//! versatiles show tile_file 14 8800 5373
+//!
+//! # Check a downloaded file against its checksums and the key of the publisher
+//! versatiles verify --public-key 3d4017c3... planet.versatiles
 //! ```
 
 // Import necessary modules and dependencies
+#[cfg(feature = "cli")]
+mod config;
 mod tools;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use log::LevelFilter;
+use clap::{Parser, Subcommand, ValueEnum};
+use log::{LevelFilter, Record};
+use std::{io::Write, path::PathBuf};
+use versatiles_core::{
+	json::JsonObject,
+	progress,
+	utils::{set_ignore_file_locks, set_max_memory, set_temp_dir},
+};
 
 /// Command-line interface for VersaTiles
 #[derive(Parser, Debug)]
@@ -71,22 +116,119 @@ struct Cli {
 		display_order = 100,
 	)]
 	verbose: u8,
+
+	#[arg(
+		long,
+		value_enum,
+		global = true,
+		default_value_t = LogFormat::Text,
+		help = "Format of log messages and progress on stderr",
+		long_help = "Format of log messages and progress on stderr.\n\
+			- `text` prints human readable messages and progress bars\n\
+			- `json` prints one JSON object per line (NDJSON) with the fields \"type\" (\"log\" or \"progress\"), \"time\" (milliseconds since 1970) and the event specific fields, so that log pipelines can parse them.",
+		display_order = 100,
+	)]
+	log_format: LogFormat,
+
+	#[arg(
+		long,
+		global = true,
+		value_name = "DIR",
+		help = "Directory for temporary files [default: the temporary directory of the system]",
+		long_help = "Directory for temporary files, e.g. downloads and the sorted index of large PMTiles files.\n\
+			Use it if the temporary directory of the system is too small.",
+		display_order = 100
+	)]
+	tmp_dir: Option<PathBuf>,
+
+	#[arg(
+		long,
+		global = true,
+		value_name = "MB",
+		value_parser = clap::value_parser!(u64).range(1..),
+		help = "Memory budget of conversions in megabytes [default: 1024]",
+		long_help = "Memory budget of conversions in megabytes [default: 1024].\n\
+			Readers load large zoom levels in chunks, writers queue fewer tiles and PMTiles writers sort their index \
+			in temporary files, so that large conversions don't run out of memory. Use it on machines with little memory.",
+		display_order = 100
+	)]
+	max_memory: Option<u64>,
+
+	#[arg(
+		long,
+		global = true,
+		help = "Ignore the locks of files that are being written by other processes",
+		long_help = "Ignore the locks of files that are being written by other processes.\n\
+			Writers lock their output file, e.g. with \"tiles.versatiles.lock\", and readers refuse locked files, \
+			because they are incomplete. Use it if the lock is left behind by mistake, e.g. on a network file system.",
+		display_order = 100
+	)]
+	force: bool,
+}
+
+/// Output format of log messages and progress
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum LogFormat {
+	Text,
+	Json,
 }
 
 /// Define subcommands for the command-line interface
 #[derive(Subcommand, Debug)]
 enum Commands {
+	#[cfg(feature = "bench")]
+	/// Run reproducible benchmarks, or compare their results to find performance regressions
+	Bench(tools::bench::Subcommand),
+
 	#[clap(alias = "converter")]
 	/// Convert between different tile containers
-	Convert(tools::convert::Subcommand),
+	Convert(Box<tools::convert::Subcommand>),
+
+	#[cfg(feature = "cli")]
+	/// Compare the tiles of two tile containers
+	Diff(tools::diff::Subcommand),
+
+	#[cfg(feature = "cli")]
+	/// Export the features of vector tiles as NDGeoJSON
+	Export(tools::export::Subcommand),
+
+	#[cfg(feature = "cli")]
+	/// Merge multiple tile containers into one
+	Merge(tools::merge::Subcommand),
+
+	#[cfg(feature = "cli")]
+	/// Export the area covered by the tiles of each zoom level as GeoJSON, with holes for missing tiles
+	Outline(tools::outline::Subcommand),
 
 	/// Show information about a tile container
 	Probe(tools::probe::Subcommand),
 
+	#[cfg(feature = "cli")]
+	/// Run a pipeline that writes its tiles into containers
+	Run(tools::run::Subcommand),
+
+	#[cfg(feature = "cli")]
+	/// Print the JSON Schema of the config file or the probe output
+	Schema(tools::schema::Subcommand),
+
+	#[cfg(feature = "cli")]
 	#[clap(alias = "server")]
 	/// Serve tiles via http
 	Serve(tools::serve::Subcommand),
 
+	#[cfg(feature = "cli")]
+	/// Preview a single tile in the terminal
+	Show(tools::show::Subcommand),
+
+	#[cfg(feature = "cli")]
+	/// Update a *.versatiles file with the tiles of another container, reusing unchanged blocks
+	Update(tools::update::Subcommand),
+
+	#[cfg(feature = "cli")]
+	/// Check the checksums and signature of a *.versatiles file
+	Verify(tools::verify::Subcommand),
+
+	#[cfg(feature = "cli")]
 	/// Show detailed help
 	Help(tools::help::Subcommand),
 }
@@ -106,30 +248,74 @@ fn main() -> Result<()> {
 		4..=i16::MAX => LevelFilter::Trace,
 	};
 
-	env_logger::Builder::new()
-		.filter_level(log_level)
-		.format_timestamp(None)
-		.init();
+	let mut logger = env_logger::Builder::new();
+	logger.filter_level(log_level).format_timestamp(None);
+	if cli.log_format == LogFormat::Json {
+		logger.format(|buf, record| writeln!(buf, "{}", format_json_record(record)));
+		progress::set_json_output(true);
+	}
+	logger.init();
 
 	run(cli)
 }
 
+/// Formats a log record as a single line of JSON
+fn format_json_record(record: &Record) -> String {
+	let mut object = JsonObject::default();
+	object.set("type", "log");
+	object.set("time", progress::unix_millis());
+	object.set("level", record.level().as_str().to_lowercase());
+	object.set("target", record.target());
+	object.set("message", record.args().to_string());
+	object.stringify()
+}
+
 /// Helper function for running subcommands
 fn run(cli: Cli) -> Result<()> {
+	set_temp_dir(cli.tmp_dir.clone())?;
+	if let Some(max_memory) = cli.max_memory {
+		set_max_memory((max_memory as usize).saturating_mul(1024 * 1024));
+	}
+	set_ignore_file_locks(cli.force);
+
 	match &cli.command {
+		#[cfg(feature = "bench")]
+		Commands::Bench(arguments) => tools::bench::run(arguments),
 		Commands::Convert(arguments) => tools::convert::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Diff(arguments) => tools::diff::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Export(arguments) => tools::export::run(arguments),
+		#[cfg(feature = "cli")]
 		Commands::Help(arguments) => tools::help::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Merge(arguments) => tools::merge::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Outline(arguments) => tools::outline::run(arguments),
 		Commands::Probe(arguments) => tools::probe::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Run(arguments) => tools::run::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Schema(arguments) => tools::schema::run(arguments),
+		#[cfg(feature = "cli")]
 		Commands::Serve(arguments) => tools::serve::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Show(arguments) => tools::show::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Update(arguments) => tools::update::run(arguments),
+		#[cfg(feature = "cli")]
+		Commands::Verify(arguments) => tools::verify::run(arguments),
 	}
 }
 
 /// Unit tests for the command-line interface
 #[cfg(test)]
 mod tests {
-	use crate::{run, Cli};
+	use crate::{format_json_record, run, Cli};
 	use anyhow::Result;
 	use clap::Parser;
+	use log::{Level, Record};
+	use versatiles_core::json::JsonObject;
 
 	/// Function for running command-line arguments in tests
 	pub fn run_command(arg_vec: Vec<&str>) -> Result<String> {
@@ -153,6 +339,14 @@ mod tests {
 		assert!(err.starts_with("versatiles "));
 	}
 
+	/// Test for subcommand 'bench'
+	#[cfg(feature = "bench")]
+	#[test]
+	fn bench_subcommand() {
+		let output = run_command(vec!["versatiles", "bench"]).unwrap_err().to_string();
+		assert!(output.starts_with("Run reproducible benchmarks"), "{output}");
+	}
+
 	/// Test for subcommand 'convert'
 	#[test]
 	fn convert_subcommand() {
@@ -163,6 +357,97 @@ mod tests {
 		);
 	}
 
+	/// Test for the global option '--tmp-dir'
+	#[test]
+	fn tmp_dir_option() {
+		let output = run_command(vec![
+			"versatiles",
+			"probe",
+			"--tmp-dir",
+			"../testdata/does_not_exist",
+			"../testdata/berlin.mbtiles",
+		])
+		.unwrap_err()
+		.to_string();
+		assert_eq!(
+			output,
+			"temporary directory \"../testdata/does_not_exist\" does not exist"
+		);
+	}
+
+	/// Test for the global option '--force'
+	#[test]
+	fn force_option() {
+		let dir = assert_fs::TempDir::new().unwrap();
+		let path = dir.path().join("berlin.pmtiles");
+		std::fs::copy("../testdata/berlin.pmtiles", &path).unwrap();
+		let filename = path.to_str().unwrap();
+
+		let lock = versatiles_core::utils::FileLock::lock_for_writing(&path).unwrap();
+		let error = run_command(vec!["versatiles", "probe", filename])
+			.unwrap_err()
+			.to_string();
+		assert!(error.contains("is being written by PID"), "{error}");
+		drop(lock);
+
+		// only parsed, because running with the flag would change the setting for tests running in parallel
+		let cli = Cli::try_parse_from(vec!["versatiles", "probe", filename, "--force"]).unwrap();
+		assert!(cli.force);
+	}
+
+	/// Test for the global option '--max-memory'
+	#[test]
+	fn max_memory_option() {
+		// only parsed, because running with the option would change the setting for tests running in parallel
+		let cli = Cli::try_parse_from(vec!["versatiles", "probe", "a.pmtiles", "--max-memory", "512"]).unwrap();
+		assert_eq!(cli.max_memory, Some(512));
+
+		let error = Cli::try_parse_from(vec!["versatiles", "probe", "a.pmtiles", "--max-memory", "0"])
+			.unwrap_err()
+			.to_string();
+		assert!(error.contains("invalid value '0' for '--max-memory <MB>'"), "{error}");
+	}
+
+	/// Test for subcommand 'diff'
+	#[test]
+	fn diff_subcommand() {
+		let output = run_command(vec!["versatiles", "diff"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Compare the tiles of two tile containers"),
+			"{output}"
+		);
+	}
+
+	/// Test for subcommand 'export'
+	#[test]
+	fn export_subcommand() {
+		let output = run_command(vec!["versatiles", "export"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Export the features of vector tiles as NDGeoJSON"),
+			"{output}"
+		);
+	}
+
+	/// Test for subcommand 'merge'
+	#[test]
+	fn merge_subcommand() {
+		let output = run_command(vec!["versatiles", "merge"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Merge multiple tile containers into one"),
+			"{output}"
+		);
+	}
+
+	/// Test for subcommand 'outline'
+	#[test]
+	fn outline_subcommand() {
+		let output = run_command(vec!["versatiles", "outline"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Export the area covered by the tiles of each zoom level as GeoJSON"),
+			"{output}"
+		);
+	}
+
 	/// Test for subcommand 'probe'
 	#[test]
 	fn probe_subcommand() {
@@ -173,10 +458,81 @@ mod tests {
 		);
 	}
 
+	/// Test for subcommand 'run'
+	#[test]
+	fn run_subcommand() {
+		let output = run_command(vec!["versatiles", "run"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Run a pipeline that writes its tiles into containers"),
+			"{output}"
+		);
+	}
+
+	/// Test for subcommand 'schema'
+	#[test]
+	fn schema_subcommand() {
+		let output = run_command(vec!["versatiles", "schema"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Print the JSON Schema of the config file or the probe output"),
+			"{output}"
+		);
+	}
+
 	/// Test for subcommand 'serve'
 	#[test]
 	fn serve_subcommand() {
 		let output = run_command(vec!["versatiles", "serve"]).unwrap_err().to_string();
 		assert!(output.starts_with("Serve tiles via http"), "{output}");
 	}
+
+	/// Test for subcommand 'show'
+	#[test]
+	fn show_subcommand() {
+		let output = run_command(vec!["versatiles", "show"]).unwrap_err().to_string();
+		assert!(output.starts_with("Preview a single tile in the terminal"), "{output}");
+	}
+
+	/// Test for subcommand 'update'
+	#[test]
+	fn update_subcommand() {
+		let output = run_command(vec!["versatiles", "update"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Update a *.versatiles file with the tiles of another container"),
+			"{output}"
+		);
+	}
+
+	/// Test for subcommand 'verify'
+	#[test]
+	fn verify_subcommand() {
+		let output = run_command(vec!["versatiles", "verify"]).unwrap_err().to_string();
+		assert!(
+			output.starts_with("Check the checksums and signature of a *.versatiles file"),
+			"{output}"
+		);
+	}
+
+	/// Test the NDJSON format of log messages
+	#[test]
+	fn json_log_record() -> Result<()> {
+		let line = format_json_record(
+			&Record::builder()
+				.args(format_args!("tile \"3/1/2\" is missing"))
+				.level(Level::Warn)
+				.target("versatiles::serve")
+				.build(),
+		);
+		assert!(!line.contains('\n'));
+
+		let object = JsonObject::parse_str(&line)?;
+		assert_eq!(object.get_string("type")?.as_deref(), Some("log"));
+		assert_eq!(object.get_string("level")?.as_deref(), Some("warn"));
+		assert_eq!(object.get_string("target")?.as_deref(), Some("versatiles::serve"));
+		assert_eq!(
+			object.get_string("message")?.as_deref(),
+			Some("tile \"3/1/2\" is missing")
+		);
+		assert!(object.get_number::<f64>("time")?.unwrap() > 1.7e12);
+		Ok(())
+	}
 }
@@ -0,0 +1,24 @@
+//! The stable public API of VersaTiles.
+//!
+//! Everything re-exported here follows semantic versioning: it will not change in a breaking way
+//! within a minor version. Import it with `use versatiles::prelude::*;`.
+//!
+//! Parameter structs like [`TilesConverterParameters`] and [`TilesReaderParameters`] are `#[non_exhaustive]`,
+//! so that fields can be added in minor versions. Create them with their constructors, e.g.
+//! [`TilesConverterParameters::new_default`], and set public fields afterwards.
+//!
+//! All other modules of this crate (`container`, `types`, `pipeline`, …) expose the internals of the
+//! workspace crates. They are only available with the opt-in `unstable` feature and may change with
+//! any release.
+
+pub use versatiles_container::{
+	convert_tiles_container, get_reader, write_to_filename, TilesConverterParameters, TilesWriterTrait,
+};
+pub use versatiles_core::{
+	metrics::{metrics, MetricsSnapshot},
+	tilejson::TileJSON,
+	types::{
+		Blob, GeoBBox, GeoCenter, TileBBox, TileBBoxPyramid, TileCompression, TileCoord2, TileCoord3, TileFormat,
+		TileStream, TilesReaderParameters, TilesReaderTrait,
+	},
+};
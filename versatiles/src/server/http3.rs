@@ -0,0 +1,218 @@
+//! An HTTP/3 listener next to the TCP listener of the server.
+//!
+//! It listens on the same address with QUIC over UDP and dispatches every request to the same router as the
+//! TCP listener, so both serve identical responses. Browsers only switch to HTTP/3 after they saw the
+//! "Alt-Svc" header, which the TCP listener adds to all responses while HTTP/3 is enabled. QUIC always uses TLS,
+//! so the TCP listener uses the same certificate for HTTPS, see `tls`.
+
+use super::tls::TlsOptions;
+use anyhow::{Context, Result};
+use axum::{body::Body, extract::Request, http::HeaderValue, response::Response, Router};
+use bytes::{Buf, Bytes};
+use futures::StreamExt;
+use quinn::crypto::rustls::QuicServerConfig;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+
+/// Returns the value of the "Alt-Svc" header, that announces HTTP/3 on `port`.
+pub fn alt_svc_header(port: u16) -> HeaderValue {
+	HeaderValue::from_str(&format!("h3=\":{port}\"; ma=86400")).unwrap()
+}
+
+/// A running HTTP/3 listener
+pub struct Http3Listener {
+	endpoint: quinn::Endpoint,
+	task: JoinHandle<()>,
+}
+
+impl Http3Listener {
+	/// Listens on the UDP port `addr` and answers all requests with `router`.
+	pub fn start(options: &TlsOptions, addr: SocketAddr, router: Router) -> Result<Http3Listener> {
+		let crypto = QuicServerConfig::try_from(options.server_config(&[b"h3"])?)?;
+		let endpoint = quinn::Endpoint::server(quinn::ServerConfig::with_crypto(Arc::new(crypto)), addr)
+			.with_context(|| format!("listening for HTTP/3 on {addr}"))?;
+
+		let accepting = endpoint.clone();
+		let task = tokio::spawn(async move {
+			while let Some(incoming) = accepting.accept().await {
+				let router = router.clone();
+				tokio::spawn(async move {
+					if let Err(error) = serve_connection(incoming, router).await {
+						log::debug!("HTTP/3 connection failed: {error:#}");
+					}
+				});
+			}
+		});
+
+		Ok(Http3Listener { endpoint, task })
+	}
+
+	/// Closes all connections and waits until the listener has stopped.
+	pub async fn stop(self) {
+		self.endpoint.close(0u32.into(), b"server stopped");
+		self.endpoint.wait_idle().await;
+		if let Err(error) = self.task.await {
+			log::warn!("HTTP/3 listener stopped with an error: {error}");
+		}
+	}
+}
+
+async fn serve_connection(incoming: quinn::Incoming, router: Router) -> Result<()> {
+	let connection = incoming.await?;
+	let mut connection = h3::server::Connection::<_, Bytes>::new(h3_quinn::Connection::new(connection)).await?;
+
+	loop {
+		match connection.accept().await {
+			Ok(Some(resolver)) => {
+				let router = router.clone();
+				tokio::spawn(async move {
+					let result = async {
+						let (request, stream) = resolver.resolve_request().await?;
+						serve_request(request, stream, router).await
+					};
+					if let Err(error) = result.await {
+						log::debug!("HTTP/3 request failed: {error:#}");
+					}
+				});
+			}
+			Ok(None) => return Ok(()),
+			Err(error) if error.is_h3_no_error() => return Ok(()),
+			Err(error) => return Err(error.into()),
+		}
+	}
+}
+
+async fn serve_request<S>(
+	request: axum::http::Request<()>,
+	mut stream: h3::server::RequestStream<S, Bytes>,
+	router: Router,
+) -> Result<()>
+where
+	S: h3::quic::BidiStream<Bytes>,
+{
+	let mut body = Vec::new();
+	while let Some(mut chunk) = stream.recv_data().await? {
+		while chunk.has_remaining() {
+			let bytes = chunk.chunk();
+			body.extend_from_slice(bytes);
+			let length = bytes.len();
+			chunk.advance(length);
+		}
+	}
+
+	let (parts, ()) = request.into_parts();
+	let response: Response = router.oneshot(Request::from_parts(parts, Body::from(body))).await?;
+
+	let (parts, body) = response.into_parts();
+	stream
+		.send_response(axum::http::Response::from_parts(parts, ()))
+		.await?;
+	let mut body = body.into_data_stream();
+	while let Some(bytes) = body.next().await {
+		stream.send_data(bytes?).await?;
+	}
+	stream.finish().await?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::server::TileServer;
+	use assert_fs::TempDir;
+	use rustls::pki_types::{CertificateDer, ServerName};
+
+	/// Writes a self-signed certificate for "localhost", returns the options and the certificate.
+	fn self_signed(dir: &TempDir) -> Result<(TlsOptions, CertificateDer<'static>)> {
+		let certified = rcgen::generate_simple_self_signed(vec![String::from("localhost")])?;
+		let options = TlsOptions {
+			cert_path: dir.path().join("cert.pem"),
+			key_path: dir.path().join("key.pem"),
+		};
+		std::fs::write(&options.cert_path, certified.cert.pem())?;
+		std::fs::write(&options.key_path, certified.key_pair.serialize_pem())?;
+		Ok((options, certified.cert.der().clone()))
+	}
+
+	/// Requests `path` with HTTP/3, trusting only `cert`.
+	async fn get(addr: SocketAddr, cert: CertificateDer<'static>, path: &str) -> Result<(u16, Vec<u8>)> {
+		let mut roots = rustls::RootCertStore::empty();
+		roots.add(cert)?;
+		let mut tls = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+			.with_protocol_versions(&[&rustls::version::TLS13])?
+			.with_root_certificates(roots)
+			.with_no_client_auth();
+		tls.alpn_protocols = vec![b"h3".to_vec()];
+		let crypto = quinn::crypto::rustls::QuicClientConfig::try_from(tls)?;
+
+		let mut endpoint = quinn::Endpoint::client("127.0.0.1:0".parse()?)?;
+		endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(crypto)));
+		let connection = endpoint
+			.connect(addr, ServerName::try_from("localhost")?.to_str().as_ref())?
+			.await?;
+
+		let (mut driver, mut send_request) = h3::client::new(h3_quinn::Connection::new(connection)).await?;
+		let drive = tokio::spawn(async move { driver.wait_idle().await });
+
+		let request = axum::http::Request::get(format!("https://localhost{path}")).body(())?;
+		let mut stream = send_request.send_request(request).await?;
+		stream.finish().await?;
+		let response = stream.recv_response().await?;
+		let mut body = Vec::new();
+		while let Some(mut chunk) = stream.recv_data().await? {
+			while chunk.has_remaining() {
+				let bytes = chunk.chunk();
+				body.extend_from_slice(bytes);
+				let length = bytes.len();
+				chunk.advance(length);
+			}
+		}
+
+		drop(send_request);
+		drive.abort();
+		endpoint.close(0u32.into(), b"");
+		Ok((response.status().as_u16(), body))
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn serve_http3() -> Result<()> {
+		let dir = TempDir::new()?;
+		let (options, cert) = self_signed(&dir)?;
+
+		let mut server = TileServer::new("127.0.0.1", 0, true, true);
+		server.set_tls(Some(options));
+		server.add_tile_source(
+			"berlin",
+			versatiles_container::get_reader("../testdata/berlin.mbtiles").await?,
+			Default::default(),
+		)?;
+		server.start().await?;
+		let addr = server.local_addr().unwrap();
+
+		// the TCP listener uses the same certificate and announces HTTP/3 on the same port
+		let client = reqwest::Client::builder()
+			.add_root_certificate(reqwest::Certificate::from_der(&cert)?)
+			.resolve("localhost", addr)
+			.build()?;
+		let response = client
+			.get(format!("https://localhost:{}/status", addr.port()))
+			.send()
+			.await?;
+		assert_eq!(
+			response.headers().get("alt-svc").unwrap(),
+			&format!("h3=\":{}\"; ma=86400", addr.port())
+		);
+		assert_eq!(response.text().await?, "ready!");
+
+		// both listeners share the router
+		assert_eq!(get(addr, cert.clone(), "/status").await?, (200, b"ready!".to_vec()));
+		let (status, body) = get(addr, cert.clone(), "/tiles/berlin/tiles.json").await?;
+		assert_eq!(status, 200);
+		assert!(String::from_utf8(body)?.contains("\"tilejson\":\"3.0.0\""));
+		assert_eq!(get(addr, cert, "/tiles/unknown/0/0/0").await?.0, 404);
+
+		server.stop().await;
+		Ok(())
+	}
+}
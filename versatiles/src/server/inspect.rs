@@ -0,0 +1,349 @@
+//! Inspection pages under "/inspect", generated on the server without any JavaScript.
+//!
+//! - "/inspect" lists all tile sources
+//! - "/inspect/{id}" shows the TileJSON and the zoom levels of a source
+//! - "/inspect/{id}/{z}" shows a grid of tiles of a zoom level
+//! - "/inspect/{id}/{z}/{x}/{y}" shows a raster tile, or the layers and features of a vector tile
+
+use super::sources::TileSource;
+use axum::{
+	body::Body,
+	extract::{Path as UrlPath, State},
+	http::header::CONTENT_TYPE,
+	response::Response,
+	routing::get,
+	Router,
+};
+use std::fmt::Write;
+use versatiles_core::types::{TileBBox, TileCoord3, TileFormat};
+use versatiles_geometry::vector_tile::VectorTile;
+
+/// Maximum number of tiles per row and column on a zoom level page
+const GRID_SIZE: u32 = 8;
+
+/// Maximum number of features listed per layer of a vector tile
+const MAX_FEATURES: usize = 500;
+
+pub fn inspect_app(sources: Vec<TileSource>) -> Router {
+	Router::new()
+		.route("/inspect", get(index))
+		.route("/inspect/{id}", get(source))
+		.route("/inspect/{id}/{z}", get(level))
+		.route("/inspect/{id}/{z}/{x}/{y}", get(tile))
+		.with_state(sources)
+}
+
+async fn index(State(sources): State<Vec<TileSource>>) -> Response<Body> {
+	let mut html =
+		String::from("<table><tr><th>id</th><th>source</th><th>format</th><th>compression</th><th>zoom</th></tr>");
+	for source in sources.iter() {
+		let parameters = source.get_parameters().await;
+		let zoom = match (
+			parameters.bbox_pyramid.get_zoom_min(),
+			parameters.bbox_pyramid.get_zoom_max(),
+		) {
+			(Some(min), Some(max)) => format!("{min}-{max}"),
+			_ => String::from("empty"),
+		};
+		write!(
+			html,
+			"<tr><td><a href=\"/inspect/{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{zoom}</td></tr>",
+			escape(&source.id),
+			escape(&source.id),
+			escape(&source.get_source_name().await),
+			parameters.tile_format,
+			parameters.tile_compression,
+		)
+		.unwrap();
+	}
+	html.push_str("</table>");
+	page("tile sources", &html)
+}
+
+async fn source(State(sources): State<Vec<TileSource>>, UrlPath(id): UrlPath<String>) -> Response<Body> {
+	let Some(source) = find_source(&sources, &id) else {
+		return error_404();
+	};
+	let tilejson = match source.build_tile_json_object().await {
+		Ok(tilejson) => tilejson,
+		Err(err) => return error_500(&err),
+	};
+	let parameters = source.get_parameters().await;
+
+	let mut html = String::from(
+		"<p><a href=\"/inspect\">all sources</a></p><h2>zoom levels</h2><table><tr><th>zoom</th><th>bbox</th><th>tiles</th></tr>",
+	);
+	for bbox in parameters.bbox_pyramid.iter_levels() {
+		write!(
+			html,
+			"<tr><td><a href=\"/inspect/{}/{}\">{}</a></td><td>[{}, {}, {}, {}]</td><td>{}</td></tr>",
+			escape(&id),
+			bbox.level,
+			bbox.level,
+			bbox.x_min,
+			bbox.y_min,
+			bbox.x_max,
+			bbox.y_max,
+			bbox.count_tiles()
+		)
+		.unwrap();
+	}
+	write!(
+		html,
+		"</table><h2>TileJSON</h2><pre>{}</pre>",
+		escape(&tilejson.stringify())
+	)
+	.unwrap();
+	page(&format!("source \"{id}\""), &html)
+}
+
+async fn level(State(sources): State<Vec<TileSource>>, UrlPath((id, z)): UrlPath<(String, u8)>) -> Response<Body> {
+	let Some(source) = find_source(&sources, &id) else {
+		return error_404();
+	};
+	let parameters = source.get_parameters().await;
+	let bbox = parameters.bbox_pyramid.get_level_bbox(z);
+	if bbox.is_empty() {
+		return error_404();
+	}
+
+	// show the tiles in the center of the bounding box
+	let x_min = bbox.x_min + bbox.width().saturating_sub(GRID_SIZE) / 2;
+	let y_min = bbox.y_min + bbox.height().saturating_sub(GRID_SIZE) / 2;
+	let grid = TileBBox::new(
+		z,
+		x_min,
+		y_min,
+		(x_min + GRID_SIZE - 1).min(bbox.x_max),
+		(y_min + GRID_SIZE - 1).min(bbox.y_max),
+	)
+	.expect("grid should be inside of the level");
+	let is_raster = is_raster(parameters.tile_format);
+
+	let mut html = format!(
+		"<p><a href=\"/inspect/{}\">source</a></p><table class=\"grid\">",
+		escape(&id)
+	);
+	for y in grid.y_min..=grid.y_max {
+		html.push_str("<tr>");
+		for x in grid.x_min..=grid.x_max {
+			let coord = TileCoord3::new(x, y, z).expect("coordinate should be valid");
+			let content = if is_raster {
+				format!(
+					"<img src=\"{}\" width=\"128\" height=\"128\">",
+					tile_url(source, &coord)
+				)
+			} else {
+				format!("{z}/{x}/{y}")
+			};
+			write!(
+				html,
+				"<td><a href=\"/inspect/{}/{z}/{x}/{y}\">{content}</a></td>",
+				escape(&id)
+			)
+			.unwrap();
+		}
+		html.push_str("</tr>");
+	}
+	html.push_str("</table>");
+	page(&format!("source \"{id}\", zoom level {z}"), &html)
+}
+
+async fn tile(
+	State(sources): State<Vec<TileSource>>,
+	UrlPath((id, z, x, y)): UrlPath<(String, u8, u32, u32)>,
+) -> Response<Body> {
+	let Some(source) = find_source(&sources, &id) else {
+		return error_404();
+	};
+	let Ok(coord) = TileCoord3::new(x, y, z) else {
+		return error_404();
+	};
+	let blob = match source.get_tile(&coord).await {
+		Ok(Some(blob)) => blob,
+		Ok(None) => return error_404(),
+		Err(err) => return error_500(&err),
+	};
+	let format = source.get_parameters().await.tile_format;
+
+	let mut html = format!(
+		"<p><a href=\"/inspect/{}/{z}\">zoom level</a> | {}</p><p>{} bytes</p>",
+		escape(&id),
+		navigation(&id, &coord),
+		blob.len()
+	);
+	if is_raster(format) {
+		write!(html, "<img src=\"{}\">", tile_url(source, &coord)).unwrap();
+	} else if format == TileFormat::PBF {
+		match vector_tile_tables(&blob) {
+			Ok(tables) => html.push_str(&tables),
+			Err(err) => return error_500(&err),
+		}
+	} else {
+		write!(html, "<pre>{}</pre>", escape(&String::from_utf8_lossy(blob.as_slice()))).unwrap();
+	}
+	page(&format!("source \"{id}\", tile {z}/{x}/{y}"), &html)
+}
+
+/// Lists the layers of a vector tile, each with a table of its features.
+fn vector_tile_tables(blob: &versatiles_core::types::Blob) -> anyhow::Result<String> {
+	let tile = VectorTile::from_blob(blob)?;
+	let mut html = String::from("<h2>layers</h2><table><tr><th>layer</th><th>features</th><th>extent</th></tr>");
+	for layer in tile.layers.iter() {
+		write!(
+			html,
+			"<tr><td><a href=\"#{0}\">{0}</a></td><td>{1}</td><td>{2}</td></tr>",
+			escape(&layer.name),
+			layer.features.len(),
+			layer.extent
+		)?;
+	}
+	html.push_str("</table>");
+
+	for layer in tile.layers.iter() {
+		write!(
+			html,
+			"<h2 id=\"{0}\">layer \"{0}\"</h2><table><tr><th>id</th><th>geometry</th><th>properties</th></tr>",
+			escape(&layer.name)
+		)?;
+		let features = layer.to_features()?;
+		for feature in features.iter().take(MAX_FEATURES) {
+			let properties: Vec<String> = feature
+				.properties
+				.iter()
+				.map(|(key, value)| format!("{}: {}", escape(key), escape(&value.to_string())))
+				.collect();
+			write!(
+				html,
+				"<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+				feature.id.as_ref().map(|id| id.to_string()).unwrap_or_default(),
+				feature.geometry.get_type_name(),
+				properties.join("<br>")
+			)?;
+		}
+		html.push_str("</table>");
+		if features.len() > MAX_FEATURES {
+			write!(html, "<p>{} more features</p>", features.len() - MAX_FEATURES)?;
+		}
+	}
+	Ok(html)
+}
+
+/// Links to the parent, the neighbours and the children of a tile.
+fn navigation(id: &str, coord: &TileCoord3) -> String {
+	let link = |label: &str, x: i64, y: i64, z: i64| -> Option<String> {
+		if !(0..=31).contains(&z) || !(0..1i64 << z).contains(&x) || !(0..1i64 << z).contains(&y) {
+			return None;
+		}
+		Some(format!("<a href=\"/inspect/{}/{z}/{x}/{y}\">{label}</a>", escape(id)))
+	};
+	let (x, y, z) = (coord.x as i64, coord.y as i64, coord.z as i64);
+	[
+		link("parent", x / 2, y / 2, z - 1),
+		link("north", x, y - 1, z),
+		link("west", x - 1, y, z),
+		link("east", x + 1, y, z),
+		link("south", x, y + 1, z),
+		link("child", x * 2, y * 2, z + 1),
+	]
+	.into_iter()
+	.flatten()
+	.collect::<Vec<_>>()
+	.join(" | ")
+}
+
+fn find_source<'a>(sources: &'a [TileSource], id: &str) -> Option<&'a TileSource> {
+	sources.iter().find(|source| source.id == id)
+}
+
+fn is_raster(format: TileFormat) -> bool {
+	matches!(
+		format,
+		TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP | TileFormat::AVIF
+	)
+}
+
+/// URL of a tile, with the scheme of the source applied to the row.
+fn tile_url(source: &TileSource, coord: &TileCoord3) -> String {
+	let mut coord = *coord;
+	source.options.scheme.apply(&mut coord);
+	source
+		.prefix
+		.join_as_string(&format!("{}/{}/{}", coord.z, coord.x, coord.y))
+}
+
+fn escape(text: &str) -> String {
+	text
+		.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+fn page(title: &str, body: &str) -> Response<Body> {
+	let html = format!(
+		"<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{0}</title><style>{STYLE}</style></head><body><h1>{0}</h1>{body}</body></html>",
+		escape(title)
+	);
+	Response::builder()
+		.status(200)
+		.header(CONTENT_TYPE, "text/html; charset=utf-8")
+		.body(Body::from(html))
+		.expect("should have build a body")
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em 2em}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:2px 6px;text-align:left;vertical-align:top}table.grid td{padding:0}pre{background:#f4f4f4;padding:1em;white-space:pre-wrap}";
+
+fn error_404() -> Response<Body> {
+	Response::builder()
+		.status(404)
+		.body(Body::from("Not Found"))
+		.expect("should have build a body")
+}
+
+fn error_500(err: &anyhow::Error) -> Response<Body> {
+	log::warn!("send 500 for inspect request. Reason: {err}");
+	Response::builder()
+		.status(500)
+		.body(Body::from(format!("Internal Server Error: {err}")))
+		.expect("should have build a body")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::server::TileSourceOptions;
+	use versatiles_container::{MockTilesReader, MockTilesReaderProfile};
+	use versatiles_core::types::TilesReaderTrait;
+
+	#[test]
+	fn test_navigation() {
+		let html = navigation("osm", &TileCoord3::new(0, 1, 1).unwrap());
+		assert_eq!(
+			html,
+			"<a href=\"/inspect/osm/0/0/0\">parent</a> | <a href=\"/inspect/osm/1/0/0\">north</a> | <a href=\"/inspect/osm/1/1/1\">east</a> | <a href=\"/inspect/osm/2/0/2\">child</a>"
+		);
+	}
+
+	#[test]
+	fn test_escape() {
+		assert_eq!(
+			escape("<a href=\"x\">&</a>"),
+			"&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_vector_tile() -> anyhow::Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?;
+		let source = TileSource::from(reader.boxed(), "cheese", TileSourceOptions::default())?;
+		let blob = source.get_tile(&TileCoord3::new(0, 0, 2)?).await?.unwrap();
+		let html = vector_tile_tables(&blob)?;
+		assert!(
+			html.contains("<tr><td><a href=\"#ocean\">ocean</a></td><td>1</td><td>4096</td></tr>"),
+			"{html}"
+		);
+		assert!(html.contains("<h2 id=\"ocean\">layer \"ocean\"</h2>"), "{html}");
+		Ok(())
+	}
+}
@@ -0,0 +1,812 @@
+//! Conversion jobs, started and observed through the admin API `/api/jobs`.
+//!
+//! - `POST /api/jobs` starts a conversion, e.g. `{"input":"planet.versatiles","output":"berlin.pmtiles","bbox":[13.0,52.3,13.8,52.7]}`
+//! - `GET /api/jobs` lists all jobs, `GET /api/jobs/{id}` returns a single job
+//! - `GET /api/jobs/{id}/events` streams the progress events as NDJSON, from the oldest kept event, until the job has ended
+//! - `DELETE /api/jobs/{id}` cancels a running job
+//! - `POST /api/jobs/{id}/resume` runs a cancelled, failed or interrupted job again
+//!
+//! All requests require one of the API keys of [`JobsOptions`]. Input and output are paths relative to the jobs root.
+//! Absolute paths, ".." and hidden files are rejected, as well as paths that leave the root through symbolic links,
+//! so that jobs can't read or write anything outside of it.
+//!
+//! Responses contain an `ETag` of the job list or the job. Starting, cancelling and resuming jobs requires an `If-Match`
+//! header with this ETag (or `*`), so two operators can't accidentally act on a state they haven't seen.
+//! Every accepted change is logged at the log target "audit" with the API key that was used and the old and new ETag.
+//!
+//! Since clients can reconnect to the event stream at any time, they can follow a job across restarts of the client.
+//! The jobs are stored in the file ".versatiles-jobs.json" in the jobs root, so they also survive restarts of the
+//! server: jobs that were running are listed as "interrupted" and can be resumed. A resumed job converts the input
+//! again from the start, since containers can't continue a partially written file.
+
+use super::utils::Auth;
+use anyhow::{bail, ensure, Context, Result};
+use futures::{
+	future::{AbortHandle, AbortRegistration, Abortable},
+	stream::{self, Stream},
+};
+use std::{
+	collections::VecDeque,
+	hash::{DefaultHasher, Hash, Hasher},
+	io::Write,
+	path::{Component, Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+use versatiles_container::{convert_tiles_container, get_reader_with_hints, TilesConverterParameters};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	progress::{unix_millis, with_progress_output, ProgressOutput},
+	types::{GeoBBox, TileBBoxPyramid, TileCompression},
+};
+
+/// File in the jobs root that stores all jobs
+const JOBS_FILE: &str = ".versatiles-jobs.json";
+
+/// Number of events kept per job. Older events are dropped, so long conversions don't fill the memory.
+const MAX_JOB_EVENTS: usize = 1000;
+
+/// Options of the admin API "/api/jobs"
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobsOptions {
+	root: PathBuf,
+	auth: Auth,
+}
+
+impl JobsOptions {
+	/// Jobs read and write files in the folder `root` and require one of the `api_keys`.
+	pub fn new(root: &Path, api_keys: &[String]) -> Result<JobsOptions> {
+		ensure!(!api_keys.is_empty(), "the jobs API requires at least one API key");
+		ensure!(root.is_dir(), "the jobs root {root:?} must be a directory");
+		Ok(JobsOptions {
+			root: root.canonicalize()?,
+			auth: Auth::new(api_keys, None)?,
+		})
+	}
+
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+
+	pub fn auth(&self) -> &Auth {
+		&self.auth
+	}
+}
+
+/// Parameters of a conversion job, like the arguments of `versatiles convert`
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobRequest {
+	/// input path, relative to the jobs root
+	pub input: String,
+	/// output path, relative to the jobs root
+	pub output: String,
+	pub min_zoom: Option<u8>,
+	pub max_zoom: Option<u8>,
+	pub bbox: Option<[f64; 4]>,
+	pub compress: Option<TileCompression>,
+}
+
+impl JobRequest {
+	pub fn from_json(text: &str) -> Result<JobRequest> {
+		JobRequest::from_object(&JsonObject::parse_str(text).context("parsing job request")?)
+	}
+
+	fn from_object(object: &JsonObject) -> Result<JobRequest> {
+		let get_zoom = |key: &str| -> Result<Option<u8>> {
+			object
+				.get_number::<f64>(key)?
+				.map(|zoom| {
+					ensure!((0.0..=31.0).contains(&zoom), "{key} must be between 0 and 31");
+					Ok(zoom as u8)
+				})
+				.transpose()
+		};
+		let get_path = |key: &str| -> Result<String> {
+			let path = object
+				.get_string(key)?
+				.with_context(|| format!("\"{key}\" is required"))?;
+			check_path(key, &path)?;
+			Ok(path)
+		};
+
+		Ok(JobRequest {
+			input: get_path("input")?,
+			output: get_path("output")?,
+			min_zoom: get_zoom("min_zoom")?,
+			max_zoom: get_zoom("max_zoom")?,
+			bbox: object.get_number_array::<f64, 4>("bbox")?,
+			compress: object
+				.get_string("compress")?
+				.map(|value| TileCompression::parse_str(&value))
+				.transpose()?,
+		})
+	}
+
+	fn as_json(&self) -> JsonObject {
+		let mut object = JsonObject::default();
+		object.set("input", self.input.as_str());
+		object.set("output", self.output.as_str());
+		object.set_optional("min_zoom", &self.min_zoom);
+		object.set_optional("max_zoom", &self.max_zoom);
+		object.set_optional("bbox", &self.bbox.map(|bbox| bbox.to_vec()));
+		object.set_optional("compress", &self.compress.map(|compress| compress.as_str().to_string()));
+		object
+	}
+
+	fn get_converter_parameters(&self) -> Result<TilesConverterParameters> {
+		let bbox_pyramid = if self.min_zoom.is_some() || self.max_zoom.is_some() || self.bbox.is_some() {
+			let mut bbox_pyramid = TileBBoxPyramid::new_full(32);
+			if let Some(min_zoom) = self.min_zoom {
+				bbox_pyramid.set_zoom_min(min_zoom);
+			}
+			if let Some(max_zoom) = self.max_zoom {
+				bbox_pyramid.set_zoom_max(max_zoom);
+			}
+			if let Some(bbox) = self.bbox {
+				bbox_pyramid.intersect_geo_bbox(&GeoBBox::try_from(bbox.to_vec())?);
+			}
+			Some(bbox_pyramid)
+		} else {
+			None
+		};
+		Ok(TilesConverterParameters::new(
+			self.compress,
+			bbox_pyramid,
+			false,
+			false,
+			false,
+		))
+	}
+
+	/// Returns the input and output path, after ensuring that both are inside the jobs `root`.
+	fn resolve_paths(&self, root: &Path) -> Result<(PathBuf, PathBuf)> {
+		Ok((
+			resolve_path(root, "input", &self.input)?,
+			resolve_path(root, "output", &self.output)?,
+		))
+	}
+
+	async fn run(&self, root: &Path) -> Result<()> {
+		let cp = self.get_converter_parameters()?;
+		let (input, output) = self.resolve_paths(root)?;
+		let reader = get_reader_with_hints(&input.to_string_lossy(), &cp.get_read_hints()).await?;
+		convert_tiles_container(reader, cp, &output.to_string_lossy()).await
+	}
+}
+
+/// Joins `path` to the canonical `root` and resolves the symbolic links of the nearest existing part,
+/// which must still be inside the root. Missing files and folders, e.g. of the output, are appended.
+fn resolve_path(root: &Path, key: &str, path: &str) -> Result<PathBuf> {
+	let joined = root.join(path);
+	// `symlink_metadata` also finds dangling symbolic links, which would be followed when writing
+	let existing = joined
+		.ancestors()
+		.find(|ancestor| std::fs::symlink_metadata(ancestor).is_ok())
+		.with_context(|| format!("{key} is not inside the jobs root"))?;
+	let canonical = existing
+		.canonicalize()
+		.with_context(|| format!("resolving {key} {path:?}"))?;
+	ensure!(canonical.starts_with(root), "{key} must not leave the jobs root");
+	let missing = joined.strip_prefix(existing)?;
+	Ok(if missing.as_os_str().is_empty() {
+		canonical
+	} else {
+		canonical.join(missing)
+	})
+}
+
+/// Ensures that `path` stays inside the jobs root: it must be relative, without "..", hidden files or hidden folders.
+fn check_path(key: &str, path: &str) -> Result<()> {
+	let mut names = 0;
+	for component in Path::new(path).components() {
+		match component {
+			Component::Normal(name) => {
+				ensure!(
+					!name.to_string_lossy().starts_with('.'),
+					"{key} must not contain hidden files or folders"
+				);
+				names += 1;
+			}
+			Component::CurDir => {}
+			Component::ParentDir => bail!("{key} must not contain \"..\""),
+			Component::RootDir | Component::Prefix(_) => bail!("{key} must be relative to the jobs root"),
+		}
+	}
+	ensure!(names > 0, "{key} must not be empty");
+	Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+	Running,
+	Finished,
+	Failed(String),
+	Cancelled,
+	/// the server stopped while the job was running
+	Interrupted,
+}
+
+impl JobStatus {
+	fn as_str(&self) -> &str {
+		match self {
+			JobStatus::Running => "running",
+			JobStatus::Finished => "finished",
+			JobStatus::Failed(_) => "failed",
+			JobStatus::Cancelled => "cancelled",
+			JobStatus::Interrupted => "interrupted",
+		}
+	}
+
+	fn is_resumable(&self) -> bool {
+		matches!(
+			self,
+			JobStatus::Failed(_) | JobStatus::Cancelled | JobStatus::Interrupted
+		)
+	}
+}
+
+struct JobState {
+	status: JobStatus,
+	/// number of times the job was started or resumed
+	runs: u32,
+	/// NDJSON lines of the last `MAX_JOB_EVENTS` progress and job events since the server started
+	events: VecDeque<String>,
+	/// number of events dropped from the front of `events`
+	dropped_events: usize,
+	/// incomplete line written by a progress indicator
+	partial: Vec<u8>,
+	abort_handle: Option<AbortHandle>,
+	/// the last event of the run has been sent
+	ended: bool,
+}
+
+impl JobState {
+	fn push_event(&mut self, line: String) {
+		if self.events.len() >= MAX_JOB_EVENTS {
+			self.events.pop_front();
+			self.dropped_events += 1;
+		}
+		self.events.push_back(line);
+	}
+}
+
+pub struct Job {
+	pub id: u32,
+	pub request: JobRequest,
+	state: Mutex<JobState>,
+	/// counts the changes of `state`, so that event streams wake up
+	changes: watch::Sender<usize>,
+}
+
+impl Job {
+	fn new(id: u32, request: JobRequest, status: JobStatus, runs: u32) -> Job {
+		Job {
+			id,
+			request,
+			state: Mutex::new(JobState {
+				ended: status != JobStatus::Running,
+				status,
+				runs,
+				events: VecDeque::new(),
+				dropped_events: 0,
+				partial: Vec::new(),
+				abort_handle: None,
+			}),
+			changes: watch::channel(0).0,
+		}
+	}
+
+	pub fn as_json(&self) -> JsonObject {
+		let state = self.state.lock().unwrap();
+		let mut object = JsonObject::default();
+		object.set("id", self.id as f64);
+		object.set("input", self.request.input.as_str());
+		object.set("output", self.request.output.as_str());
+		object.set("status", state.status.as_str());
+		object.set("runs", state.runs);
+		object.set("events", (state.dropped_events + state.events.len()) as f64);
+		if let JobStatus::Failed(error) = &state.status {
+			object.set("error", error.as_str());
+		}
+		object
+	}
+
+	/// Returns the ETag of the job. It changes when the status changes or the job is resumed.
+	pub fn etag(&self) -> String {
+		let state = self.state.lock().unwrap();
+		format!("\"{}-{}-{}\"", self.id, state.runs, state.status.as_str())
+	}
+
+	/// Cancels the job, if it is still running.
+	pub fn cancel(&self) {
+		let abort_handle = {
+			let state = self.state.lock().unwrap();
+			if state.status != JobStatus::Running {
+				return;
+			}
+			state.abort_handle.clone()
+		};
+		log::info!("cancel job {}", self.id);
+		self.push_event("cancel", None);
+		if let Some(abort_handle) = abort_handle {
+			abort_handle.abort();
+		}
+	}
+
+	/// Sets the status to running. Unless it's the first run, the job must be resumable.
+	fn begin_run(&self) -> Result<AbortRegistration> {
+		let mut state = self.state.lock().unwrap();
+		ensure!(
+			state.runs == 0 || state.status.is_resumable(),
+			"job {} is {} and can't be resumed",
+			self.id,
+			state.status.as_str()
+		);
+		let (abort_handle, abort_registration) = AbortHandle::new_pair();
+		state.status = JobStatus::Running;
+		state.runs += 1;
+		state.abort_handle = Some(abort_handle);
+		state.ended = false;
+		let event = if state.runs == 1 { "start" } else { "resume" };
+		drop(state);
+		self.push_event(event, None);
+		Ok(abort_registration)
+	}
+
+	fn push_event(&self, event: &str, error: Option<&str>) {
+		let line = self.event_line(event, error);
+		self.state.lock().unwrap().push_event(line);
+		self.changes.send_modify(|count| *count += 1);
+	}
+
+	fn event_line(&self, event: &str, error: Option<&str>) -> String {
+		let mut object = JsonObject::default();
+		object.set("type", "job");
+		object.set("event", event);
+		object.set("time", unix_millis());
+		object.set("id", self.id as f64);
+		if let Some(error) = error {
+			object.set("error", error);
+		}
+		object.stringify()
+	}
+
+	fn write_progress(&self, buf: &[u8]) {
+		let mut state = self.state.lock().unwrap();
+		state.partial.extend_from_slice(buf);
+		while let Some(index) = state.partial.iter().position(|b| *b == b'\n') {
+			let line: Vec<u8> = state.partial.drain(..=index).collect();
+			let line = String::from_utf8_lossy(&line).trim_end().to_string();
+			state.push_event(line);
+		}
+		drop(state);
+		self.changes.send_modify(|count| *count += 1);
+	}
+
+	fn set_status(&self, status: JobStatus) {
+		let mut state = self.state.lock().unwrap();
+		state.status = status;
+		state.abort_handle = None;
+	}
+
+	/// Sends the final status as last event, which ends all event streams.
+	fn end(&self) {
+		let mut state = self.state.lock().unwrap();
+		let error = match &state.status {
+			JobStatus::Failed(error) => Some(error.as_str()),
+			_ => None,
+		};
+		let line = self.event_line(state.status.as_str(), error);
+		state.push_event(line);
+		state.ended = true;
+		drop(state);
+		self.changes.send_modify(|count| *count += 1);
+	}
+
+	/// Returns the NDJSON events, starting with the oldest kept one, until the job has ended.
+	/// Events that are dropped while the stream is read are skipped.
+	pub fn event_stream(self: Arc<Self>) -> impl Stream<Item = Result<String, std::convert::Infallible>> {
+		stream::unfold((self, 0usize), |(job, index)| async move {
+			let mut changes = job.changes.subscribe();
+			loop {
+				{
+					let state = job.state.lock().unwrap();
+					let index = index.max(state.dropped_events);
+					if let Some(line) = state.events.get(index - state.dropped_events) {
+						return Some((Ok(format!("{line}\n")), (job.clone(), index + 1)));
+					}
+					if state.ended {
+						return None;
+					}
+				}
+				if changes.changed().await.is_err() {
+					return None;
+				}
+			}
+		})
+	}
+
+	/// Returns the entry of the job in the jobs file.
+	fn as_record(&self) -> JsonValue {
+		let mut object = self.request.as_json();
+		let state = self.state.lock().unwrap();
+		object.set("id", self.id);
+		object.set("status", state.status.as_str());
+		object.set("runs", state.runs);
+		if let JobStatus::Failed(error) = &state.status {
+			object.set("error", error.as_str());
+		}
+		JsonValue::Object(object)
+	}
+
+	fn from_record(value: &JsonValue) -> Result<Job> {
+		let object = value.as_object()?;
+		let status = match object.get_string("status")?.unwrap_or_default().as_str() {
+			"finished" => JobStatus::Finished,
+			"failed" => JobStatus::Failed(object.get_string("error")?.unwrap_or_default()),
+			"cancelled" => JobStatus::Cancelled,
+			"running" | "interrupted" => JobStatus::Interrupted,
+			status => bail!("unknown job status {status:?}"),
+		};
+		Ok(Job::new(
+			object.get_number("id")?.context("\"id\" is required")?,
+			JobRequest::from_object(object)?,
+			status,
+			object.get_number("runs")?.unwrap_or(1),
+		))
+	}
+}
+
+/// Writes the progress events of a job into its event list.
+struct JobWriter(Arc<Job>);
+
+impl Write for JobWriter {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.write_progress(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Keeps track of all jobs started by the admin API
+#[derive(Clone)]
+pub struct JobManager {
+	root: Arc<PathBuf>,
+	jobs: Arc<Mutex<Vec<Arc<Job>>>>,
+}
+
+impl JobManager {
+	/// Loads the jobs stored in the jobs root.
+	pub fn new(root: &Path) -> Result<JobManager> {
+		let path = root.join(JOBS_FILE);
+		let jobs = if path.exists() {
+			let text = std::fs::read_to_string(&path).with_context(|| format!("reading jobs file {path:?}"))?;
+			JsonValue::parse_str(&text)
+				.and_then(|value| value.as_array()?.0.iter().map(Job::from_record).collect())
+				.with_context(|| format!("parsing jobs file {path:?}"))?
+		} else {
+			Vec::new()
+		};
+		Ok(JobManager {
+			root: Arc::new(root.canonicalize()?),
+			jobs: Arc::new(Mutex::new(jobs.into_iter().map(Arc::new).collect())),
+		})
+	}
+
+	/// Fails if the input or output of `request` leaves the jobs root.
+	pub fn check_request(&self, request: &JobRequest) -> Result<()> {
+		request.resolve_paths(&self.root).map(|_| ())
+	}
+
+	/// Starts the conversion in the background and returns the job.
+	pub fn start(&self, request: JobRequest) -> Arc<Job> {
+		let job = {
+			let mut jobs = self.jobs.lock().unwrap();
+			let id = jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+			let job = Arc::new(Job::new(id, request, JobStatus::Running, 0));
+			jobs.push(job.clone());
+			job
+		};
+		log::info!("start job {}: {:?}", job.id, job.request);
+		self.run(job.clone()).expect("a new job can always be started");
+		job
+	}
+
+	/// Runs a cancelled, failed or interrupted job again.
+	pub fn resume(&self, job: &Arc<Job>) -> Result<()> {
+		log::info!("resume job {}", job.id);
+		self.run(job.clone())
+	}
+
+	fn run(&self, job: Arc<Job>) -> Result<()> {
+		let abort_registration = job.begin_run()?;
+		self.save();
+
+		let output: ProgressOutput = {
+			let job = job.clone();
+			Arc::new(move || Box::new(JobWriter(job.clone())))
+		};
+
+		let manager = self.clone();
+		// conversions are not `Send`, so they run on a blocking thread with their own executor
+		let handle = tokio::runtime::Handle::current();
+		tokio::task::spawn_blocking(move || {
+			let future = Abortable::new(
+				with_progress_output(output, async { job.request.run(&manager.root).await }),
+				abort_registration,
+			);
+			let status = match handle.block_on(future) {
+				Ok(Ok(())) => JobStatus::Finished,
+				Ok(Err(error)) => JobStatus::Failed(format!("{error:#}")),
+				Err(_) => JobStatus::Cancelled,
+			};
+			log::info!("job {} {}", job.id, status.as_str());
+			job.set_status(status);
+			manager.save();
+			job.end();
+		});
+		Ok(())
+	}
+
+	/// Writes all jobs into the jobs file. Errors are only logged, since the jobs keep running anyway.
+	fn save(&self) {
+		let jobs = self.jobs.lock().unwrap();
+		let text = JsonValue::from(jobs.iter().map(|job| job.as_record()).collect::<Vec<_>>()).stringify();
+		let path = self.root.join(JOBS_FILE);
+		let temp_path = path.with_extension("json.tmp");
+		if let Err(error) = std::fs::write(&temp_path, text).and_then(|()| std::fs::rename(&temp_path, &path)) {
+			log::error!("saving jobs file {path:?} failed: {error}");
+		}
+	}
+
+	pub fn get(&self, id: u32) -> Option<Arc<Job>> {
+		self.jobs.lock().unwrap().iter().find(|job| job.id == id).cloned()
+	}
+
+	/// Returns the ETag of the job list. It changes when a job is started, resumed or its status changes.
+	pub fn etag(&self) -> String {
+		let mut hasher = DefaultHasher::new();
+		for job in self.jobs.lock().unwrap().iter() {
+			job.etag().hash(&mut hasher);
+		}
+		format!("\"{:016x}\"", hasher.finish())
+	}
+
+	pub fn as_json(&self) -> JsonValue {
+		let jobs = self.jobs.lock().unwrap();
+		JsonValue::from(
+			jobs
+				.iter()
+				.map(|job| JsonValue::Object(job.as_json()))
+				.collect::<Vec<_>>(),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use futures::StreamExt;
+
+	/// Returns a jobs root containing "berlin.mbtiles".
+	fn jobs_root() -> Result<TempDir> {
+		let dir = TempDir::new()?;
+		std::fs::copy("../testdata/berlin.mbtiles", dir.path().join("berlin.mbtiles"))?;
+		Ok(dir)
+	}
+
+	async fn wait(job: &Arc<Job>) -> Vec<String> {
+		job.clone().event_stream().map(|line| line.unwrap()).collect().await
+	}
+
+	#[test]
+	fn test_job_request() -> Result<()> {
+		let request = JobRequest::from_json(
+			"{\"input\":\"a.mbtiles\",\"output\":\"b.versatiles\",\"max_zoom\":5,\"bbox\":[13,52,14,53],\"compress\":\"brotli\"}",
+		)?;
+		assert_eq!(
+			request,
+			JobRequest {
+				input: String::from("a.mbtiles"),
+				output: String::from("b.versatiles"),
+				min_zoom: None,
+				max_zoom: Some(5),
+				bbox: Some([13.0, 52.0, 14.0, 53.0]),
+				compress: Some(TileCompression::Brotli),
+			}
+		);
+		assert_eq!(JobRequest::from_object(&request.as_json())?, request);
+
+		let error = |text: &str| JobRequest::from_json(text).unwrap_err().to_string();
+		assert_eq!(error("{\"output\":\"b.versatiles\"}"), "\"input\" is required");
+		assert_eq!(
+			error("{\"input\":\"a\",\"output\":\"b\",\"min_zoom\":40}"),
+			"min_zoom must be between 0 and 31"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_path() {
+		assert!(check_path("input", "berlin.mbtiles").is_ok());
+		assert!(check_path("input", "./extracts/berlin.mbtiles").is_ok());
+
+		let error = |path: &str| check_path("output", path).unwrap_err().to_string();
+		assert_eq!(error("/etc/passwd"), "output must be relative to the jobs root");
+		assert_eq!(error("../berlin.mbtiles"), "output must not contain \"..\"");
+		assert_eq!(error("extracts/../../berlin.mbtiles"), "output must not contain \"..\"");
+		assert_eq!(
+			error(".versatiles-jobs.json"),
+			"output must not contain hidden files or folders"
+		);
+		assert_eq!(error("./"), "output must not be empty");
+		assert_eq!(error(""), "output must not be empty");
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn test_resolve_path() -> Result<()> {
+		let root = jobs_root()?;
+		let outside = TempDir::new()?;
+		std::os::unix::fs::symlink(outside.path(), root.path().join("outside"))?;
+		std::os::unix::fs::symlink(outside.path().join("missing.pbf"), root.path().join("dangling.pbf"))?;
+		std::fs::create_dir(root.path().join("extracts"))?;
+		std::os::unix::fs::symlink("../berlin.mbtiles", root.path().join("extracts/link.mbtiles"))?;
+		let manager = JobManager::new(root.path())?;
+		let root = root.path().canonicalize()?;
+
+		let resolve = |path: &str| resolve_path(&root, "output", path);
+		assert_eq!(resolve("berlin.mbtiles")?, root.join("berlin.mbtiles"));
+		assert_eq!(resolve("extracts/link.mbtiles")?, root.join("berlin.mbtiles"));
+		assert_eq!(resolve("new/folder/out.pmtiles")?, root.join("new/folder/out.pmtiles"));
+
+		let error = |path: &str| resolve(path).unwrap_err().to_string();
+		assert_eq!(error("outside/out.pmtiles"), "output must not leave the jobs root");
+		assert_eq!(error("outside"), "output must not leave the jobs root");
+		assert_eq!(error("dangling.pbf"), "resolving output \"dangling.pbf\"");
+
+		let request = JobRequest::from_json("{\"input\":\"outside/planet.versatiles\",\"output\":\"out.versatiles\"}")?;
+		assert_eq!(
+			manager.check_request(&request).unwrap_err().to_string(),
+			"input must not leave the jobs root"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_event_limit() {
+		let job = Job::new(
+			1,
+			JobRequest::from_json("{\"input\":\"a\",\"output\":\"b\"}").unwrap(),
+			JobStatus::Running,
+			1,
+		);
+		for i in 0..MAX_JOB_EVENTS + 5 {
+			job.write_progress(format!("{{\"line\":{i}}}\n").as_bytes());
+		}
+		{
+			let state = job.state.lock().unwrap();
+			assert_eq!(state.events.len(), MAX_JOB_EVENTS);
+			assert_eq!(state.dropped_events, 5);
+			assert_eq!(state.events[0], "{\"line\":5}");
+		}
+		assert!(job.as_json().stringify().contains("\"events\":1005"));
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_job() -> Result<()> {
+		let root = jobs_root()?;
+
+		let manager = JobManager::new(root.path())?;
+		let job = manager.start(JobRequest::from_json(
+			"{\"input\":\"berlin.mbtiles\",\"output\":\"berlin.versatiles\",\"max_zoom\":6}",
+		)?);
+		assert_eq!(job.id, 1);
+		assert!(manager.get(1).is_some());
+		assert!(manager.get(2).is_none());
+
+		let events = wait(&job).await;
+		assert!(events[0].contains("\"event\":\"start\",\"id\":1"));
+		assert!(events.iter().any(|e| e.contains("\"type\":\"progress\"")));
+		assert!(events.last().unwrap().contains("\"event\":\"finished\""));
+		assert!(root.path().join("berlin.versatiles").exists());
+
+		assert!(job.as_json().stringify().contains("\"status\":\"finished\""));
+		let list = manager.as_json().stringify();
+		assert!(list.contains("\"status\":\"finished\""), "{list}");
+
+		assert_eq!(job.etag(), "\"1-1-finished\"");
+		let etag = manager.etag();
+		job.cancel();
+		assert_eq!(manager.etag(), etag);
+		assert_eq!(
+			manager.resume(&job).unwrap_err().to_string(),
+			"job 1 is finished and can't be resumed"
+		);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_failed_job() -> Result<()> {
+		let root = jobs_root()?;
+		let manager = JobManager::new(root.path())?;
+		let job = manager.start(JobRequest::from_json(
+			"{\"input\":\"does_not_exist.mbtiles\",\"output\":\"never.versatiles\"}",
+		)?);
+		let events = wait(&job).await;
+		assert!(events.last().unwrap().contains("\"event\":\"failed\""));
+		let json = job.as_json().stringify();
+		assert!(
+			json.contains("\"status\":\"failed\"") && json.contains("\"error\":"),
+			"{json}"
+		);
+
+		// resume after the input has been uploaded
+		std::fs::copy("../testdata/berlin.mbtiles", root.path().join("does_not_exist.mbtiles"))?;
+		manager.resume(&job)?;
+		let events = wait(&job).await;
+		assert!(events.iter().any(|e| e.contains("\"event\":\"resume\"")));
+		assert!(events.last().unwrap().contains("\"event\":\"finished\""));
+		assert_eq!(job.etag(), "\"1-2-finished\"");
+		assert!(root.path().join("never.versatiles").exists());
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_cancel_job() -> Result<()> {
+		let root = jobs_root()?;
+
+		let manager = JobManager::new(root.path())?;
+		let empty_etag = manager.etag();
+		let job = manager.start(JobRequest::from_json(
+			"{\"input\":\"berlin.mbtiles\",\"output\":\"cancelled.versatiles\"}",
+		)?);
+		assert_ne!(manager.etag(), empty_etag);
+
+		job.cancel();
+		let events = wait(&job).await;
+		let last = events.last().unwrap();
+		if last.contains("\"event\":\"cancelled\"") {
+			assert!(events.iter().any(|e| e.contains("\"event\":\"cancel\"")));
+			assert_eq!(job.etag(), "\"1-1-cancelled\"");
+		} else {
+			// the conversion finished before it could be cancelled
+			assert!(last.contains("\"event\":\"finished\""), "{last}");
+		}
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_interrupted_job() -> Result<()> {
+		let root = jobs_root()?;
+		// the jobs file of a server that stopped during a conversion
+		std::fs::write(
+			root.path().join(JOBS_FILE),
+			"[{\"id\":3,\"input\":\"berlin.mbtiles\",\"output\":\"berlin.pmtiles\",\"max_zoom\":3,\"status\":\"running\",\"runs\":1}]",
+		)?;
+
+		let manager = JobManager::new(root.path())?;
+		let job = manager.get(3).unwrap();
+		assert_eq!(job.etag(), "\"3-1-interrupted\"");
+		assert_eq!(job.request.max_zoom, Some(3));
+
+		manager.resume(&job)?;
+		let events = wait(&job).await;
+		assert!(events.last().unwrap().contains("\"event\":\"finished\""));
+		assert!(root.path().join("berlin.pmtiles").exists());
+
+		// new jobs get the next id and all jobs are stored
+		let job = manager.start(JobRequest::from_json(
+			"{\"input\":\"berlin.mbtiles\",\"output\":\"berlin.versatiles\",\"max_zoom\":3}",
+		)?);
+		assert_eq!(job.id, 4);
+		wait(&job).await;
+		let manager = JobManager::new(root.path())?;
+		assert_eq!(manager.get(3).unwrap().etag(), "\"3-2-finished\"");
+		assert_eq!(manager.get(4).unwrap().etag(), "\"4-1-finished\"");
+		Ok(())
+	}
+}
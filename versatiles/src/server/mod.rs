@@ -0,0 +1,27 @@
+//! server implementation
+//!
+//! Requires the `server` feature. [`spawn_test_server`] starts a server for integration tests.
+
+#[cfg(feature = "http3")]
+mod http3;
+#[cfg(feature = "inspect")]
+mod inspect;
+mod jobs;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod sources;
+mod test_server;
+mod tile_cache;
+mod tile_server;
+#[cfg(feature = "http3")]
+mod tls;
+mod utils;
+
+pub use jobs::JobsOptions;
+pub use sources::{PropertyLookup, StaticSourceOptions, TileSourceOptions};
+pub use test_server::{spawn_test_server, ShutdownHandle};
+pub use tile_cache::TileCacheOptions;
+pub use tile_server::*;
+#[cfg(feature = "http3")]
+pub use tls::TlsOptions;
+pub use utils::{Auth, Cors, Url};
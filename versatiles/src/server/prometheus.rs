@@ -0,0 +1,244 @@
+//! Metrics of the server in the Prometheus text format, served at "/metrics".
+//!
+//! Every tile source records its requests by HTTP status, a histogram of the response times and the
+//! bytes sent. The counters of `versatiles_core::metrics`, like the hits and misses of the tile cache,
+//! are exported as well.
+
+use axum::{
+	body::{Body, HttpBody},
+	extract::{Request, State},
+	middleware::Next,
+	response::Response,
+};
+use std::{
+	collections::BTreeMap,
+	fmt::Write,
+	sync::{
+		atomic::{AtomicI64, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, Instant},
+};
+use versatiles_core::metrics::metrics;
+
+/// Upper bounds of the buckets of the response time histogram, in seconds
+const BUCKETS: [f64; 12] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct SourceMetrics {
+	/// requests by HTTP status
+	requests: BTreeMap<u16, u64>,
+	/// requests by bucket, the last one counts requests slower than all buckets
+	durations: [u64; BUCKETS.len() + 1],
+	duration_sum: f64,
+	bytes: u64,
+}
+
+/// Metrics of all tile sources. They are kept when the sources are reloaded.
+#[derive(Default)]
+pub struct ServerMetrics {
+	sources: Mutex<BTreeMap<String, SourceMetrics>>,
+	in_flight: AtomicI64,
+}
+
+impl ServerMetrics {
+	/// Records a finished request of the tile source `source`.
+	pub fn record(&self, source: &str, status: u16, bytes: u64, duration: Duration) {
+		let mut sources = self.sources.lock().unwrap();
+		let metrics = sources.entry(source.to_string()).or_default();
+		*metrics.requests.entry(status).or_default() += 1;
+		let seconds = duration.as_secs_f64();
+		let bucket = BUCKETS.iter().position(|le| seconds <= *le).unwrap_or(BUCKETS.len());
+		metrics.durations[bucket] += 1;
+		metrics.duration_sum += seconds;
+		metrics.bytes += bytes;
+	}
+
+	/// Returns all metrics in the Prometheus text format.
+	pub fn render(&self) -> String {
+		let mut text = String::new();
+		let sources = self.sources.lock().unwrap();
+
+		header(
+			&mut text,
+			"tile_requests_total",
+			"counter",
+			"tile requests by tile source and HTTP status",
+		);
+		for (source, metrics) in sources.iter() {
+			for (status, count) in metrics.requests.iter() {
+				let labels = format!("source=\"{}\",status=\"{status}\"", escape(source));
+				writeln!(text, "versatiles_tile_requests_total{{{labels}}} {count}").unwrap();
+			}
+		}
+
+		header(
+			&mut text,
+			"tile_request_duration_seconds",
+			"histogram",
+			"response times of tile requests by tile source",
+		);
+		for (source, metrics) in sources.iter() {
+			let source = escape(source);
+			let name = "versatiles_tile_request_duration_seconds";
+			let mut count = 0;
+			for (le, n) in BUCKETS.iter().zip(metrics.durations.iter()) {
+				count += n;
+				writeln!(text, "{name}_bucket{{source=\"{source}\",le=\"{le}\"}} {count}").unwrap();
+			}
+			count += metrics.durations[BUCKETS.len()];
+			writeln!(text, "{name}_bucket{{source=\"{source}\",le=\"+Inf\"}} {count}").unwrap();
+			writeln!(text, "{name}_sum{{source=\"{source}\"}} {}", metrics.duration_sum).unwrap();
+			writeln!(text, "{name}_count{{source=\"{source}\"}} {count}").unwrap();
+		}
+
+		header(
+			&mut text,
+			"tile_sent_bytes_total",
+			"counter",
+			"bytes of tile responses by tile source",
+		);
+		for (source, metrics) in sources.iter() {
+			let source = escape(source);
+			writeln!(
+				text,
+				"versatiles_tile_sent_bytes_total{{source=\"{source}\"}} {}",
+				metrics.bytes
+			)
+			.unwrap();
+		}
+		drop(sources);
+
+		header(
+			&mut text,
+			"tile_requests_in_flight",
+			"gauge",
+			"tile requests in progress",
+		);
+		let in_flight = self.in_flight.load(Ordering::Relaxed);
+		writeln!(text, "versatiles_tile_requests_in_flight {in_flight}").unwrap();
+
+		let m = metrics();
+		header(
+			&mut text,
+			"tile_cache_hit_ratio",
+			"gauge",
+			"share of tile cache lookups that were hits",
+		);
+		let lookups = m.server_cache_hits + m.server_cache_misses;
+		let ratio = if lookups == 0 {
+			0.0
+		} else {
+			m.server_cache_hits as f64 / lookups as f64
+		};
+		writeln!(text, "versatiles_tile_cache_hit_ratio {ratio}").unwrap();
+
+		for (name, help, value) in [
+			(
+				"http_requests_total",
+				"HTTP range requests of remote containers",
+				m.http_requests,
+			),
+			("http_retries_total", "repeated HTTP range requests", m.http_retries),
+			(
+				"http_received_bytes_total",
+				"bytes received from remote containers",
+				m.http_bytes,
+			),
+			(
+				"http_cache_hits_total",
+				"reads of remote containers from prefetched chunks",
+				m.http_cache_hits,
+			),
+			(
+				"http_cache_misses_total",
+				"reads of remote containers that fetched chunks",
+				m.http_cache_misses,
+			),
+			(
+				"tile_cache_hits_total",
+				"tile requests answered from the tile cache",
+				m.server_cache_hits,
+			),
+			(
+				"tile_cache_misses_total",
+				"tile requests not found in the tile cache",
+				m.server_cache_misses,
+			),
+			("pipeline_tiles_total", "tiles produced by pipelines", m.pipeline_tiles),
+		] {
+			header(&mut text, name, "counter", help);
+			writeln!(text, "versatiles_{name} {value}").unwrap();
+		}
+
+		text
+	}
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+	writeln!(text, "# HELP versatiles_{name} {help}").unwrap();
+	writeln!(text, "# TYPE versatiles_{name} {kind}").unwrap();
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Middleware that records the requests of the tile source `source`.
+pub async fn track(
+	State((metrics, source)): State<(Arc<ServerMetrics>, String)>,
+	request: Request,
+	next: Next,
+) -> Response {
+	metrics.in_flight.fetch_add(1, Ordering::Relaxed);
+	let start = Instant::now();
+	let response = next.run(request).await;
+	metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+	let bytes = response.body().size_hint().exact().unwrap_or(0);
+	metrics.record(&source, response.status().as_u16(), bytes, start.elapsed());
+	response
+}
+
+/// Handler of "/metrics"
+pub async fn serve_metrics(State(metrics): State<Arc<ServerMetrics>>) -> Response<Body> {
+	Response::builder()
+		.status(200)
+		.header("content-type", "text/plain; version=0.0.4")
+		.body(Body::from(metrics.render()))
+		.expect("should have build a body")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_render() {
+		let metrics = ServerMetrics::default();
+		metrics.record("osm", 200, 1000, Duration::from_millis(3));
+		metrics.record("osm", 200, 500, Duration::from_millis(30));
+		metrics.record("osm", 404, 9, Duration::from_secs(20));
+		metrics.record("a\"b", 200, 1, Duration::ZERO);
+
+		let text = metrics.render();
+		let lines: Vec<&str> = text.lines().collect();
+		for line in [
+			"versatiles_tile_requests_total{source=\"a\\\"b\",status=\"200\"} 1",
+			"versatiles_tile_requests_total{source=\"osm\",status=\"200\"} 2",
+			"versatiles_tile_requests_total{source=\"osm\",status=\"404\"} 1",
+			"versatiles_tile_request_duration_seconds_bucket{source=\"osm\",le=\"0.001\"} 0",
+			"versatiles_tile_request_duration_seconds_bucket{source=\"osm\",le=\"0.005\"} 1",
+			"versatiles_tile_request_duration_seconds_bucket{source=\"osm\",le=\"0.05\"} 2",
+			"versatiles_tile_request_duration_seconds_bucket{source=\"osm\",le=\"10\"} 2",
+			"versatiles_tile_request_duration_seconds_bucket{source=\"osm\",le=\"+Inf\"} 3",
+			"versatiles_tile_request_duration_seconds_count{source=\"osm\"} 3",
+			"versatiles_tile_sent_bytes_total{source=\"osm\"} 1509",
+			"versatiles_tile_requests_in_flight 0",
+			"# TYPE versatiles_tile_cache_hits_total counter",
+		] {
+			assert!(lines.contains(&line), "missing {line:?} in:\n{text}");
+		}
+	}
+}
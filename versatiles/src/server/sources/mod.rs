@@ -0,0 +1,19 @@
+//! implementation of different sources (tile containers, folders, tar and zip files)
+
+mod property_lookup;
+pub use property_lookup::PropertyLookup;
+
+mod response;
+pub use response::SourceResponse;
+
+mod static_source;
+pub use static_source::{StaticSource, StaticSourceOptions};
+
+mod static_source_folder;
+
+mod static_source_tar;
+
+mod static_source_zip;
+
+mod tile_source;
+pub use tile_source::{TileSource, TileSourceOptions};
@@ -0,0 +1,172 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::Blob,
+	utils::{detect_csv_separator, read_csv_iter},
+};
+use versatiles_geometry::{vector_tile::VectorTile, GeoValue};
+
+/// Translates property values of vector tiles with a lookup table, e.g. to rename categories
+/// or to add translated labels, while the tiles are served.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyLookup {
+	/// only features of this layer are changed, all layers if `None`
+	pub layer: Option<String>,
+	/// property whose value is looked up
+	pub property: String,
+	/// property that is set to the looked up value
+	pub target: String,
+	/// new values by the old values
+	pub table: HashMap<String, GeoValue>,
+}
+
+impl PropertyLookup {
+	/// Reads the lookup table from a JSON object like `{"city": "Stadt"}`, or from a CSV file
+	/// with a header, whose first column contains the old values and the second column the new values.
+	pub fn from_path(
+		path: &Path,
+		layer: Option<String>,
+		property: String,
+		target: Option<String>,
+	) -> Result<PropertyLookup> {
+		let table = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+			read_json_table(path)
+		} else {
+			read_csv_table(path)
+		}
+		.with_context(|| format!("reading lookup table {path:?}"))?;
+
+		Ok(PropertyLookup {
+			layer,
+			target: target.unwrap_or_else(|| property.clone()),
+			property,
+			table,
+		})
+	}
+
+	/// Applies the lookup to all matching features of an uncompressed vector tile.
+	/// Features without the property or with an unknown value are not changed.
+	pub fn apply(&self, blob: &Blob) -> Result<Blob> {
+		let mut tile = VectorTile::from_blob(blob).context("Failed to create VectorTile from Blob")?;
+		for layer in tile.layers.iter_mut() {
+			if self.layer.as_ref().is_some_and(|name| name != &layer.name) {
+				continue;
+			}
+			layer.map_properties(|mut properties| {
+				let value = properties
+					.get(&self.property)
+					.and_then(|value| self.table.get(&value.to_string()))
+					.cloned();
+				if let Some(value) = value {
+					properties.insert(self.target.clone(), value);
+				}
+				properties
+			})?;
+		}
+		tile.to_blob().context("Failed to convert VectorTile to Blob")
+	}
+}
+
+fn read_json_table(path: &Path) -> Result<HashMap<String, GeoValue>> {
+	let object = JsonObject::parse_str(&std::fs::read_to_string(path)?)?;
+	object
+		.iter()
+		.map(|(key, value)| {
+			let value = match value {
+				JsonValue::String(text) => GeoValue::String(text.clone()),
+				JsonValue::Number(number) if number.fract() == 0.0 => GeoValue::from(*number as i64),
+				JsonValue::Number(number) => GeoValue::Double(*number),
+				JsonValue::Boolean(boolean) => GeoValue::Bool(*boolean),
+				_ => bail!("value of {key:?} must be a string, number or boolean"),
+			};
+			Ok((key.clone(), value))
+		})
+		.collect()
+}
+
+fn read_csv_table(path: &Path) -> Result<HashMap<String, GeoValue>> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let separator = detect_csv_separator(&mut reader)?;
+	let mut rows = read_csv_iter(reader, separator)?;
+	let header = rows.next().context("the CSV file is empty")??.0;
+	ensure!(
+		header.len() >= 2,
+		"the CSV file must have at least two columns: the old and the new values"
+	);
+
+	let mut table = HashMap::new();
+	for row in rows {
+		let (fields, line, _) = row?;
+		ensure!(fields.len() >= 2, "line {line} has fewer than two fields");
+		table.insert(fields[0].clone(), GeoValue::parse_str(&fields[1]));
+	}
+	Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use versatiles_geometry::{vector_tile::VectorTileLayer, GeoFeature, GeoProperties, Geometry};
+
+	fn make_tile() -> Result<Blob> {
+		let feature = |kind: &str| {
+			let mut feature = GeoFeature::new(Geometry::new_point([1.0, 2.0]));
+			feature.set_properties(GeoProperties::from(vec![("kind", kind)]));
+			feature
+		};
+		let layer = |name: &str| {
+			VectorTileLayer::from_features(name.to_string(), vec![feature("city"), feature("village")], 4096, 1)
+		};
+		VectorTile::new(vec![layer("places")?, layer("labels")?]).to_blob()
+	}
+
+	fn get_kinds(blob: &Blob, property: &str) -> Result<Vec<Vec<String>>> {
+		let tile = VectorTile::from_blob(blob)?;
+		tile
+			.layers
+			.iter()
+			.map(|layer| {
+				Ok(layer
+					.to_features()?
+					.iter()
+					.map(|f| f.properties.get(property).map(|v| v.to_string()).unwrap_or_default())
+					.collect())
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_csv() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("kinds.csv");
+		std::fs::write(&path, "kind;name\ncity;Stadt\ntown;Kleinstadt\n")?;
+
+		let lookup = PropertyLookup::from_path(&path, Some(String::from("places")), String::from("kind"), None)?;
+		assert_eq!(lookup.table.len(), 2);
+		let blob = lookup.apply(&make_tile()?)?;
+		assert_eq!(get_kinds(&blob, "kind")?, [["Stadt", "village"], ["city", "village"]]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_json() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("kinds.json");
+		std::fs::write(&path, r#"{"city": "Stadt", "village": 3}"#)?;
+
+		let lookup = PropertyLookup::from_path(&path, None, String::from("kind"), Some(String::from("kind_de")))?;
+		let blob = lookup.apply(&make_tile()?)?;
+		assert_eq!(get_kinds(&blob, "kind")?, [["city", "village"], ["city", "village"]]);
+		assert_eq!(get_kinds(&blob, "kind_de")?, [["Stadt", "3"], ["Stadt", "3"]]);
+
+		std::fs::write(&path, r#"{"city": ["Stadt"]}"#)?;
+		let error = PropertyLookup::from_path(&path, None, String::from("kind"), None).unwrap_err();
+		assert_eq!(
+			format!("{:#}", error).split(": ").last().unwrap(),
+			"value of \"city\" must be a string, number or boolean"
+		);
+		Ok(())
+	}
+}
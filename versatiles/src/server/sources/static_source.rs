@@ -1,7 +1,14 @@
-use super::{super::utils::Url, static_source_folder::Folder, static_source_tar::TarFile, SourceResponse};
+use super::{
+	super::utils::Url, static_source_folder::Folder, static_source_tar::TarFile, static_source_zip::ZipFile,
+	SourceResponse,
+};
 use anyhow::{ensure, Result};
 use async_trait::async_trait;
-use std::{fmt::Debug, path::Path, sync::Arc};
+use std::{
+	fmt::Debug,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
 use versatiles_core::utils::TargetCompression;
 
 #[async_trait]
@@ -13,23 +20,37 @@ pub trait StaticSourceTrait: Send + Sync + Debug {
 	fn get_data(&self, url: &Url, accept: &TargetCompression) -> Option<SourceResponse>;
 }
 
+/// Options of a static source
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StaticSourceOptions {
+	/// overrides the minimal recompression of the server for this source
+	pub minimal_recompression: Option<bool>,
+}
+
 #[derive(Clone)]
 pub struct StaticSource {
 	source: Arc<Box<dyn StaticSourceTrait>>,
-	prefix: Url,
+	/// folder, tar or zip file of the source
+	pub path: PathBuf,
+	pub prefix: Url,
+	pub options: StaticSourceOptions,
 }
 
 impl StaticSource {
-	pub fn new(path: &Path, prefix: Url) -> Result<StaticSource> {
+	pub fn new(path: &Path, prefix: Url, options: StaticSourceOptions) -> Result<StaticSource> {
 		ensure!(prefix.is_dir());
 
 		Ok(StaticSource {
 			source: Arc::new(if std::fs::metadata(path)?.is_dir() {
 				Box::new(Folder::from(path)?)
+			} else if path.extension().is_some_and(|ext| ext == "zip") {
+				Box::new(ZipFile::from(path)?)
 			} else {
 				Box::new(TarFile::from(path)?)
 			}),
+			path: path.to_path_buf(),
 			prefix,
+			options,
 		})
 	}
 	#[cfg(test)]
@@ -85,12 +106,12 @@ mod tests {
 		use TileCompression::*;
 
 		let check_type = |path: PathBuf, type_name: &str| {
-			let source = StaticSource::new(&path, Url::new("")).unwrap();
+			let source = StaticSource::new(&path, Url::new(""), StaticSourceOptions::default()).unwrap();
 			assert_eq!(source.get_type(), type_name);
 		};
 
 		let check_error = |path: PathBuf, error_should: &str| {
-			let source = StaticSource::new(&path, Url::new(""));
+			let source = StaticSource::new(&path, Url::new(""), StaticSourceOptions::default());
 			let error = source.err().unwrap().to_string();
 			assert!(
 				error.ends_with(error_should),
@@ -128,6 +149,9 @@ mod tests {
 		create_file(&path, Brotli);
 		check_type(path, "tar");
 
+		// Test .zip file
+		check_type(PathBuf::from("../testdata/static.zip"), "zip");
+
 		// Test non .tar file
 		let path = temp_dir.path().join("data.tar.bmp");
 		create_file(&path, Uncompressed);
@@ -145,7 +169,9 @@ mod tests {
 	async fn get_data_valid_path() {
 		let static_source = StaticSource {
 			source: Arc::new(Box::new(MockStaticSource)),
+			path: PathBuf::from("mock"),
 			prefix: Url::new(""),
+			options: StaticSourceOptions::default(),
 		};
 		let result = static_source.get_data(&Url::new("exists"), &TargetCompression::from_none());
 		assert!(result.is_some());
@@ -155,7 +181,9 @@ mod tests {
 	async fn get_data_invalid_path() {
 		let static_source = StaticSource {
 			source: Arc::new(Box::new(MockStaticSource)),
+			path: PathBuf::from("mock"),
 			prefix: Url::new(""),
+			options: StaticSourceOptions::default(),
 		};
 		let result = static_source.get_data(&Url::new("does_not_exist"), &TargetCompression::from_none());
 		assert!(result.is_none());
@@ -165,7 +193,9 @@ mod tests {
 	async fn get_data_with_path_filtering() {
 		let static_source = StaticSource {
 			source: Arc::new(Box::new(MockStaticSource)),
+			path: PathBuf::from("mock"),
 			prefix: Url::new("path/to"),
+			options: StaticSourceOptions::default(),
 		};
 		// Should match and retrieve data
 		let result = static_source.get_data(&Url::new("path/to/exists"), &TargetCompression::from_none());
@@ -12,7 +12,7 @@ use versatiles_core::{
 	utils::TargetCompression,
 };
 
-use crate::tools::server::{utils::guess_mime, Url};
+use crate::server::{utils::guess_mime, Url};
 
 use super::{static_source::StaticSourceTrait, SourceResponse};
 
@@ -75,7 +75,7 @@ impl StaticSourceTrait for Folder {
 
 		let mime = guess_mime(&local_path);
 
-		// Check for compressed versions first (".br" and ".gz"), falling back to uncompressed if neither is found
+		// Falls back to compressed versions (".br", ".gz" and ".zst") if the uncompressed file is not found
 
 		let (file, compression) = if let Ok(file) = File::open(&local_path) {
 			(file, TileCompression::Uncompressed)
@@ -83,6 +83,8 @@ impl StaticSourceTrait for Folder {
 			(file, TileCompression::Brotli)
 		} else if let Ok(file) = File::open(format!("{}.gz", local_path.display())) {
 			(file, TileCompression::Gzip)
+		} else if let Ok(file) = File::open(format!("{}.zst", local_path.display())) {
+			(file, TileCompression::Zstd)
 		} else {
 			return None;
 		};
@@ -15,6 +15,7 @@ struct FileEntry {
 	un: Option<Blob>,
 	gz: Option<Blob>,
 	br: Option<Blob>,
+	zst: Option<Blob>,
 }
 
 impl FileEntry {
@@ -24,6 +25,7 @@ impl FileEntry {
 			un: None,
 			gz: None,
 			br: None,
+			zst: None,
 		}
 	}
 }
@@ -112,6 +114,7 @@ impl TarFile {
 					Uncompressed => versions.un = Some(blob),
 					Gzip => versions.gz = Some(blob),
 					Brotli => versions.br = Some(blob),
+					Zstd => versions.zst = Some(blob),
 				}
 			};
 
@@ -151,6 +154,12 @@ impl StaticSourceTrait for TarFile {
 			}
 		}
 
+		if accept.contains(Zstd) {
+			if let Some(blob) = &file_entry.zst {
+				return SourceResponse::new_some(blob.to_owned(), &Zstd, &file_entry.mime);
+			}
+		}
+
 		if accept.contains(Gzip) {
 			if let Some(blob) = &file_entry.gz {
 				return SourceResponse::new_some(blob.to_owned(), &Gzip, &file_entry.mime);
@@ -169,6 +178,10 @@ impl StaticSourceTrait for TarFile {
 			return SourceResponse::new_some(blob.to_owned(), &Gzip, &file_entry.mime);
 		}
 
+		if let Some(blob) = &file_entry.zst {
+			return SourceResponse::new_some(blob.to_owned(), &Zstd, &file_entry.mime);
+		}
+
 		None
 	}
 }
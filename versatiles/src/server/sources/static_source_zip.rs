@@ -0,0 +1,386 @@
+//! Static content in a ZIP archive, e.g. the release assets of a frontend.
+//!
+//! Only the central directory is read when opening the archive. The files are read from disk on request,
+//! so large archives do not have to fit into memory. Entries can be stored or deflated. Like in tar files,
+//! entries ending in ".gz" or ".br" are precompressed versions of the file without this extension.
+
+use super::super::utils::{guess_mime, Url};
+use super::{static_source::StaticSourceTrait, SourceResponse};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use flate2::read::DeflateDecoder;
+use std::{
+	collections::HashMap,
+	env::current_dir,
+	fmt::Debug,
+	fs::File,
+	io::{Read, Seek, SeekFrom},
+	path::Path,
+	sync::Mutex,
+};
+use versatiles_core::{
+	types::{Blob, TileCompression},
+	utils::TargetCompression,
+};
+
+const SIGNATURE_LOCAL_HEADER: u32 = 0x04034b50;
+const SIGNATURE_CENTRAL_HEADER: u32 = 0x02014b50;
+const SIGNATURE_END_OF_DIRECTORY: u32 = 0x06054b50;
+/// The end of central directory record has 22 bytes, followed by a comment of up to 65535 bytes
+const MAX_END_OF_DIRECTORY_SIZE: u64 = 22 + 65535;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+fn read_u16(buffer: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes([buffer[offset], buffer[offset + 1]])
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+}
+
+/// Position of a file in the archive, as listed in the central directory
+#[derive(Clone, Debug)]
+struct ZipEntry {
+	local_header_offset: u64,
+	compressed_size: u64,
+	method: u16,
+}
+
+#[derive(Debug)]
+struct FileEntry {
+	mime: String,
+	un: Option<ZipEntry>,
+	gz: Option<ZipEntry>,
+	br: Option<ZipEntry>,
+	zst: Option<ZipEntry>,
+}
+
+impl FileEntry {
+	fn new(mime: String) -> Self {
+		FileEntry {
+			mime,
+			un: None,
+			gz: None,
+			br: None,
+			zst: None,
+		}
+	}
+}
+
+pub struct ZipFile {
+	file: Mutex<File>,
+	lookup: HashMap<String, FileEntry>,
+	name: String,
+}
+
+impl ZipFile {
+	pub fn from(path: &Path) -> Result<Self> {
+		use TileCompression::*;
+
+		let path = current_dir()?.join(path).canonicalize()?;
+
+		ensure!(path.exists(), "path {path:?} does not exist");
+		ensure!(path.is_absolute(), "path {path:?} must be absolute");
+		ensure!(path.is_file(), "path {path:?} must be a file");
+		ensure!(
+			path.extension().is_some_and(|ext| ext == "zip"),
+			"{path:?} must be a name of a zip file"
+		);
+
+		let mut file = File::open(&path)?;
+		let directory = read_central_directory(&mut file).with_context(|| format!("Failed to read {path:?}"))?;
+
+		let mut lookup: HashMap<String, FileEntry> = HashMap::new();
+		let mut position = 0;
+		while position < directory.len() {
+			ensure!(
+				position + 46 <= directory.len() && read_u32(&directory, position) == SIGNATURE_CENTRAL_HEADER,
+				"invalid central directory in {path:?}"
+			);
+			let header = &directory[position..];
+			let flags = read_u16(header, 8);
+			let method = read_u16(header, 10);
+			let compressed_size = read_u32(header, 20);
+			let name_length = read_u16(header, 28) as usize;
+			let extra_length = read_u16(header, 30) as usize;
+			let comment_length = read_u16(header, 32) as usize;
+			let local_header_offset = read_u32(header, 42);
+			ensure!(
+				46 + name_length <= header.len(),
+				"invalid central directory in {path:?}"
+			);
+			let name = String::from_utf8_lossy(&header[46..46 + name_length]).into_owned();
+			position += 46 + name_length + extra_length + comment_length;
+
+			if name.ends_with('/') {
+				continue;
+			}
+			ensure!(flags & 1 == 0, "file {name:?} in {path:?} is encrypted");
+			ensure!(
+				compressed_size != u32::MAX && local_header_offset != u32::MAX,
+				"{path:?} is a ZIP64 archive, which is not supported"
+			);
+			ensure!(
+				method == METHOD_STORED || method == METHOD_DEFLATED,
+				"file {name:?} in {path:?} uses the compression method {method}, only stored and deflated files are supported"
+			);
+
+			let mut name = name.as_str();
+			let compression = match name.rsplit_once('.') {
+				Some((base, "br")) => {
+					name = base;
+					Brotli
+				}
+				Some((base, "gz")) => {
+					name = base;
+					Gzip
+				}
+				_ => Uncompressed,
+			};
+			let name = name.trim_start_matches(['.', '/']);
+			let mime = guess_mime(Path::new(name));
+			let entry = ZipEntry {
+				local_header_offset: local_header_offset as u64,
+				compressed_size: compressed_size as u64,
+				method,
+			};
+
+			let mut add = |name: &str| {
+				log::trace!("Adding file from zip: {} ({:?})", name, compression);
+				let versions = lookup
+					.entry(name.to_string())
+					.or_insert_with(|| FileEntry::new(mime.to_string()));
+				match compression {
+					Uncompressed => versions.un = Some(entry.clone()),
+					Gzip => versions.gz = Some(entry.clone()),
+					Brotli => versions.br = Some(entry.clone()),
+					Zstd => versions.zst = Some(entry.clone()),
+				}
+			};
+
+			if let Some(parent) = name.strip_suffix("index.html") {
+				if parent.is_empty() || parent.ends_with('/') {
+					add(parent.trim_end_matches('/'));
+				}
+			}
+			add(name);
+		}
+
+		Ok(Self {
+			file: Mutex::new(file),
+			lookup,
+			name: path.to_str().unwrap().to_owned(),
+		})
+	}
+
+	/// Reads a file of the archive, deflated files are inflated.
+	fn read_entry(&self, entry: &ZipEntry) -> Result<Blob> {
+		let mut file = self.file.lock().unwrap();
+
+		let mut header = [0u8; 30];
+		file.seek(SeekFrom::Start(entry.local_header_offset))?;
+		file.read_exact(&mut header)?;
+		ensure!(
+			read_u32(&header, 0) == SIGNATURE_LOCAL_HEADER,
+			"invalid local header at offset {}",
+			entry.local_header_offset
+		);
+		// the lengths in the local header can differ from the ones in the central directory
+		let skip = read_u16(&header, 26) as i64 + read_u16(&header, 28) as i64;
+		file.seek(SeekFrom::Current(skip))?;
+
+		let mut data = vec![0u8; entry.compressed_size as usize];
+		file.read_exact(&mut data)?;
+		drop(file);
+
+		Ok(Blob::from(match entry.method {
+			METHOD_DEFLATED => {
+				let mut inflated = Vec::new();
+				DeflateDecoder::new(data.as_slice()).read_to_end(&mut inflated)?;
+				inflated
+			}
+			_ => data,
+		}))
+	}
+
+	fn response(&self, entry: &ZipEntry, compression: TileCompression, mime: &str) -> Option<SourceResponse> {
+		match self.read_entry(entry) {
+			Ok(blob) => SourceResponse::new_some(blob, &compression, mime),
+			Err(error) => {
+				log::warn!("Failed to read from {}: {error}", self.name);
+				None
+			}
+		}
+	}
+}
+
+/// Finds the end of central directory record and returns the central directory.
+fn read_central_directory(file: &mut File) -> Result<Vec<u8>> {
+	let file_size = file.seek(SeekFrom::End(0))?;
+	let tail_size = file_size.min(MAX_END_OF_DIRECTORY_SIZE);
+	let mut tail = vec![0u8; tail_size as usize];
+	file.seek(SeekFrom::Start(file_size - tail_size))?;
+	file.read_exact(&mut tail)?;
+
+	let Some(end) = (0..tail.len().saturating_sub(21))
+		.rev()
+		.find(|&i| read_u32(&tail, i) == SIGNATURE_END_OF_DIRECTORY)
+	else {
+		bail!("not a zip file, the end of central directory record is missing");
+	};
+
+	let entry_count = read_u16(&tail, end + 10);
+	let size = read_u32(&tail, end + 12);
+	let offset = read_u32(&tail, end + 16);
+	if entry_count == u16::MAX || size == u32::MAX || offset == u32::MAX {
+		bail!("ZIP64 archives are not supported");
+	}
+	ensure!(
+		offset as u64 + size as u64 <= file_size,
+		"the central directory is outside of the file"
+	);
+
+	let mut directory = vec![0u8; size as usize];
+	file.seek(SeekFrom::Start(offset as u64))?;
+	file.read_exact(&mut directory)?;
+	Ok(directory)
+}
+
+#[async_trait]
+impl StaticSourceTrait for ZipFile {
+	#[cfg(test)]
+	fn get_type(&self) -> &str {
+		"zip"
+	}
+
+	#[cfg(test)]
+	fn get_name(&self) -> &str {
+		&self.name
+	}
+
+	fn get_data(&self, url: &Url, accept: &TargetCompression) -> Option<SourceResponse> {
+		use TileCompression::*;
+
+		let file_entry = self.lookup.get(&url.str[1..])?;
+
+		if accept.contains(Brotli) {
+			if let Some(entry) = &file_entry.br {
+				return self.response(entry, Brotli, &file_entry.mime);
+			}
+		}
+
+		if accept.contains(Zstd) {
+			if let Some(entry) = &file_entry.zst {
+				return self.response(entry, Zstd, &file_entry.mime);
+			}
+		}
+
+		if accept.contains(Gzip) {
+			if let Some(entry) = &file_entry.gz {
+				return self.response(entry, Gzip, &file_entry.mime);
+			}
+		}
+
+		if let Some(entry) = &file_entry.un {
+			return self.response(entry, Uncompressed, &file_entry.mime);
+		}
+
+		if let Some(entry) = &file_entry.br {
+			return self.response(entry, Brotli, &file_entry.mime);
+		}
+
+		if let Some(entry) = &file_entry.gz {
+			return self.response(entry, Gzip, &file_entry.mime);
+		}
+
+		if let Some(entry) = &file_entry.zst {
+			return self.response(entry, Zstd, &file_entry.mime);
+		}
+
+		None
+	}
+}
+
+impl Debug for ZipFile {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ZipFile").field("name", &self.name).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_core::utils::decompress_gzip;
+
+	const APP_JS: &str = "console.log(\"versatiles\");\n";
+
+	fn open() -> ZipFile {
+		ZipFile::from(Path::new("../testdata/static.zip")).unwrap()
+	}
+
+	#[test]
+	fn small_stuff() {
+		let zip_file = open();
+		assert!(zip_file.get_name().ends_with("static.zip"));
+		assert!(format!("{:?}", zip_file).starts_with("ZipFile { name:"));
+
+		let mut names: Vec<&str> = zip_file.lookup.keys().map(String::as_str).collect();
+		names.sort();
+		assert_eq!(names, ["", "assets/app.js", "index.html", "style.css"]);
+	}
+
+	#[test]
+	fn from_invalid_files() {
+		assert!(ZipFile::from(Path::new("path/to/non-existing/file.zip")).is_err());
+		assert!(ZipFile::from(Path::new(".")).is_err());
+
+		let error = ZipFile::from(Path::new("../testdata/berlin.pmtiles")).unwrap_err();
+		assert!(error.to_string().ends_with("must be a name of a zip file"), "{error}");
+
+		let temp_dir = assert_fs::TempDir::new().unwrap();
+		let path = temp_dir.path().join("broken.zip");
+		std::fs::write(&path, "not a zip file").unwrap();
+		let error = ZipFile::from(&path).unwrap_err();
+		assert_eq!(
+			format!("{:#}", error).split(": ").last().unwrap(),
+			"not a zip file, the end of central directory record is missing"
+		);
+	}
+
+	#[test]
+	fn test_get_data() {
+		use TileCompression::*;
+
+		let zip_file = open();
+		let get =
+			|url: &str, accept: TileCompression| zip_file.get_data(&Url::new(url), &TargetCompression::from(accept));
+
+		// deflated
+		let response = get("index.html", Uncompressed).unwrap();
+		assert_eq!(response.blob.as_str(), "<html><body>VersaTiles</body></html>\n");
+		assert_eq!(response.mime, "text/html; charset=utf-8");
+		assert_eq!(response.compression, Uncompressed);
+
+		// index.html of the root
+		assert_eq!(get("", Uncompressed).unwrap().blob, response.blob);
+
+		// stored
+		let response = get("style.css", Brotli).unwrap();
+		assert_eq!(response.blob.as_str(), "body { margin: 0 }\n");
+		assert!(response.mime.starts_with("text/css"), "{}", response.mime);
+
+		// precompressed version
+		let response = get("assets/app.js", Gzip).unwrap();
+		assert_eq!(response.compression, Gzip);
+		assert_eq!(decompress_gzip(&response.blob).unwrap().as_str(), APP_JS.repeat(10));
+
+		let response = get("assets/app.js", Uncompressed).unwrap();
+		assert_eq!(response.compression, Uncompressed);
+		assert_eq!(response.blob.as_str(), APP_JS.repeat(10));
+
+		assert!(get("assets", Uncompressed).is_none());
+		assert!(get("non_existing_file", Uncompressed).is_none());
+	}
+}
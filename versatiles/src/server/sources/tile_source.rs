@@ -0,0 +1,860 @@
+use super::{
+	super::utils::{Auth, Cors, RequestTrace, Url},
+	PropertyLookup, SourceResponse,
+};
+use anyhow::{bail, ensure, Context, Result};
+use std::{fmt::Debug, ops::RangeInclusive, sync::Arc, time::SystemTime};
+use tokio::sync::Mutex;
+use versatiles_container::is_blank_tile;
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	tilejson::TileJSON,
+	types::{Blob, LimitedCache, TileCompression, TileCoord3, TileFormat, TileScheme, TilesReaderTrait},
+	utils::{compress, decompress, TargetCompression},
+};
+use versatiles_geometry::vector_tile::VectorTile;
+use versatiles_image::helper::{blob2image, image2blob_fast, scale_image};
+
+/// Allowed values for the scale of raster tiles, e.g. "@1.5x" or "?scale=1.5"
+const SCALES: RangeInclusive<f64> = 0.25..=4.0;
+
+/// Number of resampled tiles that are kept in the cache
+const SCALED_TILE_CACHE_LENGTH: usize = 1024;
+
+/// Resampled tiles by coordinate and scale in hundredths
+type ScaledTileCache = LimitedCache<(TileCoord3, u16), Blob>;
+
+/// Number of vector tiles with translated properties that are kept in the cache
+const LOOKUP_TILE_CACHE_LENGTH: usize = 1024;
+
+/// Options that change how a tile source is published
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileSourceOptions {
+	/// public base URL of the tiles, e.g. "https://{s}.tiles.example.org/tiles/osm",
+	/// used in the TileJSON instead of the relative URL
+	pub tile_url: Option<String>,
+	/// values for the placeholder "{s}" in `tile_url`
+	pub subdomains: Vec<String>,
+	/// order of the tile rows in the URL
+	pub scheme: TileScheme,
+	/// origins that are allowed to read tiles and metadata
+	pub cors: Cors,
+	/// API keys or signed URLs that are required to read tiles and metadata
+	pub auth: Auth,
+	/// MapLibre style used as template for "style.json", the tile source is added to its sources
+	pub style_template: Option<JsonObject>,
+	/// lookup tables that translate property values of vector tiles, applied in order
+	pub lookups: Vec<PropertyLookup>,
+	/// overrides the minimal recompression of the server for this source
+	pub minimal_recompression: Option<bool>,
+	/// answer requests for blank tiles, i.e. fully transparent images or vector tiles without features,
+	/// with "204 No Content"
+	pub no_content_for_blank_tiles: bool,
+}
+
+impl TileSourceOptions {
+	pub fn check(&self) -> Result<()> {
+		let has_placeholder = self.tile_url.as_ref().is_some_and(|url| url.contains("{s}"));
+		ensure!(
+			has_placeholder || self.subdomains.is_empty(),
+			"subdomains are defined, but the tile URL does not contain the placeholder \"{{s}}\""
+		);
+		ensure!(
+			!has_placeholder || !self.subdomains.is_empty(),
+			"the tile URL contains the placeholder \"{{s}}\", but no subdomains are defined"
+		);
+		Ok(())
+	}
+}
+
+// TileSource struct definition
+#[derive(Clone)]
+pub struct TileSource {
+	pub prefix: Url,
+	pub id: String,
+	reader: Arc<Mutex<Box<dyn TilesReaderTrait>>>,
+	scaled_tiles: Arc<Mutex<ScaledTileCache>>,
+	lookup_tiles: Arc<Mutex<LimitedCache<TileCoord3, Blob>>>,
+	pub tile_mime: String,
+	pub compression: TileCompression,
+	pub options: TileSourceOptions,
+	/// modification time of the container file, or the time the source was opened
+	pub last_modified: SystemTime,
+}
+
+impl TileSource {
+	// Constructor function for creating a TileSource instance
+	pub fn from(reader: Box<dyn TilesReaderTrait>, id: &str, options: TileSourceOptions) -> Result<TileSource> {
+		options.check()?;
+
+		let parameters = reader.get_parameters();
+		ensure!(
+			options.lookups.is_empty() || parameters.tile_format == TileFormat::PBF,
+			"lookup tables can only be applied to vector tiles, but the tile format is {}",
+			parameters.tile_format.as_str()
+		);
+		let tile_mime = parameters.tile_format.as_mime_str().to_string();
+		let compression = parameters.tile_compression;
+		let last_modified = std::fs::metadata(reader.get_source_name())
+			.and_then(|metadata| metadata.modified())
+			.unwrap_or_else(|_| SystemTime::now());
+
+		Ok(TileSource {
+			prefix: Url::new(&format!("/tiles/{id}/")).as_dir(),
+			id: id.to_owned(),
+			reader: Arc::new(Mutex::new(reader)),
+			scaled_tiles: Arc::new(Mutex::new(ScaledTileCache::with_maximum_size(
+				SCALED_TILE_CACHE_LENGTH * size_of::<((TileCoord3, u16), Blob)>(),
+			))),
+			lookup_tiles: Arc::new(Mutex::new(LimitedCache::with_maximum_size(
+				LOOKUP_TILE_CACHE_LENGTH * size_of::<(TileCoord3, Blob)>(),
+			))),
+			tile_mime,
+			compression,
+			options,
+			last_modified,
+		})
+	}
+
+	pub async fn get_source_name(&self) -> String {
+		let reader = self.reader.lock().await;
+		reader.get_source_name().to_owned()
+	}
+
+	/// Returns the parameters of the reader, e.g. tile format and bounding box pyramid.
+	#[cfg(feature = "inspect")]
+	pub async fn get_parameters(&self) -> versatiles_core::types::TilesReaderParameters {
+		self.reader.lock().await.get_parameters().clone()
+	}
+
+	/// Returns the decompressed tile at `coord`, without applying the scheme of the URL.
+	#[cfg(feature = "inspect")]
+	pub async fn get_tile(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let tile = self.reader.lock().await.get_tile_data(coord).await?;
+		tile.map(|blob| decompress(blob, &self.compression)).transpose()
+	}
+
+	// Retrieve the tile data as an HTTP response
+	//
+	// Raster tiles can be resampled with a scale suffix like "0/0/0@1.5x.png" or a query like "scale=1.5".
+	pub async fn get_data(
+		&self,
+		url: &Url,
+		query: Option<&str>,
+		_accept: &TargetCompression,
+		trace: &mut RequestTrace,
+	) -> Result<Option<SourceResponse>> {
+		let parts: Vec<String> = url.as_vec();
+
+		if parts.len() >= 3 {
+			// Parse the tile coordinates
+			let z = parts[0].parse::<u8>();
+			let x = parts[1].parse::<u32>();
+			let y: String = parts[2].chars().take_while(|c| c.is_numeric()).collect();
+			let scale = parse_scale(&parts[2][y.len()..], query)?;
+			let y = y.parse::<u32>();
+
+			// Check for parsing errors
+			ensure!(z.is_ok(), "value for z is not a number");
+			ensure!(x.is_ok(), "value for x is not a number");
+			ensure!(y.is_ok(), "value for y is not a number");
+
+			// Create a TileCoord3 instance
+			let mut coord = TileCoord3::new(x?, y?, z?)?;
+			self.options.scheme.apply(&mut coord);
+
+			log::debug!("get tile, prefix: {}, coord: {}", self.prefix, coord.as_json());
+
+			if let Some(scale) = scale {
+				return self.get_scaled_tile(coord, scale, trace).await;
+			}
+			if !self.options.lookups.is_empty() {
+				return self.get_lookup_tile(coord, trace).await;
+			}
+
+			// Get tile data
+			let reader = self.reader.lock().await;
+			let format = reader.get_parameters().tile_format;
+			trace.step("lookup");
+			let tile = reader.get_tile_data(&coord).await;
+			drop(reader);
+			trace.step("read");
+
+			// If tile data is not found, return a not found response
+			if tile.is_err() {
+				return Ok(None);
+			}
+
+			// If tile data is not found, return a not found response
+			return if let Some(tile) = tile? {
+				if self.options.no_content_for_blank_tiles && self.is_blank(&tile, format) {
+					trace.step("analyze");
+					return Ok(SourceResponse::new_some(
+						Blob::new_empty(),
+						&TileCompression::Uncompressed,
+						&self.tile_mime,
+					));
+				}
+				Ok(SourceResponse::new_some(tile, &self.compression, &self.tile_mime))
+			} else {
+				Ok(None)
+			};
+		} else if (parts[0] == "meta.json") || (parts[0] == "tiles.json") {
+			// Get metadata
+			let tile_json = self.build_tile_json().await?;
+
+			return Ok(SourceResponse::new_some(
+				tile_json,
+				&TileCompression::Uncompressed,
+				"application/json",
+			));
+		}
+
+		// If the request is unknown, return a not found response
+		Ok(None)
+	}
+
+	/// Returns `true` if the compressed `blob` is blank. Tiles that can not be decoded are not blank.
+	fn is_blank(&self, blob: &Blob, format: TileFormat) -> bool {
+		decompress(blob.clone(), &self.compression)
+			.and_then(|blob| is_blank_tile(&blob, format))
+			.unwrap_or(false)
+	}
+
+	/// Returns the tile resampled by `scale`. Resampled tiles are cached, because resampling is expensive.
+	async fn get_scaled_tile(
+		&self,
+		coord: TileCoord3,
+		scale: f64,
+		trace: &mut RequestTrace,
+	) -> Result<Option<SourceResponse>> {
+		let reader = self.reader.lock().await;
+		let format = reader.get_parameters().tile_format;
+		ensure!(
+			matches!(format, TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP),
+			"only JPEG, PNG and WebP tiles can be scaled, but the tile format is {}",
+			format.as_str()
+		);
+
+		let key = (coord, (scale * 100.0).round() as u16);
+		if let Some(blob) = self.scaled_tiles.lock().await.get(&key) {
+			trace.step("cache");
+			return Ok(SourceResponse::new_some(
+				blob,
+				&TileCompression::Uncompressed,
+				&self.tile_mime,
+			));
+		}
+
+		trace.step("lookup");
+		let tile = reader.get_tile_data(&coord).await;
+		drop(reader);
+		trace.step("read");
+
+		let Ok(Some(blob)) = tile else {
+			return Ok(None);
+		};
+		let blob = decompress(blob, &self.compression)?;
+		let image = blob2image(&blob, format).context("Failed to decode tile")?;
+		let blob = image2blob_fast(&scale_image(&image, scale)?, format)?;
+		trace.step("scale");
+
+		self.scaled_tiles.lock().await.add(key, blob.clone());
+		Ok(SourceResponse::new_some(
+			blob,
+			&TileCompression::Uncompressed,
+			&self.tile_mime,
+		))
+	}
+
+	/// Returns the vector tile with property values translated by the lookup tables.
+	/// Translated tiles are cached and recompressed with the compression of the source.
+	async fn get_lookup_tile(&self, coord: TileCoord3, trace: &mut RequestTrace) -> Result<Option<SourceResponse>> {
+		if let Some(blob) = self.lookup_tiles.lock().await.get(&coord) {
+			trace.step("cache");
+			return Ok(SourceResponse::new_some(blob, &self.compression, &self.tile_mime));
+		}
+
+		let reader = self.reader.lock().await;
+		trace.step("lookup");
+		let tile = reader.get_tile_data(&coord).await;
+		drop(reader);
+		trace.step("read");
+
+		let Ok(Some(blob)) = tile else {
+			return Ok(None);
+		};
+		let mut blob = decompress(blob, &self.compression)?;
+		for lookup in &self.options.lookups {
+			blob = lookup.apply(&blob)?;
+		}
+		let blob = compress(blob, &self.compression)?;
+		trace.step("transform");
+
+		self.lookup_tiles.lock().await.add(coord, blob.clone());
+		Ok(SourceResponse::new_some(blob, &self.compression, &self.tile_mime))
+	}
+
+	/// Describes the tile at `coord` (in the scheme of the URL) without its payload, e.g. for smoke tests
+	/// or for debugging blank tiles: whether it exists, its size, compression, content type, whether it can be decoded and whether it is blank.
+	pub async fn get_tile_info(&self, mut coord: TileCoord3) -> Result<JsonObject> {
+		ensure!(
+			coord.is_valid(),
+			"tile {} is outside of zoom level {}",
+			coord.as_json(),
+			coord.z
+		);
+
+		let mut info = JsonObject::default();
+		info.set("z", coord.z);
+		info.set("x", coord.x);
+		info.set("y", coord.y);
+
+		self.options.scheme.apply(&mut coord);
+		let reader = self.reader.lock().await;
+		let format = reader.get_parameters().tile_format;
+		let tile = reader.get_tile_data(&coord).await?;
+		drop(reader);
+
+		info.set("exists", tile.is_some());
+		let Some(blob) = tile else {
+			return Ok(info);
+		};
+		info.set("size", blob.len() as f64);
+		info.set("compression", self.compression.as_str());
+		info.set("content_type", self.tile_mime.as_str());
+
+		match decode_tile(blob.clone(), &self.compression, format) {
+			Ok(()) => {
+				info.set("decode", "ok");
+				info.set("blank", self.is_blank(&blob, format));
+			}
+			Err(err) => info.set("decode", format!("error: {err:#}")),
+		}
+		Ok(info)
+	}
+
+	/// Returns a MapLibre style that shows this tile source, e.g. for inspecting it in maputnik.
+	///
+	/// Relative tile URLs are prefixed with `origin`, e.g. "http://localhost:8080", because styles are often
+	/// loaded from other hosts. Without a template, vector sources get no layers and raster sources one raster layer.
+	pub async fn get_style(&self, origin: &str) -> Result<Option<SourceResponse>> {
+		let style = self.build_style(origin).await?;
+		Ok(SourceResponse::new_some(
+			Blob::from(style.stringify()),
+			&TileCompression::Uncompressed,
+			"application/json",
+		))
+	}
+
+	async fn build_style(&self, origin: &str) -> Result<JsonObject> {
+		let tilejson = self.build_tile_json_object().await?.as_object();
+		let is_raster = tilejson.get_string("type")?.as_deref() == Some("image");
+
+		let mut source = JsonObject::default();
+		source.set("type", if is_raster { "raster" } else { "vector" });
+		let tiles = tilejson.get_string_vec("tiles")?.unwrap_or_default();
+		source.set(
+			"tiles",
+			tiles
+				.iter()
+				.map(|url| {
+					if url.starts_with('/') {
+						format!("{origin}{url}")
+					} else {
+						url.clone()
+					}
+				})
+				.collect::<Vec<_>>(),
+		);
+		for key in ["attribution", "bounds", "maxzoom", "minzoom", "scheme"] {
+			if let Some(value) = tilejson.get(key) {
+				source.set(key, value);
+			}
+		}
+
+		let mut style = match &self.options.style_template {
+			Some(template) => template.clone(),
+			None => {
+				let layers = if is_raster {
+					vec![JsonValue::from(vec![
+						("id", self.id.as_str()),
+						("type", "raster"),
+						("source", self.id.as_str()),
+					])]
+				} else {
+					vec![]
+				};
+				let mut style = JsonObject::default();
+				style.set("version", 8.0);
+				style.set("name", self.id.as_str());
+				style.set("layers", JsonValue::from(layers));
+				style
+			}
+		};
+
+		let mut sources = match style.get("sources") {
+			Some(sources) => sources.as_object()?.clone(),
+			None => JsonObject::default(),
+		};
+		sources.set(&self.id, JsonValue::Object(source));
+		style.set("sources", JsonValue::Object(sources));
+
+		Ok(style)
+	}
+
+	async fn build_tile_json(&self) -> Result<Blob> {
+		Ok(self.build_tile_json_object().await?.into())
+	}
+
+	pub async fn build_tile_json_object(&self) -> Result<TileJSON> {
+		let reader = self.reader.lock().await;
+		let mut tilejson = reader.get_tilejson().clone();
+		let parameters = reader.get_parameters();
+
+		tilejson.update_from_pyramid(&parameters.bbox_pyramid);
+		tilejson.set_string("type", parameters.tile_format.as_type_str())?;
+		tilejson.set_string("name", self.id.as_str())?;
+		tilejson.set_string("format", parameters.tile_format.as_str())?;
+
+		let base_url = match &self.options.tile_url {
+			Some(url) => format!("{}/", url.trim_end_matches('/')),
+			None => self.prefix.as_string(),
+		};
+		let tiles_url = format!("{base_url}{{z}}/{{x}}/{{y}}");
+		let tiles = if self.options.subdomains.is_empty() {
+			vec![tiles_url]
+		} else {
+			// TileJSON clients pick one of the listed URLs per tile, Leaflet picks one of the subdomains
+			self
+				.options
+				.subdomains
+				.iter()
+				.map(|subdomain| tiles_url.replace("{s}", subdomain))
+				.collect()
+		};
+		tilejson.set_list("tiles", tiles)?;
+
+		// the scheme of the source was already applied by its reader, only the scheme of the URL counts
+		tilejson.set_scheme(self.options.scheme);
+
+		Ok(tilejson)
+	}
+}
+
+/// Parses the scale of a tile request, either from the rest of the y segment after the number, e.g. "@1.5x.png",
+/// or from the query parameter "scale". Returns `None` if the tile is requested in its original size.
+fn parse_scale(suffix: &str, query: Option<&str>) -> Result<Option<f64>> {
+	let from_suffix = match suffix.strip_prefix('@') {
+		Some(rest) => match rest.split_once('x') {
+			Some((value, extension)) if extension.is_empty() || extension.starts_with('.') => Some(value),
+			_ => bail!("invalid scale suffix \"{suffix}\", expected something like \"@2x\""),
+		},
+		None => None,
+	};
+	let from_query = query
+		.unwrap_or_default()
+		.split('&')
+		.find_map(|pair| pair.strip_prefix("scale="));
+
+	let Some(value) = from_suffix.or(from_query) else {
+		return Ok(None);
+	};
+	let scale = value
+		.parse::<f64>()
+		.ok()
+		.filter(|scale| SCALES.contains(scale))
+		.with_context(|| {
+			format!(
+				"invalid scale \"{value}\", expected a number from {} to {}",
+				SCALES.start(),
+				SCALES.end()
+			)
+		})?;
+	Ok(if scale == 1.0 { None } else { Some(scale) })
+}
+
+/// Decompresses and decodes a tile to check that it is valid. Formats without a decoder are only decompressed.
+fn decode_tile(blob: Blob, compression: &TileCompression, format: TileFormat) -> Result<()> {
+	let blob = decompress(blob, compression).context("Failed to decompress tile")?;
+	match format {
+		TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP => {
+			blob2image(&blob, format)?;
+		}
+		TileFormat::PBF => {
+			VectorTile::from_blob(&blob)?;
+		}
+		TileFormat::GEOJSON | TileFormat::JSON | TileFormat::TOPOJSON => {
+			JsonValue::parse_blob(&blob)?;
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+// Debug implementation for TileSource
+impl Debug for TileSource {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("TileSource")
+			.field("reader", &self.reader)
+			.field("tile_mime", &self.tile_mime)
+			.field("compression", &self.compression)
+			.field("options", &self.options)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use anyhow::Result;
+	use versatiles_container::{MockTilesReader, MockTilesReaderProfile};
+
+	// Test the constructor function for TileSource
+	#[tokio::test]
+	async fn tile_container_from() -> Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		let container = TileSource::from(reader.boxed(), "prefix", TileSourceOptions::default())?;
+
+		assert_eq!(container.prefix.str, "/tiles/prefix/");
+		assert_eq!(container.build_tile_json().await?.as_str(), "{\"bounds\":[-180,-79.17133464081944,45,66.51326044311185],\"format\":\"png\",\"maxzoom\":3,\"minzoom\":2,\"name\":\"prefix\",\"tilejson\":\"3.0.0\",\"tiles\":[\"/tiles/prefix/{z}/{x}/{y}\"],\"type\":\"image\"}");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn tile_json_with_options() -> Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		let options = TileSourceOptions {
+			tile_url: Some(String::from("https://{s}.tiles.example.org/tiles/osm/")),
+			subdomains: vec![String::from("a"), String::from("b")],
+			scheme: TileScheme::Tms,
+			..Default::default()
+		};
+		let container = TileSource::from(reader.boxed(), "osm", options)?;
+
+		assert_eq!(container.build_tile_json().await?.as_str(), "{\"bounds\":[-180,-79.17133464081944,45,66.51326044311185],\"format\":\"png\",\"maxzoom\":3,\"minzoom\":2,\"name\":\"osm\",\"scheme\":\"tms\",\"tilejson\":\"3.0.0\",\"tiles\":[\"https://a.tiles.example.org/tiles/osm/{z}/{x}/{y}\",\"https://b.tiles.example.org/tiles/osm/{z}/{x}/{y}\"],\"type\":\"image\"}");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn style() -> Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		let container = TileSource::from(reader.boxed(), "osm", TileSourceOptions::default())?;
+		assert_eq!(
+			container.build_style("http://localhost:8080").await?.stringify(),
+			"{\"layers\":[{\"id\":\"osm\",\"source\":\"osm\",\"type\":\"raster\"}],\"name\":\"osm\",\"sources\":{\"osm\":{\"bounds\":[-180,-79.17133464081944,45,66.51326044311185],\"maxzoom\":3,\"minzoom\":2,\"tiles\":[\"http://localhost:8080/tiles/osm/{z}/{x}/{y}\"],\"type\":\"raster\"}},\"version\":8}"
+		);
+
+		// the template keeps its layers and sources
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?;
+		let options = TileSourceOptions {
+			tile_url: Some(String::from("https://tiles.example.org/osm")),
+			style_template: Some(JsonObject::parse_str(
+				"{\"version\":8,\"sources\":{\"other\":{}},\"layers\":[{\"id\":\"water\"}]}",
+			)?),
+			..Default::default()
+		};
+		let container = TileSource::from(reader.boxed(), "osm", options)?;
+		let style = container.build_style("http://localhost:8080").await?;
+		assert_eq!(style.get("layers").unwrap().stringify(), "[{\"id\":\"water\"}]");
+		let sources = style.get("sources").unwrap().as_object()?;
+		assert!(sources.get("other").is_some());
+		let source = sources.get("osm").unwrap().as_object()?;
+		assert_eq!(source.get_string("type")?.unwrap(), "vector");
+		assert_eq!(
+			source.get_string_vec("tiles")?.unwrap(),
+			["https://tiles.example.org/osm/{z}/{x}/{y}"]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn invalid_options() -> Result<()> {
+		let check = |tile_url: &str, subdomains: &[&str]| {
+			let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png).unwrap();
+			let options = TileSourceOptions {
+				tile_url: Some(tile_url.to_string()),
+				subdomains: subdomains.iter().map(|s| s.to_string()).collect(),
+				..Default::default()
+			};
+			TileSource::from(reader.boxed(), "osm", options)
+				.unwrap_err()
+				.to_string()
+		};
+		assert_eq!(
+			check("https://tiles.example.org/", &["a"]),
+			"subdomains are defined, but the tile URL does not contain the placeholder \"{s}\""
+		);
+		assert_eq!(
+			check("https://{s}.tiles.example.org/", &[]),
+			"the tile URL contains the placeholder \"{s}\", but no subdomains are defined"
+		);
+		Ok(())
+	}
+
+	// Test the debug function
+	#[test]
+	fn debug() -> Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		let container = TileSource::from(reader.boxed(), "prefix", TileSourceOptions::default())?;
+		assert_eq!(format!("{container:?}"), "TileSource { reader: Mutex { data: MockTilesReader { parameters: TilesReaderParameters { bbox_pyramid: [2: [0,1,2,3] (9), 3: [0,2,4,6] (25)], tile_compression: Uncompressed, tile_format: PNG } } }, tile_mime: \"image/png\", compression: Uncompressed, options: TileSourceOptions { tile_url: None, subdomains: [], scheme: Xyz, cors: Cors { origins: [\"*\"] }, auth: Auth { api_keys: 0, signed_urls: false }, style_template: None, lookups: [], minimal_recompression: None, no_content_for_blank_tiles: false } }");
+		Ok(())
+	}
+
+	// Test the get_data method of the TileSource
+	#[tokio::test]
+	async fn tile_container_get_data() -> Result<()> {
+		use TileCompression::*;
+
+		async fn check_response(
+			container: &mut TileSource,
+			url: &str,
+			compression: TileCompression,
+			mime_type: &str,
+		) -> Result<Vec<u8>> {
+			let response = container
+				.get_data(
+					&Url::new(url),
+					None,
+					&TargetCompression::from(compression),
+					&mut RequestTrace::default(),
+				)
+				.await?;
+			assert!(response.is_some());
+
+			let response = response.unwrap();
+			assert_eq!(response.mime, mime_type);
+
+			Ok(response.blob.into_vec())
+		}
+
+		async fn check_error_400(container: &mut TileSource, url: &str, compression: TileCompression) -> Result<bool> {
+			let response = container
+				.get_data(
+					&Url::new(url),
+					None,
+					&TargetCompression::from(compression),
+					&mut RequestTrace::default(),
+				)
+				.await;
+			assert!(response.is_err());
+			Ok(true)
+		}
+
+		async fn check_error_404(container: &mut TileSource, url: &str, compression: TileCompression) -> Result<bool> {
+			let response = container
+				.get_data(
+					&Url::new(url),
+					None,
+					&TargetCompression::from(compression),
+					&mut RequestTrace::default(),
+				)
+				.await?;
+			assert!(response.is_none());
+			Ok(true)
+		}
+
+		let c = &mut TileSource::from(
+			MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?.boxed(),
+			"prefix",
+			TileSourceOptions::default(),
+		)?;
+
+		assert_eq!(
+			&check_response(c, "0/0/0.png", Uncompressed, "image/png").await?[0..6],
+			b"\x89PNG\r\n"
+		);
+
+		assert_eq!(
+			String::from_utf8(
+				check_response(c, "meta.json", Uncompressed, "application/json").await?
+			)?,
+			"{\"bounds\":[-180,-79.17133464081944,45,66.51326044311185],\"format\":\"png\",\"maxzoom\":3,\"minzoom\":2,\"name\":\"prefix\",\"tilejson\":\"3.0.0\",\"tiles\":[\"/tiles/prefix/{z}/{x}/{y}\"],\"type\":\"image\"}"
+		);
+
+		assert!(check_error_400(c, "x/0/0.png", Uncompressed).await?);
+		assert!(check_error_400(c, "-1/0/0.png", Uncompressed).await?);
+		assert!(check_error_400(c, "0/0/-1.png", Uncompressed).await?);
+		assert!(check_error_404(c, "0/0/1.png", Uncompressed).await?);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_scale() {
+		let parse = |suffix: &str, query: Option<&str>| parse_scale(suffix, query).map_err(|e| e.to_string());
+		assert_eq!(parse("", None), Ok(None));
+		assert_eq!(parse(".png", Some("v=2")), Ok(None));
+		assert_eq!(parse("@2x.png", None), Ok(Some(2.0)));
+		assert_eq!(parse("@1.5x", None), Ok(Some(1.5)));
+		assert_eq!(parse("", Some("v=2&scale=0.5")), Ok(Some(0.5)));
+		assert_eq!(parse("@1x.png", None), Ok(None));
+		assert_eq!(
+			parse("@2", None),
+			Err(String::from(
+				"invalid scale suffix \"@2\", expected something like \"@2x\""
+			))
+		);
+		assert_eq!(
+			parse("", Some("scale=10")),
+			Err(String::from("invalid scale \"10\", expected a number from 0.25 to 4"))
+		);
+		assert!(parse("@abcx", None).is_err());
+	}
+
+	#[tokio::test]
+	async fn scaled_tiles() -> Result<()> {
+		let get = |source: TileSource, url: &'static str, query: Option<&'static str>| async move {
+			source
+				.get_data(
+					&Url::new(url),
+					query,
+					&TargetCompression::from_none(),
+					&mut RequestTrace::default(),
+				)
+				.await
+		};
+		let size = |response: Option<SourceResponse>| {
+			let image = blob2image(&response.unwrap().blob, TileFormat::PNG).unwrap();
+			(image.width(), image.height())
+		};
+
+		let source = TileSource::from(
+			MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?.boxed(),
+			"osm",
+			TileSourceOptions::default(),
+		)?;
+		assert_eq!(size(get(source.clone(), "0/0/0.png", None).await?), (256, 256));
+		assert_eq!(size(get(source.clone(), "0/0/0@1.5x.png", None).await?), (384, 384));
+		assert_eq!(size(get(source.clone(), "0/0/0", Some("scale=2")).await?), (512, 512));
+
+		// the second request is served from the cache
+		assert!(source
+			.scaled_tiles
+			.lock()
+			.await
+			.get(&(TileCoord3::new(0, 0, 0)?, 150))
+			.is_some());
+		assert_eq!(size(get(source.clone(), "0/0/0@1.5x.png", None).await?), (384, 384));
+
+		assert!(get(source.clone(), "0/0/1@2x.png", None).await?.is_none());
+		assert!(get(source.clone(), "0/0/0@5x.png", None).await.is_err());
+
+		let source = TileSource::from(
+			MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?.boxed(),
+			"osm",
+			TileSourceOptions::default(),
+		)?;
+		assert_eq!(
+			get(source, "0/0/0@2x", None).await.err().unwrap().to_string(),
+			"only JPEG, PNG and WebP tiles can be scaled, but the tile format is pbf"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn lookup_tiles() -> Result<()> {
+		use versatiles_geometry::GeoValue;
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?;
+		let tile = VectorTile::from_blob(&decompress(
+			reader.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.unwrap(),
+			&TileCompression::Gzip,
+		)?)?;
+		let layer = &tile.layers[0];
+		let features = layer.to_features()?;
+		let (property, value) = features[0].properties.iter().next().unwrap();
+
+		let lookup = PropertyLookup {
+			layer: Some(layer.name.clone()),
+			property: property.clone(),
+			target: String::from("translated"),
+			table: [(value.to_string(), GeoValue::from("looked up"))].into_iter().collect(),
+		};
+		let options = TileSourceOptions {
+			lookups: vec![lookup],
+			..Default::default()
+		};
+		let source = TileSource::from(reader.boxed(), "osm", options.clone())?;
+
+		let response = source
+			.get_data(
+				&Url::new("0/0/0"),
+				None,
+				&TargetCompression::from_none(),
+				&mut RequestTrace::default(),
+			)
+			.await?
+			.unwrap();
+		let tile = VectorTile::from_blob(&decompress(response.blob, &TileCompression::Gzip)?)?;
+		let features = tile.layers[0].to_features()?;
+		assert_eq!(
+			features[0].properties.get("translated"),
+			Some(&GeoValue::from("looked up"))
+		);
+		assert!(source
+			.lookup_tiles
+			.lock()
+			.await
+			.get(&TileCoord3::new(0, 0, 0)?)
+			.is_some());
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		assert_eq!(
+			TileSource::from(reader.boxed(), "osm", options)
+				.unwrap_err()
+				.to_string(),
+			"lookup tables can only be applied to vector tiles, but the tile format is png"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn tile_info() -> Result<()> {
+		use versatiles_container::MockTilesReaderOptions;
+		use versatiles_core::types::{TileBBoxPyramid, TilesReaderParameters};
+
+		let info = |reader: MockTilesReader, scheme: TileScheme| async move {
+			let options = TileSourceOptions {
+				scheme,
+				..Default::default()
+			};
+			let source = TileSource::from(reader.boxed(), "osm", options)?;
+			Ok::<_, anyhow::Error>(source.get_tile_info(TileCoord3::new(1, 2, 3)?).await?.stringify())
+		};
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?;
+		assert_eq!(
+			info(reader, TileScheme::Tms).await?,
+			"{\"blank\":false,\"compression\":\"gzip\",\"content_type\":\"application/x-protobuf\",\"decode\":\"ok\",\"exists\":true,\"size\":77,\"x\":1,\"y\":2,\"z\":3}"
+		);
+
+		let parameters = TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(4),
+		);
+		let options = MockTilesReaderOptions {
+			random_size: Some(10..11),
+			..Default::default()
+		};
+		let reader = MockTilesReader::new_mock_with_options(parameters.clone(), options)?;
+		let json = info(reader, TileScheme::Xyz).await?;
+		assert!(json.contains("\"decode\":\"error: "), "{json}");
+		assert!(json.contains("\"size\":10,"), "{json}");
+
+		let options = MockTilesReaderOptions {
+			missing_rate: 1.0,
+			..Default::default()
+		};
+		let reader = MockTilesReader::new_mock_with_options(parameters, options)?;
+		assert_eq!(
+			info(reader, TileScheme::Xyz).await?,
+			"{\"exists\":false,\"x\":1,\"y\":2,\"z\":3}"
+		);
+		Ok(())
+	}
+}
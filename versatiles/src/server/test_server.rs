@@ -0,0 +1,80 @@
+//! A real tile server on an ephemeral port, e.g. for integration tests of frontends.
+//!
+//! ```rust
+//! use versatiles::server::spawn_test_server;
+//! use versatiles_container::{MockTilesReader, MockTilesReaderProfile};
+//! use versatiles_core::types::TilesReaderTrait;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?.boxed();
+//!     let (addr, handle) = spawn_test_server(vec![("osm", reader)]).await?;
+//!     // request e.g. http://{addr}/tiles/osm/0/0/0
+//!     handle.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::{TileServer, TileSourceOptions};
+use anyhow::Result;
+use std::net::SocketAddr;
+use versatiles_core::types::TilesReaderTrait;
+
+/// Keeps a server started by [`spawn_test_server`] running. The server stops when the handle is dropped.
+pub struct ShutdownHandle {
+	server: TileServer,
+}
+
+impl ShutdownHandle {
+	/// Stops the server and waits until requests in flight are finished and the port is free again.
+	pub async fn shutdown(mut self) {
+		self.server.stop().await;
+	}
+}
+
+/// Starts a tile server on an ephemeral port of `127.0.0.1`, serving every reader at "/tiles/{id}/".
+///
+/// The API, e.g. "/tiles/index.json", is enabled. The server is ready to accept requests when this
+/// function returns. Readers with fixed content, like the `MockTilesReader` of `versatiles_container`
+/// (feature `test`), give deterministic responses.
+pub async fn spawn_test_server(
+	sources: Vec<(&str, Box<dyn TilesReaderTrait>)>,
+) -> Result<(SocketAddr, ShutdownHandle)> {
+	let mut server = TileServer::new("127.0.0.1", 0, false, true);
+	for (id, reader) in sources {
+		server.add_tile_source(id, reader, TileSourceOptions::default())?;
+	}
+	server.start().await?;
+	let addr = server.local_addr().expect("server should be running");
+	Ok((addr, ShutdownHandle { server }))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_container::{MockTilesReader, MockTilesReaderProfile};
+
+	#[tokio::test]
+	async fn test_spawn_test_server() -> Result<()> {
+		let reader = |profile| MockTilesReader::new_mock_profile(profile).map(|reader| reader.boxed());
+		let (addr, handle) = spawn_test_server(vec![
+			("png", reader(MockTilesReaderProfile::Png)?),
+			("pbf", reader(MockTilesReaderProfile::Pbf)?),
+		])
+		.await?;
+		assert_ne!(addr.port(), 0);
+
+		let get = |path: &str| reqwest::get(format!("http://{addr}/{path}"));
+		let response = get("tiles/png/0/0/0").await?;
+		assert_eq!(response.status(), 200);
+		assert_eq!(response.headers()["content-type"], "image/png");
+		assert_eq!(get("tiles/pbf/0/0/0").await?.status(), 200);
+		assert_eq!(get("tiles/jpg/0/0/0").await?.status(), 404);
+		let index = get("tiles/index.json").await?.text().await?;
+		assert_eq!(index, "[\"png\",\"pbf\"]");
+
+		handle.shutdown().await;
+		assert!(get("tiles/png/0/0/0").await.is_err());
+		Ok(())
+	}
+}
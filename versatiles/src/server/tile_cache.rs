@@ -0,0 +1,281 @@
+//! In-memory cache of tile responses, so that hot tiles skip reading, decoding and recompression.
+//!
+//! Responses are cached after recompression, separately for every set of accepted encodings.
+//! The least recently used responses are evicted when the cache exceeds its size budget.
+
+use super::sources::SourceResponse;
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex,
+	},
+	time::{Duration, Instant},
+};
+use versatiles_core::{
+	json::JsonObject,
+	metrics::counters,
+	types::{Blob, TileCompression},
+	utils::TargetCompression,
+};
+
+/// Options of the tile cache
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileCacheOptions {
+	/// maximum size of all cached responses in bytes
+	pub max_bytes: u64,
+	/// cached responses older than this are read again, `None` keeps them until they are evicted
+	pub ttl: Option<Duration>,
+}
+
+/// Identifies a cached response.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TileCacheKey {
+	source: String,
+	path: String,
+	/// accepted content encodings: none, gzip, brotli
+	encodings: [bool; 3],
+}
+
+impl TileCacheKey {
+	/// Creates a key for the request of `path` with `query` to the tile source `source`.
+	pub fn new(source: &str, path: &str, query: Option<&str>, target: &TargetCompression) -> TileCacheKey {
+		use TileCompression::*;
+		TileCacheKey {
+			source: source.to_string(),
+			path: match query {
+				Some(query) => format!("{path}?{query}"),
+				None => path.to_string(),
+			},
+			encodings: [Uncompressed, Gzip, Brotli].map(|c| target.contains(c)),
+		}
+	}
+
+	fn size(&self) -> u64 {
+		(self.source.len() + self.path.len() + 3) as u64
+	}
+}
+
+/// A cached response, already in its final content encoding.
+#[derive(Clone)]
+pub struct CachedTile {
+	pub blob: Blob,
+	pub compression: TileCompression,
+	pub mime: String,
+	pub etag: String,
+}
+
+impl CachedTile {
+	pub fn into_response(self) -> SourceResponse {
+		SourceResponse {
+			blob: self.blob,
+			compression: self.compression,
+			mime: self.mime,
+		}
+	}
+
+	fn size(&self) -> u64 {
+		self.blob.len() + (self.mime.len() + self.etag.len()) as u64
+	}
+}
+
+struct Entry {
+	tile: CachedTile,
+	inserted: Instant,
+	/// index in `State::order`
+	access: u64,
+	size: u64,
+}
+
+#[derive(Default)]
+struct State {
+	entries: HashMap<TileCacheKey, Entry>,
+	/// keys by their last access, the least recently used first
+	order: BTreeMap<u64, TileCacheKey>,
+	last_access: u64,
+	bytes: u64,
+}
+
+impl State {
+	fn remove(&mut self, key: &TileCacheKey) {
+		if let Some(entry) = self.entries.remove(key) {
+			self.order.remove(&entry.access);
+			self.bytes -= entry.size;
+		}
+	}
+}
+
+/// A least recently used cache of tile responses with a size budget.
+pub struct TileCache {
+	options: TileCacheOptions,
+	state: Mutex<State>,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+impl TileCache {
+	pub fn new(options: TileCacheOptions) -> TileCache {
+		TileCache {
+			options,
+			state: Mutex::new(State::default()),
+			hits: AtomicU64::new(0),
+			misses: AtomicU64::new(0),
+		}
+	}
+
+	pub fn options(&self) -> &TileCacheOptions {
+		&self.options
+	}
+
+	/// Returns the cached response, if it exists and has not expired.
+	pub fn get(&self, key: &TileCacheKey) -> Option<CachedTile> {
+		let mut state = self.state.lock().unwrap();
+		let expired = match state.entries.get(key) {
+			Some(entry) => self.options.ttl.is_some_and(|ttl| entry.inserted.elapsed() > ttl),
+			None => {
+				drop(state);
+				self.count_miss();
+				return None;
+			}
+		};
+		if expired {
+			state.remove(key);
+			drop(state);
+			self.count_miss();
+			return None;
+		}
+
+		state.last_access += 1;
+		let access = state.last_access;
+		let entry = state.entries.get_mut(key).unwrap();
+		let old_access = std::mem::replace(&mut entry.access, access);
+		let tile = entry.tile.clone();
+		state.order.remove(&old_access);
+		state.order.insert(access, key.clone());
+		drop(state);
+
+		self.hits.fetch_add(1, Ordering::Relaxed);
+		counters().server_cache_hits.inc();
+		Some(tile)
+	}
+
+	/// Adds a response, evicting the least recently used responses until it fits into the size budget.
+	/// Responses larger than the whole budget are not cached.
+	pub fn add(&self, key: TileCacheKey, tile: CachedTile) {
+		let size = key.size() + tile.size();
+		if size > self.options.max_bytes {
+			return;
+		}
+
+		let mut state = self.state.lock().unwrap();
+		state.remove(&key);
+		while state.bytes + size > self.options.max_bytes {
+			let Some((_, oldest)) = state.order.pop_first() else {
+				break;
+			};
+			let entry = state.entries.remove(&oldest).unwrap();
+			state.bytes -= entry.size;
+		}
+
+		state.last_access += 1;
+		let access = state.last_access;
+		state.order.insert(access, key.clone());
+		state.bytes += size;
+		state.entries.insert(
+			key,
+			Entry {
+				tile,
+				inserted: Instant::now(),
+				access,
+				size,
+			},
+		);
+	}
+
+	fn count_miss(&self) {
+		self.misses.fetch_add(1, Ordering::Relaxed);
+		counters().server_cache_misses.inc();
+	}
+
+	/// Returns size and hit/miss counters of the cache, e.g. for the status API.
+	pub fn as_json(&self) -> JsonObject {
+		let state = self.state.lock().unwrap();
+		let mut object = JsonObject::default();
+		object.set("entries", state.entries.len() as f64);
+		object.set("bytes", state.bytes as f64);
+		object.set("max_bytes", self.options.max_bytes as f64);
+		object.set("hits", self.hits.load(Ordering::Relaxed) as f64);
+		object.set("misses", self.misses.load(Ordering::Relaxed) as f64);
+		object
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(path: &str) -> TileCacheKey {
+		TileCacheKey::new("osm", path, None, &TargetCompression::from_none())
+	}
+
+	fn tile(size: usize) -> CachedTile {
+		CachedTile {
+			blob: Blob::new_sized(size),
+			compression: TileCompression::Uncompressed,
+			mime: String::new(),
+			etag: String::new(),
+		}
+	}
+
+	#[test]
+	fn test_lru() {
+		// every entry needs 100 bytes plus 11 bytes for the key
+		let cache = TileCache::new(TileCacheOptions {
+			max_bytes: 350,
+			ttl: None,
+		});
+		cache.add(key("0/0/0"), tile(100));
+		cache.add(key("1/0/0"), tile(100));
+		cache.add(key("1/0/1"), tile(100));
+		assert!(cache.get(&key("0/0/0")).is_some());
+
+		// "1/0/0" is the least recently used entry
+		cache.add(key("1/1/0"), tile(100));
+		assert!(cache.get(&key("1/0/0")).is_none());
+		assert!(cache.get(&key("0/0/0")).is_some());
+		assert!(cache.get(&key("1/1/0")).is_some());
+
+		// too large for the cache
+		cache.add(key("2/0/0"), tile(400));
+		assert!(cache.get(&key("2/0/0")).is_none());
+
+		assert_eq!(
+			cache.as_json().stringify(),
+			"{\"bytes\":333,\"entries\":3,\"hits\":3,\"max_bytes\":350,\"misses\":2}"
+		);
+	}
+
+	#[test]
+	fn test_ttl() {
+		let cache = TileCache::new(TileCacheOptions {
+			max_bytes: 1000,
+			ttl: Some(Duration::ZERO),
+		});
+		cache.add(key("0/0/0"), tile(10));
+		std::thread::sleep(Duration::from_millis(2));
+		assert!(cache.get(&key("0/0/0")).is_none());
+		assert_eq!(cache.as_json().get("entries").unwrap().stringify(), "0");
+	}
+
+	#[test]
+	fn test_key() {
+		let mut target = TargetCompression::from_none();
+		let plain = TileCacheKey::new("osm", "0/0/0", None, &target);
+		target.insert(TileCompression::Brotli);
+		assert_ne!(plain, TileCacheKey::new("osm", "0/0/0", None, &target));
+		assert_ne!(
+			TileCacheKey::new("osm", "0/0/0", Some("scale=2"), &target),
+			TileCacheKey::new("osm", "0/0/0", None, &target)
+		);
+	}
+}
@@ -0,0 +1,1958 @@
+use super::{
+	jobs::{JobManager, JobRequest, JobsOptions},
+	sources::{SourceResponse, StaticSource, StaticSourceOptions, TileSource, TileSourceOptions},
+	tile_cache::{CachedTile, TileCache, TileCacheKey, TileCacheOptions},
+	utils::{add_cors_headers, require_auth, Actor, Auth, RequestTrace, Url},
+};
+use anyhow::{bail, ensure, Result};
+use axum::{
+	body::Body,
+	extract::{Path as UrlPath, State},
+	http::{
+		header::{
+			ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_MATCH, IF_MODIFIED_SINCE,
+			IF_NONE_MATCH, LAST_MODIFIED,
+		},
+		HeaderMap, Uri,
+	},
+	response::Response,
+	routing::{get, post},
+	Extension, Router,
+};
+use hyper::header::{ACCESS_CONTROL_ALLOW_ORIGIN, VARY};
+use sha2::{Digest, Sha256};
+use std::{
+	hash::{DefaultHasher, Hash, Hasher},
+	net::SocketAddr,
+	path::Path,
+	sync::{Arc, RwLock},
+	time::SystemTime,
+};
+use tokio::{
+	sync::{mpsc, oneshot::Sender},
+	task::JoinHandle,
+};
+use tower::ServiceExt;
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	metrics::{counters, metrics},
+	types::{Blob, TileCompression, TileCoord3, TilesReaderTrait},
+	utils::{compress, decompress, select_compression, TargetCompression},
+};
+
+/// A reload requested via the admin API "/api/config/reload", see [`TileServer::set_config_api`].
+#[derive(Debug)]
+pub struct ReloadRequest {
+	/// the value of the `If-Match` header
+	pub if_match: String,
+	/// who requested the reload, for the audit log
+	pub actor: String,
+	pub response: tokio::sync::oneshot::Sender<ReloadOutcome>,
+}
+
+/// The result of a reload, see [`TileServer::reload_sources`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReloadOutcome {
+	/// the configuration was reloaded and has the new ETag
+	Reloaded(String),
+	/// the If-Match value doesn't match the ETag of the loaded configuration
+	Outdated(String),
+	/// the new configuration couldn't be loaded
+	Failed(String),
+}
+
+pub struct TileServer {
+	ip: String,
+	port: u16,
+	tile_sources: Vec<TileSource>,
+	static_sources: Vec<StaticSource>,
+	exit_signal: Option<Sender<()>>,
+	/// the task of the running server and the address it listens on
+	running: Option<(JoinHandle<()>, SocketAddr)>,
+	use_best_compression: bool,
+	use_api: bool,
+	trace: bool,
+	jobs: Option<(JobManager, Auth)>,
+	/// reload requests of the admin API "/api/config" are sent to the owner of the server
+	config_api: Option<(Auth, mpsc::Sender<ReloadRequest>)>,
+	tile_cache: Option<Arc<TileCache>>,
+	#[cfg(feature = "prometheus")]
+	metrics: Arc<super::prometheus::ServerMetrics>,
+	router: Arc<RwLock<Router>>,
+	#[cfg(feature = "http3")]
+	tls: Option<super::TlsOptions>,
+	#[cfg(feature = "http3")]
+	http3_listener: Option<super::http3::Http3Listener>,
+}
+
+impl TileServer {
+	pub fn new(ip: &str, port: u16, use_best_compression: bool, use_api: bool) -> TileServer {
+		TileServer {
+			ip: ip.to_owned(),
+			port,
+			tile_sources: Vec::new(),
+			static_sources: Vec::new(),
+			exit_signal: None,
+			running: None,
+			use_best_compression,
+			use_api,
+			trace: false,
+			jobs: None,
+			config_api: None,
+			tile_cache: None,
+			#[cfg(feature = "prometheus")]
+			metrics: Arc::default(),
+			router: Arc::new(RwLock::new(Router::new())),
+			#[cfg(feature = "http3")]
+			tls: None,
+			#[cfg(feature = "http3")]
+			http3_listener: None,
+		}
+	}
+
+	/// Serves all requests with HTTPS instead of HTTP, and additionally with HTTP/3 on the same port,
+	/// using the certificate of `options`.
+	#[cfg(feature = "http3")]
+	pub fn set_tls(&mut self, options: Option<super::TlsOptions>) {
+		self.tls = options;
+	}
+
+	/// Adds a timing breakdown to every tile response, not only to requests with the `X-Versatiles-Trace` header.
+	pub fn set_trace(&mut self, trace: bool) {
+		self.trace = trace;
+	}
+
+	/// Enables the admin API "/api/jobs" for starting conversions on the server, and loads the stored jobs.
+	pub fn set_jobs(&mut self, options: Option<JobsOptions>) -> Result<()> {
+		self.jobs = match options {
+			Some(options) => Some((JobManager::new(options.root())?, options.auth().clone())),
+			None => None,
+		};
+		Ok(())
+	}
+
+	/// Enables the admin API "/api/config" for reading the loaded configuration and requesting a reload.
+	///
+	/// The server can't rebuild itself from the configuration, so every reload request is sent to
+	/// the returned receiver. Its owner is expected to build a new server and to pass it to
+	/// [`TileServer::reload_sources`] together with the `If-Match` value and the actor of the request.
+	pub fn set_config_api(&mut self, auth: Auth) -> Result<mpsc::Receiver<ReloadRequest>> {
+		ensure!(!auth.is_public(), "the config API requires at least one API key");
+		let (tx, rx) = mpsc::channel(1);
+		self.config_api = Some((auth, tx));
+		Ok(rx)
+	}
+
+	/// Caches encoded tile responses in memory, so that repeated requests skip reading and recompression.
+	pub fn set_tile_cache(&mut self, options: Option<TileCacheOptions>) {
+		self.tile_cache = options.map(|options| Arc::new(TileCache::new(options)));
+	}
+
+	pub fn add_tile_source(
+		&mut self,
+		id: &str,
+		reader: Box<dyn TilesReaderTrait>,
+		options: TileSourceOptions,
+	) -> Result<()> {
+		log::info!("add source: id='{}', source={:?}", id, reader);
+
+		let source = TileSource::from(reader, id, options)?;
+		let url_prefix = &source.prefix;
+
+		for other_tile_source in self.tile_sources.iter() {
+			let other_prefix = &other_tile_source.prefix;
+			if other_prefix.starts_with(url_prefix) || url_prefix.starts_with(other_prefix) {
+				bail!("multiple sources with the prefix '{url_prefix}' and '{other_prefix}' are defined");
+			};
+		}
+
+		self.tile_sources.push(source);
+
+		Ok(())
+	}
+
+	pub fn add_static_source(&mut self, path: &Path, url_prefix: Url) -> Result<()> {
+		self.add_static_source_with_options(path, url_prefix, StaticSourceOptions::default())
+	}
+
+	pub fn add_static_source_with_options(
+		&mut self,
+		path: &Path,
+		url_prefix: Url,
+		options: StaticSourceOptions,
+	) -> Result<()> {
+		let url_prefix = url_prefix.as_dir();
+
+		log::info!("add static: {path:?}");
+		self.static_sources.push(StaticSource::new(path, url_prefix, options)?);
+		Ok(())
+	}
+
+	pub async fn start(&mut self) -> Result<()> {
+		if self.exit_signal.is_some() {
+			self.stop().await
+		}
+
+		log::info!("starting server");
+
+		*self.router.write().unwrap() = self.build_router().await?;
+
+		// Every request is dispatched to the current router, so that `reload_sources` can swap it
+		// without closing the listener. Requests in flight finish with the router they started with.
+		let shared_router = self.router.clone();
+		let router = Router::new().fallback(move |request: axum::extract::Request| {
+			let router = shared_router.read().unwrap().clone();
+			router.oneshot(request)
+		});
+
+		let listener = tokio::net::TcpListener::bind(format!("{}:{}", self.ip, self.port)).await?;
+		let addr = listener.local_addr()?;
+		versatiles_core::progress::print_status(&format!("server starts listening on {addr}"));
+
+		let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+		let shutdown = async {
+			rx.await.ok();
+		};
+
+		#[cfg(feature = "http3")]
+		if let Some(options) = &self.tls {
+			let acceptor = options.acceptor()?;
+			// with port 0, HTTP/3 uses the port chosen for TCP
+			let http3 = super::http3::Http3Listener::start(options, addr, router.clone())?;
+			versatiles_core::progress::print_status(&format!("server starts listening for HTTP/3 on {addr}"));
+			self.http3_listener = Some(http3);
+			let alt_svc = super::http3::alt_svc_header(addr.port());
+			let router = router.layer(axum::middleware::map_response(move |mut response: Response| {
+				response.headers_mut().insert(hyper::header::ALT_SVC, alt_svc.clone());
+				async { response }
+			}));
+
+			let task = tokio::spawn(super::tls::serve(listener, acceptor, router, shutdown));
+			self.exit_signal = Some(tx);
+			self.running = Some((task, addr));
+			return Ok(());
+		}
+
+		let task = tokio::spawn(async {
+			axum::serve(listener, router.into_make_service())
+				.with_graceful_shutdown(shutdown)
+				.await
+				.expect("should start server")
+		});
+
+		self.exit_signal = Some(tx);
+		self.running = Some((task, addr));
+
+		Ok(())
+	}
+
+	/// Returns the address the server listens on, while it is running.
+	/// With port `0` it contains the port chosen by the operating system.
+	pub fn local_addr(&self) -> Option<SocketAddr> {
+		self.running.as_ref().map(|(_, addr)| *addr)
+	}
+
+	/// Returns the ETag of the loaded configuration: the tile and static sources with their options,
+	/// the options for compression, API, tracing and the tile cache.
+	pub async fn config_etag(&self) -> String {
+		let mut hasher = DefaultHasher::new();
+		for source in self.tile_sources.iter() {
+			source.id.hash(&mut hasher);
+			source.prefix.as_string().hash(&mut hasher);
+			source.get_source_name().await.hash(&mut hasher);
+			format!("{:?}", source.options).hash(&mut hasher);
+			source.last_modified.hash(&mut hasher);
+		}
+		for source in self.static_sources.iter() {
+			source.path.hash(&mut hasher);
+			source.prefix.as_string().hash(&mut hasher);
+			format!("{:?}", source.options).hash(&mut hasher);
+		}
+		self.use_best_compression.hash(&mut hasher);
+		self.use_api.hash(&mut hasher);
+		self.trace.hash(&mut hasher);
+		format!("{:?}", self.tile_cache.as_ref().map(|cache| cache.options())).hash(&mut hasher);
+		format!("\"config-{:016x}\"", hasher.finish())
+	}
+
+	/// Returns the loaded sources as served by "/api/config".
+	async fn config_json(&self) -> JsonObject {
+		let mut tile_sources = Vec::new();
+		for source in self.tile_sources.iter() {
+			let mut entry = JsonObject::default();
+			entry.set("id", source.id.as_str());
+			entry.set("prefix", source.prefix.as_string());
+			entry.set("source", source.get_source_name().await);
+			tile_sources.push(JsonValue::Object(entry));
+		}
+		let mut static_sources = Vec::new();
+		for source in self.static_sources.iter() {
+			let mut entry = JsonObject::default();
+			entry.set("prefix", source.prefix.as_string());
+			entry.set("path", source.path.to_string_lossy().to_string());
+			static_sources.push(JsonValue::Object(entry));
+		}
+		let mut config = JsonObject::default();
+		config.set("tile_sources", JsonValue::from(tile_sources));
+		config.set("static_sources", JsonValue::from(static_sources));
+		config
+	}
+
+	/// Replaces all tile and static sources with the ones of `other`, as well as the options
+	/// for compression, API, tracing and the tile cache.
+	///
+	/// The reload is only done if `if_match` matches the current [`TileServer::config_etag`] or is `*`,
+	/// otherwise [`ReloadOutcome::Outdated`] is returned. Every reload is logged with the `actor` and
+	/// the old and new ETag at the log target "audit".
+	///
+	/// If the server is running, new requests are served from the new sources immediately, while
+	/// open connections and requests in flight are not interrupted. IP, port, the HTTP/3 listener,
+	/// the jobs and the config API of the admin API and the metrics at "/metrics" are kept.
+	pub async fn reload_sources(&mut self, other: TileServer, if_match: &str, actor: &str) -> Result<ReloadOutcome> {
+		let old_etag = self.config_etag().await;
+		if !if_match_matches(if_match, &old_etag) {
+			log::warn!("{actor} tried to reload the configuration with If-Match {if_match}, but it is {old_etag}");
+			return Ok(ReloadOutcome::Outdated(old_etag));
+		}
+
+		if (other.ip.as_str(), other.port) != (self.ip.as_str(), self.port) {
+			log::warn!(
+				"changing the address from {}:{} to {}:{} requires a restart",
+				self.ip,
+				self.port,
+				other.ip,
+				other.port
+			);
+		}
+
+		self.tile_sources = other.tile_sources;
+		self.static_sources = other.static_sources;
+		self.use_best_compression = other.use_best_compression;
+		self.use_api = other.use_api;
+		self.trace = other.trace;
+		self.tile_cache = other.tile_cache;
+
+		if self.exit_signal.is_some() {
+			let router = self.build_router().await?;
+			*self.router.write().unwrap() = router;
+		}
+
+		let new_etag = self.config_etag().await;
+		log::info!(target: "audit", "{actor} reloaded the configuration, ETag {old_etag} -> {new_etag}");
+		Ok(ReloadOutcome::Reloaded(new_etag))
+	}
+
+	pub async fn stop(&mut self) {
+		if self.exit_signal.is_none() {
+			return;
+		}
+
+		log::info!("stopping server");
+
+		self
+			.exit_signal
+			.take()
+			.expect("should have exit signal")
+			.send(())
+			.expect("should habe send exit signal");
+
+		// requests in flight are finished, idle connections are closed
+		if let Some((task, _)) = self.running.take() {
+			if let Err(error) = task.await {
+				log::warn!("server stopped with an error: {error}");
+			}
+		}
+
+		#[cfg(feature = "http3")]
+		if let Some(http3) = self.http3_listener.take() {
+			http3.stop().await;
+		}
+	}
+
+	async fn build_router(&self) -> Result<Router> {
+		let mut router = Router::new().route("/status", get(|| async { "ready!" }));
+
+		#[cfg(feature = "prometheus")]
+		{
+			let metrics_app = Router::new()
+				.route("/metrics", get(super::prometheus::serve_metrics))
+				.with_state(self.metrics.clone());
+			router = router.merge(metrics_app);
+		}
+
+		router = self.add_tile_sources_to_app(router);
+		if self.use_api {
+			router = self.add_api_to_app(router).await?;
+			#[cfg(feature = "inspect")]
+			{
+				// protected sources must not be readable via the inspection pages
+				let public_sources = self
+					.tile_sources
+					.iter()
+					.filter(|source| source.options.auth.is_public())
+					.cloned()
+					.collect();
+				router = router.merge(super::inspect::inspect_app(public_sources));
+			}
+		}
+		router = self.add_static_sources_to_app(router);
+
+		Ok(router)
+	}
+
+	fn add_tile_sources_to_app(&self, mut app: Router) -> Router {
+		for tile_source in self.tile_sources.iter() {
+			let route = tile_source.prefix.join_as_string("{*path}");
+
+			let mut tile_app = Router::new().route(&route, get(serve_tile)).with_state((
+				tile_source.clone(),
+				self.use_best_compression,
+				self.trace,
+				self.tile_cache.clone(),
+			));
+
+			if !tile_source.options.auth.is_public() {
+				tile_app = tile_app.layer(axum::middleware::from_fn_with_state(
+					tile_source.options.auth.clone(),
+					require_auth,
+				));
+			}
+
+			// outside of `require_auth`, so that 401 and 403 have CORS headers, too
+			tile_app = tile_app.layer(axum::middleware::from_fn_with_state(
+				tile_source.options.cors.clone(),
+				add_cors_headers,
+			));
+
+			#[cfg(feature = "prometheus")]
+			{
+				tile_app = tile_app.layer(axum::middleware::from_fn_with_state(
+					(self.metrics.clone(), tile_source.id.clone()),
+					super::prometheus::track,
+				));
+			}
+
+			app = app.merge(tile_app);
+
+			async fn serve_tile(
+				uri: Uri,
+				headers: HeaderMap,
+				State((tile_source, use_best_compression, trace, tile_cache)): State<(
+					TileSource,
+					bool,
+					bool,
+					Option<Arc<TileCache>>,
+				)>,
+			) -> Response<Body> {
+				let path = Url::new(uri.path());
+				let mut trace = RequestTrace::from_request(trace, &headers);
+
+				log::debug!("handle tile request: {path}");
+				counters().server_requests.inc();
+
+				let mut target_compressions = get_encoding(headers.clone());
+				if tile_source
+					.options
+					.minimal_recompression
+					.unwrap_or(!use_best_compression)
+				{
+					target_compressions.set_fast_compression();
+				}
+
+				let sub_path = path
+					.strip_prefix(&tile_source.prefix)
+					.expect("should start with prefix");
+				let is_style = sub_path.as_vec() == ["style.json"];
+				let last_modified = tile_source.last_modified;
+
+				// the style depends on the origin of the request, so it is never cached
+				let cache_key = match &tile_cache {
+					Some(_) if !is_style => Some(TileCacheKey::new(
+						&tile_source.id,
+						&path.as_string(),
+						uri.query(),
+						&target_compressions,
+					)),
+					_ => None,
+				};
+				if let (Some(cache), Some(key)) = (&tile_cache, &cache_key) {
+					if let Some(cached) = cache.get(key) {
+						trace.step("cache");
+						let mut response = if is_not_modified(&headers, &cached.etag, last_modified) {
+							log::info!("send cached 304 for tile request: {path}");
+							not_modified(&cached.etag, last_modified)
+						} else {
+							log::info!("send cached response for tile request: {path}");
+							let etag = cached.etag.clone();
+							with_validators(ok_encoded(cached.into_response()), &etag, last_modified)
+						};
+						trace.finish(&path, response.headers_mut());
+						return response;
+					}
+				}
+
+				let response = if is_style {
+					tile_source.get_style(&get_origin(&headers)).await
+				} else {
+					tile_source
+						.get_data(&sub_path, uri.query(), &target_compressions, &mut trace)
+						.await
+				};
+
+				let mut response = if let Ok(Some(response)) = response {
+					let compression = response_compression(&response, &mut target_compressions);
+					let etag = get_etag(&response.blob, &compression);
+					if response.blob.is_empty() {
+						log::info!("send 204 for tile request: {path}");
+						no_content()
+					} else if is_not_modified(&headers, &etag, last_modified) {
+						log::info!("send 304 for tile request: {path}");
+						not_modified(&etag, last_modified)
+					} else if let (Some(cache), Some(key)) = (&tile_cache, cache_key) {
+						log::info!("send response for tile request: {path}");
+						let blob = encode_blob(response.blob, &response.compression, &compression, &mut trace);
+						let cached = CachedTile {
+							blob,
+							compression,
+							mime: response.mime,
+							etag: etag.clone(),
+						};
+						cache.add(key, cached.clone());
+						with_validators(ok_encoded(cached.into_response()), &etag, last_modified)
+					} else {
+						log::info!("send response for tile request: {path}");
+						with_validators(ok_data(response, target_compressions, &mut trace), &etag, last_modified)
+					}
+				} else if let Err(err) = response {
+					log::warn!("send 400 for tile request: {path}. Reason: {err}");
+					counters().server_errors.inc();
+					error_400()
+				} else {
+					log::warn!("send 404 for tile request: {path}");
+					counters().server_not_found.inc();
+					error_404()
+				};
+
+				trace.finish(&path, response.headers_mut());
+				response
+			}
+		}
+
+		app
+	}
+
+	fn add_static_sources_to_app(&self, app: Router) -> Router {
+		let static_app = Router::new()
+			.fallback(get(serve_static))
+			.with_state((self.static_sources.clone(), self.use_best_compression));
+
+		return app.merge(static_app);
+
+		async fn serve_static(
+			uri: Uri,
+			headers: HeaderMap,
+			State((sources, use_best_compression)): State<(Vec<StaticSource>, bool)>,
+		) -> Response<Body> {
+			let mut url = Url::new(uri.path());
+
+			log::debug!("handle static request: {url}");
+
+			if url.is_dir() {
+				url.push("index.html");
+			}
+
+			let accepted_compressions = get_encoding(headers);
+
+			for source in sources.iter() {
+				let mut target_compressions = accepted_compressions.clone();
+				if source.options.minimal_recompression.unwrap_or(!use_best_compression) {
+					target_compressions.set_fast_compression();
+				}
+				if let Some(result) = source.get_data(&url, &target_compressions) {
+					log::info!("send response to static request: {url}");
+					return ok_data(result, target_compressions, &mut RequestTrace::default());
+				}
+			}
+
+			log::warn!("send 404 to static request: {url}");
+			error_404()
+		}
+	}
+
+	async fn add_api_to_app(&self, app: Router) -> Result<Router> {
+		let mut api_app = Router::new();
+
+		// sources that require authentication are not listed
+		let tiles_index_json: String = format!(
+			"[{}]",
+			self
+				.tile_sources
+				.iter()
+				.filter(|s| s.options.auth.is_public())
+				.map(|s| format!("\"{}\"", s.id))
+				.collect::<Vec<String>>()
+				.join(","),
+		);
+
+		api_app = api_app.route("/tiles/index.json", get(|| async move { ok_json(&tiles_index_json) }));
+
+		let tile_cache = self.tile_cache.clone();
+		api_app = api_app.route(
+			"/api/status",
+			get(|| async move { ok_json(&get_status(tile_cache.as_deref()).stringify()) }),
+		);
+
+		for tile_source in self.tile_sources.iter() {
+			let route = format!("/api/source/{}/tile/{{z}}/{{x}}/{{y}}/info", tile_source.id);
+			let mut info_app = Router::new()
+				.route(&route, get(tile_info))
+				.with_state(tile_source.clone());
+			if !tile_source.options.auth.is_public() {
+				info_app = info_app.layer(axum::middleware::from_fn_with_state(
+					tile_source.options.auth.clone(),
+					require_auth,
+				));
+			}
+			api_app = api_app.merge(info_app);
+		}
+
+		if let Some((jobs, auth)) = &self.jobs {
+			let jobs_app = jobs_app(jobs.clone()).layer(axum::middleware::from_fn_with_state(auth.clone(), require_auth));
+			api_app = api_app.merge(jobs_app);
+		}
+
+		if let Some((auth, reload)) = &self.config_api {
+			let state = ConfigState {
+				config: self.config_json().await.stringify(),
+				etag: self.config_etag().await,
+				reload: reload.clone(),
+			};
+			let config_app = config_app(state).layer(axum::middleware::from_fn_with_state(auth.clone(), require_auth));
+			api_app = api_app.merge(config_app);
+		}
+
+		Ok(app.merge(api_app))
+	}
+
+	pub async fn get_url_mapping(&self) -> Vec<(String, String)> {
+		let mut result = Vec::new();
+		for tile_source in self.tile_sources.iter() {
+			let id = tile_source.get_source_name().await;
+			result.push((tile_source.prefix.as_string(), id.to_owned()))
+		}
+		result
+	}
+}
+
+/// Returns the counters of the server and the statistics of its tile cache, as served by "/api/status".
+fn get_status(tile_cache: Option<&TileCache>) -> JsonObject {
+	let metrics = metrics();
+	let mut server = JsonObject::default();
+	server.set("requests", metrics.server_requests as f64);
+	server.set("not_found", metrics.server_not_found as f64);
+	server.set("errors", metrics.server_errors as f64);
+
+	let mut status = JsonObject::default();
+	status.set("server", JsonValue::Object(server));
+	if let Some(tile_cache) = tile_cache {
+		status.set("tile_cache", JsonValue::Object(tile_cache.as_json()));
+	}
+	status
+}
+
+/// Serves "/api/source/{name}/tile/{z}/{x}/{y}/info": a description of a tile as JSON, without its payload.
+async fn tile_info(
+	State(tile_source): State<TileSource>,
+	UrlPath((z, x, y)): UrlPath<(u8, u32, u32)>,
+) -> Response<Body> {
+	let info = match TileCoord3::new(x, y, z) {
+		Ok(coord) => tile_source.get_tile_info(coord).await,
+		Err(err) => Err(err),
+	};
+	match info {
+		Ok(info) => ok_json(&info.stringify()),
+		Err(err) => {
+			log::warn!("send 400 for tile info request: {z}/{x}/{y}. Reason: {err}");
+			error_400()
+		}
+	}
+}
+
+fn jobs_app(jobs: JobManager) -> Router {
+	async fn list(State(jobs): State<JobManager>) -> Response<Body> {
+		with_etag(ok_json(&jobs.as_json().stringify()), &jobs.etag())
+	}
+
+	async fn start(
+		State(jobs): State<JobManager>,
+		Extension(Actor(actor)): Extension<Actor>,
+		headers: HeaderMap,
+		body: String,
+	) -> Response<Body> {
+		let old_etag = jobs.etag();
+		if let Some(response) = check_if_match(&headers, &old_etag) {
+			return response;
+		}
+		match JobRequest::from_json(&body).and_then(|request| jobs.check_request(&request).map(|()| request)) {
+			Ok(request) => {
+				let job = jobs.start(request);
+				log::info!(target: "audit", "{actor} started job {}, ETag {old_etag} -> {}", job.id, jobs.etag());
+				with_etag(ok_json(&job.as_json().stringify()), &job.etag())
+			}
+			Err(err) => {
+				log::warn!("send 400 for job request. Reason: {err}");
+				error_400()
+			}
+		}
+	}
+
+	async fn status(State(jobs): State<JobManager>, UrlPath(id): UrlPath<u32>) -> Response<Body> {
+		match jobs.get(id) {
+			Some(job) => with_etag(ok_json(&job.as_json().stringify()), &job.etag()),
+			None => error_404(),
+		}
+	}
+
+	async fn cancel(
+		State(jobs): State<JobManager>,
+		Extension(Actor(actor)): Extension<Actor>,
+		UrlPath(id): UrlPath<u32>,
+		headers: HeaderMap,
+	) -> Response<Body> {
+		match jobs.get(id) {
+			Some(job) => {
+				let old_etag = job.etag();
+				if let Some(response) = check_if_match(&headers, &old_etag) {
+					return response;
+				}
+				job.cancel();
+				log::info!(target: "audit", "{actor} cancelled job {id}, ETag {old_etag} -> {}", job.etag());
+				with_etag(ok_json(&job.as_json().stringify()), &job.etag())
+			}
+			None => error_404(),
+		}
+	}
+
+	async fn resume(
+		State(jobs): State<JobManager>,
+		Extension(Actor(actor)): Extension<Actor>,
+		UrlPath(id): UrlPath<u32>,
+		headers: HeaderMap,
+	) -> Response<Body> {
+		match jobs.get(id) {
+			Some(job) => {
+				let old_etag = job.etag();
+				if let Some(response) = check_if_match(&headers, &old_etag) {
+					return response;
+				}
+				match jobs.resume(&job) {
+					Ok(()) => {
+						log::info!(target: "audit", "{actor} resumed job {id}, ETag {old_etag} -> {}", job.etag());
+						with_etag(ok_json(&job.as_json().stringify()), &job.etag())
+					}
+					Err(err) => {
+						log::warn!("send 409 for job request. Reason: {err}");
+						Response::builder()
+							.status(409)
+							.body(Body::from("Conflict"))
+							.expect("should have build a body")
+					}
+				}
+			}
+			None => error_404(),
+		}
+	}
+
+	async fn events(State(jobs): State<JobManager>, UrlPath(id): UrlPath<u32>) -> Response<Body> {
+		match jobs.get(id) {
+			Some(job) => Response::builder()
+				.status(200)
+				.header(CONTENT_TYPE, "application/x-ndjson")
+				.header(CACHE_CONTROL, "no-cache")
+				.body(Body::from_stream(job.event_stream()))
+				.expect("should have build a body"),
+			None => error_404(),
+		}
+	}
+
+	Router::new()
+		.route("/api/jobs", get(list).post(start))
+		.route("/api/jobs/{id}", get(status).delete(cancel))
+		.route("/api/jobs/{id}/resume", post(resume))
+		.route("/api/jobs/{id}/events", get(events))
+		.with_state(jobs)
+}
+
+#[derive(Clone)]
+struct ConfigState {
+	config: String,
+	/// the ETag of the configuration this router was built from
+	etag: String,
+	reload: mpsc::Sender<ReloadRequest>,
+}
+
+fn config_app(state: ConfigState) -> Router {
+	async fn get_config(State(state): State<ConfigState>) -> Response<Body> {
+		with_etag(ok_json(&state.config), &state.etag)
+	}
+
+	async fn reload(
+		State(state): State<ConfigState>,
+		Extension(Actor(actor)): Extension<Actor>,
+		headers: HeaderMap,
+	) -> Response<Body> {
+		if let Some(response) = check_if_match(&headers, &state.etag) {
+			return response;
+		}
+		let if_match = headers
+			.get(IF_MATCH)
+			.and_then(|value| value.to_str().ok())
+			.unwrap_or_default();
+		let (tx, rx) = tokio::sync::oneshot::channel();
+		let request = ReloadRequest {
+			if_match: if_match.to_owned(),
+			actor,
+			response: tx,
+		};
+		let outcome = match state.reload.send(request).await {
+			Ok(()) => rx.await.ok(),
+			Err(_) => None,
+		};
+		match outcome {
+			Some(ReloadOutcome::Reloaded(etag)) => Response::builder()
+				.status(204)
+				.header(ETAG, etag)
+				.body(Body::empty())
+				.expect("should have build a body"),
+			Some(ReloadOutcome::Outdated(etag)) => Response::builder()
+				.status(412)
+				.header(ETAG, etag)
+				.body(Body::from("Precondition Failed: the resource has changed"))
+				.expect("should have build a body"),
+			Some(ReloadOutcome::Failed(err)) => {
+				log::warn!("send 500 for config reload. Reason: {err}");
+				Response::builder()
+					.status(500)
+					.body(Body::from(format!("Internal Server Error: {err}")))
+					.expect("should have build a body")
+			}
+			None => {
+				log::warn!("send 503 for config reload, because nobody handles reloads");
+				Response::builder()
+					.status(503)
+					.body(Body::from("Service Unavailable"))
+					.expect("should have build a body")
+			}
+		}
+	}
+
+	Router::new()
+		.route("/api/config", get(get_config))
+		.route("/api/config/reload", post(reload))
+		.with_state(state)
+}
+
+fn error_400() -> Response<Body> {
+	Response::builder()
+		.status(400)
+		.body(Body::from("Bad Request"))
+		.expect("should have build a body")
+}
+
+/// Returns an error response, if the `If-Match` header is missing (428) or doesn't match the current `etag` (412).
+fn check_if_match(headers: &HeaderMap, etag: &str) -> Option<Response<Body>> {
+	let (status, body) = match headers.get(IF_MATCH).and_then(|value| value.to_str().ok()) {
+		None => (
+			428,
+			"Precondition Required: send the ETag of the resource as If-Match header",
+		),
+		Some(value) => {
+			if if_match_matches(value, etag) {
+				return None;
+			}
+			(412, "Precondition Failed: the resource has changed")
+		}
+	};
+	log::warn!(
+		"send {status} for admin request with If-Match {:?}",
+		headers.get(IF_MATCH)
+	);
+	Some(
+		Response::builder()
+			.status(status)
+			.header(ETAG, etag)
+			.body(Body::from(body))
+			.expect("should have build a body"),
+	)
+}
+
+/// Returns whether the value of an `If-Match` header matches `etag`.
+fn if_match_matches(value: &str, etag: &str) -> bool {
+	value.split(',').map(str::trim).any(|tag| tag == "*" || tag == etag)
+}
+
+fn with_etag(mut response: Response<Body>, etag: &str) -> Response<Body> {
+	response
+		.headers_mut()
+		.insert(ETAG, etag.parse().expect("should have parsed the ETag"));
+	response
+}
+
+/// Response for blank tiles, so clients don't have to decode and draw them.
+fn no_content() -> Response<Body> {
+	Response::builder()
+		.status(204)
+		.header(CACHE_CONTROL, "public, max-age=2419200, no-transform")
+		.body(Body::empty())
+		.expect("should have build a body")
+}
+
+fn error_404() -> Response<Body> {
+	Response::builder()
+		.status(404)
+		.body(Body::from("Not Found"))
+		.expect("should have build a body")
+}
+
+/// Strong ETag of a response, derived from the source data and the content encoding that is sent.
+fn get_etag(blob: &Blob, compression: &TileCompression) -> String {
+	let hash = Sha256::digest(blob.as_slice());
+	let hex: String = hash[..16].iter().map(|byte| format!("{byte:02x}")).collect();
+	match compression {
+		TileCompression::Uncompressed => format!("\"{hex}\""),
+		TileCompression::Gzip => format!("\"{hex}-gzip\""),
+		TileCompression::Brotli => format!("\"{hex}-br\""),
+		TileCompression::Zstd => format!("\"{hex}-zstd\""),
+	}
+}
+
+/// Evaluates `If-None-Match` and, only if that is missing, `If-Modified-Since`.
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: SystemTime) -> bool {
+	if let Some(value) = headers.get(IF_NONE_MATCH) {
+		let value = value.to_str().unwrap_or("");
+		return value
+			.split(',')
+			.map(|tag| tag.trim().trim_start_matches("W/"))
+			.any(|tag| tag == "*" || tag == etag);
+	}
+
+	match headers
+		.get(IF_MODIFIED_SINCE)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| httpdate::parse_http_date(value).ok())
+	{
+		// HTTP dates have a resolution of seconds
+		Some(since) => httpdate::HttpDate::from(last_modified) <= httpdate::HttpDate::from(since),
+		None => false,
+	}
+}
+
+fn with_validators(mut response: Response<Body>, etag: &str, last_modified: SystemTime) -> Response<Body> {
+	let headers = response.headers_mut();
+	headers.insert(ETAG, etag.parse().expect("should have parsed the ETag"));
+	headers.insert(
+		LAST_MODIFIED,
+		httpdate::fmt_http_date(last_modified)
+			.parse()
+			.expect("should have parsed the date"),
+	);
+	response
+}
+
+fn not_modified(etag: &str, last_modified: SystemTime) -> Response<Body> {
+	let response = Response::builder()
+		.status(304)
+		.header(CACHE_CONTROL, "public, max-age=2419200, no-transform")
+		.header(VARY, "accept-encoding")
+		.body(Body::empty())
+		.expect("should have build a body");
+	with_validators(response, etag, last_modified)
+}
+
+/// Selects the content encoding of a response. Images are never compressed again.
+fn response_compression(result: &SourceResponse, target_compressions: &mut TargetCompression) -> TileCompression {
+	if matches!(
+		result.mime.as_str(),
+		"image/png" | "image/jpeg" | "image/webp" | "image/avif" | "image/jxl"
+	) {
+		target_compressions.set_incompressible();
+	}
+
+	select_compression(&result.compression, target_compressions).expect("should have selected a compression")
+}
+
+fn ok_data(
+	result: SourceResponse,
+	mut target_compressions: TargetCompression,
+	trace: &mut RequestTrace,
+) -> Response<Body> {
+	let compression = response_compression(&result, &mut target_compressions);
+
+	log::trace!(
+		"optimize_compression from \"{}\" to {:?}",
+		result.compression,
+		target_compressions
+	);
+	let blob = encode_blob(result.blob, &result.compression, &compression, trace);
+
+	ok_encoded(SourceResponse {
+		blob,
+		compression,
+		mime: result.mime,
+	})
+}
+
+/// Sends `result` as it is, using its compression as content encoding.
+fn ok_encoded(result: SourceResponse) -> Response<Body> {
+	let mut response = Response::builder()
+		.status(200)
+		.header(CONTENT_TYPE, result.mime)
+		.header(CACHE_CONTROL, "public, max-age=2419200, no-transform")
+		.header(VARY, "accept-encoding")
+		.header(ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+
+	use TileCompression::*;
+	match result.compression {
+		Uncompressed => {}
+		Gzip => response = response.header(CONTENT_ENCODING, "gzip"),
+		Brotli => response = response.header(CONTENT_ENCODING, "br"),
+		Zstd => response = response.header(CONTENT_ENCODING, "zstd"),
+	}
+
+	log::trace!("send repsonse using headers: {:?}", response.headers_ref());
+
+	response
+		.body(Body::from(result.blob.into_vec()))
+		.expect("should have build a body")
+}
+
+/// Recompresses `blob` from `compression` to the content encoding `target`.
+fn encode_blob(blob: Blob, compression: &TileCompression, target: &TileCompression, trace: &mut RequestTrace) -> Blob {
+	if compression == target {
+		return blob;
+	}
+	let blob = decompress(blob, compression).expect("should have decompressed");
+	trace.step("decompress");
+	let blob = compress(blob, target).expect("should have compressed");
+	trace.step("recompress");
+	blob
+}
+
+fn ok_json(message: &str) -> Response<Body> {
+	ok_data(
+		SourceResponse {
+			blob: Blob::from(message),
+			compression: TileCompression::Uncompressed,
+			mime: String::from("application/json"),
+		},
+		TargetCompression::from_none(),
+		&mut RequestTrace::default(),
+	)
+}
+
+/// Returns the origin of the server as seen by the client, e.g. "http://localhost:8080".
+fn get_origin(headers: &HeaderMap) -> String {
+	let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+	match header("host") {
+		Some(host) => format!("{}://{host}", header("x-forwarded-proto").unwrap_or("http")),
+		None => String::new(),
+	}
+}
+
+/// Parses the header "Accept-Encoding" into the content encodings the client accepts.
+///
+/// Only the encodings with the highest weight "q" are accepted, so that the server chooses between them by its
+/// own preference. Encodings with "q=0" are refused, "*" stands for all encodings that are not listed.
+/// Uncompressed responses are always allowed, since every response can be sent without content encoding, and
+/// are the only choice if "identity" has a higher weight than all other encodings. Encodings the server can't
+/// produce, like "deflate", are ignored.
+fn get_encoding(headers: HeaderMap) -> TargetCompression {
+	let mut encoding_set: TargetCompression = TargetCompression::from_none();
+	let Some(header) = headers.get(ACCEPT_ENCODING).and_then(|value| value.to_str().ok()) else {
+		return encoding_set;
+	};
+
+	// pairs of encoding and weight
+	let entries: Vec<(String, f32)> = header
+		.split(',')
+		.filter_map(|entry| {
+			let mut parts = entry.split(';').map(str::trim);
+			let name = parts.next()?.to_ascii_lowercase();
+			if name.is_empty() {
+				return None;
+			}
+			// an invalid weight refuses the encoding, so that the client gets a response it understands
+			let weight = parts
+				.find_map(|param| param.strip_prefix("q=").or_else(|| param.strip_prefix("Q=")))
+				.map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+				.unwrap_or(0.0);
+			Some((name, weight))
+		})
+		.collect();
+	let find = |name: &str| entries.iter().find(|(n, _)| n == name).map(|(_, weight)| *weight);
+	let weight = |names: &[&str]| -> f32 {
+		names
+			.iter()
+			.find_map(|name| find(name))
+			.or_else(|| find("*"))
+			.unwrap_or(0.0)
+	};
+
+	let weights = [
+		(TileCompression::Gzip, weight(&["gzip", "x-gzip"])),
+		(TileCompression::Brotli, weight(&["br"])),
+		(TileCompression::Zstd, weight(&["zstd"])),
+	];
+	let best = weights
+		.iter()
+		.map(|(_, weight)| *weight)
+		.fold(find("identity").unwrap_or(0.0), f32::max);
+	for (compression, weight) in weights {
+		if weight > 0.0 && weight == best {
+			encoding_set.insert(compression);
+		}
+	}
+	encoding_set
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::server::{Auth, Cors};
+	use axum::http::{header::ACCEPT_ENCODING, HeaderMap};
+	use enumset::{enum_set, EnumSet};
+	use versatiles_container::{MockTilesReader, MockTilesReaderProfile};
+	use versatiles_core::types::TileCompression::*;
+	use versatiles_core::types::TileScheme;
+
+	const IP: &str = "127.0.0.1";
+
+	#[test]
+	fn test_get_encoding() {
+		let test = |encoding: &str, comp0: EnumSet<TileCompression>| {
+			let mut map = HeaderMap::new();
+			if encoding != "NONE" {
+				map.insert(ACCEPT_ENCODING, encoding.parse().unwrap());
+			}
+			let comp0 = TargetCompression::from_set(comp0);
+			let comp = get_encoding(map);
+			assert_eq!(comp, comp0);
+		};
+
+		test("NONE", enum_set!(Uncompressed));
+		test("", enum_set!(Uncompressed));
+		test("*", enum_set!(Uncompressed | Brotli | Gzip | Zstd));
+		test("br", enum_set!(Uncompressed | Brotli));
+		test("br;q=1.0, gzip;q=0.8, *;q=0.1", enum_set!(Uncompressed | Brotli));
+		test("compress", enum_set!(Uncompressed));
+		test("compress, gzip", enum_set!(Uncompressed | Gzip));
+		test("compress;q=0.5, gzip;q=1.0", enum_set!(Uncompressed | Gzip));
+		test("deflate", enum_set!(Uncompressed));
+		test("deflate, gzip;q=1.0, *;q=0.5", enum_set!(Uncompressed | Gzip));
+		test("gzip", enum_set!(Uncompressed | Gzip));
+		test("gzip, compress, br", enum_set!(Uncompressed | Brotli | Gzip));
+		test(
+			"gzip, deflate, br;q=1.0, identity;q=0.5, *;q=0.25",
+			enum_set!(Uncompressed | Brotli | Gzip),
+		);
+		test("gzip;q=1.0, identity; q=0.5, *;q=0", enum_set!(Uncompressed | Gzip));
+		test("identity", enum_set!(Uncompressed));
+
+		// the encodings with the highest weight win, the server decides between equal weights
+		test("gzip;q=1, br;q=0.1", enum_set!(Uncompressed | Gzip));
+		test(
+			"br;q=0.5, gzip;q=0.9, zstd;q=0.9",
+			enum_set!(Uncompressed | Gzip | Zstd),
+		);
+		test("*;q=0.2, zstd;q=0.3", enum_set!(Uncompressed | Zstd));
+		test("identity;q=1, gzip;q=0.5", enum_set!(Uncompressed));
+		test("identity, gzip", enum_set!(Uncompressed | Gzip));
+
+		// refused encodings
+		test("br;q=0, gzip", enum_set!(Uncompressed | Gzip));
+		test("gzip;q=0.000, br;q=0.001", enum_set!(Uncompressed | Brotli));
+		test("*;q=0.5, br;q=0", enum_set!(Uncompressed | Gzip | Zstd));
+		test("gzip, br, zstd;q=0", enum_set!(Uncompressed | Brotli | Gzip));
+		test("gzip;q=abc", enum_set!(Uncompressed));
+
+		// names are case insensitive and must match completely
+		test("GZIP, Br", enum_set!(Uncompressed | Brotli | Gzip));
+		test("x-gzip", enum_set!(Uncompressed | Gzip));
+		test("brotli, gzip2", enum_set!(Uncompressed));
+		test("zstd", enum_set!(Uncompressed | Zstd));
+		test("ZSTD, gzip", enum_set!(Uncompressed | Gzip | Zstd));
+		test("zst", enum_set!(Uncompressed));
+	}
+
+	#[tokio::test]
+	async fn server() {
+		async fn get(path: &str) -> String {
+			reqwest::get(format!("http://{IP}:50001/{path}"))
+				.await
+				.expect("should have made a get request")
+				.text()
+				.await
+				.expect("should have returned text")
+		}
+
+		let mut server = TileServer::new(IP, 50001, true, true);
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)
+			.unwrap()
+			.boxed();
+		server
+			.add_tile_source("cheese", reader, TileSourceOptions::default())
+			.unwrap();
+
+		server.start().await.unwrap();
+		let before = versatiles_core::metrics::metrics();
+
+		assert_eq!(get("tiles/cheese/brum.json").await, "Not Found");
+
+		let meta = "{\"bounds\":[-180,-79.17133464081944,45,66.51326044311185],\"format\":\"pbf\",\"maxzoom\":3,\"minzoom\":2,\"name\":\"cheese\",\"tilejson\":\"3.0.0\",\"tiles\":[\"/tiles/cheese/{z}/{x}/{y}\"],\"type\":\"vector\"}";
+		assert_eq!(get("tiles/cheese/meta.json").await, meta);
+		assert_eq!(get("tiles/cheese/tiles.json").await, meta);
+		assert!(get("tiles/cheese/0/0/0.png").await.starts_with("\u{1a}4\n\u{5}ocean"));
+		assert_eq!(get("tiles/index.json").await, "[\"cheese\"]");
+		assert_eq!(get("status").await, "ready!");
+
+		let after = versatiles_core::metrics::metrics();
+		assert!(after.server_requests >= before.server_requests + 4);
+		assert!(after.server_not_found > before.server_not_found);
+
+		server.stop().await;
+	}
+
+	#[tokio::test]
+	async fn server_reload_sources() -> Result<()> {
+		async fn get(path: &str) -> Result<(u16, String)> {
+			let response = reqwest::get(format!("http://{IP}:50011/{path}")).await?;
+			Ok((response.status().as_u16(), response.text().await?))
+		}
+
+		let mut server = TileServer::new(IP, 50011, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		assert_eq!(get("tiles/cheese/3/1/2").await?, (200, String::from("{x:1,y:2,z:3}")));
+		assert_eq!(get("tiles/index.json").await?.1, "[\"cheese\"]");
+
+		let mut other = TileServer::new(IP, 50011, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		other.add_tile_source("bread", reader, TileSourceOptions::default())?;
+		server.reload_sources(other, "*", "test").await?;
+
+		assert_eq!(get("tiles/cheese/3/1/2").await?.0, 404);
+		assert_eq!(get("tiles/bread/3/1/2").await?, (200, String::from("{x:1,y:2,z:3}")));
+		assert_eq!(get("tiles/index.json").await?.1, "[\"bread\"]");
+		assert_eq!(get("status").await?.1, "ready!");
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_config_api() -> Result<()> {
+		async fn reload(key: Option<&str>, if_match: Option<&str>) -> Result<(u16, String)> {
+			let mut request = reqwest::Client::new().post(format!("http://{IP}:50020/api/config/reload"));
+			if let Some(key) = key {
+				request = request.header("X-API-Key", key);
+			}
+			if let Some(if_match) = if_match {
+				request = request.header("If-Match", if_match);
+			}
+			let response = request.send().await?;
+			let etag = response.headers().get("etag").map(|v| v.to_str().unwrap().to_owned());
+			Ok((response.status().as_u16(), etag.unwrap_or_default()))
+		}
+
+		fn new_server(id: &str) -> Result<TileServer> {
+			let mut server = TileServer::new(IP, 50020, true, true);
+			let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+			server.add_tile_source(id, reader, TileSourceOptions::default())?;
+			Ok(server)
+		}
+
+		let mut server = new_server("cheese")?;
+		assert!(server.set_config_api(Auth::default()).is_err());
+		let mut requests = server.set_config_api(Auth::new(&[String::from("admin-key")], None)?)?;
+		server.start().await?;
+		let etag = server.config_etag().await;
+
+		let response = reqwest::Client::new()
+			.get(format!("http://{IP}:50020/api/config"))
+			.header("X-API-Key", "admin-key")
+			.send()
+			.await?;
+		assert_eq!(response.status(), 200);
+		assert_eq!(response.headers().get("etag").unwrap().to_str()?, etag);
+		assert_eq!(
+			response.text().await?,
+			"{\"static_sources\":[],\"tile_sources\":[{\"id\":\"cheese\",\"prefix\":\"/tiles/cheese/\",\"source\":\"dummy_name\"}]}"
+		);
+
+		// reloads require a key and the current ETag
+		assert_eq!(reload(None, Some(&etag)).await?.0, 401);
+		assert_eq!(reload(Some("admin-key"), None).await?, (428, etag.clone()));
+		assert_eq!(
+			reload(Some("admin-key"), Some("\"outdated\"")).await?,
+			(412, etag.clone())
+		);
+
+		let client = tokio::spawn({
+			let etag = etag.clone();
+			async move { reload(Some("admin-key"), Some(&etag)).await }
+		});
+		let request = requests.recv().await.unwrap();
+		assert_eq!(request.if_match, etag);
+		assert_eq!(request.actor, "API key 1");
+		let outcome = server
+			.reload_sources(new_server("bread")?, &request.if_match, &request.actor)
+			.await?;
+		let new_etag = server.config_etag().await;
+		assert_ne!(new_etag, etag);
+		assert_eq!(outcome, ReloadOutcome::Reloaded(new_etag.clone()));
+		request.response.send(outcome).unwrap();
+		assert_eq!(client.await??, (204, new_etag.clone()));
+
+		// a reload based on the old configuration is refused
+		let outcome = server.reload_sources(new_server("cheese")?, &etag, "test").await?;
+		assert_eq!(outcome, ReloadOutcome::Outdated(new_etag.clone()));
+		assert_eq!(server.config_etag().await, new_etag);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_conditional_requests() -> Result<()> {
+		let mut server = TileServer::new(IP, 50012, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |headers: &[(&str, &str)]| {
+			let mut request = reqwest::Client::new().get(format!("http://{IP}:50012/tiles/cheese/3/1/2"));
+			for (key, value) in headers {
+				request = request.header(*key, *value);
+			}
+			request.send()
+		};
+		let header =
+			|response: &reqwest::Response, key: &str| response.headers().get(key).unwrap().to_str().unwrap().to_string();
+
+		let response = get(&[]).await?;
+		assert_eq!(response.status(), 200);
+		let etag = header(&response, "etag");
+		let last_modified = header(&response, "last-modified");
+		assert!(etag.starts_with('"') && etag.ends_with('"'), "{etag}");
+
+		// the ETag depends on the content encoding
+		let response = get(&[("accept-encoding", "br")]).await?;
+		assert_eq!(
+			header(&response, "etag"),
+			format!("\"{}-br\"", &etag[1..etag.len() - 1])
+		);
+
+		let response = get(&[("if-none-match", &etag)]).await?;
+		assert_eq!(response.status(), 304);
+		assert_eq!(header(&response, "etag"), etag);
+		assert_eq!(response.text().await?, "");
+
+		let response = get(&[("if-none-match", "\"other\", W/\"foo\"")]).await?;
+		assert_eq!(response.status(), 200);
+
+		let response = get(&[("if-modified-since", &last_modified)]).await?;
+		assert_eq!(response.status(), 304);
+
+		let response = get(&[("if-modified-since", "Mon, 01 Jan 2001 00:00:00 GMT")]).await?;
+		assert_eq!(response.status(), 200);
+
+		// If-None-Match takes precedence over If-Modified-Since
+		let response = get(&[("if-none-match", "\"other\""), ("if-modified-since", &last_modified)]).await?;
+		assert_eq!(response.status(), 200);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_with_options() -> Result<()> {
+		let mut server = TileServer::new(IP, 50006, true, true);
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		let options = TileSourceOptions {
+			scheme: TileScheme::Tms,
+			cors: Cors::new(&[String::from("https://*.example.org")])?,
+			..Default::default()
+		};
+		server.add_tile_source("cheese", reader, options)?;
+		server.start().await?;
+
+		let get = |path: &str, origin: &str| {
+			reqwest::Client::new()
+				.get(format!("http://{IP}:50006/tiles/cheese/{path}"))
+				.header("origin", origin)
+				.send()
+		};
+
+		// TMS rows are counted from the south
+		let response = get("3/1/2", "https://www.example.org").await?;
+		assert_eq!(
+			response.headers().get("access-control-allow-origin").unwrap(),
+			"https://www.example.org"
+		);
+		assert_eq!(response.text().await?, "{x:1,y:5,z:3}");
+
+		let response = get("meta.json", "https://example.com").await?;
+		assert!(response.headers().get("access-control-allow-origin").is_none());
+		assert!(response.text().await?.contains("\"scheme\":\"tms\""));
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_tile_info() -> Result<()> {
+		let mut server = TileServer::new(IP, 50017, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |path: &str| {
+			let url = format!("http://{IP}:50017/api/source/{path}");
+			async move {
+				let response = reqwest::get(url).await?;
+				Ok::<_, anyhow::Error>((response.status().as_u16(), response.text().await?))
+			}
+		};
+
+		// the mock JSON tiles are not valid JSON
+		let (status, json) = get("cheese/tile/3/1/2/info").await?;
+		assert_eq!(status, 200);
+		assert!(
+			json.starts_with("{\"compression\":\"none\",\"content_type\":\"application/json\",\"decode\":\"error: "),
+			"{json}"
+		);
+		assert!(
+			json.ends_with("\"exists\":true,\"size\":13,\"x\":1,\"y\":2,\"z\":3}"),
+			"{json}"
+		);
+
+		assert_eq!(get("cheese/tile/3/8/2/info").await?.0, 400);
+		assert_eq!(get("cheese/tile/3/x/2/info").await?.0, 400);
+		assert_eq!(get("bread/tile/3/1/2/info").await?.0, 404);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[cfg(feature = "prometheus")]
+	#[tokio::test]
+	async fn server_metrics() -> Result<()> {
+		let mut server = TileServer::new(IP, 50015, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |path: &str| reqwest::get(format!("http://{IP}:50015/{path}"));
+		get("tiles/cheese/3/1/2").await?;
+		get("tiles/cheese/3/1/2").await?;
+		get("tiles/cheese/brum.json").await?;
+
+		let response = get("metrics").await?;
+		assert_eq!(
+			response.headers().get("content-type").unwrap(),
+			"text/plain; version=0.0.4"
+		);
+		let text = response.text().await?;
+		let lines: Vec<&str> = text.lines().collect();
+		for line in [
+			"versatiles_tile_requests_total{source=\"cheese\",status=\"200\"} 2",
+			"versatiles_tile_requests_total{source=\"cheese\",status=\"404\"} 1",
+			"versatiles_tile_request_duration_seconds_count{source=\"cheese\"} 3",
+			"versatiles_tile_sent_bytes_total{source=\"cheese\"} 35",
+			"versatiles_tile_requests_in_flight 0",
+		] {
+			assert!(lines.contains(&line), "missing {line:?} in:\n{text}");
+		}
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_auth() -> Result<()> {
+		let mut server = TileServer::new(IP, 50016, true, true);
+		let auth = Auth::new(&[String::from("alice")], Some("0123456789abcdef"))?;
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		let options = TileSourceOptions {
+			auth: auth.clone(),
+			..Default::default()
+		};
+		server.add_tile_source("cheese", reader, options)?;
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("bread", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |path: String, key: Option<&'static str>| {
+			let mut request = reqwest::Client::new().get(format!("http://{IP}:50016{path}"));
+			if let Some(key) = key {
+				request = request.header("x-api-key", key);
+			}
+			request.send()
+		};
+		let status = |path: &str, key: Option<&'static str>| {
+			let path = path.to_string();
+			async move { Ok::<u16, reqwest::Error>(get(path, key).await?.status().as_u16()) }
+		};
+
+		assert_eq!(status("/tiles/cheese/3/1/2", None).await?, 401);
+		assert_eq!(status("/tiles/cheese/meta.json", None).await?, 401);
+		assert_eq!(status("/tiles/cheese/3/1/2", Some("bob")).await?, 403);
+		assert_eq!(status("/tiles/cheese/3/1/2", Some("alice")).await?, 200);
+		assert_eq!(status("/tiles/cheese/3/1/2?key=alice", None).await?, 200);
+		assert_eq!(status("/tiles/bread/3/1/2", None).await?, 200);
+
+		// protected tiles must not be stored by shared caches, and browsers must be able to read rejections
+		let response = get(String::from("/tiles/cheese/3/1/2"), Some("alice")).await?;
+		let headers = response.headers();
+		assert_eq!(headers["cache-control"], "private, max-age=2419200, no-transform");
+		assert!(headers.get_all("vary").iter().any(|value| value == "x-api-key"));
+		let response = get(String::from("/tiles/bread/3/1/2"), None).await?;
+		assert_eq!(
+			response.headers()["cache-control"],
+			"public, max-age=2419200, no-transform"
+		);
+		let response = get(String::from("/tiles/cheese/3/1/2"), None).await?;
+		assert_eq!(response.headers()["access-control-allow-origin"], "*");
+
+		let expires = SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs() + 60;
+		let query = auth.sign("/tiles/cheese/3/1/2", expires).unwrap();
+		let response = get(format!("/tiles/cheese/3/1/2?{query}"), None).await?;
+		assert_eq!(response.text().await?, "{x:1,y:2,z:3}");
+		assert_eq!(status(&format!("/tiles/cheese/3/1/3?{query}"), None).await?, 403);
+
+		#[cfg(feature = "inspect")]
+		assert_eq!(status("/inspect/cheese", None).await?, 404);
+
+		// protected sources are not listed
+		let index = get(String::from("/tiles/index.json"), None).await?.text().await?;
+		assert_eq!(index, "[\"bread\"]");
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_content_negotiation() -> Result<()> {
+		let mut server = TileServer::new(IP, 50018, true, true);
+		let minimal = Some(true);
+		for (id, minimal_recompression) in [("best", None), ("minimal", minimal)] {
+			let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?.boxed();
+			let options = TileSourceOptions {
+				minimal_recompression,
+				..Default::default()
+			};
+			server.add_tile_source(id, reader, options)?;
+		}
+		let static_path = Path::new("../testdata/static.zip");
+		server.add_static_source(static_path, Url::new("/best"))?;
+		let options = StaticSourceOptions {
+			minimal_recompression: minimal,
+		};
+		server.add_static_source_with_options(static_path, Url::new("/minimal"), options)?;
+		server.start().await?;
+
+		async fn encoding(path: &str, accept: &str) -> String {
+			let client = reqwest::Client::builder().no_gzip().no_brotli().build().unwrap();
+			let response = client
+				.get(format!("http://{IP}:50018/{path}"))
+				.header("accept-encoding", accept)
+				.send()
+				.await
+				.unwrap();
+			assert_eq!(response.status(), 200, "{path}");
+			let header = response.headers().get("content-encoding");
+			header.map_or(String::from("identity"), |h| h.to_str().unwrap().to_string())
+		}
+
+		// the mock tiles are stored with gzip
+		assert_eq!(encoding("tiles/best/3/1/2", "gzip, br").await, "br");
+		assert_eq!(encoding("tiles/minimal/3/1/2", "gzip, br").await, "gzip");
+		assert_eq!(encoding("tiles/best/3/1/2", "gzip, br;q=0").await, "gzip");
+		assert_eq!(encoding("tiles/minimal/3/1/2", "br").await, "br");
+		assert_eq!(encoding("tiles/best/3/1/2", "identity").await, "identity");
+		assert_eq!(encoding("tiles/minimal/3/1/2", "*;q=0").await, "identity");
+		assert_eq!(encoding("tiles/best/3/1/2", "gzip, zstd").await, "zstd");
+		assert_eq!(encoding("tiles/minimal/3/1/2", "zstd").await, "zstd");
+		assert_eq!(encoding("tiles/best/3/1/2", "gzip, br, zstd").await, "br");
+		assert_eq!(encoding("tiles/best/3/1/2", "gzip;q=1, br;q=0.1").await, "gzip");
+		assert_eq!(encoding("tiles/minimal/3/1/2", "gzip;q=0.5, br").await, "br");
+
+		// "index.html" is stored uncompressed, "assets/app.js" is also stored with gzip
+		assert_eq!(encoding("best/index.html", "br").await, "br");
+		assert_eq!(encoding("minimal/index.html", "br").await, "identity");
+		assert_eq!(encoding("minimal/assets/app.js", "*").await, "gzip");
+		assert_eq!(encoding("best/assets/app.js", "zstd").await, "zstd");
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_no_content_for_blank_tiles() -> Result<()> {
+		use image::{DynamicImage, RgbaImage};
+		use versatiles_container::DirectoryTilesReader;
+		use versatiles_core::types::TileFormat;
+		use versatiles_image::helper::{create_image_rgb, image2blob};
+
+		let dir = assert_fs::TempDir::new()?;
+		let blank = DynamicImage::ImageRgba8(RgbaImage::new(256, 256));
+		for (path, image) in [("0/0/0.png", &blank), ("1/0/0.png", &create_image_rgb())] {
+			let path = dir.path().join(path);
+			std::fs::create_dir_all(path.parent().unwrap())?;
+			std::fs::write(path, image2blob(image, TileFormat::PNG)?.as_slice())?;
+		}
+
+		let mut server = TileServer::new(IP, 50019, true, true);
+		for (id, no_content_for_blank_tiles) in [("plain", false), ("elided", true)] {
+			let reader = DirectoryTilesReader::open_path(dir.path())?.boxed();
+			let options = TileSourceOptions {
+				no_content_for_blank_tiles,
+				..Default::default()
+			};
+			server.add_tile_source(id, reader, options)?;
+		}
+		server.start().await?;
+
+		async fn get(path: &str) -> (u16, usize) {
+			let response = reqwest::get(format!("http://{IP}:50019/{path}")).await.unwrap();
+			(response.status().as_u16(), response.bytes().await.unwrap().len())
+		}
+
+		assert_eq!(get("tiles/plain/0/0/0").await.0, 200);
+		assert_eq!(get("tiles/elided/0/0/0").await, (204, 0));
+		assert_eq!(get("tiles/elided/1/0/0").await.0, 200);
+		assert_eq!(get("tiles/elided/1/1/1").await.0, 404);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_tile_cache() -> Result<()> {
+		let mut server = TileServer::new(IP, 50014, true, true);
+		server.set_tile_cache(Some(TileCacheOptions {
+			max_bytes: 1024 * 1024,
+			ttl: None,
+		}));
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |path: &str, encoding: &str| {
+			reqwest::Client::new()
+				.get(format!("http://{IP}:50014/{path}"))
+				.header("accept-encoding", encoding)
+				.header("x-versatiles-trace", "1")
+				.send()
+		};
+		let steps = |response: &reqwest::Response| -> Vec<String> {
+			let timing = response.headers().get("server-timing").unwrap().to_str().unwrap();
+			timing
+				.split(", ")
+				.map(|s| s.split(';').next().unwrap().to_string())
+				.collect()
+		};
+
+		let response = get("tiles/cheese/3/1/2", "br").await?;
+		assert_eq!(
+			steps(&response),
+			["lookup", "read", "decompress", "recompress", "total"]
+		);
+		let etag = response.headers().get("etag").unwrap().clone();
+
+		let response = get("tiles/cheese/3/1/2", "br").await?;
+		assert_eq!(steps(&response), ["cache", "total"]);
+		assert_eq!(response.headers().get("content-encoding").unwrap(), "br");
+		assert_eq!(response.headers().get("etag").unwrap(), etag);
+
+		// other encodings are cached separately
+		let response = get("tiles/cheese/3/1/2", "").await?;
+		assert_eq!(steps(&response), ["lookup", "read", "total"]);
+		assert_eq!(response.text().await?, "{x:1,y:2,z:3}");
+
+		let status = get("api/status", "").await?.text().await?;
+		let status = JsonValue::parse_str(&status)?.to_object()?;
+		let cache = status.get("tile_cache").unwrap().as_object()?;
+		assert_eq!(cache.get("entries").unwrap().stringify(), "2");
+		assert_eq!(cache.get("hits").unwrap().stringify(), "1");
+		assert_eq!(cache.get("misses").unwrap().stringify(), "2");
+		assert!(status.get("server").unwrap().as_object()?.get("requests").is_some());
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_trace() -> Result<()> {
+		let mut server = TileServer::new(IP, 50007, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |trace: bool| {
+			let mut request = reqwest::Client::new()
+				.get(format!("http://{IP}:50007/tiles/cheese/3/1/2"))
+				.header("accept-encoding", "br");
+			if trace {
+				request = request.header("x-versatiles-trace", "1");
+			}
+			request.send()
+		};
+
+		let response = get(false).await?;
+		assert!(response.headers().get("server-timing").is_none());
+
+		let response = get(true).await?;
+		let timing = response.headers().get("server-timing").unwrap().to_str()?;
+		let steps: Vec<&str> = timing.split(", ").map(|s| s.split(';').next().unwrap()).collect();
+		assert_eq!(steps, ["lookup", "read", "decompress", "recompress", "total"]);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_style() -> Result<()> {
+		let mut server = TileServer::new(IP, 50008, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let response = reqwest::get(format!("http://{IP}:50008/tiles/cheese/style.json")).await?;
+		assert_eq!(response.headers().get("content-type").unwrap(), "application/json");
+		let style = response.text().await?;
+		assert!(style.contains("\"layers\":[]"), "{style}");
+		assert!(
+			style.contains("\"tiles\":[\"http://127.0.0.1:50008/tiles/cheese/{z}/{x}/{y}\"]"),
+			"{style}"
+		);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn server_jobs() -> Result<()> {
+		let root = assert_fs::TempDir::new()?;
+		std::fs::copy("../testdata/berlin.mbtiles", root.path().join("berlin.mbtiles"))?;
+		let mut server = TileServer::new(IP, 50009, true, true);
+		server.set_jobs(Some(JobsOptions::new(root.path(), &[String::from("admin-key")])?))?;
+		server.start().await?;
+
+		let url = |path: &str| format!("http://{IP}:50009/api/jobs{path}");
+		assert_eq!(reqwest::get(url("")).await?.status(), 401);
+		assert_eq!(reqwest::get(url("?key=wrong")).await?.status(), 403);
+
+		let mut headers = reqwest::header::HeaderMap::new();
+		headers.insert("x-api-key", "admin-key".parse()?);
+		let client = reqwest::Client::builder().default_headers(headers).build()?;
+
+		let etag = |response: &reqwest::Response| response.headers()["etag"].to_str().unwrap().to_string();
+		let list = client.get(url("")).send().await?;
+		let list_etag = etag(&list);
+
+		let response = client.post(url("")).body("{}").send().await?;
+		assert_eq!(response.status(), 428);
+		let response = client
+			.post(url(""))
+			.header("if-match", "\"old\"")
+			.body("{}")
+			.send()
+			.await?;
+		assert_eq!(response.status(), 412);
+		let post = |body: &str| {
+			client
+				.post(url(""))
+				.header("if-match", &list_etag)
+				.body(body.to_string())
+				.send()
+		};
+		assert_eq!(post("{}").await?.status(), 400);
+
+		// jobs can't access files outside of the jobs root
+		for body in [
+			"{\"input\":\"../testdata/berlin.mbtiles\",\"output\":\"berlin.pmtiles\"}",
+			"{\"input\":\"berlin.mbtiles\",\"output\":\"/tmp/berlin.pmtiles\"}",
+			"{\"input\":\"berlin.mbtiles\",\"output\":\".versatiles-jobs.json\"}",
+		] {
+			assert_eq!(post(body).await?.status(), 400, "{body}");
+		}
+
+		let body = "{\"input\":\"berlin.mbtiles\",\"output\":\"berlin.pmtiles\",\"max_zoom\":4}";
+		let response = post(body).await?;
+		assert!(etag(&response).starts_with("\"1-1-"));
+		assert!(response.text().await?.starts_with("{\"events\":"));
+
+		// the job list has changed
+		assert_eq!(post(body).await?.status(), 412);
+
+		// the event stream ends when the job has ended
+		let events = client.get(url("/1/events")).send().await?.text().await?;
+		assert!(
+			events.lines().last().unwrap().contains("\"event\":\"finished\""),
+			"{events}"
+		);
+		assert!(root.path().join("berlin.pmtiles").exists());
+
+		let job = client.get(url("/1")).send().await?.text().await?;
+		assert!(job.contains("\"status\":\"finished\""), "{job}");
+		assert_eq!(client.delete(url("/1")).send().await?.status(), 428);
+		let response = client
+			.delete(url("/1"))
+			.header("if-match", "\"1-1-running\"")
+			.send()
+			.await?;
+		assert_eq!(response.status(), 412);
+		assert_eq!(etag(&response), "\"1-1-finished\"");
+		let response = client
+			.delete(url("/1"))
+			.header("if-match", "\"1-1-finished\"")
+			.send()
+			.await?;
+		assert_eq!(response.status(), 200);
+		assert_eq!(client.delete(url("/2")).send().await?.status(), 404);
+		assert!(client.get(url("")).send().await?.text().await?.starts_with("[{"));
+
+		// finished jobs can't be resumed
+		let response = client.post(url("/1/resume")).header("if-match", "*").send().await?;
+		assert_eq!(response.status(), 409);
+		assert_eq!(client.post(url("/2/resume")).send().await?.status(), 404);
+
+		server.stop().await;
+
+		// jobs are kept across restarts of the server
+		let mut server = TileServer::new(IP, 50009, true, true);
+		server.set_jobs(Some(JobsOptions::new(root.path(), &[String::from("admin-key")])?))?;
+		server.start().await?;
+		let job = client.get(url("/1")).send().await?.text().await?;
+		assert!(job.contains("\"status\":\"finished\""), "{job}");
+		server.stop().await;
+		Ok(())
+	}
+
+	#[cfg(feature = "inspect")]
+	#[tokio::test]
+	async fn server_inspect() -> Result<()> {
+		let mut server = TileServer::new(IP, 50013, true, true);
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)?.boxed();
+		server.add_tile_source("cheese", reader, TileSourceOptions::default())?;
+		server.start().await?;
+
+		let get = |path: &str| {
+			let url = format!("http://{IP}:50013/inspect{path}");
+			async move {
+				let response = reqwest::get(url).await?;
+				Ok::<_, anyhow::Error>((response.status().as_u16(), response.text().await?))
+			}
+		};
+
+		let (status, html) = get("").await?;
+		assert_eq!(status, 200);
+		assert!(html.contains("<a href=\"/inspect/cheese\">cheese</a>"), "{html}");
+		assert!(get("/cheese").await?.1.contains("<a href=\"/inspect/cheese/3\">3</a>"));
+		assert!(get("/cheese/2")
+			.await?
+			.1
+			.contains("<a href=\"/inspect/cheese/2/0/1\">2/0/1</a>"));
+		assert!(get("/cheese/2/0/1").await?.1.contains("layer \"ocean\""));
+		assert_eq!(get("/cheese/9").await?.0, 404);
+		assert_eq!(get("/bread").await?.0, 404);
+
+		server.stop().await;
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_origin() {
+		let mut headers = HeaderMap::new();
+		assert_eq!(get_origin(&headers), "");
+		headers.insert("host", "localhost:8080".parse().unwrap());
+		assert_eq!(get_origin(&headers), "http://localhost:8080");
+		headers.insert("x-forwarded-proto", "https".parse().unwrap());
+		assert_eq!(get_origin(&headers), "https://localhost:8080");
+	}
+
+	#[tokio::test]
+	#[should_panic]
+	async fn same_prefix_twice() {
+		let mut server = TileServer::new(IP, 50002, true, true);
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)
+			.unwrap()
+			.boxed();
+		server
+			.add_tile_source("cheese", reader, TileSourceOptions::default())
+			.unwrap();
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)
+			.unwrap()
+			.boxed();
+		server
+			.add_tile_source("cheese", reader, TileSourceOptions::default())
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn tile_server_new() {
+		let mut server = TileServer::new(IP, 50003, true, true);
+		assert_eq!(server.ip, IP);
+		assert_eq!(server.port, 50003);
+		assert_eq!(server.tile_sources.len(), 0);
+		assert_eq!(server.static_sources.len(), 0);
+		assert!(server.exit_signal.is_none());
+
+		assert!(server.start().await.is_ok());
+
+		server.stop().await; // No assertion here as it's void
+	}
+
+	#[test]
+	fn tile_server_add_tile_source() {
+		let mut server = TileServer::new(IP, 50004, true, true);
+		assert_eq!(server.ip, IP);
+		assert_eq!(server.port, 50004);
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)
+			.unwrap()
+			.boxed();
+		server
+			.add_tile_source("cheese", reader, TileSourceOptions::default())
+			.unwrap();
+
+		assert_eq!(server.tile_sources.len(), 1);
+		assert_eq!(server.tile_sources[0].prefix.str, "/tiles/cheese/");
+	}
+
+	#[tokio::test]
+	async fn tile_server_iter_url_mapping() {
+		let mut server = TileServer::new(IP, 50005, true, true);
+		assert_eq!(server.ip, IP);
+		assert_eq!(server.port, 50005);
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Pbf)
+			.unwrap()
+			.boxed();
+		server
+			.add_tile_source("cheese", reader, TileSourceOptions::default())
+			.unwrap();
+
+		let mappings: Vec<(String, String)> = server.get_url_mapping().await;
+		assert_eq!(mappings.len(), 1);
+		assert_eq!(mappings[0].0, "/tiles/cheese/");
+		assert_eq!(mappings[0].1, "dummy_name");
+	}
+}
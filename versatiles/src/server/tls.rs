@@ -0,0 +1,117 @@
+//! HTTPS on the TCP listener of the server.
+//!
+//! Browsers only use HTTP/3 for origins they reached with HTTPS before, so the TCP listener uses the same
+//! certificate as the HTTP/3 listener. Behind a TLS terminating proxy, the proxy has to provide HTTP/3.
+
+use anyhow::{Context, Result};
+use axum::Router;
+use hyper_util::{
+	rt::{TokioExecutor, TokioIo},
+	server::conn::auto::Builder,
+	service::TowerToHyperService,
+};
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use std::{future::Future, path::PathBuf, sync::Arc};
+use tokio::{net::TcpListener, sync::watch};
+use tokio_rustls::TlsAcceptor;
+
+/// Certificate and private key of the server, used for HTTPS and HTTP/3.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsOptions {
+	/// PEM file with the certificate chain
+	pub cert_path: PathBuf,
+	/// PEM file with the private key
+	pub key_path: PathBuf,
+}
+
+impl TlsOptions {
+	/// Returns the TLS configuration, offering the protocols `alpn`, e.g. `h3` for HTTP/3.
+	pub fn server_config(&self, alpn: &[&[u8]]) -> Result<rustls::ServerConfig> {
+		let certs = CertificateDer::pem_file_iter(&self.cert_path)
+			.and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+			.with_context(|| format!("reading the certificates {:?}", self.cert_path))?;
+		let key = PrivateKeyDer::from_pem_file(&self.key_path)
+			.with_context(|| format!("reading the private key {:?}", self.key_path))?;
+
+		let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+			.with_protocol_versions(&[&rustls::version::TLS13])?
+			.with_no_client_auth()
+			.with_single_cert(certs, key)?;
+		config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+		Ok(config)
+	}
+
+	/// Returns the acceptor of TLS connections for HTTP/2 and HTTP/1.1.
+	pub fn acceptor(&self) -> Result<TlsAcceptor> {
+		Ok(TlsAcceptor::from(Arc::new(self.server_config(&[b"h2", b"http/1.1"])?)))
+	}
+}
+
+/// Answers HTTPS requests on `listener` with `router`, until `shutdown` resolves.
+/// Afterwards open connections finish their requests in flight, and then this function returns.
+pub async fn serve(listener: TcpListener, acceptor: TlsAcceptor, router: Router, shutdown: impl Future<Output = ()>) {
+	// every connection holds a receiver, so that the sender knows when all connections are closed
+	let (stop_sender, stop_receiver) = watch::channel(());
+	tokio::pin!(shutdown);
+
+	loop {
+		let (stream, addr) = tokio::select! {
+			result = listener.accept() => match result {
+				Ok(accepted) => accepted,
+				Err(error) => {
+					log::debug!("accepting a TCP connection failed: {error}");
+					continue;
+				}
+			},
+			() = &mut shutdown => break,
+		};
+
+		let acceptor = acceptor.clone();
+		let router = router.clone();
+		let mut stop_receiver = stop_receiver.clone();
+		tokio::spawn(async move {
+			let stream = match acceptor.accept(stream).await {
+				Ok(stream) => stream,
+				Err(error) => {
+					log::debug!("TLS handshake with {addr} failed: {error}");
+					return;
+				}
+			};
+
+			let builder = Builder::new(TokioExecutor::new());
+			let connection =
+				builder.serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(router));
+			tokio::pin!(connection);
+			let result = tokio::select! {
+				result = connection.as_mut() => result,
+				_ = stop_receiver.changed() => {
+					connection.as_mut().graceful_shutdown();
+					connection.await
+				}
+			};
+			if let Err(error) = result {
+				log::debug!("HTTPS connection with {addr} failed: {error}");
+			}
+		});
+	}
+
+	drop(listener);
+	drop(stop_receiver);
+	stop_sender.send_replace(());
+	stop_sender.closed().await;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_certificate() {
+		let options = TlsOptions {
+			cert_path: PathBuf::from("../testdata/does_not_exist.pem"),
+			key_path: PathBuf::from("../testdata/does_not_exist.pem"),
+		};
+		let error = options.server_config(&[b"h3"]).unwrap_err().to_string();
+		assert_eq!(error, "reading the certificates \"../testdata/does_not_exist.pem\"");
+	}
+}
@@ -0,0 +1,374 @@
+use anyhow::{ensure, Result};
+use axum::{
+	body::Body,
+	extract::{Request, State},
+	http::{
+		header::{CACHE_CONTROL, VARY},
+		HeaderMap, HeaderValue, Uri,
+	},
+	middleware::Next,
+	response::Response,
+};
+use ring::hmac;
+use std::{
+	borrow::Cow,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Header that contains an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Query parameters used for authentication. They are removed before the request is handled.
+const AUTH_PARAMS: [&str; 3] = ["key", "expires", "signature"];
+
+/// Decides which requests may read a tile source.
+///
+/// A request is allowed if it contains one of the API keys, either in the header `X-API-Key` or in the
+/// query parameter `key`, or if it is a signed URL that has not expired. Signed URLs have the query
+/// parameters `expires` (a Unix timestamp) and `signature`, the hex encoded HMAC-SHA256 of
+/// `"{path}?expires={expires}"` with the signing secret. Without keys and secret, every request is allowed.
+#[derive(Clone, Default, PartialEq)]
+pub struct Auth {
+	api_keys: Vec<String>,
+	secret: Option<Vec<u8>>,
+}
+
+/// Who sent an allowed request, e.g. "API key 2", for the audit log of the admin APIs.
+/// Added to the extensions of every request that passes [`require_auth`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Actor(pub String);
+
+/// Result of checking a request
+#[derive(Debug, PartialEq)]
+pub enum AuthResult {
+	Allowed,
+	/// no API key or signature was sent
+	Missing,
+	/// the API key or signature is wrong, or the signed URL has expired
+	Denied,
+}
+
+impl Auth {
+	pub fn new(api_keys: &[String], secret: Option<&str>) -> Result<Auth> {
+		ensure!(api_keys.iter().all(|key| !key.is_empty()), "API keys must not be empty");
+		ensure!(
+			secret.is_none_or(|s| s.len() >= 16),
+			"the signing secret must have at least 16 characters"
+		);
+		Ok(Auth {
+			api_keys: api_keys.to_vec(),
+			secret: secret.map(|s| s.as_bytes().to_vec()),
+		})
+	}
+
+	/// Returns `true`, if every request is allowed.
+	pub fn is_public(&self) -> bool {
+		self.api_keys.is_empty() && self.secret.is_none()
+	}
+
+	pub fn check(&self, path: &str, query: Option<&str>, headers: &HeaderMap, now: SystemTime) -> AuthResult {
+		if self.is_public() {
+			return AuthResult::Allowed;
+		}
+		let param = |name: &str| get_param(query, name);
+
+		if let Some(key) = get_key(query, headers) {
+			if self.api_keys.iter().any(|k| equal(k.as_bytes(), key.as_bytes())) {
+				return AuthResult::Allowed;
+			}
+			return AuthResult::Denied;
+		}
+
+		let (Some(expires), Some(signature)) = (param("expires"), param("signature")) else {
+			return AuthResult::Missing;
+		};
+		let Some(secret) = &self.secret else {
+			return AuthResult::Denied;
+		};
+		let Ok(timestamp) = expires.parse::<u64>() else {
+			return AuthResult::Denied;
+		};
+		let now = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+		let Some(signature) = decode_hex(&signature) else {
+			return AuthResult::Denied;
+		};
+		let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+		let message = format!("{path}?expires={expires}");
+		if timestamp >= now && hmac::verify(&key, message.as_bytes(), &signature).is_ok() {
+			AuthResult::Allowed
+		} else {
+			AuthResult::Denied
+		}
+	}
+
+	/// Describes who sent an allowed request, without revealing the API key:
+	/// "API key 2" for the second key, "signed URL" or "anonymous" for public resources.
+	pub fn actor(&self, query: Option<&str>, headers: &HeaderMap) -> Actor {
+		if self.is_public() {
+			return Actor(String::from("anonymous"));
+		}
+		let index =
+			get_key(query, headers).and_then(|key| self.api_keys.iter().position(|k| equal(k.as_bytes(), key.as_bytes())));
+		Actor(match index {
+			Some(index) => format!("API key {}", index + 1),
+			None => String::from("signed URL"),
+		})
+	}
+
+	/// Returns the query of a URL for `path` that is valid until the Unix timestamp `expires`.
+	/// Backends of the tile provider sign URLs the same way.
+	#[cfg(test)]
+	pub fn sign(&self, path: &str, expires: u64) -> Option<String> {
+		let key = hmac::Key::new(hmac::HMAC_SHA256, self.secret.as_ref()?);
+		let tag = hmac::sign(&key, format!("{path}?expires={expires}").as_bytes());
+		let signature: String = tag.as_ref().iter().map(|byte| format!("{byte:02x}")).collect();
+		Some(format!("expires={expires}&signature={signature}"))
+	}
+}
+
+impl std::fmt::Debug for Auth {
+	/// Never prints keys or secret
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Auth")
+			.field("api_keys", &self.api_keys.len())
+			.field("signed_urls", &self.secret.is_some())
+			.finish()
+	}
+}
+
+/// Decodes a hex string, case-insensitive. Returns `None` for invalid characters or an odd length.
+fn get_param<'a>(query: Option<&'a str>, name: &str) -> Option<Cow<'a, str>> {
+	form_urlencoded::parse(query?.as_bytes()).find_map(|(key, value)| (key == name).then_some(value))
+}
+
+/// Returns the API key of a request, sent as header or as query parameter
+fn get_key<'a>(query: Option<&'a str>, headers: &'a HeaderMap) -> Option<Cow<'a, str>> {
+	headers
+		.get(API_KEY_HEADER)
+		.and_then(|value| value.to_str().ok())
+		.map(Cow::Borrowed)
+		.or_else(|| get_param(query, "key"))
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+	if !text.len().is_multiple_of(2) || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+		return None;
+	}
+	(0..text.len())
+		.step_by(2)
+		.map(|index| u8::from_str_radix(&text[index..index + 2], 16).ok())
+		.collect()
+}
+
+/// Compares in constant time, so that keys can't be guessed by measuring response times.
+fn equal(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Removes the authentication parameters from the query of `uri`, so that they don't split the tile cache.
+/// Names are compared URL decoded, like `check` reads them, so `%6Bey` is removed as well.
+fn strip_auth_params(uri: &Uri) -> Uri {
+	let Some(query) = uri.query() else {
+		return uri.clone();
+	};
+	let query: Vec<&str> = query
+		.split('&')
+		.filter(|pair| {
+			let name = pair.split('=').next().unwrap_or("");
+			let name = form_urlencoded::parse(name.as_bytes()).next().map(|(name, _)| name);
+			!name.is_some_and(|name| AUTH_PARAMS.contains(&name.as_ref()))
+		})
+		.collect();
+	let path_and_query = if query.is_empty() {
+		uri.path().to_string()
+	} else {
+		format!("{}?{}", uri.path(), query.join("&"))
+	};
+	path_and_query.parse().unwrap_or_else(|_| uri.clone())
+}
+
+/// Keeps shared caches from storing a protected response and serving it to clients without a key.
+fn make_private(headers: &mut HeaderMap) {
+	let cache_control = match headers.get(CACHE_CONTROL).and_then(|value| value.to_str().ok()) {
+		Some(value) => match value.strip_prefix("public") {
+			Some(rest) => format!("private{rest}"),
+			None if value.contains("private") || value.contains("no-store") => value.to_string(),
+			None => format!("private, {value}"),
+		},
+		None => String::from("private"),
+	};
+	if let Ok(value) = HeaderValue::from_str(&cache_control) {
+		headers.insert(CACHE_CONTROL, value);
+	}
+	headers.append(VARY, HeaderValue::from_static(API_KEY_HEADER));
+}
+
+/// Middleware that answers requests with 401 or 403, unless `auth` allows them.
+/// Allowed requests get an [`Actor`] extension, and their responses are marked as private.
+pub async fn require_auth(State(auth): State<Auth>, mut request: Request, next: Next) -> Response {
+	let uri = request.uri();
+	let status = match auth.check(uri.path(), uri.query(), request.headers(), SystemTime::now()) {
+		AuthResult::Allowed => {
+			let actor = auth.actor(uri.query(), request.headers());
+			request.extensions_mut().insert(actor);
+			*request.uri_mut() = strip_auth_params(request.uri());
+			let mut response = next.run(request).await;
+			make_private(response.headers_mut());
+			return response;
+		}
+		AuthResult::Missing => 401,
+		AuthResult::Denied => 403,
+	};
+	log::warn!("send {status} for request: {}", uri.path());
+	let body = if status == 401 { "Unauthorized" } else { "Forbidden" };
+	Response::builder()
+		.status(status)
+		.body(Body::from(body))
+		.expect("should have build a body")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use axum::http::HeaderValue;
+	use std::time::Duration;
+
+	const SECRET: &str = "0123456789abcdef";
+
+	fn at(seconds: u64) -> SystemTime {
+		UNIX_EPOCH + Duration::from_secs(seconds)
+	}
+
+	#[test]
+	fn test_sign() -> Result<()> {
+		// the same as: echo -n "/tiles/osm/3/4/2?expires=1767225600" | openssl sha256 -hmac "0123456789abcdef"
+		let auth = Auth::new(&[], Some(SECRET))?;
+		assert_eq!(
+			auth.sign("/tiles/osm/3/4/2", 1767225600).unwrap(),
+			"expires=1767225600&signature=aab45070df31644e435e22ee1d17ef1d288679d2e01a5e3dab7e2e72f6a4ef52"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_decode_hex() {
+		assert_eq!(decode_hex("00ff7F"), Some(vec![0, 255, 127]));
+		assert_eq!(decode_hex(""), Some(vec![]));
+		assert_eq!(decode_hex("abc"), None);
+		assert_eq!(decode_hex("zz"), None);
+		assert_eq!(decode_hex("+1"), None);
+	}
+
+	#[test]
+	fn test_api_keys() -> Result<()> {
+		let auth = Auth::new(&[String::from("alice"), String::from("bob")], None)?;
+		let check = |query: Option<&str>, header: Option<&'static str>| {
+			let mut headers = HeaderMap::new();
+			if let Some(header) = header {
+				headers.insert(API_KEY_HEADER, HeaderValue::from_static(header));
+			}
+			auth.check("/tiles/osm/0/0/0", query, &headers, at(0))
+		};
+		assert_eq!(check(None, None), AuthResult::Missing);
+		assert_eq!(check(Some("key=bob"), None), AuthResult::Allowed);
+		assert_eq!(check(Some("scale=2&key=alice"), None), AuthResult::Allowed);
+		assert_eq!(check(Some("key=eve"), None), AuthResult::Denied);
+		assert_eq!(check(None, Some("alice")), AuthResult::Allowed);
+		assert_eq!(check(None, Some("alic")), AuthResult::Denied);
+
+		// keys in the query are URL encoded
+		let auth = Auth::new(&[String::from("a&b=c d")], None)?;
+		let check = |query: &str| auth.check("/tiles/osm/0/0/0", Some(query), &HeaderMap::new(), at(0));
+		assert_eq!(check("key=a%26b%3Dc%20d"), AuthResult::Allowed);
+		assert_eq!(check("key=a%26b%3Dc+d"), AuthResult::Allowed);
+		assert_eq!(check("key=a&b=c d"), AuthResult::Denied);
+		Ok(())
+	}
+
+	#[test]
+	fn test_actor() -> Result<()> {
+		let auth = Auth::new(&[String::from("alice"), String::from("bob")], Some(SECRET))?;
+		let mut headers = HeaderMap::new();
+		assert_eq!(auth.actor(Some("key=bob"), &headers), Actor(String::from("API key 2")));
+		assert_eq!(
+			auth.actor(Some("expires=1"), &headers),
+			Actor(String::from("signed URL"))
+		);
+		headers.insert(API_KEY_HEADER, HeaderValue::from_static("alice"));
+		assert_eq!(auth.actor(None, &headers), Actor(String::from("API key 1")));
+		assert_eq!(Auth::default().actor(None, &headers), Actor(String::from("anonymous")));
+		Ok(())
+	}
+
+	#[test]
+	fn test_signed_urls() -> Result<()> {
+		let auth = Auth::new(&[], Some(SECRET))?;
+		let path = "/tiles/osm/0/0/0";
+		let query = auth.sign(path, 1000).unwrap();
+		let check = |path: &str, query: &str, now: u64| auth.check(path, Some(query), &HeaderMap::new(), at(now));
+		assert_eq!(check(path, &query, 999), AuthResult::Allowed);
+		assert_eq!(check(path, &query, 1000), AuthResult::Allowed);
+		assert_eq!(check(path, &query, 1001), AuthResult::Denied);
+		assert_eq!(check("/tiles/osm/1/0/0", &query, 999), AuthResult::Denied);
+		assert_eq!(
+			check(path, &query.replace("expires=1000", "expires=2000"), 999),
+			AuthResult::Denied
+		);
+		let signature = query.split_once("signature=").unwrap().1;
+		let upper = format!("expires=1000&signature={}", signature.to_uppercase());
+		assert_eq!(check(path, &upper, 999), AuthResult::Allowed);
+		assert_eq!(check(path, "expires=1000&signature=00", 999), AuthResult::Denied);
+		assert_eq!(check(path, "expires=1000&signature=xyz", 999), AuthResult::Denied);
+		assert_eq!(check(path, "expires=1000", 999), AuthResult::Missing);
+		Ok(())
+	}
+
+	#[test]
+	fn test_new() {
+		assert!(Auth::default().is_public());
+		assert_eq!(
+			Auth::new(&[], Some("short")).unwrap_err().to_string(),
+			"the signing secret must have at least 16 characters"
+		);
+		assert_eq!(
+			format!("{:?}", Auth::new(&[String::from("alice")], Some(SECRET)).unwrap()),
+			"Auth { api_keys: 1, signed_urls: true }"
+		);
+	}
+
+	#[test]
+	fn test_strip_auth_params() {
+		let strip = |uri: &str| strip_auth_params(&uri.parse().unwrap()).to_string();
+		assert_eq!(strip("/tiles/osm/0/0/0?key=alice"), "/tiles/osm/0/0/0");
+		assert_eq!(
+			strip("/tiles/osm/0/0/0?expires=1&scale=2&signature=ab"),
+			"/tiles/osm/0/0/0?scale=2"
+		);
+		assert_eq!(strip("/tiles/osm/0/0/0"), "/tiles/osm/0/0/0");
+		assert_eq!(strip("/tiles/osm/0/0/0?%6Bey=alice&k%65y=bob"), "/tiles/osm/0/0/0");
+		assert_eq!(
+			strip("/tiles/osm/0/0/0?sig%6Eature=ab&scale=2"),
+			"/tiles/osm/0/0/0?scale=2"
+		);
+	}
+
+	#[test]
+	fn test_make_private() {
+		let private = |cache_control: Option<&'static str>| {
+			let mut headers = HeaderMap::new();
+			if let Some(value) = cache_control {
+				headers.insert(CACHE_CONTROL, HeaderValue::from_static(value));
+			}
+			make_private(&mut headers);
+			assert_eq!(headers.get(VARY).unwrap(), API_KEY_HEADER);
+			headers.get(CACHE_CONTROL).unwrap().to_str().unwrap().to_string()
+		};
+		assert_eq!(
+			private(Some("public, max-age=2419200, no-transform")),
+			"private, max-age=2419200, no-transform"
+		);
+		assert_eq!(private(Some("no-cache")), "private, no-cache");
+		assert_eq!(private(Some("no-store")), "no-store");
+		assert_eq!(private(None), "private");
+	}
+}
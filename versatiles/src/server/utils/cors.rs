@@ -0,0 +1,153 @@
+use anyhow::{ensure, Result};
+use axum::{
+	extract::{Request, State},
+	http::{
+		header::{ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN, VARY},
+		HeaderMap, HeaderValue,
+	},
+	middleware::Next,
+	response::Response,
+};
+
+/// Decides which origins may read responses via CORS.
+///
+/// Allowed origins are either `*` (any origin), an exact origin like `https://example.org`
+/// or an origin with a leading wildcard subdomain like `https://*.example.org`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cors {
+	origins: Vec<String>,
+}
+
+impl Cors {
+	pub fn new(origins: &[String]) -> Result<Cors> {
+		for origin in origins {
+			ensure!(
+				origin == "*" || origin.contains("://"),
+				"CORS origin {origin:?} must be \"*\" or start with a scheme like \"https://\""
+			);
+			ensure!(
+				!origin.contains('*') || origin == "*" || (origin.matches('*').count() == 1 && origin.contains("://*.")),
+				"CORS origin {origin:?} may only contain a wildcard as the first subdomain, like \"https://*.example.org\""
+			);
+		}
+		Ok(Cors {
+			origins: origins.to_vec(),
+		})
+	}
+
+	pub fn any() -> Cors {
+		Cors {
+			origins: vec![String::from("*")],
+		}
+	}
+
+	/// Returns the value for the `Access-Control-Allow-Origin` header, if the origin is allowed.
+	pub fn allow_origin(&self, origin: Option<&str>) -> Option<String> {
+		if self.origins.iter().any(|o| o == "*") {
+			return Some(String::from("*"));
+		}
+		let origin = origin?;
+		self
+			.origins
+			.iter()
+			.any(|allowed| match allowed.split_once("://*.") {
+				Some((scheme, domain)) => origin
+					.strip_prefix(scheme)
+					.and_then(|rest| rest.strip_prefix("://"))
+					.and_then(|host| host.strip_suffix(domain))
+					.is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+				None => allowed == origin,
+			})
+			.then(|| origin.to_string())
+	}
+
+	/// Sets the CORS headers of a response according to the `Origin` header of the request.
+	pub fn apply(&self, request_headers: &HeaderMap, response_headers: &mut HeaderMap) {
+		let origin = request_headers.get(ORIGIN).and_then(|o| o.to_str().ok());
+		match self.allow_origin(origin).and_then(|o| HeaderValue::from_str(&o).ok()) {
+			Some(value) => {
+				response_headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, value);
+			}
+			None => {
+				response_headers.remove(ACCESS_CONTROL_ALLOW_ORIGIN);
+			}
+		}
+		if !self.origins.iter().any(|o| o == "*") {
+			// the response depends on the origin, so caches must keep them apart
+			response_headers.append(VARY, HeaderValue::from_static("origin"));
+		}
+	}
+}
+
+/// Middleware that sets the CORS headers of every response, also of the ones rejected by other middlewares,
+/// so that browsers can read 401 and 403 responses.
+pub async fn add_cors_headers(State(cors): State<Cors>, request: Request, next: Next) -> Response {
+	let request_headers = request.headers().clone();
+	let mut response = next.run(request).await;
+	cors.apply(&request_headers, response.headers_mut());
+	response
+}
+
+impl Default for Cors {
+	fn default() -> Self {
+		Cors::any()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn cors(origins: &[&str]) -> Cors {
+		Cors::new(&origins.iter().map(|o| o.to_string()).collect::<Vec<_>>()).unwrap()
+	}
+
+	#[test]
+	fn test_allow_origin() {
+		let any = Cors::any();
+		assert_eq!(any.allow_origin(None).as_deref(), Some("*"));
+		assert_eq!(any.allow_origin(Some("https://a.org")).as_deref(), Some("*"));
+
+		let list = cors(&["https://example.org", "https://*.tiles.example.org"]);
+		assert_eq!(list.allow_origin(None), None);
+		assert_eq!(
+			list.allow_origin(Some("https://example.org")).as_deref(),
+			Some("https://example.org")
+		);
+		assert_eq!(list.allow_origin(Some("http://example.org")), None);
+		assert_eq!(
+			list.allow_origin(Some("https://a.tiles.example.org")).as_deref(),
+			Some("https://a.tiles.example.org")
+		);
+		assert_eq!(list.allow_origin(Some("https://tiles.example.org")), None);
+		assert_eq!(list.allow_origin(Some("https://.tiles.example.org")), None);
+		assert_eq!(list.allow_origin(Some("https://evil-tiles.example.org")), None);
+	}
+
+	#[test]
+	fn test_invalid_origins() {
+		let check = |origin: &str| Cors::new(&[origin.to_string()]).unwrap_err().to_string();
+		assert!(check("example.org").starts_with("CORS origin \"example.org\" must be"));
+		assert!(check("https://tiles.*.org").starts_with("CORS origin \"https://tiles.*.org\" may only"));
+	}
+
+	#[test]
+	fn test_apply() {
+		let mut request = HeaderMap::new();
+		request.insert(ORIGIN, HeaderValue::from_static("https://a.example.org"));
+
+		let mut response = HeaderMap::new();
+		response.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+		cors(&["https://*.example.org"]).apply(&request, &mut response);
+		assert_eq!(
+			response.get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+			"https://a.example.org"
+		);
+		assert_eq!(response.get(VARY).unwrap(), "origin");
+
+		let mut response = HeaderMap::new();
+		response.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+		cors(&["https://b.example.org"]).apply(&request, &mut response);
+		assert!(response.get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+	}
+}
@@ -0,0 +1,13 @@
+//! helper function for handling URLs, MIME, CORS, authentication and request tracing
+
+mod auth;
+mod cors;
+mod mime;
+mod trace;
+mod url;
+
+pub use auth::*;
+pub use cors::*;
+pub use mime::*;
+pub use trace::*;
+pub use url::*;
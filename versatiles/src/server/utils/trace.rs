@@ -0,0 +1,123 @@
+use super::Url;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use std::time::{Duration, Instant};
+
+/// Request header that enables tracing for a single request, e.g. `X-Versatiles-Trace: 1`
+pub const TRACE_HEADER: HeaderName = HeaderName::from_static("x-versatiles-trace");
+
+/// Response header with the timing breakdown, shown by the developer tools of browsers
+pub const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// Measures how long the steps of a request take, e.g. reading or recompressing a tile.
+///
+/// A disabled trace ignores all steps, so it can be passed around unconditionally.
+#[derive(Debug)]
+pub struct RequestTrace {
+	enabled: bool,
+	start: Instant,
+	last: Instant,
+	steps: Vec<(&'static str, Duration)>,
+}
+
+impl RequestTrace {
+	pub fn new(enabled: bool) -> RequestTrace {
+		let now = Instant::now();
+		RequestTrace {
+			enabled,
+			start: now,
+			last: now,
+			steps: Vec::new(),
+		}
+	}
+
+	/// Starts a trace, if tracing is enabled for all requests or requested by the `X-Versatiles-Trace` header.
+	pub fn from_request(enabled: bool, request_headers: &HeaderMap) -> RequestTrace {
+		let requested = request_headers
+			.get(TRACE_HEADER)
+			.is_some_and(|value| !matches!(value.as_bytes(), b"" | b"0" | b"false"));
+		RequestTrace::new(enabled || requested)
+	}
+
+	/// Ends the current step and starts the next one.
+	pub fn step(&mut self, name: &'static str) {
+		if self.enabled {
+			let now = Instant::now();
+			self.steps.push((name, now - self.last));
+			self.last = now;
+		}
+	}
+
+	/// Returns the steps and the total duration in the format of the `Server-Timing` header,
+	/// e.g. `lookup;dur=0.010, read;dur=1.250, total;dur=1.260`.
+	pub fn as_server_timing(&self) -> String {
+		let total = ("total", self.last - self.start);
+		self
+			.steps
+			.iter()
+			.chain([&total])
+			.map(|(name, duration)| format!("{name};dur={:.3}", duration.as_secs_f64() * 1000.0))
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+
+	/// Adds the `Server-Timing` header to the response and logs the timing breakdown.
+	pub fn finish(&mut self, url: &Url, response_headers: &mut HeaderMap) {
+		if !self.enabled {
+			return;
+		}
+		self.last = Instant::now();
+		let timing = self.as_server_timing();
+		log::info!("trace {url}: {timing}");
+		if let Ok(value) = HeaderValue::from_str(&timing) {
+			response_headers.insert(SERVER_TIMING, value);
+		}
+	}
+}
+
+impl Default for RequestTrace {
+	fn default() -> Self {
+		RequestTrace::new(false)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use regex::Regex;
+
+	#[test]
+	fn test_from_request() {
+		let mut headers = HeaderMap::new();
+		assert!(!RequestTrace::from_request(false, &headers).enabled);
+		assert!(RequestTrace::from_request(true, &headers).enabled);
+
+		headers.insert(TRACE_HEADER, HeaderValue::from_static("1"));
+		assert!(RequestTrace::from_request(false, &headers).enabled);
+
+		headers.insert(TRACE_HEADER, HeaderValue::from_static("0"));
+		assert!(!RequestTrace::from_request(false, &headers).enabled);
+	}
+
+	#[test]
+	fn test_finish() {
+		let mut trace = RequestTrace::new(true);
+		trace.step("lookup");
+		trace.step("read");
+
+		let mut headers = HeaderMap::new();
+		trace.finish(&Url::new("/tiles/osm/0/0/0"), &mut headers);
+		let timing = headers.get(SERVER_TIMING).unwrap().to_str().unwrap();
+		assert!(
+			Regex::new(r"^lookup;dur=\d+\.\d{3}, read;dur=\d+\.\d{3}, total;dur=\d+\.\d{3}$")
+				.unwrap()
+				.is_match(timing),
+			"{timing}"
+		);
+
+		let mut trace = RequestTrace::default();
+		trace.step("lookup");
+		let mut headers = HeaderMap::new();
+		trace.finish(&Url::new("/"), &mut headers);
+		assert!(headers.is_empty());
+	}
+}
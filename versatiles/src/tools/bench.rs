@@ -0,0 +1,549 @@
+//! Reproducible benchmarks of reading, converting and serving tiles.
+//!
+//! All benchmarks use the seeded mock source of `versatiles_container`, so every run processes exactly the
+//! same tiles. The results contain the version, the operating system and the architecture, so that results
+//! of different releases and machines can be tracked, e.g. as CI artifacts, and checked with
+//! `versatiles bench compare old.json new.json`.
+
+use anyhow::{bail, ensure, Context, Result};
+use futures::{stream, StreamExt};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+use versatiles::server::spawn_test_server;
+use versatiles_container::{get_reader, write_to_filename, MockTilesReader, MockTilesReaderOptions};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::{TileBBoxPyramid, TileCompression, TileFormat, TilesReaderParameters},
+	utils::get_temp_dir,
+};
+
+/// Containers that are written and read by the benchmarks
+const CONTAINERS: [&str; 4] = ["versatiles", "pmtiles", "mbtiles", "tar"];
+/// Number of parallel requests of the server benchmark
+const CONCURRENCY: usize = 16;
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+	/// Run the benchmarks: read latency and convert throughput of every container, and requests per second of the server
+	Run(RunArguments),
+	/// Compare two results of "versatiles bench run", fails if a benchmark got slower than the threshold
+	Compare(CompareArguments),
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArguments {
+	/// write the results as JSON to this file, use "-" for stdout
+	#[arg(long, short, value_name = "FILE")]
+	output: Option<String>,
+
+	/// highest zoom level of the mock source, every level has 4 times more tiles than the one before
+	#[arg(long, value_name = "int", default_value_t = 7)]
+	max_zoom: u8,
+
+	/// number of measurements of every benchmark, the median is reported
+	#[arg(long, value_name = "int", default_value_t = 5)]
+	repeat: u32,
+
+	/// run only the benchmarks whose name contains this text, e.g. "pmtiles" or "serve"
+	#[arg(long, value_name = "TEXT")]
+	filter: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct CompareArguments {
+	/// results of the old version
+	#[arg(required = true)]
+	old_file: String,
+
+	/// results of the new version
+	#[arg(required = true)]
+	new_file: String,
+
+	/// slowdown in percent that counts as a regression
+	#[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+	threshold: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Unit {
+	MicrosPerTile,
+	TilesPerSecond,
+	RequestsPerSecond,
+}
+
+impl Unit {
+	fn as_str(&self) -> &str {
+		match self {
+			Unit::MicrosPerTile => "us/tile",
+			Unit::TilesPerSecond => "tiles/s",
+			Unit::RequestsPerSecond => "requests/s",
+		}
+	}
+
+	fn parse_str(value: &str) -> Result<Unit> {
+		Ok(match value {
+			"us/tile" => Unit::MicrosPerTile,
+			"tiles/s" => Unit::TilesPerSecond,
+			"requests/s" => Unit::RequestsPerSecond,
+			_ => bail!("unknown unit \"{value}\""),
+		})
+	}
+
+	fn higher_is_better(&self) -> bool {
+		!matches!(self, Unit::MicrosPerTile)
+	}
+}
+
+/// Median of one benchmark
+#[derive(Clone, Debug, PartialEq)]
+struct Measurement {
+	name: String,
+	unit: Unit,
+	value: f64,
+}
+
+impl Measurement {
+	/// Converts the median duration of processing `count` tiles or requests.
+	fn new(name: &str, unit: Unit, count: u64, mut durations: Vec<Duration>) -> Measurement {
+		durations.sort();
+		let seconds = durations[durations.len() / 2].as_secs_f64().max(1e-9);
+		let value = match unit {
+			Unit::MicrosPerTile => seconds * 1e6 / count as f64,
+			Unit::TilesPerSecond | Unit::RequestsPerSecond => count as f64 / seconds,
+		};
+		Measurement {
+			name: name.to_string(),
+			unit,
+			value,
+		}
+	}
+
+	/// Slowdown in percent compared to `old`, negative if it got faster
+	fn slowdown(&self, old: &Measurement) -> f64 {
+		if self.unit.higher_is_better() {
+			(old.value / self.value - 1.0) * 100.0
+		} else {
+			(self.value / old.value - 1.0) * 100.0
+		}
+	}
+}
+
+/// All results of one run, and the environment they were measured in
+#[derive(Clone, Debug, PartialEq)]
+struct BenchReport {
+	version: String,
+	os: String,
+	arch: String,
+	cpus: u32,
+	max_zoom: u8,
+	results: Vec<Measurement>,
+}
+
+impl BenchReport {
+	fn new(max_zoom: u8) -> BenchReport {
+		BenchReport {
+			version: env!("CARGO_PKG_VERSION").to_string(),
+			os: std::env::consts::OS.to_string(),
+			arch: std::env::consts::ARCH.to_string(),
+			cpus: std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+			max_zoom,
+			results: Vec::new(),
+		}
+	}
+
+	/// Describes the environment, e.g. "versatiles 0.15.1, linux x86_64, 8 cpus, max zoom 7"
+	fn environment(&self) -> String {
+		format!(
+			"versatiles {}, {} {}, {} cpus, max zoom {}",
+			self.version, self.os, self.arch, self.cpus, self.max_zoom
+		)
+	}
+
+	fn as_json(&self) -> JsonObject {
+		let mut object = JsonObject::default();
+		object.set("version", &self.version);
+		object.set("os", &self.os);
+		object.set("arch", &self.arch);
+		object.set("cpus", self.cpus);
+		object.set("max_zoom", self.max_zoom);
+		let results: Vec<JsonValue> = self
+			.results
+			.iter()
+			.map(|m| {
+				JsonValue::from(vec![
+					("name", JsonValue::from(&m.name)),
+					("unit", JsonValue::from(m.unit.as_str())),
+					("value", JsonValue::from(m.value)),
+				])
+			})
+			.collect();
+		object.set("results", results);
+		object
+	}
+
+	fn from_json(object: &JsonObject) -> Result<BenchReport> {
+		let string =
+			|key: &str| -> Result<String> { object.get_string(key)?.with_context(|| format!("missing \"{key}\"")) };
+		let mut results = Vec::new();
+		for value in &object.get_array("results")?.context("missing \"results\"")?.0 {
+			let result = value.as_object()?;
+			results.push(Measurement {
+				name: result.get_string("name")?.context("missing \"name\"")?,
+				unit: Unit::parse_str(&result.get_string("unit")?.context("missing \"unit\"")?)?,
+				value: result.get_number("value")?.context("missing \"value\"")?,
+			});
+		}
+		Ok(BenchReport {
+			version: string("version")?,
+			os: string("os")?,
+			arch: string("arch")?,
+			cpus: object.get_number("cpus")?.unwrap_or(0),
+			max_zoom: object.get_number("max_zoom")?.unwrap_or(0),
+			results,
+		})
+	}
+
+	fn read_file(filename: &str) -> Result<BenchReport> {
+		let json = fs::read_to_string(filename).with_context(|| format!("Failed to read {filename:?}"))?;
+		BenchReport::from_json(&JsonObject::parse_str(&json)?).with_context(|| format!("Failed to parse {filename:?}"))
+	}
+}
+
+pub fn run(arguments: &Subcommand) -> Result<()> {
+	match &arguments.command {
+		Command::Run(arguments) => run_benchmarks(arguments),
+		Command::Compare(arguments) => compare(arguments),
+	}
+}
+
+#[tokio::main]
+async fn run_benchmarks(arguments: &RunArguments) -> Result<()> {
+	ensure!(arguments.repeat > 0, "--repeat must be at least 1");
+	ensure!(arguments.max_zoom <= 12, "--max-zoom must be at most 12");
+
+	let dir = get_temp_dir().join(format!("versatiles-bench-{}", std::process::id()));
+	fs::create_dir_all(&dir)?;
+	let result = measure_all(arguments, &dir).await;
+	fs::remove_dir_all(&dir)?;
+	let report = result?;
+
+	match arguments.output.as_deref() {
+		None => println!("{}", format_report(&report)),
+		Some("-") => println!("{}", report.as_json().stringify()),
+		Some(filename) => {
+			fs::write(filename, report.as_json().stringify())?;
+			super::print_status(&format!("results written to {filename:?}"));
+		}
+	}
+	Ok(())
+}
+
+/// Seeded mock source with tiles of 100 to 1000 random bytes
+fn mock_reader(max_zoom: u8) -> Result<MockTilesReader> {
+	MockTilesReader::new_mock_with_options(
+		TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(max_zoom),
+		),
+		MockTilesReaderOptions {
+			seed: 2025,
+			random_size: Some(100..1000),
+			..Default::default()
+		},
+	)
+}
+
+async fn measure_all(arguments: &RunArguments, dir: &Path) -> Result<BenchReport> {
+	let selected = |name: &str| arguments.filter.as_ref().is_none_or(|filter| name.contains(filter));
+	let max_zoom = arguments.max_zoom;
+	let mut report = BenchReport::new(max_zoom);
+	let tile_count = TileBBoxPyramid::new_full(max_zoom).count_tiles();
+
+	// every read benchmark needs the container written by the convert benchmark
+	let mut files: Vec<(&str, PathBuf)> = Vec::new();
+	for container in CONTAINERS {
+		let path = dir.join(format!("tiles.{container}"));
+		let name = format!("convert/{container}");
+		let needed = selected(&name)
+			|| selected(&format!("read/{container}"))
+			|| (container == "versatiles" && selected("serve/versatiles"));
+		if !needed {
+			continue;
+		}
+		let repeat = if selected(&name) { arguments.repeat + 1 } else { 1 };
+		let mut durations = Vec::new();
+		for _ in 0..repeat {
+			if path.exists() {
+				fs::remove_file(&path)?;
+			}
+			let mut reader = mock_reader(max_zoom)?;
+			let start = Instant::now();
+			write_to_filename(&mut reader, path.to_str().unwrap()).await?;
+			durations.push(start.elapsed());
+		}
+		if selected(&name) {
+			// the first run warms up the caches of the file system
+			durations.remove(0);
+			super::print_status(&format!("benchmarked {name}"));
+			report
+				.results
+				.push(Measurement::new(&name, Unit::TilesPerSecond, tile_count, durations));
+		}
+		files.push((container, path));
+	}
+
+	let level_bbox = TileBBoxPyramid::new_full(max_zoom).get_level_bbox(max_zoom).clone();
+	for (container, path) in &files {
+		let name = format!("read/{container}");
+		if !selected(&name) {
+			continue;
+		}
+		let reader = get_reader(path.to_str().unwrap()).await?;
+		let mut durations = Vec::new();
+		for index in 0..=arguments.repeat {
+			let start = Instant::now();
+			for coord in level_bbox.iter_coords() {
+				reader.get_tile_data(&coord).await?.context("missing tile")?;
+			}
+			if index > 0 {
+				durations.push(start.elapsed());
+			}
+		}
+		super::print_status(&format!("benchmarked {name}"));
+		report.results.push(Measurement::new(
+			&name,
+			Unit::MicrosPerTile,
+			level_bbox.count_tiles(),
+			durations,
+		));
+	}
+
+	let name = "serve/versatiles";
+	if selected(name) {
+		let path = &files
+			.iter()
+			.find(|(container, _)| *container == "versatiles")
+			.unwrap()
+			.1;
+		let reader = get_reader(path.to_str().unwrap()).await?;
+		let (addr, handle) = spawn_test_server(vec![("bench", reader)]).await?;
+		let client = reqwest::Client::new();
+		let urls: Vec<String> = level_bbox
+			.iter_coords()
+			.map(|c| format!("http://{addr}/tiles/bench/{}/{}/{}", c.z, c.x, c.y))
+			.collect();
+
+		let mut durations = Vec::new();
+		for index in 0..=arguments.repeat {
+			let start = Instant::now();
+			let responses: Vec<Result<()>> = stream::iter(&urls)
+				.map(|url| {
+					let client = &client;
+					async move {
+						let response = client.get(url).send().await?;
+						ensure!(response.status().is_success(), "{url} returned {}", response.status());
+						response.bytes().await?;
+						Ok(())
+					}
+				})
+				.buffer_unordered(CONCURRENCY)
+				.collect()
+				.await;
+			responses.into_iter().collect::<Result<()>>()?;
+			if index > 0 {
+				durations.push(start.elapsed());
+			}
+		}
+		handle.shutdown().await;
+		super::print_status(&format!("benchmarked {name}"));
+		report.results.push(Measurement::new(
+			name,
+			Unit::RequestsPerSecond,
+			urls.len() as u64,
+			durations,
+		));
+	}
+
+	ensure!(!report.results.is_empty(), "no benchmark matches the filter");
+	Ok(report)
+}
+
+fn format_report(report: &BenchReport) -> String {
+	let mut lines = vec![report.environment()];
+	for m in &report.results {
+		lines.push(format!("{:<20} {:>12.1} {}", m.name, m.value, m.unit.as_str()));
+	}
+	lines.join("\n")
+}
+
+fn compare(arguments: &CompareArguments) -> Result<()> {
+	let old = BenchReport::read_file(&arguments.old_file)?;
+	let new = BenchReport::read_file(&arguments.new_file)?;
+	let (text, regressions) = compare_reports(&old, &new, arguments.threshold);
+	println!("{text}");
+	ensure!(
+		regressions.is_empty(),
+		"{} of {} benchmarks are more than {}% slower: {}",
+		regressions.len(),
+		new.results.len(),
+		arguments.threshold,
+		regressions.join(", ")
+	);
+	Ok(())
+}
+
+/// Returns the comparison as text, and the names of all benchmarks that got slower than `threshold` percent.
+fn compare_reports(old: &BenchReport, new: &BenchReport, threshold: f64) -> (String, Vec<String>) {
+	let mut lines = vec![
+		format!("old: {}", old.environment()),
+		format!("new: {}", new.environment()),
+	];
+	if (&old.os, &old.arch, old.cpus, old.max_zoom) != (&new.os, &new.arch, new.cpus, new.max_zoom) {
+		lines.push(String::from(
+			"warning: the results were measured in different environments",
+		));
+	}
+
+	let mut regressions = Vec::new();
+	for m in &new.results {
+		let Some(o) = old.results.iter().find(|o| o.name == m.name && o.unit == m.unit) else {
+			lines.push(format!("{:<20} new benchmark", m.name));
+			continue;
+		};
+		let slowdown = m.slowdown(o);
+		let mut line = format!(
+			"{:<20} {:>12.1} -> {:>12.1} {:<10} {:>+7.1}% slower",
+			m.name,
+			o.value,
+			m.value,
+			m.unit.as_str(),
+			slowdown
+		);
+		if slowdown > threshold {
+			line.push_str("  REGRESSION");
+			regressions.push(m.name.clone());
+		}
+		lines.push(line);
+	}
+	for o in &old.results {
+		if !new.results.iter().any(|m| m.name == o.name) {
+			lines.push(format!("{:<20} removed", o.name));
+		}
+	}
+	(lines.join("\n"), regressions)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::run_command;
+	use assert_fs::{fixture::PathChild, TempDir};
+
+	fn report(values: &[(&str, Unit, f64)]) -> BenchReport {
+		let mut report = BenchReport::new(7);
+		report.results = values
+			.iter()
+			.map(|(name, unit, value)| Measurement {
+				name: name.to_string(),
+				unit: *unit,
+				value: *value,
+			})
+			.collect();
+		report
+	}
+
+	#[test]
+	fn measurement() {
+		let durations = vec![Duration::from_secs(4), Duration::from_secs(1), Duration::from_secs(2)];
+		let m = Measurement::new("a", Unit::TilesPerSecond, 100, durations.clone());
+		assert_eq!(m.value, 50.0);
+		let m = Measurement::new("a", Unit::MicrosPerTile, 100, durations);
+		assert_eq!(m.value, 20000.0);
+	}
+
+	#[test]
+	fn json() -> Result<()> {
+		let report = report(&[
+			("read/tar", Unit::MicrosPerTile, 1.5),
+			("serve/versatiles", Unit::RequestsPerSecond, 9000.0),
+		]);
+		let json = report.as_json().stringify();
+		assert_eq!(BenchReport::from_json(&JsonObject::parse_str(&json)?)?, report);
+
+		let error = BenchReport::from_json(&JsonObject::parse_str(
+			r#"{"version":"1","os":"linux","arch":"x86_64","results":[{"name":"a","unit":"days","value":1}]}"#,
+		)?)
+		.unwrap_err();
+		assert_eq!(error.to_string(), "unknown unit \"days\"");
+		Ok(())
+	}
+
+	#[test]
+	fn compare_results() {
+		let old = report(&[
+			("read/tar", Unit::MicrosPerTile, 10.0),
+			("convert/tar", Unit::TilesPerSecond, 1000.0),
+			("convert/mbtiles", Unit::TilesPerSecond, 1000.0),
+		]);
+		let new = report(&[
+			("read/tar", Unit::MicrosPerTile, 12.0),
+			("convert/tar", Unit::TilesPerSecond, 1100.0),
+			("serve/versatiles", Unit::RequestsPerSecond, 5000.0),
+		]);
+
+		let (text, regressions) = compare_reports(&old, &new, 10.0);
+		assert_eq!(regressions, ["read/tar"]);
+		let lines: Vec<&str> = text.lines().skip(2).collect();
+		assert_eq!(
+			lines,
+			[
+				"read/tar                     10.0 ->         12.0 us/tile      +20.0% slower  REGRESSION",
+				"convert/tar                1000.0 ->       1100.0 tiles/s       -9.1% slower",
+				"serve/versatiles     new benchmark",
+				"convert/mbtiles      removed",
+			]
+		);
+
+		let (_, regressions) = compare_reports(&old, &new, 25.0);
+		assert!(regressions.is_empty());
+	}
+
+	#[test]
+	fn run_and_compare() -> Result<()> {
+		let dir = TempDir::new()?;
+		let filename = dir.child("results.json").to_str().unwrap().to_string();
+		run_command(vec![
+			"versatiles",
+			"bench",
+			"run",
+			"--max-zoom",
+			"2",
+			"--repeat",
+			"1",
+			"--filter",
+			"versatiles",
+			"--output",
+			&filename,
+		])?;
+
+		let report = BenchReport::read_file(&filename)?;
+		let names: Vec<&str> = report.results.iter().map(|m| m.name.as_str()).collect();
+		assert_eq!(names, ["convert/versatiles", "read/versatiles", "serve/versatiles"]);
+		assert!(report.results.iter().all(|m| m.value > 0.0));
+
+		// compared with itself, nothing is slower
+		run_command(vec!["versatiles", "bench", "compare", &filename, &filename])?;
+		Ok(())
+	}
+}
@@ -0,0 +1,283 @@
+//! SHA-256 verification of input files
+//!
+//! Remote inputs are downloaded into a temporary file while hashing, so a file that changed on the
+//! server or got corrupted is detected before any tiles are read. Local inputs are hashed in place.
+
+use anyhow::{bail, ensure, Context, Result};
+#[cfg(feature = "cli")]
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "cli")]
+use std::io::Write;
+use std::{
+	fs::{remove_file, File},
+	io::Read,
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+#[cfg(feature = "cli")]
+use versatiles_core::utils::ensure_available_space;
+use versatiles_core::{progress::get_progress_bar, utils::get_temp_dir};
+
+static DOWNLOAD_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// An input file that has been checked against its expected checksum.
+/// Temporary downloads are deleted when this is dropped.
+#[derive(Debug)]
+pub struct VerifiedInput {
+	filename: String,
+	temporary: Option<PathBuf>,
+}
+
+impl VerifiedInput {
+	/// Filename (or URL) that should be opened instead of the original input.
+	pub fn filename(&self) -> &str {
+		&self.filename
+	}
+}
+
+impl Drop for VerifiedInput {
+	fn drop(&mut self) {
+		if let Some(path) = &self.temporary {
+			let _ = remove_file(path);
+		}
+	}
+}
+
+/// Verifies `filename` against `expected_sha256`, or against the hash in the sidecar file
+/// "<filename>.sha256" if `use_sidecar` is set.
+///
+/// If neither is given, the input is passed through unchanged. Remote files are downloaded into a
+/// temporary file, so the returned filename points to the verified copy.
+pub async fn verify_input(filename: &str, expected_sha256: Option<&str>, use_sidecar: bool) -> Result<VerifiedInput> {
+	let is_remote = filename.starts_with("http://") || filename.starts_with("https://");
+
+	let expected = match (expected_sha256, use_sidecar) {
+		(Some(hash), _) => parse_sha256(hash)?,
+		(None, true) => {
+			let sidecar = format!("{filename}.sha256");
+			let text = if is_remote {
+				fetch_text(&sidecar).await?
+			} else {
+				std::fs::read_to_string(&sidecar).with_context(|| format!("reading checksum file {sidecar:?}"))?
+			};
+			parse_sha256(&text).with_context(|| format!("parsing checksum file {sidecar:?}"))?
+		}
+		(None, false) => {
+			return Ok(VerifiedInput {
+				filename: filename.to_string(),
+				temporary: None,
+			})
+		}
+	};
+
+	if is_remote {
+		let path = temp_path(filename);
+		let result = VerifiedInput {
+			filename: path.to_string_lossy().to_string(),
+			temporary: Some(path.clone()),
+		};
+		let hash = download(filename, &path).await?;
+		check_hash(filename, &expected, &hash)?;
+		Ok(result)
+	} else {
+		let hash = hash_file(filename)?;
+		check_hash(filename, &expected, &hash)?;
+		Ok(VerifiedInput {
+			filename: filename.to_string(),
+			temporary: None,
+		})
+	}
+}
+
+/// Extracts the hex encoded hash from a string like "<hash>" or "<hash>  <filename>".
+fn parse_sha256(text: &str) -> Result<String> {
+	let hash = text.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+	ensure!(
+		hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()),
+		"invalid SHA-256 checksum {hash:?}, expected 64 hex characters"
+	);
+	Ok(hash)
+}
+
+fn check_hash(filename: &str, expected: &str, actual: &str) -> Result<()> {
+	if expected != actual {
+		bail!("SHA-256 checksum mismatch for {filename:?}: expected {expected}, but got {actual}");
+	}
+	Ok(())
+}
+
+fn temp_path(url: &str) -> PathBuf {
+	// keep the original filename, so that the container format can still be detected by extension
+	let path = url.split(['?', '#']).next().unwrap_or_default();
+	let name = path
+		.rsplit('/')
+		.next()
+		.filter(|name| !name.is_empty())
+		.unwrap_or("download");
+	let index = DOWNLOAD_COUNTER.fetch_add(1, Ordering::Relaxed);
+	get_temp_dir().join(format!("versatiles-{}-{index}-{name}", std::process::id()))
+}
+
+/// Returns the hex encoded SHA-256 hash of a local file.
+pub fn hash_file(filename: &str) -> Result<String> {
+	let mut file = File::open(filename).with_context(|| format!("opening {filename:?}"))?;
+	let mut progress = get_progress_bar("verifying checksum", file.metadata()?.len());
+	let mut hasher = Sha256::new();
+	let mut buffer = vec![0u8; 1 << 20];
+	loop {
+		let size = file.read(&mut buffer)?;
+		if size == 0 {
+			break;
+		}
+		hasher.update(&buffer[..size]);
+		progress.inc(size as u64);
+	}
+	progress.finish();
+	Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Streams `url` into the file `path` and returns the SHA-256 hash of the received data.
+#[cfg(feature = "cli")]
+async fn download(url: &str, path: &Path) -> Result<String> {
+	let mut response = Client::new().get(url).send().await?;
+	ensure!(
+		response.status().is_success(),
+		"downloading {url} failed with status {}",
+		response.status()
+	);
+
+	let expected_size = response.content_length();
+	if let Some(expected_size) = expected_size {
+		ensure_available_space(path, expected_size, &format!("downloading {url}"))?;
+	}
+	let mut file = File::create(path)?;
+	let mut progress = get_progress_bar("downloading", expected_size.unwrap_or(0));
+	let mut hasher = Sha256::new();
+	let mut size = 0u64;
+	while let Some(chunk) = response.chunk().await? {
+		hasher.update(&chunk);
+		file.write_all(&chunk)?;
+		size += chunk.len() as u64;
+		progress.inc(chunk.len() as u64);
+	}
+	progress.finish();
+
+	if let Some(expected_size) = expected_size {
+		ensure!(
+			size == expected_size,
+			"downloading {url} stopped after {size} of {expected_size} bytes"
+		);
+	}
+
+	Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(feature = "cli")]
+async fn fetch_text(url: &str) -> Result<String> {
+	let response = Client::new().get(url).send().await?;
+	ensure!(
+		response.status().is_success(),
+		"downloading {url} failed with status {}",
+		response.status()
+	);
+	Ok(response.text().await?)
+}
+
+#[cfg(not(feature = "cli"))]
+async fn download(url: &str, _path: &Path) -> Result<String> {
+	bail!("downloading {url} is not supported by the minimal build")
+}
+
+#[cfg(not(feature = "cli"))]
+async fn fetch_text(url: &str) -> Result<String> {
+	bail!("downloading {url} is not supported by the minimal build")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tools::server::{TileServer, Url as ServerUrl};
+	use assert_fs::TempDir;
+
+	const BERLIN_SHA256: &str = "94a5b47127f26608bc5e0e74300b197b01c732d66ba62c34783ca5b83a8bfb38";
+
+	#[test]
+	fn test_parse_sha256() {
+		let hash = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+		assert_eq!(parse_sha256(hash).unwrap(), hash);
+		assert_eq!(
+			parse_sha256(&format!("{}  file.pmtiles\n", hash.to_uppercase())).unwrap(),
+			hash
+		);
+		assert_eq!(
+			parse_sha256("abc").unwrap_err().to_string(),
+			"invalid SHA-256 checksum \"abc\", expected 64 hex characters"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_local() -> Result<()> {
+		let filename = "../testdata/berlin.mbtiles";
+
+		let input = verify_input(filename, None, false).await?;
+		assert_eq!(input.filename(), filename);
+
+		let input = verify_input(filename, Some(BERLIN_SHA256), false).await?;
+		assert_eq!(input.filename(), filename);
+
+		let wrong = "0".repeat(64);
+		let error = verify_input(filename, Some(&wrong), false).await.unwrap_err();
+		assert!(error.to_string().starts_with(&format!(
+			"SHA-256 checksum mismatch for \"{filename}\": expected {wrong}, but got {BERLIN_SHA256}"
+		)));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_local_sidecar() -> Result<()> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("berlin.mbtiles");
+		std::fs::copy("../testdata/berlin.mbtiles", &filename)?;
+		let filename = filename.to_str().unwrap();
+
+		let error = verify_input(filename, None, true).await.unwrap_err();
+		assert!(error.to_string().starts_with("reading checksum file"));
+
+		std::fs::write(
+			format!("{filename}.sha256"),
+			format!("{BERLIN_SHA256}  berlin.mbtiles\n"),
+		)?;
+		assert_eq!(verify_input(filename, None, true).await?.filename(), filename);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_download() -> Result<()> {
+		let dir = TempDir::new()?;
+		std::fs::copy("../testdata/berlin.mbtiles", dir.path().join("berlin.mbtiles"))?;
+		std::fs::write(dir.path().join("berlin.mbtiles.sha256"), BERLIN_SHA256)?;
+
+		let mut server = TileServer::new("127.0.0.1", 50010, false, false);
+		server.add_static_source(dir.path(), ServerUrl::new("/"))?;
+		server.start().await?;
+
+		let url = "http://127.0.0.1:50010/berlin.mbtiles";
+
+		let input = verify_input(url, None, true).await?;
+		let path = Path::new(input.filename()).to_path_buf();
+		assert!(path.to_str().unwrap().ends_with("-berlin.mbtiles"));
+		assert_eq!(hash_file(input.filename())?, BERLIN_SHA256);
+		drop(input);
+		assert!(!path.exists());
+
+		let wrong = "f".repeat(64);
+		let error = verify_input(url, Some(&wrong), false).await.unwrap_err();
+		assert!(error.to_string().starts_with("SHA-256 checksum mismatch for"));
+
+		server.stop().await;
+		Ok(())
+	}
+}
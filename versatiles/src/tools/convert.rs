@@ -1,19 +1,35 @@
-use anyhow::{bail, Result};
-use versatiles::types::GeoBBox;
-use versatiles_container::{convert_tiles_container, get_reader, TilesConverterParameters};
-use versatiles_core::types::{TileBBoxPyramid, TileCompression};
+use super::checksum::verify_input;
+use anyhow::{bail, ensure, Context, Result};
+use std::path::Path;
+use versatiles_container::{
+	convert_tiles_container, estimate_bbox_pyramid, get_reader_with_hints, ChecksumAlgorithm, CompressionLevels,
+	ConversionPreset, LevelCoverage, SelectionEstimate, TilesConvertReader, TilesConverterParameters, VersaTilesWriter,
+	VersaTilesWriterOptions,
+};
+use versatiles_core::{
+	types::{Blob, GeoBBox, TileBBoxPyramid, TileCompression, TileScheme, TileSelection, TilesReaderTrait},
+	utils::{ensure_available_space, parse_zoom_range},
+};
+use versatiles_geometry::PolygonFilter;
+use versatiles_image::ImageQuality;
 
 #[derive(clap::Args, Debug)]
 #[command(arg_required_else_help = true, disable_version_flag = true)]
 pub struct Subcommand {
-	/// supported container formats: *.versatiles, *.tar, *.pmtiles, *.mbtiles or a directory
+	/// supported container formats: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
 	#[arg()]
 	input_file: String,
 
-	/// supported container formats: *.versatiles, *.tar, *.pmtiles, *.mbtiles or a directory
-	#[arg()]
+	/// supported container formats: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory.
+	/// use "<directory>?layout=sharded" for huge directories, to keep at most 256 entries per folder
+	/// and "<directory>?index=true" to write an index of all tiles, so that reading the directory needs no scan
+	#[arg(verbatim_doc_comment)]
 	output_file: String,
 
+	/// convert only a single zoom level, same as --min-zoom and --max-zoom with the same value
+	#[arg(long, value_name = "int", conflicts_with_all = ["min_zoom", "max_zoom"], display_order = 1)]
+	zoom: Option<u8>,
+
 	/// minimum zoom level
 	#[arg(long, value_name = "int", display_order = 1)]
 	min_zoom: Option<u8>,
@@ -22,7 +38,20 @@ pub struct Subcommand {
 	#[arg(long, value_name = "int", display_order = 1)]
 	max_zoom: Option<u8>,
 
-	/// use only tiles inside a bounding box
+	/// coverage per zoom range, e.g. "0-5:world,6-14:germany.geojson" for global low and regional high zoom levels.
+	/// a coverage is "world", a bounding box "lon_min,lat_min,lon_max,lat_max" or the bounding box of a GeoJSON file.
+	/// can also be a JSON file like {"0-5":"world","6-14":"germany.geojson"}
+	#[arg(
+		long,
+		value_name = "zoom_range:coverage,...",
+		allow_hyphen_values = true,
+		display_order = 1
+	)]
+	levels: Option<String>,
+
+	/// use only tiles inside a bounding box.
+	/// can be repeated to use the tiles inside any of the bounding boxes.
+	/// if lon_min > lon_max, the bounding box crosses the antimeridian, e.g. "177,-19,-178,-16" for Fiji
 	#[arg(
 		long,
 		short,
@@ -30,16 +59,57 @@ pub struct Subcommand {
 		allow_hyphen_values = true,
 		display_order = 1
 	)]
-	bbox: Option<String>,
+	bbox: Vec<String>,
 
 	/// also include additional tiles surrounding the bounding box as a border
 	#[arg(long, value_name = "int", display_order = 1)]
 	bbox_border: Option<u32>,
 
+	/// use a different bounding box for a zoom range, e.g. "0-10:5.8,47.2,15.1,55.1" for a whole country
+	/// and "11-:13.0,52.3,13.8,52.7" for a city. can be repeated, zoom levels without a bounding box are removed
+	#[arg(
+		long,
+		value_name = "zoom_range:lon_min,lat_min,lon_max,lat_max",
+		allow_hyphen_values = true,
+		display_order = 1
+	)]
+	bbox_zoom: Vec<String>,
+
+	/// remove all tiles that are completely inside this bounding box, e.g. to cut out a region.
+	/// can be repeated
+	#[arg(
+		long,
+		value_name = "lon_min,lat_min,lon_max,lat_max",
+		allow_hyphen_values = true,
+		display_order = 1
+	)]
+	exclude_bbox: Vec<String>,
+
+	/// only keep tiles overlapping the polygons of a GeoJSON file, e.g. the outline of a country,
+	/// instead of a rectangular bounding box
+	#[arg(long, value_name = "file", display_order = 1)]
+	filter_polygon: Option<String>,
+
+	/// use recommended settings: "web-raster" stores raster tiles uncompressed,
+	/// "vector-cdn" compresses vector tiles with brotli for static hosting.
+	/// other compression options override the settings of the preset
+	#[arg(long, value_name = "name", value_parser = ConversionPreset::parse_str, display_order = 2)]
+	preset: Option<ConversionPreset>,
+
 	/// set new compression
 	#[arg(long, short, value_enum, display_order = 2)]
 	compress: Option<TileCompression>,
 
+	/// compression levels per zoom range, e.g. "0-8:11,9-12:8,13-:5" for brotli (0-11) or gzip (0-9).
+	/// tiles of these zoom levels are always recompressed
+	#[arg(long, value_name = "zoom_range:level,...", display_order = 2)]
+	compress_levels: Option<String>,
+
+	/// image quality per zoom range, from 0 (smallest) to 100 (best), e.g. "0-9:90,10-:75".
+	/// jpg and webp tiles of these zoom levels are encoded again
+	#[arg(long, value_name = "zoom_range:quality,...", display_order = 2)]
+	quality: Option<String>,
+
 	/// force recompression, e.g. to improve an existing gzip compression
 	#[arg(long, short, display_order = 2)]
 	force_recompress: bool,
@@ -55,37 +125,217 @@ pub struct Subcommand {
 	/// flip input vertically
 	#[arg(long, display_order = 3)]
 	flip_y: bool,
+
+	/// order of the tile rows in the output: "tms" counts rows from the south.
+	/// only supported when writing a directory or a tar file
+	#[arg(long, value_enum, default_value_t = TileScheme::Xyz, display_order = 3)]
+	scheme: TileScheme,
+
+	/// verify the input file against this SHA-256 checksum before converting.
+	/// remote files are downloaded and checked first
+	#[arg(long, value_name = "hex", display_order = 4)]
+	expected_sha256: Option<String>,
+
+	/// verify the input file against the SHA-256 checksum in "<input_file>.sha256"
+	#[arg(long, conflicts_with = "expected_sha256", display_order = 4)]
+	sha256_sidecar: bool,
+
+	/// add checksums of all blocks to a *.versatiles output, so that "versatiles verify" can detect corruption.
+	/// "xxhash" is fast, "sha256" can also be signed
+	#[arg(long, value_name = "algorithm", value_parser = ChecksumAlgorithm::parse_str, display_order = 4)]
+	checksum: Option<ChecksumAlgorithm>,
+
+	/// sign the checksums of a *.versatiles output with an Ed25519 key in PKCS#8 DER format, e.g. created by
+	/// "openssl genpkey -algorithm ed25519 -outform DER -out key.der". implies "--checksum sha256"
+	#[arg(long, value_name = "file", display_order = 4)]
+	sign_key: Option<String>,
+
+	/// store identical tiles of a *.versatiles output only once across the whole container, e.g. empty ocean tiles.
+	/// the output can only be read by versions supporting deduplication
+	#[arg(long, display_order = 4)]
+	deduplicate: bool,
+
+	/// allow zstd compressed tiles in a *.versatiles output.
+	/// zstd is an extension of the specification, so the output can only be read by versions supporting it
+	#[arg(long, display_order = 4)]
+	allow_zstd: bool,
+
+	/// only print the number and size of the selected tiles, using the index of the input container,
+	/// without reading tiles or writing the output
+	#[arg(long, display_order = 5)]
+	dry_run: bool,
 }
 
 #[tokio::main]
 pub async fn run(arguments: &Subcommand) -> Result<()> {
-	eprintln!("convert from {:?} to {:?}", arguments.input_file, arguments.output_file);
-
-	let mut reader = get_reader(&arguments.input_file).await?;
-
-	if arguments.override_input_compression.is_some() {
-		reader.override_compression(arguments.override_input_compression.unwrap());
-	}
-
-	let cp = TilesConverterParameters::new(
+	super::print_status(&format!(
+		"convert from {:?} to {:?}",
+		arguments.input_file, arguments.output_file
+	));
+
+	let input = verify_input(
+		&arguments.input_file,
+		arguments.expected_sha256.as_deref(),
+		arguments.sha256_sidecar,
+	)
+	.await?;
+
+	let selection = get_tile_selection(arguments)?;
+	let mut cp = TilesConverterParameters::new(
 		arguments.compress,
-		get_bbox_pyramid(arguments)?,
+		get_bbox_pyramid(arguments, &selection)?,
 		arguments.force_recompress,
 		arguments.flip_y,
 		arguments.swap_xy,
 	);
-	convert_tiles_container(reader, cp, &arguments.output_file).await?;
+	if selection.needs_filter() {
+		cp.tile_selection = Some(selection);
+	}
+	if !arguments.bbox_zoom.is_empty() {
+		cp.intersect_level_coverage(&get_bbox_zoom_coverage(arguments)?)?;
+	}
+	if let Some(filename) = &arguments.filter_polygon {
+		cp.set_polygon_filter(PolygonFilter::from_geojson_file(Path::new(filename))?);
+	}
+	cp.tile_scheme = arguments.scheme;
+	if let Some(preset) = arguments.preset {
+		preset.apply(&mut cp);
+		if let Some(compression) = arguments.compress {
+			// the levels of the preset belong to its compression
+			cp.tile_compression = Some(compression);
+			cp.compression_levels = CompressionLevels::default();
+		}
+		cp.force_recompress |= arguments.force_recompress;
+	}
+	if let Some(levels) = &arguments.compress_levels {
+		cp.compression_levels = CompressionLevels::parse_str(levels)?;
+	}
+	if let Some(quality) = &arguments.quality {
+		cp.image_quality = ImageQuality::parse_str(quality)?;
+	}
+
+	// let the reader skip everything outside of the requested zoom levels and bbox
+	let mut reader = get_reader_with_hints(input.filename(), &cp.get_read_hints()).await?;
+
+	if let Some(compression) = arguments.override_input_compression {
+		reader.override_compression(compression);
+	}
 
+	if let Some(preset) = arguments.preset {
+		preset.check_tile_format(reader.get_parameters().tile_format)?;
+		if !preset.is_recommended_output(&arguments.output_file) {
+			log::warn!(
+				"preset \"{}\" recommends an output file ending in {}",
+				preset.as_str(),
+				preset.get_recommended_extensions().join(" or ")
+			);
+		}
+	}
+
+	if arguments.dry_run {
+		let pyramid = cp.bbox_pyramid.clone().unwrap_or_else(|| TileBBoxPyramid::new_full(32));
+		println!("{}", format_estimate(&estimate_bbox_pyramid(&*reader, &pyramid).await?));
+		if cp.tile_selection.is_some() {
+			println!("the estimate covers the bounding box of all --bbox arguments, ignoring gaps and --exclude-bbox");
+		}
+		if cp.polygon_filter.is_some() {
+			println!("the estimate covers the bounding box of --filter-polygon");
+		}
+		return Ok(());
+	}
+
+	check_disk_space(&*reader, &cp, &arguments.output_file).await?;
+
+	let versatiles_options =
+		arguments.checksum.is_some() || arguments.sign_key.is_some() || arguments.deduplicate || arguments.allow_zstd;
+	if !versatiles_options {
+		convert_tiles_container(reader, cp, &arguments.output_file).await?;
+		return Ok(());
+	}
+
+	ensure!(
+		arguments.output_file.ends_with(".versatiles"),
+		"checksums, signatures, deduplication and --allow-zstd are only supported by *.versatiles containers"
+	);
+	let options = VersaTilesWriterOptions {
+		checksum: arguments.checksum,
+		signing_key: match &arguments.sign_key {
+			Some(filename) => Some(std::fs::read(filename).with_context(|| format!("Failed to read key {filename:?}"))?),
+			None => None,
+		},
+		deduplicate: arguments.deduplicate,
+		allow_zstd: arguments.allow_zstd,
+		..Default::default()
+	};
+	let mut converter = TilesConvertReader::new_from_reader(reader, cp)?;
+	VersaTilesWriter::write_to_path_with_options(&mut converter, Path::new(&arguments.output_file), &options).await?;
+
+	if let Some(public_key) = options.get_public_key()? {
+		super::print_status(&format!(
+			"signed with the public key {}",
+			Blob::from(public_key).as_hex()
+		));
+	}
+
+	Ok(())
+}
+
+/// Fails early if the output will not fit on the disk. The size is only known if the input has an index
+/// and the tiles are not recompressed.
+async fn check_disk_space(
+	reader: &dyn TilesReaderTrait,
+	cp: &TilesConverterParameters,
+	output_file: &str,
+) -> Result<()> {
+	let input_compression = reader.get_parameters().tile_compression;
+	if cp.force_recompress || cp.tile_compression.is_some_and(|c| c != input_compression) {
+		return Ok(());
+	}
+	let pyramid = cp.bbox_pyramid.clone().unwrap_or_else(|| TileBBoxPyramid::new_full(32));
+	if let Some(bytes) = estimate_bbox_pyramid(reader, &pyramid).await?.tile_bytes() {
+		ensure_available_space(Path::new(output_file), bytes, "the output")?;
+	}
 	Ok(())
 }
 
-fn get_bbox_pyramid(arguments: &Subcommand) -> Result<Option<TileBBoxPyramid>> {
-	if arguments.min_zoom.is_none() && arguments.max_zoom.is_none() && arguments.bbox.is_none() {
+fn format_estimate(estimate: &SelectionEstimate) -> String {
+	let format_bytes = |bytes: Option<u64>| bytes.map_or(String::from("unknown size"), |b| format!("{b} bytes"));
+	let mut lines: Vec<String> = estimate
+		.levels
+		.iter()
+		.map(|l| format!("z{}: {} tiles, {}", l.level, l.tile_count, format_bytes(l.tile_bytes)))
+		.collect();
+	lines.push(format!(
+		"total: {} tiles, {}",
+		estimate.tile_count(),
+		format_bytes(estimate.tile_bytes())
+	));
+	if !estimate.is_exact() {
+		lines.push(String::from(
+			"the input has no index, so the number of tiles covered by the selection is shown",
+		));
+	}
+	lines.join("\n")
+}
+
+fn get_bbox_pyramid(arguments: &Subcommand, selection: &TileSelection) -> Result<Option<TileBBoxPyramid>> {
+	let selection_pyramid = selection.get_bbox_pyramid();
+	if arguments.zoom.is_none()
+		&& arguments.min_zoom.is_none()
+		&& arguments.max_zoom.is_none()
+		&& arguments.levels.is_none()
+		&& selection_pyramid.is_none()
+	{
 		return Ok(None);
 	}
 
 	let mut bbox_pyramid = TileBBoxPyramid::new_full(32);
 
+	if let Some(zoom) = arguments.zoom {
+		bbox_pyramid.set_zoom_min(zoom);
+		bbox_pyramid.set_zoom_max(zoom);
+	}
+
 	if let Some(min_zoom) = arguments.min_zoom {
 		bbox_pyramid.set_zoom_min(min_zoom)
 	}
@@ -94,33 +344,120 @@ fn get_bbox_pyramid(arguments: &Subcommand) -> Result<Option<TileBBoxPyramid>> {
 		bbox_pyramid.set_zoom_max(max_zoom)
 	}
 
-	if let Some(bbox) = &arguments.bbox {
-		log::trace!("parsing bbox argument: {:?}", bbox);
-		let values: Vec<f64> = bbox
-			.split(&[' ', ',', ';'])
-			.filter(|s| !s.is_empty())
-			.map(|s| s.parse::<f64>().expect("bbox value is not a number"))
-			.collect();
+	if let Some(levels) = &arguments.levels {
+		bbox_pyramid.intersect(&LevelCoverage::parse_str(levels)?.get_bbox_pyramid()?);
+	}
 
-		if values.len() != 4 {
-			bail!("bbox must contain exactly 4 numbers, but instead i'v got: {bbox:?}");
-		}
+	if let Some(selection_pyramid) = selection_pyramid {
+		bbox_pyramid.intersect(&selection_pyramid);
+	}
 
-		bbox_pyramid.intersect_geo_bbox(&GeoBBox::try_from(values)?);
+	Ok(Some(bbox_pyramid))
+}
 
-		if let Some(b) = arguments.bbox_border {
-			bbox_pyramid.add_border(b, b, b, b);
+/// Combines all `--bbox` arguments (union) and `--exclude-bbox` arguments (subtraction).
+fn get_tile_selection(arguments: &Subcommand) -> Result<TileSelection> {
+	let mut selection = TileSelection::default();
+
+	for bbox in arguments.bbox.iter() {
+		// a bbox crossing the antimeridian is selected as two parts, one on each side
+		for part in parse_bbox(bbox)?.split_antimeridian() {
+			let mut pyramid = TileBBoxPyramid::new_full(32);
+			pyramid.intersect_geo_bbox(&part);
+			if let Some(b) = arguments.bbox_border {
+				pyramid.add_border(b, b, b, b);
+			}
+			selection.include.push(pyramid);
 		}
 	}
 
-	Ok(Some(bbox_pyramid))
+	for bbox in arguments.exclude_bbox.iter() {
+		selection.exclude.push(parse_bbox(bbox)?);
+	}
+
+	Ok(selection)
+}
+
+/// Combines all `--bbox-zoom` arguments into a coverage per zoom range.
+fn get_bbox_zoom_coverage(arguments: &Subcommand) -> Result<LevelCoverage> {
+	let mut coverage = LevelCoverage::default();
+	for argument in arguments.bbox_zoom.iter() {
+		let Some((zooms, bbox)) = argument.split_once(':') else {
+			bail!("bbox-zoom {argument:?} must have the form \"zoom_range:lon_min,lat_min,lon_max,lat_max\"");
+		};
+		coverage.add_bbox(parse_zoom_range(zooms.trim())?, parse_bbox(bbox)?)?;
+	}
+	Ok(coverage)
+}
+
+fn parse_bbox(bbox: &str) -> Result<GeoBBox> {
+	log::trace!("parsing bbox argument: {:?}", bbox);
+	let values: Vec<f64> = bbox
+		.split(&[' ', ',', ';'])
+		.filter(|s| !s.is_empty())
+		.map(|s| {
+			s.parse::<f64>()
+				.with_context(|| format!("bbox value {s:?} is not a number"))
+		})
+		.collect::<Result<_>>()?;
+
+	if values.len() != 4 {
+		bail!("bbox must contain exactly 4 numbers, but instead i'v got: {bbox:?}");
+	}
+
+	let bbox = GeoBBox::try_from(values)?;
+	bbox.check()?;
+	Ok(bbox)
 }
 
 #[cfg(test)]
 mod tests {
+	use super::*;
 	use crate::tests::run_command;
 	use anyhow::Result;
 	use std::fs;
+	use versatiles_container::LevelEstimate;
+	use versatiles_core::types::TileCoord3;
+
+	#[test]
+	fn test_dry_run() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let output = dir.path().join("berlin.versatiles");
+		run_command(vec![
+			"versatiles",
+			"convert",
+			"--dry-run",
+			"--max-zoom=10",
+			"../testdata/berlin.mbtiles",
+			output.to_str().unwrap(),
+		])?;
+		assert!(!output.exists());
+		Ok(())
+	}
+
+	#[test]
+	fn test_format_estimate() {
+		let level = |level: u8, tile_count: u64, tile_bytes: Option<u64>| LevelEstimate {
+			level,
+			tile_count,
+			tile_bytes,
+		};
+		let estimate = SelectionEstimate {
+			levels: vec![level(0, 1, Some(100)), level(1, 3, Some(250))],
+		};
+		assert_eq!(
+			format_estimate(&estimate),
+			"z0: 1 tiles, 100 bytes\nz1: 3 tiles, 250 bytes\ntotal: 4 tiles, 350 bytes"
+		);
+
+		let estimate = SelectionEstimate {
+			levels: vec![level(0, 1, None)],
+		};
+		assert_eq!(
+			format_estimate(&estimate),
+			"z0: 1 tiles, unknown size\ntotal: 1 tiles, unknown size\nthe input has no index, so the number of tiles covered by the selection is shown"
+		);
+	}
 
 	#[test]
 	fn test_local() -> Result<()> {
@@ -155,6 +492,435 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_scheme() -> Result<()> {
+		fs::create_dir("../tmp/").unwrap_or_default();
+
+		run_command(vec![
+			"versatiles",
+			"convert",
+			"--max-zoom=3",
+			"--scheme=tms",
+			"../testdata/berlin.mbtiles",
+			"../tmp/berlin_tms.tar",
+		])?;
+
+		let error = run_command(vec![
+			"versatiles",
+			"convert",
+			"--max-zoom=3",
+			"--scheme=tms",
+			"../testdata/berlin.mbtiles",
+			"../tmp/berlin_tms.versatiles",
+		])
+		.unwrap_err();
+		assert!(
+			format!("{error:#}").contains("tms scheme can only be written"),
+			"{error:#}"
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_single_zoom() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let tar = dir.path().join("berlin.tar");
+		let versatiles = dir.path().join("berlin.versatiles");
+
+		let convert = |input: String, output: String| {
+			std::thread::spawn(move || {
+				run_command(vec!["versatiles", "convert", "--zoom=10", &input, &output]).unwrap();
+			})
+			.join()
+			.unwrap()
+		};
+		convert(
+			String::from("../testdata/berlin.mbtiles"),
+			tar.to_str().unwrap().to_string(),
+		);
+		convert(
+			tar.to_str().unwrap().to_string(),
+			versatiles.to_str().unwrap().to_string(),
+		);
+
+		let reader = versatiles_container::get_reader(versatiles.to_str().unwrap()).await?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[10: [549,335,551,336] (6)]"
+		);
+
+		let error = run_command(vec![
+			"versatiles",
+			"convert",
+			"--zoom=10",
+			"--min-zoom=5",
+			"a.tar",
+			"b.tar",
+		])
+		.unwrap_err();
+		assert!(error.to_string().contains("cannot be used with"), "{error}");
+
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_multiple_bboxes() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+
+		// at zoom 10, berlin.mbtiles contains the tiles x = 549..=551, y = 335..=336
+		async fn convert(dir: &assert_fs::TempDir, name: &str, args: &[&str]) -> Result<Vec<String>> {
+			let output = dir.path().join(name).to_str().unwrap().to_string();
+			let mut command: Vec<String> = ["versatiles", "convert", "--zoom=10"].map(String::from).to_vec();
+			command.extend(args.iter().map(|a| a.to_string()));
+			command.push(String::from("../testdata/berlin.mbtiles"));
+			command.push(output.clone());
+			std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()).unwrap())
+				.join()
+				.unwrap();
+
+			let reader = versatiles_container::get_reader(&output).await?;
+			let bbox = reader.get_parameters().bbox_pyramid.get_level_bbox(10).clone();
+			let tiles = reader.get_bbox_tile_stream(bbox).await.collect().await;
+			let mut coords: Vec<String> = tiles.iter().map(|(c, _)| format!("{},{}", c.x, c.y)).collect();
+			coords.sort();
+			Ok(coords)
+		}
+
+		assert_eq!(
+			convert(
+				&dir,
+				"union.versatiles",
+				&["--bbox=13.1,52.3,13.2,52.4", "--bbox=13.8,52.6,13.9,52.65"]
+			)
+			.await?,
+			vec!["549,336", "551,335"]
+		);
+		assert_eq!(
+			convert(&dir, "exclude.versatiles", &["--exclude-bbox=13.3,52,13.8,53"]).await?,
+			vec!["549,335", "549,336", "551,335", "551,336"]
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_levels() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let output = dir.path().join("levels.versatiles").to_str().unwrap().to_string();
+
+		let command = [
+			"versatiles",
+			"convert",
+			"--levels=0-2:world,10:13.1,52.3,13.2,52.4,11-12:13.3,52.4,13.4,52.5",
+			"--max-zoom=11",
+			"../testdata/berlin.mbtiles",
+			&output,
+		]
+		.map(String::from);
+		std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()).unwrap())
+			.join()
+			.unwrap();
+
+		let reader = versatiles_container::get_reader(&output).await?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[0: [0,0,0,0] (1), 1: [1,0,1,0] (1), 2: [2,1,2,1] (1), 10: [549,336,549,336] (1), 11: [1099,671,1100,672] (4)]"
+		);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_bbox_zoom() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let output = dir.path().join("staged.versatiles").to_str().unwrap().to_string();
+		let convert = |bbox_zooms: &[&str], output: String| {
+			let mut command: Vec<String> = ["versatiles", "convert", "--max-zoom=12"].map(String::from).to_vec();
+			command.extend(bbox_zooms.iter().map(|b| format!("--bbox-zoom={b}")));
+			command.extend(["../testdata/berlin.mbtiles".to_string(), output]);
+			std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()))
+				.join()
+				.unwrap()
+		};
+
+		convert(&["0-10:13.1,52.3,13.8,52.7", "11-:13.3,52.4,13.4,52.5"], output.clone())?;
+		let reader = versatiles_container::get_reader(&output).await?;
+		let bbox_pyramid = &reader.get_parameters().bbox_pyramid;
+		assert_eq!(
+			format!("{:?}", bbox_pyramid.get_level_bbox(10)),
+			"10: [549,335,551,336] (6)"
+		);
+		assert_eq!(
+			format!("{:?}", bbox_pyramid.get_level_bbox(11)),
+			"11: [1099,671,1100,672] (4)"
+		);
+
+		assert_eq!(
+			convert(&["0-10"], output).unwrap_err().to_string(),
+			"bbox-zoom \"0-10\" must have the form \"zoom_range:lon_min,lat_min,lon_max,lat_max\""
+		);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_filter_polygon() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let polygon = dir.path().join("polygon.geojson");
+		// a triangle in the center of Berlin
+		std::fs::write(
+			&polygon,
+			r#"{"type":"FeatureCollection","features":[{"type":"Feature","properties":{},"geometry":{"type":"Polygon","coordinates":[[[13.3,52.4],[13.5,52.4],[13.3,52.6],[13.3,52.4]]]}}]}"#,
+		)?;
+		let output = dir.path().join("polygon.versatiles").to_str().unwrap().to_string();
+
+		let command = [
+			"versatiles",
+			"convert",
+			"--min-zoom=11",
+			"--max-zoom=12",
+			&format!("--filter-polygon={}", polygon.to_str().unwrap()),
+			"../testdata/berlin.mbtiles",
+			&output,
+		]
+		.map(String::from);
+		std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()).unwrap())
+			.join()
+			.unwrap();
+
+		let reader = versatiles_container::get_reader(&output).await?;
+		let bbox_pyramid = reader.get_parameters().bbox_pyramid.clone();
+		assert_eq!(
+			format!("{:?}", bbox_pyramid.get_level_bbox(11)),
+			"11: [1099,670,1100,672] (6)"
+		);
+		let tiles: Vec<_> = reader
+			.get_bbox_tile_stream(bbox_pyramid.get_level_bbox(12).clone())
+			.await
+			.collect()
+			.await;
+		assert!(tiles.len() < bbox_pyramid.get_level_bbox(12).count_tiles() as usize);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_preset() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let output = dir.path().join("cdn.versatiles").to_str().unwrap().to_string();
+		let convert = |preset: &str, output: String| {
+			let preset = format!("--preset={preset}");
+			std::thread::spawn(move || {
+				run_command(vec![
+					"versatiles",
+					"convert",
+					"--min-zoom=13",
+					&preset,
+					"../testdata/berlin.mbtiles",
+					&output,
+				])
+			})
+			.join()
+			.unwrap()
+		};
+
+		convert("vector-cdn", output.clone())?;
+		let reader = versatiles_container::get_reader(&output).await?;
+		assert_eq!(reader.get_parameters().tile_compression, TileCompression::Brotli);
+
+		let error = convert("web-raster", output).unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"preset \"web-raster\" does not support tiles in format pbf"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_compress_levels() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let convert = |name: &str, levels: &str| -> Result<u64> {
+			let output = dir.path().join(name);
+			run_command(vec![
+				"versatiles",
+				"convert",
+				"--min-zoom=12",
+				"--compress=gzip",
+				&format!("--compress-levels={levels}"),
+				"../testdata/berlin.mbtiles",
+				output.to_str().unwrap(),
+			])?;
+			Ok(fs::metadata(output)?.len())
+		};
+		assert!(convert("fast.versatiles", "0")? > convert("small.versatiles", "9")?);
+
+		let error = convert("error.versatiles", "0-8:11").unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"gzip compression level must be between 0 and 9, but is 11"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_quality() {
+		let error = run_command(vec![
+			"versatiles",
+			"convert",
+			"--min-zoom=14",
+			"--quality=0-9:90,10-:75",
+			"../testdata/berlin.mbtiles",
+			"../testdata/temp_quality.versatiles",
+		])
+		.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"quality is only supported for jpg and webp tiles, not for pbf"
+		);
+	}
+
+	#[test]
+	fn test_parse_bbox() {
+		assert_eq!(parse_bbox("-1, 2;3 4").unwrap(), GeoBBox(-1.0, 2.0, 3.0, 4.0));
+		assert_eq!(
+			parse_bbox("1,2,3,x").unwrap_err().to_string(),
+			"bbox value \"x\" is not a number"
+		);
+		assert!(parse_bbox("1,2,3").is_err());
+		assert_eq!(
+			parse_bbox("1,2,3,95").unwrap_err().to_string(),
+			"y_max (95) must be <= 90"
+		);
+		assert!(parse_bbox("177,-19,-178,-16").unwrap().crosses_antimeridian());
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_bbox_antimeridian() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let input = dir.path().join("debug.vpl").to_str().unwrap().to_string();
+		let output = dir.path().join("fiji.versatiles").to_str().unwrap().to_string();
+		fs::write(&input, "from_debug format=pbf")?;
+
+		let command = [
+			String::from("versatiles"),
+			String::from("convert"),
+			String::from("--zoom=3"),
+			String::from("--bbox=177,-19,-178,-16"),
+			input,
+			output.clone(),
+		];
+		std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()).unwrap())
+			.join()
+			.unwrap();
+
+		let reader = versatiles_container::get_reader(&output).await?;
+		let bbox = reader.get_parameters().bbox_pyramid.get_level_bbox(3).clone();
+		let tiles = reader.get_bbox_tile_stream(bbox).await.collect().await;
+		let mut coords: Vec<String> = tiles.iter().map(|(c, _)| format!("{},{}", c.x, c.y)).collect();
+		coords.sort();
+		assert_eq!(coords, vec!["0,4", "7,4"]);
+
+		let bounds = reader.get_tilejson().bounds.unwrap();
+		assert_eq!(
+			bounds.as_array().map(|v| (v * 1e6).round() / 1e6),
+			[177.0, -19.0, -178.0, -16.0]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_checksum() {
+		fs::create_dir("../tmp/").unwrap_or_default();
+
+		let hash = "94a5b47127f26608bc5e0e74300b197b01c732d66ba62c34783ca5b83a8bfb38";
+		run_command(vec![
+			"versatiles",
+			"convert",
+			"--expected-sha256",
+			hash,
+			"../testdata/berlin.mbtiles",
+			"../tmp/berlin4.versatiles",
+		])
+		.unwrap();
+
+		let error = run_command(vec![
+			"versatiles",
+			"convert",
+			"--expected-sha256",
+			&hash.replace('9', "0"),
+			"../testdata/berlin.mbtiles",
+			"../tmp/berlin5.versatiles",
+		])
+		.unwrap_err();
+		assert!(error.to_string().starts_with("SHA-256 checksum mismatch"), "{error}");
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_deduplicate() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let convert = |args: &[&str], filename: &str| {
+			let output = dir.path().join(filename).to_str().unwrap().to_string();
+			let mut command: Vec<String> = ["versatiles", "convert"].map(String::from).to_vec();
+			command.extend(args.iter().map(|a| a.to_string()));
+			command.extend(["../testdata/berlin.mbtiles".to_string(), output.clone()]);
+			std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()))
+				.join()
+				.unwrap()
+				.map(|_| output)
+		};
+
+		let plain = convert(&[], "plain.versatiles")?;
+		let deduplicated = convert(&["--deduplicate"], "deduplicated.versatiles")?;
+		assert!(fs::metadata(&deduplicated)?.len() <= fs::metadata(&plain)?.len());
+
+		let reader = versatiles_container::get_reader(&deduplicated).await?;
+		let coord = TileCoord3::new(8802, 5373, 14)?;
+		assert!(reader.get_tile_data(&coord).await?.is_some());
+		assert_eq!(
+			reader.get_tile_data(&coord).await?,
+			versatiles_container::get_reader(&plain)
+				.await?
+				.get_tile_data(&coord)
+				.await?
+		);
+
+		assert_eq!(
+			convert(&["--deduplicate"], "deduplicated.mbtiles")
+				.unwrap_err()
+				.to_string(),
+			"checksums, signatures, deduplication and --allow-zstd are only supported by *.versatiles containers"
+		);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_zstd() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let convert = |args: &[&str], filename: &str| {
+			let output = dir.path().join(filename).to_str().unwrap().to_string();
+			let mut command: Vec<String> = ["versatiles", "convert", "--min-zoom=12", "--compress=zstd"]
+				.map(String::from)
+				.to_vec();
+			command.extend(args.iter().map(|a| a.to_string()));
+			command.extend(["../testdata/berlin.mbtiles".to_string(), output.clone()]);
+			std::thread::spawn(move || run_command(command.iter().map(|s| s.as_str()).collect()))
+				.join()
+				.unwrap()
+				.map(|_| output)
+		};
+
+		let pmtiles = convert(&[], "zstd.pmtiles")?;
+		let reader = versatiles_container::get_reader(&pmtiles).await?;
+		assert_eq!(reader.get_parameters().tile_compression, TileCompression::Zstd);
+
+		assert_eq!(
+			convert(&[], "zstd.versatiles").unwrap_err().to_string(),
+			"zstd compression is an extension of the VersaTiles specification and must be allowed explicitly"
+		);
+		let versatiles = convert(&["--allow-zstd"], "zstd.versatiles")?;
+		let reader = versatiles_container::get_reader(&versatiles).await?;
+		assert_eq!(reader.get_parameters().tile_compression, TileCompression::Zstd);
+		Ok(())
+	}
+
 	#[test]
 
 	fn test_remote1() {
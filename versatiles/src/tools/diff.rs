@@ -0,0 +1,327 @@
+use anyhow::{ensure, Context, Result};
+use std::{
+	fs::File,
+	io::{stdout, BufWriter, Write},
+};
+use versatiles_container::get_reader;
+use versatiles_core::{
+	json::JsonObject,
+	types::{Blob, TileBBox, TileBBoxPyramid, TileCoord3, TileFormat, TileMap, TilesReaderTrait},
+	utils::decompress,
+};
+use versatiles_geometry::{vector_tile::VectorTile, GeoProperties, GeoValue, Geometry};
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// container with the old tiles
+	/// supported container formats are: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
+	#[arg(required = true, verbatim_doc_comment)]
+	old_file: String,
+
+	/// container with the new tiles
+	#[arg(required = true)]
+	new_file: String,
+
+	/// write the coordinates of all added, removed and changed tiles as NDJSON to this file,
+	/// use "-" for stdout
+	#[arg(long, value_name = "FILE", verbatim_doc_comment)]
+	ndjson: Option<String>,
+
+	/// compare the decoded layers and features of vector tiles instead of their bytes,
+	/// so that re-encoded but identical tiles are not reported as changed
+	#[arg(long, verbatim_doc_comment)]
+	decode: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Change {
+	Added,
+	Removed,
+	Changed,
+}
+
+impl Change {
+	fn as_str(&self) -> &str {
+		match self {
+			Change::Added => "added",
+			Change::Removed => "removed",
+			Change::Changed => "changed",
+		}
+	}
+}
+
+/// Number of tiles per kind of change of one zoom level
+#[derive(Clone, Debug, Default, PartialEq)]
+struct DiffCounts {
+	added: u64,
+	removed: u64,
+	changed: u64,
+	unchanged: u64,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	let to_stdout = arguments.ndjson.as_deref() == Some("-");
+	let print = |text: String| {
+		if to_stdout {
+			eprintln!("{text}")
+		} else {
+			println!("{text}")
+		}
+	};
+
+	super::print_status(&format!("diff {:?} and {:?}", arguments.old_file, arguments.new_file));
+
+	let old_reader = get_reader(&arguments.old_file).await?;
+	let new_reader = get_reader(&arguments.new_file).await?;
+
+	let mut list: Option<Box<dyn Write>> = match arguments.ndjson.as_deref() {
+		None => None,
+		Some("-") => Some(Box::new(stdout().lock())),
+		Some(filename) => Some(Box::new(BufWriter::new(
+			File::create(filename).with_context(|| format!("Failed to create {filename:?}"))?,
+		))),
+	};
+
+	let counts = diff_readers(
+		old_reader.as_ref(),
+		new_reader.as_ref(),
+		arguments.decode,
+		|coord, change| {
+			if let Some(list) = list.as_mut() {
+				let mut object = JsonObject::default();
+				object.set("z", coord.z);
+				object.set("x", coord.x);
+				object.set("y", coord.y);
+				object.set("change", change.as_str());
+				writeln!(list, "{}", object.stringify())?;
+			}
+			Ok(())
+		},
+	)
+	.await?;
+
+	if let Some(mut list) = list {
+		list.flush()?;
+	}
+
+	print(format!(
+		"{:>5} {:>10} {:>10} {:>10} {:>10}",
+		"zoom", "added", "removed", "changed", "unchanged"
+	));
+	let mut total = DiffCounts::default();
+	for (level, c) in counts.iter().enumerate() {
+		if c == &DiffCounts::default() {
+			continue;
+		}
+		print(format!(
+			"{level:>5} {:>10} {:>10} {:>10} {:>10}",
+			c.added, c.removed, c.changed, c.unchanged
+		));
+		total.added += c.added;
+		total.removed += c.removed;
+		total.changed += c.changed;
+		total.unchanged += c.unchanged;
+	}
+	print(format!(
+		"{:>5} {:>10} {:>10} {:>10} {:>10}",
+		"total", total.added, total.removed, total.changed, total.unchanged
+	));
+
+	Ok(())
+}
+
+/// Compares all tiles of both readers and calls `callback` for every added, removed or changed tile.
+/// Returns the number of tiles per zoom level and kind of change.
+async fn diff_readers(
+	old_reader: &dyn TilesReaderTrait,
+	new_reader: &dyn TilesReaderTrait,
+	decode: bool,
+	mut callback: impl FnMut(&TileCoord3, Change) -> Result<()>,
+) -> Result<Vec<DiffCounts>> {
+	let old_parameters = old_reader.get_parameters();
+	let new_parameters = new_reader.get_parameters();
+	if decode {
+		ensure!(
+			old_parameters.tile_format == TileFormat::PBF && new_parameters.tile_format == TileFormat::PBF,
+			"--decode only supports vector tiles"
+		);
+	}
+
+	let mut pyramid = TileBBoxPyramid::new_empty();
+	pyramid.include_bbox_pyramid(&old_parameters.bbox_pyramid);
+	pyramid.include_bbox_pyramid(&new_parameters.bbox_pyramid);
+
+	let mut counts = vec![DiffCounts::default(); 32];
+
+	for level_bbox in pyramid.iter_levels() {
+		let c = &mut counts[level_bbox.level as usize];
+		for bbox in level_bbox.iter_bbox_grid(256) {
+			let mut old_tiles = get_tiles(old_reader, &bbox).await?;
+			let new_tiles = get_tiles(new_reader, &bbox).await?;
+
+			for (coord, new_blob) in new_tiles.into_sorted_vec() {
+				let Some(old_blob) = old_tiles.remove(&coord) else {
+					c.added += 1;
+					callback(&coord, Change::Added)?;
+					continue;
+				};
+				let old_blob = decompress(old_blob, &old_parameters.tile_compression)?;
+				let new_blob = decompress(new_blob, &new_parameters.tile_compression)?;
+				if tiles_are_equal(&old_blob, &new_blob, decode)
+					.with_context(|| format!("Failed to compare tile {coord:?}"))?
+				{
+					c.unchanged += 1;
+				} else {
+					c.changed += 1;
+					callback(&coord, Change::Changed)?;
+				}
+			}
+
+			for (coord, _) in old_tiles.into_sorted_vec() {
+				c.removed += 1;
+				callback(&coord, Change::Removed)?;
+			}
+		}
+	}
+
+	Ok(counts)
+}
+
+async fn get_tiles(reader: &dyn TilesReaderTrait, bbox: &TileBBox) -> Result<TileMap<Blob>> {
+	let mut bbox = bbox.clone();
+	bbox.intersect_pyramid(&reader.get_parameters().bbox_pyramid)?;
+	if bbox.is_empty() {
+		return Ok(TileMap::new());
+	}
+	Ok(reader
+		.get_bbox_tile_stream(bbox)
+		.await
+		.collect()
+		.await
+		.into_iter()
+		.collect())
+}
+
+/// Compares two uncompressed tiles.
+fn tiles_are_equal(old_blob: &Blob, new_blob: &Blob, decode: bool) -> Result<bool> {
+	if old_blob == new_blob {
+		return Ok(true);
+	}
+	if !decode {
+		return Ok(false);
+	}
+	Ok(decode_vector_tile(old_blob)? == decode_vector_tile(new_blob)?)
+}
+
+type DecodedFeature = (Option<GeoValue>, Geometry, GeoProperties);
+
+/// Decodes a vector tile into its layers, sorted by name, since the order of layers has no meaning.
+fn decode_vector_tile(blob: &Blob) -> Result<Vec<(String, u32, Vec<DecodedFeature>)>> {
+	let tile = VectorTile::from_blob(blob)?;
+	let mut layers = tile
+		.layers
+		.iter()
+		.map(|layer| {
+			let features = layer
+				.to_features()?
+				.into_iter()
+				.map(|f| (f.id, f.geometry, f.properties))
+				.collect();
+			Ok((layer.name.clone(), layer.extent, features))
+		})
+		.collect::<Result<Vec<_>>>()?;
+	layers.sort_by(|a, b| a.0.cmp(&b.0));
+	Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::run_command;
+	use std::fs;
+
+	#[test]
+	fn test_tiles_are_equal() -> Result<()> {
+		let blob = Blob::from(fs::read("../testdata/shortbread-tile.pbf")?);
+		let mut tile = VectorTile::from_blob(&blob)?;
+		tile.layers.reverse();
+		let reordered = tile.to_blob()?;
+
+		assert!(tiles_are_equal(&blob, &blob, false)?);
+		assert!(!tiles_are_equal(&blob, &reordered, false)?);
+		assert!(tiles_are_equal(&blob, &reordered, true)?);
+
+		tile.layers.pop();
+		assert!(!tiles_are_equal(&blob, &tile.to_blob()?, true)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_diff() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+		// at zoom 10, berlin.mbtiles contains the tiles x = 549..=551, y = 335..=336
+		for (name, bbox, compress) in [
+			("old", "13.0,52.3,13.4,52.7", "gzip"),
+			("new", "13.5,52.3,13.8,52.7", "brotli"),
+		] {
+			run_command(vec![
+				"versatiles",
+				"convert",
+				"--zoom=10",
+				&format!("--bbox={bbox}"),
+				&format!("--compress={compress}"),
+				"../testdata/berlin.mbtiles",
+				&path(&format!("{name}.versatiles")),
+			])?;
+		}
+
+		run_command(vec![
+			"versatiles",
+			"diff",
+			&path("old.versatiles"),
+			&path("new.versatiles"),
+			&format!("--ndjson={}", path("diff.ndjson")),
+		])?;
+
+		let ndjson = fs::read_to_string(path("diff.ndjson"))?;
+		let lines: Vec<&str> = ndjson.lines().collect();
+		assert_eq!(
+			lines,
+			[
+				"{\"change\":\"added\",\"x\":551,\"y\":335,\"z\":10}",
+				"{\"change\":\"added\",\"x\":551,\"y\":336,\"z\":10}",
+				"{\"change\":\"removed\",\"x\":549,\"y\":335,\"z\":10}",
+				"{\"change\":\"removed\",\"x\":549,\"y\":336,\"z\":10}"
+			]
+		);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_diff_readers() -> Result<()> {
+		let old_reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let new_reader = get_reader("../testdata/berlin.pmtiles").await?;
+
+		let mut changes = 0;
+		let counts = diff_readers(old_reader.as_ref(), new_reader.as_ref(), true, |_, _| {
+			changes += 1;
+			Ok(())
+		})
+		.await?;
+
+		assert_eq!(changes, 0);
+		assert_eq!(
+			counts[14],
+			DiffCounts {
+				unchanged: counts[14].unchanged,
+				..Default::default()
+			}
+		);
+		assert!(counts[14].unchanged > 0);
+		Ok(())
+	}
+}
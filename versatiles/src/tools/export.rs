@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use std::{
+	fs::File,
+	io::{stdout, BufWriter, Write},
+};
+use versatiles_container::get_reader;
+use versatiles_geometry::geojson::{export_ndgeojson, ExportOptions};
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// container with vector tiles
+	/// supported container formats are: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
+	#[arg(required = true, verbatim_doc_comment)]
+	input_file: String,
+
+	/// write the features as NDGeoJSON to this file instead of stdout
+	#[arg(long, short, value_name = "FILE")]
+	output: Option<String>,
+
+	/// zoom level to export, defaults to the highest zoom level of the container
+	#[arg(long, short)]
+	zoom: Option<u8>,
+
+	/// only export these layers, e.g. "--layers buildings,water"
+	#[arg(long, value_delimiter = ',')]
+	layers: Vec<String>,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	super::print_status(&format!("export {:?}", arguments.input_file));
+
+	let reader = get_reader(&arguments.input_file).await?;
+
+	let mut writer: Box<dyn Write> = match &arguments.output {
+		None => Box::new(BufWriter::new(stdout().lock())),
+		Some(filename) => Box::new(BufWriter::new(
+			File::create(filename).with_context(|| format!("Failed to create {filename:?}"))?,
+		)),
+	};
+
+	let options = ExportOptions {
+		zoom: arguments.zoom,
+		layers: arguments.layers.clone(),
+	};
+	let count = export_ndgeojson(reader.as_ref(), &options, &mut writer).await?;
+
+	super::print_status(&format!("exported {count} features"));
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::tests::run_command;
+	use anyhow::Result;
+	use std::fs;
+	use versatiles_core::json::JsonObject;
+
+	#[test]
+	fn test_export() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let output = dir.path().join("berlin.ndgeojson").to_str().unwrap().to_string();
+
+		run_command(vec![
+			"versatiles",
+			"export",
+			"--zoom=10",
+			"--layers=water_polygons,boundaries",
+			"../testdata/berlin.mbtiles",
+			&format!("--output={output}"),
+		])?;
+
+		let ndgeojson = fs::read_to_string(&output)?;
+		let mut layers = Vec::new();
+		for line in ndgeojson.lines() {
+			let feature = JsonObject::parse_str(line)?;
+			assert_eq!(feature.get_string("type")?.as_deref(), Some("Feature"));
+			layers.push(feature.get_string("layer")?.unwrap());
+
+			// all coordinates are near Berlin
+			let geometry = feature.get("geometry").unwrap().as_object()?;
+			let coordinates = geometry.get("coordinates").unwrap().stringify();
+			let numbers: Vec<f64> = coordinates
+				.split(['[', ']', ','])
+				.filter(|s| !s.is_empty())
+				.map(|s| s.parse().unwrap())
+				.collect();
+			for point in numbers.chunks(2) {
+				assert!((12.5..14.5).contains(&point[0]), "{point:?}");
+				assert!((51.9..53.1).contains(&point[1]), "{point:?}");
+			}
+		}
+		layers.sort();
+		layers.dedup();
+		assert_eq!(layers, ["boundaries", "water_polygons"]);
+		Ok(())
+	}
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+use futures::future::try_join_all;
+use versatiles_container::{get_reader, write_to_filename, MergePrefer, TilesMergeParameters, TilesMergeReader};
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// input containers followed by the output container.
+	/// supported container formats: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
+	#[arg(required = true, num_args = 3.., value_name = "FILES", verbatim_doc_comment)]
+	files: Vec<String>,
+
+	/// which input wins if several inputs contain the same tile
+	#[arg(long, value_enum, default_value_t = Prefer::First)]
+	prefer: Prefer,
+
+	/// fail if the inputs have different tile compressions, instead of recompressing their tiles.
+	/// the tile formats must always be the same
+	#[arg(long)]
+	require_same_format: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Prefer {
+	First,
+	Last,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	// clap ensures at least two inputs and an output
+	let (output_file, input_files) = arguments.files.split_last().unwrap();
+
+	super::print_status(&format!("merge {input_files:?} into {output_file:?}"));
+
+	let readers = try_join_all(input_files.iter().map(|file| get_reader(file))).await?;
+	let mp = TilesMergeParameters {
+		prefer: match arguments.prefer {
+			Prefer::First => MergePrefer::First,
+			Prefer::Last => MergePrefer::Last,
+		},
+		require_same_format: arguments.require_same_format,
+	};
+	let mut reader = TilesMergeReader::new(readers, mp)?;
+
+	write_to_filename(&mut reader, output_file).await?;
+
+	super::print_status("finished merging tiles");
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::tests::run_command;
+	use anyhow::Result;
+	use versatiles_container::get_reader;
+	use versatiles_core::types::TileCompression;
+
+	fn merge(args: &[&str]) -> Result<()> {
+		let mut command = vec!["versatiles", "merge"];
+		command.extend_from_slice(args);
+		run_command(command).map(|_| ())
+	}
+
+	#[test]
+	fn test_merge() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+		// two overlapping extracts of berlin at zoom 10, x = 549..=551
+		for (name, bbox) in [("west", "13.0,52.3,13.4,52.7"), ("east", "13.3,52.3,13.8,52.7")] {
+			run_command(vec![
+				"versatiles",
+				"convert",
+				"--zoom=10",
+				&format!("--bbox={bbox}"),
+				"--compress=brotli",
+				"../testdata/berlin.mbtiles",
+				&path(&format!("{name}.versatiles")),
+			])?;
+		}
+		let merged = path("merged.versatiles");
+		merge(&[&path("west.versatiles"), &path("east.versatiles"), &merged])?;
+
+		let parameters = tokio::runtime::Runtime::new()?
+			.block_on(async { Ok::<_, anyhow::Error>(get_reader(&merged).await?.get_parameters().clone()) })?;
+		assert_eq!(parameters.bbox_pyramid.to_string(), "[10: [549,335,551,336] (6)]");
+		assert_eq!(parameters.tile_compression, TileCompression::Brotli);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_require_same_format() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+		// berlin.mbtiles is gzip compressed
+		run_command(vec![
+			"versatiles",
+			"convert",
+			"--zoom=10",
+			"--compress=brotli",
+			"../testdata/berlin.mbtiles",
+			&path("brotli.versatiles"),
+		])?;
+		let inputs = ["../testdata/berlin.mbtiles", &path("brotli.versatiles")];
+
+		merge(&[inputs[0], inputs[1], &path("merged.versatiles")])?;
+
+		let error = merge(&["--require-same-format", inputs[0], inputs[1], &path("error.versatiles")]).unwrap_err();
+		assert!(
+			error
+				.to_string()
+				.ends_with("brotli.versatiles\" is brotli, but the first input has gzip"),
+			"{error}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_too_few_inputs() {
+		let error = merge(&["../testdata/berlin.mbtiles", "../tmp/merged.versatiles"]).unwrap_err();
+		assert!(error.to_string().contains("FILES"), "{error}");
+	}
+}
@@ -1,7 +1,36 @@
 //! cli tools
+//!
+//! Only "convert" and "probe" are part of the feature `minimal`, all other tools require `cli`.
+//! "bench" requires the feature `bench`.
 
+#[cfg(feature = "bench")]
+pub mod bench;
+mod checksum;
 pub mod convert;
+#[cfg(feature = "cli")]
+pub mod diff;
+#[cfg(feature = "cli")]
+pub mod export;
+#[cfg(feature = "cli")]
 pub mod help;
+#[cfg(feature = "cli")]
+pub mod merge;
+#[cfg(feature = "cli")]
+pub mod outline;
 pub mod probe;
+#[cfg(feature = "cli")]
+pub mod run;
+#[cfg(feature = "cli")]
+pub mod schema;
+#[cfg(feature = "cli")]
 pub mod serve;
-mod server;
+#[cfg(feature = "cli")]
+pub mod show;
+#[cfg(feature = "cli")]
+pub mod update;
+#[cfg(feature = "cli")]
+pub mod verify;
+
+#[cfg(feature = "cli")]
+pub use versatiles::server;
+pub use versatiles_core::progress::print_status;
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use std::fs;
+use versatiles_container::get_reader;
+use versatiles_geometry::geojson::{features_to_feature_collection, tile_outline_features};
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// tile container
+	/// supported container formats are: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
+	#[arg(required = true, verbatim_doc_comment)]
+	input_file: String,
+
+	/// write the GeoJSON to this file instead of stdout
+	#[arg(long, short, value_name = "FILE")]
+	output: Option<String>,
+
+	/// only outline these zoom levels, e.g. "--zoom 10,14", defaults to all zoom levels
+	#[arg(long, short, value_delimiter = ',')]
+	zoom: Vec<u8>,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	super::print_status(&format!("outline {:?}", arguments.input_file));
+
+	let reader = get_reader(&arguments.input_file).await?;
+	let features = tile_outline_features(reader.as_ref(), &arguments.zoom).await?;
+	let geojson = features_to_feature_collection(&features).stringify();
+
+	match &arguments.output {
+		None => println!("{geojson}"),
+		Some(filename) => fs::write(filename, geojson).with_context(|| format!("Failed to write {filename:?}"))?,
+	}
+
+	let holes: u64 = features
+		.iter()
+		.filter_map(|f| f.properties.get("holes"))
+		.filter_map(|v| v.as_u64().ok())
+		.sum();
+	super::print_status(&format!("outlined {} zoom levels with {holes} holes", features.len()));
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::tests::run_command;
+	use anyhow::Result;
+	use std::fs;
+	use versatiles_core::json::JsonObject;
+
+	#[test]
+	fn test_outline() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let output = dir.path().join("outline.geojson").to_str().unwrap().to_string();
+
+		run_command(vec![
+			"versatiles",
+			"outline",
+			"--zoom=5,14",
+			"../testdata/berlin.mbtiles",
+			&format!("--output={output}"),
+		])?;
+
+		let geojson = JsonObject::parse_str(&fs::read_to_string(&output)?)?;
+		assert_eq!(geojson.get_string("type")?.as_deref(), Some("FeatureCollection"));
+		let features = geojson.get("features").unwrap().as_array()?;
+		assert_eq!(features.0.len(), 2);
+		let properties = features.0[1].as_object()?.get("properties").unwrap().as_object()?;
+		assert_eq!(properties.get_number::<u8>("zoom")?, Some(14));
+		assert_eq!(properties.get_number::<u32>("tiles")?, Some(610));
+		// berlin.mbtiles misses a tile inside its area
+		assert_eq!(properties.get_number::<u32>("holes")?, Some(1));
+		Ok(())
+	}
+}
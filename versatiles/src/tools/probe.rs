@@ -1,29 +1,53 @@
-use anyhow::Result;
-use versatiles_container::get_reader;
-use versatiles_core::types::ProbeDepth;
+use super::checksum::hash_file;
+use anyhow::{ensure, Result};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+use versatiles_container::{advise_compression, get_reader, scan_tiles, CompressionAdvice, TileStatistics};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::{ProbeDepth, TilesReaderTrait},
+	utils::PrettyPrint,
+};
+use versatiles_derive::ConfigDoc;
 
 #[derive(clap::Args, Debug)]
 #[command(arg_required_else_help = true, disable_version_flag = true)]
 pub struct Subcommand {
 	/// tile container you want to probe
-	/// supported container formats are: *.versatiles, *.tar, *.pmtiles, *.mbtiles or a directory
+	/// supported container formats are: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
 	#[arg(required = true, verbatim_doc_comment)]
 	filename: String,
 
 	/// deep scan (depending on the container implementation)
 	///   -d: scans container
 	///  -dd: scans all tiles
-	/// -ddd: scans all tile contents
+	/// -ddd: scans all tile contents and reports per zoom level the tile sizes, compression ratios and
+	///       empty tiles, and for vector tiles the features and vertices per layer
 	#[arg(long, short, action = clap::ArgAction::Count, verbatim_doc_comment)]
 	deep: u8,
+
+	/// cache the report in this directory, keyed by the SHA-256 hash of the container,
+	/// so that probing an unchanged file again is instant, e.g. in CI
+	#[arg(long, value_name = "DIR", verbatim_doc_comment)]
+	cache: Option<PathBuf>,
+
+	/// recompress a sample of N tiles with other compressions and report the estimated savings,
+	/// e.g. before converting a large container
+	#[arg(long, value_name = "N", verbatim_doc_comment)]
+	compression_sample: Option<u64>,
+
+	/// print name, container, meta data and parameters as JSON to stdout,
+	/// e.g. to pass the bbox pyramid to other tools
+	#[arg(long, conflicts_with_all = ["deep", "cache", "compression_sample"], verbatim_doc_comment)]
+	json: bool,
 }
 
 #[tokio::main]
 pub async fn run(arguments: &Subcommand) -> Result<()> {
 	eprintln!("probe {:?}", arguments.filename);
 
-	let mut reader = get_reader(&arguments.filename).await?;
-
 	let level = match arguments.deep {
 		0 => ProbeDepth::Shallow,
 		1 => ProbeDepth::Container,
@@ -31,14 +55,330 @@ pub async fn run(arguments: &Subcommand) -> Result<()> {
 		3..=255 => ProbeDepth::TileContents,
 	};
 
-	reader.probe(level).await?;
+	if arguments.json {
+		let reader = get_reader(&arguments.filename).await?;
+		println!("{}", get_json(reader.as_ref()).stringify());
+		return Ok(());
+	}
+
+	let cache_path = match &arguments.cache {
+		Some(dir) => Some(get_cache_path(
+			dir,
+			&arguments.filename,
+			level,
+			arguments.compression_sample,
+		)?),
+		None => None,
+	};
+	if let Some(path) = &cache_path {
+		if path.exists() {
+			eprint!("{}", fs::read_to_string(path)?);
+			return Ok(());
+		}
+	}
+
+	let mut reader = get_reader(&arguments.filename).await?;
+	let mut print = PrettyPrint::new();
+	if level == ProbeDepth::TileContents {
+		reader.probe_with_print(ProbeDepth::Tiles, &mut print).await?;
+		let statistics = scan_tiles(reader.as_ref()).await?;
+		print_statistics(&statistics, &mut print).await;
+	} else {
+		reader.probe_with_print(level, &mut print).await?;
+	}
+
+	if let Some(sample_size) = arguments.compression_sample {
+		let advice = advise_compression(reader.as_ref(), sample_size).await?;
+		print_advice(&advice, &mut print).await;
+	}
+
+	if let Some(path) = &cache_path {
+		fs::create_dir_all(path.parent().unwrap())?;
+		fs::write(path, print.get_output().await)?;
+	}
 
 	Ok(())
 }
 
+/// Output of "versatiles probe --json"
+#[derive(ConfigDoc)]
+#[allow(dead_code)]
+pub struct ProbeJson {
+	/// name of the source, e.g. the filename
+	name: String,
+	/// type of the container, e.g. "mbtiles"
+	container: String,
+	/// TileJSON of the container
+	meta: JsonObject,
+	parameters: ProbeJsonParameters,
+}
+
+#[derive(ConfigDoc)]
+#[allow(dead_code)]
+struct ProbeJsonParameters {
+	/// tile bounding box of every zoom level
+	bbox_pyramid: Vec<ProbeJsonBBox>,
+	/// e.g. "none", "gzip" or "brotli"
+	tile_compression: String,
+	/// e.g. "pbf", "png" or "webp"
+	tile_format: String,
+}
+
+#[derive(ConfigDoc)]
+#[allow(dead_code)]
+struct ProbeJsonBBox {
+	level: u8,
+	x_min: u32,
+	y_min: u32,
+	x_max: u32,
+	y_max: u32,
+}
+
+/// Returns the results of a shallow probe as JSON, as described by [`ProbeJson`].
+fn get_json(reader: &dyn TilesReaderTrait) -> JsonValue {
+	let mut object = JsonObject::default();
+	object.set("name", reader.get_source_name());
+	object.set("container", reader.get_container_name());
+	object.set("meta", JsonValue::Object(reader.get_tilejson().as_object()));
+	object.set("parameters", reader.get_parameters().as_json_value());
+	JsonValue::Object(object)
+}
+
+async fn print_statistics(statistics: &TileStatistics, print: &mut PrettyPrint) {
+	let mut cat = print.get_category("tile contents").await;
+	let list = cat.get_list("levels").await;
+	for level in statistics.levels.iter() {
+		list
+			.add_key_value(
+				&format!("z{}", level.level),
+				&format!(
+					"{} tiles, {} empty, size min/avg/max: {}/{:.0}/{} bytes, compression ratio: {:.3}",
+					level.tile_count,
+					level.empty_count,
+					level.size_min,
+					level.get_size_avg(),
+					level.size_max,
+					level.get_compression_ratio()
+				),
+			)
+			.await;
+	}
+	if !statistics.layers.is_empty() {
+		let list = cat.get_list("layers").await;
+		for (name, layer) in statistics.layers.iter() {
+			list
+				.add_key_value(
+					name,
+					&format!(
+						"{} tiles, {} features, {} vertices",
+						layer.tile_count, layer.feature_count, layer.vertex_count
+					),
+				)
+				.await;
+		}
+	}
+}
+
+async fn print_advice(advice: &CompressionAdvice, print: &mut PrettyPrint) {
+	let mut cat = print.get_category("compression").await;
+	cat.add_key_value("sampled tiles", &advice.tile_count).await;
+	cat.add_key_value(advice.compression.as_str(), &format!("{} bytes", advice.bytes))
+		.await;
+	let list = cat.get_list("recompressed").await;
+	for candidate in advice.candidates.iter() {
+		let change = advice.get_change(candidate) * 100.0;
+		list
+			.add_key_value(
+				&candidate.get_name(),
+				&format!("{} bytes ({change:+.1}%)", candidate.bytes),
+			)
+			.await;
+	}
+	match advice.get_recommendation() {
+		Some(text) => cat.add_key_value("recommendation", &text).await,
+		None => {
+			cat.add_key_value("recommendation", "keep the current compression")
+				.await
+		}
+	}
+}
+
+/// Returns the path of the cached report. It depends on the content of the container, the depth, the
+/// compression sample and the version of VersaTiles, since reports of other versions might differ.
+fn get_cache_path(dir: &Path, filename: &str, level: ProbeDepth, sample: Option<u64>) -> Result<PathBuf> {
+	ensure!(
+		Path::new(filename).is_file(),
+		"--cache is only supported for local container files"
+	);
+	let hash = hash_file(filename)?;
+	let sample = sample.map(|n| format!("sample{n}-")).unwrap_or_default();
+	Ok(dir.join(format!(
+		"probe-{}-{level:?}-{sample}{hash}.txt",
+		env!("CARGO_PKG_VERSION")
+	)))
+}
+
 #[cfg(test)]
 mod tests {
+	use super::*;
 	use crate::tests::run_command;
+	use assert_fs::TempDir;
+	use versatiles_core::types::TilesReaderParameters;
+
+	/// Checks that every object of `value` has exactly the required properties of `schema`.
+	fn check_schema(value: &JsonValue, schema: &JsonObject) -> Result<()> {
+		match schema.get_string("type")?.as_deref() {
+			Some("object") => {
+				let object = value.as_object()?;
+				let Some(properties) = schema.get("properties") else {
+					return Ok(());
+				};
+				let properties = properties.as_object()?;
+				let mut keys: Vec<&String> = object.iter().map(|(key, _)| key).collect();
+				let mut expected: Vec<&String> = properties.iter().map(|(key, _)| key).collect();
+				keys.sort();
+				expected.sort();
+				assert_eq!(keys, expected);
+				for (key, value) in object.iter() {
+					check_schema(value, properties.get(key).unwrap().as_object()?)?;
+				}
+			}
+			Some("array") => {
+				for item in value.as_array()?.0.iter() {
+					check_schema(item, schema.get("items").unwrap().as_object()?)?;
+				}
+			}
+			Some("string") => assert!(value.as_str().is_ok(), "{value:?}"),
+			Some("integer") => assert!(value.as_number::<u32>().is_ok(), "{value:?}"),
+			_ => {}
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_json_schema() -> Result<()> {
+		let reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let schema = ProbeJson::get_json_schema().to_object()?;
+		check_schema(&get_json(reader.as_ref()), &schema)
+	}
+
+	#[test]
+	fn test_cache() -> Result<()> {
+		let dir = TempDir::new()?;
+		let cache = dir.path().to_str().unwrap();
+		let probe = || {
+			run_command(vec![
+				"versatiles",
+				"probe",
+				"-dd",
+				"--cache",
+				cache,
+				"../testdata/berlin.mbtiles",
+			])
+		};
+
+		probe()?;
+		let files: Vec<PathBuf> = fs::read_dir(dir.path())?.map(|e| e.unwrap().path()).collect();
+		assert_eq!(files.len(), 1);
+		let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+		assert!(name.starts_with("probe-") && name.contains("-Tiles-"), "{name}");
+		assert!(fs::read_to_string(&files[0])?.contains("meta_data:"));
+
+		// a second run uses the cached report
+		fs::write(&files[0], "cached report\n")?;
+		probe()?;
+		assert_eq!(fs::read_to_string(&files[0])?, "cached report\n");
+
+		assert_eq!(
+			get_cache_path(
+				dir.path(),
+				"https://example.org/osm.versatiles",
+				ProbeDepth::Tiles,
+				None
+			)
+			.unwrap_err()
+			.to_string(),
+			"--cache is only supported for local container files"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_json() -> Result<()> {
+		let reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let json = get_json(reader.as_ref()).to_object()?;
+		assert_eq!(json.get_string("container")?.unwrap(), "mbtiles");
+		assert_eq!(
+			json.get("meta").unwrap().as_object()?.get_string("name")?.unwrap(),
+			"Tilemaker to Geofabrik Vector Tiles schema"
+		);
+
+		let parameters = TilesReaderParameters::from_json_value(json.get("parameters").unwrap())?;
+		assert_eq!(&parameters, reader.get_parameters());
+		Ok(())
+	}
+
+	#[test]
+	fn test_json_conflicts_with_deep() {
+		let error = run_command(vec![
+			"versatiles",
+			"probe",
+			"--json",
+			"-d",
+			"../testdata/berlin.mbtiles",
+		])
+		.unwrap_err();
+		assert!(error.to_string().contains("cannot be used with"), "{error}");
+	}
+
+	#[tokio::test]
+	async fn test_print_advice() -> Result<()> {
+		let reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let advice = advise_compression(reader.as_ref(), 10).await?;
+		let mut print = PrettyPrint::new();
+		print_advice(&advice, &mut print).await;
+		let output = print.as_string().await;
+		assert!(output.contains("sampled tiles: "), "{output}");
+		assert!(output.contains("brotli-11: \""), "{output}");
+		assert!(
+			output.contains("recommendation: \"switching pbf tiles from gzip to brotli-"),
+			"{output}"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_print_statistics() -> Result<()> {
+		let reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let statistics = scan_tiles(reader.as_ref()).await?;
+		let mut print = PrettyPrint::new();
+		print_statistics(&statistics, &mut print).await;
+		let output = print.as_string().await;
+		assert!(output.contains("z14: \""), "{output}");
+		assert!(output.contains("compression ratio: 0."), "{output}");
+		assert!(output.contains("buildings: \""), "{output}");
+		assert!(output.contains(" features, "), "{output}");
+		Ok(())
+	}
+
+	#[test]
+	fn test_deep_probe() -> Result<()> {
+		run_command(vec!["versatiles", "probe", "-q", "-ddd", "../testdata/berlin.mbtiles"])?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_compression_sample() -> Result<()> {
+		run_command(vec![
+			"versatiles",
+			"probe",
+			"-q",
+			"--compression-sample",
+			"5",
+			"../testdata/berlin.mbtiles",
+		])?;
+		Ok(())
+	}
 
 	#[test]
 
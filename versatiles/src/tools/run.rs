@@ -0,0 +1,48 @@
+use anyhow::Result;
+use std::path::Path;
+use versatiles_container::run_pipeline_path;
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// pipeline file (*.vpl) that ends with one or more "to_container" operations.
+	/// see "versatiles help pipeline" for details
+	#[arg(verbatim_doc_comment)]
+	vpl_file: String,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	super::print_status(&format!("run pipeline {:?}", arguments.vpl_file));
+
+	run_pipeline_path(Path::new(&arguments.vpl_file)).await?;
+
+	super::print_status("finished running pipeline");
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::tests::run_command;
+	use anyhow::Result;
+	use versatiles_container::get_reader;
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn run_vpl_file() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let input = std::fs::canonicalize("../testdata/berlin.mbtiles")?;
+		let vpl_file = dir.path().join("pipeline.vpl");
+		std::fs::write(
+			&vpl_file,
+			format!("from_container filename={input:?} | filter_zoom max=2 | to_container filename=\"output.versatiles\""),
+		)?;
+
+		let vpl_file = vpl_file.to_str().unwrap().to_string();
+		tokio::task::spawn_blocking(move || run_command(vec!["versatiles", "run", &vpl_file])).await??;
+
+		let reader = get_reader(dir.path().join("output.versatiles").to_str().unwrap()).await?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.get_zoom_max(), Some(2));
+		Ok(())
+	}
+}
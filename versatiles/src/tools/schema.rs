@@ -0,0 +1,66 @@
+use crate::{config::Config, tools::probe::ProbeJson};
+use anyhow::Result;
+use versatiles_core::json::JsonObject;
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	#[command(subcommand)]
+	topic: Topic,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Topic {
+	/// configuration file of "versatiles serve --config"
+	Config,
+	/// output of "versatiles probe --json"
+	Probe,
+}
+
+pub fn run(arguments: &Subcommand) -> Result<()> {
+	println!("{}", get_schema(&arguments.topic)?.stringify());
+	Ok(())
+}
+
+/// Returns the JSON Schema of the topic. It contains the version, since the schema may change with every release.
+fn get_schema(topic: &Topic) -> Result<JsonObject> {
+	let (title, schema) = match topic {
+		Topic::Config => ("VersaTiles server configuration", Config::get_json_schema()),
+		Topic::Probe => ("VersaTiles probe output", ProbeJson::get_json_schema()),
+	};
+	let mut schema = schema.to_object()?;
+	schema.set("$schema", "https://json-schema.org/draft/2020-12/schema");
+	schema.set("title", title);
+	schema.set(
+		"$comment",
+		format!("generated by versatiles {}", env!("CARGO_PKG_VERSION")),
+	);
+	Ok(schema)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::run_command;
+
+	#[test]
+	fn test_schema() -> Result<()> {
+		run_command(vec!["versatiles", "schema", "config"])?;
+		run_command(vec!["versatiles", "schema", "probe"])?;
+
+		let schema = get_schema(&Topic::Probe)?;
+		assert_eq!(
+			schema.get_string("$comment")?.unwrap(),
+			format!("generated by versatiles {}", env!("CARGO_PKG_VERSION"))
+		);
+		assert_eq!(
+			schema.get_string_vec("required")?.unwrap(),
+			["name", "container", "meta", "parameters"]
+		);
+
+		// the schema is valid JSON
+		let schema = get_schema(&Topic::Config)?;
+		assert_eq!(JsonObject::parse_str(&schema.stringify())?, schema);
+		Ok(())
+	}
+}
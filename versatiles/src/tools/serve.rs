@@ -1,40 +1,66 @@
-use super::server::{TileServer, Url};
+use super::server::{
+	Auth, JobsOptions, ReloadOutcome, ReloadRequest, StaticSourceOptions, TileCacheOptions, TileServer,
+	TileSourceOptions, Url,
+};
+use crate::config::Config;
 use anyhow::Result;
 use regex::Regex;
-use std::path::Path;
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	time::{Instant, SystemTime},
+};
 use tokio::time::{sleep, Duration};
-use versatiles_container::{get_reader, TilesConvertReader, TilesConverterParameters};
+use versatiles_container::{
+	get_reader, TilesConvertReader, TilesConverterParameters, TilesMergeParameters, TilesMergeReader,
+};
 use versatiles_core::types::{TileCompression, TilesReaderTrait};
 
 #[derive(clap::Args, Debug)]
 #[command(arg_required_else_help = true, disable_version_flag = true, verbatim_doc_comment)]
 pub struct Subcommand {
 	/// One or more tile containers you want to serve.
-	/// Supported container formats are: *.versatiles, *.tar, *.pmtiles, *.mbtiles or a directory
+	/// Supported container formats are: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
 	/// Container files have to be on the local filesystem, except VersaTiles containers:
 	///    VersaTiles containers can also be served from http://... or https://...
 	/// The id used in the url (/tiles/$id/) will be generated automatically from the file id:
 	///    e.g. ".../ukraine.versatiles" will be served at url "/tiles/ukraine/..."
 	/// You can also configure a different id for each file using:
 	///    "[id]file", "file[id]" or "file#id"
-	#[arg(num_args = 1.., required = true, verbatim_doc_comment)]
+	/// Raster tiles can be requested resampled for high-DPI screens, using a suffix like
+	/// "/tiles/$id/z/x/y@1.5x" or the query parameter "?scale=1.5" (from 0.25 to 4).
+	#[arg(num_args = 1.., required_unless_present = "config", verbatim_doc_comment)]
 	pub tile_sources: Vec<String>,
 
-	/// Serve via socket ip.
-	#[arg(short = 'i', long, default_value = "0.0.0.0", display_order = 0)]
-	pub ip: String,
+	/// Load tile sources, static sources and server options from a YAML file.
+	/// Per-source options like public tile URLs with subdomains, the URL scheme (xyz/tms)
+	/// and allowed CORS origins can only be set in this file.
+	/// Command line arguments take precedence over the values in the file.
+	/// Send SIGHUP to the process to reload the file and all sources without dropping connections.
+	/// Every reload is logged with its origin and the old and new ETag of the configuration.
+	#[arg(short = 'c', long, verbatim_doc_comment, display_order = 0)]
+	pub config: Option<PathBuf>,
 
-	/// Serve via port.
-	#[arg(short, long, default_value = "8080", display_order = 0)]
-	pub port: u16,
+	/// Serve via socket ip. [default: 0.0.0.0]
+	#[arg(short = 'i', long, display_order = 0)]
+	pub ip: Option<String>,
 
-	/// Serve static content at "http:/.../" from a local folder or a tar file.
+	/// Serve via port. [default: 8080]
+	#[arg(short, long, display_order = 0)]
+	pub port: Option<u16>,
+
+	/// Serve static content at "http:/.../" from a local folder, a tar file or a zip file.
 	/// Tar files can be compressed (.tar / .tar.gz / .tar.br).
 	/// If multiple static sources are defined, the first hit will be served.
 	/// You can also add an optional url prefix like "[/assets/styles]styles.tar".
 	#[arg(short = 's', long = "static", verbatim_doc_comment, display_order = 1)]
 	pub static_content: Vec<String>,
 
+	/// Watch the config file and all local tile, style and static sources, and reload the server
+	/// when one of them changes. Meant for development, use SIGHUP to reload in production.
+	#[arg(long, verbatim_doc_comment, display_order = 4)]
+	pub watch_config: bool,
+
 	/// Shutdown server automatically after x milliseconds.
 	#[arg(long, display_order = 4)]
 	pub auto_shutdown: Option<u64>,
@@ -51,10 +77,65 @@ pub struct Subcommand {
 	#[arg(long, display_order = 2)]
 	pub fast: bool,
 
-	/// disable API
+	/// disable API, including the inspection pages at "/inspect" and the tile info at "/api/source/{name}/tile/{z}/{x}/{y}/info"
 	#[arg(long, display_order = 4)]
 	pub disable_api: bool,
 
+	/// add a timing breakdown to every tile response, as "Server-Timing" header and log line.
+	/// Single requests can be traced by sending the header "X-Versatiles-Trace: 1".
+	#[arg(long, verbatim_doc_comment, display_order = 4)]
+	pub trace: bool,
+
+	/// enable the admin API "/api/jobs" to start, follow, cancel and resume conversions on the server.
+	/// Jobs can only read and write files in this folder, e.g. "/api/jobs" with {"input":"planet.versatiles",...}
+	/// reads "$DIR/planet.versatiles". Requires --jobs-api-key.
+	#[arg(
+		long,
+		value_name = "DIR",
+		requires = "jobs_api_key",
+		verbatim_doc_comment,
+		display_order = 4
+	)]
+	pub jobs_root: Option<PathBuf>,
+
+	/// API key for the jobs API, sent in the header "X-API-Key" or the query parameter "key". Can be repeated.
+	#[arg(long, value_name = "KEY", requires = "jobs_root", display_order = 4)]
+	pub jobs_api_key: Vec<String>,
+
+	/// enable the admin API "/api/config" to read the loaded sources and their ETag, and
+	/// "/api/config/reload" to reload the config file and all sources. A reload requires the
+	/// ETag of the loaded configuration as "If-Match" header. Can be repeated.
+	#[arg(long, value_name = "KEY", verbatim_doc_comment, display_order = 4)]
+	pub config_api_key: Vec<String>,
+
+	/// cache up to this many megabytes of encoded tile responses in memory,
+	/// so that repeated requests of hot tiles skip reading and recompression.
+	/// Hits and misses are reported at "/api/status".
+	#[arg(long, value_name = "MB", verbatim_doc_comment, display_order = 2)]
+	pub tile_cache_mb: Option<u64>,
+
+	/// read cached tiles again after this many seconds, e.g. if the containers are replaced
+	#[arg(long, value_name = "SECONDS", requires = "tile_cache_mb", display_order = 2)]
+	pub tile_cache_ttl: Option<u64>,
+
+	/// serve with HTTPS instead of HTTP, and additionally with HTTP/3 on the same port (UDP),
+	/// using this certificate chain (PEM file). Clients are told about HTTP/3 with the "Alt-Svc" header.
+	/// Behind a TLS terminating proxy, leave this out and let the proxy provide HTTP/3.
+	#[cfg(feature = "http3")]
+	#[arg(
+		long,
+		value_name = "PEM",
+		requires = "tls_key",
+		verbatim_doc_comment,
+		display_order = 4
+	)]
+	pub tls_cert: Option<PathBuf>,
+
+	/// private key of the TLS certificate (PEM file)
+	#[cfg(feature = "http3")]
+	#[arg(long, value_name = "PEM", requires = "tls_cert", display_order = 4)]
+	pub tls_key: Option<PathBuf>,
+
 	/// override the compression of the input source, e.g. to handle gzipped tiles in a tar, that do not end in .gz
 	/// (deprecated in favor of a better solution that does not yet exist)
 	#[arg(long, value_enum, value_name = "COMPRESSION", display_order = 4)]
@@ -63,9 +144,147 @@ pub struct Subcommand {
 
 #[tokio::main]
 pub async fn run(arguments: &Subcommand) -> Result<()> {
-	let mut server: TileServer = TileServer::new(&arguments.ip, arguments.port, !arguments.fast, !arguments.disable_api);
+	let mut server = build_server(arguments).await?;
+	print_url_mapping(&server).await;
+
+	let mut reload_requests = match arguments.config_api_key.is_empty() {
+		true => None,
+		false => Some(server.set_config_api(Auth::new(&arguments.config_api_key, None)?)?),
+	};
+
+	server.start().await?;
+
+	let shutdown = async {
+		match arguments.auto_shutdown {
+			Some(milliseconds) => sleep(Duration::from_millis(milliseconds)).await,
+			None => std::future::pending().await,
+		}
+	};
+	tokio::pin!(shutdown);
+
+	let mut reload_signal = ReloadSignal::new()?;
+	let mut watcher = arguments.watch_config.then(|| SourceWatcher::new(arguments));
+	loop {
+		// local reloads are conditional on the configuration they were started with,
+		// so that they don't overwrite a reload that finished in the meantime
+		tokio::select! {
+			_ = &mut shutdown => break,
+			_ = reload_signal.recv() => {
+				super::print_status("reloading sources");
+				let etag = server.config_etag().await;
+				match build_server(arguments).await {
+					Ok(new_server) => {
+						print_url_mapping(&new_server).await;
+						server.reload_sources(new_server, &etag, "SIGHUP").await?;
+					}
+					Err(err) => log::error!("reload failed, keeping the previous sources: {err:#}"),
+				}
+			}
+			Some(request) = recv_reload_request(&mut reload_requests) => {
+				super::print_status(&format!("reloading sources, requested by {}", request.actor));
+				let outcome = match build_server(arguments).await {
+					Ok(new_server) => server.reload_sources(new_server, &request.if_match, &request.actor).await?,
+					Err(err) => {
+						log::error!("reload failed, keeping the previous sources: {err:#}");
+						ReloadOutcome::Failed(format!("{err:#}"))
+					}
+				};
+				request.response.send(outcome).ok();
+			}
+			changed = wait_for_changes(&mut watcher, arguments) => {
+				let names: Vec<String> = changed.iter().map(|path| path.to_string_lossy().to_string()).collect();
+				super::print_status(&format!("changed: {}", names.join(", ")));
+				let start = Instant::now();
+				let etag = server.config_etag().await;
+				match build_server(arguments).await {
+					Ok(new_server) => {
+						let count = new_server.get_url_mapping().await.len();
+						server.reload_sources(new_server, &etag, "file watcher").await?;
+						super::print_status(&format!(
+							"reloaded {count} tile source(s) in {} ms",
+							start.elapsed().as_millis()
+						));
+					}
+					Err(err) => super::print_status(&format!("reload failed, keeping the previous sources: {err:#}")),
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Creates a server with all sources from the config file and the command line arguments.
+async fn build_server(arguments: &Subcommand) -> Result<TileServer> {
+	let config = match &arguments.config {
+		Some(path) => Config::from_path(path)?,
+		None => Config::default(),
+	};
+
+	let ip = arguments
+		.ip
+		.as_deref()
+		.or(config.server.ip.as_deref())
+		.unwrap_or("0.0.0.0");
+	let port = arguments.port.or(config.server.port).unwrap_or(8080);
+	let fast = arguments.fast || config.server.minimal_recompression.unwrap_or(false);
+	let disable_api = arguments.disable_api || config.server.disable_api.unwrap_or(false);
+	let mut server: TileServer = TileServer::new(ip, port, !fast, !disable_api);
+	server.set_trace(arguments.trace || config.server.trace.unwrap_or(false));
+	server.set_jobs(match &arguments.jobs_root {
+		Some(root) => Some(JobsOptions::new(root, &arguments.jobs_api_key)?),
+		None => config.server.jobs.as_ref().map(|jobs| jobs.options()).transpose()?,
+	})?;
+	#[cfg(feature = "http3")]
+	if let (Some(cert_path), Some(key_path)) = (&arguments.tls_cert, &arguments.tls_key) {
+		server.set_tls(Some(super::server::TlsOptions {
+			cert_path: cert_path.clone(),
+			key_path: key_path.clone(),
+		}));
+	}
+	server.set_tile_cache(match arguments.tile_cache_mb {
+		Some(size_mb) => Some(TileCacheOptions {
+			max_bytes: size_mb * 1024 * 1024,
+			ttl: arguments.tile_cache_ttl.map(Duration::from_secs),
+		}),
+		None => config.server.tile_cache.as_ref().map(|cache| cache.options()),
+	});
+
+	for tiles in config.tiles.iter() {
+		let id = tiles.name.clone().unwrap_or_else(|| default_id(tiles.src.first()));
+		let reader = open_fallback_reader(&tiles.src.0, arguments).await?;
+		server.add_tile_source(&id, reader, config.tile_source_options(tiles)?)?;
+	}
+
+	for static_source in config.static_sources.iter() {
+		let url_prefix = static_source.prefix.as_deref().unwrap_or("");
+		let options = StaticSourceOptions {
+			minimal_recompression: static_source.minimal_recompression,
+		};
+		server.add_static_source_with_options(Path::new(&static_source.src), Url::new(url_prefix), options)?;
+	}
+
+	for argument in arguments.tile_sources.iter() {
+		let (url, id) = parse_tile_argument(argument);
+		let reader = open_reader(&url, arguments).await?;
+		let options = TileSourceOptions {
+			cors: config.default_cors()?,
+			..Default::default()
+		};
+		server.add_tile_source(&id, reader, options)?;
+	}
+
+	for argument in arguments.static_content.iter() {
+		let (filename, url_prefix) = parse_static_argument(argument);
+		server.add_static_source(Path::new(&filename), Url::new(&url_prefix))?;
+	}
 
-	let tile_patterns: Vec<Regex> = [
+	Ok(server)
+}
+
+/// Splits a tile source argument like "[id]file", "file[id]" or "file#id" into url and id.
+fn parse_tile_argument(argument: &str) -> (String, String) {
+	let capture = [
 		r"^\[(?P<id>[^\]]+?)\](?P<url>.*)$",
 		r"^(?P<url>.*)\[(?P<id>[^\]]+?)\]$",
 		r"^(?P<url>.*)#(?P<id>[^\]]+?)$",
@@ -73,87 +292,202 @@ pub async fn run(arguments: &Subcommand) -> Result<()> {
 	]
 	.iter()
 	.map(|pat| Regex::new(pat).unwrap())
-	.collect();
+	.find(|p| p.is_match(argument))
+	.unwrap()
+	.captures(argument)
+	.unwrap();
+
+	let url: &str = capture.name("url").unwrap().as_str();
+	let id: String = match capture.name("id") {
+		None => default_id(url),
+		Some(m) => m.as_str().to_string(),
+	};
+	(url.to_string(), id)
+}
 
-	let static_patterns: Vec<Regex> = [
+/// Splits a static source argument like "[/prefix]file" or "file[/prefix]" into filename and url prefix.
+fn parse_static_argument(argument: &str) -> (String, String) {
+	let capture = [
 		r"^\[(?P<path>[^\]]+?)\](?P<filename>.*)$",
 		r"^(?P<filename>.*)\[(?P<path>[^\]]+?)\]$",
 		r"^(?P<filename>.*)$",
 	]
 	.iter()
 	.map(|pat| Regex::new(pat).unwrap())
-	.collect();
+	.find(|p| p.is_match(argument))
+	.unwrap()
+	.captures(argument)
+	.unwrap();
 
-	for argument in arguments.tile_sources.iter() {
-		// parse url: Does it also contain a "id" or other parameters?
-		let capture = tile_patterns
-			.iter()
-			.find(|p| p.is_match(argument))
-			.unwrap()
-			.captures(argument)
-			.unwrap();
-
-		let url: &str = capture.name("url").unwrap().as_str();
-		let id: &str = match capture.name("id") {
-			None => url.split(&['/', '\\']).last().unwrap().split('.').next().unwrap(),
-			Some(m) => m.as_str(),
-		};
+	let filename: &str = capture.name("filename").unwrap().as_str();
+	let url_prefix: &str = match capture.name("path") {
+		None => "",
+		Some(m) => m.as_str(),
+	};
+	(filename.to_string(), url_prefix.to_string())
+}
 
-		let mut reader = get_reader(url).await?;
+/// Waits for the next reload request of the config API. Never resolves without the config API.
+async fn recv_reload_request(
+	requests: &mut Option<tokio::sync::mpsc::Receiver<ReloadRequest>>,
+) -> Option<ReloadRequest> {
+	match requests {
+		Some(requests) => requests.recv().await,
+		None => std::future::pending().await,
+	}
+}
 
-		if arguments.override_input_compression.is_some() {
-			reader.override_compression(arguments.override_input_compression.unwrap())
+/// Polls the watcher until a watched file changes. Never resolves without a watcher.
+async fn wait_for_changes(watcher: &mut Option<SourceWatcher>, arguments: &Subcommand) -> Vec<PathBuf> {
+	let Some(watcher) = watcher else {
+		return std::future::pending().await;
+	};
+	loop {
+		sleep(WATCH_INTERVAL).await;
+		let changed = watcher.poll(arguments);
+		if !changed.is_empty() {
+			return changed;
 		}
+	}
+}
 
-		if arguments.flip_y || arguments.swap_xy {
-			let mut cp = TilesConverterParameters::new_default();
-			cp.flip_y = arguments.flip_y;
-			cp.swap_xy = arguments.swap_xy;
-			reader = TilesConvertReader::new_from_reader(reader, cp)?.boxed();
-		}
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modification time and size of a file, `None` if it doesn't exist
+type FileState = Option<(SystemTime, u64)>;
 
-		server.add_tile_source(id, reader)?;
+/// Watches the config file and all local files it or the command line refers to.
+/// Directories are only checked for their own modification time, not for the files inside.
+struct SourceWatcher {
+	files: BTreeMap<PathBuf, FileState>,
+}
+
+impl SourceWatcher {
+	fn new(arguments: &Subcommand) -> SourceWatcher {
+		SourceWatcher {
+			files: Self::snapshot(arguments),
+		}
 	}
 
-	for argument in arguments.static_content.iter() {
-		let capture = static_patterns
+	/// Returns all files that changed, appeared or disappeared since the last call.
+	fn poll(&mut self, arguments: &Subcommand) -> Vec<PathBuf> {
+		let files = Self::snapshot(arguments);
+		let mut changed: Vec<PathBuf> = files
 			.iter()
-			.find(|p| p.is_match(argument))
-			.unwrap()
-			.captures(argument)
-			.unwrap();
+			.filter(|(path, state)| self.files.get(*path) != Some(state))
+			.map(|(path, _)| path.clone())
+			.collect();
+		changed.extend(self.files.keys().filter(|path| !files.contains_key(*path)).cloned());
+		self.files = files;
+		changed
+	}
 
-		let filename: &str = capture.name("filename").unwrap().as_str();
-		let url_prefix: &str = match capture.name("path") {
-			None => "",
-			Some(m) => m.as_str(),
-		};
+	fn snapshot(arguments: &Subcommand) -> BTreeMap<PathBuf, FileState> {
+		Self::watched_paths(arguments)
+			.into_iter()
+			.map(|path| {
+				let state = std::fs::metadata(&path)
+					.and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+					.ok();
+				(path, state)
+			})
+			.collect()
+	}
 
-		server.add_static_source(Path::new(filename), Url::new(url_prefix))?;
+	fn watched_paths(arguments: &Subcommand) -> Vec<PathBuf> {
+		let mut sources: Vec<String> = Vec::new();
+		let mut paths: Vec<PathBuf> = Vec::new();
+
+		if let Some(path) = &arguments.config {
+			paths.push(path.clone());
+			// an invalid config is reported when reloading, until then only the file itself is watched
+			if let Ok(config) = Config::from_path(path) {
+				for tiles in config.tiles {
+					sources.extend(tiles.src.0);
+					sources.extend(tiles.style);
+					sources.extend(tiles.lookups.into_iter().map(|l| l.src));
+				}
+				sources.extend(config.static_sources.into_iter().map(|s| s.src));
+			}
+		}
+		sources.extend(arguments.tile_sources.iter().map(|a| parse_tile_argument(a).0));
+		sources.extend(arguments.static_content.iter().map(|a| parse_static_argument(a).0));
+
+		paths.extend(sources.into_iter().filter(|s| !s.contains("://")).map(PathBuf::from));
+		paths
 	}
+}
 
+async fn print_url_mapping(server: &TileServer) {
 	let mut list: Vec<(String, String)> = server.get_url_mapping().await;
 	list.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
 	list
 		.iter()
-		.for_each(|(url, source)| eprintln!("   {:30}  <-  {}", url.to_owned() + "*", source));
+		.for_each(|(url, source)| super::print_status(&format!("   {:30}  <-  {}", url.to_owned() + "*", source)));
+}
 
-	server.start().await?;
+/// Resolves whenever the process receives SIGHUP. On other platforms than Unix it never resolves.
+struct ReloadSignal {
+	#[cfg(unix)]
+	signal: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+	fn new() -> Result<Self> {
+		Ok(Self {
+			#[cfg(unix)]
+			signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+		})
+	}
 
-	if let Some(milliseconds) = arguments.auto_shutdown {
-		sleep(Duration::from_millis(milliseconds)).await
-	} else {
-		loop {
-			sleep(Duration::from_secs(60)).await
+	async fn recv(&mut self) {
+		#[cfg(unix)]
+		if self.signal.recv().await.is_some() {
+			return;
 		}
+		std::future::pending::<()>().await
 	}
+}
 
-	Ok(())
+/// Generates the id from the filename, e.g. ".../ukraine.versatiles" -> "ukraine"
+fn default_id(url: &str) -> String {
+	let filename = url.split(&['/', '\\']).next_back().unwrap();
+	filename.split('.').next().unwrap().to_string()
+}
+
+async fn open_reader(url: &str, arguments: &Subcommand) -> Result<Box<dyn TilesReaderTrait>> {
+	let mut reader = get_reader(url).await?;
+
+	if let Some(compression) = arguments.override_input_compression {
+		reader.override_compression(compression)
+	}
+
+	if arguments.flip_y || arguments.swap_xy {
+		let mut cp = TilesConverterParameters::new_default();
+		cp.flip_y = arguments.flip_y;
+		cp.swap_xy = arguments.swap_xy;
+		reader = TilesConvertReader::new_from_reader(reader, cp)?.boxed();
+	}
+
+	Ok(reader)
+}
+
+/// Opens multiple containers as one source, serving each tile from the first container that has it.
+async fn open_fallback_reader(urls: &[String], arguments: &Subcommand) -> Result<Box<dyn TilesReaderTrait>> {
+	if let [url] = urls {
+		return open_reader(url, arguments).await;
+	}
+	let mut readers = Vec::new();
+	for url in urls {
+		readers.push(open_reader(url, arguments).await?);
+	}
+	Ok(TilesMergeReader::new(readers, TilesMergeParameters::default())?.boxed())
 }
 
 #[cfg(test)]
 mod tests {
 	use crate::tests::run_command;
+	use assert_fs::TempDir;
 
 	#[test]
 	fn test_local() {
@@ -171,6 +505,165 @@ mod tests {
 		.unwrap();
 	}
 
+	#[cfg(feature = "http3")]
+	#[test]
+	fn test_tls_requires_key() {
+		let error = run_command(vec![
+			"versatiles",
+			"serve",
+			"--tls-cert",
+			"cert.pem",
+			"../testdata/berlin.mbtiles",
+		])
+		.unwrap_err()
+		.to_string();
+		assert!(error.contains("--tls-key <PEM>"), "{error}");
+	}
+
+	#[test]
+	fn test_config() {
+		let dir = TempDir::new().unwrap();
+		let path = dir.path().join("config.yaml");
+		let berlin = std::fs::canonicalize("../testdata/berlin.mbtiles").unwrap();
+		std::fs::write(
+			&path,
+			format!(
+				"server: {{ip: 127.0.0.1, port: 65003}}\ntiles:\n  - src: {berlin:?}\n    tile_url: \"https://{{s}}.example.org/tiles/berlin\"\n    subdomains: [a, b]\n"
+			),
+		)
+		.unwrap();
+
+		run_command(vec![
+			"versatiles",
+			"serve",
+			"--config",
+			path.to_str().unwrap(),
+			"--auto-shutdown",
+			"500",
+		])
+		.unwrap();
+	}
+
+	#[test]
+	fn test_parse_arguments() {
+		assert_eq!(
+			super::parse_tile_argument("[osm]tiles/world.versatiles"),
+			(String::from("tiles/world.versatiles"), String::from("osm"))
+		);
+		assert_eq!(
+			super::parse_tile_argument("world.mbtiles#w"),
+			(String::from("world.mbtiles"), String::from("w"))
+		);
+		assert_eq!(
+			super::parse_tile_argument("../berlin.pmtiles"),
+			(String::from("../berlin.pmtiles"), String::from("berlin"))
+		);
+		assert_eq!(
+			super::parse_static_argument("styles.tar[/assets/styles]"),
+			(String::from("styles.tar"), String::from("/assets/styles"))
+		);
+		assert_eq!(
+			super::parse_static_argument("frontend.tar"),
+			(String::from("frontend.tar"), String::new())
+		);
+	}
+
+	#[test]
+	fn test_source_watcher() {
+		use super::{SourceWatcher, Subcommand};
+		use clap::Parser;
+
+		#[derive(Parser)]
+		struct Cli {
+			#[command(flatten)]
+			serve: Subcommand,
+		}
+
+		let dir = TempDir::new().unwrap();
+		let config = dir.path().join("config.yaml");
+		let style = dir.path().join("style.json");
+		let frontend = dir.path().join("frontend.tar");
+		std::fs::write(&config, "tiles: [{src: \"https://example.org/osm.versatiles\"}]").unwrap();
+		std::fs::write(&style, "{}").unwrap();
+
+		let cli = Cli::parse_from([
+			"serve",
+			"--watch-config",
+			"--config",
+			config.to_str().unwrap(),
+			"--static",
+			frontend.to_str().unwrap(),
+		]);
+		let mut watcher = SourceWatcher::new(&cli.serve);
+		assert!(watcher.poll(&cli.serve).is_empty());
+
+		// a new file referenced by the config is watched after the config changed
+		std::fs::write(
+			&config,
+			"tiles: [{src: \"https://example.org/osm.versatiles\", style: style.json}]",
+		)
+		.unwrap();
+		assert_eq!(watcher.poll(&cli.serve), [config.clone(), style.clone()]);
+
+		std::fs::write(&style, "{\"layers\":[]}").unwrap();
+		std::fs::write(&frontend, "").unwrap();
+		let mut changed = watcher.poll(&cli.serve);
+		changed.sort();
+		assert_eq!(changed, [frontend, style]);
+		assert!(watcher.poll(&cli.serve).is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_fallback_reader() {
+		use super::{open_fallback_reader, Subcommand};
+		use clap::Parser;
+		use versatiles_core::types::{Blob, TileCoord3};
+
+		#[derive(Parser)]
+		struct Cli {
+			#[command(flatten)]
+			serve: Subcommand,
+		}
+		let cli = Cli::parse_from(["serve", "tiles"]);
+
+		let dir = TempDir::new().unwrap();
+		let write = |path: &str, content: &str| {
+			let path = dir.path().join(path);
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(path, content).unwrap();
+		};
+		write("fresh/1/0/0.png", "fresh 1/0/0");
+		write("old/0/0/0.png", "old 0/0/0");
+		write("old/1/0/0.png", "old 1/0/0");
+		let url = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+		let reader = open_fallback_reader(&[url("fresh"), url("old")], &cli.serve)
+			.await
+			.unwrap();
+		assert_eq!(reader.get_container_name(), "merge");
+		for (x, y, z, expected) in [
+			(0, 0, 1, Some("fresh 1/0/0")),
+			(0, 0, 0, Some("old 0/0/0")),
+			(1, 0, 1, None),
+		] {
+			let tile = reader.get_tile_data(&TileCoord3::new(x, y, z).unwrap()).await.unwrap();
+			assert_eq!(tile, expected.map(Blob::from));
+		}
+
+		let reader = open_fallback_reader(&[url("old")], &cli.serve).await.unwrap();
+		assert_eq!(reader.get_container_name(), "directory");
+	}
+
+	#[test]
+	fn test_default_id() {
+		assert_eq!(super::default_id("../data/ukraine.versatiles"), "ukraine");
+		assert_eq!(
+			super::default_id("https://example.org/osm.shortbread.versatiles"),
+			"osm"
+		);
+		assert_eq!(super::default_id("C:\\tiles\\berlin.mbtiles"), "berlin");
+	}
+
 	#[test]
 	fn test_remote() {
 		run_command(vec![
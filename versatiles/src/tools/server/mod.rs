@@ -1,8 +0,0 @@
-//! server implementation
-
-mod sources;
-mod tile_server;
-mod utils;
-
-pub use tile_server::*;
-pub use utils::Url;
@@ -1,7 +0,0 @@
-//! helper function for handling URLs and MIME
-
-mod mime;
-mod url;
-
-pub use mime::*;
-pub use url::*;
@@ -0,0 +1,366 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::{imageops::FilterType, DynamicImage, Rgba};
+use versatiles_container::get_reader;
+use versatiles_core::{
+	types::{Blob, TileCoord3, TileFormat},
+	utils::decompress,
+};
+use versatiles_geometry::{vector_tile::VectorTile, Geometry};
+use versatiles_image::helper::blob2image;
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// tile container you want to preview
+	/// supported container formats are: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
+	#[arg(required = true, verbatim_doc_comment)]
+	filename: String,
+
+	/// zoom level of the tile
+	#[arg(required = true)]
+	z: u8,
+
+	/// column of the tile
+	#[arg(required = true)]
+	x: u32,
+
+	/// row of the tile
+	#[arg(required = true)]
+	y: u32,
+
+	/// how raster tiles are drawn: true color half blocks (ansi), sixel graphics or the kitty graphics protocol
+	#[arg(long, short, value_enum, default_value_t = Mode::Ansi)]
+	mode: Mode,
+
+	/// width of the preview in terminal columns
+	#[arg(long, short, value_name = "int", default_value_t = 64)]
+	width: u32,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+	Ansi,
+	Sixel,
+	Kitty,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	super::print_status(&format!(
+		"show tile {}/{}/{} of {:?}",
+		arguments.z, arguments.x, arguments.y, arguments.filename
+	));
+
+	let reader = get_reader(&arguments.filename).await?;
+	let parameters = reader.get_parameters().clone();
+
+	let coord = TileCoord3::new(arguments.x, arguments.y, arguments.z)?;
+	let Some(blob) = reader.get_tile_data(&coord).await? else {
+		bail!("tile {}/{}/{} not found", arguments.z, arguments.x, arguments.y);
+	};
+	let blob = decompress(blob, &parameters.tile_compression)?;
+
+	print!(
+		"{}",
+		render_tile(&blob, parameters.tile_format, arguments.mode, arguments.width)?
+	);
+
+	Ok(())
+}
+
+/// Renders a decompressed tile as text that can be printed to a terminal.
+fn render_tile(blob: &Blob, format: TileFormat, mode: Mode, width: u32) -> Result<String> {
+	if width < 2 {
+		bail!("width must be at least 2 columns");
+	}
+
+	use TileFormat::*;
+	match format {
+		JPG | PNG | WEBP => {
+			let image = blob2image(blob, format)?;
+			match mode {
+				Mode::Ansi => Ok(render_ansi(&image, width)),
+				Mode::Sixel => Ok(render_sixel(&image, width * 8)),
+				Mode::Kitty => render_kitty(&image, width),
+			}
+		}
+		PBF => render_vector(blob, width),
+		_ => bail!("can not preview tiles of format '{}'", format.as_str()),
+	}
+}
+
+/// Blends a pixel onto a black background.
+fn blend(pixel: &Rgba<u8>) -> [u8; 3] {
+	let a = pixel[3] as u16;
+	[0, 1, 2].map(|i| (pixel[i] as u16 * a / 255) as u8)
+}
+
+/// Draws the image with "upper half block" characters, so every character cell shows two pixels.
+fn render_ansi(image: &DynamicImage, width: u32) -> String {
+	let height = ((image.height() * width / image.width().max(1)).div_ceil(2) * 2).max(2);
+	let image = image.resize_exact(width, height, FilterType::Triangle).to_rgba8();
+
+	let mut text = String::new();
+	for y in (0..height).step_by(2) {
+		for x in 0..width {
+			let [r0, g0, b0] = blend(image.get_pixel(x, y));
+			let [r1, g1, b1] = blend(image.get_pixel(x, y + 1));
+			text.push_str(&format!("\x1b[38;2;{r0};{g0};{b0}m\x1b[48;2;{r1};{g1};{b1}m▀"));
+		}
+		text.push_str("\x1b[0m\n");
+	}
+	text
+}
+
+/// Encodes the image as sixel graphics, using a 6×6×6 color cube as palette.
+fn render_sixel(image: &DynamicImage, width: u32) -> String {
+	let height = (image.height() * width / image.width().max(1)).max(1);
+	let image = image.resize_exact(width, height, FilterType::Triangle).to_rgba8();
+
+	let palette_index = |pixel: &Rgba<u8>| -> Option<usize> {
+		if pixel[3] < 128 {
+			return None;
+		}
+		let [r, g, b] = [0, 1, 2].map(|i| (pixel[i] as usize * 5 + 127) / 255);
+		Some(r * 36 + g * 6 + b)
+	};
+
+	// P2=1: pixels without a color stay transparent
+	let mut text = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+	for i in 0..216 {
+		let (r, g, b) = (i / 36, (i / 6) % 6, i % 6);
+		text.push_str(&format!("#{i};2;{};{};{}", r * 20, g * 20, b * 20));
+	}
+
+	let mut row = vec![0u8; width as usize];
+	for y0 in (0..height).step_by(6) {
+		let band: Vec<Vec<Option<usize>>> = (y0..(y0 + 6).min(height))
+			.map(|y| (0..width).map(|x| palette_index(image.get_pixel(x, y))).collect())
+			.collect();
+
+		let mut colors: Vec<usize> = band.iter().flatten().flatten().copied().collect();
+		colors.sort_unstable();
+		colors.dedup();
+
+		for color in colors {
+			row.fill(0);
+			for (bit, line) in band.iter().enumerate() {
+				for (x, index) in line.iter().enumerate() {
+					if *index == Some(color) {
+						row[x] |= 1 << bit;
+					}
+				}
+			}
+
+			text.push_str(&format!("#{color}"));
+			let mut x = 0;
+			while x < row.len() {
+				let count = row[x..].iter().take_while(|v| **v == row[x]).count();
+				let char = (63 + row[x]) as char;
+				if count > 3 {
+					text.push_str(&format!("!{count}{char}"));
+				} else {
+					(0..count).for_each(|_| text.push(char));
+				}
+				x += count;
+			}
+			text.push('$');
+		}
+		text.push('-');
+	}
+	text.push_str("\x1b\\\n");
+	text
+}
+
+/// Sends the image as PNG via the kitty graphics protocol, scaled to the given number of columns.
+fn render_kitty(image: &DynamicImage, width: u32) -> Result<String> {
+	let png = versatiles_image::png::image2blob(image, false).context("encoding tile as PNG")?;
+	let data = BASE64.encode(png.as_slice());
+	let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+
+	let mut text = String::new();
+	for (i, chunk) in chunks.iter().enumerate() {
+		let more = u8::from(i + 1 < chunks.len());
+		let chunk = std::str::from_utf8(chunk)?;
+		if i == 0 {
+			text.push_str(&format!("\x1b_Ga=T,f=100,c={width},m={more};{chunk}\x1b\\"));
+		} else {
+			text.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+		}
+	}
+	text.push('\n');
+	Ok(text)
+}
+
+const LAYER_SYMBOLS: &[u8] = b"#*+o%@x=~&";
+
+/// Plots the geometries of all layers as ASCII lines, one symbol per layer, followed by a legend.
+fn render_vector(blob: &Blob, width: u32) -> Result<String> {
+	let tile = VectorTile::from_blob(blob).context("decoding vector tile")?;
+
+	// terminal characters are roughly twice as high as wide
+	let (width, height) = (width as usize, (width as usize).div_ceil(2));
+	let mut grid = vec![vec![b' '; width]; height];
+	let mut legend = String::new();
+
+	for (index, layer) in tile.layers.iter().enumerate() {
+		let symbol = LAYER_SYMBOLS[index % LAYER_SYMBOLS.len()];
+		let scale = [width as f64 / layer.extent as f64, height as f64 / layer.extent as f64];
+		let to_cell = |p: &[f64; 2]| [(p[0] * scale[0]).floor() as i64, (p[1] * scale[1]).floor() as i64];
+
+		let mut plot = |x: i64, y: i64| {
+			if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+				grid[y as usize][x as usize] = symbol;
+			}
+		};
+
+		for feature in layer.features.iter() {
+			let lines: Vec<Vec<[f64; 2]>> = match feature.to_geometry()? {
+				Geometry::Point(g) => vec![vec![g.0]],
+				Geometry::MultiPoint(g) => g.0.into_iter().map(|p| vec![p]).collect(),
+				Geometry::LineString(g) => vec![g.0],
+				Geometry::MultiLineString(g) => g.0,
+				Geometry::Polygon(g) => g.0,
+				Geometry::MultiPolygon(g) => g.0.concat(),
+			};
+
+			for line in lines {
+				let cells: Vec<[i64; 2]> = line.iter().map(to_cell).collect();
+				if let [cell] = cells.as_slice() {
+					plot(cell[0], cell[1]);
+				}
+				for pair in cells.windows(2) {
+					draw_line(pair[0], pair[1], &mut plot);
+				}
+			}
+		}
+
+		legend.push_str(&format!(
+			"{} {} ({} features)\n",
+			symbol as char,
+			layer.name,
+			layer.features.len()
+		));
+	}
+
+	let border = format!("+{}+\n", "-".repeat(width));
+	let mut text = border.clone();
+	for row in grid {
+		text.push('|');
+		text.push_str(std::str::from_utf8(&row)?);
+		text.push_str("|\n");
+	}
+	text.push_str(&border);
+	text.push_str(&legend);
+	Ok(text)
+}
+
+/// Bresenham's line algorithm
+fn draw_line(a: [i64; 2], b: [i64; 2], plot: &mut impl FnMut(i64, i64)) {
+	let (dx, dy) = ((b[0] - a[0]).abs(), -(b[1] - a[1]).abs());
+	let (sx, sy) = ((b[0] - a[0]).signum(), (b[1] - a[1]).signum());
+	let (mut x, mut y, mut err) = (a[0], a[1], dx + dy);
+	loop {
+		plot(x, y);
+		if x == b[0] && y == b[1] {
+			break;
+		}
+		let e2 = 2 * err;
+		if e2 >= dy {
+			err += dy;
+			x += sx;
+		}
+		if e2 <= dx {
+			err += dx;
+			y += sy;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::run_command;
+	use versatiles_geometry::vector_tile::{VectorTileFeature, VectorTileLayer};
+	use versatiles_image::helper::create_image_rgba;
+
+	#[test]
+	fn test_local() {
+		run_command(vec![
+			"versatiles",
+			"show",
+			"-q",
+			"../testdata/berlin.mbtiles",
+			"14",
+			"8803",
+			"5376",
+		])
+		.unwrap();
+	}
+
+	#[test]
+	fn test_missing_tile() {
+		let error = run_command(vec![
+			"versatiles",
+			"show",
+			"-q",
+			"../testdata/berlin.mbtiles",
+			"3",
+			"0",
+			"0",
+		])
+		.unwrap_err()
+		.to_string();
+		assert_eq!(error, "tile 3/0/0 not found");
+	}
+
+	#[test]
+	fn test_ansi() {
+		let text = render_ansi(&create_image_rgba(), 16);
+		assert_eq!(text.lines().count(), 8);
+		assert_eq!(text.matches('▀').count(), 128);
+		assert!(text.starts_with("\x1b[38;2;8;237;8m\x1b[48;2;8;223;21m▀"), "{text:?}");
+	}
+
+	#[test]
+	fn test_sixel() {
+		let text = render_sixel(&create_image_rgba(), 12);
+		assert!(text.starts_with("\x1bP0;1;0q\"1;1;12;12#0;2;0;0;0#1;2;0;0;20"));
+		assert!(text.ends_with("\x1b\\\n"));
+		assert_eq!(text.matches('-').count(), 2);
+	}
+
+	#[test]
+	fn test_kitty() {
+		let text = render_kitty(&create_image_rgba(), 20).unwrap();
+		assert!(text.starts_with("\x1b_Ga=T,f=100,c=20,m="));
+		assert!(text.contains(";iVBORw0KGgo"));
+		assert!(text.ends_with("\x1b\\\n"));
+		assert_eq!(text.matches("m=0;").count(), 1);
+	}
+
+	#[test]
+	fn test_vector() -> Result<()> {
+		let mut layer = VectorTileLayer::new_standard("roads");
+		layer.features.push(VectorTileFeature::from_geometry(
+			None,
+			vec![],
+			Geometry::new_line_string(vec![[0, 0], [4095, 4095]]),
+		)?);
+		let blob = VectorTile::new(vec![layer]).to_blob()?;
+
+		let text = render_tile(&blob, TileFormat::PBF, Mode::Ansi, 8)?;
+		assert_eq!(
+			text,
+			"+--------+\n|##      |\n|  ##    |\n|    ##  |\n|      ##|\n+--------+\n# roads (1 features)\n"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_unsupported_format() {
+		let error = render_tile(&Blob::from("{}"), TileFormat::JSON, Mode::Ansi, 8).unwrap_err();
+		assert_eq!(error.to_string(), "can not preview tiles of format 'json'");
+	}
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::path::Path;
+use versatiles_container::{get_reader, VersaTilesUpdater};
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true)]
+pub struct Subcommand {
+	/// *.versatiles container that is updated
+	#[arg()]
+	base_file: String,
+
+	/// container with the new tiles, e.g. a regional extract, replacing the tiles of the base.
+	/// supported container formats: *.versatiles, *.tar, *.pmtiles, *.comt, *.mbtiles, *.gpkg or a directory
+	#[arg(verbatim_doc_comment)]
+	diff_file: String,
+
+	/// new *.versatiles container. blocks without new tiles are copied byte-for-byte from the base
+	#[arg()]
+	output_file: String,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	super::print_status(&format!(
+		"update {:?} with {:?} to {:?}",
+		arguments.base_file, arguments.diff_file, arguments.output_file
+	));
+
+	let diff = get_reader(&arguments.diff_file).await?;
+	let output = std::env::current_dir()?.join(&arguments.output_file);
+	let report = VersaTilesUpdater::update_path(Path::new(&arguments.base_file), diff, &output).await?;
+
+	super::print_status(&format!(
+		"copied {} blocks with {} bytes, wrote {} blocks",
+		report.copied_blocks, report.copied_bytes, report.written_blocks
+	));
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::tests::run_command;
+	use anyhow::Result;
+	use versatiles_container::get_reader;
+	use versatiles_core::types::TileCoord3;
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_update() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+		let convert = |args: Vec<String>| {
+			std::thread::spawn(move || run_command(args.iter().map(|s| s.as_str()).collect()))
+				.join()
+				.unwrap()
+		};
+
+		for (name, bbox, compress) in [
+			("base", "13.0,52.3,13.8,52.7", "gzip"),
+			("diff", "13.3,52.4,13.4,52.5", "gzip"),
+			("diff_brotli", "13.3,52.4,13.4,52.5", "brotli"),
+		] {
+			convert(
+				[
+					"versatiles",
+					"convert",
+					"--max-zoom=12",
+					&format!("--bbox={bbox}"),
+					&format!("--compress={compress}"),
+					"../testdata/berlin.mbtiles",
+					&path(&format!("{name}.versatiles")),
+				]
+				.map(String::from)
+				.to_vec(),
+			)?;
+		}
+		convert(
+			[
+				"versatiles",
+				"update",
+				&path("base.versatiles"),
+				&path("diff.versatiles"),
+				&path("output.versatiles"),
+			]
+			.map(String::from)
+			.to_vec(),
+		)?;
+
+		let base = get_reader(&path("base.versatiles")).await?;
+		let output = get_reader(&path("output.versatiles")).await?;
+		assert_eq!(output.get_parameters().bbox_pyramid, base.get_parameters().bbox_pyramid);
+		let coord = TileCoord3::new(2200, 1343, 12)?;
+		assert!(output.get_tile_data(&coord).await?.is_some());
+		assert_eq!(output.get_tile_data(&coord).await?, base.get_tile_data(&coord).await?);
+
+		let error = convert(
+			[
+				"versatiles",
+				"update",
+				&path("base.versatiles"),
+				&path("diff_brotli.versatiles"),
+				&path("output2.versatiles"),
+			]
+			.map(String::from)
+			.to_vec(),
+		)
+		.unwrap_err();
+		assert!(error.to_string().starts_with("tile compression of"), "{error}");
+		Ok(())
+	}
+}
@@ -0,0 +1,121 @@
+use anyhow::{bail, ensure, Result};
+use reqwest::Url;
+use std::path::Path;
+use versatiles_container::VersaTilesReader;
+use versatiles_core::{io::DataReaderHttp, types::Blob};
+
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true, disable_version_flag = true, verbatim_doc_comment)]
+pub struct Subcommand {
+	/// *.versatiles file with checksums, e.g. written by "versatiles convert --checksum sha256".
+	/// can also be a http://... or https://... URL
+	#[arg(required = true, verbatim_doc_comment)]
+	input_file: String,
+
+	/// public Ed25519 key of the expected signer, as 64 hex characters.
+	/// without it, a signature only proves that the file was not changed after signing,
+	/// but not by whom it was signed
+	#[arg(long, value_name = "hex", verbatim_doc_comment)]
+	public_key: Option<String>,
+}
+
+#[tokio::main]
+pub async fn run(arguments: &Subcommand) -> Result<()> {
+	super::print_status(&format!("verify {:?}", arguments.input_file));
+
+	let public_key = arguments.public_key.as_deref().map(parse_public_key).transpose()?;
+
+	let filename = &arguments.input_file;
+	let reader = if filename.starts_with("http://") || filename.starts_with("https://") {
+		VersaTilesReader::open_reader(DataReaderHttp::from_url(Url::parse(filename)?)?).await?
+	} else {
+		VersaTilesReader::open_path(Path::new(filename)).await?
+	};
+
+	let report = reader.verify(public_key.as_deref()).await?;
+
+	super::print_status(&format!(
+		"checked {} blocks and {} bytes using {} checksums",
+		report.checked_blocks,
+		report.checked_bytes,
+		report.algorithm.as_str()
+	));
+	if let Some(signer) = &report.signer {
+		super::print_status(&format!("signed by {}", Blob::from(signer.clone()).as_hex()));
+	}
+	for error in report.errors.iter() {
+		super::print_status(&format!("error: {error}"));
+	}
+	if !report.is_valid() {
+		bail!(
+			"verification of {filename:?} failed with {} errors",
+			report.errors.len()
+		);
+	}
+
+	super::print_status("file is intact");
+	Ok(())
+}
+
+fn parse_public_key(hex: &str) -> Result<Vec<u8>> {
+	let hex = hex.trim();
+	ensure!(
+		hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+		"invalid public key {hex:?}, expected 64 hex characters"
+	);
+	Ok((0..32)
+		.map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap())
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::run_command;
+	use std::fs;
+
+	#[test]
+	fn test_parse_public_key() -> Result<()> {
+		let key = parse_public_key(&"0f".repeat(32))?;
+		assert_eq!(key, [15u8; 32]);
+		assert!(parse_public_key("0f0f").is_err());
+		assert!(parse_public_key(&"xy".repeat(32)).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let filename = dir.path().join("berlin.versatiles").to_str().unwrap().to_string();
+
+		run_command(vec![
+			"versatiles",
+			"convert",
+			"--max-zoom=8",
+			"--checksum=xxhash",
+			"../testdata/berlin.mbtiles",
+			&filename,
+		])?;
+		run_command(vec!["versatiles", "verify", &filename])?;
+
+		// flip one byte in the middle of the tiles
+		let mut data = fs::read(&filename)?;
+		let index = data.len() / 2;
+		data[index] ^= 0xff;
+		fs::write(&filename, data)?;
+		let error = run_command(vec!["versatiles", "verify", &filename]).unwrap_err();
+		assert!(error.to_string().contains("failed with 1 errors"), "{error}");
+
+		// files without checksums are rejected
+		run_command(vec![
+			"versatiles",
+			"convert",
+			"--max-zoom=8",
+			"../testdata/berlin.mbtiles",
+			&filename,
+		])?;
+		let error = run_command(vec!["versatiles", "verify", &filename]).unwrap_err();
+		assert!(error.to_string().contains("contains no checksums"), "{error}");
+		Ok(())
+	}
+}
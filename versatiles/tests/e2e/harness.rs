@@ -0,0 +1,127 @@
+//! Starts a tile server with sample containers and fetches tiles like a browser.
+
+use anyhow::{bail, Result};
+use reqwest::header::{HeaderMap, ACCEPT_ENCODING, CONTENT_ENCODING};
+use std::{net::SocketAddr, time::Duration};
+use versatiles::{
+	container::get_reader,
+	server::{TileCacheOptions, TileServer, TileSourceOptions},
+	types::{Blob, TileCompression, TileCoord3, TilesReaderTrait},
+	utils::decompress,
+};
+
+/// Sample containers of the repository, served at "/tiles/{id}/"
+pub const SAMPLES: [(&str, &str); 2] = [
+	("berlin_mbtiles", "../testdata/berlin.mbtiles"),
+	("berlin_pmtiles", "../testdata/berlin.pmtiles"),
+];
+
+/// How the server is configured
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerConfig {
+	/// Recompress tiles to the best compression the client accepts, like `versatiles serve --best`
+	pub best_compression: bool,
+	/// Keep encoded responses in memory, like `versatiles serve --cache-size`
+	pub tile_cache: bool,
+}
+
+/// A running tile server, serving all [`SAMPLES`]
+pub struct Harness {
+	server: TileServer,
+	pub addr: SocketAddr,
+	pub client: reqwest::Client,
+}
+
+impl Harness {
+	pub async fn start(config: ServerConfig) -> Result<Harness> {
+		let mut server = TileServer::new("127.0.0.1", 0, config.best_compression, true);
+		if config.tile_cache {
+			server.set_tile_cache(Some(TileCacheOptions {
+				max_bytes: 64 * 1024 * 1024,
+				ttl: None,
+			}));
+		}
+		for (id, filename) in SAMPLES {
+			server.add_tile_source(id, get_reader(filename).await?, TileSourceOptions::default())?;
+		}
+		server.start().await?;
+		let addr = server.local_addr().expect("server should be running");
+
+		let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?;
+		Ok(Harness { server, addr, client })
+	}
+
+	/// Fetches `path` with the request `headers`, e.g. "Accept-Encoding".
+	pub async fn fetch(&self, path: &str, headers: &[(&'static str, &str)]) -> Result<Response> {
+		fetch(&self.client, self.addr, path, headers).await
+	}
+
+	pub async fn stop(mut self) {
+		self.server.stop().await;
+	}
+}
+
+/// Fetches `path` from the server at `addr`. Bodies are not decoded, so the bytes on the wire can be checked.
+pub async fn fetch(
+	client: &reqwest::Client,
+	addr: SocketAddr,
+	path: &str,
+	headers: &[(&'static str, &str)],
+) -> Result<Response> {
+	let mut request = client.get(format!("http://{addr}{path}"));
+	for (name, value) in headers {
+		request = request.header(*name, *value);
+	}
+	let response = request.send().await?;
+	Ok(Response {
+		path: path.to_string(),
+		status: response.status().as_u16(),
+		headers: response.headers().clone(),
+		body: Blob::from(response.bytes().await?.to_vec()),
+	})
+}
+
+/// A response with the raw body
+#[derive(Clone, Debug)]
+pub struct Response {
+	pub path: String,
+	pub status: u16,
+	pub headers: HeaderMap,
+	pub body: Blob,
+}
+
+impl Response {
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.get(name).and_then(|value| value.to_str().ok())
+	}
+
+	/// Compression of the body, according to the "Content-Encoding" header
+	pub fn content_encoding(&self) -> Result<TileCompression> {
+		Ok(match self.header(CONTENT_ENCODING.as_str()) {
+			None => TileCompression::Uncompressed,
+			Some("gzip") => TileCompression::Gzip,
+			Some("br") => TileCompression::Brotli,
+			Some("zstd") => TileCompression::Zstd,
+			Some(encoding) => bail!("unexpected content encoding {encoding:?} of {}", self.path),
+		})
+	}
+
+	/// Decodes the body, like a browser does
+	pub fn decoded_body(&self) -> Result<Blob> {
+		decompress(self.body.clone(), &self.content_encoding()?)
+	}
+}
+
+/// Returns the uncompressed tile of a sample container, read directly from the file, or `None` if it doesn't exist.
+pub async fn expected_tile(reader: &dyn TilesReaderTrait, coord: &TileCoord3) -> Result<Option<Blob>> {
+	let compression = reader.get_parameters().tile_compression;
+	match reader.get_tile_data(coord).await? {
+		Some(blob) => Ok(Some(decompress(blob, &compression)?)),
+		None => Ok(None),
+	}
+}
+
+/// Request header of a browser
+pub fn accept_encoding(value: &str) -> (&'static str, &str) {
+	(ACCEPT_ENCODING.as_str(), value)
+}
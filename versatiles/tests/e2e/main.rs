@@ -0,0 +1,214 @@
+//! End-to-end tests of the tile server: a MapLibre GL JS session against the sample containers of the repository.
+//!
+//! Every response is checked like a browser would use it: status, headers, content encoding, caching and the
+//! decoded tile, which must match the tile in the container byte for byte.
+//!
+//! Run with: `cargo test -p versatiles --features test --test e2e`
+
+mod harness;
+mod session;
+
+use anyhow::Result;
+use harness::{expected_tile, Harness, Response, ServerConfig, SAMPLES};
+use session::{fetch_tiles, Browser, Source, BROWSERS, SCRIPT};
+use versatiles::{
+	container::get_reader,
+	types::{Blob, TileCompression},
+};
+
+/// Content encoding the server should choose for a `browser`, if the tiles are stored with `stored` compression.
+fn expected_encoding(config: &ServerConfig, browser: &Browser, stored: TileCompression) -> TileCompression {
+	use TileCompression::*;
+	let candidates = if config.best_compression {
+		vec![Brotli, Zstd, Gzip]
+	} else {
+		// without recompression, the tiles are only decompressed for clients that don't accept them
+		vec![stored]
+	};
+	candidates
+		.into_iter()
+		.find(|compression| browser.accepts(*compression))
+		.unwrap_or(Uncompressed)
+}
+
+/// Checks a tile response for a `browser` against the tile in the container.
+fn check_tile(
+	response: &Response,
+	expected: &Option<Blob>,
+	browser: &Browser,
+	encoding: TileCompression,
+) -> Result<()> {
+	let path = format!("{} requested by {}", response.path, browser.name);
+	let Some(expected) = expected else {
+		assert_eq!(response.status, 404, "{path}");
+		return Ok(());
+	};
+
+	assert_eq!(response.status, 200, "{path}");
+	assert_eq!(
+		response.header("content-type"),
+		Some("application/x-protobuf"),
+		"{path}"
+	);
+	assert_eq!(
+		response.header("cache-control"),
+		Some("public, max-age=2419200, no-transform"),
+		"{path}"
+	);
+	assert_eq!(response.header("vary"), Some("accept-encoding"), "{path}");
+	assert_eq!(response.header("access-control-allow-origin"), Some("*"), "{path}");
+	assert!(response.header("last-modified").is_some(), "{path}");
+	if let Some(length) = response.header("content-length") {
+		assert_eq!(length, response.body.len().to_string(), "{path}");
+	}
+
+	assert_eq!(response.content_encoding()?, encoding, "{path}");
+	let etag = response.header("etag").expect("tiles should have an ETag");
+	let suffix = match encoding {
+		TileCompression::Uncompressed => "\"",
+		TileCompression::Gzip => "-gzip\"",
+		TileCompression::Brotli => "-br\"",
+		TileCompression::Zstd => "-zstd\"",
+	};
+	assert!(etag.starts_with('"') && etag.ends_with(suffix), "{path}: {etag}");
+
+	assert_eq!(&response.decoded_body()?, expected, "{path}");
+	Ok(())
+}
+
+async fn replay_session(config: ServerConfig) -> Result<()> {
+	let harness = Harness::start(config).await?;
+
+	let index = harness.fetch("/tiles/index.json", &[]).await?;
+	assert_eq!(index.status, 200);
+	assert_eq!(index.body.as_str(), "[\"berlin_mbtiles\",\"berlin_pmtiles\"]");
+
+	for (id, filename) in SAMPLES {
+		let reader = get_reader(filename).await?;
+		let stored = reader.get_parameters().tile_compression;
+		let source = Source::load(&harness, id).await?;
+
+		for viewport in SCRIPT {
+			let coords = source.tiles(&viewport)?;
+			assert!(!coords.is_empty(), "{id}: no tiles for {viewport:?}");
+
+			let mut expected = Vec::new();
+			for coord in &coords {
+				expected.push(expected_tile(&*reader, coord).await?);
+			}
+			assert!(expected.iter().any(Option::is_some), "{id}: no tiles for {viewport:?}");
+
+			for browser in BROWSERS {
+				let responses = fetch_tiles(&harness, &source, &coords, &browser.headers()).await?;
+				let encoding = expected_encoding(&config, &browser, stored);
+				for (response, expected) in responses.iter().zip(&expected) {
+					check_tile(response, expected, &browser, encoding)?;
+				}
+			}
+		}
+	}
+
+	harness.stop().await;
+	Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session() -> Result<()> {
+	replay_session(ServerConfig::default()).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_with_best_compression() -> Result<()> {
+	replay_session(ServerConfig {
+		best_compression: true,
+		..Default::default()
+	})
+	.await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_with_tile_cache() -> Result<()> {
+	replay_session(ServerConfig {
+		tile_cache: true,
+		..Default::default()
+	})
+	.await
+}
+
+/// Browsers revalidate tiles in their HTTP cache with the ETag or the date of the last modification.
+#[tokio::test(flavor = "multi_thread")]
+async fn revalidation() -> Result<()> {
+	let harness = Harness::start(ServerConfig::default()).await?;
+	let source = Source::load(&harness, "berlin_mbtiles").await?;
+	let coords = source.tiles(&SCRIPT[2])?;
+	let chrome = BROWSERS[0];
+
+	let responses = fetch_tiles(&harness, &source, &coords, &chrome.headers()).await?;
+	assert!(responses.iter().any(|response| response.status == 200));
+	for response in responses.iter().filter(|response| response.status == 200) {
+		let etag = response.header("etag").unwrap();
+		let last_modified = response.header("last-modified").unwrap();
+
+		let mut headers = chrome.headers();
+		headers.push(("if-none-match", etag));
+		let revalidated = harness.fetch(&response.path, &headers).await?;
+		assert_eq!(revalidated.status, 304, "{}", response.path);
+		assert!(revalidated.body.is_empty());
+		assert_eq!(revalidated.header("etag"), Some(etag));
+		assert_eq!(revalidated.header("vary"), Some("accept-encoding"));
+
+		let mut headers = chrome.headers();
+		headers.push(("if-modified-since", last_modified));
+		assert_eq!(harness.fetch(&response.path, &headers).await?.status, 304);
+
+		// the uncompressed representation has another ETag
+		let revalidated = harness.fetch(&response.path, &[("if-none-match", etag)]).await?;
+		assert_eq!(revalidated.status, 200, "{}", response.path);
+		assert_ne!(revalidated.header("etag"), Some(etag));
+	}
+
+	harness.stop().await;
+	Ok(())
+}
+
+/// Repeated requests are answered from the tile cache of the server, with identical responses.
+#[tokio::test(flavor = "multi_thread")]
+async fn tile_cache() -> Result<()> {
+	let harness = Harness::start(ServerConfig {
+		tile_cache: true,
+		..Default::default()
+	})
+	.await?;
+	let source = Source::load(&harness, "berlin_pmtiles").await?;
+	let coords = source.tiles(&SCRIPT[1])?;
+	let mut headers = BROWSERS[1].headers();
+	headers.push(("x-versatiles-trace", "1"));
+
+	let is_cached = |response: &Response| {
+		response
+			.header("server-timing")
+			.is_some_and(|timing| timing.starts_with("cache;"))
+	};
+
+	let first = fetch_tiles(&harness, &source, &coords, &headers).await?;
+	let second = fetch_tiles(&harness, &source, &coords, &headers).await?;
+	assert!(first.iter().any(|response| response.status == 200));
+	for (first, second) in first.iter().zip(&second) {
+		if first.status != 200 {
+			continue;
+		}
+		assert!(!is_cached(first), "{}", first.path);
+		assert!(is_cached(second), "{}", second.path);
+		assert_eq!(first.body, second.body);
+		assert_eq!(first.header("etag"), second.header("etag"));
+		assert_eq!(first.header("content-encoding"), second.header("content-encoding"));
+
+		// cached responses are revalidated too
+		let mut headers = headers.clone();
+		headers.push(("if-none-match", first.header("etag").unwrap()));
+		assert_eq!(harness.fetch(&first.path, &headers).await?.status, 304);
+	}
+
+	harness.stop().await;
+	Ok(())
+}
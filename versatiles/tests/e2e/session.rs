@@ -0,0 +1,215 @@
+//! Simulates the tile requests of MapLibre GL JS while a user moves the map.
+
+use super::harness::{accept_encoding, fetch, Harness, Response};
+use anyhow::{ensure, Context, Result};
+use std::f64::consts::PI;
+use versatiles::{
+	json::JsonObject,
+	types::{GeoBBox, TileBBox, TileCompression, TileCoord3},
+};
+
+/// A browser, identified by the encodings it accepts
+#[derive(Clone, Copy, Debug)]
+pub struct Browser {
+	pub name: &'static str,
+	pub accept_encoding: Option<&'static str>,
+}
+
+pub const BROWSERS: [Browser; 4] = [
+	Browser {
+		name: "Chrome",
+		accept_encoding: Some("gzip, deflate, br, zstd"),
+	},
+	Browser {
+		name: "Safari",
+		accept_encoding: Some("gzip, deflate, br"),
+	},
+	Browser {
+		name: "old Firefox",
+		accept_encoding: Some("gzip, deflate"),
+	},
+	Browser {
+		name: "native client",
+		accept_encoding: None,
+	},
+];
+
+impl Browser {
+	pub fn headers(&self) -> Vec<(&'static str, &'static str)> {
+		self.accept_encoding.map(accept_encoding).into_iter().collect()
+	}
+
+	pub fn accepts(&self, compression: TileCompression) -> bool {
+		let encoding = match compression {
+			TileCompression::Uncompressed => return true,
+			TileCompression::Gzip => "gzip",
+			TileCompression::Brotli => "br",
+			TileCompression::Zstd => "zstd",
+		};
+		self
+			.accept_encoding
+			.is_some_and(|value| value.split(',').any(|e| e.trim() == encoding))
+	}
+}
+
+/// The visible part of the map
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+	pub lon: f64,
+	pub lat: f64,
+	pub zoom: f64,
+	pub width: u32,
+	pub height: u32,
+}
+
+/// A user opening a map of Berlin, panning around, zooming in beyond the maximum zoom level and out again
+pub const SCRIPT: [Viewport; 6] = [
+	Viewport {
+		lon: 13.40,
+		lat: 52.52,
+		zoom: 10.0,
+		width: 1280,
+		height: 800,
+	},
+	Viewport {
+		lon: 13.45,
+		lat: 52.50,
+		zoom: 11.3,
+		width: 1280,
+		height: 800,
+	},
+	Viewport {
+		lon: 13.38,
+		lat: 52.51,
+		zoom: 12.8,
+		width: 1280,
+		height: 800,
+	},
+	Viewport {
+		lon: 13.39,
+		lat: 52.51,
+		zoom: 14.6,
+		width: 1280,
+		height: 800,
+	},
+	Viewport {
+		lon: 13.39,
+		lat: 52.51,
+		zoom: 16.2,
+		width: 390,
+		height: 844,
+	},
+	Viewport {
+		lon: 13.30,
+		lat: 52.45,
+		zoom: 9.2,
+		width: 1280,
+		height: 800,
+	},
+];
+
+/// What MapLibre knows about a source from its TileJSON
+#[derive(Debug)]
+pub struct Source {
+	pub tile_url: String,
+	pub min_zoom: u8,
+	pub max_zoom: u8,
+	pub bounds: GeoBBox,
+}
+
+impl Source {
+	/// Requests the TileJSON of the source at `/tiles/{id}/tiles.json`, like MapLibre does when the style is loaded.
+	pub async fn load(harness: &Harness, id: &str) -> Result<Source> {
+		let response = harness.fetch(&format!("/tiles/{id}/tiles.json"), &[]).await?;
+		ensure!(
+			response.status == 200,
+			"tiles.json of {id} returned {}",
+			response.status
+		);
+		assert_eq!(response.header("content-type"), Some("application/json"));
+
+		let tilejson = JsonObject::parse_str(response.decoded_body()?.as_str())?;
+		let tiles = tilejson.get_string_vec("tiles")?.context("tiles are missing")?;
+		let bounds = tilejson
+			.get_number_array::<f64, 4>("bounds")?
+			.context("bounds are missing")?;
+		Ok(Source {
+			tile_url: tiles[0].clone(),
+			min_zoom: tilejson.get_number("minzoom")?.context("minzoom is missing")?,
+			max_zoom: tilejson.get_number("maxzoom")?.context("maxzoom is missing")?,
+			bounds: GeoBBox::new(bounds[0], bounds[1], bounds[2], bounds[3]),
+		})
+	}
+
+	/// Returns the tiles MapLibre requests for a `viewport`: tiles of 512 pixels at the integer zoom level,
+	/// limited to the zoom levels and the bounds of the source. Deeper zoom levels use the tiles of `max_zoom`.
+	pub fn tiles(&self, viewport: &Viewport) -> Result<Vec<TileCoord3>> {
+		let level = (viewport.zoom.floor() as u8).clamp(self.min_zoom, self.max_zoom);
+
+		// size of the world in pixels of 512 pixel tiles at the zoom of the viewport
+		let world_size = 512.0 * 2f64.powf(viewport.zoom);
+		let center_x = (viewport.lon + 180.0) / 360.0 * world_size;
+		let center_y =
+			(1.0 - (viewport.lat.to_radians().tan() + 1.0 / viewport.lat.to_radians().cos()).ln() / PI) / 2.0 * world_size;
+		let to_lonlat = |x: f64, y: f64| {
+			let lon = x / world_size * 360.0 - 180.0;
+			let lat = (PI * (1.0 - 2.0 * y / world_size)).sinh().atan().to_degrees();
+			(lon, lat)
+		};
+		let (west, north) = to_lonlat(
+			center_x - viewport.width as f64 / 2.0,
+			center_y - viewport.height as f64 / 2.0,
+		);
+		let (east, south) = to_lonlat(
+			center_x + viewport.width as f64 / 2.0,
+			center_y + viewport.height as f64 / 2.0,
+		);
+
+		let mut bbox = TileBBox::from_geo(level, &GeoBBox::new(west, south, east, north))?;
+		bbox.intersect_bbox(&TileBBox::from_geo(level, &self.bounds)?)?;
+		Ok(bbox.iter_coords().collect())
+	}
+
+	pub fn tile_path(&self, coord: &TileCoord3) -> String {
+		self
+			.tile_url
+			.replace("{z}", &coord.z.to_string())
+			.replace("{x}", &coord.x.to_string())
+			.replace("{y}", &coord.y.to_string())
+	}
+}
+
+/// Requests all tiles of a viewport in parallel, like MapLibre does, with additional request `headers`.
+/// The responses are in the order of `coords`.
+pub async fn fetch_tiles(
+	harness: &Harness,
+	source: &Source,
+	coords: &[TileCoord3],
+	headers: &[(&'static str, &str)],
+) -> Result<Vec<Response>> {
+	let mut tasks = tokio::task::JoinSet::new();
+	for (index, coord) in coords.iter().enumerate() {
+		let client = harness.client.clone();
+		let addr = harness.addr;
+		let path = source.tile_path(coord);
+		let headers = headers
+			.iter()
+			.map(|(name, value)| (*name, value.to_string()))
+			.collect::<Vec<_>>();
+		tasks.spawn(async move {
+			let headers = headers
+				.iter()
+				.map(|(name, value)| (*name, value.as_str()))
+				.collect::<Vec<_>>();
+			(index, fetch(&client, addr, &path, &headers).await)
+		});
+	}
+
+	let mut responses = Vec::new();
+	while let Some(result) = tasks.join_next().await {
+		let (index, response) = result?;
+		responses.push((index, response?));
+	}
+	responses.sort_by_key(|(index, _)| *index);
+	Ok(responses.into_iter().map(|(_, response)| response).collect())
+}
@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use versatiles_container::{
+	ChecksumAlgorithm, MockTilesReader, MockTilesReaderOptions, VersaTilesWriter, VersaTilesWriterOptions,
+};
+use versatiles_core::{
+	io::DataWriterBlob,
+	types::{TileBBox, TileBBoxPyramid, TileCompression, TileFormat, TilesReaderParameters},
+};
+
+/// Writes 8 blocks of 2048 random tiles with 4 to 12 KB each, so that serializing the blocks takes most of the time.
+fn write(workers: usize) {
+	let mut pyramid = TileBBoxPyramid::new_empty();
+	pyramid.set_level_bbox(TileBBox::new(12, 0, 0, 2047, 7).unwrap());
+	let parameters = TilesReaderParameters::new(TileFormat::BIN, TileCompression::Uncompressed, pyramid);
+	let mut reader = MockTilesReader::new_mock_with_options(
+		parameters,
+		MockTilesReaderOptions {
+			random_size: Some(4096..12288),
+			..Default::default()
+		},
+	)
+	.unwrap();
+	let options = VersaTilesWriterOptions {
+		checksum: Some(ChecksumAlgorithm::Sha256),
+		workers: Some(workers),
+		..Default::default()
+	};
+
+	let runtime = tokio::runtime::Runtime::new().unwrap();
+	runtime
+		.block_on(VersaTilesWriter::write_to_writer_with_options(
+			&mut reader,
+			&mut DataWriterBlob::new().unwrap(),
+			&options,
+		))
+		.unwrap();
+}
+
+fn bench_write_blocks(c: &mut Criterion) {
+	let mut group = c.benchmark_group("VersaTilesWriter");
+	group.bench_function("1 worker", |b| b.iter(|| write(1)));
+	group.bench_function(format!("{} workers", num_cpus::get()), |b| {
+		b.iter(|| write(num_cpus::get()))
+	});
+	group.finish();
+}
+
+criterion_group!(
+	name = benches;
+	config = Criterion::default().sample_size(10);
+	targets = bench_write_blocks
+);
+criterion_main!(benches);
@@ -0,0 +1,248 @@
+//! Builds a tile container from tiles that are added one by one, e.g. by a tile generator or by bindings
+//! to other languages, without writing an intermediate container like MBTiles first.
+//!
+//! Added tiles are appended to a temporary file, so that only their index is kept in memory.
+//! [`TileCollector::finalize`] writes them into any container format supported by [`write_to_filename`].
+//!
+//! ```rust
+//! use versatiles_container::TileCollector;
+//! use versatiles_core::types::{Blob, TileCompression, TileCoord3, TileFormat};
+//! use anyhow::Result;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let mut collector = TileCollector::new(TileFormat::PNG, TileCompression::Uncompressed)?;
+//!     collector.add_tile(&TileCoord3::new(0, 0, 0)?, Blob::from("a png image"))?;
+//!     collector.finalize("../testdata/temp3.versatiles").await?;
+//!     Ok(())
+//! }
+//! ```
+
+use super::write_to_filename;
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use std::{
+	fs::File,
+	io::{BufWriter, Write},
+	path::PathBuf,
+	sync::atomic::{AtomicU64, Ordering},
+};
+use versatiles_core::{
+	io::{DataReaderFile, DataReaderTrait},
+	tilejson::TileJSON,
+	types::*,
+	utils::get_temp_dir,
+};
+
+/// Added tiles are written to the temporary file in batches of this size.
+const BATCH_SIZE: usize = 4 * 1024 * 1024;
+
+/// Collects tiles and writes them into a tile container.
+pub struct TileCollector {
+	parameters: TilesReaderParameters,
+	tilejson: TileJSON,
+	/// position of every tile in the temporary file
+	index: TileMap<ByteRange>,
+	file: TempFile,
+	writer: BufWriter<File>,
+	size: u64,
+}
+
+impl TileCollector {
+	/// Creates an empty collector for tiles of `tile_format`, that are compressed with `tile_compression`.
+	pub fn new(tile_format: TileFormat, tile_compression: TileCompression) -> Result<TileCollector> {
+		let file = TempFile::new();
+		let writer = BufWriter::with_capacity(
+			BATCH_SIZE,
+			File::create(&file.path).with_context(|| format!("Failed to create temporary file {:?}", file.path))?,
+		);
+		Ok(TileCollector {
+			parameters: TilesReaderParameters::new(tile_format, tile_compression, TileBBoxPyramid::new_empty()),
+			tilejson: TileJSON::default(),
+			index: TileMap::new(),
+			file,
+			writer,
+			size: 0,
+		})
+	}
+
+	/// Sets the metadata of the container, e.g. `vector_layers` or `attribution`.
+	/// Zoom levels and bounds are set from the added tiles.
+	pub fn set_tilejson(&mut self, tilejson: TileJSON) {
+		self.tilejson = tilejson;
+	}
+
+	/// Adds a tile, already compressed with the compression of the collector.
+	/// A tile that is added again replaces the previous one.
+	pub fn add_tile(&mut self, coord: &TileCoord3, blob: Blob) -> Result<()> {
+		ensure!(coord.is_valid(), "invalid tile coordinate {coord:?}");
+		self.writer.write_all(blob.as_slice())?;
+		self.index.insert(coord, ByteRange::new(self.size, blob.len()));
+		self.size += blob.len();
+		self.parameters.bbox_pyramid.include_coord(coord);
+		Ok(())
+	}
+
+	/// Adds several tiles at once, e.g. to reduce the number of calls from other languages.
+	pub fn add_tiles(&mut self, tiles: Vec<(TileCoord3, Blob)>) -> Result<()> {
+		for (coord, blob) in tiles {
+			self.add_tile(&coord, blob)?;
+		}
+		Ok(())
+	}
+
+	/// Returns the number of tiles.
+	pub fn len(&self) -> usize {
+		self.index.len()
+	}
+
+	/// Returns `true` if no tiles have been added.
+	pub fn is_empty(&self) -> bool {
+		self.index.is_empty()
+	}
+
+	/// Writes all tiles into the container `filename` and removes the temporary file.
+	pub async fn finalize(mut self, filename: &str) -> Result<()> {
+		self.writer.flush()?;
+		self.tilejson.update_from_pyramid(&self.parameters.bbox_pyramid);
+		let mut reader = CollectedTilesReader {
+			parameters: self.parameters,
+			tilejson: self.tilejson,
+			index: self.index,
+			data: DataReaderFile::open(&self.file.path)?,
+		};
+		write_to_filename(&mut reader, filename).await
+	}
+}
+
+impl std::fmt::Debug for TileCollector {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("TileCollector")
+			.field("parameters", &self.parameters)
+			.field("tiles", &self.index.len())
+			.field("size", &self.size)
+			.finish()
+	}
+}
+
+/// A temporary file that is removed when dropped.
+struct TempFile {
+	path: PathBuf,
+}
+
+impl TempFile {
+	fn new() -> TempFile {
+		static COUNTER: AtomicU64 = AtomicU64::new(0);
+		let name = format!(
+			"versatiles-collector-{}-{}",
+			std::process::id(),
+			COUNTER.fetch_add(1, Ordering::Relaxed)
+		);
+		TempFile {
+			path: get_temp_dir().join(name),
+		}
+	}
+}
+
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.path);
+	}
+}
+
+/// Reads the collected tiles from the temporary file.
+#[derive(Debug)]
+struct CollectedTilesReader {
+	parameters: TilesReaderParameters,
+	tilejson: TileJSON,
+	index: TileMap<ByteRange>,
+	data: Box<DataReaderFile>,
+}
+
+#[async_trait]
+impl TilesReaderTrait for CollectedTilesReader {
+	fn get_source_name(&self) -> &str {
+		"collected tiles"
+	}
+
+	fn get_container_name(&self) -> &str {
+		"collector"
+	}
+
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn override_compression(&mut self, tile_compression: TileCompression) {
+		self.parameters.tile_compression = tile_compression;
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		match self.index.get(coord) {
+			Some(range) => Ok(Some(self.data.read_range(range).await?)),
+			None => Ok(None),
+		}
+	}
+
+	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let mut coords: Vec<TileCoord3> = self.index.coords().filter(|coord| bbox.contains3(coord)).collect();
+		coords.sort_by_key(TileCoord3::get_sort_index);
+		let mut tiles = Vec::with_capacity(coords.len());
+		for coord in coords {
+			let range = self.index.get(&coord).unwrap();
+			tiles.push((coord, self.data.read_range(range).await.unwrap()));
+		}
+		TileStream::from_vec(tiles)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::get_reader;
+
+	#[tokio::test]
+	async fn test_collector() -> Result<()> {
+		let dir = assert_fs::TempDir::new()?;
+		let filename = dir.path().join("collected.versatiles").to_str().unwrap().to_string();
+
+		let mut collector = TileCollector::new(TileFormat::PBF, TileCompression::Gzip)?;
+		let path = collector.file.path.clone();
+		collector.add_tile(&TileCoord3::new(0, 0, 0)?, Blob::from("zero"))?;
+		collector.add_tiles(vec![
+			(TileCoord3::new(1, 0, 1)?, Blob::from("one")),
+			(TileCoord3::new(0, 1, 1)?, Blob::from("old")),
+			(TileCoord3::new(0, 1, 1)?, Blob::from("two")),
+		])?;
+		assert!(collector
+			.add_tile(&TileCoord3 { x: 2, y: 0, z: 0 }, Blob::from("x"))
+			.is_err());
+		assert_eq!(collector.len(), 3);
+		assert!(path.exists());
+
+		collector.finalize(&filename).await?;
+		assert!(!path.exists());
+
+		let reader = get_reader(&filename).await?;
+		let parameters = reader.get_parameters();
+		assert_eq!(parameters.tile_format, TileFormat::PBF);
+		assert_eq!(parameters.tile_compression, TileCompression::Gzip);
+		assert_eq!(
+			parameters.bbox_pyramid.to_string(),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4)]"
+		);
+		assert_eq!(
+			reader.get_tilejson().as_string(),
+			"{\"bounds\":[-180,-85.05112877980659,180,85.05112877980659],\"maxzoom\":1,\"minzoom\":0,\"tilejson\":\"3.0.0\"}"
+		);
+		let coord = |x, y, z| TileCoord3::new(x, y, z).unwrap();
+		assert_eq!(reader.get_tile_data(&coord(0, 0, 0)).await?.unwrap().as_str(), "zero");
+		assert_eq!(reader.get_tile_data(&coord(0, 1, 1)).await?.unwrap().as_str(), "two");
+		assert!(reader.get_tile_data(&coord(0, 0, 1)).await?.is_none());
+		Ok(())
+	}
+}
@@ -0,0 +1,230 @@
+//! Estimates how the size of a container would change with another tile compression.
+//!
+//! A sample of tiles, spread evenly over all tiles of the bbox pyramid, is recompressed with several
+//! compressions. Comparing the sizes guides the choice of `--compress` and `--compression-levels`
+//! before running an expensive conversion.
+//!
+//! ```no_run
+//! use versatiles_container::{advise_compression, get_reader};
+//! use anyhow::Result;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let reader = get_reader("planet.versatiles").await?;
+//!     let advice = advise_compression(&*reader, 100).await?;
+//!     if let Some(text) = advice.get_recommendation() {
+//!         println!("{text}");
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::Result;
+use versatiles_core::{
+	types::{Blob, TileCompression, TileCoord3, TileFormat, TilesReaderTrait},
+	utils::{compress_brotli_quality, compress_gzip_level, compress_zstd_level, decompress},
+};
+
+/// Size of the sampled tiles with one compression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressionCandidate {
+	pub compression: TileCompression,
+	/// compression level, `None` for uncompressed tiles
+	pub level: Option<u32>,
+	/// sum of the sizes of the recompressed tiles
+	pub bytes: u64,
+}
+
+impl CompressionCandidate {
+	/// Returns a name like "brotli-10" or "none".
+	pub fn get_name(&self) -> String {
+		match self.level {
+			Some(level) => format!("{}-{level}", self.compression.as_str()),
+			None => self.compression.as_str().to_string(),
+		}
+	}
+}
+
+/// Sizes of the sampled tiles with their current and other compressions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressionAdvice {
+	pub tile_format: TileFormat,
+	/// current compression of the tiles
+	pub compression: TileCompression,
+	/// number of sampled tiles
+	pub tile_count: u64,
+	/// sum of the current sizes of the sampled tiles
+	pub bytes: u64,
+	pub candidates: Vec<CompressionCandidate>,
+}
+
+impl CompressionAdvice {
+	/// Returns the relative size change of `candidate`, e.g. `-0.18` if it is 18% smaller than the current tiles.
+	pub fn get_change(&self, candidate: &CompressionCandidate) -> f64 {
+		if self.bytes == 0 {
+			return 0.0;
+		}
+		candidate.bytes as f64 / self.bytes as f64 - 1.0
+	}
+
+	/// Returns the smallest candidate.
+	pub fn get_best(&self) -> Option<&CompressionCandidate> {
+		self.candidates.iter().min_by_key(|c| c.bytes)
+	}
+
+	/// Returns a sentence recommending the smallest candidate, if it saves at least 1%.
+	pub fn get_recommendation(&self) -> Option<String> {
+		let best = self.get_best()?;
+		let change = self.get_change(best);
+		if change > -0.01 {
+			return None;
+		}
+		Some(format!(
+			"switching {} tiles from {} to {} would save ~{:.0}%",
+			self.tile_format.as_str(),
+			self.compression.as_str(),
+			best.get_name(),
+			-change * 100.0
+		))
+	}
+}
+
+/// Compressions that are compared, with their levels.
+const CANDIDATES: [(TileCompression, Option<u32>); 5] = [
+	(TileCompression::Uncompressed, None),
+	(TileCompression::Gzip, Some(9)),
+	(TileCompression::Zstd, Some(19)),
+	(TileCompression::Brotli, Some(10)),
+	(TileCompression::Brotli, Some(11)),
+];
+
+/// Recompresses up to `sample_size` tiles of `reader` with other compressions and sums their sizes.
+/// Sampled coordinates without a tile are skipped.
+pub async fn advise_compression(reader: &dyn TilesReaderTrait, sample_size: u64) -> Result<CompressionAdvice> {
+	let parameters = reader.get_parameters();
+	let mut advice = CompressionAdvice {
+		tile_format: parameters.tile_format,
+		compression: parameters.tile_compression,
+		tile_count: 0,
+		bytes: 0,
+		candidates: CANDIDATES
+			.iter()
+			.map(|(compression, level)| CompressionCandidate {
+				compression: *compression,
+				level: *level,
+				bytes: 0,
+			})
+			.collect(),
+	};
+
+	for coord in sample_coords(reader, sample_size)? {
+		let Some(blob) = reader.get_tile_data(&coord).await? else {
+			continue;
+		};
+		advice.tile_count += 1;
+		advice.bytes += blob.len();
+
+		let blob = decompress(blob, &advice.compression)?;
+		for candidate in advice.candidates.iter_mut() {
+			candidate.bytes += recompress(&blob, candidate)?.len();
+		}
+	}
+
+	Ok(advice)
+}
+
+fn recompress(blob: &Blob, candidate: &CompressionCandidate) -> Result<Blob> {
+	match (candidate.compression, candidate.level) {
+		(TileCompression::Gzip, Some(level)) => compress_gzip_level(blob, level),
+		(TileCompression::Brotli, Some(level)) => compress_brotli_quality(blob, level),
+		(TileCompression::Zstd, Some(level)) => compress_zstd_level(blob, level),
+		_ => Ok(blob.clone()),
+	}
+}
+
+/// Returns up to `sample_size` coordinates, spread evenly over all tiles of the bbox pyramid.
+fn sample_coords(reader: &dyn TilesReaderTrait, sample_size: u64) -> Result<Vec<TileCoord3>> {
+	let levels: Vec<_> = reader
+		.get_parameters()
+		.bbox_pyramid
+		.iter_levels()
+		.filter(|bbox| !bbox.is_empty())
+		.cloned()
+		.collect();
+	let total: u64 = levels.iter().map(|bbox| bbox.count_tiles()).sum();
+	if total == 0 || sample_size == 0 {
+		return Ok(Vec::new());
+	}
+
+	let sample_size = sample_size.min(total);
+	let mut coords = Vec::with_capacity(sample_size as usize);
+	let mut levels = levels.iter();
+	let mut bbox = levels.next().unwrap();
+	let mut level_start = 0;
+	for i in 0..sample_size {
+		// the center of the i-th of `sample_size` equal parts of all tiles
+		let mut index = ((2 * i + 1) as u128 * total as u128 / (2 * sample_size) as u128) as u64;
+		while index >= level_start + bbox.count_tiles() {
+			level_start += bbox.count_tiles();
+			bbox = levels.next().unwrap();
+		}
+		index -= level_start;
+		let width = bbox.width() as u64;
+		coords.push(TileCoord3::new(
+			bbox.x_min + (index % width) as u32,
+			bbox.y_min + (index / width) as u32,
+			bbox.level,
+		)?);
+	}
+	Ok(coords)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{get_reader, MockTilesReader};
+	use versatiles_core::types::{TileBBoxPyramid, TilesReaderParameters};
+
+	fn reader(format: TileFormat, compression: TileCompression) -> Result<MockTilesReader> {
+		MockTilesReader::new_mock(TilesReaderParameters::new(
+			format,
+			compression,
+			TileBBoxPyramid::new_full(4),
+		))
+	}
+
+	#[test]
+	fn test_sample_coords() -> Result<()> {
+		let reader = reader(TileFormat::PBF, TileCompression::Gzip)?;
+		let coords = sample_coords(&reader, 5)?;
+		let coords: Vec<String> = coords.iter().map(|c| format!("{}/{}/{}", c.z, c.x, c.y)).collect();
+		// 341 tiles: 1 + 4 + 16 + 64 + 256
+		assert_eq!(coords, ["3/5/1", "4/1/1", "4/5/5", "4/9/9", "4/13/13"]);
+
+		assert_eq!(sample_coords(&reader, 1000)?.len(), 341);
+		assert!(sample_coords(&reader, 0)?.is_empty());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_advise_compression() -> Result<()> {
+		let berlin = get_reader("../testdata/berlin.mbtiles").await?;
+		let advice = advise_compression(&*berlin, 20).await?;
+		// sampled coordinates without a tile are skipped
+		assert_eq!(advice.tile_count, 15);
+		assert_eq!(advice.compression, TileCompression::Gzip);
+		let names: Vec<String> = advice.candidates.iter().map(|c| c.get_name()).collect();
+		assert_eq!(names, ["none", "gzip-9", "zstd-19", "brotli-10", "brotli-11"]);
+		assert!(advice.get_change(&advice.candidates[0]) > 0.0);
+		assert!(advice.get_change(&advice.candidates[3]) < 0.0);
+		assert!(advice
+			.get_recommendation()
+			.unwrap()
+			.starts_with("switching pbf tiles from gzip to brotli-"));
+
+		let advice = advise_compression(&reader(TileFormat::PBF, TileCompression::Gzip)?, 0).await?;
+		assert_eq!(advice.tile_count, 0);
+		assert_eq!(advice.get_recommendation(), None);
+		Ok(())
+	}
+}
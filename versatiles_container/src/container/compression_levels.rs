@@ -0,0 +1,144 @@
+//! Compression levels per zoom range.
+//!
+//! Low zoom levels contain few tiles that are requested often, high zoom levels contain most of the tiles.
+//! So it can pay off to compress low zoom levels as small as possible and high zoom levels as fast as possible,
+//! e.g. `0-8:11,13-:5` for brotli. All tiles of a container still use the same compression algorithm, since
+//! containers declare a single tile compression.
+
+use anyhow::{ensure, Context, Result};
+use std::{fmt, ops::RangeInclusive};
+use versatiles_core::{types::TileCompression, utils::parse_zoom_range};
+
+/// Compression levels for ranges of zoom levels. Zoom levels without a range use the default level.
+#[derive(Clone, Default, PartialEq)]
+pub struct CompressionLevels {
+	ranges: Vec<(RangeInclusive<u8>, u32)>,
+}
+
+impl CompressionLevels {
+	/// Parses a comma separated list of `zoom_range:level` items, e.g. `0-8:11,9-12:8,13-:5`.
+	///
+	/// A zoom range is a single zoom level (`5`), a closed range (`0-8`) or an open range (`13-` or `-4`).
+	/// An item without a zoom range, e.g. `6`, applies to all zoom levels. If ranges overlap, the first one wins.
+	pub fn parse_str(text: &str) -> Result<CompressionLevels> {
+		let mut ranges = Vec::new();
+		for item in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+			let (zooms, level) = match item.split_once(':') {
+				Some((zooms, level)) => (parse_zoom_range(zooms.trim())?, level.trim()),
+				None => (0..=31, item),
+			};
+			let level = level
+				.parse::<u32>()
+				.with_context(|| format!("compression level {level:?} is not a number"))?;
+			ranges.push((zooms, level));
+		}
+		Ok(CompressionLevels { ranges })
+	}
+
+	/// Returns `true` if no levels are defined.
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	/// Returns the compression level for a zoom level, or `None` to use the default level.
+	pub fn get_level(&self, zoom: u8) -> Option<u32> {
+		self
+			.ranges
+			.iter()
+			.find(|(zooms, _)| zooms.contains(&zoom))
+			.map(|(_, level)| *level)
+	}
+
+	/// Returns the zoom ranges and their levels.
+	pub fn iter(&self) -> impl Iterator<Item = &(RangeInclusive<u8>, u32)> {
+		self.ranges.iter()
+	}
+
+	/// Checks that all levels are supported by the compression.
+	pub fn check(&self, compression: &TileCompression) -> Result<()> {
+		let (min, max) = match compression {
+			TileCompression::Uncompressed => {
+				ensure!(self.is_empty(), "compression levels need a compression");
+				return Ok(());
+			}
+			TileCompression::Gzip => (0, 9),
+			TileCompression::Brotli => (0, 11),
+			TileCompression::Zstd => (1, 22),
+		};
+		for (_, level) in self.ranges.iter() {
+			ensure!(
+				(min..=max).contains(level),
+				"{} compression level must be between {min} and {max}, but is {level}",
+				compression.as_str()
+			);
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Debug for CompressionLevels {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let items: Vec<String> = self
+			.ranges
+			.iter()
+			.map(|(zooms, level)| format!("{}-{}:{level}", zooms.start(), zooms.end()))
+			.collect();
+		f.write_str(&items.join(","))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse() -> Result<()> {
+		let levels = CompressionLevels::parse_str("0-8:11, 10:8,13-:5")?;
+		assert_eq!(format!("{levels:?}"), "0-8:11,10-10:8,13-31:5");
+		assert_eq!(levels.get_level(0), Some(11));
+		assert_eq!(levels.get_level(8), Some(11));
+		assert_eq!(levels.get_level(9), None);
+		assert_eq!(levels.get_level(10), Some(8));
+		assert_eq!(levels.get_level(20), Some(5));
+
+		let levels = CompressionLevels::parse_str("-4:9,6")?;
+		assert_eq!(format!("{levels:?}"), "0-4:9,0-31:6");
+		assert_eq!(levels.get_level(3), Some(9));
+		assert_eq!(levels.get_level(5), Some(6));
+
+		assert!(CompressionLevels::parse_str("")?.is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn test_errors() {
+		let error = |text: &str| CompressionLevels::parse_str(text).unwrap_err().to_string();
+		assert_eq!(error("0-8:x"), "compression level \"x\" is not a number");
+		assert_eq!(error("8-2:5"), "zoom range \"8-2\" is empty");
+		assert_eq!(error("40:5"), "zoom level must be between 0 and 31, but is 40");
+		assert_eq!(error(":5"), "zoom range is missing");
+	}
+
+	#[test]
+	fn test_check() -> Result<()> {
+		let levels = CompressionLevels::parse_str("0-8:11,9-:6")?;
+		assert!(levels.check(&TileCompression::Brotli).is_ok());
+		assert_eq!(
+			levels.check(&TileCompression::Gzip).unwrap_err().to_string(),
+			"gzip compression level must be between 0 and 9, but is 11"
+		);
+		assert!(levels.check(&TileCompression::Zstd).is_ok());
+		assert_eq!(
+			CompressionLevels::parse_str("0")?
+				.check(&TileCompression::Zstd)
+				.unwrap_err()
+				.to_string(),
+			"zstd compression level must be between 1 and 22, but is 0"
+		);
+		assert!(levels.check(&TileCompression::Uncompressed).is_err());
+		assert!(CompressionLevels::default()
+			.check(&TileCompression::Uncompressed)
+			.is_ok());
+		Ok(())
+	}
+}
@@ -0,0 +1,15 @@
+//! Provides functionality for reading and writing tile data in a COMTiles (Cloud Optimized Map Tiles) container.
+//!
+//! This module contains the primary components for working with COMTiles containers:
+//! - `COMTilesReader` for reading tile data from files and URLs.
+//! - `COMTilesWriter` for writing tile data.
+//!
+//! COMTiles files use the extensions `.comt` and `.com`. Only the WebMercatorQuad tile matrix set
+//! with row-major ordering of fragments and tiles is supported.
+
+mod reader;
+mod types;
+mod writer;
+
+pub use reader::COMTilesReader;
+pub use writer::COMTilesWriter;
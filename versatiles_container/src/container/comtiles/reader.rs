@@ -0,0 +1,292 @@
+//! Provides functionality for reading tile data from a COMTiles container.
+//!
+//! The `COMTilesReader` struct is the primary component of this module, offering methods to read metadata and tile data from a COMTiles container.
+//!
+//! ## Features
+//! - Reads the index fragment by fragment, so only small parts of the index are loaded, even over HTTP
+//! - Caches the loaded index fragments
+//! - Reads all tiles of a fragment with a single request when streaming a bounding box
+//!
+//! ## Usage Example
+//! ```rust,no_run
+//! use versatiles_container::COMTilesReader;
+//! use versatiles_core::types::{TileCoord3, TilesReaderTrait};
+//! use std::path::Path;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let reader = COMTilesReader::open_path(Path::new("/absolute/path/to/tiles.comt")).await?;
+//!
+//!     if let Some(tile_data) = reader.get_tile_data(&TileCoord3::new(1, 1, 1)?).await? {
+//!         println!("Tile data: {:?}", tile_data);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Errors
+//! - Returns errors if the container file does not exist, if the header or metadata are invalid, or if there are issues reading the data.
+
+use super::types::{read_entry, HeaderCOMT, MetadataCOMT, TileMatrix};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use futures::{lock::Mutex, StreamExt};
+use std::{path::Path, sync::Arc};
+#[cfg(feature = "cli")]
+use versatiles_core::utils::PrettyPrint;
+use versatiles_core::{io::*, tilejson::TileJSON, types::*};
+
+/// Tiles that are at most this far apart are read with a single request.
+const MAX_SPAN_LENGTH: u64 = 64 * 1024 * 1024;
+
+/// A tile matrix with the position of the index entries of each fragment.
+#[derive(Debug)]
+struct Level {
+	matrix: TileMatrix,
+	/// bounding box and number of the first index entry of every fragment
+	fragments: Vec<(TileBBox, u64)>,
+}
+
+/// A struct that provides functionality to read tile data from a COMTiles container.
+#[derive(Debug)]
+pub struct COMTilesReader {
+	pub data_reader: DataReader,
+	pub header: HeaderCOMT,
+	pub tile_offset_bytes: u8,
+	pub tilejson: TileJSON,
+	pub parameters: TilesReaderParameters,
+	levels: Vec<Level>,
+	fragment_cache: Mutex<LimitedCache<(u8, usize), Arc<Blob>>>,
+}
+
+impl COMTilesReader {
+	/// Opens a COMTiles container from a file.
+	///
+	/// # Errors
+	/// Returns an error if the file does not exist or is not a valid COMTiles container.
+	pub async fn open_path(path: &Path) -> Result<COMTilesReader> {
+		COMTilesReader::open_reader(DataReaderFile::open(path)?).await
+	}
+
+	/// Opens a COMTiles container from a `DataReader`, e.g. a file or an HTTP URL.
+	///
+	/// # Errors
+	/// Returns an error if the header or the metadata are invalid or cannot be read.
+	pub async fn open_reader(data_reader: DataReader) -> Result<COMTilesReader> {
+		let header = HeaderCOMT::deserialize(&data_reader.read_range(&ByteRange::new(0, HeaderCOMT::len())).await?)?;
+		let metadata = MetadataCOMT::from_blob(&data_reader.read_range(&header.metadata_range()).await?)?;
+
+		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
+		let mut levels = Vec::new();
+		let mut entry_count = 0;
+		for matrix in metadata.tile_matrices {
+			bbox_pyramid.include_bbox(&matrix.bbox);
+			let fragments = matrix
+				.fragments()
+				.into_iter()
+				.map(|bbox| {
+					let first_entry = entry_count;
+					entry_count += bbox.count_tiles();
+					(bbox, first_entry)
+				})
+				.collect();
+			levels.push(Level { matrix, fragments });
+		}
+		ensure!(
+			entry_count * (metadata.tile_offset_bytes as u64 + 4) == header.index_length,
+			"the index should contain {entry_count} entries, but is {} bytes long",
+			header.index_length
+		);
+
+		let mut reader = COMTilesReader {
+			data_reader,
+			header,
+			tile_offset_bytes: metadata.tile_offset_bytes,
+			tilejson: metadata.tilejson,
+			parameters: TilesReaderParameters::new(metadata.tile_format, TileCompression::Uncompressed, bbox_pyramid),
+			levels,
+			fragment_cache: Mutex::new(LimitedCache::with_maximum_size(100_000_000)),
+		};
+		reader.parameters.tile_compression = reader.detect_compression().await?;
+
+		Ok(reader)
+	}
+
+	/// COMTiles does not store the tile compression. Raster tiles are never compressed,
+	/// vector tiles are checked for the gzip magic number.
+	async fn detect_compression(&self) -> Result<TileCompression> {
+		if self.parameters.tile_format != TileFormat::PBF {
+			return Ok(TileCompression::Uncompressed);
+		}
+		for level in self.levels.iter() {
+			for index in 0..level.fragments.len() {
+				let entries = self.get_fragment_entries(level, index).await?;
+				for i in 0..level.fragments[index].0.count_tiles() as usize {
+					let range = read_entry(&entries, i, self.tile_offset_bytes)?;
+					if range.length >= 2 {
+						let blob = self
+							.data_reader
+							.read_range(&ByteRange::new(range.offset + self.header.data_offset(), 2))
+							.await?;
+						return Ok(if blob.as_slice() == [0x1f, 0x8b] {
+							TileCompression::Gzip
+						} else {
+							TileCompression::Uncompressed
+						});
+					}
+				}
+			}
+		}
+		Ok(TileCompression::Uncompressed)
+	}
+
+	fn get_level(&self, level: u8) -> Option<&Level> {
+		self.levels.iter().find(|l| l.matrix.bbox.level == level)
+	}
+
+	/// Returns the index entries of a fragment, loading them if they are not cached.
+	async fn get_fragment_entries(&self, level: &Level, index: usize) -> Result<Arc<Blob>> {
+		let key = (level.matrix.bbox.level, index);
+		if let Some(entries) = self.fragment_cache.lock().await.get(&key) {
+			return Ok(entries);
+		}
+
+		let (bbox, first_entry) = &level.fragments[index];
+		let entry_length = self.tile_offset_bytes as u64 + 4;
+		let range = ByteRange::new(
+			self.header.index_range().offset + first_entry * entry_length,
+			bbox.count_tiles() * entry_length,
+		);
+		let entries = Arc::new(self.data_reader.read_range(&range).await?);
+		Ok(self.fragment_cache.lock().await.add(key, entries))
+	}
+
+	/// Reads all tiles of a fragment that are inside `bbox`.
+	async fn read_fragment_tiles(
+		&self,
+		level: &Level,
+		index: usize,
+		bbox: &TileBBox,
+	) -> Result<Vec<(TileCoord3, Blob)>> {
+		let entries = self.get_fragment_entries(level, index).await?;
+		let data_offset = self.header.data_offset();
+
+		let mut tiles: Vec<(TileCoord3, ByteRange)> = Vec::new();
+		for (i, coord) in level.fragments[index].0.iter_coords().enumerate() {
+			if !bbox.contains3(&coord) {
+				continue;
+			}
+			let range = read_entry(&entries, i, self.tile_offset_bytes)?;
+			if range.length > 0 {
+				tiles.push((coord, range.get_shifted_forward(data_offset)));
+			}
+		}
+		if tiles.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let start = tiles.iter().map(|(_, r)| r.offset).min().unwrap();
+		let end = tiles.iter().map(|(_, r)| r.offset + r.length).max().unwrap();
+		let mut result = Vec::with_capacity(tiles.len());
+		if end - start <= MAX_SPAN_LENGTH {
+			let span = self.data_reader.read_range(&ByteRange::new(start, end - start)).await?;
+			for (coord, range) in tiles {
+				result.push((coord, span.read_range(&range.get_shifted_backward(start))?));
+			}
+		} else {
+			for (coord, range) in tiles {
+				result.push((coord, self.data_reader.read_range(&range).await?));
+			}
+		}
+		Ok(result)
+	}
+}
+
+#[async_trait]
+impl TilesReaderTrait for COMTilesReader {
+	fn get_container_name(&self) -> &str {
+		"comtiles"
+	}
+
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn override_compression(&mut self, tile_compression: TileCompression) {
+		self.parameters.tile_compression = tile_compression;
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	fn get_source_name(&self) -> &str {
+		self.data_reader.get_name()
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(level) = self.get_level(coord.z) else {
+			return Ok(None);
+		};
+		let Some(index) = level.matrix.fragment_index(coord) else {
+			return Ok(None);
+		};
+
+		let entries = self.get_fragment_entries(level, index).await?;
+		let tile_index = level.fragments[index].0.get_tile_index3(coord)?;
+		let range = read_entry(&entries, tile_index, self.tile_offset_bytes)?;
+		if range.length == 0 {
+			return Ok(None);
+		}
+		let range = range.get_shifted_forward(self.header.data_offset());
+		Ok(Some(self.data_reader.read_range(&range).await?))
+	}
+
+	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let Some(level) = self.get_level(bbox.level) else {
+			return TileStream::new_empty();
+		};
+		let fragments: Vec<usize> = (0..level.fragments.len())
+			.filter(|i| level.fragments[*i].0.overlaps_bbox(&bbox).unwrap_or(false))
+			.collect();
+
+		TileStream::from_stream(
+			futures::stream::iter(fragments)
+				.then(move |index| {
+					let bbox = bbox.clone();
+					async move {
+						let tiles = self
+							.read_fragment_tiles(level, index, &bbox)
+							.await
+							.unwrap_or_else(|error| {
+								log::error!("failed to read fragment {index} of level {}: {error:?}", bbox.level);
+								Vec::new()
+							});
+						futures::stream::iter(tiles)
+					}
+				})
+				.flatten()
+				.boxed(),
+		)
+	}
+
+	#[cfg(feature = "cli")]
+	async fn probe_container(&mut self, print: &PrettyPrint) -> Result<()> {
+		print.add_key_value("header", &self.header).await;
+		print.add_key_value("tile offset bytes", &self.tile_offset_bytes).await;
+		for level in self.levels.iter() {
+			print
+				.add_key_value(
+					&format!("level {}", level.matrix.bbox.level),
+					&format!(
+						"{} fragments, aggregation coefficient {}",
+						level.fragments.len(),
+						level.matrix.aggregation_coefficient
+					),
+				)
+				.await;
+		}
+		Ok(())
+	}
+}
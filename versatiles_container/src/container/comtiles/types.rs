@@ -0,0 +1,368 @@
+//! Header, metadata and index structures of a COMTiles container.
+//!
+//! A COMTiles file consists of four sections:
+//! - header (17 bytes, little endian): magic word "COMT", version (u32), length of the metadata (u32)
+//!   and length of the index (u40)
+//! - metadata: JSON document with the TileJSON fields, the tile format and the `tileMatrixSet`
+//! - index: one entry per tile of every tile matrix, consisting of the offset of the tile relative to the
+//!   start of the data section (`tileOffsetBytes` bytes) and the size of the tile (u32). Missing tiles have size 0.
+//! - data: the tiles
+//!
+//! Every tile matrix describes the tiles of one zoom level and is split into fragments of
+//! 2^`aggregationCoefficient` × 2^`aggregationCoefficient` aligned tiles. An aggregation coefficient of -1 means
+//! that the whole zoom level is one fragment. Fragments and the tiles inside a fragment are ordered row by row,
+//! so the index entries of a fragment are contiguous and can be fetched with a single request.
+//! Rows are counted from the top, like in the XYZ scheme.
+
+use anyhow::{bail, ensure, Context, Result};
+use versatiles_core::{io::*, json::*, tilejson::TileJSON, types::*};
+
+const MAGIC: &[u8; 4] = b"COMT";
+const VERSION: u32 = 1;
+const MAX_U40: u64 = (1 << 40) - 1;
+
+/// The fixed size header of a COMTiles container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderCOMT {
+	pub version: u32,
+	pub metadata_length: u32,
+	pub index_length: u64,
+}
+
+impl HeaderCOMT {
+	pub fn new(metadata_length: u64, index_length: u64) -> Result<HeaderCOMT> {
+		ensure!(metadata_length <= u32::MAX as u64, "metadata is too large");
+		ensure!(index_length <= MAX_U40, "index is too large");
+		Ok(HeaderCOMT {
+			version: VERSION,
+			metadata_length: metadata_length as u32,
+			index_length,
+		})
+	}
+
+	/// Length of the header in bytes.
+	pub const fn len() -> u64 {
+		17
+	}
+
+	pub fn serialize(&self) -> Result<Blob> {
+		let mut writer = ValueWriterBlob::new_le();
+		writer.write_slice(MAGIC)?;
+		writer.write_u32(self.version)?;
+		writer.write_u32(self.metadata_length)?;
+		writer.write_u32(self.index_length as u32)?;
+		writer.write_u8((self.index_length >> 32) as u8)?;
+		Ok(writer.into_blob())
+	}
+
+	pub fn deserialize(blob: &Blob) -> Result<HeaderCOMT> {
+		ensure!(
+			blob.len() == Self::len(),
+			"COMTiles header must be {} bytes long",
+			Self::len()
+		);
+		let mut reader = ValueReaderSlice::new_le(blob.as_slice());
+		ensure!(
+			reader.read_blob(4)?.as_slice() == MAGIC,
+			"COMTiles file must start with \"COMT\""
+		);
+		let version = reader.read_u32()?;
+		ensure!(version == VERSION, "COMTiles version {version} is not supported");
+		let metadata_length = reader.read_u32()?;
+		let index_length = reader.read_u32()? as u64 | ((reader.read_u8()? as u64) << 32);
+		Ok(HeaderCOMT {
+			version,
+			metadata_length,
+			index_length,
+		})
+	}
+
+	pub fn metadata_range(&self) -> ByteRange {
+		ByteRange::new(Self::len(), self.metadata_length as u64)
+	}
+
+	pub fn index_range(&self) -> ByteRange {
+		ByteRange::new(Self::len() + self.metadata_length as u64, self.index_length)
+	}
+
+	/// Position of the data section, all tile offsets are relative to it.
+	pub fn data_offset(&self) -> u64 {
+		Self::len() + self.metadata_length as u64 + self.index_length
+	}
+}
+
+/// The tiles of one zoom level and how they are split into fragments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileMatrix {
+	pub bbox: TileBBox,
+	pub aggregation_coefficient: i8,
+}
+
+impl TileMatrix {
+	pub fn new(bbox: TileBBox, aggregation_coefficient: i8) -> TileMatrix {
+		TileMatrix {
+			bbox,
+			aggregation_coefficient,
+		}
+	}
+
+	/// Width and height of a fragment in tiles.
+	fn fragment_size(&self) -> u32 {
+		if self.aggregation_coefficient < 0 {
+			1 << self.bbox.level
+		} else {
+			1 << self.aggregation_coefficient.min(self.bbox.level as i8)
+		}
+	}
+
+	/// Returns the fragments in the order they are stored in the index.
+	pub fn fragments(&self) -> Vec<TileBBox> {
+		self.bbox.iter_bbox_grid(self.fragment_size()).collect()
+	}
+
+	/// Returns the position of the fragment containing `coord` in the list of fragments.
+	pub fn fragment_index(&self, coord: &TileCoord3) -> Option<usize> {
+		if !self.bbox.contains3(coord) {
+			return None;
+		}
+		let size = self.fragment_size();
+		let mut grid = self.bbox.clone();
+		grid.scale_down(size);
+		let x = (coord.x / size - grid.x_min) as usize;
+		let y = (coord.y / size - grid.y_min) as usize;
+		Some(y * grid.width() as usize + x)
+	}
+
+	fn from_json(json: &JsonValue) -> Result<TileMatrix> {
+		let object = json.as_object()?;
+		let level: u8 = object.get_number("zoom")?.context("tile matrix needs a zoom level")?;
+		let aggregation_coefficient: i8 = object
+			.get_number("aggregationCoefficient")?
+			.context("tile matrix needs an aggregation coefficient")?;
+		let limits = object
+			.get("tileMatrixLimits")
+			.context("tile matrix needs limits")?
+			.as_object()?;
+		let limit = |key: &str| -> Result<u32> { limits.get_number(key)?.with_context(|| format!("missing \"{key}\"")) };
+		let bbox = TileBBox::new(
+			level,
+			limit("minTileCol")?,
+			limit("minTileRow")?,
+			limit("maxTileCol")?,
+			limit("maxTileRow")?,
+		)?;
+		Ok(TileMatrix::new(bbox, aggregation_coefficient))
+	}
+
+	fn as_json(&self) -> JsonValue {
+		JsonValue::from(vec![
+			("zoom", JsonValue::from(self.bbox.level)),
+			(
+				"aggregationCoefficient",
+				JsonValue::from(self.aggregation_coefficient as i32),
+			),
+			(
+				"tileMatrixLimits",
+				JsonValue::from(vec![
+					("minTileCol", self.bbox.x_min),
+					("minTileRow", self.bbox.y_min),
+					("maxTileCol", self.bbox.x_max),
+					("maxTileRow", self.bbox.y_max),
+				]),
+			),
+		])
+	}
+}
+
+/// The metadata section of a COMTiles container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataCOMT {
+	pub tilejson: TileJSON,
+	pub tile_format: TileFormat,
+	/// number of bytes of the tile offsets in the index
+	pub tile_offset_bytes: u8,
+	pub tile_matrices: Vec<TileMatrix>,
+}
+
+impl MetadataCOMT {
+	pub fn from_blob(blob: &Blob) -> Result<MetadataCOMT> {
+		let mut object = JsonObject::parse_str(blob.as_str()).context("parsing COMTiles metadata")?;
+		let mut take = |key: &str| object.0.remove(key);
+
+		let tile_format = match take("tileFormat") {
+			Some(value) => TileFormat::parse_str(value.as_str()?)?,
+			None => TileFormat::PBF,
+		};
+		let tile_offset_bytes = match take("tileOffsetBytes") {
+			Some(value) => value.as_number::<u8>()?,
+			None => 5,
+		};
+		ensure!(
+			(1..=8).contains(&tile_offset_bytes),
+			"tileOffsetBytes must be between 1 and 8, but is {tile_offset_bytes}"
+		);
+
+		let tile_matrix_set = take("tileMatrixSet").context("COMTiles metadata needs a \"tileMatrixSet\"")?;
+		let tile_matrix_set = tile_matrix_set.as_object()?;
+		for (key, expected) in [
+			("tileMatrixCRS", "WebMercatorQuad"),
+			("fragmentOrdering", "RowMajor"),
+			("tileOrdering", "RowMajor"),
+		] {
+			if let Some(value) = tile_matrix_set.get_string(key)? {
+				if !value.eq_ignore_ascii_case(expected) {
+					bail!("{key} \"{value}\" is not supported, only \"{expected}\"");
+				}
+			}
+		}
+		let tile_matrices = tile_matrix_set
+			.get_array("tileMatrix")?
+			.context("tileMatrixSet needs a \"tileMatrix\" array")?
+			.0
+			.iter()
+			.map(TileMatrix::from_json)
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(MetadataCOMT {
+			tilejson: TileJSON::from_object(&object)?,
+			tile_format,
+			tile_offset_bytes,
+			tile_matrices,
+		})
+	}
+
+	pub fn to_blob(&self) -> Blob {
+		let mut object = self.tilejson.as_object();
+		object.set("tileFormat", self.tile_format.as_str());
+		object.set("tileOffsetBytes", self.tile_offset_bytes);
+		object.set(
+			"tileMatrixSet",
+			JsonValue::from(vec![
+				("tileMatrixCRS", JsonValue::from("WebMercatorQuad")),
+				("fragmentOrdering", JsonValue::from("RowMajor")),
+				("tileOrdering", JsonValue::from("RowMajor")),
+				(
+					"tileMatrix",
+					JsonValue::from(self.tile_matrices.iter().map(|m| m.as_json()).collect::<Vec<_>>()),
+				),
+			]),
+		);
+		Blob::from(object.stringify())
+	}
+
+	/// Size of an index entry in bytes.
+	pub fn entry_length(&self) -> u64 {
+		self.tile_offset_bytes as u64 + 4
+	}
+}
+
+/// Reads the index entry at position `index` of `entries`.
+pub fn read_entry(entries: &Blob, index: usize, tile_offset_bytes: u8) -> Result<ByteRange> {
+	let entry_length = tile_offset_bytes as usize + 4;
+	let start = index * entry_length;
+	ensure!(
+		start + entry_length <= entries.len() as usize,
+		"index entry {index} is missing"
+	);
+	let entry = &entries.as_slice()[start..start + entry_length];
+	let mut offset = [0u8; 8];
+	offset[..tile_offset_bytes as usize].copy_from_slice(&entry[..tile_offset_bytes as usize]);
+	let length = u32::from_le_bytes(entry[tile_offset_bytes as usize..].try_into()?);
+	Ok(ByteRange::new(u64::from_le_bytes(offset), length as u64))
+}
+
+/// Serializes index entries, empty ranges mark missing tiles.
+pub fn write_entries(ranges: &[ByteRange], tile_offset_bytes: u8) -> Result<Blob> {
+	let mut entries = Vec::with_capacity(ranges.len() * (tile_offset_bytes as usize + 4));
+	for range in ranges {
+		ensure!(
+			tile_offset_bytes >= 8 || range.offset < 1 << (8 * tile_offset_bytes as u32),
+			"tile offset {} does not fit into {tile_offset_bytes} bytes",
+			range.offset
+		);
+		ensure!(range.length <= u32::MAX as u64, "tile is too large");
+		entries.extend_from_slice(&range.offset.to_le_bytes()[..tile_offset_bytes as usize]);
+		entries.extend_from_slice(&(range.length as u32).to_le_bytes());
+	}
+	Ok(Blob::from(entries))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn header() -> Result<()> {
+		let header = HeaderCOMT::new(1234, (1 << 36) + 5)?;
+		let blob = header.serialize()?;
+		assert_eq!(blob.as_hex(), "43 4f 4d 54 01 00 00 00 d2 04 00 00 05 00 00 00 10");
+		assert_eq!(HeaderCOMT::deserialize(&blob)?, header);
+		assert_eq!(header.metadata_range(), ByteRange::new(17, 1234));
+		assert_eq!(header.data_offset(), 17 + 1234 + (1 << 36) + 5);
+
+		assert!(HeaderCOMT::new(0, 1 << 40).is_err());
+		assert!(HeaderCOMT::deserialize(&Blob::from(vec![0u8; 17])).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn fragments() -> Result<()> {
+		let matrix = TileMatrix::new(TileBBox::new(8, 60, 100, 140, 130)?, 6);
+		let fragments = matrix.fragments();
+		assert_eq!(fragments.len(), 6);
+		assert_eq!(fragments[0], TileBBox::new(8, 60, 100, 63, 127)?);
+		assert_eq!(fragments[4], TileBBox::new(8, 64, 128, 127, 130)?);
+
+		for (index, fragment) in fragments.iter().enumerate() {
+			for coord in fragment.iter_coords() {
+				assert_eq!(matrix.fragment_index(&coord), Some(index));
+			}
+		}
+		assert_eq!(matrix.fragment_index(&TileCoord3::new(0, 0, 8)?), None);
+
+		let matrix = TileMatrix::new(TileBBox::new(3, 1, 2, 5, 6)?, -1);
+		assert_eq!(matrix.fragments(), vec![TileBBox::new(3, 1, 2, 5, 6)?]);
+		Ok(())
+	}
+
+	#[test]
+	fn metadata() -> Result<()> {
+		let mut tilejson = TileJSON::default();
+		tilejson.set_string("name", "test")?;
+		let metadata = MetadataCOMT {
+			tilejson,
+			tile_format: TileFormat::PNG,
+			tile_offset_bytes: 5,
+			tile_matrices: vec![
+				TileMatrix::new(TileBBox::new_full(0)?, -1),
+				TileMatrix::new(TileBBox::new(7, 1, 2, 3, 4)?, 6),
+			],
+		};
+		let blob = metadata.to_blob();
+		assert_eq!(MetadataCOMT::from_blob(&blob)?, metadata);
+
+		let blob = Blob::from("{\"tileMatrixSet\":{\"fragmentOrdering\":\"HilbertCurve\",\"tileMatrix\":[]}}");
+		assert_eq!(
+			MetadataCOMT::from_blob(&blob).unwrap_err().to_string(),
+			"fragmentOrdering \"HilbertCurve\" is not supported, only \"RowMajor\""
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn entries() -> Result<()> {
+		let ranges = vec![
+			ByteRange::new(0, 10),
+			ByteRange::empty(),
+			ByteRange::new(0x12_3456_789a, 0x1234),
+		];
+		let blob = write_entries(&ranges, 5)?;
+		assert_eq!(blob.len(), 27);
+		for (index, range) in ranges.iter().enumerate() {
+			assert_eq!(&read_entry(&blob, index, 5)?, range);
+		}
+		assert!(read_entry(&blob, 3, 5).is_err());
+		assert!(write_entries(&[ByteRange::new(1 << 40, 1)], 5).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,212 @@
+//! Provides functionality for writing tile data to a COMTiles container.
+//!
+//! The tiles are written fragment by fragment: the tiles of a fragment are appended to the data section,
+//! then the index entries of the fragment are written to the reserved index section.
+//! Zoom levels up to 6 are stored as a single fragment, higher zoom levels in fragments of 64×64 tiles.
+//!
+//! ## Usage Example
+//! ```rust
+//! use versatiles_container::{COMTilesWriter, MBTilesReader, TilesWriterTrait};
+//! use std::path::Path;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let path = std::env::current_dir().unwrap().join("../testdata/berlin.mbtiles");
+//!     let mut reader = MBTilesReader::open_path(&path).unwrap();
+//!
+//!     let temp_path = std::env::temp_dir().join("temp.comt");
+//!     COMTilesWriter::write_to_path(&mut reader, &temp_path).await.unwrap();
+//! }
+//! ```
+//!
+//! ## Errors
+//! - Returns errors if the tile format and compression are not supported, or if there are issues writing the data.
+
+use super::types::{write_entries, HeaderCOMT, MetadataCOMT, TileMatrix};
+use crate::TilesWriterTrait;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use versatiles_core::{io::DataWriterTrait, progress::get_progress_bar, types::*};
+
+const TILE_OFFSET_BYTES: u8 = 5;
+/// Zoom levels up to this level are stored as a single fragment.
+const MAX_SINGLE_FRAGMENT_LEVEL: u8 = 6;
+const AGGREGATION_COEFFICIENT: i8 = 6;
+
+/// A struct that provides functionality to write tile data to a COMTiles container.
+pub struct COMTilesWriter {}
+
+#[async_trait]
+impl TilesWriterTrait for COMTilesWriter {
+	/// Writes tile data from a `TilesReader` to a `DataWriterTrait`.
+	///
+	/// # Errors
+	/// Returns an error if the tile format and compression are not supported or if there are issues writing the data.
+	async fn write_to_writer(reader: &mut dyn TilesReaderTrait, writer: &mut dyn DataWriterTrait) -> Result<()> {
+		use TileCompression::*;
+		use TileFormat::*;
+
+		let parameters = reader.get_parameters().clone();
+		match (parameters.tile_format, parameters.tile_compression) {
+			(JPG | PNG | WEBP, Uncompressed) | (PBF, Gzip | Uncompressed) => {}
+			_ => bail!(
+				"combination of format ({}) and compression ({}) is not supported. COMTiles supports only uncompressed jpg/png/webp or uncompressed/gzipped pbf",
+				parameters.tile_format,
+				parameters.tile_compression
+			),
+		}
+
+		let tile_matrices: Vec<TileMatrix> = parameters
+			.bbox_pyramid
+			.iter_levels()
+			.map(|bbox| {
+				let aggregation_coefficient = if bbox.level <= MAX_SINGLE_FRAGMENT_LEVEL {
+					-1
+				} else {
+					AGGREGATION_COEFFICIENT
+				};
+				TileMatrix::new(bbox.clone(), aggregation_coefficient)
+			})
+			.collect();
+
+		let metadata = MetadataCOMT {
+			tilejson: reader.get_tilejson().clone(),
+			tile_format: parameters.tile_format,
+			tile_offset_bytes: TILE_OFFSET_BYTES,
+			tile_matrices,
+		};
+		let metadata_blob = metadata.to_blob();
+		let entry_length = metadata.entry_length();
+		let tile_count = parameters.bbox_pyramid.count_tiles();
+		let header = HeaderCOMT::new(metadata_blob.len(), tile_count * entry_length)?;
+
+		writer.set_position(0)?;
+		writer.append(&header.serialize()?)?;
+		writer.append(&metadata_blob)?;
+		let index_start = header.index_range().offset;
+		let data_start = header.data_offset();
+		writer.set_position(data_start)?;
+
+		let mut progress = get_progress_bar("converting tiles", tile_count);
+		let mut entry_count = 0;
+		for matrix in metadata.tile_matrices.iter() {
+			for fragment in matrix.fragments() {
+				let mut ranges = vec![ByteRange::empty(); fragment.count_tiles() as usize];
+				let mut stream = reader.get_bbox_tile_stream(fragment.clone()).await;
+				while let Some((coord, blob)) = stream.next().await {
+					ranges[fragment.get_tile_index3(&coord)?] = writer.append(&blob)?.get_shifted_backward(data_start);
+				}
+
+				let data_end = writer.get_position()?;
+				writer.set_position(index_start + entry_count * entry_length)?;
+				writer.append(&write_entries(&ranges, TILE_OFFSET_BYTES)?)?;
+				writer.set_position(data_end)?;
+
+				entry_count += ranges.len() as u64;
+				progress.inc(ranges.len() as u64);
+			}
+		}
+		progress.finish();
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		container::{
+			comtiles::COMTilesReader,
+			mock::{MockTilesReader, MockTilesWriter},
+		},
+		PMTilesReader,
+	};
+	use std::env::current_dir;
+	use versatiles_core::io::*;
+
+	async fn write_mock(parameters: TilesReaderParameters) -> Result<COMTilesReader> {
+		let mut mock_reader = MockTilesReader::new_mock(parameters)?;
+		let mut data_writer = DataWriterBlob::new()?;
+		COMTilesWriter::write_to_writer(&mut mock_reader, &mut data_writer).await?;
+		COMTilesReader::open_reader(Box::new(DataReaderBlob::from(data_writer))).await
+	}
+
+	#[tokio::test]
+	async fn read_write() -> Result<()> {
+		for (tile_format, tile_compression) in [
+			(TileFormat::PBF, TileCompression::Gzip),
+			(TileFormat::PBF, TileCompression::Uncompressed),
+			(TileFormat::PNG, TileCompression::Uncompressed),
+		] {
+			let mut reader = write_mock(TilesReaderParameters::new(
+				tile_format,
+				tile_compression,
+				TileBBoxPyramid::new_full(8),
+			))
+			.await?;
+			assert_eq!(reader.get_parameters().tile_format, tile_format);
+			assert_eq!(reader.get_parameters().tile_compression, tile_compression);
+			assert_eq!(reader.get_parameters().bbox_pyramid, TileBBoxPyramid::new_full(8));
+			MockTilesWriter::write(&mut reader).await?;
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn tiles_and_fragments() -> Result<()> {
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(3, 1, 2, 5, 6)?);
+		pyramid.set_level_bbox(TileBBox::new(8, 60, 100, 140, 130)?);
+		let reader = write_mock(TilesReaderParameters::new(
+			TileFormat::JSON,
+			TileCompression::Uncompressed,
+			pyramid.clone(),
+		))
+		.await;
+		assert!(reader.is_err());
+
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			pyramid.clone(),
+		))?;
+		let mut data_writer = DataWriterBlob::new()?;
+		COMTilesWriter::write_to_writer(&mut mock_reader, &mut data_writer).await?;
+		let reader = COMTilesReader::open_reader(Box::new(DataReaderBlob::from(data_writer))).await?;
+
+		assert_eq!(reader.get_parameters().bbox_pyramid, pyramid);
+		assert_eq!(reader.header.index_length, (25 + 81 * 31) * 9);
+		assert!(reader.get_tile_data(&TileCoord3::new(63, 127, 8)?).await?.is_some());
+		assert!(reader.get_tile_data(&TileCoord3::new(64, 128, 8)?).await?.is_some());
+		assert!(reader.get_tile_data(&TileCoord3::new(0, 0, 8)?).await?.is_none());
+		assert!(reader.get_tile_data(&TileCoord3::new(0, 0, 5)?).await?.is_none());
+
+		let bbox = TileBBox::new(8, 62, 126, 66, 129)?;
+		let tiles = reader.get_bbox_tile_stream(bbox.clone()).await.collect().await;
+		assert_eq!(tiles.len() as u64, bbox.count_tiles());
+		for (coord, blob) in tiles {
+			assert_eq!(reader.get_tile_data(&coord).await?, Some(blob));
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn convert_pmtiles() -> Result<()> {
+		let mut pmtiles = PMTilesReader::open_path(&current_dir()?.join("../testdata/berlin.pmtiles")).await?;
+		let mut data_writer = DataWriterBlob::new()?;
+		COMTilesWriter::write_to_writer(&mut pmtiles, &mut data_writer).await?;
+		let comtiles = COMTilesReader::open_reader(Box::new(DataReaderBlob::from(data_writer))).await?;
+
+		assert_eq!(comtiles.get_parameters(), pmtiles.get_parameters());
+		assert_eq!(comtiles.get_tilejson(), pmtiles.get_tilejson());
+		for coord in [(0, 0, 0), (137, 83, 8), (8800, 5370, 14)] {
+			let coord = TileCoord3::new(coord.0, coord.1, coord.2)?;
+			assert_eq!(
+				comtiles.get_tile_data(&coord).await?,
+				pmtiles.get_tile_data(&coord).await?
+			);
+		}
+		Ok(())
+	}
+}
@@ -33,19 +33,36 @@
 //! }
 //! ```
 
-use super::{tile_converter::TileConverter, write_to_filename};
+use super::{tile_converter::TileConverter, write_to_filename, CompressionLevels, LevelCoverage};
 use anyhow::Result;
 use async_trait::async_trait;
+use std::{ops::RangeInclusive, sync::Arc};
 use versatiles_core::{tilejson::TileJSON, types::*, utils::TransformCoord};
+use versatiles_geometry::PolygonFilter;
+use versatiles_image::ImageQuality;
 
 /// Parameters for tile conversion.
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct TilesConverterParameters {
 	pub tile_compression: Option<TileCompression>,
 	pub bbox_pyramid: Option<TileBBoxPyramid>,
 	pub force_recompress: bool,
 	pub flip_y: bool,
 	pub swap_xy: bool,
+	/// Removes tiles that are inside `bbox_pyramid`, but not selected, e.g. because of multiple or excluded bboxes.
+	/// Uses output coordinates, like `bbox_pyramid`.
+	pub tile_selection: Option<TileSelection>,
+	/// Removes tiles that don't overlap these polygons, e.g. the ocean around a country. Uses output coordinates.
+	/// See [`TilesConverterParameters::set_polygon_filter`].
+	pub polygon_filter: Option<Arc<PolygonFilter>>,
+	/// Compression levels per zoom range. Tiles of these zoom levels are always recompressed.
+	pub compression_levels: CompressionLevels,
+	/// Image quality per zoom range. JPEG and WebP tiles of these zoom levels are decoded and encoded again.
+	pub image_quality: ImageQuality,
+	/// Order of the tile rows in the output. Tiles keep their XYZ coordinates, the scheme is declared in
+	/// the TileJSON and applied by writers that address tiles by path, like directories and tar files.
+	pub tile_scheme: TileScheme,
 }
 
 impl TilesConverterParameters {
@@ -63,9 +80,55 @@ impl TilesConverterParameters {
 			force_recompress,
 			flip_y,
 			swap_xy,
+			tile_selection: None,
+			polygon_filter: None,
+			compression_levels: CompressionLevels::default(),
+			image_quality: ImageQuality::default(),
+			tile_scheme: TileScheme::Xyz,
 		}
 	}
 
+	/// Hints for the source reader: the tiles it has to provide, in source coordinates.
+	pub fn get_read_hints(&self) -> ReadHints {
+		match &self.bbox_pyramid {
+			Some(bbox_pyramid) => {
+				// same order as in `TilesConvertReader::get_bbox_tile_stream`
+				let mut bbox_pyramid = bbox_pyramid.clone();
+				if self.swap_xy {
+					bbox_pyramid.swap_xy();
+				}
+				if self.flip_y {
+					bbox_pyramid.flip_y();
+				}
+				ReadHints::from_bbox_pyramid(bbox_pyramid)
+			}
+			None => ReadHints::default(),
+		}
+	}
+
+	/// Limits the tiles to a coverage per zoom range, e.g. a whole country up to zoom level 10 and only a city above,
+	/// as used for staged extracts. Zoom levels without a range are removed. Uses output coordinates.
+	pub fn intersect_level_coverage(&mut self, coverage: &LevelCoverage) -> Result<()> {
+		let coverage_pyramid = coverage.get_bbox_pyramid()?;
+		match &mut self.bbox_pyramid {
+			Some(bbox_pyramid) => bbox_pyramid.intersect(&coverage_pyramid),
+			None => self.bbox_pyramid = Some(coverage_pyramid),
+		}
+		Ok(())
+	}
+
+	/// Keeps only the tiles overlapping the polygons of `polygon_filter`, instead of a rectangular bounding box.
+	/// `bbox_pyramid` is limited to the bounding box of the polygons, so that the reader skips everything outside.
+	pub fn set_polygon_filter(&mut self, polygon_filter: PolygonFilter) {
+		let mut bbox_pyramid = self
+			.bbox_pyramid
+			.take()
+			.unwrap_or_else(|| TileBBoxPyramid::new_full(31));
+		bbox_pyramid.intersect_geo_bbox(&polygon_filter.get_geo_bbox());
+		self.bbox_pyramid = Some(bbox_pyramid);
+		self.polygon_filter = Some(Arc::new(polygon_filter));
+	}
+
 	/// Create new converter parameters with default settings.
 	pub fn new_default() -> TilesConverterParameters {
 		TilesConverterParameters {
@@ -74,13 +137,23 @@ impl TilesConverterParameters {
 			force_recompress: false,
 			flip_y: false,
 			swap_xy: false,
+			tile_selection: None,
+			polygon_filter: None,
+			compression_levels: CompressionLevels::default(),
+			image_quality: ImageQuality::default(),
+			tile_scheme: TileScheme::Xyz,
 		}
 	}
+
+	fn is_selected(&self, coord: &TileCoord3) -> bool {
+		self.tile_selection.as_ref().is_none_or(|s| s.contains_coord(coord))
+			&& self.polygon_filter.as_ref().is_none_or(|p| p.contains_coord(coord))
+	}
 }
 
 /// Converts tiles from a given reader and writes them to a file.
-pub async fn convert_tiles_container(
-	reader: Box<dyn TilesReaderTrait>,
+pub async fn convert_tiles_container<'a>(
+	reader: Box<dyn TilesReaderTrait + 'a>,
 	cp: TilesConverterParameters,
 	filename: &str,
 ) -> Result<()> {
@@ -90,21 +163,28 @@ pub async fn convert_tiles_container(
 
 /// A reader that converts tiles from one format to another.
 #[derive(Debug)]
-pub struct TilesConvertReader {
-	reader: Box<dyn TilesReaderTrait>,
+pub struct TilesConvertReader<'a> {
+	reader: Box<dyn TilesReaderTrait + 'a>,
 	converter_parameters: TilesConverterParameters,
 	reader_parameters: TilesReaderParameters,
+	tilejson: TileJSON,
 	container_name: String,
 	tile_recompressor: Option<TileConverter>,
+	/// recompressors of the zoom levels with a specific compression level or image quality
+	level_recompressors: Vec<(RangeInclusive<u8>, TileConverter)>,
 	name: String,
 }
 
-impl TilesConvertReader {
+impl<'a> TilesConvertReader<'a> {
 	/// Creates a new converter reader from an existing reader.
+	///
+	/// The reader may be borrowed, e.g. `Box::new(&mut reader)`, so it can be used again after the conversion.
 	pub fn new_from_reader(
-		reader: Box<dyn TilesReaderTrait>,
+		mut reader: Box<dyn TilesReaderTrait + 'a>,
 		cp: TilesConverterParameters,
-	) -> Result<TilesConvertReader> {
+	) -> Result<TilesConvertReader<'a>> {
+		reader.set_read_hints(&cp.get_read_hints());
+
 		let container_name = format!("converter({})", reader.get_container_name());
 		let name = format!("converter({})", reader.get_source_name());
 
@@ -131,19 +211,70 @@ impl TilesConvertReader {
 			cp.force_recompress,
 		)?);
 
+		cp.compression_levels.check(&new_rp.tile_compression)?;
+		cp.image_quality.check(rp.tile_format)?;
+		let mut level_recompressors = Vec::new();
+		for zoom in 0..=31u8 {
+			let level = cp.compression_levels.get_level(zoom);
+			let recompressor = if let Some(quality) = cp.image_quality.get_quality(zoom) {
+				TileConverter::new_tile_reencoder(
+					&rp.tile_compression,
+					&new_rp.tile_compression,
+					rp.tile_format,
+					quality,
+					level,
+				)
+			} else if level.is_some() {
+				TileConverter::new_tile_recompressor_with_level(
+					&rp.tile_compression,
+					&new_rp.tile_compression,
+					cp.force_recompress,
+					level,
+				)?
+			} else {
+				continue;
+			};
+			level_recompressors.push((zoom..=zoom, recompressor));
+		}
+
+		// the selection knows whether the selected area crosses the antimeridian, the bbox pyramid doesn't
+		let mut tilejson = reader.get_tilejson().clone();
+		let bounds = match &cp.tile_selection {
+			Some(selection) => selection.get_geo_bbox(),
+			None => cp.bbox_pyramid.as_ref().and_then(|p| p.get_geo_bbox()),
+		};
+		if let Some(bounds) = bounds {
+			tilejson.limit_bbox(bounds);
+		}
+		if let Some(polygon_filter) = &cp.polygon_filter {
+			tilejson.limit_bbox(polygon_filter.get_geo_bbox());
+		}
+		tilejson.set_scheme(cp.tile_scheme);
+
 		Ok(TilesConvertReader {
 			reader,
 			converter_parameters: cp,
 			reader_parameters: new_rp,
+			tilejson,
 			container_name,
 			tile_recompressor,
+			level_recompressors,
 			name,
 		})
 	}
+
+	fn get_recompressor(&self, level: u8) -> Option<&TileConverter> {
+		self
+			.level_recompressors
+			.iter()
+			.find(|(zooms, _)| zooms.contains(&level))
+			.map(|(_, recompressor)| recompressor)
+			.or(self.tile_recompressor.as_ref())
+	}
 }
 
 #[async_trait]
-impl TilesReaderTrait for TilesConvertReader {
+impl TilesReaderTrait for TilesConvertReader<'_> {
 	fn get_source_name(&self) -> &str {
 		&self.name
 	}
@@ -161,10 +292,13 @@ impl TilesReaderTrait for TilesConvertReader {
 	}
 
 	fn get_tilejson(&self) -> &TileJSON {
-		self.reader.get_tilejson()
+		&self.tilejson
 	}
 
 	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if !self.converter_parameters.is_selected(coord) {
+			return Ok(None);
+		}
 		let mut coord = *coord;
 		if self.converter_parameters.flip_y {
 			coord.flip_y();
@@ -174,7 +308,7 @@ impl TilesReaderTrait for TilesConvertReader {
 		}
 		let mut blob = self.reader.get_tile_data(&coord).await?;
 
-		if let Some(tile_recompressor) = &self.tile_recompressor {
+		if let Some(tile_recompressor) = self.get_recompressor(coord.z) {
 			if let Some(b) = blob {
 				blob = Some(tile_recompressor.process_blob(b)?);
 			}
@@ -184,6 +318,7 @@ impl TilesReaderTrait for TilesConvertReader {
 	}
 
 	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let tile_recompressor = self.get_recompressor(bbox.level);
 		let mut bbox = bbox.clone();
 		if self.converter_parameters.swap_xy {
 			bbox.swap_xy();
@@ -209,7 +344,17 @@ impl TilesReaderTrait for TilesConvertReader {
 			});
 		}
 
-		if let Some(tile_recompressor) = &self.tile_recompressor {
+		if let Some(selection) = &self.converter_parameters.tile_selection {
+			let selection = selection.clone();
+			stream = stream.filter_coord(move |coord| selection.contains_coord(coord));
+		}
+
+		if let Some(polygon_filter) = &self.converter_parameters.polygon_filter {
+			let polygon_filter = polygon_filter.clone();
+			stream = stream.filter_coord(move |coord| polygon_filter.contains_coord(coord));
+		}
+
+		if let Some(tile_recompressor) = tile_recompressor {
 			stream = tile_recompressor.process_stream(stream);
 		}
 
@@ -222,9 +367,12 @@ mod tests {
 	use super::*;
 	use crate::{MockTilesReader, VersaTilesReader};
 	use assert_fs::NamedTempFile;
-	use versatiles_core::types::{
-		TileCompression::*,
-		TileFormat::{self, *},
+	use versatiles_core::{
+		types::{
+			TileCompression::*,
+			TileFormat::{self, *},
+		},
+		utils::decompress_brotli,
 	};
 
 	fn get_mock_reader(tf: TileFormat, tc: TileCompression) -> MockTilesReader {
@@ -240,9 +388,32 @@ mod tests {
 			force_recompress,
 			flip_y: false,
 			swap_xy: false,
+			tile_selection: None,
+			polygon_filter: None,
+			compression_levels: CompressionLevels::default(),
+			image_quality: ImageQuality::default(),
+			tile_scheme: TileScheme::Xyz,
 		}
 	}
 
+	#[test]
+	fn test_get_read_hints() -> Result<()> {
+		let mut cp = TilesConverterParameters::new_default();
+		assert_eq!(cp.get_read_hints(), ReadHints::default());
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(3, 1, 2, 3, 4)?);
+		cp.bbox_pyramid = Some(pyramid.clone());
+		assert_eq!(cp.get_read_hints(), ReadHints::from_bbox_pyramid(pyramid));
+
+		cp.flip_y = true;
+		cp.swap_xy = true;
+		let hints = cp.get_read_hints().bbox_pyramid.unwrap();
+		assert_eq!(hints.to_string(), "[3: [2,4,4,6] (9)]");
+
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn tile_recompression() -> Result<()> {
 		async fn test(c_in: TileCompression, c_out: TileCompression) -> Result<()> {
@@ -318,6 +489,123 @@ mod tests {
 		Ok(())
 	}
 
+	#[tokio::test]
+	async fn tile_selection() -> Result<()> {
+		let reader = get_mock_reader(JSON, Uncompressed);
+		let mut cp = TilesConverterParameters::new_default();
+		let selection = TileSelection {
+			include: vec![
+				TileBBoxPyramid::from_geo_bbox(1, 1, &GeoBBox(-180.0, 0.0, -1.0, 85.0)),
+				TileBBoxPyramid::from_geo_bbox(1, 1, &GeoBBox(1.0, -85.0, 180.0, 0.0)),
+			],
+			exclude: vec![],
+		};
+		cp.bbox_pyramid = selection.get_bbox_pyramid();
+		cp.tile_selection = Some(selection);
+		let tcr = TilesConvertReader::new_from_reader(reader.boxed(), cp)?;
+
+		let tiles = tcr.get_bbox_tile_stream(TileBBox::new_full(1)?).await.collect().await;
+		let mut coords: Vec<TileCoord3> = tiles.into_iter().map(|(coord, _)| coord).collect();
+		coords.sort_by_key(|c| c.get_sort_index());
+		assert_eq!(coords, vec![TileCoord3::new(0, 0, 1)?, TileCoord3::new(1, 1, 1)?]);
+
+		assert!(tcr.get_tile_data(&TileCoord3::new(0, 0, 1)?).await?.is_some());
+		assert!(tcr.get_tile_data(&TileCoord3::new(1, 0, 1)?).await?.is_none());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn polygon_filter() -> Result<()> {
+		use versatiles_geometry::Geometry;
+
+		// a triangle overlapping all tiles of zoom level 1, except the south east
+		let triangle = Geometry::new_polygon(vec![vec![[-80.0, 60.0], [80.0, 60.0], [-80.0, -60.0], [-80.0, 60.0]]]);
+		let reader = get_mock_reader(JSON, Uncompressed);
+		let mut cp = TilesConverterParameters::new_default();
+		cp.set_polygon_filter(PolygonFilter::new(vec![triangle])?);
+		let tcr = TilesConvertReader::new_from_reader(reader.boxed(), cp)?;
+		assert_eq!(
+			format!("{:?}", tcr.get_parameters().bbox_pyramid),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4)]"
+		);
+
+		let tiles = tcr.get_bbox_tile_stream(TileBBox::new_full(1)?).await.collect().await;
+		let mut coords: Vec<TileCoord3> = tiles.into_iter().map(|(coord, _)| coord).collect();
+		coords.sort_by_key(|c| c.get_sort_index());
+		assert_eq!(
+			coords,
+			vec![
+				TileCoord3::new(0, 0, 1)?,
+				TileCoord3::new(1, 0, 1)?,
+				TileCoord3::new(0, 1, 1)?
+			]
+		);
+		assert!(tcr.get_tile_data(&TileCoord3::new(1, 1, 1)?).await?.is_none());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn compression_levels() -> Result<()> {
+		let reader = get_mock_reader(PBF, Gzip);
+		let mut cp = get_converter_parameters(Brotli, false);
+		cp.compression_levels = CompressionLevels::parse_str("0:11,2-:3")?;
+		let tcr = TilesConvertReader::new_from_reader(reader.boxed(), cp)?;
+
+		let recompressor = |level: u8| tcr.get_recompressor(level).unwrap().as_string();
+		assert_eq!(recompressor(0), "ungzip,brotli:11");
+		assert_eq!(recompressor(1), "ungzip,brotli");
+		assert_eq!(recompressor(5), "ungzip,brotli:3");
+
+		let blob = tcr.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.unwrap();
+		assert!(decompress_brotli(&blob).is_ok());
+
+		let reader = get_mock_reader(PBF, Gzip);
+		let mut cp = get_converter_parameters(Gzip, false);
+		cp.compression_levels = CompressionLevels::parse_str("10")?;
+		assert!(TilesConvertReader::new_from_reader(reader.boxed(), cp).is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn image_quality() -> Result<()> {
+		let reader = get_mock_reader(WEBP, Uncompressed);
+		let mut cp = get_converter_parameters(Uncompressed, false);
+		cp.image_quality = ImageQuality::parse_str("0:90,1-:40")?;
+		let tcr = TilesConvertReader::new_from_reader(reader.boxed(), cp)?;
+
+		let recompressor = |level: u8| tcr.get_recompressor(level).unwrap().as_string();
+		assert_eq!(recompressor(0), "webp:90");
+		assert_eq!(recompressor(1), "webp:40");
+
+		let tiles = tcr.get_bbox_tile_stream(TileBBox::new_full(1)?).await.collect().await;
+		assert_eq!(tiles.len(), 4);
+		for (_, blob) in tiles {
+			assert!(versatiles_image::webp::blob2image(&blob).is_ok());
+		}
+
+		let mut cp = get_converter_parameters(Uncompressed, false);
+		cp.image_quality = ImageQuality::parse_str("80")?;
+		assert_eq!(
+			TilesConvertReader::new_from_reader(get_mock_reader(PNG, Uncompressed).boxed(), cp)
+				.unwrap_err()
+				.to_string(),
+			"quality is only supported for jpg and webp tiles, not for png"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn borrowed_reader() -> Result<()> {
+		let mut reader = get_mock_reader(PBF, Gzip);
+		{
+			let tcr = TilesConvertReader::new_from_reader(Box::new(&mut reader), get_converter_parameters(Brotli, false))?;
+			let tiles = tcr.get_bbox_tile_stream(TileBBox::new_full(1)?).await.collect().await;
+			assert_eq!(tiles.len(), 4);
+		}
+		assert_eq!(reader.get_parameters().tile_compression, Gzip);
+		Ok(())
+	}
+
 	#[test]
 	fn test_tiles_converter_parameters_new() {
 		let cp = TilesConverterParameters::new(Some(Gzip), Some(TileBBoxPyramid::new_full(1)), true, true, true);
@@ -340,6 +628,28 @@ mod tests {
 		assert!(!cp.swap_xy);
 	}
 
+	#[test]
+	fn test_intersect_level_coverage() -> Result<()> {
+		let mut coverage = LevelCoverage::default();
+		coverage.add_bbox(0..=2, GeoBBox(-180.0, -85.0, 180.0, 85.0))?;
+		coverage.add_bbox(3..=4, GeoBBox(13.1, 52.3, 13.8, 52.7))?;
+
+		let mut cp = TilesConverterParameters::new_default();
+		cp.intersect_level_coverage(&coverage)?;
+		assert_eq!(
+			cp.bbox_pyramid.as_ref().unwrap().to_string(),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4), 2: [0,0,3,3] (16), 3: [4,2,4,2] (1), 4: [8,5,8,5] (1)]"
+		);
+
+		let mut cp = TilesConverterParameters::new(None, Some(TileBBoxPyramid::new_full(3)), false, false, false);
+		cp.intersect_level_coverage(&coverage)?;
+		assert_eq!(
+			cp.bbox_pyramid.unwrap().to_string(),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4), 2: [0,0,3,3] (16), 3: [4,2,4,2] (1)]"
+		);
+		Ok(())
+	}
+
 	#[test]
 	fn test_tiles_convert_reader_new_from_reader() {
 		let reader = get_mock_reader(PBF, Uncompressed);
@@ -0,0 +1,168 @@
+//! This module reads and writes the optional index of a directory, the file `tiles.index.csv` in its root.
+//!
+//! Scanning a directory with millions of tiles takes minutes. With an index, the reader gets all tiles from one file
+//! instead. Every line contains the coordinates of a tile in the scheme of the directory, the size and XXH64 hash
+//! of the file and its path relative to the root:
+//!
+//! ```text
+//! z,x,y,size,xxh64,path
+//! 3,2,1,1234,ef46db3751d8e999,3/2/1.pbf.gz
+//! ```
+//!
+//! The index is not updated when tiles are added or removed later. In that case, write it again or ignore it
+//! with `tiles/?index=false`.
+
+use crate::ChecksumAlgorithm;
+use anyhow::{bail, ensure, Context, Result};
+use std::{
+	fs::{self, File},
+	io::{BufWriter, Write},
+	path::{Path, PathBuf},
+};
+use versatiles_core::types::{Blob, TileCoord3};
+
+/// Name of the index file in the root of the directory
+pub const INDEX_FILENAME: &str = "tiles.index.csv";
+const HEADER: &str = "z,x,y,size,xxh64,path";
+
+/// A tile listed in the index
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexEntry {
+	/// Coordinates in the scheme of the directory
+	pub coord: TileCoord3,
+	pub size: u64,
+	pub hash: String,
+	/// Path relative to the root of the directory, using `/` as separator
+	pub path: String,
+}
+
+impl IndexEntry {
+	pub fn new(coord: TileCoord3, blob: &Blob, path: String) -> IndexEntry {
+		let hash = ChecksumAlgorithm::Xxh64
+			.checksum(blob.as_slice())
+			.iter()
+			.map(|byte| format!("{byte:02x}"))
+			.collect();
+		IndexEntry {
+			coord,
+			size: blob.len(),
+			hash,
+			path,
+		}
+	}
+
+	fn parse_line(line: &str) -> Result<IndexEntry> {
+		let fields: Vec<&str> = line.split(',').collect();
+		ensure!(fields.len() == 6, "expected 6 fields, found {}", fields.len());
+		ensure!(!fields[5].is_empty(), "path is empty");
+		Ok(IndexEntry {
+			coord: TileCoord3::new(fields[1].parse()?, fields[2].parse()?, fields[0].parse()?)?,
+			size: fields[3].parse()?,
+			hash: fields[4].to_string(),
+			path: fields[5].to_string(),
+		})
+	}
+}
+
+/// Reads the index of the directory `dir`. Returns `None`, if there is no index.
+pub fn read_index(dir: &Path) -> Result<Option<Vec<IndexEntry>>> {
+	let path = dir.join(INDEX_FILENAME);
+	if !path.is_file() {
+		return Ok(None);
+	}
+
+	let content = fs::read_to_string(&path)?;
+	let mut lines = content.lines().enumerate();
+	match lines.next() {
+		Some((_, HEADER)) => {}
+		_ => bail!("the index {path:?} must start with the header \"{HEADER}\""),
+	}
+
+	let mut entries = Vec::new();
+	for (index, line) in lines.filter(|(_, line)| !line.is_empty()) {
+		let entry =
+			IndexEntry::parse_line(line).with_context(|| format!("Failed to parse line {} of {path:?}", index + 1))?;
+		entries.push(entry);
+	}
+	Ok(Some(entries))
+}
+
+/// Writes the index of the directory `dir`, sorted by coordinates.
+pub fn write_index(dir: &Path, mut entries: Vec<IndexEntry>) -> Result<PathBuf> {
+	entries.sort_by_key(|entry| (entry.coord.z, entry.coord.x, entry.coord.y));
+
+	let path = dir.join(INDEX_FILENAME);
+	let mut writer = BufWriter::new(File::create(&path)?);
+	writeln!(writer, "{HEADER}")?;
+	for entry in entries {
+		let TileCoord3 { x, y, z } = entry.coord;
+		writeln!(writer, "{z},{x},{y},{},{},{}", entry.size, entry.hash, entry.path)?;
+	}
+	writer.flush()?;
+	Ok(path)
+}
+
+/// Parses the value of a boolean query parameter. A parameter without value, like `?index`, is `true`.
+pub fn parse_bool(key: &str, value: &str) -> Result<bool> {
+	Ok(match value {
+		"" | "true" | "1" => true,
+		"false" | "0" => false,
+		_ => bail!("parameter \"{key}\" must be \"true\" or \"false\", found \"{value}\""),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::{
+		fixture::{FileWriteStr, PathChild},
+		TempDir,
+	};
+
+	#[test]
+	fn write_and_read() -> Result<()> {
+		let dir = TempDir::new()?;
+		assert_eq!(read_index(dir.path())?, None);
+
+		let entry =
+			|x, y, z, path: &str| IndexEntry::new(TileCoord3::new(x, y, z).unwrap(), &Blob::from("abc"), path.to_string());
+		let entries = vec![entry(3, 1, 2, "2/3/1.png"), entry(0, 0, 0, "0/0/0.png")];
+		write_index(dir.path(), entries.clone())?;
+
+		assert_eq!(
+			fs::read_to_string(dir.path().join(INDEX_FILENAME))?,
+			"z,x,y,size,xxh64,path\n0,0,0,3,44bc2cf5ad770999,0/0/0.png\n2,3,1,3,44bc2cf5ad770999,2/3/1.png\n"
+		);
+		assert_eq!(
+			read_index(dir.path())?,
+			Some(vec![entries[1].clone(), entries[0].clone()])
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn read_errors() -> Result<()> {
+		let dir = TempDir::new()?;
+		let error = |content: &str| {
+			dir.child(INDEX_FILENAME).write_str(content).unwrap();
+			format!("{:#}", read_index(dir.path()).unwrap_err())
+		};
+
+		assert!(error("z,x,y\n").contains("must start with the header"));
+		assert!(error("z,x,y,size,xxh64,path\n0,0,0,3,abc\n").ends_with("expected 6 fields, found 5"));
+		assert!(error("z,x,y,size,xxh64,path\n32,0,0,3,abc,32/0/0.png\n").ends_with("z (32) must be <= 31"));
+		Ok(())
+	}
+
+	#[test]
+	fn bool_parameter() -> Result<()> {
+		assert!(parse_bool("index", "")?);
+		assert!(parse_bool("index", "true")?);
+		assert!(!parse_bool("index", "false")?);
+		assert_eq!(
+			parse_bool("index", "yes").unwrap_err().to_string(),
+			"parameter \"index\" must be \"true\" or \"false\", found \"yes\""
+		);
+		Ok(())
+	}
+}
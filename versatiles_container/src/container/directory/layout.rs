@@ -0,0 +1,213 @@
+//! This module defines the `DirectoryLayout` enum, the arrangement of the tile files in a directory.
+//!
+//! Huge tile dumps contain millions of files. Many file systems (e.g. ext4 or NFS) get slow with that many
+//! entries in one folder, so the `sharded` layout splits every folder into subfolders of at most 256 entries.
+//! Up to zoom level 16, columns and rows need one level of shard folders, above that two or three.
+//!
+//! # Examples
+//!
+//! ```
+//! use versatiles_container::DirectoryLayout;
+//! use versatiles_core::types::TileCoord3;
+//!
+//! let coord = TileCoord3::new(1000, 300, 12).unwrap();
+//! assert_eq!(DirectoryLayout::Xyz.tile_path(&coord), "12/1000/300");
+//! assert_eq!(DirectoryLayout::Sharded.tile_path(&coord), "12/3/1000/1/300");
+//!
+//! let coord = TileCoord3::new(1000000, 300, 20).unwrap();
+//! assert_eq!(DirectoryLayout::Sharded.tile_path(&coord), "20/15/3906/1000000/0/1/300");
+//! ```
+
+use anyhow::{bail, Result};
+use std::{
+	fmt::Display,
+	fs,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+use versatiles_core::types::TileCoord3;
+
+/// Arrangement of the tile files in a directory
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DirectoryLayout {
+	/// `<z>/<x>/<y>.<format>`, as used by most tools
+	#[default]
+	Xyz,
+	/// `<z>/<x / 256>/<x>/<y / 256>/<y>.<format>`, so that folders contain at most 256 shards, or 256 files per shard.
+	/// Above zoom level 16 there are more shard levels, e.g. `<x / 65536>/<x / 256>/<x>`, see [`DirectoryLayout::shard_levels`].
+	Sharded,
+}
+
+impl DirectoryLayout {
+	pub fn as_str(&self) -> &str {
+		match self {
+			DirectoryLayout::Xyz => "xyz",
+			DirectoryLayout::Sharded => "sharded",
+		}
+	}
+
+	pub fn parse_str(value: &str) -> Result<DirectoryLayout> {
+		Ok(match value.trim().to_lowercase().as_str() {
+			"xyz" => DirectoryLayout::Xyz,
+			"sharded" => DirectoryLayout::Sharded,
+			_ => bail!("unknown directory layout '{value}', expected 'xyz' or 'sharded'"),
+		})
+	}
+
+	/// Returns the path of a tile relative to the root of the directory, without file extension.
+	/// The coordinates must already be in the scheme of the directory.
+	pub fn tile_path(&self, coord: &TileCoord3) -> String {
+		match self {
+			DirectoryLayout::Xyz => format!("{}/{}/{}", coord.z, coord.x, coord.y),
+			DirectoryLayout::Sharded => {
+				let levels = Self::shard_levels(coord.z);
+				format!(
+					"{}/{}{}/{}{}",
+					coord.z,
+					shard_prefix(coord.x, levels),
+					coord.x,
+					shard_prefix(coord.y, levels),
+					coord.y
+				)
+			}
+		}
+	}
+
+	/// Returns the number of shard folders above every column and every row in the sharded layout.
+	/// Each level takes 8 bits, so that no folder contains more than 256 entries.
+	pub fn shard_levels(level: u8) -> u8 {
+		level.saturating_sub(8).div_ceil(8).max(1)
+	}
+
+	/// Detects the layout of an existing directory: In the sharded layout, the folders below the zoom levels
+	/// contain folders instead of tiles. Empty directories have the layout `Xyz`.
+	pub fn detect(dir: &Path) -> Result<DirectoryLayout> {
+		for (_, level_dir) in numeric_dirs::<u8>(dir)? {
+			if let Some((_, sub_dir)) = numeric_dirs::<u32>(&level_dir)?.into_iter().next() {
+				if !numeric_dirs::<u32>(&sub_dir)?.is_empty() {
+					return Ok(DirectoryLayout::Sharded);
+				}
+				break;
+			}
+		}
+		Ok(DirectoryLayout::Xyz)
+	}
+}
+
+impl Display for DirectoryLayout {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// Returns the subfolders of `dir` whose names are numbers, e.g. zoom levels or columns.
+pub(super) fn numeric_dirs<T: FromStr>(dir: &Path) -> Result<Vec<(T, PathBuf)>> {
+	let mut result = Vec::new();
+	for entry in fs::read_dir(dir)?.flatten() {
+		let Some(number) = entry.file_name().to_str().and_then(|name| name.parse::<T>().ok()) else {
+			continue;
+		};
+		let path = entry.path();
+		if path.is_dir() {
+			result.push((number, path));
+		}
+	}
+	Ok(result)
+}
+
+/// Returns the shard folders of a column or row, e.g. "15/3906/" for 1000000 with two levels.
+fn shard_prefix(value: u32, levels: u8) -> String {
+	(1..=u32::from(levels))
+		.rev()
+		.map(|level| format!("{}/", value >> (8 * level)))
+		.collect()
+}
+
+/// Returns the numeric subfolders of `dir` that are `levels` shard folders deep, e.g. the columns of a
+/// zoom level in the sharded layout.
+pub(super) fn sharded_numeric_dirs(dir: &Path, levels: u8) -> Result<Vec<(u32, PathBuf)>> {
+	let mut dirs = vec![dir.to_path_buf()];
+	for _ in 0..levels {
+		let mut next = Vec::new();
+		for dir in dirs {
+			next.extend(numeric_dirs::<u32>(&dir)?.into_iter().map(|(_, path)| path));
+		}
+		dirs = next;
+	}
+	let mut result = Vec::new();
+	for dir in dirs {
+		result.append(&mut numeric_dirs::<u32>(&dir)?);
+	}
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::{
+		fixture::{FileWriteStr, PathChild},
+		TempDir,
+	};
+
+	#[test]
+	fn parse() {
+		assert_eq!(DirectoryLayout::parse_str("xyz").unwrap(), DirectoryLayout::Xyz);
+		assert_eq!(
+			DirectoryLayout::parse_str(" Sharded").unwrap(),
+			DirectoryLayout::Sharded
+		);
+		assert!(DirectoryLayout::parse_str("hashed").is_err());
+		assert_eq!(DirectoryLayout::default().to_string(), "xyz");
+	}
+
+	#[test]
+	fn shard_levels() {
+		let levels: Vec<u8> = [0, 8, 9, 16, 17, 20, 24, 25, 31]
+			.into_iter()
+			.map(DirectoryLayout::shard_levels)
+			.collect();
+		assert_eq!(levels, [1, 1, 1, 1, 2, 2, 2, 3, 3]);
+	}
+
+	#[test]
+	fn sharded_tile_path() {
+		let path = |x, y, z| DirectoryLayout::Sharded.tile_path(&TileCoord3::new(x, y, z).unwrap());
+		assert_eq!(path(0, 0, 0), "0/0/0/0/0");
+		assert_eq!(path(65535, 256, 16), "16/255/65535/1/256");
+		assert_eq!(path(65536, 131071, 17), "17/1/256/65536/1/511/131071");
+		assert_eq!(
+			path(1 << 30, (1 << 31) - 1, 31),
+			"31/64/16384/4194304/1073741824/127/32767/8388607/2147483647"
+		);
+	}
+
+	#[test]
+	fn sharded_dirs() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("20/15/3906/1000000/0/1/300.png").write_str("tile")?;
+		dir.child("20/0/1/300/0/0/5.png").write_str("tile")?;
+		let mut columns = sharded_numeric_dirs(&dir.path().join("20"), 2)?;
+		columns.sort();
+		assert_eq!(columns.iter().map(|(x, _)| *x).collect::<Vec<_>>(), [300, 1000000]);
+		// the last shard folders of the rows contain the tiles
+		let rows = sharded_numeric_dirs(&columns[1].1, 1)?;
+		assert_eq!(rows.len(), 1);
+		assert!(rows[0].1.join("300.png").is_file());
+		Ok(())
+	}
+
+	#[test]
+	fn detect() -> Result<()> {
+		let dir = TempDir::new()?;
+		assert_eq!(DirectoryLayout::detect(&dir)?, DirectoryLayout::Xyz);
+
+		dir.child("tiles.json").write_str("{}")?;
+		dir.child("3/2/1.png").write_str("tile")?;
+		assert_eq!(DirectoryLayout::detect(&dir)?, DirectoryLayout::Xyz);
+
+		let dir = TempDir::new()?;
+		dir.child("12/3/1000/1/300.png").write_str("tile")?;
+		assert_eq!(DirectoryLayout::detect(&dir)?, DirectoryLayout::Sharded);
+		Ok(())
+	}
+}
@@ -5,9 +5,14 @@
 //! The main components of this module are:
 //! - `DirectoryTilesReader`: Reads tiles from a directory structure.
 //! - `DirectoryTilesWriter`: Writes tiles to a directory structure.
+//! - `DirectoryLayout`: The arrangement of the files, e.g. sharded for huge tile dumps.
+//! - The optional index `tiles.index.csv`, which lists all tiles, so that huge directories open without scanning them.
 
+mod index;
+mod layout;
 mod reader;
 mod writer;
 
-pub use reader::DirectoryTilesReader;
-pub use writer::DirectoryTilesWriter;
+pub use layout::DirectoryLayout;
+pub use reader::{DirectoryReaderOptions, DirectoryTilesReader};
+pub use writer::{DirectoryTilesWriter, DirectoryWriterOptions};
@@ -11,6 +11,12 @@
 //! - `<x>`: Tile X coordinate (directory)
 //! - `<y>.<format>[.<compression>]`: Tile Y coordinate with the tile format and optional compression type as the file extension
 //!
+//! Huge directories can also use the sharded layout `<root>/<z>/<x / 256>/<x>/<y / 256>/<y>.<format>[.<compression>]`,
+//! with more shard levels above zoom level 16, see [`DirectoryLayout`]. The layout is detected automatically.
+//!
+//! If the directory contains the index `tiles.index.csv`, written with `tiles/?index=true`, the tiles are read from
+//! the index instead of scanning the directory. Use `tiles/?index=false` to scan the directory anyway.
+//!
 //! Example:
 //! ```text
 //! /tiles/3/2/1.png
@@ -20,7 +26,11 @@
 //!
 //! ## Features
 //! - Supports multiple tile formats and compressions
-//! - Automatically detects and reads metadata files in the directory
+//! - Automatically detects and reads metadata files in the directory: `meta.json`, `tiles.json`, `tilejson.json`
+//!   and `metadata.json`, also in the MBTiles style of other tools, where bounds and zoom levels are strings
+//! - Honors `"scheme": "tms"` in the metadata: rows are flipped once while reading, so the reader always returns XYZ coordinates
+//! - Without scheme in the metadata, TMS is detected by comparing the tiles with the bounds of the metadata.
+//!   The scheme can also be set with `DirectoryReaderOptions`, or as query like `tiles/?scheme=tms` in `get_reader`
 //! - Provides asynchronous methods to fetch tile data
 //!
 //! ## Usage
@@ -43,16 +53,58 @@
 //! ## Testing
 //! This module includes comprehensive tests to ensure the correct functionality of opening paths, reading metadata, handling different file formats, and edge cases.
 
+use super::{
+	index::{parse_bool, read_index},
+	layout::{numeric_dirs, sharded_numeric_dirs, DirectoryLayout},
+};
 use anyhow::{bail, ensure, Context, Result};
 use async_trait::async_trait;
 use itertools::Itertools;
 use std::{
-	collections::HashMap,
 	fmt::Debug,
 	fs,
 	path::{Path, PathBuf},
 };
-use versatiles_core::{tilejson::TileJSON, types::*, utils::*};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	tilejson::TileJSON,
+	types::*,
+	utils::*,
+};
+
+/// Options for reading a directory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirectoryReaderOptions {
+	/// Order of the tile rows in the directory. If `None`, the "scheme" of the metadata is used. If the metadata
+	/// has no scheme, TMS is detected by comparing the tiles with the "bounds" of the metadata.
+	pub scheme: Option<TileScheme>,
+	/// Arrangement of the files. If `None`, it is detected, see [`DirectoryLayout::detect`].
+	pub layout: Option<DirectoryLayout>,
+	/// Ignore the index `tiles.index.csv` and scan the directory
+	pub ignore_index: bool,
+}
+
+impl DirectoryReaderOptions {
+	/// Parses the query of a path like `tiles/?scheme=tms&layout=sharded&index=false`.
+	pub fn from_query(query: &str) -> Result<DirectoryReaderOptions> {
+		let mut options = DirectoryReaderOptions::default();
+		for parameter in query.split('&').filter(|p| !p.is_empty()) {
+			let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+			match key {
+				"layout" => options.layout = Some(DirectoryLayout::parse_str(value)?),
+				"scheme" => options.scheme = Some(TileScheme::parse_str(value)?),
+				"index" => options.ignore_index = !parse_bool(key, value)?,
+				_ => bail!(
+					"unknown parameter \"{key}\" for directories, only \"layout\", \"scheme\" and \"index\" are supported"
+				),
+			}
+		}
+		Ok(options)
+	}
+}
+
+/// A tile file: coordinates in the scheme of the directory, format, compression, path and the size if it is known
+type TileFile = (TileCoord3, TileFormat, TileCompression, PathBuf, Option<u64>);
 
 /// A reader for tiles stored in a directory structure.
 /// The directory should be structured as follows:
@@ -63,7 +115,8 @@ use versatiles_core::{tilejson::TileJSON, types::*, utils::*};
 pub struct DirectoryTilesReader {
 	tilejson: TileJSON,
 	dir: PathBuf,
-	tile_map: HashMap<TileCoord3, PathBuf>,
+	/// Paths of the tiles, and their sizes if known from the index
+	tile_map: TileMap<(PathBuf, Option<u64>)>,
 	parameters: TilesReaderParameters,
 }
 
@@ -81,103 +134,90 @@ impl DirectoryTilesReader {
 	where
 		Self: Sized,
 	{
+		DirectoryTilesReader::open_path_with_hints(dir, &ReadHints::default())
+	}
+
+	/// Opens the directory, but only scans zoom levels and columns that might be requested according to `hints`.
+	pub fn open_path_with_hints(dir: &Path, hints: &ReadHints) -> Result<DirectoryTilesReader> {
+		DirectoryTilesReader::open_path_with_options(dir, hints, &DirectoryReaderOptions::default())
+	}
+
+	/// Opens the directory like `open_path_with_hints`, using the given options.
+	pub fn open_path_with_options(
+		dir: &Path,
+		hints: &ReadHints,
+		options: &DirectoryReaderOptions,
+	) -> Result<DirectoryTilesReader> {
 		log::trace!("read {dir:?}");
 
 		ensure!(dir.is_absolute(), "path {dir:?} must be absolute");
 		ensure!(dir.exists(), "path {dir:?} does not exist");
 		ensure!(dir.is_dir(), "path {dir:?} is not a directory");
 
-		let mut tilejson = TileJSON::default();
-		let mut tile_map = HashMap::new();
+		let mut tilejson = Self::read_tilejson(dir)?;
+
+		let index = if options.ignore_index { None } else { read_index(dir)? };
+		let files = match index {
+			Some(entries) => {
+				log::trace!("read {} tiles from the index", entries.len());
+				let mut files = Vec::new();
+				for entry in entries {
+					let coord3 = entry.coord;
+					if hints.skips_level(coord3.z) || !hints.contains_column(coord3.z, coord3.x) {
+						continue;
+					}
+					let mut filename = entry.path.rsplit('/').next().unwrap_or_default().to_string();
+					let file_comp = TileCompression::from_filename(&mut filename);
+					let file_form = TileFormat::from_filename(&mut filename)
+						.with_context(|| format!("unknown tile format of {:?} in the index", entry.path))?;
+					files.push((coord3, file_form, file_comp, dir.join(&entry.path), Some(entry.size)));
+				}
+				files
+			}
+			None => Self::scan_files(dir, hints, options)?,
+		};
+
+		// the scheme of the option overrides the scheme of the metadata
+		let scheme = match (options.scheme, tilejson.get_str("scheme")) {
+			(Some(scheme), _) => scheme,
+			(None, Some(_)) => tilejson.get_scheme()?,
+			(None, None) => Self::detect_scheme(&tilejson, &files),
+		};
+		tilejson.set_scheme(TileScheme::Xyz);
+
+		let mut tile_map = TileMap::new();
 		let mut container_form: Option<TileFormat> = None;
 		let mut container_comp: Option<TileCompression> = None;
 		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
 
-		for result1 in fs::read_dir(dir)? {
-			// z level
-			if result1.is_err() {
+		for (mut coord3, file_form, file_comp, path, size) in files {
+			scheme.apply(&mut coord3);
+			if !hints.contains_coord(&coord3) {
 				continue;
 			}
-			let entry1 = result1?;
-			let name1 = entry1.file_name().into_string().unwrap();
-			let numeric1 = name1.parse::<u8>();
-			if numeric1.is_ok() {
-				let z = numeric1?;
-
-				for result2 in fs::read_dir(entry1.path())? {
-					// x level
-					if result2.is_err() {
-						continue;
-					}
-					let entry2 = result2?;
-					let name2 = entry2.file_name().into_string().unwrap();
-					let numeric2 = name2.parse::<u32>();
-					if numeric2.is_err() {
-						continue;
-					}
-					let x = numeric2?;
-
-					let files = fs::read_dir(entry2.path())?.map(|f| f.unwrap());
-					let files = files.sorted_unstable_by(|a, b| a.file_name().partial_cmp(&b.file_name()).unwrap());
-
-					for entry3 in files {
-						// y level
-						let mut filename = entry3.file_name().into_string().unwrap();
-						let file_comp = TileCompression::from_filename(&mut filename);
-						let this_form = TileFormat::from_filename(&mut filename);
 
-						if this_form.is_none() {
-							continue;
-						}
-						let file_form = this_form.unwrap();
+			if let Some(form) = container_form {
+				if form != file_form {
+					let mut list = [form, file_form];
+					list.sort();
+					bail!("found multiple tile formats: {list:?}");
+				}
+			} else {
+				container_form = Some(file_form);
+			}
 
-						let numeric3 = filename.parse::<u32>();
-						if numeric3.is_err() {
-							continue;
-						}
-						let y = numeric3?;
-
-						if container_form.is_none() {
-							container_form = Some(file_form);
-						} else if container_form != Some(file_form) {
-							let mut list = [container_form.unwrap(), file_form];
-							list.sort();
-							bail!("found multiple tile formats: {list:?}");
-						}
-
-						if container_comp.is_none() {
-							container_comp = Some(file_comp);
-						} else if container_comp != Some(file_comp) {
-							let mut list = [container_comp.unwrap(), file_comp];
-							list.sort();
-							bail!("found multiple tile compressions: {list:?}");
-						}
-
-						let coord3 = TileCoord3::new(x, y, z)?;
-						bbox_pyramid.include_coord(&coord3);
-						tile_map.insert(coord3, entry3.path());
-					}
+			if let Some(comp) = container_comp {
+				if comp != file_comp {
+					let mut list = [comp, file_comp];
+					list.sort();
+					bail!("found multiple tile compressions: {list:?}");
 				}
 			} else {
-				match name1.as_str() {
-					"meta.json" | "tiles.json" | "metadata.json" => {
-						tilejson.merge(&TileJSON::try_from(&Self::read(&entry1.path())?)?)?;
-					}
-					"meta.json.gz" | "tiles.json.gz" | "metadata.json.gz" => {
-						tilejson.merge(&TileJSON::try_from(&decompress(
-							Self::read(&entry1.path())?,
-							&TileCompression::Gzip,
-						)?)?)?;
-					}
-					"meta.json.br" | "tiles.json.br" | "metadata.json.br" => {
-						tilejson.merge(&TileJSON::try_from(&decompress(
-							Self::read(&entry1.path())?,
-							&TileCompression::Brotli,
-						)?)?)?;
-					}
-					&_ => {}
-				};
+				container_comp = Some(file_comp);
 			}
+
+			bbox_pyramid.include_coord(&coord3);
+			tile_map.insert(&coord3, (path, size));
 		}
 
 		if tile_map.is_empty() {
@@ -197,6 +237,143 @@ impl DirectoryTilesReader {
 		})
 	}
 
+	/// Scans the directory for tiles. Returns the coordinates of the files, before applying the scheme.
+	fn scan_files(dir: &Path, hints: &ReadHints, options: &DirectoryReaderOptions) -> Result<Vec<TileFile>> {
+		let mut files: Vec<TileFile> = Vec::new();
+
+		let layout = match options.layout {
+			Some(layout) => layout,
+			None => DirectoryLayout::detect(dir)?,
+		};
+
+		for (z, level_dir) in numeric_dirs::<u8>(dir)? {
+			if hints.skips_level(z) {
+				continue;
+			}
+
+			let shard_levels = DirectoryLayout::shard_levels(z);
+			let column_dirs = match layout {
+				DirectoryLayout::Xyz => numeric_dirs::<u32>(&level_dir)?,
+				DirectoryLayout::Sharded => sharded_numeric_dirs(&level_dir, shard_levels)?,
+			};
+
+			for (x, column_dir) in column_dirs {
+				if !hints.contains_column(z, x) {
+					continue;
+				}
+
+				let tile_dirs = match layout {
+					DirectoryLayout::Xyz => vec![column_dir],
+					DirectoryLayout::Sharded => sharded_numeric_dirs(&column_dir, shard_levels - 1)?
+						.into_iter()
+						.map(|(_, shard_dir)| shard_dir)
+						.collect(),
+				};
+
+				for tile_dir in tile_dirs {
+					let entries = fs::read_dir(tile_dir)?.map(|f| f.unwrap());
+					let entries = entries.sorted_unstable_by(|a, b| a.file_name().partial_cmp(&b.file_name()).unwrap());
+
+					for entry in entries {
+						// y level
+						let mut filename = entry.file_name().into_string().unwrap();
+						let file_comp = TileCompression::from_filename(&mut filename);
+						let Some(file_form) = TileFormat::from_filename(&mut filename) else {
+							continue;
+						};
+						let Ok(y) = filename.parse::<u32>() else {
+							continue;
+						};
+						let Ok(coord3) = TileCoord3::new(x, y, z) else {
+							continue;
+						};
+						files.push((coord3, file_form, file_comp, entry.path(), None));
+					}
+				}
+			}
+		}
+
+		Ok(files)
+	}
+
+	/// Detects TMS, if the tiles of the highest zoom level are within the "bounds" of the metadata only
+	/// when their rows are flipped. Without bounds, XYZ is assumed.
+	fn detect_scheme(tilejson: &TileJSON, files: &[TileFile]) -> TileScheme {
+		let (Some(bounds), Some(z)) = (tilejson.bounds, files.iter().map(|file| file.0.z).max()) else {
+			return TileScheme::Xyz;
+		};
+		let Ok(bbox) = TileBBox::from_geo(z, &bounds) else {
+			return TileScheme::Xyz;
+		};
+		let (mut xyz, mut tms) = (0, 0);
+		for (coord, ..) in files.iter().filter(|file| file.0.z == z) {
+			if bbox.contains3(coord) {
+				xyz += 1;
+			}
+			let mut flipped = *coord;
+			flipped.flip_y();
+			if bbox.contains3(&flipped) {
+				tms += 1;
+			}
+		}
+		if tms > xyz {
+			log::info!("detected tms scheme in {:?}", bounds);
+			TileScheme::Tms
+		} else {
+			TileScheme::Xyz
+		}
+	}
+
+	/// Reads and merges all metadata files in the root of the directory.
+	fn read_tilejson(dir: &Path) -> Result<TileJSON> {
+		let mut tilejson = TileJSON::default();
+		for result in fs::read_dir(dir)? {
+			let Ok(entry) = result else { continue };
+			let mut filename = entry.file_name().to_str().unwrap_or_default().to_string();
+			let compression = TileCompression::from_filename(&mut filename);
+			if !matches!(
+				filename.as_str(),
+				"meta.json" | "tiles.json" | "metadata.json" | "tilejson.json"
+			) {
+				continue;
+			}
+			let blob = decompress(Self::read(&entry.path())?, &compression)?;
+			let metadata = Self::parse_metadata(&blob).with_context(|| format!("Failed to parse {:?}", entry.path()))?;
+			tilejson.merge(&metadata)?;
+		}
+		Ok(tilejson)
+	}
+
+	/// Parses a TileJSON, or the metadata of MBTiles exported by other tools, where bounds, center and
+	/// zoom levels are strings and the vector layers are in the JSON string "json".
+	fn parse_metadata(blob: &Blob) -> Result<TileJSON> {
+		let parse_numbers = |text: &str| -> Result<Vec<f64>> {
+			text
+				.split(',')
+				.map(|v| {
+					v.trim()
+						.parse::<f64>()
+						.with_context(|| format!("{v:?} is not a number"))
+				})
+				.collect()
+		};
+
+		let mut object = JsonObject::default();
+		for (key, value) in JsonValue::parse_blob(blob)?.as_object()?.iter() {
+			match (key.as_str(), value) {
+				("bounds" | "center", JsonValue::String(text)) => object.set(key, parse_numbers(text)?),
+				("minzoom" | "maxzoom", JsonValue::String(text)) => object.set(key, text.trim().parse::<u8>()?),
+				("json", JsonValue::String(text)) => {
+					if let Some(vector_layers) = JsonObject::parse_str(text)?.get("vector_layers") {
+						object.set("vector_layers", vector_layers);
+					}
+				}
+				_ => object.set(key, value),
+			}
+		}
+		TileJSON::from_object(&object)
+	}
+
 	fn read(path: &Path) -> Result<Blob> {
 		Ok(Blob::from(fs::read(path)?))
 	}
@@ -216,10 +393,36 @@ impl TilesReaderTrait for DirectoryTilesReader {
 	fn get_tilejson(&self) -> &TileJSON {
 		&self.tilejson
 	}
+	fn set_read_hints(&mut self, hints: &ReadHints) {
+		self.tile_map.retain(|coord, _| hints.contains_coord(coord));
+
+		// parameters and TileJSON must not advertise the removed tiles
+		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
+		for coord in self.tile_map.coords() {
+			bbox_pyramid.include_coord(&coord);
+		}
+		self.tilejson.update_from_pyramid(&bbox_pyramid);
+		self.parameters.bbox_pyramid = bbox_pyramid;
+	}
+	/// Counts the tiles inside `bbox`, using the sizes of the index or of the files.
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		let (mut count, mut bytes) = (0, 0);
+		for (coord, (path, size)) in self.tile_map.iter() {
+			if bbox.contains3(&coord) {
+				count += 1;
+				bytes += match size {
+					Some(size) => *size,
+					None => fs::metadata(path)?.len(),
+				};
+			}
+		}
+		Ok(Some((count, bytes)))
+	}
+
 	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
 		log::trace!("get_tile_data {:?}", coord);
 
-		if let Some(path) = self.tile_map.get(coord) {
+		if let Some((path, _)) = self.tile_map.get(coord) {
 			Self::read(path).map(Some)
 		} else {
 			Ok(None)
@@ -268,6 +471,219 @@ mod tests {
 		Ok(())
 	}
 
+	#[tokio::test]
+	async fn tms_scheme() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("3/2/1.png").write_str("tile 3/2/1")?;
+		dir.child("meta.json").write_str(r#"{"type":"dummy","scheme":"tms"}"#)?;
+
+		// the hints use XYZ coordinates
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(3, 2, 6, 2, 6)?);
+		let reader = DirectoryTilesReader::open_path_with_hints(&dir, &ReadHints::from_bbox_pyramid(pyramid))?;
+
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,6,2,6] (1)]");
+		assert_eq!(reader.get_tilejson().get_scheme()?, TileScheme::Xyz);
+		let tile_data = reader.get_tile_data(&TileCoord3::new(2, 6, 3)?).await?.unwrap();
+		assert_eq!(tile_data, Blob::from("tile 3/2/1"));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn scheme_option() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("3/2/1.png").write_str("tile 3/2/1")?;
+		dir.child("meta.json").write_str(r#"{"scheme":"xyz"}"#)?;
+
+		let options = DirectoryReaderOptions::from_query("scheme=tms")?;
+		let reader = DirectoryTilesReader::open_path_with_options(&dir, &ReadHints::default(), &options)?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,6,2,6] (1)]");
+
+		assert_eq!(
+			DirectoryReaderOptions::from_query("")?,
+			DirectoryReaderOptions::default()
+		);
+		assert_eq!(
+			DirectoryReaderOptions::from_query("scheme=tms&x=1")
+				.unwrap_err()
+				.to_string(),
+			"unknown parameter \"x\" for directories, only \"layout\", \"scheme\" and \"index\" are supported"
+		);
+		assert!(DirectoryReaderOptions::from_query("scheme=abc").is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn read_index() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("3/2/1.png").write_str("tile 3/2/1")?;
+		dir.child("3/2/2.png").write_str("tile 3/2/2")?;
+		// the size is taken from the index, and the tile 3/2/2 is missing in the index
+		dir.child("tiles.index.csv")
+			.write_str("z,x,y,size,xxh64,path\n3,2,1,1000,0000000000000000,3/2/1.png\n")?;
+
+		let reader = DirectoryTilesReader::open_path(&dir)?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,1,2,1] (1)]");
+		assert_eq!(
+			reader.get_tile_data(&TileCoord3::new(2, 1, 3)?).await?,
+			Some(Blob::from("tile 3/2/1"))
+		);
+		assert_eq!(reader.count_bbox_tiles(&TileBBox::new_full(3)?).await?, Some((1, 1000)));
+
+		let options = DirectoryReaderOptions::from_query("index=false")?;
+		let reader = DirectoryTilesReader::open_path_with_options(&dir, &ReadHints::default(), &options)?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,1,2,2] (2)]");
+		assert_eq!(reader.count_bbox_tiles(&TileBBox::new_full(3)?).await?, Some((2, 20)));
+
+		dir.child("tiles.index.csv")
+			.write_str("z,x,y,size,xxh64,path\n3,2,1,10,0000000000000000,3/2/1.txt\n")?;
+		assert_eq!(
+			DirectoryTilesReader::open_path(&dir).unwrap_err().to_string(),
+			"unknown tile format of \"3/2/1.txt\" in the index"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn write_and_read_index() -> Result<()> {
+		let dir = TempDir::new()?;
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(9, 300, 10, 301, 11)?);
+		let mut mock_reader = crate::MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			pyramid,
+		))?;
+		let options = crate::DirectoryWriterOptions::from_query("layout=sharded&index=true")?;
+		crate::DirectoryTilesWriter::write_to_path_with_options(&mut mock_reader, dir.path(), &options).await?;
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(9, 301, 0, 400, 400)?);
+		let reader = DirectoryTilesReader::open_path_with_hints(&dir, &ReadHints::from_bbox_pyramid(pyramid))?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[9: [301,10,301,11] (2)]"
+		);
+		assert!(reader.get_tile_data(&TileCoord3::new(301, 11, 9)?).await?.is_some());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn detect_tms_scheme() -> Result<()> {
+		// tiles of Berlin at zoom level 10 are in row 335 (XYZ) or 688 (TMS)
+		let bounds = r#"{"bounds":[13.0,52.3,13.8,52.7]}"#;
+
+		let dir = TempDir::new()?;
+		dir.child("10/550/688.png").write_str("tile")?;
+		dir.child("9/275/344.png").write_str("tile")?;
+		dir.child("tilejson.json").write_str(bounds)?;
+		let reader = DirectoryTilesReader::open_path(&dir)?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[9: [275,167,275,167] (1), 10: [550,335,550,335] (1)]"
+		);
+
+		let dir = TempDir::new()?;
+		dir.child("10/550/335.png").write_str("tile")?;
+		dir.child("tilejson.json").write_str(bounds)?;
+		let reader = DirectoryTilesReader::open_path(&dir)?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[10: [550,335,550,335] (1)]"
+		);
+
+		// without bounds, XYZ is assumed
+		let dir = TempDir::new()?;
+		dir.child("10/550/688.png").write_str("tile")?;
+		let reader = DirectoryTilesReader::open_path(&dir)?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[10: [550,688,550,688] (1)]"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn read_mbtiles_style_metadata() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("10/550/335.pbf").write_str("tile")?;
+		dir.child("metadata.json").write_str(
+			r#"{"name":"berlin","format":"pbf","bounds":"13.0,52.3,13.8,52.7","center":"13.4,52.5,10","minzoom":"10","maxzoom":"10","json":"{\"vector_layers\":[{\"id\":\"water\",\"fields\":{}}]}"}"#,
+		)?;
+
+		let reader = DirectoryTilesReader::open_path(&dir)?;
+		let tilejson = reader.get_tilejson();
+		assert_eq!(tilejson.get_str("name"), Some("berlin"));
+		assert_eq!(tilejson.vector_layers.0.keys().collect::<Vec<_>>(), ["water"]);
+		assert_eq!(
+			tilejson.as_string(),
+			r#"{"bounds":[13.359375,52.4827802220782,13.7109375,52.69636107827448],"center":[13.4,52.5,10],"format":"pbf","maxzoom":10,"minzoom":10,"name":"berlin","tilejson":"3.0.0","vector_layers":[{"fields":{},"id":"water"}]}"#
+		);
+
+		dir.child("metadata.json").write_str(r#"{"bounds":"13.0,north"}"#)?;
+		assert_eq!(
+			format!("{:#}", DirectoryTilesReader::open_path(&dir).unwrap_err()),
+			format!(
+				"Failed to parse {:?}: \"north\" is not a number: invalid float literal",
+				dir.path().join("metadata.json")
+			)
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn open_path_with_hints() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("3/2/1.png").write_str("tile 3/2/1")?;
+		dir.child("3/5/1.png").write_str("tile 3/5/1")?;
+		// would fail with "found multiple tile formats", if level 4 was scanned
+		dir.child("4/2/1.jpg").write_str("tile 4/2/1")?;
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(3, 0, 0, 3, 7)?);
+		let mut reader = DirectoryTilesReader::open_path_with_hints(&dir, &ReadHints::from_bbox_pyramid(pyramid))?;
+
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,1,2,1] (1)]");
+		assert!(reader.get_tile_data(&TileCoord3::new(2, 1, 3)?).await?.is_some());
+		assert!(reader.get_tile_data(&TileCoord3::new(5, 1, 3)?).await?.is_none());
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(3, 3, 0, 7, 7)?);
+		reader.set_read_hints(&ReadHints::from_bbox_pyramid(pyramid));
+		assert!(reader.get_tile_data(&TileCoord3::new(2, 1, 3)?).await?.is_none());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn set_read_hints_updates_parameters() -> Result<()> {
+		let dir = TempDir::new()?;
+		dir.child("3/2/1.png").write_str("tile 3/2/1")?;
+		dir.child("3/5/1.png").write_str("tile 3/5/1")?;
+		dir.child("4/4/2.png").write_str("tile 4/4/2")?;
+		dir.child("4/10/2.png").write_str("tile 4/10/2")?;
+		let mut reader = DirectoryTilesReader::open_path(&dir)?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[3: [2,1,5,1] (4), 4: [4,2,10,2] (7)]"
+		);
+
+		let mut pyramid = TileBBoxPyramid::new_full(4);
+		pyramid.intersect_geo_bbox(&GeoBBox(-180.0, -85.0, -45.0, 85.0));
+		reader.set_read_hints(&ReadHints::from_bbox_pyramid(pyramid));
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[3: [2,1,2,1] (1), 4: [4,2,4,2] (1)]"
+		);
+		assert_eq!(
+			reader.get_tilejson().as_string(),
+			"{\"bounds\":[-90,74.01954331150229,-67.5,79.17133464081945],\"maxzoom\":4,\"minzoom\":3,\"tilejson\":\"3.0.0\"}"
+		);
+
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn open_path_with_nonexistent_directory() -> Result<()> {
 		let dir = TempDir::new()?;
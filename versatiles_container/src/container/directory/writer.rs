@@ -11,6 +11,10 @@
 //! - `<x>`: Tile X coordinate (directory)
 //! - `<y>.<format>[.<compression>]`: Tile Y coordinate with the tile format and optional compression type as the file extension
 //!
+//! For huge tile dumps, `DirectoryWriterOptions` can select the sharded layout, where no folder contains more than
+//! 256 entries, see [`DirectoryLayout`]. They can also write the index `tiles.index.csv`, so that readers do not
+//! have to scan the directory, e.g. `tiles/?layout=sharded&index=true`.
+//!
 //! Example:
 //! ```text
 //! /tiles/1/2/3.png
@@ -44,7 +48,11 @@
 //! ## Testing
 //! This module includes comprehensive tests to ensure the correct functionality of writing metadata, handling different file formats, and verifying directory structure.
 
-use crate::TilesWriterTrait;
+use super::{
+	index::{parse_bool, write_index, IndexEntry, INDEX_FILENAME},
+	DirectoryLayout,
+};
+use crate::{TilesWriterTrait, WRITE_QUEUE_SIZE};
 use anyhow::{bail, ensure, Result};
 use async_trait::async_trait;
 use std::{
@@ -58,40 +66,43 @@ use versatiles_core::{
 	utils::compress,
 };
 
-/// A struct that provides functionality to write tile data to a directory structure.
-pub struct DirectoryTilesWriter {}
+/// Options for writing a directory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirectoryWriterOptions {
+	/// Arrangement of the tile files
+	pub layout: DirectoryLayout,
+	/// Write the index `tiles.index.csv` of all tiles, which readers use instead of scanning the directory
+	pub index: bool,
+}
 
-impl DirectoryTilesWriter {
-	/// Writes the given blob to the specified path. Creates the necessary directory structure if it doesn't exist.
-	///
-	/// # Arguments
-	/// * `path` - The path where the blob should be written.
-	/// * `blob` - The blob data to write.
-	///
-	/// # Errors
-	/// Returns an error if the parent directory cannot be created or if writing to the file fails.
-	fn write(path: PathBuf, blob: Blob) -> Result<()> {
-		let parent = path.parent().unwrap();
-		if !parent.exists() {
-			fs::create_dir_all(parent)?;
+impl DirectoryWriterOptions {
+	/// Parses the query of a path like `tiles/?layout=sharded&index=true`.
+	pub fn from_query(query: &str) -> Result<DirectoryWriterOptions> {
+		let mut options = DirectoryWriterOptions::default();
+		for parameter in query.split('&').filter(|p| !p.is_empty()) {
+			let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+			match key {
+				"layout" => options.layout = DirectoryLayout::parse_str(value)?,
+				"index" => options.index = parse_bool(key, value)?,
+				_ => {
+					bail!("unknown parameter \"{key}\" for writing directories, only \"layout\" and \"index\" are supported")
+				}
+			}
 		}
-
-		fs::write(&path, blob.as_slice())?;
-		Ok(())
+		Ok(options)
 	}
 }
 
-#[async_trait]
-impl TilesWriterTrait for DirectoryTilesWriter {
-	/// Writes the tile data and metadata from the given `TilesReader` to the specified directory path.
-	///
-	/// # Arguments
-	/// * `reader` - A mutable reference to the `TilesReader` providing the data.
-	/// * `path` - The directory path where the data should be written.
-	///
-	/// # Errors
-	/// Returns an error if the path is not absolute, if there are issues with file I/O, or if compression fails.
-	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+/// A struct that provides functionality to write tile data to a directory structure.
+pub struct DirectoryTilesWriter {}
+
+impl DirectoryTilesWriter {
+	/// Writes the tiles and metadata of `reader` to the directory `path`, using the given options.
+	pub async fn write_to_path_with_options(
+		reader: &mut dyn TilesReaderTrait,
+		path: &Path,
+		options: &DirectoryWriterOptions,
+	) -> Result<()> {
 		ensure!(path.is_absolute(), "path {path:?} must be absolute");
 
 		log::trace!("convert_from");
@@ -105,34 +116,84 @@ impl TilesWriterTrait for DirectoryTilesWriter {
 		let extension_compression = tile_compression.extension();
 
 		let tilejson = reader.get_tilejson();
+		let scheme = tilejson.get_scheme()?;
 		let meta_data = compress(tilejson.into(), tile_compression)?;
 		let filename = format!("tiles.json{extension_compression}");
 		Self::write(path.join(filename), meta_data)?;
 
+		// an old index would hide the new tiles
+		let index_path = path.join(INDEX_FILENAME);
+		if !options.index && index_path.exists() {
+			fs::remove_file(index_path)?;
+		}
+		let mut index_entries = Vec::new();
+
 		let mut progress = get_progress_bar("converting tiles", bbox_pyramid.count_tiles());
 
 		for bbox in bbox_pyramid.iter_levels() {
-			let mut stream = reader.get_bbox_tile_stream(bbox.clone()).await;
+			let stream = reader.get_bbox_tile_stream(bbox.clone()).await;
 
-			while let Some(entry) = stream.next().await {
-				let (coord, blob) = entry;
+			stream
+				.for_each_queued(WRITE_QUEUE_SIZE, |(mut coord, blob)| {
+					progress.inc(1);
+					scheme.apply(&mut coord);
 
-				progress.inc(1);
+					let filename = format!(
+						"{}{extension_format}{extension_compression}",
+						options.layout.tile_path(&coord)
+					);
 
-				let filename = format!(
-					"{}/{}/{}{}{}",
-					coord.z, coord.y, coord.x, extension_format, extension_compression
-				);
+					if options.index {
+						index_entries.push(IndexEntry::new(coord, &blob, filename.clone()));
+					}
 
-				// Write blob to file
-				Self::write(path.join(filename), blob)?;
-			}
+					// Write blob to file
+					Self::write(path.join(filename), blob)
+				})
+				.await?;
 		}
 
 		progress.finish();
 
+		if options.index {
+			write_index(path, index_entries)?;
+		}
+
+		Ok(())
+	}
+
+	/// Writes the given blob to the specified path. Creates the necessary directory structure if it doesn't exist.
+	///
+	/// # Arguments
+	/// * `path` - The path where the blob should be written.
+	/// * `blob` - The blob data to write.
+	///
+	/// # Errors
+	/// Returns an error if the parent directory cannot be created or if writing to the file fails.
+	fn write(path: PathBuf, blob: Blob) -> Result<()> {
+		let parent = path.parent().unwrap();
+		if !parent.exists() {
+			fs::create_dir_all(parent)?;
+		}
+
+		fs::write(&path, blob.as_slice())?;
 		Ok(())
 	}
+}
+
+#[async_trait]
+impl TilesWriterTrait for DirectoryTilesWriter {
+	/// Writes the tile data and metadata from the given `TilesReader` to the specified directory path.
+	///
+	/// # Arguments
+	/// * `reader` - A mutable reference to the `TilesReader` providing the data.
+	/// * `path` - The directory path where the data should be written.
+	///
+	/// # Errors
+	/// Returns an error if the path is not absolute, if there are issues with file I/O, or if compression fails.
+	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+		Self::write_to_path_with_options(reader, path, &DirectoryWriterOptions::default()).await
+	}
 
 	/// Writes the tile data from the given `TilesReader` to the specified `DataWriterTrait`.
 	///
@@ -151,7 +212,7 @@ impl TilesWriterTrait for DirectoryTilesWriter {
 mod tests {
 	use super::*;
 	use crate::{MockTilesReader, MOCK_BYTES_PBF};
-	use versatiles_core::{types::*, utils::decompress_gzip};
+	use versatiles_core::{assert_wildcard, types::*, utils::decompress_gzip};
 
 	/// Tests the functionality of writing tile data to a directory from a mock reader.
 	#[tokio::test]
@@ -187,4 +248,66 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn test_sharded_layout() -> Result<()> {
+		let temp_dir = assert_fs::TempDir::new()?;
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(9, 300, 10, 301, 10)?);
+		pyramid.set_level_bbox(TileBBox::new(18, 70000, 1000, 70000, 1000)?);
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			pyramid,
+		))?;
+
+		let options = DirectoryWriterOptions::from_query("layout=sharded")?;
+		DirectoryTilesWriter::write_to_path_with_options(&mut mock_reader, temp_dir.path(), &options).await?;
+
+		assert!(temp_dir.path().join("9/1/300/0/10.pbf.gz").exists());
+		assert!(temp_dir.path().join("9/1/301/0/10.pbf.gz").exists());
+		// two shard levels above zoom level 16
+		assert!(temp_dir.path().join("18/1/273/70000/0/3/1000.pbf.gz").exists());
+
+		let reader = crate::DirectoryTilesReader::open_path(temp_dir.path())?;
+		assert_eq!(
+			reader.get_parameters().bbox_pyramid.to_string(),
+			"[9: [300,10,301,10] (2), 18: [70000,1000,70000,1000] (1)]"
+		);
+
+		assert_eq!(
+			DirectoryWriterOptions::from_query("scheme=tms")
+				.unwrap_err()
+				.to_string(),
+			"unknown parameter \"scheme\" for writing directories, only \"layout\" and \"index\" are supported"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_index() -> Result<()> {
+		let temp_dir = assert_fs::TempDir::new()?;
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(1),
+		))?;
+
+		let options = DirectoryWriterOptions::from_query("layout=sharded&index")?;
+		assert!(options.index);
+		DirectoryTilesWriter::write_to_path_with_options(&mut mock_reader, temp_dir.path(), &options).await?;
+
+		let index = fs::read_to_string(temp_dir.path().join("tiles.index.csv"))?;
+		let lines: Vec<&str> = index.lines().collect();
+		assert_eq!(lines.len(), 6);
+		assert_eq!(lines[0], "z,x,y,size,xxh64,path");
+		assert_wildcard!(lines[1], "0,0,0,*,*,0/0/0/0/0.pbf.gz");
+		assert_wildcard!(lines[5], "1,1,1,*,*,1/0/1/0/1.pbf.gz");
+
+		// writing again without index removes the old index
+		DirectoryTilesWriter::write_to_path(&mut mock_reader, temp_dir.path()).await?;
+		assert!(!temp_dir.path().join("tiles.index.csv").exists());
+		Ok(())
+	}
 }
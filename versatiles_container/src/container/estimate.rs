@@ -0,0 +1,169 @@
+//! Estimates the size of a selection of tiles, e.g. to quote the size of an extract before running it.
+//!
+//! Only the indexes of the containers are used, no tiles are read. Containers without an index, e.g.
+//! pipelines, report the number of tiles covered by the selection and no byte sizes.
+//!
+//! ```no_run
+//! use versatiles_container::{estimate_selection, get_reader};
+//! use versatiles_core::types::GeoBBox;
+//! use anyhow::Result;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let reader = get_reader("planet.versatiles").await?;
+//!     let estimate = estimate_selection(&*reader, &GeoBBox(13.0, 52.3, 13.8, 52.7), 0..=14).await?;
+//!     println!("{} tiles, {:?} bytes", estimate.tile_count(), estimate.tile_bytes());
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::Result;
+use std::ops::RangeInclusive;
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::{GeoBBox, TileBBoxPyramid, TilesReaderTrait},
+};
+
+/// Number and size of the selected tiles of a single zoom level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelEstimate {
+	pub level: u8,
+	/// Number of tiles. Without a container index, this is the number of tiles covered by the selection.
+	pub tile_count: u64,
+	/// Sum of the tile sizes in bytes, or `None` if the container has no index.
+	pub tile_bytes: Option<u64>,
+}
+
+/// Number and size of the tiles of a selection, per zoom level.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelectionEstimate {
+	pub levels: Vec<LevelEstimate>,
+}
+
+impl SelectionEstimate {
+	/// Total number of tiles.
+	pub fn tile_count(&self) -> u64 {
+		self.levels.iter().map(|l| l.tile_count).sum()
+	}
+
+	/// Total size of all tiles in bytes, or `None` if the size of any zoom level is unknown.
+	pub fn tile_bytes(&self) -> Option<u64> {
+		self.levels.iter().map(|l| l.tile_bytes).sum()
+	}
+
+	/// Returns `true` if the numbers are read from the container index.
+	pub fn is_exact(&self) -> bool {
+		self.levels.iter().all(|l| l.tile_bytes.is_some())
+	}
+
+	pub fn as_json(&self) -> JsonObject {
+		let bytes = |bytes: Option<u64>| bytes.map_or(JsonValue::Null, |b| JsonValue::from(b as f64));
+		let mut object = JsonObject::default();
+		object.set("tile_count", self.tile_count() as f64);
+		object.set("tile_bytes", bytes(self.tile_bytes()));
+		object.set("exact", self.is_exact());
+		object.set(
+			"levels",
+			JsonValue::from(
+				self
+					.levels
+					.iter()
+					.map(|l| {
+						let mut level = JsonObject::default();
+						level.set("level", l.level as f64);
+						level.set("tile_count", l.tile_count as f64);
+						level.set("tile_bytes", bytes(l.tile_bytes));
+						JsonValue::Object(level)
+					})
+					.collect::<Vec<_>>(),
+			),
+		);
+		object
+	}
+}
+
+/// Estimates the number and size of the tiles inside `bbox` at the zoom levels `zooms`.
+pub async fn estimate_selection(
+	reader: &dyn TilesReaderTrait,
+	bbox: &GeoBBox,
+	zooms: RangeInclusive<u8>,
+) -> Result<SelectionEstimate> {
+	let pyramid = TileBBoxPyramid::from_geo_bbox(*zooms.start(), *zooms.end(), bbox);
+	estimate_bbox_pyramid(reader, &pyramid).await
+}
+
+/// Estimates the number and size of the tiles inside `pyramid`.
+pub async fn estimate_bbox_pyramid(
+	reader: &dyn TilesReaderTrait,
+	pyramid: &TileBBoxPyramid,
+) -> Result<SelectionEstimate> {
+	let mut selection = reader.get_parameters().bbox_pyramid.clone();
+	selection.intersect(pyramid);
+
+	let mut levels = Vec::new();
+	for bbox in selection.iter_levels() {
+		let level = match reader.count_bbox_tiles(bbox).await? {
+			Some((tile_count, tile_bytes)) => LevelEstimate {
+				level: bbox.level,
+				tile_count,
+				tile_bytes: Some(tile_bytes),
+			},
+			None => LevelEstimate {
+				level: bbox.level,
+				tile_count: bbox.count_tiles(),
+				tile_bytes: None,
+			},
+		};
+		levels.push(level);
+	}
+	Ok(SelectionEstimate { levels })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{get_reader, make_test_file, MockTilesReader};
+	use versatiles_core::types::{TileCompression, TileFormat, TilesReaderParameters};
+
+	#[tokio::test]
+	async fn test_containers() -> Result<()> {
+		let path = make_test_file(TileFormat::PBF, TileCompression::Gzip, 4, "versatiles").await?;
+		let reader = get_reader(path.to_str().unwrap()).await?;
+
+		let estimate = estimate_selection(&*reader, &GeoBBox(-180.0, 0.0, 0.0, 85.0), 2..=3).await?;
+		assert_eq!(estimate.levels.len(), 2);
+		assert_eq!(estimate.levels[0].level, 2);
+		assert_eq!(estimate.levels[0].tile_count, 4);
+		assert_eq!(estimate.levels[1].tile_count, 16);
+		assert_eq!(estimate.tile_count(), 20);
+		assert!(estimate.is_exact());
+		assert!(estimate.tile_bytes().unwrap() > 0);
+
+		// all containers with an index agree
+		for extension in ["tar", "pmtiles", "mbtiles"] {
+			let path = make_test_file(TileFormat::PBF, TileCompression::Gzip, 4, extension).await?;
+			let reader = get_reader(path.to_str().unwrap()).await?;
+			let other = estimate_selection(&*reader, &GeoBBox(-180.0, 0.0, 0.0, 85.0), 2..=3).await?;
+			assert_eq!(estimate, other, "{extension}");
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_without_index() -> Result<()> {
+		let reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(3),
+		))?;
+		let estimate = estimate_bbox_pyramid(&reader, &TileBBoxPyramid::new_full(1)).await?;
+		assert_eq!(estimate.tile_count(), 5);
+		assert_eq!(estimate.tile_bytes(), None);
+		assert!(!estimate.is_exact());
+		assert_eq!(
+			estimate.as_json().stringify(),
+			"{\"exact\":false,\"levels\":[{\"level\":0,\"tile_bytes\":null,\"tile_count\":1},{\"level\":1,\"tile_bytes\":null,\"tile_count\":4}],\"tile_bytes\":null,\"tile_count\":5}"
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,90 @@
+//! Opens tile containers with an injected range fetcher, e.g. in a browser or web worker.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use versatiles_container::open_fetch_reader;
+//!
+//! // `BrowserFetch` implements `RangeFetchTrait` with HTTP range requests via the `fetch` API
+//! let reader = open_fetch_reader(BrowserFetch::new("https://example.org/osm.versatiles")).await?;
+//! let tile = reader.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?;
+//! ```
+
+use crate::*;
+use anyhow::{bail, Result};
+use versatiles_core::{
+	io::{DataReader, DataReaderFetch, RangeFetchTrait},
+	types::{ByteRange, TilesReaderTrait},
+};
+
+/// Opens a `*.versatiles` or `*.pmtiles` file, that is read with `fetch`.
+/// The format is detected by the magic word at the start of the file.
+pub async fn open_fetch_reader(fetch: impl RangeFetchTrait + 'static) -> Result<Box<dyn TilesReaderTrait>> {
+	let reader: DataReader = DataReaderFetch::new(fetch);
+	let magic = reader.read_range(&ByteRange::new(0, 14)).await?;
+
+	if magic.as_slice().starts_with(b"versatiles_v02") {
+		Ok(VersaTilesReader::open_reader(reader).await?.boxed())
+	} else if magic.as_slice().starts_with(b"PMTiles") {
+		Ok(PMTilesReader::open_reader(reader).await?.boxed())
+	} else {
+		bail!("{} is neither a versatiles nor a PMTiles file", reader.get_name())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use async_trait::async_trait;
+	use versatiles_core::{
+		io::DataWriterBlob,
+		types::{Blob, TileBBoxPyramid, TileCompression, TileFormat, TilesReaderParameters},
+	};
+
+	#[derive(Debug)]
+	struct BlobFetch(Blob);
+
+	#[async_trait]
+	impl RangeFetchTrait for BlobFetch {
+		async fn fetch_range(&self, range: &ByteRange) -> Result<Blob> {
+			self.0.read_range(range)
+		}
+		async fn fetch_all(&self) -> Result<Blob> {
+			Ok(self.0.clone())
+		}
+		fn get_name(&self) -> &str {
+			"blob"
+		}
+	}
+
+	async fn write_blob(pmtiles: bool) -> Result<Blob> {
+		let mut reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(3),
+		))?;
+		let mut writer = DataWriterBlob::new()?;
+		if pmtiles {
+			PMTilesWriter::write_to_writer(&mut reader, &mut writer).await?;
+		} else {
+			VersaTilesWriter::write_to_writer(&mut reader, &mut writer).await?;
+		}
+		Ok(writer.into_blob())
+	}
+
+	#[tokio::test]
+	async fn open_versatiles_and_pmtiles() -> Result<()> {
+		for (pmtiles, container_name) in [(false, "versatiles"), (true, "pmtiles")] {
+			let mut reader = open_fetch_reader(BlobFetch(write_blob(pmtiles).await?)).await?;
+			assert_eq!(reader.get_container_name(), container_name);
+			assert_eq!(reader.get_parameters().bbox_pyramid, TileBBoxPyramid::new_full(3));
+			MockTilesWriter::write(reader.as_mut()).await?;
+		}
+
+		let error = open_fetch_reader(BlobFetch(Blob::from(vec![0u8; 64])))
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "blob is neither a versatiles nor a PMTiles file");
+		Ok(())
+	}
+}
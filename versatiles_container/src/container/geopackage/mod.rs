@@ -0,0 +1,20 @@
+//! SQLite file `*.gpkg` as tile container
+//!
+//! This module provides structures and implementations for reading and writing tiles to and from an OGC GeoPackage.
+//! Only tile pyramids in Web Mercator (EPSG:3857) are supported, whose tile matrices are aligned to the usual XYZ tile grid.
+//!
+//! The main components of this module are:
+//! - `GeoPackageTilesReader`: Reads tiles from a GeoPackage.
+//! - `GeoPackageTilesWriter`: Writes tiles to a GeoPackage.
+
+mod reader;
+mod writer;
+
+pub use reader::GeoPackageTilesReader;
+pub use writer::GeoPackageTilesWriter;
+
+/// Half of the width of the Web Mercator world in meters
+const WORLD_HALF: f64 = 20_037_508.342_789_244;
+
+/// Standard URI of the metadata entry that stores the complete TileJSON
+const TILEJSON_STANDARD_URI: &str = "https://github.com/mapbox/tilejson-spec";
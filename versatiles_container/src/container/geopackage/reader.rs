@@ -0,0 +1,509 @@
+//! Provides functionality for reading tile data from an OGC GeoPackage.
+//!
+//! The `GeoPackageTilesReader` reads the first tile pyramid table listed in `gpkg_contents`. Its tile matrices must use
+//! Web Mercator (EPSG:3857) and must be aligned to the usual XYZ tile grid, but they may cover only a part of the world
+//! and their `zoom_level` may differ from the actual zoom level.
+//!
+//! The tile format is detected from the tile data. The metadata is restored from the TileJSON that
+//! `GeoPackageTilesWriter` stores in `gpkg_metadata`, or otherwise from the identifier and description of the table.
+//!
+//! ## Usage Example
+//! ```rust
+//! use versatiles_container::{GeoPackageTilesReader, GeoPackageTilesWriter, MBTilesReader, TilesWriterTrait};
+//! use versatiles_core::types::{TileCoord3, TilesReaderTrait};
+//! use anyhow::Result;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let path = std::env::current_dir()?.join("../testdata/berlin.mbtiles");
+//!     let mut reader = MBTilesReader::open_path(&path)?;
+//!     let temp_path = std::env::temp_dir().join("berlin.gpkg");
+//!     GeoPackageTilesWriter::write_to_path(&mut reader, &temp_path).await?;
+//!
+//!     let reader = GeoPackageTilesReader::open_path(&temp_path)?;
+//!     let tile = reader.get_tile_data(&TileCoord3::new(8803, 5376, 14)?).await?;
+//!     assert!(tile.is_some());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use super::{TILEJSON_STANDARD_URI, WORLD_HALF};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use log::trace;
+use r2d2::Pool;
+use r2d2_sqlite::{
+	rusqlite::{params, OptionalExtension},
+	SqliteConnectionManager,
+};
+use std::path::Path;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::get_read_chunk_size};
+
+/// Maps the XYZ tiles of one zoom level to a tile matrix of the GeoPackage.
+#[derive(Clone, Debug)]
+struct Level {
+	gpkg_zoom: u32,
+	x_offset: u32,
+	y_offset: u32,
+}
+
+impl Level {
+	/// Converts a bbox of XYZ tiles into the ranges of `tile_column` and `tile_row`.
+	fn get_ranges(&self, bbox: &TileBBox) -> Option<[u32; 4]> {
+		if bbox.is_empty() || bbox.x_max < self.x_offset || bbox.y_max < self.y_offset {
+			return None;
+		}
+		Some([
+			bbox.x_min.saturating_sub(self.x_offset),
+			bbox.x_max - self.x_offset,
+			bbox.y_min.saturating_sub(self.y_offset),
+			bbox.y_max - self.y_offset,
+		])
+	}
+}
+
+/// A struct that provides functionality to read tile data from a GeoPackage.
+pub struct GeoPackageTilesReader {
+	name: String,
+	pool: Pool<SqliteConnectionManager>,
+	table: String,
+	levels: Vec<Option<Level>>,
+	tilejson: TileJSON,
+	parameters: TilesReaderParameters,
+}
+
+impl GeoPackageTilesReader {
+	/// Opens the GeoPackage and creates a `GeoPackageTilesReader` instance.
+	///
+	/// # Errors
+	/// Returns an error if the file does not exist, if the path is not absolute,
+	/// or if the GeoPackage doesn't contain a supported tile pyramid.
+	pub fn open_path(path: &Path) -> Result<GeoPackageTilesReader> {
+		trace!("open {path:?}");
+
+		ensure!(path.exists(), "file {path:?} does not exist");
+		ensure!(path.is_absolute(), "path {path:?} must be absolute");
+
+		let name = String::from(path.to_str().unwrap());
+		GeoPackageTilesReader::load_from_sqlite(path).with_context(|| format!("Failed to read GeoPackage {name:?}"))
+	}
+
+	fn load_from_sqlite(path: &Path) -> Result<GeoPackageTilesReader> {
+		let manager = SqliteConnectionManager::file(path);
+		let pool = Pool::builder().max_size(10).build(manager)?;
+		let conn = pool.get()?;
+
+		let (table, data_type, identifier, description) = conn
+			.query_row(
+				"SELECT table_name, data_type, identifier, description FROM gpkg_contents WHERE data_type IN ('tiles', 'vector-tiles') ORDER BY table_name LIMIT 1",
+				[],
+				|row| {
+					Ok((
+						row.get::<_, String>(0)?,
+						row.get::<_, String>(1)?,
+						row.get::<_, Option<String>>(2)?,
+						row.get::<_, Option<String>>(3)?,
+					))
+				},
+			)
+			.optional()?
+			.context("GeoPackage contains no tile pyramid")?;
+
+		let levels = load_levels(&conn, &table)?;
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		for (z, level) in levels.iter().enumerate() {
+			let Some(level) = level else { continue };
+			let range = conn.query_row(
+				&format!(
+					"SELECT MIN(tile_column), MIN(tile_row), MAX(tile_column), MAX(tile_row) FROM {} WHERE zoom_level = ?",
+					quote(&table)
+				),
+				[level.gpkg_zoom],
+				|row| {
+					Ok([
+						row.get::<_, Option<u32>>(0)?,
+						row.get::<_, Option<u32>>(1)?,
+						row.get::<_, Option<u32>>(2)?,
+						row.get::<_, Option<u32>>(3)?,
+					])
+				},
+			)?;
+			if let [Some(x0), Some(y0), Some(x1), Some(y1)] = range {
+				pyramid.set_level_bbox(TileBBox::new(
+					z as u8,
+					x0 + level.x_offset,
+					y0 + level.y_offset,
+					x1 + level.x_offset,
+					y1 + level.y_offset,
+				)?);
+			}
+		}
+
+		// the vector tiles extension registers itself in gpkg_extensions, which may not exist
+		let is_vector = data_type == "vector-tiles"
+			|| conn
+				.query_row(
+					"SELECT COUNT(*) FROM gpkg_extensions WHERE table_name = ? AND extension_name LIKE '%vector_tiles%'",
+					[&table],
+					|row| row.get::<_, u32>(0),
+				)
+				.unwrap_or(0)
+				> 0;
+		let first_tile = conn
+			.query_row(&format!("SELECT tile_data FROM {} LIMIT 1", quote(&table)), [], |row| {
+				row.get::<_, Vec<u8>>(0)
+			})
+			.optional()?;
+		let (tile_format, tile_compression) = match first_tile {
+			Some(data) => detect_format(&data, is_vector)?,
+			None if is_vector => (TileFormat::PBF, TileCompression::Uncompressed),
+			None => (TileFormat::PNG, TileCompression::Uncompressed),
+		};
+
+		// the metadata tables are optional as well
+		let stored_tilejson = conn
+			.query_row(
+				"SELECT m.metadata FROM gpkg_metadata m JOIN gpkg_metadata_reference r ON r.md_file_id = m.id WHERE r.table_name = ? AND m.md_standard_uri = ? LIMIT 1",
+				params![table, TILEJSON_STANDARD_URI],
+				|row| row.get::<_, String>(0),
+			)
+			.ok();
+
+		let mut tilejson = TileJSON::default();
+		match stored_tilejson {
+			Some(text) => tilejson.merge(&TileJSON::try_from(&text).context("Failed to parse stored TileJSON")?)?,
+			None => {
+				if let Some(identifier) = identifier {
+					tilejson.set_string("name", &identifier)?;
+				}
+				if let Some(description) = description.filter(|d| !d.is_empty()) {
+					tilejson.set_string("description", &description)?;
+				}
+			}
+		}
+		tilejson.update_from_pyramid(&pyramid);
+
+		drop(conn);
+
+		Ok(GeoPackageTilesReader {
+			name: String::from(path.to_str().unwrap()),
+			pool,
+			table,
+			levels,
+			tilejson,
+			parameters: TilesReaderParameters::new(tile_format, tile_compression, pyramid),
+		})
+	}
+
+	fn get_level(&self, z: u8) -> Option<&Level> {
+		self.levels.get(z as usize)?.as_ref()
+	}
+
+	/// Returns a stream of the tiles inside `bbox`, that reads chunks of `chunk_size`x`chunk_size` tiles
+	/// one after another, while the stream is consumed.
+	async fn read_bbox_chunks(&self, bbox: TileBBox, chunk_size: u32) -> TileStream<'_> {
+		trace!("read bbox {bbox:?} in chunks of {chunk_size}x{chunk_size} tiles");
+		let chunks: Vec<TileBBox> = bbox.iter_bbox_grid(chunk_size).collect();
+		TileStream::from_stream_iter(
+			chunks
+				.into_iter()
+				.map(move |chunk| async move { TileStream::from_vec(self.read_bbox_tiles(&chunk)) }),
+		)
+		.await
+	}
+
+	/// Reads all tiles inside `bbox` at once.
+	fn read_bbox_tiles(&self, bbox: &TileBBox) -> Vec<(TileCoord3, Blob)> {
+		let Some(level) = self.get_level(bbox.level) else {
+			return Vec::new();
+		};
+		let Some([x0, x1, y0, y1]) = level.get_ranges(bbox) else {
+			return Vec::new();
+		};
+
+		let conn = self.pool.get().unwrap();
+		let mut stmt = conn
+			.prepare(&format!(
+				"SELECT tile_column, tile_row, tile_data FROM {} WHERE zoom_level = ? AND tile_column >= ? AND tile_column <= ? AND tile_row >= ? AND tile_row <= ?",
+				quote(&self.table)
+			))
+			.unwrap();
+
+		let vec: Vec<(TileCoord3, Blob)> = stmt
+			.query_map([level.gpkg_zoom, x0, x1, y0, y1], |row| {
+				let coord = TileCoord3::new(
+					row.get::<_, u32>(0)? + level.x_offset,
+					row.get::<_, u32>(1)? + level.y_offset,
+					bbox.level,
+				)
+				.unwrap();
+				Ok((coord, Blob::from(row.get::<_, Vec<u8>>(2)?)))
+			})
+			.unwrap()
+			.filter_map(|r| r.ok())
+			.collect();
+
+		trace!("got {} tiles", vec.len());
+
+		vec
+	}
+}
+
+/// Reads the tile matrices of `table` and maps them to XYZ zoom levels.
+fn load_levels(conn: &r2d2_sqlite::rusqlite::Connection, table: &str) -> Result<Vec<Option<Level>>> {
+	let (organization, coordsys_id) = conn.query_row(
+		"SELECT s.organization, s.organization_coordsys_id FROM gpkg_tile_matrix_set t JOIN gpkg_spatial_ref_sys s ON s.srs_id = t.srs_id WHERE t.table_name = ?",
+		[table],
+		|row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+	)?;
+	ensure!(
+		organization.eq_ignore_ascii_case("EPSG") && [3857, 3785, 900913].contains(&coordsys_id),
+		"only tile pyramids in Web Mercator (EPSG:3857) are supported, but {table:?} uses {organization}:{coordsys_id}"
+	);
+
+	let (min_x, max_x, max_y) = conn.query_row(
+		"SELECT min_x, max_x, max_y FROM gpkg_tile_matrix_set WHERE table_name = ?",
+		[table],
+		|row| Ok((row.get::<_, f64>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?)),
+	)?;
+
+	let mut stmt = conn.prepare("SELECT zoom_level, matrix_width FROM gpkg_tile_matrix WHERE table_name = ?")?;
+	let matrices = stmt
+		.query_map([table], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?)))?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut levels: Vec<Option<Level>> = vec![None; 32];
+	for (gpkg_zoom, matrix_width) in matrices {
+		let tile_size = (max_x - min_x) / matrix_width as f64;
+		let z = (2.0 * WORLD_HALF / tile_size).log2();
+		let x_offset = (min_x + WORLD_HALF) / tile_size;
+		let y_offset = (WORLD_HALF - max_y) / tile_size;
+		ensure!(
+			is_integer(z) && (0.0..32.0).contains(&z) && is_integer(x_offset) && is_integer(y_offset),
+			"tile matrix {gpkg_zoom} of {table:?} is not aligned to the Web Mercator tile grid"
+		);
+		levels[z.round() as usize] = Some(Level {
+			gpkg_zoom,
+			x_offset: x_offset.round().max(0.0) as u32,
+			y_offset: y_offset.round().max(0.0) as u32,
+		});
+	}
+	Ok(levels)
+}
+
+fn is_integer(value: f64) -> bool {
+	(value - value.round()).abs() < 1e-3
+}
+
+/// Quotes an SQL identifier.
+fn quote(identifier: &str) -> String {
+	format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Detects the tile format by the first bytes of a tile.
+fn detect_format(data: &[u8], is_vector: bool) -> Result<(TileFormat, TileCompression)> {
+	use TileCompression::*;
+	use TileFormat::*;
+	Ok(match data {
+		[0x1f, 0x8b, ..] => (PBF, Gzip),
+		[0x89, b'P', b'N', b'G', ..] => (PNG, Uncompressed),
+		[0xff, 0xd8, 0xff, ..] => (JPG, Uncompressed),
+		[b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => (WEBP, Uncompressed),
+		_ if is_vector => (PBF, Uncompressed),
+		_ => bail!("unknown format of raster tiles"),
+	})
+}
+
+#[async_trait]
+impl TilesReaderTrait for GeoPackageTilesReader {
+	fn get_container_name(&self) -> &str {
+		"gpkg"
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn override_compression(&mut self, tile_compression: TileCompression) {
+		self.parameters.tile_compression = tile_compression;
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		trace!("read tile from coord {coord:?}");
+
+		let Some(level) = self.get_level(coord.z) else {
+			return Ok(None);
+		};
+		let (Some(column), Some(row)) = (coord.x.checked_sub(level.x_offset), coord.y.checked_sub(level.y_offset)) else {
+			return Ok(None);
+		};
+
+		let conn = self.pool.get()?;
+		let mut stmt = conn.prepare(&format!(
+			"SELECT tile_data FROM {} WHERE zoom_level = ? AND tile_column = ? AND tile_row = ?",
+			quote(&self.table)
+		))?;
+		let data = stmt
+			.query_row([level.gpkg_zoom, column, row], |row| row.get::<_, Vec<u8>>(0))
+			.optional()?;
+		Ok(data.map(Blob::from))
+	}
+
+	/// Counts the tiles inside `bbox` and sums up their sizes in the database, without reading the tiles.
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		let Some(level) = self.get_level(bbox.level) else {
+			return Ok(Some((0, 0)));
+		};
+		let Some([x0, x1, y0, y1]) = level.get_ranges(bbox) else {
+			return Ok(Some((0, 0)));
+		};
+
+		let conn = self.pool.get()?;
+		let mut stmt = conn.prepare(&format!(
+			"SELECT COUNT(*), IFNULL(SUM(LENGTH(tile_data)), 0) FROM {} WHERE zoom_level = ? AND tile_column >= ? AND tile_column <= ? AND tile_row >= ? AND tile_row <= ?",
+			quote(&self.table)
+		))?;
+		let (count, bytes) = stmt.query_row([level.gpkg_zoom, x0, x1, y0, y1], |row| {
+			Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?))
+		})?;
+		Ok(Some((count, bytes)))
+	}
+
+	/// Returns a stream of the tiles inside `bbox`. If they don't fit into the memory budget, the bounding box
+	/// is read in chunks, see `versatiles_core::utils::get_read_chunk_size`.
+	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		trace!("read tile stream from bbox {bbox:?}");
+
+		let chunk_size = match self.count_bbox_tiles(&bbox).await {
+			Ok(Some((count, bytes))) => get_read_chunk_size(count, bytes),
+			_ => None,
+		};
+		let Some(chunk_size) = chunk_size else {
+			return TileStream::from_vec(self.read_bbox_tiles(&bbox));
+		};
+
+		self.read_bbox_chunks(bbox, chunk_size).await
+	}
+
+	fn get_source_name(&self) -> &str {
+		&self.name
+	}
+}
+
+impl std::fmt::Debug for GeoPackageTilesReader {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("GeoPackageTilesReader")
+			.field("parameters", &self.get_parameters())
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GeoPackageTilesWriter, MBTilesReader, TilesWriterTrait};
+	use assert_fs::NamedTempFile;
+	use std::env;
+
+	#[tokio::test]
+	async fn convert_mbtiles() -> Result<()> {
+		let mut mbtiles = MBTilesReader::open_path(&env::current_dir()?.join("../testdata/berlin.mbtiles"))?;
+		let filename = NamedTempFile::new("berlin.gpkg")?;
+		GeoPackageTilesWriter::write_to_path(&mut mbtiles, &filename).await?;
+
+		let reader = GeoPackageTilesReader::open_path(&filename)?;
+		assert_eq!(reader.get_container_name(), "gpkg");
+		assert_eq!(reader.get_parameters(), mbtiles.get_parameters());
+		// all metadata survives, including the vector layers and the attribution
+		assert_eq!(reader.get_tilejson(), mbtiles.get_tilejson());
+
+		let coord = TileCoord3::new(8803, 5376, 14)?;
+		assert_eq!(
+			reader.get_tile_data(&coord).await?,
+			mbtiles.get_tile_data(&coord).await?
+		);
+
+		let bbox = TileBBox::new(14, 8800, 5370, 8805, 5375)?;
+		let mut tiles1 = reader.get_bbox_tile_stream(bbox.clone()).await.collect().await;
+		let mut tiles2 = mbtiles.get_bbox_tile_stream(bbox.clone()).await.collect().await;
+		tiles1.sort_by_key(|(c, _)| c.get_sort_index());
+		tiles2.sort_by_key(|(c, _)| c.get_sort_index());
+		assert_eq!(tiles1.len(), 36);
+		assert_eq!(tiles1, tiles2);
+
+		// tiles are counted without reading them, and can be read in chunks
+		assert_eq!(
+			reader.count_bbox_tiles(&bbox).await?,
+			mbtiles.count_bbox_tiles(&bbox).await?
+		);
+		let mut tiles3 = reader.read_bbox_chunks(bbox, 4).await.collect().await;
+		tiles3.sort_by_key(|(c, _)| c.get_sort_index());
+		assert_eq!(tiles3, tiles2);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn partial_tile_matrix() -> Result<()> {
+		// a GeoPackage as written by other tools: the tile matrix set covers only a part of the world,
+		// and the zoom levels of the tile matrices start at 0
+		let filename = NamedTempFile::new("partial.gpkg")?;
+		let conn = r2d2_sqlite::rusqlite::Connection::open(&filename)?;
+		let tile_size = 2.0 * WORLD_HALF / 4.0;
+		conn.execute_batch(&format!(
+			"CREATE TABLE gpkg_spatial_ref_sys (srs_name TEXT, srs_id INTEGER PRIMARY KEY, organization TEXT, organization_coordsys_id INTEGER, definition TEXT);
+			INSERT INTO gpkg_spatial_ref_sys VALUES ('Web Mercator', 3857, 'epsg', 3857, '');
+			CREATE TABLE gpkg_contents (table_name TEXT, data_type TEXT, identifier TEXT, description TEXT, srs_id INTEGER);
+			INSERT INTO gpkg_contents VALUES ('my tiles', 'tiles', 'My Tiles', '', 3857);
+			CREATE TABLE gpkg_tile_matrix_set (table_name TEXT, srs_id INTEGER, min_x DOUBLE, min_y DOUBLE, max_x DOUBLE, max_y DOUBLE);
+			INSERT INTO gpkg_tile_matrix_set VALUES ('my tiles', 3857, {}, {}, {}, {});
+			CREATE TABLE gpkg_tile_matrix (table_name TEXT, zoom_level INTEGER, matrix_width INTEGER, matrix_height INTEGER);
+			INSERT INTO gpkg_tile_matrix VALUES ('my tiles', 0, 1, 1), ('my tiles', 1, 2, 2);
+			CREATE TABLE \"my tiles\" (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+			INSERT INTO \"my tiles\" VALUES (0, 0, 0, X'89504E47'), (1, 1, 0, X'89504E4701');",
+			-WORLD_HALF + 2.0 * tile_size,
+			WORLD_HALF - 2.0 * tile_size,
+			-WORLD_HALF + 3.0 * tile_size,
+			WORLD_HALF - tile_size,
+		))?;
+
+		let reader = GeoPackageTilesReader::open_path(&filename)?;
+		assert_eq!(
+			format!("{:?}", reader),
+			"GeoPackageTilesReader { parameters: TilesReaderParameters { bbox_pyramid: [2: [2,1,2,1] (1), 3: [5,2,5,2] (1)], tile_compression: Uncompressed, tile_format: PNG } }"
+		);
+		assert_eq!(reader.get_tilejson().get_str("name"), Some("My Tiles"));
+		assert_eq!(
+			reader.get_tile_data(&TileCoord3::new(5, 2, 3)?).await?,
+			Some(Blob::from(vec![0x89, 0x50, 0x4e, 0x47, 0x01]))
+		);
+		assert_eq!(reader.get_tile_data(&TileCoord3::new(1, 1, 2)?).await?, None);
+		assert_eq!(
+			reader
+				.get_bbox_tile_stream(TileBBox::new_full(3)?)
+				.await
+				.collect()
+				.await
+				.len(),
+			1
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn detect() -> Result<()> {
+		use TileCompression::*;
+		use TileFormat::*;
+		assert_eq!(detect_format(&[0x1f, 0x8b, 8], false)?, (PBF, Gzip));
+		assert_eq!(detect_format(&[0x1a, 0x02], true)?, (PBF, Uncompressed));
+		assert_eq!(detect_format(b"\xff\xd8\xff\xe0", false)?, (JPG, Uncompressed));
+		assert_eq!(detect_format(b"RIFF\0\0\0\0WEBPVP8", false)?, (WEBP, Uncompressed));
+		assert!(detect_format(&[0x1a, 0x02], false).is_err());
+		Ok(())
+	}
+}
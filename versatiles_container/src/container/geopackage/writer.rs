@@ -0,0 +1,276 @@
+//! This module provides functionality for writing tile data to an OGC GeoPackage.
+//!
+//! The `GeoPackageTilesWriter` creates a GeoPackage with a single tile pyramid table `tiles` in Web Mercator (EPSG:3857).
+//! Raster tiles (JPG, PNG, WEBP) are stored as a regular tile pyramid, vector tiles are stored using the
+//! vector tiles extension (`im_vector_tiles_mapbox`).
+//!
+//! GeoPackages have no place for most of the TileJSON metadata, so the complete TileJSON is stored in `gpkg_metadata`,
+//! where `GeoPackageTilesReader` restores it from.
+//!
+//! ## Usage
+//! ```rust
+//! use versatiles_container::{GeoPackageTilesWriter, PMTilesReader, TilesWriterTrait};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let path = std::env::current_dir().unwrap().join("../testdata/berlin.pmtiles");
+//!     let mut reader = PMTilesReader::open_path(&path).await.unwrap();
+//!
+//!     let temp_path = std::env::temp_dir().join("temp.gpkg");
+//!     GeoPackageTilesWriter::write_to_path(&mut reader, &temp_path).await.unwrap();
+//! }
+//! ```
+
+use super::{TILEJSON_STANDARD_URI, WORLD_HALF};
+use crate::TilesWriterTrait;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use r2d2::Pool;
+use r2d2_sqlite::{rusqlite::params, SqliteConnectionManager};
+use std::{f64::consts::PI, fs::remove_file, path::Path};
+use versatiles_core::{io::DataWriterTrait, progress::get_progress_bar, tilejson::TileJSON, types::*};
+
+const CREATE_TABLES: &str = "
+	PRAGMA application_id = 1196444487;
+	PRAGMA user_version = 10300;
+	CREATE TABLE gpkg_spatial_ref_sys (srs_name TEXT NOT NULL, srs_id INTEGER PRIMARY KEY, organization TEXT NOT NULL, organization_coordsys_id INTEGER NOT NULL, definition TEXT NOT NULL, description TEXT);
+	CREATE TABLE gpkg_contents (table_name TEXT NOT NULL PRIMARY KEY, data_type TEXT NOT NULL, identifier TEXT UNIQUE, description TEXT DEFAULT '', last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')), min_x DOUBLE, min_y DOUBLE, max_x DOUBLE, max_y DOUBLE, srs_id INTEGER, CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id));
+	CREATE TABLE gpkg_tile_matrix_set (table_name TEXT NOT NULL PRIMARY KEY, srs_id INTEGER NOT NULL, min_x DOUBLE NOT NULL, min_y DOUBLE NOT NULL, max_x DOUBLE NOT NULL, max_y DOUBLE NOT NULL, CONSTRAINT fk_gtms_table_name FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name), CONSTRAINT fk_gtms_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id));
+	CREATE TABLE gpkg_tile_matrix (table_name TEXT NOT NULL, zoom_level INTEGER NOT NULL, matrix_width INTEGER NOT NULL, matrix_height INTEGER NOT NULL, tile_width INTEGER NOT NULL, tile_height INTEGER NOT NULL, pixel_x_size DOUBLE NOT NULL, pixel_y_size DOUBLE NOT NULL, CONSTRAINT pk_ttm PRIMARY KEY (table_name, zoom_level), CONSTRAINT fk_tmm_table_name FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name));
+	CREATE TABLE gpkg_extensions (table_name TEXT, column_name TEXT, extension_name TEXT NOT NULL, definition TEXT NOT NULL, scope TEXT NOT NULL, CONSTRAINT ge_tce UNIQUE (table_name, column_name, extension_name));
+	CREATE TABLE gpkg_metadata (id INTEGER CONSTRAINT m_pk PRIMARY KEY ASC NOT NULL, md_scope TEXT NOT NULL DEFAULT 'dataset', md_standard_uri TEXT NOT NULL, mime_type TEXT NOT NULL DEFAULT 'text/xml', metadata TEXT NOT NULL DEFAULT '');
+	CREATE TABLE gpkg_metadata_reference (reference_scope TEXT NOT NULL, table_name TEXT, column_name TEXT, row_id_value INTEGER, timestamp DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')), md_file_id INTEGER NOT NULL, md_parent_id INTEGER, CONSTRAINT crmr_mfi_fk FOREIGN KEY (md_file_id) REFERENCES gpkg_metadata(id));
+	CREATE TABLE tiles (id INTEGER PRIMARY KEY AUTOINCREMENT, zoom_level INTEGER NOT NULL, tile_column INTEGER NOT NULL, tile_row INTEGER NOT NULL, tile_data BLOB NOT NULL, UNIQUE (zoom_level, tile_column, tile_row));
+	INSERT INTO gpkg_spatial_ref_sys VALUES
+		('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', 'undefined cartesian coordinate reference system'),
+		('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', 'undefined geographic coordinate reference system'),
+		('WGS 84 geodetic', 4326, 'EPSG', 4326, 'GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],AXIS[\"Latitude\",NORTH],AXIS[\"Longitude\",EAST],AUTHORITY[\"EPSG\",\"4326\"]]', 'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid'),
+		('WGS 84 / Pseudo-Mercator', 3857, 'EPSG', 3857, 'PROJCS[\"WGS 84 / Pseudo-Mercator\",GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],AUTHORITY[\"EPSG\",\"4326\"]],PROJECTION[\"Mercator_1SP\"],PARAMETER[\"central_meridian\",0],PARAMETER[\"scale_factor\",1],PARAMETER[\"false_easting\",0],PARAMETER[\"false_northing\",0],UNIT[\"metre\",1,AUTHORITY[\"EPSG\",\"9001\"]],AXIS[\"Easting\",EAST],AXIS[\"Northing\",NORTH],EXTENSION[\"PROJ4\",\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +wktext +no_defs\"],AUTHORITY[\"EPSG\",\"3857\"]]', 'Web Mercator');
+	INSERT INTO gpkg_extensions VALUES
+		('gpkg_metadata', NULL, 'gpkg_metadata', 'http://www.geopackage.org/spec/#extension_metadata', 'read-write'),
+		('gpkg_metadata_reference', NULL, 'gpkg_metadata', 'http://www.geopackage.org/spec/#extension_metadata', 'read-write');
+";
+
+/// A writer for creating and populating GeoPackages.
+pub struct GeoPackageTilesWriter {
+	pool: Pool<SqliteConnectionManager>,
+}
+
+impl GeoPackageTilesWriter {
+	/// Creates a new GeoPackage with all required tables.
+	///
+	/// # Errors
+	/// Returns an error if the SQLite connection cannot be established or if the tables cannot be created.
+	fn new(path: &Path) -> Result<Self> {
+		if path.exists() {
+			remove_file(path)?;
+		}
+		let manager = SqliteConnectionManager::file(path);
+		let pool = Pool::builder().max_size(10).build(manager)?;
+
+		pool.get()?.execute_batch(CREATE_TABLES)?;
+
+		Ok(GeoPackageTilesWriter { pool })
+	}
+
+	/// Adds multiple tiles within a single transaction.
+	fn add_tiles(&mut self, tiles: &Vec<(TileCoord3, Blob)>) -> Result<()> {
+		let mut conn = self.pool.get()?;
+		let transaction = conn.transaction()?;
+		for (c, blob) in tiles {
+			transaction.execute(
+				"INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+				params![c.z, c.x, c.y, blob.as_slice()],
+			)?;
+		}
+		transaction.commit()?;
+		Ok(())
+	}
+
+	/// Registers the tile table in `gpkg_contents`, `gpkg_tile_matrix_set` and `gpkg_tile_matrix`.
+	fn add_tile_pyramid(&self, data_type: &str, pyramid: &TileBBoxPyramid, tilejson: &TileJSON) -> Result<()> {
+		let bbox = pyramid.get_geo_bbox().context("the tile pyramid is empty")?;
+		let (min_x, min_y) = lon_lat_to_mercator(bbox.0, bbox.1);
+		let (max_x, max_y) = lon_lat_to_mercator(bbox.2, bbox.3);
+
+		let conn = self.pool.get()?;
+		conn.execute(
+			"INSERT INTO gpkg_contents (table_name, data_type, identifier, description, min_x, min_y, max_x, max_y, srs_id) VALUES ('tiles', ?1, ?2, ?3, ?4, ?5, ?6, ?7, 3857)",
+			params![
+				data_type,
+				tilejson.get_str("name").unwrap_or("tiles"),
+				tilejson.get_str("description").unwrap_or(""),
+				min_x,
+				min_y,
+				max_x,
+				max_y
+			],
+		)?;
+		conn.execute(
+			"INSERT INTO gpkg_tile_matrix_set VALUES ('tiles', 3857, ?1, ?1, ?2, ?2)",
+			params![-WORLD_HALF, WORLD_HALF],
+		)?;
+		for bbox in pyramid.iter_levels() {
+			let size = 2u32.pow(bbox.level as u32);
+			let pixel_size = 2.0 * WORLD_HALF / (size as f64 * 256.0);
+			conn.execute(
+				"INSERT INTO gpkg_tile_matrix VALUES ('tiles', ?1, ?2, ?2, 256, 256, ?3, ?3)",
+				params![bbox.level, size, pixel_size],
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Registers an extension for the `tile_data` column of the tile table.
+	fn add_extension(&self, name: &str, definition: &str) -> Result<()> {
+		self.pool.get()?.execute(
+			"INSERT INTO gpkg_extensions VALUES ('tiles', 'tile_data', ?1, ?2, 'read-write')",
+			params![name, definition],
+		)?;
+		Ok(())
+	}
+
+	/// Stores the complete TileJSON as metadata of the tile table.
+	fn add_tilejson(&self, tilejson: &TileJSON) -> Result<()> {
+		let conn = self.pool.get()?;
+		conn.execute(
+			"INSERT INTO gpkg_metadata (md_scope, md_standard_uri, mime_type, metadata) VALUES ('dataset', ?1, 'application/json', ?2)",
+			params![TILEJSON_STANDARD_URI, tilejson.as_string()],
+		)?;
+		conn.execute(
+			"INSERT INTO gpkg_metadata_reference (reference_scope, table_name, md_file_id) VALUES ('table', 'tiles', ?1)",
+			params![conn.last_insert_rowid()],
+		)?;
+		Ok(())
+	}
+}
+
+/// Projects longitude and latitude to Web Mercator meters.
+fn lon_lat_to_mercator(lon: f64, lat: f64) -> (f64, f64) {
+	let lat = lat.clamp(-85.051_128_78, 85.051_128_78);
+	let x = lon / 180.0 * WORLD_HALF;
+	let y = ((90.0 + lat) * PI / 360.0).tan().ln() / PI * WORLD_HALF;
+	(x, y)
+}
+
+#[async_trait]
+impl TilesWriterTrait for GeoPackageTilesWriter {
+	/// Writes tiles and metadata to the GeoPackage.
+	///
+	/// # Errors
+	/// Returns an error if the tile format or compression is not supported, or if there are issues with writing to the SQLite database.
+	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+		use TileCompression::*;
+		use TileFormat::*;
+
+		let parameters = reader.get_parameters().clone();
+
+		let data_type = match (parameters.tile_format, parameters.tile_compression) {
+			(JPG | PNG | WEBP, Uncompressed) => "tiles",
+			(PBF, Uncompressed | Gzip) => "vector-tiles",
+			_ => bail!(
+				"combination of format ({}) and compression ({}) is not supported. GeoPackage supports only uncompressed jpg/png/webp or uncompressed/gzipped pbf",
+				parameters.tile_format,
+				parameters.tile_compression
+			),
+		};
+
+		let writer = GeoPackageTilesWriter::new(path)?;
+		let pyramid = &parameters.bbox_pyramid;
+		let tilejson = reader.get_tilejson().clone();
+
+		writer.add_tile_pyramid(data_type, pyramid, &tilejson)?;
+		match parameters.tile_format {
+			WEBP => writer.add_extension("gpkg_webp", "http://www.geopackage.org/spec/#extension_tiles_webp")?,
+			PBF => writer.add_extension(
+				"im_vector_tiles_mapbox",
+				"https://docs.ogc.org/per/20-013r4.html#_mapbox_vector_tiles_extension",
+			)?,
+			_ => {}
+		}
+		writer.add_tilejson(&tilejson)?;
+
+		let mut writer = writer;
+		let mut progress = get_progress_bar("converting tiles", pyramid.count_tiles());
+
+		for bbox in pyramid.iter_levels() {
+			let stream = reader.get_bbox_tile_stream(bbox.clone()).await;
+
+			stream
+				.for_each_buffered(2000, |v| {
+					writer.add_tiles(&v).unwrap();
+					progress.inc(v.len() as u64)
+				})
+				.await;
+		}
+
+		progress.finish();
+
+		Ok(())
+	}
+
+	/// Not implemented: Writes tiles and metadata to a generic data writer.
+	async fn write_to_writer(_reader: &mut dyn TilesReaderTrait, _writer: &mut dyn DataWriterTrait) -> Result<()> {
+		bail!("not implemented")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GeoPackageTilesReader, MockTilesReader, MockTilesWriter};
+	use assert_fs::NamedTempFile;
+
+	#[tokio::test]
+	async fn read_write() -> Result<()> {
+		for (tile_format, tile_compression) in [
+			(TileFormat::PBF, TileCompression::Gzip),
+			(TileFormat::PNG, TileCompression::Uncompressed),
+		] {
+			let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+				tile_format,
+				tile_compression,
+				TileBBoxPyramid::new_full(4),
+			))?;
+
+			let filename = NamedTempFile::new("temp.gpkg")?;
+			GeoPackageTilesWriter::write_to_path(&mut mock_reader, &filename).await?;
+
+			let mut reader = GeoPackageTilesReader::open_path(&filename)?;
+			assert_eq!(reader.get_parameters(), mock_reader.get_parameters());
+
+			MockTilesWriter::write(&mut reader).await?;
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn unsupported_format() -> Result<()> {
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Brotli,
+			TileBBoxPyramid::new_full(2),
+		))?;
+
+		let filename = NamedTempFile::new("temp.gpkg")?;
+		let error = GeoPackageTilesWriter::write_to_path(&mut mock_reader, &filename)
+			.await
+			.unwrap_err();
+		assert!(error
+			.to_string()
+			.starts_with("combination of format (pbf) and compression (brotli)"));
+		Ok(())
+	}
+
+	#[test]
+	fn mercator() {
+		let round = |(x, y): (f64, f64)| (x.round(), y.round());
+		assert_eq!(round(lon_lat_to_mercator(0.0, 0.0)), (0.0, 0.0));
+		assert_eq!(
+			round(lon_lat_to_mercator(-180.0, -90.0)),
+			(-WORLD_HALF.round(), -WORLD_HALF.round())
+		);
+		assert_eq!(round(lon_lat_to_mercator(13.4, 52.5)), (1491681.0, 6891042.0));
+	}
+}
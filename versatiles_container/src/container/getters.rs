@@ -26,22 +26,44 @@
 
 use crate::*;
 use anyhow::{bail, Context, Result};
+#[cfg(feature = "http")]
 use reqwest::Url;
 use std::env;
-use versatiles_core::{io::*, types::TilesReaderTrait};
+use versatiles_core::{
+	io::*,
+	types::{ReadHints, TileScheme, TilesReaderTrait},
+	utils::{ensure_not_being_written, FileLock},
+};
 
 /// Get a reader for a given filename or URL.
 pub async fn get_reader(filename: &str) -> Result<Box<dyn TilesReaderTrait>> {
+	get_reader_with_hints(filename, &ReadHints::default()).await
+}
+
+/// Get a reader for a given filename or URL, that only has to provide the tiles described by `hints`.
+///
+/// Containers that are indexed while opening (directories and tar files) skip everything else,
+/// all other readers receive the hints via [`TilesReaderTrait::set_read_hints`].
+pub async fn get_reader_with_hints(filename: &str, hints: &ReadHints) -> Result<Box<dyn TilesReaderTrait>> {
+	let mut reader = open_reader(filename, hints).await?;
+	reader.set_read_hints(hints);
+	Ok(reader)
+}
+
+async fn open_reader(filename: &str, hints: &ReadHints) -> Result<Box<dyn TilesReaderTrait>> {
 	let extension = get_extension(filename);
 
-	if let Ok(reader) = parse_as_url(filename) {
+	if let Some(reader) = parse_as_url(filename)? {
 		match extension {
+			"com" | "comt" => return Ok(COMTilesReader::open_reader(reader).await?.boxed()),
 			"pmtiles" => return Ok(PMTilesReader::open_reader(reader).await?.boxed()),
 			"versatiles" => return Ok(VersaTilesReader::open_reader(reader).await?.boxed()),
 			_ => bail!("Error when reading: file extension '{extension:?}' unknown"),
 		}
 	}
 
+	// directories accept options as query, e.g. "tiles/?scheme=tms"
+	let (filename, query) = filename.split_once('?').unwrap_or((filename, ""));
 	let path = env::current_dir()?.join(filename);
 
 	if !path.exists() {
@@ -49,46 +71,93 @@ pub async fn get_reader(filename: &str) -> Result<Box<dyn TilesReaderTrait>> {
 	}
 
 	if path.is_dir() {
-		return Ok(DirectoryTilesReader::open_path(&path)
+		let options = DirectoryReaderOptions::from_query(query)?;
+		return Ok(DirectoryTilesReader::open_path_with_options(&path, hints, &options)
 			.with_context(|| format!("Failed opening {path:?} as directory"))?
 			.boxed());
 	}
+	if !query.is_empty() {
+		bail!("options like \"?{query}\" are only supported for directories");
+	}
+	ensure_not_being_written(&path)?;
 
 	match extension {
+		"com" | "comt" => Ok(COMTilesReader::open_path(&path).await?.boxed()),
+		#[cfg(feature = "sqlite")]
+		"gpkg" => Ok(GeoPackageTilesReader::open_path(&path)?.boxed()),
+		#[cfg(feature = "sqlite")]
 		"mbtiles" => Ok(MBTilesReader::open_path(&path)?.boxed()),
 		"pmtiles" => Ok(PMTilesReader::open_path(&path).await?.boxed()),
-		"tar" => Ok(TarTilesReader::open_path(&path)?.boxed()),
+		"tar" => Ok(TarTilesReader::open_path_with_hints(&path, hints)?.boxed()),
 		"versatiles" => Ok(VersaTilesReader::open_path(&path).await?.boxed()),
+		#[cfg(feature = "pipeline")]
 		"vpl" => Ok(PipelineReader::open_path(&path).await?.boxed()),
-		_ => bail!("Error when reading: file extension '{extension:?}' unknown"),
+		_ => bail!("{}", unknown_extension("reading", extension)),
 	}
 }
 
-/// Parse a filename as a URL and return a DataReader if successful.
-fn parse_as_url(filename: &str) -> Result<DataReader> {
-	if filename.starts_with("http://") || filename.starts_with("https://") {
-		Ok(DataReaderHttp::from_url(Url::parse(filename)?)?)
-	} else {
-		bail!("not an url")
+/// Parse a filename as a URL and return a DataReader, or `None` if it is not a URL.
+fn parse_as_url(filename: &str) -> Result<Option<DataReader>> {
+	if !filename.starts_with("http://") && !filename.starts_with("https://") {
+		return Ok(None);
 	}
+	#[cfg(feature = "http")]
+	return Ok(Some(DataReaderHttp::from_url(Url::parse(filename)?)?));
+	#[cfg(not(feature = "http"))]
+	bail!("this build can not read from URLs like \"{filename}\", as the feature \"http\" is disabled");
+}
+
+/// Returns the error message for an extension that is unknown, or whose container is disabled in this build.
+fn unknown_extension(action: &str, extension: &str) -> String {
+	let feature = match extension {
+		"gpkg" | "mbtiles" if cfg!(not(feature = "sqlite")) => "sqlite",
+		"vpl" if cfg!(not(feature = "pipeline")) => "pipeline",
+		_ => return format!("Error when {action}: file extension '{extension:?}' unknown"),
+	};
+	format!("Error when {action}: this build does not support '*.{extension}', as the feature \"{feature}\" is disabled")
 }
 
 /// Write tiles from a reader to a file.
 pub async fn write_to_filename(reader: &mut dyn TilesReaderTrait, filename: &str) -> Result<()> {
+	// directories accept options as query, e.g. "tiles/?layout=sharded"
+	let (filename, query) = filename.split_once('?').unwrap_or((filename, ""));
 	let path = env::current_dir()?.join(filename);
 
 	if path.is_dir() {
-		return DirectoryTilesWriter::write_to_path(reader, &path).await;
+		let options = DirectoryWriterOptions::from_query(query)?;
+		return DirectoryTilesWriter::write_to_path_with_options(reader, &path, &options).await;
+	}
+	if !query.is_empty() {
+		bail!("options like \"?{query}\" are only supported for directories");
 	}
 
 	let extension = get_extension(filename);
-	match extension {
+	if reader.get_tilejson().get_scheme()? == TileScheme::Tms && extension != "tar" {
+		bail!("the tms scheme can only be written to a directory or a tar file, not to '{extension}'");
+	}
+
+	// readers in other processes refuse the file until it is complete
+	let _lock = FileLock::lock_for_writing(&path)?;
+
+	// a partial file from a failed conversion, e.g. on a full disk, must not look like a valid container
+	let existed = path.exists();
+	let result = match extension {
+		"com" | "comt" => COMTilesWriter::write_to_path(reader, &path).await,
+		#[cfg(feature = "sqlite")]
+		"gpkg" => GeoPackageTilesWriter::write_to_path(reader, &path).await,
+		#[cfg(feature = "sqlite")]
 		"mbtiles" => MBTilesWriter::write_to_path(reader, &path).await,
 		"pmtiles" => PMTilesWriter::write_to_path(reader, &path).await,
 		"tar" => TarTilesWriter::write_to_path(reader, &path).await,
 		"versatiles" => VersaTilesWriter::write_to_path(reader, &path).await,
-		_ => bail!("Error when writing: file extension '{extension:?}' unknown"),
+		_ => bail!("{}", unknown_extension("writing", extension)),
+	};
+	if result.is_err() && !existed && path.is_file() {
+		if let Err(error) = std::fs::remove_file(&path) {
+			log::warn!("could not remove the incomplete file {path:?}: {error}");
+		}
 	}
+	result
 }
 
 /// Get the file extension from a filename.
@@ -125,6 +194,10 @@ pub mod tests {
 
 		// get to test container converter
 		let container_file = match extension {
+			"comt" => NamedTempFile::new("temp.comt"),
+			"gpkg" => NamedTempFile::new("temp.gpkg"),
+			"mbtiles" => NamedTempFile::new("temp.mbtiles"),
+			"pmtiles" => NamedTempFile::new("temp.pmtiles"),
 			"tar" => NamedTempFile::new("temp.tar"),
 			"versatiles" => NamedTempFile::new("temp.versatiles"),
 			_ => panic!("make_test_file: extension {extension} not found"),
@@ -140,6 +213,7 @@ pub mod tests {
 	fn writers_and_readers() -> Result<()> {
 		#[derive(Debug)]
 		enum Container {
+			COMTiles,
 			Directory,
 			Tar,
 			Versatiles,
@@ -169,6 +243,7 @@ pub mod tests {
 
 			// get to test container converter
 			let path: TempType = match container {
+				Container::COMTiles => TempType::File(NamedTempFile::new("temp.comt")?),
 				Container::Directory => TempType::Dir(TempDir::new()?),
 				Container::Tar => TempType::File(NamedTempFile::new("temp.tar")?),
 				Container::Versatiles => TempType::File(NamedTempFile::new("temp.versatiles")?),
@@ -188,7 +263,12 @@ pub mod tests {
 			Ok(())
 		}
 
-		let containers = vec![Container::Directory, Container::Tar, Container::Versatiles];
+		let containers = vec![
+			Container::COMTiles,
+			Container::Directory,
+			Container::Tar,
+			Container::Versatiles,
+		];
 
 		for container in containers {
 			test_writer_and_reader(&container, TileFormat::PNG, TileCompression::Uncompressed)?;
@@ -198,4 +278,116 @@ pub mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn reader_options_as_query() -> Result<()> {
+		let dir = TempDir::new()?;
+		std::fs::create_dir_all(dir.path().join("3/2"))?;
+		std::fs::write(dir.path().join("3/2/1.png"), "tile")?;
+		let path = dir.path().to_str().unwrap();
+
+		let reader = get_reader(&format!("{path}?scheme=tms")).await?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,6,2,6] (1)]");
+
+		let reader = get_reader(path).await?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[3: [2,1,2,1] (1)]");
+
+		let filename = make_test_file(TileFormat::PNG, TileCompression::Uncompressed, 1, "tar").await?;
+		assert_eq!(
+			get_reader(&format!("{}?scheme=tms", filename.to_str().unwrap()))
+				.await
+				.unwrap_err()
+				.to_string(),
+			"options like \"?scheme=tms\" are only supported for directories"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn writer_options_as_query() -> Result<()> {
+		let mut reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(2),
+		))?;
+		let dir = TempDir::new()?;
+		let path = dir.path().to_str().unwrap();
+
+		write_to_filename(&mut reader, &format!("{path}?layout=sharded")).await?;
+		assert!(dir.path().join("2/0/3/0/1.png").exists());
+
+		let reader = get_reader(path).await?;
+		assert_eq!(reader.get_parameters().bbox_pyramid.count_tiles(), 21);
+
+		let error = write_to_filename(
+			&mut MockTilesReader::new_mock(reader.get_parameters().clone())?,
+			&format!("{path}/tiles.tar?layout=sharded"),
+		)
+		.await
+		.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"options like \"?layout=sharded\" are only supported for directories"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn remove_incomplete_file() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("tiles.comt");
+		let mut reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Brotli,
+			TileBBoxPyramid::new_full(1),
+		))?;
+
+		let error = write_to_filename(&mut reader, path.to_str().unwrap())
+			.await
+			.unwrap_err();
+		assert!(error.to_string().starts_with("combination of format"), "{error}");
+		assert!(!path.exists());
+
+		// existing files are kept
+		std::fs::write(&path, "old")?;
+		write_to_filename(&mut reader, path.to_str().unwrap())
+			.await
+			.unwrap_err();
+		assert!(path.exists());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn refuse_file_being_written() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("tiles.versatiles");
+		let filename = path.to_str().unwrap();
+		let mut reader = MockTilesReader::new_mock_profile(crate::MockTilesReaderProfile::Pbf)?;
+		write_to_filename(&mut reader, filename).await?;
+
+		let lock = FileLock::lock_for_writing(&path)?;
+		let error = get_reader(filename).await.unwrap_err().to_string();
+		assert!(error.contains("is being written by PID"), "{error}");
+		let error = write_to_filename(&mut reader, filename).await.unwrap_err().to_string();
+		assert!(error.contains("is being written by PID"), "{error}");
+		drop(lock);
+
+		get_reader(filename).await?;
+		write_to_filename(&mut reader, filename).await?;
+		assert!(!dir.path().join("tiles.versatiles.lock").exists());
+		Ok(())
+	}
+
+	#[test]
+	fn unknown_extension_messages() {
+		assert_eq!(
+			unknown_extension("reading", "xyz"),
+			"Error when reading: file extension '\"xyz\"' unknown"
+		);
+		// with the feature "full", all containers are available
+		assert_eq!(
+			unknown_extension("writing", "mbtiles"),
+			"Error when writing: file extension '\"mbtiles\"' unknown"
+		);
+	}
 }
@@ -0,0 +1,288 @@
+//! Coverage per zoom range.
+//!
+//! Containers often combine global low zoom levels with regional high zoom levels, e.g.
+//! `0-5:world,6-14:germany.geojson`. Each zoom range has its own coverage: the whole world, a bounding box
+//! or the bounding box of all features in a GeoJSON file. The declaration is compiled into a
+//! [`TileBBoxPyramid`], so every zoom level is limited to the tiles intersecting the bounding boxes of its coverage.
+
+use anyhow::{bail, ensure, Context, Result};
+use std::{fs::File, io::BufReader, ops::RangeInclusive, path::Path};
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::{GeoBBox, TileBBox, TileBBoxPyramid},
+	utils::parse_zoom_range,
+};
+use versatiles_geometry::{read_geojson, Geometry};
+
+/// The area covered by a zoom range.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Coverage {
+	World,
+	BBox(GeoBBox),
+}
+
+impl Coverage {
+	/// Parses `world`, a bounding box `lon_min,lat_min,lon_max,lat_max` or the filename of a GeoJSON file.
+	/// Relative filenames are resolved against `base_dir`.
+	fn parse_str(text: &str, base_dir: &Path) -> Result<Coverage> {
+		let text = text.trim();
+		if text.eq_ignore_ascii_case("world") {
+			return Ok(Coverage::World);
+		}
+		if text.to_lowercase().ends_with("json") {
+			return Coverage::from_geojson(&base_dir.join(text));
+		}
+		let values = text
+			.split(&[' ', ',', ';'])
+			.filter(|s| !s.is_empty())
+			.map(|s| {
+				s.parse::<f64>()
+					.with_context(|| format!("bbox value {s:?} is not a number"))
+			})
+			.collect::<Result<Vec<f64>>>()?;
+		Coverage::from_vec(values)
+	}
+
+	fn from_vec(values: Vec<f64>) -> Result<Coverage> {
+		ensure!(
+			values.len() == 4,
+			"a bbox must contain exactly 4 numbers, but has {}",
+			values.len()
+		);
+		let bbox = GeoBBox::try_from(values)?;
+		bbox.check()?;
+		Ok(Coverage::BBox(bbox))
+	}
+
+	/// Uses the bounding box of all features in a GeoJSON feature collection.
+	fn from_geojson(path: &Path) -> Result<Coverage> {
+		let file = File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+		let collection = read_geojson(BufReader::new(file)).with_context(|| format!("Failed to parse {path:?}"))?;
+
+		let mut bbox: Option<GeoBBox> = None;
+		for feature in collection.features {
+			for [x, y] in geometry_coordinates(feature.geometry) {
+				let point = GeoBBox(x, y, x, y);
+				match &mut bbox {
+					Some(bbox) => bbox.extend(&point),
+					None => bbox = Some(point),
+				}
+			}
+		}
+		match bbox {
+			Some(bbox) => {
+				bbox.check()?;
+				Ok(Coverage::BBox(bbox))
+			}
+			None => bail!("{path:?} contains no coordinates"),
+		}
+	}
+
+	/// Returns the tiles of a zoom level intersecting the coverage.
+	fn get_level_bbox(&self, level: u8) -> Result<TileBBox> {
+		match self {
+			Coverage::World => TileBBox::new_full(level),
+			Coverage::BBox(geo_bbox) => {
+				let mut bbox = TileBBox::new_empty(level)?;
+				for part in geo_bbox.split_antimeridian() {
+					bbox.include_bbox(&TileBBox::from_geo(level, &part)?)?;
+				}
+				Ok(bbox)
+			}
+		}
+	}
+}
+
+fn geometry_coordinates(geometry: Geometry) -> Vec<[f64; 2]> {
+	match geometry.into_multi() {
+		Geometry::MultiPoint(g) => g.0,
+		Geometry::MultiLineString(g) => g.0.concat(),
+		Geometry::MultiPolygon(g) => g.0.into_iter().flatten().flatten().collect(),
+		_ => unreachable!("into_multi returns only multi geometries"),
+	}
+}
+
+/// Coverages for ranges of zoom levels. Zoom levels without a range are not covered.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelCoverage {
+	ranges: Vec<(RangeInclusive<u8>, Coverage)>,
+}
+
+impl LevelCoverage {
+	/// Parses a comma separated list of `zoom_range:coverage` items, e.g. `0-5:world,6-14:germany.geojson`.
+	///
+	/// A zoom range is a single zoom level (`5`), a closed range (`0-8`) or an open range (`13-` or `-4`).
+	/// A coverage is `world`, a bounding box like `5.8,47.2,15.1,55.1` or a GeoJSON file. If the text ends with
+	/// `.json`, it is read as a JSON file instead, see [`LevelCoverage::from_json`].
+	pub fn parse_str(text: &str) -> Result<LevelCoverage> {
+		let text = text.trim();
+		if text.to_lowercase().ends_with(".json") && !text.contains(':') {
+			return LevelCoverage::from_json_file(Path::new(text));
+		}
+
+		// the values of a bbox are separated by commas too, so they are joined with the item before
+		let mut items: Vec<String> = Vec::new();
+		for part in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+			match items.last_mut() {
+				Some(item) if !part.contains(':') => {
+					item.push(',');
+					item.push_str(part);
+				}
+				_ => items.push(part.to_string()),
+			}
+		}
+
+		let mut ranges = Vec::new();
+		for item in items {
+			let Some((zooms, coverage)) = item.split_once(':') else {
+				bail!("level coverage {item:?} must have the form \"zoom_range:coverage\"");
+			};
+			ranges.push((
+				parse_zoom_range(zooms.trim())?,
+				Coverage::parse_str(coverage, Path::new(""))?,
+			));
+		}
+		ensure!(!ranges.is_empty(), "level coverage is empty");
+		Ok(LevelCoverage { ranges })
+	}
+
+	fn from_json_file(path: &Path) -> Result<LevelCoverage> {
+		let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+		let object = JsonObject::parse_str(&text).with_context(|| format!("Failed to parse {path:?}"))?;
+		LevelCoverage::from_json(&object, path.parent().unwrap_or(Path::new("")))
+	}
+
+	/// Reads a JSON object that maps zoom ranges to coverages, e.g.
+	/// `{"0-5": "world", "6-14": "germany.geojson", "15": [13.3, 52.4, 13.5, 52.6]}`.
+	/// Relative GeoJSON filenames are resolved against `base_dir`.
+	pub fn from_json(object: &JsonObject, base_dir: &Path) -> Result<LevelCoverage> {
+		let mut ranges = Vec::new();
+		for (zooms, value) in object.iter() {
+			let coverage = match value {
+				JsonValue::String(text) => Coverage::parse_str(text, base_dir)?,
+				JsonValue::Array(array) => Coverage::from_vec(array.as_number_vec()?)?,
+				_ => bail!("coverage of zoom range {zooms:?} must be a string or a bbox array"),
+			};
+			ranges.push((parse_zoom_range(zooms.trim())?, coverage));
+		}
+		ensure!(!ranges.is_empty(), "level coverage is empty");
+		Ok(LevelCoverage { ranges })
+	}
+
+	/// Adds a bounding box for a zoom range, e.g. a whole country up to zoom level 10 and only a city above.
+	pub fn add_bbox(&mut self, zooms: RangeInclusive<u8>, bbox: GeoBBox) -> Result<()> {
+		ensure!(!zooms.is_empty(), "zoom range {zooms:?} is empty");
+		bbox.check()?;
+		self.ranges.push((zooms, Coverage::BBox(bbox)));
+		Ok(())
+	}
+
+	/// Returns the zoom ranges and their coverages.
+	pub fn iter(&self) -> impl Iterator<Item = &(RangeInclusive<u8>, Coverage)> {
+		self.ranges.iter()
+	}
+
+	/// Compiles the coverages into a pyramid. Overlapping zoom ranges cover the union of their areas.
+	pub fn get_bbox_pyramid(&self) -> Result<TileBBoxPyramid> {
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		for (zooms, coverage) in self.ranges.iter() {
+			for level in zooms.clone() {
+				pyramid.include_bbox(&coverage.get_level_bbox(level)?);
+			}
+		}
+		Ok(pyramid)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use std::fs;
+
+	const GEOJSON: &str = r#"{"type":"FeatureCollection","features":[
+		{"type":"Feature","properties":{},"geometry":{"type":"Polygon","coordinates":[[[13.1,52.3],[13.8,52.3],[13.5,52.7],[13.1,52.3]]]}},
+		{"type":"Feature","properties":{},"geometry":{"type":"Point","coordinates":[13.0,52.5]}}
+	]}"#;
+
+	#[test]
+	fn parse_str() -> Result<()> {
+		let coverage = LevelCoverage::parse_str("0-2:world, 3-4:13.1,52.3,13.8,52.7,6-:-1 -2 1 2")?;
+		assert_eq!(
+			coverage.ranges,
+			vec![
+				(0..=2, Coverage::World),
+				(3..=4, Coverage::BBox(GeoBBox(13.1, 52.3, 13.8, 52.7))),
+				(6..=31, Coverage::BBox(GeoBBox(-1.0, -2.0, 1.0, 2.0))),
+			]
+		);
+
+		let pyramid = LevelCoverage::parse_str("0-2:world,4:13.1,52.3,13.8,52.7")?.get_bbox_pyramid()?;
+		assert_eq!(
+			pyramid.to_string(),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4), 2: [0,0,3,3] (16), 4: [8,5,8,5] (1)]"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn errors() {
+		let error = |text: &str| LevelCoverage::parse_str(text).unwrap_err().to_string();
+		assert_eq!(
+			error("world"),
+			"level coverage \"world\" must have the form \"zoom_range:coverage\""
+		);
+		assert_eq!(error("0-5:1,2,3"), "a bbox must contain exactly 4 numbers, but has 3");
+		assert_eq!(error("0-5:1,2,x,4"), "bbox value \"x\" is not a number");
+		assert_eq!(error("5-2:world"), "zoom range \"5-2\" is empty");
+		assert_eq!(error(""), "level coverage is empty");
+	}
+
+	#[test]
+	fn add_bbox() -> Result<()> {
+		let mut coverage = LevelCoverage::default();
+		coverage.add_bbox(0..=3, GeoBBox(-10.0, -10.0, 10.0, 10.0))?;
+		coverage.add_bbox(4..=4, GeoBBox(13.1, 52.3, 13.8, 52.7))?;
+		assert_eq!(
+			coverage.get_bbox_pyramid()?.to_string(),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4), 2: [1,1,2,2] (4), 3: [3,3,4,4] (4), 4: [8,5,8,5] (1)]"
+		);
+		assert!(coverage
+			.add_bbox(RangeInclusive::new(5, 4), GeoBBox(0.0, 0.0, 1.0, 1.0))
+			.is_err());
+		assert!(coverage.add_bbox(5..=6, GeoBBox(0.0, 0.0, 1.0, 100.0)).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn antimeridian() -> Result<()> {
+		let pyramid = LevelCoverage::parse_str("3:177,-19,-178,-16")?.get_bbox_pyramid()?;
+		assert_eq!(pyramid.to_string(), "[3: [0,4,7,4] (8)]");
+		Ok(())
+	}
+
+	#[test]
+	fn geojson_and_json_file() -> Result<()> {
+		let dir = TempDir::new()?;
+		fs::write(dir.path().join("berlin.geojson"), GEOJSON)?;
+		fs::write(
+			dir.path().join("levels.json"),
+			r#"{"0-1": "world", "10": "berlin.geojson", "12": [13.3, 52.4, 13.4, 52.5]}"#,
+		)?;
+
+		let coverage = LevelCoverage::parse_str(dir.path().join("levels.json").to_str().unwrap())?;
+		assert_eq!(
+			coverage.ranges[1],
+			(10..=10, Coverage::BBox(GeoBBox(13.0, 52.3, 13.8, 52.7)))
+		);
+		assert_eq!(
+			coverage.get_bbox_pyramid()?.to_string(),
+			"[0: [0,0,0,0] (1), 1: [0,0,1,1] (4), 10: [548,334,551,336] (12), 12: [2199,1343,2200,1345] (6)]"
+		);
+
+		let path = dir.path().join("berlin.geojson");
+		let coverage = LevelCoverage::parse_str(&format!("10:{}", path.to_str().unwrap()))?;
+		assert_eq!(coverage.ranges[0].1, Coverage::BBox(GeoBBox(13.0, 52.3, 13.8, 52.7)));
+		Ok(())
+	}
+}
@@ -10,4 +10,4 @@ mod reader;
 mod writer;
 
 pub use reader::MBTilesReader;
-pub use writer::MBTilesWriter;
+pub use writer::{MBTilesWriter, MBTilesWriterOptions};
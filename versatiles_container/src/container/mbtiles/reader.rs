@@ -50,7 +50,7 @@ use versatiles_core::{
 	progress::get_progress_bar,
 	tilejson::TileJSON,
 	types::{TileBBoxPyramid, TileCompression::*, TileFormat::*, *},
-	utils::TransformCoord,
+	utils::{get_read_chunk_size, TransformCoord},
 };
 
 /// A struct that provides functionality to read tile data from an MBTiles SQLite database.
@@ -273,6 +273,59 @@ impl MBTilesReader {
 
 		Ok(bbox_pyramid)
 	}
+
+	/// Returns a stream of the tiles inside `bbox`, that reads chunks of `chunk_size`x`chunk_size` tiles
+	/// one after another, while the stream is consumed.
+	async fn read_bbox_chunks(&self, bbox: TileBBox, chunk_size: u32) -> TileStream<'_> {
+		trace!("read bbox {bbox:?} in chunks of {chunk_size}x{chunk_size} tiles");
+		let chunks: Vec<TileBBox> = bbox.iter_bbox_grid(chunk_size).collect();
+		TileStream::from_stream_iter(
+			chunks
+				.into_iter()
+				.map(move |chunk| async move { TileStream::from_vec(self.read_bbox_tiles(&chunk)) }),
+		)
+		.await
+	}
+
+	/// Reads all tiles inside `bbox` at once.
+	fn read_bbox_tiles(&self, bbox: &TileBBox) -> Vec<(TileCoord3, Blob)> {
+		let max_index = bbox.max;
+
+		let conn = self.pool.get().unwrap();
+		let mut stmt = conn
+			 .prepare(
+					"SELECT tile_column, tile_row, zoom_level, tile_data FROM tiles WHERE tile_column >= ? AND tile_column <= ? AND tile_row >= ? AND tile_row <= ? AND zoom_level = ?",
+			 )
+			 .unwrap();
+
+		let vec: Vec<(TileCoord3, Blob)> = stmt
+			.query_map(
+				[
+					bbox.x_min,
+					bbox.x_max,
+					max_index - bbox.y_max,
+					max_index - bbox.y_min,
+					bbox.level as u32,
+				],
+				move |row| {
+					let coord = TileCoord3::new(
+						row.get::<_, u32>(0)?,
+						max_index - row.get::<_, u32>(1)?,
+						row.get::<_, u8>(2)?,
+					)
+					.unwrap();
+					let blob = Blob::from(row.get::<_, Vec<u8>>(3)?);
+					Ok((coord, blob))
+				},
+			)
+			.unwrap()
+			.filter_map(|r| r.ok())
+			.collect();
+
+		trace!("got {} tiles", vec.len());
+
+		vec
+	}
 }
 
 #[async_trait]
@@ -327,8 +380,35 @@ impl TilesReaderTrait for MBTilesReader {
 		}
 	}
 
+	/// Counts the tiles inside `bbox` and sums up their sizes in the database, without reading the tiles.
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		if bbox.is_empty() {
+			return Ok(Some((0, 0)));
+		}
+
+		let max_index = bbox.max;
+		let conn = self.pool.get()?;
+		let mut stmt = conn.prepare(
+			"SELECT COUNT(*), IFNULL(SUM(LENGTH(tile_data)), 0) FROM tiles WHERE tile_column >= ? AND tile_column <= ? AND tile_row >= ? AND tile_row <= ? AND zoom_level = ?",
+		)?;
+		let (count, bytes) = stmt.query_row(
+			[
+				bbox.x_min,
+				bbox.x_max,
+				max_index - bbox.y_max,
+				max_index - bbox.y_min,
+				bbox.level as u32,
+			],
+			|row| Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?)),
+		)?;
+		Ok(Some((count, bytes)))
+	}
+
 	/// Returns a stream of tile data for the specified bounding box.
 	///
+	/// If the tiles don't fit into the memory budget (see `versatiles_core::utils::set_max_memory`),
+	/// the bounding box is read in chunks, one after another, while the stream is consumed.
+	///
 	/// # Arguments
 	/// * `bbox` - The bounding box of the tiles.
 	///
@@ -341,47 +421,15 @@ impl TilesReaderTrait for MBTilesReader {
 			return TileStream::new_empty();
 		}
 
-		let max_index = bbox.max;
-
-		trace!("corrected bbox {bbox:?}");
-
-		let conn = self.pool.get().unwrap();
-		let mut stmt = conn
-			 .prepare(
-					"SELECT tile_column, tile_row, zoom_level, tile_data FROM tiles WHERE tile_column >= ? AND tile_column <= ? AND tile_row >= ? AND tile_row <= ? AND zoom_level = ?",
-			 )
-			 .unwrap();
-
-		let vec: Vec<(TileCoord3, Blob)> = stmt
-			.query_map(
-				[
-					bbox.x_min,
-					bbox.x_max,
-					max_index - bbox.y_max,
-					max_index - bbox.y_min,
-					bbox.level as u32,
-				],
-				move |row| {
-					let coord = TileCoord3::new(
-						row.get::<_, u32>(0)?,
-						max_index - row.get::<_, u32>(1)?,
-						row.get::<_, u8>(2)?,
-					)
-					.unwrap();
-					let blob = Blob::from(row.get::<_, Vec<u8>>(3)?);
-					Ok((coord, blob))
-				},
-			)
-			.unwrap()
-			.filter_map(|r| match r {
-				Ok(ok) => Some(ok),
-				Err(_) => None,
-			})
-			.collect();
-
-		trace!("got {} tiles", vec.len());
+		let chunk_size = match self.count_bbox_tiles(&bbox).await {
+			Ok(Some((count, bytes))) => get_read_chunk_size(count, bytes),
+			_ => None,
+		};
+		let Some(chunk_size) = chunk_size else {
+			return TileStream::from_vec(self.read_bbox_tiles(&bbox));
+		};
 
-		TileStream::from_vec(vec)
+		self.read_bbox_chunks(bbox, chunk_size).await
 	}
 
 	/// Returns the name of the MBTiles database.
@@ -441,6 +489,24 @@ pub mod tests {
 		Ok(())
 	}
 
+	#[tokio::test]
+	async fn read_bbox_in_chunks() -> Result<()> {
+		let reader = MBTilesReader::open_path(&PATH)?;
+		let bbox = TileBBox::new(14, 8787, 5361, 8818, 5387)?;
+
+		let mut tiles1 = reader.get_bbox_tile_stream(bbox.clone()).await.collect().await;
+		let mut tiles2 = reader.read_bbox_chunks(bbox.clone(), 5).await.collect().await;
+		tiles1.sort_by_key(|(c, _)| c.get_sort_index());
+		tiles2.sort_by_key(|(c, _)| c.get_sort_index());
+		assert_eq!(tiles1.len(), 610);
+		assert_eq!(tiles1, tiles2);
+
+		let (count, bytes) = reader.count_bbox_tiles(&bbox).await?.unwrap();
+		assert_eq!(count, 610);
+		assert_eq!(bytes, tiles1.iter().map(|(_, blob)| blob.len()).sum::<u64>());
+		Ok(())
+	}
+
 	// Test tile fetching
 	#[cfg(feature = "cli")]
 	#[tokio::test]
@@ -5,6 +5,8 @@
 //! ## Features
 //! - Supports writing metadata and tile data in multiple formats and compressions.
 //! - Ensures the necessary tables and indices are created in the SQLite database.
+//! - Inserts tiles in batched transactions with prepared statements, optionally from several worker threads
+//!   and with a write-ahead log, see `MBTilesWriterOptions`.
 //! - Provides progress feedback during the write process.
 //!
 //! ## Usage
@@ -29,13 +31,46 @@
 //! This module includes comprehensive tests to ensure the correct functionality of writing metadata, handling different file formats, and verifying the database structure.
 
 use crate::TilesWriterTrait;
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use async_trait::async_trait;
 use r2d2::Pool;
-use r2d2_sqlite::{rusqlite::params, SqliteConnectionManager};
-use std::{fs::remove_file, path::Path};
+use r2d2_sqlite::{
+	rusqlite::{params, Connection},
+	SqliteConnectionManager,
+};
+use std::{
+	fs::remove_file,
+	path::Path,
+	sync::{mpsc::sync_channel, Arc, Mutex},
+	thread,
+	time::Duration,
+};
 use versatiles_core::{io::DataWriterTrait, json::JsonObject, progress::get_progress_bar, types::*};
 
+/// Options for writing an MBTiles file.
+#[derive(Clone, Debug)]
+pub struct MBTilesWriterOptions {
+	/// Number of tiles inserted per transaction.
+	pub batch_size: usize,
+	/// Number of threads inserting batches, each with its own connection.
+	/// SQLite allows only one writing transaction at a time, so more workers only help if binding and
+	/// inserting the tiles is slower than reading them. More than one worker requires `wal`.
+	pub workers: usize,
+	/// Uses a write-ahead log while writing, so that the workers don't block each other while preparing batches.
+	/// When finished, the file is switched back to a rollback journal, so no "-wal" file remains.
+	pub wal: bool,
+}
+
+impl Default for MBTilesWriterOptions {
+	fn default() -> Self {
+		Self {
+			batch_size: 10_000,
+			workers: 1,
+			wal: true,
+		}
+	}
+}
+
 /// A writer for creating and populating MBTiles databases.
 pub struct MBTilesWriter {
 	pool: Pool<SqliteConnectionManager>,
@@ -46,17 +81,37 @@ impl MBTilesWriter {
 	///
 	/// # Arguments
 	/// * `path` - The path to the MBTiles file.
+	/// * `options` - The options for writing.
 	///
 	/// # Errors
 	/// Returns an error if the SQLite connection cannot be established or if the necessary tables cannot be created.
-	fn new(path: &Path) -> Result<Self> {
+	fn new(path: &Path, options: &MBTilesWriterOptions) -> Result<Self> {
+		ensure!(options.batch_size > 0, "batch size must be greater than 0");
+		ensure!(options.workers > 0, "number of workers must be greater than 0");
+		ensure!(
+			options.workers == 1 || options.wal,
+			"multiple workers require a write-ahead log"
+		);
+
 		if path.exists() {
 			remove_file(path)?;
 		}
-		let manager = SqliteConnectionManager::file(path);
-		let pool = Pool::builder().max_size(10).build(manager)?;
+		// The file is new, so if writing is interrupted, it's incomplete anyway. No need to sync every transaction.
+		let manager = SqliteConnectionManager::file(path).with_init(|c| {
+			c.busy_timeout(Duration::from_secs(600))?;
+			c.execute_batch("PRAGMA synchronous = OFF;")
+		});
+		let pool = Pool::builder().max_size(options.workers as u32 + 1).build(manager)?;
 
-		pool.get()?.execute_batch(
+		let conn = pool.get()?;
+		if options.wal {
+			let mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+			ensure!(
+				mode == "wal",
+				"failed to enable write-ahead log, journal mode is \"{mode}\""
+			);
+		}
+		conn.execute_batch(
 			"CREATE TABLE metadata (name TEXT, value TEXT, UNIQUE (name));
 			CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB, UNIQUE (zoom_level, tile_column, tile_row));
 			CREATE UNIQUE INDEX tile_index on tiles (zoom_level, tile_column, tile_row);",
@@ -66,21 +121,23 @@ impl MBTilesWriter {
 	}
 
 	/// Adds multiple tiles to the MBTiles file within a single transaction.
-	///s
+	///
 	/// # Arguments
+	/// * `conn` - The connection of the worker.
 	/// * `tiles` - A vector of tuples containing tile coordinates and tile data.
 	///
 	/// # Errors
 	/// Returns an error if the transaction fails.
-	fn add_tiles(&mut self, tiles: &Vec<(TileCoord3, Blob)>) -> Result<()> {
-		let mut conn = self.pool.get()?;
+	fn add_tiles(conn: &mut Connection, tiles: &Vec<(TileCoord3, Blob)>) -> Result<()> {
 		let transaction = conn.transaction()?;
-		for (c, blob) in tiles {
-			let max_index = 2u32.pow(c.z as u32) - 1;
-			transaction.execute(
+		{
+			let mut statement = transaction.prepare_cached(
 				"INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
-				params![c.z, c.x, max_index - c.y, blob.as_slice()],
 			)?;
+			for (c, blob) in tiles {
+				let max_index = 2u32.pow(c.z as u32) - 1;
+				statement.execute(params![c.z, c.x, max_index - c.y, blob.as_slice()])?;
+			}
 		}
 		transaction.commit()?;
 		Ok(())
@@ -101,23 +158,25 @@ impl MBTilesWriter {
 		)?;
 		Ok(())
 	}
-}
 
-#[async_trait]
-impl TilesWriterTrait for MBTilesWriter {
-	/// Writes tiles and metadata to the MBTiles file.
+	/// Writes tiles and metadata to the MBTiles file, using the given options.
 	///
 	/// # Arguments
 	/// * `reader` - The reader from which to fetch tiles and metadata.
 	/// * `path` - The path to the MBTiles file.
+	/// * `options` - Batch size, number of workers and journal mode.
 	///
 	/// # Errors
 	/// Returns an error if the file format or compression is not supported, or if there are issues with writing to the SQLite database.
-	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+	pub async fn write_to_path_with_options(
+		reader: &mut dyn TilesReaderTrait,
+		path: &Path,
+		options: &MBTilesWriterOptions,
+	) -> Result<()> {
 		use TileCompression::*;
 		use TileFormat::*;
 
-		let mut writer = MBTilesWriter::new(path)?;
+		let writer = MBTilesWriter::new(path, options)?;
 
 		let parameters = reader.get_parameters().clone();
 
@@ -162,21 +221,79 @@ impl TilesWriterTrait for MBTilesWriter {
 
 		let mut progress = get_progress_bar("converting tiles", pyramid.count_tiles());
 
+		// The workers share one queue of batches. It is bounded, so reading waits if writing is slower.
+		let (sender, receiver) = sync_channel::<Vec<(TileCoord3, Blob)>>(options.workers * 2);
+		let receiver = Arc::new(Mutex::new(receiver));
+		let workers = (0..options.workers)
+			.map(|_| {
+				let mut conn = writer.pool.get()?;
+				let receiver = receiver.clone();
+				Ok(thread::spawn(move || -> Result<()> {
+					loop {
+						let Ok(tiles) = receiver.lock().unwrap().recv() else {
+							return Ok(());
+						};
+						MBTilesWriter::add_tiles(&mut conn, &tiles)?;
+					}
+				}))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		let mut disconnected = false;
 		for bbox in pyramid.iter_levels() {
 			let stream = reader.get_bbox_tile_stream(bbox.clone()).await;
 
 			stream
-				.for_each_buffered(2000, |v| {
-					writer.add_tiles(&v).unwrap();
-					progress.inc(v.len() as u64)
+				.for_each_buffered(options.batch_size, |tiles| {
+					let count = tiles.len() as u64;
+					if !disconnected && sender.send(tiles).is_ok() {
+						progress.inc(count)
+					} else {
+						disconnected = true;
+					}
 				})
 				.await;
+
+			if disconnected {
+				break;
+			}
+		}
+		drop(sender);
+
+		for worker in workers {
+			worker.join().map_err(|_| anyhow!("MBTiles worker panicked"))??;
 		}
+		ensure!(!disconnected, "all MBTiles workers stopped unexpectedly");
 
 		progress.finish();
 
+		drop(writer);
+		if options.wal {
+			// Merge the write-ahead log into the database, so that the file can be used on its own.
+			let mode: String = Connection::open(path)?.query_row("PRAGMA journal_mode = DELETE", [], |row| row.get(0))?;
+			ensure!(
+				mode == "delete",
+				"failed to disable write-ahead log, journal mode is \"{mode}\""
+			);
+		}
+
 		Ok(())
 	}
+}
+
+#[async_trait]
+impl TilesWriterTrait for MBTilesWriter {
+	/// Writes tiles and metadata to the MBTiles file.
+	///
+	/// # Arguments
+	/// * `reader` - The reader from which to fetch tiles and metadata.
+	/// * `path` - The path to the MBTiles file.
+	///
+	/// # Errors
+	/// Returns an error if the file format or compression is not supported, or if there are issues with writing to the SQLite database.
+	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+		MBTilesWriter::write_to_path_with_options(reader, path, &MBTilesWriterOptions::default()).await
+	}
 
 	/// Not implemented: Writes tiles and metadata to a generic data writer.
 	async fn write_to_writer(_reader: &mut dyn TilesReaderTrait, _writer: &mut dyn DataWriterTrait) -> Result<()> {
@@ -192,11 +309,11 @@ mod tests {
 
 	#[tokio::test]
 	async fn read_write() -> Result<()> {
-		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters {
-			bbox_pyramid: TileBBoxPyramid::new_full(5),
-			tile_compression: TileCompression::Gzip,
-			tile_format: TileFormat::PBF,
-		})?;
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(5),
+		))?;
 
 		let filename = NamedTempFile::new("temp.mbtiles")?;
 		MBTilesWriter::write_to_path(&mut mock_reader, &filename).await?;
@@ -207,4 +324,67 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn options() -> Result<()> {
+		let parameters = TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(6),
+		);
+		for (batch_size, workers, wal) in [(1, 1, false), (7, 1, false), (100, 4, true), (100_000, 2, true)] {
+			let options = MBTilesWriterOptions {
+				batch_size,
+				workers,
+				wal,
+			};
+			let filename = NamedTempFile::new("temp.mbtiles")?;
+			let mut mock_reader = MockTilesReader::new_mock(parameters.clone())?;
+			MBTilesWriter::write_to_path_with_options(&mut mock_reader, &filename, &options).await?;
+
+			// the write-ahead log is merged into the file
+			let wal_path = filename.path().with_extension("mbtiles-wal");
+			assert!(!wal_path.exists());
+
+			let reader = MBTilesReader::open_path(&filename)?;
+			assert_eq!(reader.get_parameters().bbox_pyramid.count_tiles(), 5461);
+			let count: u64 =
+				Connection::open(filename.path())?.query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0))?;
+			assert_eq!(count, 5461);
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn invalid_options() -> Result<()> {
+		let filename = NamedTempFile::new("temp.mbtiles")?;
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(2),
+		))?;
+		for (options, message) in [
+			(
+				MBTilesWriterOptions {
+					workers: 2,
+					wal: false,
+					..Default::default()
+				},
+				"multiple workers require a write-ahead log",
+			),
+			(
+				MBTilesWriterOptions {
+					batch_size: 0,
+					..Default::default()
+				},
+				"batch size must be greater than 0",
+			),
+		] {
+			let error = MBTilesWriter::write_to_path_with_options(&mut mock_reader, &filename, &options)
+				.await
+				.unwrap_err();
+			assert_eq!(error.to_string(), message);
+		}
+		Ok(())
+	}
 }
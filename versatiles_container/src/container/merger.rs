@@ -0,0 +1,356 @@
+//! Merges multiple tile containers into one, e.g. to stitch regional extracts together.
+//!
+//! The bbox pyramids of all inputs are combined. If several inputs contain the same tile,
+//! the tile of the preferred input is used. All inputs must have the same tile format.
+//! Tiles with a different compression are recompressed to the compression of the first input.
+//!
+//! ```no_run
+//! use versatiles_container::{get_reader, write_to_filename, TilesMergeParameters, TilesMergeReader};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let readers = vec![get_reader("north.versatiles").await?, get_reader("south.versatiles").await?];
+//! let mut reader = TilesMergeReader::new(readers, TilesMergeParameters::default())?;
+//! write_to_filename(&mut reader, "merged.versatiles").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::tile_converter::TileConverter;
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use versatiles_core::{tilejson::TileJSON, types::*};
+
+/// Which input wins if several inputs contain the same tile.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MergePrefer {
+	/// Uses the tile of the first input containing it.
+	#[default]
+	First,
+	/// Uses the tile of the last input containing it.
+	Last,
+}
+
+/// Parameters for merging tile containers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TilesMergeParameters {
+	pub prefer: MergePrefer,
+	/// Fails if the inputs have different tile compressions, instead of recompressing tiles.
+	pub require_same_format: bool,
+}
+
+/// A reader that combines the tiles of multiple readers.
+#[derive(Debug)]
+pub struct TilesMergeReader<'a> {
+	/// readers sorted by priority, each with a recompressor to the output compression
+	readers: Vec<(Box<dyn TilesReaderTrait + 'a>, TileConverter)>,
+	parameters: TilesReaderParameters,
+	tilejson: TileJSON,
+	name: String,
+}
+
+impl<'a> TilesMergeReader<'a> {
+	/// Creates a new reader merging the given readers.
+	///
+	/// # Errors
+	///
+	/// Returns an error if there are no readers, if the readers have different tile formats or if
+	/// `require_same_format` is set and the readers have different tile compressions.
+	pub fn new(readers: Vec<Box<dyn TilesReaderTrait + 'a>>, mp: TilesMergeParameters) -> Result<TilesMergeReader<'a>> {
+		ensure!(!readers.is_empty(), "at least one input is needed to merge");
+
+		let name = format!(
+			"merge({})",
+			readers
+				.iter()
+				.map(|r| r.get_source_name())
+				.collect::<Vec<_>>()
+				.join(",")
+		);
+
+		let first = readers[0].get_parameters();
+		let tile_format = first.tile_format;
+		let tile_compression = first.tile_compression;
+		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
+
+		for reader in readers.iter() {
+			let rp = reader.get_parameters();
+			ensure!(
+				rp.tile_format == tile_format,
+				"tile format of {:?} is {}, but the first input has {}",
+				reader.get_source_name(),
+				rp.tile_format.as_str(),
+				tile_format.as_str()
+			);
+			ensure!(
+				!mp.require_same_format || rp.tile_compression == tile_compression,
+				"tile compression of {:?} is {}, but the first input has {}",
+				reader.get_source_name(),
+				rp.tile_compression.as_str(),
+				tile_compression.as_str()
+			);
+			bbox_pyramid.include_bbox_pyramid(&rp.bbox_pyramid);
+		}
+
+		let mut readers = readers
+			.into_iter()
+			.map(|reader| {
+				let recompressor = TileConverter::new_tile_recompressor(
+					&reader.get_parameters().tile_compression,
+					&tile_compression,
+					false,
+				)?;
+				Ok((reader, recompressor))
+			})
+			.collect::<Result<Vec<_>>>()?;
+		if mp.prefer == MergePrefer::Last {
+			readers.reverse();
+		}
+
+		// merge the metadata starting with the lowest priority, so the preferred input overwrites the others
+		let mut tilejson = TileJSON::default();
+		for (reader, _) in readers.iter().rev() {
+			tilejson.merge(reader.get_tilejson())?;
+		}
+
+		Ok(TilesMergeReader {
+			readers,
+			parameters: TilesReaderParameters::new(tile_format, tile_compression, bbox_pyramid),
+			tilejson,
+			name,
+		})
+	}
+}
+
+#[async_trait]
+impl TilesReaderTrait for TilesMergeReader<'_> {
+	fn get_source_name(&self) -> &str {
+		&self.name
+	}
+
+	fn get_container_name(&self) -> &str {
+		"merge"
+	}
+
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn override_compression(&mut self, tile_compression: TileCompression) {
+		self.parameters.tile_compression = tile_compression;
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	fn set_read_hints(&mut self, hints: &ReadHints) {
+		for (reader, _) in self.readers.iter_mut() {
+			reader.set_read_hints(hints);
+		}
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		for (reader, recompressor) in self.readers.iter() {
+			if !reader.get_parameters().bbox_pyramid.contains_coord(coord) {
+				continue;
+			}
+			if let Some(blob) = reader.get_tile_data(coord).await? {
+				return Ok(Some(recompressor.process_blob(blob)?));
+			}
+		}
+		Ok(None)
+	}
+
+	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let bboxes: Vec<TileBBox> = bbox.iter_bbox_grid(64).collect();
+
+		TileStream::from_stream_iter(bboxes.into_iter().map(move |bbox| async move {
+			let mut tiles: Vec<Option<(TileCoord3, Blob)>> = vec![None; bbox.count_tiles() as usize];
+
+			for (reader, recompressor) in self.readers.iter() {
+				let mut reader_bbox = bbox.clone();
+				reader_bbox
+					.intersect_pyramid(&reader.get_parameters().bbox_pyramid)
+					.unwrap();
+				if reader_bbox.is_empty() {
+					continue;
+				}
+
+				reader
+					.get_bbox_tile_stream(reader_bbox)
+					.await
+					.for_each_sync(|(coord, blob)| {
+						let index = bbox.get_tile_index3(&coord).unwrap();
+						if tiles[index].is_none() {
+							tiles[index] = Some((coord, recompressor.process_blob(blob).unwrap()));
+						}
+					})
+					.await;
+			}
+
+			TileStream::from_vec(tiles.into_iter().flatten().collect())
+		}))
+		.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MockTilesReader;
+	use std::collections::HashMap;
+	use versatiles_core::utils::{compress, decompress};
+
+	/// A reader with a fixed set of tiles, each containing the name of the reader.
+	#[derive(Debug)]
+	struct NamedReader {
+		name: String,
+		parameters: TilesReaderParameters,
+		tilejson: TileJSON,
+		tiles: HashMap<TileCoord3, Blob>,
+	}
+
+	impl NamedReader {
+		fn new_boxed(name: &str, bbox: [u32; 4], compression: TileCompression) -> Box<dyn TilesReaderTrait> {
+			let bbox = TileBBox::new(3, bbox[0], bbox[1], bbox[2], bbox[3]).unwrap();
+			let tiles = bbox
+				.iter_coords()
+				.map(|coord| (coord, compress(Blob::from(name), &compression).unwrap()))
+				.collect();
+			let mut pyramid = TileBBoxPyramid::new_empty();
+			pyramid.include_bbox(&bbox);
+			let mut tilejson = TileJSON::default();
+			tilejson.set_string("name", name).unwrap();
+			Box::new(NamedReader {
+				name: name.to_string(),
+				parameters: TilesReaderParameters::new(TileFormat::JSON, compression, pyramid),
+				tilejson,
+				tiles,
+			})
+		}
+	}
+
+	#[async_trait]
+	impl TilesReaderTrait for NamedReader {
+		fn get_source_name(&self) -> &str {
+			&self.name
+		}
+		fn get_container_name(&self) -> &str {
+			"named"
+		}
+		fn get_parameters(&self) -> &TilesReaderParameters {
+			&self.parameters
+		}
+		fn override_compression(&mut self, tile_compression: TileCompression) {
+			self.parameters.tile_compression = tile_compression;
+		}
+		fn get_tilejson(&self) -> &TileJSON {
+			&self.tilejson
+		}
+		async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+			Ok(self.tiles.get(coord).cloned())
+		}
+	}
+
+	fn get_merger(prefer: MergePrefer) -> TilesMergeReader<'static> {
+		let readers = vec![
+			NamedReader::new_boxed("a", [0, 0, 2, 1], TileCompression::Gzip),
+			NamedReader::new_boxed("b", [1, 1, 3, 2], TileCompression::Brotli),
+		];
+		let mp = TilesMergeParameters {
+			prefer,
+			require_same_format: false,
+		};
+		TilesMergeReader::new(readers, mp).unwrap()
+	}
+
+	async fn get_tiles(merger: &TilesMergeReader<'_>) -> String {
+		let bbox = merger.get_parameters().bbox_pyramid.get_level_bbox(3).clone();
+		let mut tiles = merger.get_bbox_tile_stream(bbox).await.collect().await;
+		tiles.sort_by_key(|(coord, _)| (coord.y, coord.x));
+		tiles
+			.into_iter()
+			.map(|(coord, blob)| {
+				let name = decompress(blob, &TileCompression::Gzip).unwrap();
+				format!("{}{}{}", coord.x, coord.y, name.as_str())
+			})
+			.collect::<Vec<_>>()
+			.join(" ")
+	}
+
+	#[tokio::test]
+	async fn merges_tiles() -> Result<()> {
+		let merger = get_merger(MergePrefer::First);
+		let parameters = merger.get_parameters();
+		assert_eq!(parameters.bbox_pyramid.to_string(), "[3: [0,0,3,2] (12)]");
+		assert_eq!(parameters.tile_compression, TileCompression::Gzip);
+		assert_eq!(merger.get_source_name(), "merge(a,b)");
+		assert_eq!(merger.get_tilejson().get_str("name"), Some("a"));
+		assert_eq!(get_tiles(&merger).await, "00a 10a 20a 01a 11a 21a 31b 12b 22b 32b");
+
+		let merger = get_merger(MergePrefer::Last);
+		assert_eq!(merger.get_tilejson().get_str("name"), Some("b"));
+		assert_eq!(get_tiles(&merger).await, "00a 10a 20a 01a 11b 21b 31b 12b 22b 32b");
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn get_tile_data() -> Result<()> {
+		async fn tile(merger: &TilesMergeReader<'_>, x: u32, y: u32) -> Option<String> {
+			let coord = TileCoord3::new(x, y, 3).unwrap();
+			let blob = merger.get_tile_data(&coord).await.unwrap()?;
+			Some(decompress(blob, &TileCompression::Gzip).unwrap().as_str().to_string())
+		}
+
+		let merger = get_merger(MergePrefer::First);
+		assert_eq!(tile(&merger, 0, 0).await.as_deref(), Some("a"));
+		assert_eq!(tile(&merger, 1, 1).await.as_deref(), Some("a"));
+		assert_eq!(tile(&merger, 3, 2).await.as_deref(), Some("b"));
+		assert_eq!(tile(&merger, 0, 2).await, None);
+
+		let merger = get_merger(MergePrefer::Last);
+		assert_eq!(tile(&merger, 1, 1).await.as_deref(), Some("b"));
+		Ok(())
+	}
+
+	#[test]
+	fn checks_formats() {
+		let error = |readers: Vec<Box<dyn TilesReaderTrait>>, require_same_format: bool| {
+			let mp = TilesMergeParameters {
+				require_same_format,
+				..Default::default()
+			};
+			TilesMergeReader::new(readers, mp).unwrap_err().to_string()
+		};
+
+		assert_eq!(error(vec![], false), "at least one input is needed to merge");
+
+		let png = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(3),
+		))
+		.unwrap();
+		assert_eq!(
+			error(
+				vec![
+					NamedReader::new_boxed("a", [0, 0, 1, 1], TileCompression::Gzip),
+					png.boxed()
+				],
+				false
+			),
+			"tile format of \"dummy_name\" is png, but the first input has json"
+		);
+
+		assert_eq!(
+			error(
+				vec![
+					NamedReader::new_boxed("a", [0, 0, 1, 1], TileCompression::Gzip),
+					NamedReader::new_boxed("b", [0, 0, 1, 1], TileCompression::Brotli)
+				],
+				true
+			),
+			"tile compression of \"b\" is brotli, but the first input has gzip"
+		);
+	}
+}
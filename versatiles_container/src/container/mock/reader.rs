@@ -5,6 +5,11 @@
 //! ## MockTilesReader
 //! The `MockTilesReader` struct is the main component, which can be initialized with different profiles representing various tile formats and compressions.
 //!
+//! ## MockTilesReaderOptions
+//! Simulates realistic sources: seedable pseudo-random tile content and sizes, missing tiles, failing reads and latency.
+//! Every decision is derived from the seed and the tile coordinate, so repeated reads return the same result.
+//! Outside of this crate the mocks are available with the `test-utils` feature.
+//!
 //! ## Usage
 //! These mocks can be used to simulate tile reading operations in tests, allowing verification of code behavior under controlled conditions.
 //!
@@ -15,15 +20,16 @@
 //!
 //! #[tokio::test]
 //! async fn test_mock_reader() -> Result<()> {
-//!     let mut reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::PNG)?;
+//!     let mut reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
 //!     let tile_data = reader.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?;
 //!     assert!(tile_data.is_some());
 //!     Ok(())
 //! }
 //! ```
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use std::{ops::Range, time::Duration};
 use versatiles_core::{tilejson::TileJSON, types::*, utils::compress};
 
 /// Enum representing different mock profiles for tile data.
@@ -42,10 +48,29 @@ pub const MOCK_BYTES_PBF: &[u8; 54] = include_bytes!("./mock_tiles/mock.pbf");
 pub const MOCK_BYTES_PNG: &[u8; 103] = include_bytes!("./mock_tiles/mock.png");
 pub const MOCK_BYTES_WEBP: &[u8; 44] = include_bytes!("./mock_tiles/mock.webp");
 
+/// Options for simulating realistic sources with [`MockTilesReader::new_mock_with_options`].
+///
+/// All random decisions are derived from the seed and the tile coordinate,
+/// so the same tile always gets the same content, error and latency.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockTilesReaderOptions {
+	/// Seed of the pseudo-random generator. Default: 0
+	pub seed: u64,
+	/// If set, tiles contain random bytes with a length in this range, instead of the fixed mock tile of the format.
+	pub random_size: Option<Range<usize>>,
+	/// Fraction of tiles, from 0 to 1, that don't exist. Default: 0
+	pub missing_rate: f64,
+	/// Fraction of tiles, from 0 to 1, whose reading fails with an error. Default: 0
+	pub error_rate: f64,
+	/// Every read is delayed by a random duration in this range. Default: no delay
+	pub latency: Option<Range<Duration>>,
+}
+
 /// Mock implementation of a `TilesReader`.
 pub struct MockTilesReader {
 	parameters: TilesReaderParameters,
 	tilejson: TileJSON,
+	options: MockTilesReaderOptions,
 }
 
 impl MockTilesReader {
@@ -70,10 +95,56 @@ impl MockTilesReader {
 
 	/// Creates a new mock tiles reader with the specified parameters.
 	pub fn new_mock(parameters: TilesReaderParameters) -> Result<MockTilesReader> {
+		Self::new_mock_with_options(parameters, MockTilesReaderOptions::default())
+	}
+
+	/// Creates a new mock tiles reader with the specified parameters, that simulates a source as described by `options`.
+	pub fn new_mock_with_options(
+		parameters: TilesReaderParameters,
+		options: MockTilesReaderOptions,
+	) -> Result<MockTilesReader> {
 		let mut tilejson = TileJSON::default();
 		tilejson.set_string("type", "dummy")?;
-		Ok(MockTilesReader { parameters, tilejson })
+		Ok(MockTilesReader {
+			parameters,
+			tilejson,
+			options,
+		})
 	}
+
+	/// Pseudo-random number for a tile. `salt` separates the decisions made for the same tile.
+	fn random(&self, coord: &TileCoord3, salt: u64) -> u64 {
+		[coord.z as u64, coord.x as u64, coord.y as u64]
+			.into_iter()
+			.fold(self.options.seed ^ salt, |value, v| splitmix64(value ^ v))
+	}
+
+	/// Pseudo-random number for a tile, from 0 (inclusive) to 1 (exclusive).
+	fn random_fraction(&self, coord: &TileCoord3, salt: u64) -> f64 {
+		(self.random(coord, salt) >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	fn random_bytes(&self, coord: &TileCoord3, size: &Range<usize>) -> Vec<u8> {
+		let mut length = size.start;
+		if size.end > size.start {
+			length += (self.random(coord, 3) % (size.end - size.start) as u64) as usize;
+		}
+		let mut state = self.random(coord, 4);
+		let mut bytes = Vec::with_capacity(length + 8);
+		while bytes.len() < length {
+			state = splitmix64(state);
+			bytes.extend_from_slice(&state.to_le_bytes());
+		}
+		bytes.truncate(length);
+		bytes
+	}
+}
+
+fn splitmix64(mut value: u64) -> u64 {
+	value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	value ^ (value >> 31)
 }
 
 #[async_trait]
@@ -105,15 +176,30 @@ impl TilesReaderTrait for MockTilesReader {
 			return Ok(None);
 		}
 
+		if let Some(latency) = &self.options.latency {
+			let fraction = self.random_fraction(coord, 0);
+			tokio::time::sleep(latency.start + (latency.end.saturating_sub(latency.start)).mul_f64(fraction)).await;
+		}
+		if self.random_fraction(coord, 1) < self.options.error_rate {
+			bail!("simulated error while reading tile {coord:?}");
+		}
+		if self.random_fraction(coord, 2) < self.options.missing_rate {
+			return Ok(None);
+		}
+
 		let format = self.parameters.tile_format;
-		let mut blob = match format {
-			JSON => Blob::from(coord.as_json()),
-			PNG => Blob::from(MOCK_BYTES_PNG.to_vec()),
-			PBF => Blob::from(MOCK_BYTES_PBF.to_vec()),
-			//AVIF => Blob::from(MOCK_BYTES_AVIF.to_vec()),
-			JPG => Blob::from(MOCK_BYTES_JPG.to_vec()),
-			WEBP => Blob::from(MOCK_BYTES_WEBP.to_vec()),
-			_ => panic!("tile format {format:?} is not implemented for MockTileReader"),
+		let mut blob = if let Some(size) = &self.options.random_size {
+			Blob::from(self.random_bytes(coord, size))
+		} else {
+			match format {
+				JSON => Blob::from(coord.as_json()),
+				PNG => Blob::from(MOCK_BYTES_PNG.to_vec()),
+				PBF => Blob::from(MOCK_BYTES_PBF.to_vec()),
+				//AVIF => Blob::from(MOCK_BYTES_AVIF.to_vec()),
+				JPG => Blob::from(MOCK_BYTES_JPG.to_vec()),
+				WEBP => Blob::from(MOCK_BYTES_WEBP.to_vec()),
+				_ => panic!("tile format {format:?} is not implemented for MockTileReader"),
+			}
 		};
 		blob = compress(blob, &self.parameters.tile_compression)?;
 		Ok(Some(blob))
@@ -177,6 +263,85 @@ mod tests {
 		test(MockTilesReaderProfile::Json, Blob::from("{x:23,y:45,z:6}")).await;
 	}
 
+	fn new_reader(options: MockTilesReaderOptions) -> Result<MockTilesReader> {
+		let bbox_pyramid = TileBBoxPyramid::new_full(6);
+		let parameters = TilesReaderParameters::new(TileFormat::PBF, TileCompression::Uncompressed, bbox_pyramid);
+		MockTilesReader::new_mock_with_options(parameters, options)
+	}
+
+	async fn read_level(reader: &MockTilesReader, level: u8) -> Vec<Result<Option<Blob>>> {
+		let mut results = Vec::new();
+		for coord in TileBBox::new_full(level).unwrap().iter_coords() {
+			results.push(reader.get_tile_data(&coord).await);
+		}
+		results
+	}
+
+	#[tokio::test]
+	async fn random_content_is_seedable() -> Result<()> {
+		let options = MockTilesReaderOptions {
+			seed: 42,
+			random_size: Some(100..1000),
+			..Default::default()
+		};
+		let blobs = |options: MockTilesReaderOptions| async move {
+			let reader = new_reader(options).unwrap();
+			read_level(&reader, 4)
+				.await
+				.into_iter()
+				.map(|r| r.unwrap().unwrap())
+				.collect::<Vec<Blob>>()
+		};
+
+		let blobs1 = blobs(options.clone()).await;
+		assert!(blobs1.iter().all(|b| (100..1000).contains(&b.len())));
+		assert!(blobs1.iter().any(|b| b.len() != blobs1[0].len()));
+		assert_eq!(blobs1, blobs(options.clone()).await);
+
+		let blobs2 = blobs(MockTilesReaderOptions { seed: 43, ..options }).await;
+		assert_ne!(blobs1, blobs2);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn missing_and_failing_tiles() -> Result<()> {
+		let reader = new_reader(MockTilesReaderOptions {
+			seed: 7,
+			missing_rate: 0.2,
+			error_rate: 0.1,
+			..Default::default()
+		})?;
+		let results = read_level(&reader, 6).await;
+		let errors = results.iter().filter(|r| r.is_err()).count();
+		let missing = results.iter().filter(|r| matches!(r, Ok(None))).count();
+
+		// 4096 tiles: 10% fail, 20% of the rest are missing
+		assert!((350..470).contains(&errors), "{errors}");
+		assert!((650..830).contains(&missing), "{missing}");
+
+		// failing tiles fail again
+		let coord = TileBBox::new_full(6)?
+			.iter_coords()
+			.zip(results)
+			.find(|(_, r)| r.is_err())
+			.unwrap()
+			.0;
+		assert!(reader.get_tile_data(&coord).await.is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn latency() -> Result<()> {
+		let reader = new_reader(MockTilesReaderOptions {
+			latency: Some(Duration::from_millis(10)..Duration::from_millis(20)),
+			..Default::default()
+		})?;
+		let start = std::time::Instant::now();
+		assert!(reader.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.is_some());
+		assert!(start.elapsed() >= Duration::from_millis(10));
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn convert_from() -> Result<()> {
 		let mut reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
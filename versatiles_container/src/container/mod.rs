@@ -2,45 +2,112 @@
 //!
 //! ## Supported tile container formats
 //!
-//! | Format         | Read | Write | Feature   |
-//! |----------------|:----:|:-----:|-----------|
-//! | `*.versatiles` | ✅   | ✅     | `default` |
-//! | `*.mbtiles`    | ✅   | ✅     | `full`    |
-//! | `*.pmtiles`    | ✅   | ✅     | `full`    |
-//! | `*.tar`        | ✅   | ✅     | `full`    |
-//! | directory      | ✅   | ✅     | `default` |
-//! | pipeline       | ✅   | ❌     | `full`    |
+//! | Format         | Read | Write | Feature    |
+//! |----------------|:----:|:-----:|------------|
+//! | `*.versatiles` | ✅   | ✅     | -          |
+//! | `*.comt`       | ✅   | ✅     | -          |
+//! | `*.mbtiles`    | ✅   | ✅     | `sqlite`   |
+//! | `*.gpkg`       | ✅   | ✅     | `sqlite`   |
+//! | `*.pmtiles`    | ✅   | ✅     | -          |
+//! | `*.tar`        | ✅   | ✅     | -          |
+//! | directory      | ✅   | ✅     | -          |
+//! | pipeline       | ✅   | ❌     | `pipeline` |
+//!
+//! Reading from URLs requires the feature `http`, converting raster tiles requires `image`.
+//! The default feature `full` enables all of them.
 //!
 //! This module provides a unified interface for reading and writing various tile container formats.
 //! Depending on the enabled features, it supports different formats with corresponding read and write capabilities.
+//!
+//! On `wasm32` targets, only the formats without native dependencies are available. With the feature `wasm`,
+//! `*.versatiles` and `*.pmtiles` files can be read with an injected range fetcher, see [`open_fetch_reader`].
 
+#[cfg(all(feature = "pipeline", not(target_arch = "wasm32")))]
 mod pipeline;
+#[cfg(all(feature = "pipeline", not(target_arch = "wasm32")))]
 pub use pipeline::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod collector;
+#[cfg(not(target_arch = "wasm32"))]
+pub use collector::*;
+
+mod comtiles;
+pub use comtiles::*;
+
+mod compression_advice;
+pub use compression_advice::*;
+
+mod compression_levels;
+pub use compression_levels::*;
+
+#[cfg(not(target_arch = "wasm32"))]
 mod converter;
+#[cfg(not(target_arch = "wasm32"))]
 pub use converter::*;
 
+mod estimate;
+pub use estimate::*;
+
+#[cfg(any(test, feature = "wasm"))]
+mod fetch;
+#[cfg(any(test, feature = "wasm"))]
+pub use fetch::*;
+
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+mod geopackage;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub use geopackage::*;
+
+#[cfg(not(target_arch = "wasm32"))]
 mod getters;
 #[cfg(test)]
 pub use getters::tests::*;
-pub use getters::{get_reader, write_to_filename};
+#[cfg(not(target_arch = "wasm32"))]
+pub use getters::{get_reader, get_reader_with_hints, write_to_filename};
+
+mod level_coverage;
+pub use level_coverage::*;
 
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
 mod mbtiles;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
 pub use mbtiles::*;
 
-#[cfg(any(test, feature = "test"))]
+mod merger;
+pub use merger::*;
+
+#[cfg(any(test, feature = "test-utils"))]
 mod mock;
-#[cfg(any(test, feature = "test"))]
+#[cfg(any(test, feature = "test-utils"))]
 pub use mock::*;
 
+mod overzoom;
+pub use overzoom::*;
+
 mod pmtiles;
 pub use pmtiles::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod preset;
+#[cfg(not(target_arch = "wasm32"))]
+pub use preset::*;
+
 mod tar;
 pub use tar::*;
 
 pub mod tile_converter;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod tile_content;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tile_content::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tile_statistics;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tile_statistics::*;
+
 mod directory;
 pub use directory::*;
 
@@ -0,0 +1,261 @@
+//! Synthesizes tiles beyond the maximum zoom level of a tile container, e.g. for tools that need z15+ tiles.
+//!
+//! A tile below the maximum zoom level of the source is cut out of its ancestor at the maximum zoom level
+//! and scaled up. Raster tiles are cropped and resized, vector tiles are clipped and their geometries scaled.
+//!
+//! ```no_run
+//! use versatiles_container::{get_reader, write_to_filename, TilesOverzoomReader};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let reader = get_reader("berlin.mbtiles").await?;
+//! let mut reader = TilesOverzoomReader::new(reader, 16)?;
+//! write_to_filename(&mut reader, "berlin_z16.versatiles").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use versatiles_core::{
+	tilejson::TileJSON,
+	types::*,
+	utils::{compress, decompress},
+};
+use versatiles_geometry::vector_tile::VectorTile;
+use versatiles_image::helper::{blob2image, image2blob, overzoom_image};
+
+/// Margin around overzoomed vector tiles, as a fraction of the tile size.
+const VECTOR_BUFFER: f64 = 1.0 / 64.0;
+
+/// A reader that adds zoom levels beyond the maximum zoom level of another reader.
+#[derive(Debug)]
+pub struct TilesOverzoomReader<'a> {
+	reader: Box<dyn TilesReaderTrait + 'a>,
+	/// maximum zoom level of the source
+	source_zoom: u8,
+	parameters: TilesReaderParameters,
+	tilejson: TileJSON,
+	name: String,
+}
+
+impl<'a> TilesOverzoomReader<'a> {
+	/// Creates a new reader that extends `reader` up to `max_zoom`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the reader is empty or its tiles are neither raster nor vector tiles.
+	pub fn new(reader: Box<dyn TilesReaderTrait + 'a>, max_zoom: u8) -> Result<TilesOverzoomReader<'a>> {
+		let mut parameters = reader.get_parameters().clone();
+		ensure!(
+			matches!(
+				parameters.tile_format,
+				TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP | TileFormat::PBF
+			),
+			"tile format {} can not be overzoomed",
+			parameters.tile_format.as_str()
+		);
+		let source_zoom = parameters
+			.bbox_pyramid
+			.get_zoom_max()
+			.context("can not overzoom an empty container")?;
+		ensure!(max_zoom <= 30, "max_zoom must not be greater than 30");
+
+		let source_bbox = parameters.bbox_pyramid.get_level_bbox(source_zoom).clone();
+		for level in source_zoom + 1..=max_zoom {
+			let scale = 1u32 << (level - source_zoom);
+			parameters.bbox_pyramid.set_level_bbox(TileBBox::new(
+				level,
+				source_bbox.x_min * scale,
+				source_bbox.y_min * scale,
+				(source_bbox.x_max + 1) * scale - 1,
+				(source_bbox.y_max + 1) * scale - 1,
+			)?);
+		}
+
+		let mut tilejson = reader.get_tilejson().clone();
+		tilejson.set_byte("maxzoom", max_zoom.max(source_zoom))?;
+
+		Ok(TilesOverzoomReader {
+			name: format!("overzoom({})", reader.get_source_name()),
+			reader,
+			source_zoom,
+			parameters,
+			tilejson,
+		})
+	}
+
+	/// Returns the coordinate of the source tile, that contains `coord`.
+	fn source_coord(&self, coord: &TileCoord3) -> Result<TileCoord3> {
+		let level_diff = coord.z - self.source_zoom;
+		TileCoord3::new(coord.x >> level_diff, coord.y >> level_diff, self.source_zoom)
+	}
+
+	/// Cuts the tile `coord` out of the source tile `blob`.
+	fn overzoom_blob(&self, blob: &Blob, coord: &TileCoord3) -> Result<Blob> {
+		let level_diff = coord.z - self.source_zoom;
+		let mask = (1u32 << level_diff) - 1;
+		let (x, y) = (coord.x & mask, coord.y & mask);
+
+		let compression = &self.parameters.tile_compression;
+		let format = self.parameters.tile_format;
+		let blob = decompress(blob.clone(), compression)?;
+		let blob = match format {
+			TileFormat::PBF => VectorTile::from_blob(&blob)?
+				.overzoom(level_diff, x, y, VECTOR_BUFFER)?
+				.to_blob()?,
+			TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP => {
+				image2blob(&overzoom_image(&blob2image(&blob, format)?, level_diff, x, y)?, format)?
+			}
+			_ => bail!("tile format {} can not be overzoomed", format.as_str()),
+		};
+		compress(blob, compression)
+	}
+}
+
+#[async_trait]
+impl TilesReaderTrait for TilesOverzoomReader<'_> {
+	fn get_source_name(&self) -> &str {
+		&self.name
+	}
+
+	fn get_container_name(&self) -> &str {
+		"overzoom"
+	}
+
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn override_compression(&mut self, tile_compression: TileCompression) {
+		self.parameters.tile_compression = tile_compression;
+		self.reader.override_compression(tile_compression);
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if coord.z <= self.source_zoom {
+			return self.reader.get_tile_data(coord).await;
+		}
+		if !self.parameters.bbox_pyramid.contains_coord(coord) {
+			return Ok(None);
+		}
+		let Some(blob) = self.reader.get_tile_data(&self.source_coord(coord)?).await? else {
+			return Ok(None);
+		};
+		Ok(Some(self.overzoom_blob(&blob, coord)?))
+	}
+
+	async fn get_bbox_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		if bbox.level <= self.source_zoom {
+			return self.reader.get_bbox_tile_stream(bbox).await;
+		}
+		bbox.intersect_pyramid(&self.parameters.bbox_pyramid).unwrap();
+		if bbox.is_empty() {
+			return TileStream::new_empty();
+		}
+
+		let level_diff = bbox.level - self.source_zoom;
+		let bboxes: Vec<TileBBox> = bbox.iter_bbox_grid(64).collect();
+
+		TileStream::from_stream_iter(bboxes.into_iter().map(move |bbox| async move {
+			let source_bbox = TileBBox::new(
+				self.source_zoom,
+				bbox.x_min >> level_diff,
+				bbox.y_min >> level_diff,
+				bbox.x_max >> level_diff,
+				bbox.y_max >> level_diff,
+			)
+			.unwrap();
+
+			let source_tiles = self.reader.get_bbox_tile_stream(source_bbox).await.collect().await;
+
+			let mut tiles = Vec::new();
+			for (source_coord, blob) in source_tiles {
+				let scale = 1u32 << level_diff;
+				for y in 0..scale {
+					for x in 0..scale {
+						let coord =
+							TileCoord3::new(source_coord.x * scale + x, source_coord.y * scale + y, bbox.level).unwrap();
+						if bbox.contains3(&coord) {
+							tiles.push((coord, self.overzoom_blob(&blob, &coord).unwrap()));
+						}
+					}
+				}
+			}
+			TileStream::from_vec(tiles)
+		}))
+		.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{get_reader, MockTilesReader, MockTilesReaderProfile};
+
+	#[test]
+	fn new() -> Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		let reader = TilesOverzoomReader::new(reader.boxed(), 5)?;
+		let pyramid = &reader.get_parameters().bbox_pyramid;
+		assert_eq!(pyramid.get_zoom_max(), Some(5));
+		assert_eq!(pyramid.get_level_bbox(3), &TileBBox::new(3, 0, 2, 4, 6)?);
+		assert_eq!(pyramid.get_level_bbox(5), &TileBBox::new(5, 0, 8, 19, 27)?);
+		assert_eq!(reader.get_tilejson().values.get_byte("maxzoom"), Some(5));
+
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Json)?;
+		assert!(TilesOverzoomReader::new(reader.boxed(), 5).is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn raster() -> Result<()> {
+		let reader = MockTilesReader::new_mock_profile(MockTilesReaderProfile::Png)?;
+		let reader = TilesOverzoomReader::new(reader.boxed(), 5)?;
+
+		let blob = reader.get_tile_data(&TileCoord3::new(3, 10, 5)?).await?.unwrap();
+		let image = blob2image(&blob, TileFormat::PNG)?;
+		assert_eq!(image.width(), 256);
+
+		assert!(reader.get_tile_data(&TileCoord3::new(0, 0, 5)?).await?.is_none());
+
+		let tiles = reader
+			.get_bbox_tile_stream(TileBBox::new(4, 0, 0, 15, 15)?)
+			.await
+			.collect()
+			.await;
+		assert_eq!(tiles.len(), 10 * 10);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn vector() -> Result<()> {
+		let reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let reader = TilesOverzoomReader::new(reader, 15)?;
+		let parameters = reader.get_parameters();
+		assert_eq!(parameters.tile_compression, TileCompression::Gzip);
+
+		let parent = reader.get_tile_data(&TileCoord3::new(8800, 5377, 14)?).await?.unwrap();
+		let parent = VectorTile::from_blob(&decompress(parent, &TileCompression::Gzip)?)?;
+
+		let child = reader
+			.get_tile_data(&TileCoord3::new(17601, 10755, 15)?)
+			.await?
+			.unwrap();
+		let child = VectorTile::from_blob(&decompress(child, &TileCompression::Gzip)?)?;
+		assert!(!child.layers.is_empty());
+		assert!(child.layers.len() <= parent.layers.len());
+		assert_eq!(child.layers[0].extent, parent.layers[0].extent);
+
+		let tiles = reader
+			.get_bbox_tile_stream(TileBBox::new(15, 17600, 10754, 17601, 10755)?)
+			.await
+			.collect()
+			.await;
+		assert_eq!(tiles.len(), 4);
+		Ok(())
+	}
+}
@@ -1,6 +1,9 @@
 /*!
-The `pipeline` module provides functionality for reading, processing, and composing tiles from multiple sources.
+The `pipeline` module provides functionality for reading, processing, and composing tiles from multiple sources,
+and for running pipelines that write their tiles into containers.
 */
 
 mod reader;
+mod runner;
 pub use reader::PipelineReader;
+pub use runner::{run_pipeline_path, run_pipeline_str};
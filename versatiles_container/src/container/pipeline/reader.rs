@@ -3,7 +3,7 @@ use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use futures::future::BoxFuture;
 use std::path::Path;
-use versatiles_core::{io::DataReader, tilejson::TileJSON, types::*};
+use versatiles_core::{io::DataReader, metrics::counters, tilejson::TileJSON, types::*};
 use versatiles_pipeline::{OperationTrait, PipelineFactory};
 
 /// The `PipelineReader` struct is responsible for managing the tile reading process,
@@ -102,12 +102,19 @@ impl TilesReaderTrait for PipelineReader {
 
 	/// Get tile data for the given coordinate, always compressed and formatted.
 	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
-		self.operation.get_tile_data(coord).await
+		let blob = self.operation.get_tile_data(coord).await?;
+		if blob.is_some() {
+			counters().pipeline_tiles.inc();
+		}
+		Ok(blob)
 	}
 
 	/// Get a stream of tiles within the bounding box.
 	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
-		self.operation.get_tile_stream(bbox).await
+		self.operation.get_tile_stream(bbox).await.map_coord(|coord| {
+			counters().pipeline_tiles.inc();
+			coord
+		})
 	}
 }
 
@@ -0,0 +1,85 @@
+use super::PipelineReader;
+use crate::{convert_tiles_container, get_reader, TilesConverterParameters};
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use std::path::Path;
+use versatiles_core::types::{TileCompression, TilesReaderTrait};
+use versatiles_pipeline::{OperationTrait, PipelineFactory};
+
+/// Runs a vpl file, whose pipeline ends with `to_container`, and writes all its sinks.
+///
+/// # Arguments
+///
+/// * `path` - The path to the vpl file. Filenames in the vpl are relative to its directory.
+pub async fn run_pipeline_path(path: &Path) -> Result<()> {
+	let vpl = std::fs::read_to_string(path).with_context(|| anyhow!("Failed to open {path:?}"))?;
+	run_pipeline_str(&vpl, path.parent().unwrap())
+		.await
+		.with_context(|| format!("failed running {path:?} as VPL"))
+}
+
+/// Runs a vpl pipeline, whose pipeline ends with `to_container`, and writes all its sinks.
+///
+/// # Arguments
+///
+/// * `vpl` - The pipeline.
+/// * `dir` - The directory that filenames in the vpl are relative to.
+pub async fn run_pipeline_str(vpl: &str, dir: &Path) -> Result<()> {
+	let callback = Box::new(|filename: String| -> BoxFuture<Result<Box<dyn TilesReaderTrait>>> {
+		Box::pin(async move { get_reader(&filename).await })
+	});
+	let mut factory = PipelineFactory::default(dir, callback);
+	factory.set_writer(Box::new(
+		|operation: Box<dyn OperationTrait>,
+		 filename: String,
+		 compression: Option<TileCompression>|
+		 -> BoxFuture<Result<()>> {
+			Box::pin(async move {
+				let reader = PipelineReader {
+					name: filename.clone(),
+					parameters: operation.get_parameters().clone(),
+					operation,
+				};
+				let cp = TilesConverterParameters::new(compression, None, false, false, false);
+				convert_tiles_container(reader.boxed(), cp, &filename).await
+			})
+		},
+	));
+	factory.run_vpl(vpl).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use versatiles_core::types::TileCoord3;
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+	async fn run_two_sinks() -> Result<()> {
+		let dir = TempDir::new()?;
+		let raw = dir.path().join("raw.versatiles");
+		let low = dir.path().join("low.versatiles");
+		let vpl = format!(
+			"from_container filename=\"berlin.mbtiles\" | to_container filename={raw:?} | filter_zoom max=3 | to_container filename={low:?} compression=br"
+		);
+		run_pipeline_str(&vpl, Path::new("../testdata/")).await?;
+
+		let reader = get_reader(raw.to_str().unwrap()).await?;
+		assert_eq!(reader.get_parameters().tile_compression, TileCompression::Gzip);
+		assert!(reader.get_tile_data(&TileCoord3::new(8800, 5377, 14)?).await?.is_some());
+
+		let reader = get_reader(low.to_str().unwrap()).await?;
+		let parameters = reader.get_parameters();
+		assert_eq!(parameters.tile_compression, TileCompression::Brotli);
+		assert_eq!(parameters.bbox_pyramid.get_zoom_max(), Some(3));
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn run_without_sink() {
+		let error = run_pipeline_str("from_container filename=\"berlin.mbtiles\"", Path::new("../testdata/"))
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "pipeline must end with a 'to_container' operation");
+	}
+}
@@ -19,4 +19,4 @@ mod types;
 mod writer;
 
 pub use reader::PMTilesReader;
-pub use writer::PMTilesWriter;
+pub use writer::{PMTilesWriter, PMTilesWriterOptions};
@@ -186,6 +186,45 @@ impl TilesReaderTrait for PMTilesReader {
 		self.data_reader.get_name()
 	}
 
+	/// Counts the tiles inside `bbox` by walking through all directories, without reading any tiles.
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		fn count_directory(
+			bbox: &TileBBox,
+			dir: &Blob,
+			leaves_bytes: &Blob,
+			compression: &TileCompression,
+			sum: &mut (u64, u64),
+		) -> Result<()> {
+			for entry in EntriesV3::from_blob(dir)?.iter() {
+				if entry.range.length == 0 {
+					continue;
+				}
+				if entry.run_length > 0 {
+					for i in 0..entry.run_length as u64 {
+						if bbox.contains3(&tile_id_to_coord(i + entry.tile_id)?) {
+							sum.0 += 1;
+							sum.1 += entry.range.length;
+						}
+					}
+				} else {
+					let blob = decompress(leaves_bytes.read_range(&entry.range)?, compression)?;
+					count_directory(bbox, &blob, leaves_bytes, compression, sum)?;
+				}
+			}
+			Ok(())
+		}
+
+		let mut sum = (0, 0);
+		count_directory(
+			bbox,
+			&self.root_bytes_uncompressed,
+			&self.leaves_bytes,
+			&self.internal_compression,
+			&mut sum,
+		)?;
+		Ok(Some(sum))
+	}
+
 	/// Returns the tile data for the specified coordinates as a `Blob`.
 	///
 	/// # Arguments
@@ -0,0 +1,222 @@
+//! External merge sort for PMTiles directory entries.
+//!
+//! PMTiles directories must be sorted by tile id. Instead of keeping all entries in memory, the
+//! `EntriesSorter` keeps at most `max_memory` bytes of entries. Whenever this budget is exceeded, the
+//! entries are sorted and spilled as a "run" to a temporary file. Reading merges all runs and the
+//! remaining in-memory entries into a single sorted sequence.
+
+use super::EntryV3;
+use anyhow::{Context, Result};
+use std::{
+	cmp::Reverse,
+	collections::BinaryHeap,
+	fs::{remove_file, File},
+	io::{BufReader, BufWriter, ErrorKind, Read, Write},
+	mem::size_of,
+	path::PathBuf,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+use versatiles_core::{types::ByteRange, utils::get_temp_dir};
+
+/// Size of a serialized entry: tile id, offset, length and run length.
+const ENTRY_SIZE: usize = 8 + 8 + 8 + 4;
+
+static SORTER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Collects `EntryV3`s in any order and returns them sorted by tile id, using bounded memory.
+#[derive(Debug)]
+pub struct EntriesSorter {
+	id: usize,
+	max_entries: usize,
+	buffer: Vec<EntryV3>,
+	runs: Vec<PathBuf>,
+	len: u64,
+}
+
+impl EntriesSorter {
+	/// Creates a sorter that keeps at most `max_memory` bytes of entries in memory.
+	pub fn new(max_memory: usize) -> Self {
+		Self {
+			id: SORTER_COUNT.fetch_add(1, Ordering::Relaxed),
+			max_entries: (max_memory / size_of::<EntryV3>()).max(1024),
+			buffer: Vec::new(),
+			runs: Vec::new(),
+			len: 0,
+		}
+	}
+
+	/// Adds an entry, spilling the buffer to a temporary file if the memory budget is exhausted.
+	pub fn push(&mut self, entry: EntryV3) -> Result<()> {
+		self.buffer.push(entry);
+		self.len += 1;
+		if self.buffer.len() >= self.max_entries {
+			self.spill()?;
+		}
+		Ok(())
+	}
+
+	/// Returns the number of entries.
+	pub fn len(&self) -> u64 {
+		self.len
+	}
+
+	fn spill(&mut self) -> Result<()> {
+		self.buffer.sort_unstable_by_key(|e| e.tile_id);
+
+		let path = get_temp_dir().join(format!(
+			"versatiles_pmtiles_{}_{}_{}.tmp",
+			std::process::id(),
+			self.id,
+			self.runs.len()
+		));
+		let file = File::create(&path).with_context(|| format!("creating temporary file {path:?}"))?;
+		self.runs.push(path);
+
+		let mut writer = BufWriter::new(file);
+		for entry in self.buffer.iter() {
+			writer.write_all(&entry.tile_id.to_le_bytes())?;
+			writer.write_all(&entry.range.offset.to_le_bytes())?;
+			writer.write_all(&entry.range.length.to_le_bytes())?;
+			writer.write_all(&entry.run_length.to_le_bytes())?;
+		}
+		writer.flush()?;
+
+		self.buffer.clear();
+		Ok(())
+	}
+
+	/// Returns all entries sorted by tile id. Can be called multiple times.
+	pub fn iter(&mut self) -> Result<SortedEntries<'_>> {
+		self.buffer.sort_unstable_by_key(|e| e.tile_id);
+
+		let mut runs = Vec::with_capacity(self.runs.len());
+		for path in self.runs.iter() {
+			let file = File::open(path).with_context(|| format!("opening temporary file {path:?}"))?;
+			runs.push(BufReader::new(file));
+		}
+
+		let mut sorted = SortedEntries {
+			buffer: &self.buffer,
+			buffer_index: 0,
+			runs,
+			heads: Vec::new(),
+			heap: BinaryHeap::new(),
+		};
+		for source in 0..=sorted.runs.len() {
+			sorted.heads.push(None);
+			sorted.advance(source)?;
+		}
+		Ok(sorted)
+	}
+}
+
+impl Drop for EntriesSorter {
+	fn drop(&mut self) {
+		for path in self.runs.iter() {
+			let _ = remove_file(path);
+		}
+	}
+}
+
+/// Iterator merging the sorted runs of an `EntriesSorter`.
+pub struct SortedEntries<'a> {
+	buffer: &'a [EntryV3],
+	buffer_index: usize,
+	runs: Vec<BufReader<File>>,
+	// next entry of every source: the runs first, the in-memory buffer last
+	heads: Vec<Option<EntryV3>>,
+	heap: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl SortedEntries<'_> {
+	fn advance(&mut self, source: usize) -> Result<()> {
+		let entry = if source < self.runs.len() {
+			read_entry(&mut self.runs[source])?
+		} else {
+			let entry = self.buffer.get(self.buffer_index).copied();
+			self.buffer_index += 1;
+			entry
+		};
+		if let Some(entry) = &entry {
+			self.heap.push(Reverse((entry.tile_id, source)));
+		}
+		self.heads[source] = entry;
+		Ok(())
+	}
+}
+
+impl Iterator for SortedEntries<'_> {
+	type Item = Result<EntryV3>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let Reverse((_, source)) = self.heap.pop()?;
+		let entry = self.heads[source].take()?;
+		Some(self.advance(source).map(|_| entry))
+	}
+}
+
+fn read_entry(reader: &mut BufReader<File>) -> Result<Option<EntryV3>> {
+	let mut bytes = [0u8; ENTRY_SIZE];
+	match reader.read_exact(&mut bytes) {
+		Ok(()) => {}
+		Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e.into()),
+	}
+	let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+	Ok(Some(EntryV3::new(
+		u64_at(0),
+		ByteRange::new(u64_at(8), u64_at(16)),
+		u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+	)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn collect(sorter: &mut EntriesSorter) -> Vec<u64> {
+		sorter.iter().unwrap().map(|e| e.unwrap().tile_id).collect()
+	}
+
+	#[test]
+	fn sorts_in_memory() -> Result<()> {
+		let mut sorter = EntriesSorter::new(1 << 20);
+		for id in [5, 3, 9, 1] {
+			sorter.push(EntryV3::new(id, ByteRange::new(id * 10, 10), 1))?;
+		}
+		assert_eq!(sorter.runs.len(), 0);
+		assert_eq!(collect(&mut sorter), vec![1, 3, 5, 9]);
+		Ok(())
+	}
+
+	#[test]
+	fn spills_runs_to_temporary_files() -> Result<()> {
+		// the minimum budget is 1024 entries
+		let mut sorter = EntriesSorter::new(0);
+		let ids: Vec<u64> = (0..5000u64).map(|i| (i * 7919) % 5000).collect();
+		for id in ids.iter() {
+			sorter.push(EntryV3::new(*id, ByteRange::new(*id * 3, 3), 1))?;
+		}
+		assert_eq!(sorter.len(), 5000);
+		assert_eq!(sorter.runs.len(), 4);
+
+		let paths = sorter.runs.clone();
+		assert!(paths.iter().all(|p| p.exists()));
+
+		// can be read multiple times
+		for _ in 0..2 {
+			let entries: Vec<EntryV3> = sorter.iter()?.collect::<Result<_>>()?;
+			assert_eq!(entries.len(), 5000);
+			for (index, entry) in entries.iter().enumerate() {
+				assert_eq!(
+					entry,
+					&EntryV3::new(index as u64, ByteRange::new(index as u64 * 3, 3), 1)
+				);
+			}
+		}
+
+		drop(sorter);
+		assert!(paths.iter().all(|p| !p.exists()));
+		Ok(())
+	}
+}
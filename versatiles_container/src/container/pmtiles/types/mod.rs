@@ -1,4 +1,5 @@
 mod directory_v3;
+mod entries_sorter;
 mod entries_v3;
 mod entry_v3;
 mod header_v3;
@@ -7,6 +8,7 @@ mod tile_id;
 mod tile_type;
 
 pub use directory_v3::Directory;
+pub use entries_sorter::EntriesSorter;
 pub use entries_v3::EntriesV3;
 pub use entry_v3::EntryV3;
 pub use header_v3::HeaderV3;
@@ -32,6 +32,7 @@ impl PMTilesType {
 			GEOJSON => bail!("PMTiles does not support GEOJSON"),
 			JPG => PMTilesType::JPEG,
 			JSON => bail!("PMTiles does not support JSON"),
+			JXL => bail!("PMTiles does not support JXL"),
 			PBF => PMTilesType::MVT,
 			PNG => PMTilesType::PNG,
 			SVG => bail!("PMTiles does not support SVG"),
@@ -80,6 +81,7 @@ mod tests {
 		assert!(PMTilesType::from_value(BIN).is_err());
 		assert!(PMTilesType::from_value(GEOJSON).is_err());
 		assert!(PMTilesType::from_value(JSON).is_err());
+		assert!(PMTilesType::from_value(JXL).is_err());
 		assert!(PMTilesType::from_value(SVG).is_err());
 		assert!(PMTilesType::from_value(TOPOJSON).is_err());
 	}
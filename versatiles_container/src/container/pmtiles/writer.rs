@@ -6,6 +6,9 @@
 //! - Supports writing metadata and tile data with internal compression
 //! - Efficiently organizes and compresses tile data for storage
 //! - Implements progress feedback during the write process
+//! - Sorts the directory entries with bounded memory, spilling to temporary files if necessary
+//! - Writes clustered archives by default: tile data in tile id order, identical tiles stored once and contiguous
+//!   tile ids with identical content run-length encoded, like go-pmtiles does
 //!
 //! ## Usage Example
 //! ```rust
@@ -28,34 +31,128 @@
 //! ## Testing
 //! This module includes comprehensive tests to ensure the correct functionality of writing metadata, handling different tile formats, and verifying the integrity of the written data.
 
-use super::types::{EntriesV3, EntryV3, HeaderV3, PMTilesCompression, TileId};
-use crate::TilesWriterTrait;
+use super::types::{EntriesSorter, EntriesV3, EntryV3, HeaderV3, PMTilesCompression, TileId};
+use crate::{TilesWriterTrait, WRITE_QUEUE_SIZE};
 use anyhow::Result;
 use async_trait::async_trait;
-use versatiles_core::{io::DataWriterTrait, progress::get_progress_bar, types::*, utils::compress};
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	hash::{DefaultHasher, Hash, Hasher},
+};
+use versatiles_core::{
+	io::DataWriterTrait,
+	progress::get_progress_bar,
+	types::*,
+	utils::{compress, get_max_memory},
+};
+
+const INTERNAL_COMPRESSION: TileCompression = TileCompression::Gzip;
+
+/// Size of the header plus root directory, the root directory must fit into it.
+const ROOT_LENGTH: u64 = 16384;
+
+/// Options for writing a PMTiles container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PMTilesWriterOptions {
+	/// Memory budget for sorting the directory entries. Larger directories are sorted externally, using temporary files.
+	/// Defaults to half of the memory budget of the process, see `versatiles_core::utils::set_max_memory`.
+	pub max_memory: usize,
+	/// Writes the tile data in tile id order, stores identical tiles only once and run-length encodes contiguous
+	/// tile ids with identical content. Otherwise tiles are written in the order of the reader.
+	pub clustered: bool,
+}
+
+impl Default for PMTilesWriterOptions {
+	fn default() -> Self {
+		Self {
+			max_memory: get_max_memory() / 2,
+			clustered: true,
+		}
+	}
+}
+
+/// Collects the directory entries of the tiles, merging runs of identical tiles.
+struct TileEntries {
+	entries: EntriesSorter,
+	/// last entry, that might be extended by the next tile
+	pending: Option<EntryV3>,
+	tile_count: u64,
+}
+
+impl TileEntries {
+	fn push(&mut self, tile_id: u64, range: ByteRange) -> Result<()> {
+		self.tile_count += 1;
+		if let Some(entry) = &mut self.pending {
+			if entry.range == range && entry.tile_id + entry.run_length as u64 == tile_id && entry.run_length < u32::MAX {
+				entry.run_length += 1;
+				return Ok(());
+			}
+		}
+		if let Some(entry) = self.pending.replace(EntryV3::new(tile_id, range, 1)) {
+			self.entries.push(entry)?;
+		}
+		Ok(())
+	}
+
+	fn finish(mut self) -> Result<(EntriesSorter, u64)> {
+		if let Some(entry) = self.pending.take() {
+			self.entries.push(entry)?;
+		}
+		Ok((self.entries, self.tile_count))
+	}
+}
+
+/// Returns a 128 bit hash of the tile content, like the FNV-128 hash used by go-pmtiles for deduplication.
+fn hash_tile(blob: &Blob) -> (u64, u64, u64) {
+	let mut hasher1 = DefaultHasher::new();
+	blob.as_slice().hash(&mut hasher1);
+	let mut hasher2 = DefaultHasher::new();
+	0xA5u8.hash(&mut hasher2);
+	blob.as_slice().hash(&mut hasher2);
+	(hasher1.finish(), hasher2.finish(), blob.len())
+}
 
 /// A struct that provides functionality to write tile data to a PMTiles container.
 pub struct PMTilesWriter {}
 
-#[async_trait]
-impl TilesWriterTrait for PMTilesWriter {
-	/// Writes tile data from a `TilesReader` to a `DataWriterTrait` (such as a PMTiles container).
-	///
-	/// # Arguments
-	/// * `reader` - The tiles reader providing the tile data.
-	/// * `writer` - The data writer to write the tile data to.
+impl PMTilesWriter {
+	/// Writes tile data like `write_to_writer`, but keeps at most `max_memory` bytes of directory
+	/// entries in memory. Larger directories are sorted externally, using temporary files.
 	///
 	/// # Errors
-	/// Returns an error if there are issues with writing data or internal processing.
-	async fn write_to_writer(reader: &mut dyn TilesReaderTrait, writer: &mut dyn DataWriterTrait) -> Result<()> {
-		const INTERNAL_COMPRESSION: TileCompression = TileCompression::Gzip;
+	/// Returns an error if there are issues with writing data, temporary files or internal processing.
+	pub async fn write_with_max_memory(
+		reader: &mut dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		max_memory: usize,
+	) -> Result<()> {
+		let options = PMTilesWriterOptions {
+			max_memory,
+			..Default::default()
+		};
+		Self::write_with_options(reader, writer, &options).await
+	}
 
+	/// Writes tile data like `write_to_writer`, using the given options.
+	///
+	/// Clustered archives are written block by block. The blocks are aligned squares of tiles, so each block
+	/// covers a contiguous range of tile ids. The tiles of a block are sorted in memory before writing.
+	///
+	/// # Errors
+	/// Returns an error if there are issues with writing data, temporary files or internal processing.
+	pub async fn write_with_options(
+		reader: &mut dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		options: &PMTilesWriterOptions,
+	) -> Result<()> {
 		let parameters = reader.get_parameters().clone();
 		let pyramid = &parameters.bbox_pyramid;
 
+		// smaller blocks limit the memory needed to sort the tiles of a block
+		let block_size = if options.clustered { 64 } else { 256 };
 		let mut blocks: Vec<TileBBox> = pyramid
 			.iter_levels()
-			.flat_map(|level_bbox| level_bbox.iter_bbox_grid(256))
+			.flat_map(|level_bbox| level_bbox.iter_bbox_grid(block_size))
 			.collect();
 		blocks.sort_by_cached_key(|b| b.get_tile_id().unwrap());
 
@@ -65,9 +162,15 @@ impl TilesWriterTrait for PMTilesWriter {
 		);
 		let mut tile_count = 0;
 
-		let mut entries = EntriesV3::new();
+		let mut entries = TileEntries {
+			entries: EntriesSorter::new(options.max_memory),
+			pending: None,
+			tile_count: 0,
+		};
+		let mut contents: HashMap<(u64, u64, u64), ByteRange> = HashMap::new();
+		let mut contents_count = 0;
 
-		writer.set_position(16384)?;
+		writer.set_position(ROOT_LENGTH)?;
 
 		let mut header = HeaderV3::from_parameters(&parameters);
 
@@ -78,12 +181,38 @@ impl TilesWriterTrait for PMTilesWriter {
 		let tile_data_start = writer.get_position()?;
 
 		for bbox in blocks.iter() {
-			let mut stream = reader.get_bbox_tile_stream(bbox.clone()).await;
-			while let Some((coord, blob)) = stream.next().await {
-				progress.inc(1);
-				let id = coord.get_tile_id().unwrap();
-				let range = writer.append(&blob).unwrap();
-				entries.push(EntryV3::new(id, range.get_shifted_backward(tile_data_start), 1));
+			let stream = reader.get_bbox_tile_stream(bbox.clone()).await;
+			if options.clustered {
+				let mut tiles: Vec<(u64, Blob)> = Vec::new();
+				stream
+					.for_each_queued(WRITE_QUEUE_SIZE, |(coord, blob)| {
+						progress.inc(1);
+						tiles.push((coord.get_tile_id()?, blob));
+						Ok(())
+					})
+					.await?;
+				tiles.sort_unstable_by_key(|(id, _)| *id);
+
+				for (id, blob) in tiles {
+					let range = match contents.entry(hash_tile(&blob)) {
+						Entry::Occupied(entry) => *entry.get(),
+						Entry::Vacant(entry) => {
+							contents_count += 1;
+							*entry.insert(writer.append(&blob)?.get_shifted_backward(tile_data_start))
+						}
+					};
+					entries.push(id, range)?;
+				}
+			} else {
+				stream
+					.for_each_queued(WRITE_QUEUE_SIZE, |(coord, blob)| {
+						progress.inc(1);
+						let id = coord.get_tile_id()?;
+						let range = writer.append(&blob)?;
+						contents_count += 1;
+						entries.push(id, range.get_shifted_backward(tile_data_start))
+					})
+					.await?;
 			}
 
 			tile_count += bbox.count_tiles();
@@ -91,22 +220,23 @@ impl TilesWriterTrait for PMTilesWriter {
 		}
 		progress.finish();
 
+		let (mut entries, addressed_tiles_count) = entries.finish()?;
+
 		let tile_data_end = writer.get_position()?;
 
 		header.tile_data = ByteRange::new(tile_data_start, tile_data_end - tile_data_start);
 
-		writer.set_position(HeaderV3::len())?;
-		let directory = entries.as_directory(16384 - HeaderV3::len(), &INTERNAL_COMPRESSION)?;
-		header.root_dir = writer.append(&directory.root_bytes)?;
+		let (root_bytes, leaf_dirs) = write_leaf_directories(&mut entries, writer, tile_data_end)?;
+		header.leaf_dirs = leaf_dirs;
 
-		writer.set_position(tile_data_end)?;
-		header.leaf_dirs = writer.append(&directory.leaves_bytes)?;
+		writer.set_position(HeaderV3::len())?;
+		header.root_dir = writer.append(&root_bytes)?;
 
-		header.clustered = true;
+		header.clustered = options.clustered;
 		header.internal_compression = PMTilesCompression::from_value(INTERNAL_COMPRESSION)?;
-		header.addressed_tiles_count = entries.tile_count();
-		header.tile_entries_count = entries.len() as u64;
-		header.tile_contents_count = entries.len() as u64;
+		header.addressed_tiles_count = addressed_tiles_count;
+		header.tile_entries_count = entries.len();
+		header.tile_contents_count = contents_count;
 
 		writer.write_start(&header.serialize()?)?;
 
@@ -114,6 +244,78 @@ impl TilesWriterTrait for PMTilesWriter {
 	}
 }
 
+/// Writes the leaf directories at `leaves_start` and returns the root directory and the range of the leaves.
+///
+/// Small directories fit completely into the root directory. Otherwise the sorted entries are
+/// streamed into leaf directories, increasing the leaf size until the root directory fits.
+fn write_leaf_directories(
+	entries: &mut EntriesSorter,
+	writer: &mut dyn DataWriterTrait,
+	leaves_start: u64,
+) -> Result<(Blob, ByteRange)> {
+	let target_root_len = ROOT_LENGTH - HeaderV3::len();
+
+	if entries.len() < 16384 {
+		let mut all = EntriesV3::new();
+		for entry in entries.iter()? {
+			all.push(entry?);
+		}
+		let directory = all.as_directory(target_root_len, &INTERNAL_COMPRESSION)?;
+		writer.set_position(leaves_start)?;
+		let leaf_dirs = writer.append(&directory.leaves_bytes)?;
+		return Ok((directory.root_bytes, leaf_dirs));
+	}
+
+	let mut leaf_size: f32 = (entries.len() as f32 / 3500f32).max(4096f32);
+
+	loop {
+		writer.set_position(leaves_start)?;
+		let mut root_entries = EntriesV3::new();
+		let mut leaf = EntriesV3::new();
+
+		let mut flush_leaf = |leaf: EntriesV3| -> Result<()> {
+			let bytes = compress(leaf.as_slice().serialize_entries()?, &INTERNAL_COMPRESSION)?;
+			let range = writer.append(&bytes)?.get_shifted_backward(leaves_start);
+			root_entries.push(EntryV3::new(leaf.as_slice().get(0).tile_id, range, 0));
+			Ok(())
+		};
+
+		for entry in entries.iter()? {
+			leaf.push(entry?);
+			if leaf.len() >= leaf_size as usize {
+				flush_leaf(std::mem::take(&mut leaf))?;
+			}
+		}
+		if leaf.len() > 0 {
+			flush_leaf(leaf)?;
+		}
+
+		let root_bytes = compress(root_entries.as_slice().serialize_entries()?, &INTERNAL_COMPRESSION)?;
+		if root_bytes.len() <= target_root_len {
+			let leaves_end = writer.get_position()?;
+			return Ok((root_bytes, ByteRange::new(leaves_start, leaves_end - leaves_start)));
+		}
+		leaf_size *= 1.2
+	}
+}
+
+#[async_trait]
+impl TilesWriterTrait for PMTilesWriter {
+	/// Writes tile data from a `TilesReader` to a `DataWriterTrait` (such as a PMTiles container).
+	///
+	/// Uses at most half of the memory budget of the process for sorting the directory entries.
+	///
+	/// # Arguments
+	/// * `reader` - The tiles reader providing the tile data.
+	/// * `writer` - The data writer to write the tile data to.
+	///
+	/// # Errors
+	/// Returns an error if there are issues with writing data or internal processing.
+	async fn write_to_writer(reader: &mut dyn TilesReaderTrait, writer: &mut dyn DataWriterTrait) -> Result<()> {
+		Self::write_with_options(reader, writer, &PMTilesWriterOptions::default()).await
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -121,15 +323,15 @@ mod tests {
 		mock::{MockTilesReader, MockTilesWriter},
 		pmtiles::PMTilesReader,
 	};
-	use versatiles_core::io::*;
+	use versatiles_core::{io::*, utils::decompress};
 
 	#[tokio::test]
 	async fn read_write() -> Result<()> {
-		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters {
-			bbox_pyramid: TileBBoxPyramid::new_full(4),
-			tile_compression: TileCompression::Gzip,
-			tile_format: TileFormat::PBF,
-		})?;
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(4),
+		))?;
 
 		let mut data_writer = DataWriterBlob::new()?;
 		PMTilesWriter::write_to_writer(&mut mock_reader, &mut data_writer).await?;
@@ -140,4 +342,104 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn read_write_with_external_sort() -> Result<()> {
+		// more than 16384 tiles, so leaf directories are needed
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(7),
+		))?;
+
+		let mut data_writer = DataWriterBlob::new()?;
+		// the smallest possible budget forces many temporary runs
+		PMTilesWriter::write_with_max_memory(&mut mock_reader, &mut data_writer, 0).await?;
+
+		let data_reader = DataReaderBlob::from(data_writer);
+		let reader = PMTilesReader::open_reader(Box::new(data_reader)).await?;
+		assert_eq!(reader.get_parameters().bbox_pyramid, TileBBoxPyramid::new_full(7));
+		for coord in [(0, 0, 0), (5, 9, 4), (127, 127, 7), (64, 3, 7)] {
+			let coord = TileCoord3::new(coord.0, coord.1, coord.2)?;
+			assert!(reader.get_tile_data(&coord).await?.is_some(), "{coord:?}");
+		}
+
+		Ok(())
+	}
+
+	/// Writes the mock tiles and returns the header and the root directory.
+	async fn write_mock(format: TileFormat, clustered: bool) -> Result<(HeaderV3, EntriesV3, DataReaderBlob)> {
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			format,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(4),
+		))?;
+
+		let mut data_writer = DataWriterBlob::new()?;
+		let options = PMTilesWriterOptions {
+			clustered,
+			..Default::default()
+		};
+		PMTilesWriter::write_with_options(&mut mock_reader, &mut data_writer, &options).await?;
+
+		let data_reader = DataReaderBlob::from(data_writer);
+		let header = HeaderV3::deserialize(&data_reader.read_range(&ByteRange::new(0, HeaderV3::len())).await?)?;
+		let root = decompress(data_reader.read_range(&header.root_dir).await?, &INTERNAL_COMPRESSION)?;
+		Ok((header, EntriesV3::from_blob(&root)?, data_reader))
+	}
+
+	#[tokio::test]
+	async fn clustered() -> Result<()> {
+		// every tile is different
+		let (header, entries, data_reader) = write_mock(TileFormat::JSON, true).await?;
+		assert!(header.clustered);
+		assert_eq!(header.addressed_tiles_count, 341);
+		assert_eq!(header.tile_entries_count, 341);
+		assert_eq!(header.tile_contents_count, 341);
+
+		// tile data is ordered by tile id
+		let mut offset = 0;
+		for entry in entries.iter() {
+			assert_eq!(entry.range.offset, offset);
+			offset += entry.range.length;
+		}
+
+		let mut reader = PMTilesReader::open_reader(Box::new(data_reader)).await?;
+		let blob = reader.get_tile_data(&TileCoord3::new(5, 9, 4)?).await?.unwrap();
+		assert_eq!(blob.as_str(), "{x:5,y:9,z:4}");
+		MockTilesWriter::write(&mut reader).await?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn clustered_deduplicates() -> Result<()> {
+		// every tile is identical, so all tiles are stored in a single run
+		let (header, entries, data_reader) = write_mock(TileFormat::PBF, true).await?;
+		assert_eq!(header.addressed_tiles_count, 341);
+		assert_eq!(header.tile_entries_count, 1);
+		assert_eq!(header.tile_contents_count, 1);
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries.iter().next().unwrap().run_length, 341);
+		assert_eq!(header.tile_data.length, entries.iter().next().unwrap().range.length);
+
+		let mut reader = PMTilesReader::open_reader(Box::new(data_reader)).await?;
+		assert_eq!(reader.get_parameters().bbox_pyramid, TileBBoxPyramid::new_full(4));
+		assert!(reader.get_tile_data(&TileCoord3::new(15, 15, 4)?).await?.is_some());
+		MockTilesWriter::write(&mut reader).await?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn unclustered() -> Result<()> {
+		let (header, entries, data_reader) = write_mock(TileFormat::PBF, false).await?;
+		assert!(!header.clustered);
+		assert_eq!(header.addressed_tiles_count, 341);
+		assert_eq!(header.tile_entries_count, 341);
+		assert_eq!(header.tile_contents_count, 341);
+		assert!(entries.iter().all(|entry| entry.run_length == 1));
+
+		let mut reader = PMTilesReader::open_reader(Box::new(data_reader)).await?;
+		MockTilesWriter::write(&mut reader).await?;
+		Ok(())
+	}
 }
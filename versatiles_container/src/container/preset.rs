@@ -0,0 +1,148 @@
+//! Named conversion presets with recommended settings for common use cases.
+//!
+//! A preset sets the tile compression and the compression levels per zoom range, and checks that the
+//! tiles and the output container suit the use case. Explicit settings can still be changed afterwards.
+//!
+//! ```rust
+//! use versatiles_container::{ConversionPreset, TilesConverterParameters};
+//! use versatiles_core::types::TileCompression;
+//!
+//! let mut parameters = TilesConverterParameters::new_default();
+//! ConversionPreset::parse_str("vector-cdn").unwrap().apply(&mut parameters);
+//! assert_eq!(parameters.tile_compression, Some(TileCompression::Brotli));
+//! ```
+
+use super::{CompressionLevels, TilesConverterParameters};
+use anyhow::{bail, ensure, Result};
+use versatiles_core::types::{TileCompression, TileFormat};
+
+/// A named set of conversion settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConversionPreset {
+	/// Raster tiles for web maps. Images are already compressed, so tiles are stored uncompressed,
+	/// which saves browsers from decompressing them twice.
+	WebRaster,
+	/// Vector tiles served by a CDN or a static file host. Tiles are compressed with brotli, as small as
+	/// possible for the few but frequently requested tiles of low zoom levels and faster for the
+	/// millions of tiles of high zoom levels.
+	VectorCdn,
+}
+
+impl ConversionPreset {
+	pub const ALL: [ConversionPreset; 2] = [ConversionPreset::WebRaster, ConversionPreset::VectorCdn];
+
+	/// Parses the name of a preset, e.g. "web-raster".
+	pub fn parse_str(value: &str) -> Result<Self> {
+		let value = value.to_lowercase().replace('_', "-");
+		for preset in ConversionPreset::ALL {
+			if preset.as_str() == value {
+				return Ok(preset);
+			}
+		}
+		let names: Vec<&str> = ConversionPreset::ALL.iter().map(|p| p.as_str()).collect();
+		bail!("unknown preset \"{value}\", use one of: {}", names.join(", "))
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ConversionPreset::WebRaster => "web-raster",
+			ConversionPreset::VectorCdn => "vector-cdn",
+		}
+	}
+
+	/// Sets compression, compression levels and recompression of `parameters`.
+	pub fn apply(&self, parameters: &mut TilesConverterParameters) {
+		match self {
+			ConversionPreset::WebRaster => {
+				parameters.tile_compression = Some(TileCompression::Uncompressed);
+				parameters.compression_levels = CompressionLevels::default();
+			}
+			ConversionPreset::VectorCdn => {
+				parameters.tile_compression = Some(TileCompression::Brotli);
+				parameters.compression_levels =
+					CompressionLevels::parse_str("0-10:11,11-:9").expect("levels should be valid");
+				parameters.force_recompress = true;
+			}
+		}
+	}
+
+	/// Checks that tiles of this format suit the preset.
+	pub fn check_tile_format(&self, format: TileFormat) -> Result<()> {
+		use TileFormat::*;
+		let supported = match self {
+			ConversionPreset::WebRaster => matches!(format, AVIF | JPG | PNG | WEBP),
+			ConversionPreset::VectorCdn => format == PBF,
+		};
+		ensure!(
+			supported,
+			"preset \"{}\" does not support tiles in format {}",
+			self.as_str(),
+			format.as_str()
+		);
+		Ok(())
+	}
+
+	/// Returns the recommended file extensions of the output container. Both deduplicate identical tiles
+	/// and store tiles in an order that keeps neighbouring tiles close together.
+	pub fn get_recommended_extensions(&self) -> &'static [&'static str] {
+		&[".versatiles", ".pmtiles"]
+	}
+
+	/// Returns `true`, if the output file has a recommended extension.
+	pub fn is_recommended_output(&self, filename: &str) -> bool {
+		let filename = filename.to_lowercase();
+		self
+			.get_recommended_extensions()
+			.iter()
+			.any(|extension| filename.ends_with(extension))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_str() {
+		assert_eq!(
+			ConversionPreset::parse_str("Web_Raster").unwrap(),
+			ConversionPreset::WebRaster
+		);
+		for preset in ConversionPreset::ALL {
+			assert_eq!(ConversionPreset::parse_str(preset.as_str()).unwrap(), preset);
+		}
+		assert_eq!(
+			ConversionPreset::parse_str("fast").unwrap_err().to_string(),
+			"unknown preset \"fast\", use one of: web-raster, vector-cdn"
+		);
+	}
+
+	#[test]
+	fn test_apply() {
+		let mut parameters = TilesConverterParameters::new_default();
+		ConversionPreset::VectorCdn.apply(&mut parameters);
+		assert_eq!(parameters.tile_compression, Some(TileCompression::Brotli));
+		assert_eq!(parameters.compression_levels.get_level(5), Some(11));
+		assert_eq!(parameters.compression_levels.get_level(14), Some(9));
+		assert!(parameters.force_recompress);
+
+		ConversionPreset::WebRaster.apply(&mut parameters);
+		assert_eq!(parameters.tile_compression, Some(TileCompression::Uncompressed));
+		assert!(parameters.compression_levels.is_empty());
+	}
+
+	#[test]
+	fn test_checks() {
+		assert!(ConversionPreset::WebRaster.check_tile_format(TileFormat::WEBP).is_ok());
+		assert_eq!(
+			ConversionPreset::WebRaster
+				.check_tile_format(TileFormat::PBF)
+				.unwrap_err()
+				.to_string(),
+			"preset \"web-raster\" does not support tiles in format pbf"
+		);
+		assert!(ConversionPreset::VectorCdn.check_tile_format(TileFormat::PBF).is_ok());
+		assert!(ConversionPreset::VectorCdn.is_recommended_output("planet.PMTiles"));
+		assert!(!ConversionPreset::VectorCdn.is_recommended_output("planet.mbtiles"));
+	}
+}
@@ -41,4 +41,4 @@ mod reader;
 mod writer;
 
 pub use reader::TarTilesReader;
-pub use writer::TarTilesWriter;
+pub use writer::{TarLayout, TarTilesWriter, TarWriterOptions};
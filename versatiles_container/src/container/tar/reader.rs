@@ -1,8 +1,8 @@
 //! Provides functionality for reading tile data from a tar archive.
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Context, Result};
 use async_trait::async_trait;
-use std::{collections::HashMap, fmt::Debug, io::Read, path::Path};
+use std::{fmt::Debug, io::Read, path::Path};
 use tar::{Archive, EntryType};
 use versatiles_core::{io::*, tilejson::TileJSON, types::*, utils::decompress};
 
@@ -11,7 +11,7 @@ pub struct TarTilesReader {
 	tilejson: TileJSON,
 	name: String,
 	reader: Box<DataReaderFile>,
-	tile_map: HashMap<TileCoord3, ByteRange>,
+	tile_map: TileMap<ByteRange>,
 	parameters: TilesReaderParameters,
 }
 
@@ -24,14 +24,19 @@ impl TarTilesReader {
 	/// # Errors
 	/// Returns an error if the file cannot be opened or read.
 	pub fn open_path(path: &Path) -> Result<TarTilesReader> {
+		TarTilesReader::open_path_with_hints(path, &ReadHints::default())
+	}
+
+	/// Opens the tar archive, but only indexes tiles that might be requested according to `hints`.
+	pub fn open_path_with_hints(path: &Path, hints: &ReadHints) -> Result<TarTilesReader> {
 		let mut reader = DataReaderFile::open(path)?;
 		let mut archive = Archive::new(&mut reader);
 
 		let mut tilejson = TileJSON::default();
-		let mut tile_map = HashMap::new();
+		// metadata can follow the tiles, so the scheme and the hints are applied afterwards
+		let mut tiles: Vec<(TileCoord3, ByteRange)> = Vec::new();
 		let mut tile_format: Option<TileFormat> = None;
 		let mut tile_compression: Option<TileCompression> = None;
-		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
 
 		for entry in archive.entries()? {
 			let mut entry = entry?;
@@ -66,24 +71,28 @@ impl TarTilesReader {
 
 				let x = filename.parse::<u32>()?;
 
-				if tile_format.is_none() {
+				let coord3 = TileCoord3::new(x, y, z)?;
+
+				if let Some(format) = &tile_format {
+					if format != &this_format {
+						bail!("unknown filename {path_tmp_string:?}, can't detect format");
+					}
+				} else {
 					tile_format = Some(this_format);
-				} else if tile_format.as_ref().unwrap() != &this_format {
-					bail!("unknown filename {path_tmp_string:?}, can't detect format");
 				}
 
-				if tile_compression.is_none() {
+				if let Some(compression) = &tile_compression {
+					if compression != &this_compression {
+						bail!("unknown filename {path_tmp_string:?}, can't detect compression");
+					}
+				} else {
 					tile_compression = Some(this_compression);
-				} else if tile_compression.as_ref().unwrap() != &this_compression {
-					bail!("unknown filename {path_tmp_string:?}, can't detect compression");
 				}
 
 				let offset = entry.raw_file_position();
 				let length = entry.size();
 
-				let coord3 = TileCoord3::new(x, y, z)?;
-				bbox_pyramid.include_coord(&coord3);
-				tile_map.insert(coord3, ByteRange { offset, length });
+				tiles.push((coord3, ByteRange { offset, length }));
 				continue;
 			}
 
@@ -95,18 +104,18 @@ impl TarTilesReader {
 
 			if path_vec.len() == 1 {
 				match path_vec[0] {
-					"meta.json" | "tiles.json" | "metadata.json" => {
+					"meta.json" | "tiles.json" | "metadata.json" | "tilejson.json" => {
 						tilejson.merge(&TileJSON::try_from(&read_to_end())?)?;
 						continue;
 					}
-					"meta.json.gz" | "tiles.json.gz" | "metadata.json.gz" => {
+					"meta.json.gz" | "tiles.json.gz" | "metadata.json.gz" | "tilejson.json.gz" => {
 						tilejson.merge(&TileJSON::try_from(&decompress(
 							read_to_end(),
 							&TileCompression::Gzip,
 						)?)?)?;
 						continue;
 					}
-					"meta.json.br" | "tiles.json.br" | "metadata.json.br" => {
+					"meta.json.br" | "tiles.json.br" | "metadata.json.br" | "tilejson.json.br" => {
 						tilejson.merge(&TileJSON::try_from(&decompress(
 							read_to_end(),
 							&TileCompression::Brotli,
@@ -120,10 +129,27 @@ impl TarTilesReader {
 			log::warn!("unknown file in tar: {path_tmp_string:?}");
 		}
 
+		let tile_format = tile_format.context("no tiles found")?;
+		let tile_compression = tile_compression.context("no tiles found")?;
+
+		let scheme = tilejson.get_scheme()?;
+		tilejson.set_scheme(TileScheme::Xyz);
+
+		let mut tile_map = TileMap::new();
+		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
+		for (mut coord3, range) in tiles {
+			scheme.apply(&mut coord3);
+			if hints.contains_coord(&coord3) {
+				bbox_pyramid.include_coord(&coord3);
+				tile_map.insert(&coord3, range);
+			}
+		}
+		ensure!(!tile_map.is_empty(), "no tiles found");
+
 		Ok(TarTilesReader {
 			tilejson,
 			name: path.to_str().unwrap().to_string(),
-			parameters: TilesReaderParameters::new(tile_format.unwrap(), tile_compression.unwrap(), bbox_pyramid),
+			parameters: TilesReaderParameters::new(tile_format, tile_compression, bbox_pyramid),
 			reader,
 			tile_map,
 		})
@@ -158,6 +184,31 @@ impl TilesReaderTrait for TarTilesReader {
 		&self.tilejson
 	}
 
+	/// Drops all tiles from the index that will not be requested.
+	fn set_read_hints(&mut self, hints: &ReadHints) {
+		self.tile_map.retain(|coord, _| hints.contains_coord(coord));
+
+		// parameters and TileJSON must not advertise the removed tiles
+		let mut bbox_pyramid = TileBBoxPyramid::new_empty();
+		for coord in self.tile_map.coords() {
+			bbox_pyramid.include_coord(&coord);
+		}
+		self.tilejson.update_from_pyramid(&bbox_pyramid);
+		self.parameters.bbox_pyramid = bbox_pyramid;
+	}
+
+	/// Counts the tiles inside `bbox` using the index of the archive.
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		let (mut count, mut bytes) = (0, 0);
+		for (coord, range) in self.tile_map.iter() {
+			if bbox.contains3(&coord) {
+				count += 1;
+				bytes += range.length;
+			}
+		}
+		Ok(Some((count, bytes)))
+	}
+
 	/// Returns the tile data for the specified coordinates as a `Blob`.
 	///
 	/// # Arguments
@@ -224,6 +275,26 @@ pub mod tests {
 		Ok(())
 	}
 
+	#[tokio::test]
+	async fn reader_with_hints() -> Result<()> {
+		let temp_file = make_test_file(TileFormat::PBF, TileCompression::Gzip, 3, "tar").await?;
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(2, 1, 1, 2, 2)?);
+		let reader = TarTilesReader::open_path_with_hints(&temp_file, &ReadHints::from_bbox_pyramid(pyramid))?;
+
+		assert_eq!(reader.get_parameters().bbox_pyramid.to_string(), "[2: [1,1,2,2] (4)]");
+		assert_eq!(reader.tile_map.len(), 4);
+		assert!(reader.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.is_none());
+
+		let error =
+			TarTilesReader::open_path_with_hints(&temp_file, &ReadHints::from_bbox_pyramid(TileBBoxPyramid::new_empty()))
+				.unwrap_err();
+		assert_eq!(error.to_string(), "no tiles found");
+
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn all_compressions() -> Result<()> {
 		async fn test_compression(compression: TileCompression) -> Result<()> {
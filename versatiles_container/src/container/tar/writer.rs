@@ -1,6 +1,6 @@
 //! Provides functionality for writing tile data to a tar archive.
 
-use crate::TilesWriterTrait;
+use crate::{TilesWriterTrait, WRITE_QUEUE_SIZE};
 use anyhow::{bail, Result};
 use async_trait::async_trait;
 use std::{
@@ -8,22 +8,68 @@ use std::{
 	path::{Path, PathBuf},
 };
 use tar::{Builder, Header};
-use versatiles_core::{io::DataWriterTrait, progress::get_progress_bar, types::TilesReaderTrait, utils::compress};
+use versatiles_core::{
+	io::DataWriterTrait,
+	progress::get_progress_bar,
+	types::{TileCoord3, TilesReaderTrait},
+	utils::compress,
+};
+
+/// Directory layout of the tiles inside the tar archive.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TarLayout {
+	/// `{z}/{y}/{x}.ext`, as read by `TarTilesReader`
+	#[default]
+	ZYX,
+	/// `{z}/{x}/{y}.ext`, the layout of XYZ tile URLs, expected by most other tools
+	ZXY,
+}
+
+impl TarLayout {
+	fn tile_path(&self, coord: &TileCoord3, extension: &str) -> String {
+		match self {
+			TarLayout::ZYX => format!("./{}/{}/{}{extension}", coord.z, coord.y, coord.x),
+			TarLayout::ZXY => format!("./{}/{}/{}{extension}", coord.z, coord.x, coord.y),
+		}
+	}
+}
+
+/// Options of `TarTilesWriter::write_with_options`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TarWriterOptions {
+	/// Directory layout of the tiles.
+	pub layout: TarLayout,
+	/// Name of the file containing the TileJSON, e.g. "metadata.json" or "tilejson.json".
+	/// `None` writes no metadata file.
+	pub metadata_filename: Option<String>,
+	/// Appends the extension of the tile compression to all filenames, e.g. ".gz" for gzip.
+	/// Some tools expect compressed tiles without this suffix, e.g. `0/0/0.pbf`.
+	pub compression_suffix: bool,
+}
+
+impl Default for TarWriterOptions {
+	fn default() -> Self {
+		TarWriterOptions {
+			layout: TarLayout::default(),
+			metadata_filename: Some(String::from("tiles.json")),
+			compression_suffix: true,
+		}
+	}
+}
 
 /// A struct that provides functionality to write tile data to a tar archive.
 pub struct TarTilesWriter {}
 
-#[async_trait]
-impl TilesWriterTrait for TarTilesWriter {
-	/// Writes the tile data from the `TilesReader` to a tar archive at the specified path.
-	///
-	/// # Arguments
-	/// * `reader` - The `TilesReader` instance containing the tile data.
-	/// * `path` - The path to the output tar archive file.
+impl TarTilesWriter {
+	/// Writes the tile data from the `TilesReader` to a tar archive, using the given layout and filenames.
 	///
 	/// # Errors
 	/// Returns an error if there is an issue creating the tar archive or writing the data.
-	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+	pub async fn write_with_options(
+		reader: &mut dyn TilesReaderTrait,
+		path: &Path,
+		options: &TarWriterOptions,
+	) -> Result<()> {
 		let file = File::create(path)?;
 		let mut builder = Builder::new(file);
 
@@ -31,39 +77,46 @@ impl TilesWriterTrait for TarTilesWriter {
 		let tile_format = &parameters.tile_format.clone();
 		let tile_compression = &parameters.tile_compression.clone();
 		let bbox_pyramid = reader.get_parameters().bbox_pyramid.clone();
+		let scheme = reader.get_tilejson().get_scheme()?;
 
-		let extension_format = tile_format.extension();
-		let extension_compression = tile_compression.extension();
+		let extension_compression = if options.compression_suffix {
+			tile_compression.extension()
+		} else {
+			""
+		};
+		let extension = format!("{}{extension_compression}", tile_format.extension());
 
-		let meta_data = compress(reader.get_tilejson().into(), tile_compression)?;
-		let filename = format!("tiles.json{}", extension_compression);
-		let mut header = Header::new_gnu();
-		header.set_size(meta_data.len() as u64);
-		header.set_mode(0o644);
-		builder.append_data(&mut header, Path::new(&filename), meta_data.as_slice())?;
+		if let Some(metadata_filename) = &options.metadata_filename {
+			let meta_data = compress(reader.get_tilejson().into(), tile_compression)?;
+			let filename = format!("{metadata_filename}{extension_compression}");
+			let mut header = Header::new_gnu();
+			header.set_size(meta_data.len() as u64);
+			header.set_mode(0o644);
+			builder.append_data(&mut header, Path::new(&filename), meta_data.as_slice())?;
+		}
 
 		let mut progress = get_progress_bar("converting tiles", bbox_pyramid.count_tiles());
 
 		for bbox in bbox_pyramid.iter_levels() {
-			let mut stream = reader.get_bbox_tile_stream(bbox.clone()).await;
+			let stream = reader.get_bbox_tile_stream(bbox.clone()).await;
 
-			while let Some((coord, blob)) = stream.next().await {
-				progress.inc(1);
+			stream
+				.for_each_queued(WRITE_QUEUE_SIZE, |(mut coord, blob)| {
+					progress.inc(1);
+					scheme.apply(&mut coord);
 
-				let filename = format!(
-					"./{}/{}/{}{}{}",
-					coord.z, coord.y, coord.x, extension_format, extension_compression
-				);
-				let path = PathBuf::from(&filename);
+					let path = PathBuf::from(options.layout.tile_path(&coord, &extension));
 
-				// Build header
-				let mut header = Header::new_gnu();
-				header.set_size(blob.len());
-				header.set_mode(0o644);
+					// Build header
+					let mut header = Header::new_gnu();
+					header.set_size(blob.len());
+					header.set_mode(0o644);
 
-				// Write blob to file
-				builder.append_data(&mut header, path, blob.as_slice())?;
-			}
+					// Write blob to file
+					builder.append_data(&mut header, path, blob.as_slice())?;
+					Ok(())
+				})
+				.await?;
 		}
 
 		progress.finish();
@@ -71,6 +124,22 @@ impl TilesWriterTrait for TarTilesWriter {
 
 		Ok(())
 	}
+}
+
+#[async_trait]
+impl TilesWriterTrait for TarTilesWriter {
+	/// Writes the tile data from the `TilesReader` to a tar archive at the specified path,
+	/// using the default `TarWriterOptions`.
+	///
+	/// # Arguments
+	/// * `reader` - The `TilesReader` instance containing the tile data.
+	/// * `path` - The path to the output tar archive file.
+	///
+	/// # Errors
+	/// Returns an error if there is an issue creating the tar archive or writing the data.
+	async fn write_to_path(reader: &mut dyn TilesReaderTrait, path: &Path) -> Result<()> {
+		Self::write_with_options(reader, path, &TarWriterOptions::default()).await
+	}
 
 	/// Writes the tile data from the `TilesReader` to the specified `DataWriterTrait`.
 	///
@@ -88,17 +157,17 @@ impl TilesWriterTrait for TarTilesWriter {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::{MockTilesReader, MockTilesWriter, TarTilesReader};
+	use crate::{MockTilesReader, MockTilesWriter, TarTilesReader, TilesConvertReader, TilesConverterParameters};
 	use assert_fs::NamedTempFile;
 	use versatiles_core::types::*;
 
 	#[tokio::test]
 	async fn read_write() -> Result<()> {
-		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters {
-			bbox_pyramid: TileBBoxPyramid::new_full(4),
-			tile_compression: TileCompression::Gzip,
-			tile_format: TileFormat::PBF,
-		})?;
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(4),
+		))?;
 
 		let temp_path = NamedTempFile::new("test_output.tar")?;
 		TarTilesWriter::write_to_path(&mut mock_reader, &temp_path).await?;
@@ -111,11 +180,11 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_meta_data() -> Result<()> {
-		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters {
-			bbox_pyramid: TileBBoxPyramid::new_full(1),
-			tile_compression: TileCompression::Uncompressed,
-			tile_format: TileFormat::JSON,
-		})?;
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::JSON,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(1),
+		))?;
 
 		let temp_path = NamedTempFile::new("test_meta_output.tar")?;
 		TarTilesWriter::write_to_path(&mut mock_reader, &temp_path).await?;
@@ -128,4 +197,70 @@ mod tests {
 
 		Ok(())
 	}
+
+	fn list_entries(path: &Path) -> Result<Vec<String>> {
+		let mut archive = tar::Archive::new(File::open(path)?);
+		let mut names = Vec::new();
+		for entry in archive.entries()? {
+			names.push(entry?.path()?.to_string_lossy().to_string());
+		}
+		Ok(names)
+	}
+
+	#[tokio::test]
+	async fn test_options() -> Result<()> {
+		let mut mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::from_geo_bbox(1, 1, &GeoBBox(-180.0, -80.0, -1.0, -1.0)),
+		))?;
+		let temp_path = NamedTempFile::new("test_options.tar")?;
+
+		TarTilesWriter::write_to_path(&mut mock_reader, &temp_path).await?;
+		assert_eq!(list_entries(&temp_path)?, ["tiles.json.gz", "1/1/0.pbf.gz"]);
+
+		let options = TarWriterOptions {
+			layout: TarLayout::ZXY,
+			metadata_filename: Some(String::from("metadata.json")),
+			compression_suffix: false,
+		};
+		TarTilesWriter::write_with_options(&mut mock_reader, &temp_path, &options).await?;
+		assert_eq!(list_entries(&temp_path)?, ["metadata.json", "1/0/1.pbf"]);
+
+		let options = TarWriterOptions {
+			metadata_filename: None,
+			..TarWriterOptions::default()
+		};
+		TarTilesWriter::write_with_options(&mut mock_reader, &temp_path, &options).await?;
+		assert_eq!(list_entries(&temp_path)?, ["1/1/0.pbf.gz"]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_tms_scheme() -> Result<()> {
+		let mock_reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::from_geo_bbox(1, 1, &GeoBBox(-180.0, -80.0, -1.0, -1.0)),
+		))?;
+		let bbox_pyramid = mock_reader.get_parameters().bbox_pyramid.clone();
+
+		let mut cp = TilesConverterParameters::new_default();
+		cp.tile_scheme = TileScheme::Tms;
+		let mut converter = TilesConvertReader::new_from_reader(mock_reader.boxed(), cp)?;
+
+		let temp_path = NamedTempFile::new("test_tms.tar")?;
+		TarTilesWriter::write_to_path(&mut converter, &temp_path).await?;
+		// rows are counted from the south
+		assert_eq!(list_entries(&temp_path)?, ["tiles.json.gz", "1/0/0.pbf.gz"]);
+
+		// flipped back exactly once while reading
+		let reader = TarTilesReader::open_path(&temp_path)?;
+		assert_eq!(reader.get_parameters().bbox_pyramid, bbox_pyramid);
+		assert_eq!(reader.get_tilejson().get_scheme()?, TileScheme::Xyz);
+		assert!(reader.get_tile_data(&TileCoord3::new(0, 1, 1)?).await?.is_some());
+
+		Ok(())
+	}
 }
@@ -0,0 +1,85 @@
+//! Analysis of the content of single tiles, e.g. to skip empty tiles or to find tiles that hide all tiles below them.
+//!
+//! All functions expect uncompressed tiles.
+//!
+//! ```
+//! use versatiles_container::is_blank_tile;
+//! use versatiles_core::types::{Blob, TileFormat};
+//!
+//! assert!(is_blank_tile(&Blob::new_empty(), TileFormat::PBF).unwrap());
+//! ```
+
+use anyhow::{Context, Result};
+use versatiles_core::types::{Blob, TileFormat};
+use versatiles_geometry::vector_tile::VectorTile;
+use versatiles_image::helper::{blob2image, is_image_opaque, is_image_transparent};
+
+/// Returns `true` if the tile shows nothing: raster tiles are fully transparent, vector tiles have no features,
+/// and tiles of other formats are empty.
+pub fn is_blank_tile(blob: &Blob, format: TileFormat) -> Result<bool> {
+	Ok(match format {
+		TileFormat::PBF => VectorTile::from_blob(blob)
+			.context("Failed to create VectorTile from Blob")?
+			.is_featureless(),
+		TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP => is_image_transparent(&blob2image(blob, format)?),
+		_ => blob.is_empty(),
+	})
+}
+
+/// Returns `true` if the tile is a raster tile without any transparent pixel, so it hides all tiles below it.
+/// Tiles of other formats are never opaque.
+pub fn is_opaque_tile(blob: &Blob, format: TileFormat) -> Result<bool> {
+	Ok(match format {
+		TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP => is_image_opaque(&blob2image(blob, format)?),
+		_ => false,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::{DynamicImage, RgbaImage};
+	use versatiles_geometry::vector_tile::VectorTileLayer;
+	use versatiles_image::helper::{create_image_rgb, create_image_rgba, image2blob};
+
+	fn png(image: &DynamicImage) -> Blob {
+		image2blob(image, TileFormat::PNG).unwrap()
+	}
+
+	#[test]
+	fn raster_tiles() -> Result<()> {
+		let transparent = png(&DynamicImage::ImageRgba8(RgbaImage::new(8, 8)));
+		let translucent = png(&create_image_rgba());
+		let opaque = png(&create_image_rgb());
+
+		assert!(is_blank_tile(&transparent, TileFormat::PNG)?);
+		assert!(!is_blank_tile(&translucent, TileFormat::PNG)?);
+		assert!(!is_blank_tile(&opaque, TileFormat::PNG)?);
+
+		assert!(!is_opaque_tile(&transparent, TileFormat::PNG)?);
+		assert!(!is_opaque_tile(&translucent, TileFormat::PNG)?);
+		assert!(is_opaque_tile(&opaque, TileFormat::PNG)?);
+
+		assert!(is_blank_tile(&Blob::from("garbage"), TileFormat::PNG).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn vector_tiles() -> Result<()> {
+		let empty = VectorTile::new(vec![VectorTileLayer::new_standard("empty")]).to_blob()?;
+		assert!(is_blank_tile(&empty, TileFormat::PBF)?);
+		assert!(!is_opaque_tile(&empty, TileFormat::PBF)?);
+
+		let pbf = Blob::from(std::fs::read("../testdata/shortbread-tile.pbf")?);
+		assert!(!is_blank_tile(&pbf, TileFormat::PBF)?);
+		Ok(())
+	}
+
+	#[test]
+	fn other_tiles() -> Result<()> {
+		assert!(is_blank_tile(&Blob::new_empty(), TileFormat::JSON)?);
+		assert!(!is_blank_tile(&Blob::from("{}"), TileFormat::JSON)?);
+		assert!(!is_opaque_tile(&Blob::from("{}"), TileFormat::JSON)?);
+		Ok(())
+	}
+}
@@ -5,18 +5,35 @@ use std::{
 	sync::Arc,
 };
 use versatiles_core::{types::*, utils::*};
+use versatiles_image::helper::{blob2image, image2blob_quality};
 
 #[derive(Clone, Debug)]
 enum FnConv {
 	UnGzip,
 	UnBrotli,
-	Gzip,
-	Brotli,
+	UnZstd,
+	/// gzip with an optional compression level
+	Gzip(Option<u32>),
+	/// brotli with an optional quality
+	Brotli(Option<u32>),
+	/// zstd with an optional compression level
+	Zstd(Option<u32>),
+	/// decodes an image and encodes it again with a quality
+	Reencode(TileFormat, u8),
 }
 
 impl fmt::Display for FnConv {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "{:?}", self)
+		match self {
+			FnConv::Gzip(Some(level)) => write!(f, "Gzip:{level}"),
+			FnConv::Brotli(Some(level)) => write!(f, "Brotli:{level}"),
+			FnConv::Zstd(Some(level)) => write!(f, "Zstd:{level}"),
+			FnConv::Gzip(None) => write!(f, "Gzip"),
+			FnConv::Brotli(None) => write!(f, "Brotli"),
+			FnConv::Zstd(None) => write!(f, "Zstd"),
+			FnConv::Reencode(format, quality) => write!(f, "{}:{quality}", format.as_str()),
+			_ => write!(f, "{:?}", self),
+		}
 	}
 }
 
@@ -27,8 +44,14 @@ impl FnConv {
 		match self {
 			FnConv::UnGzip => decompress_gzip(&blob),
 			FnConv::UnBrotli => decompress_brotli(&blob),
-			FnConv::Gzip => compress_gzip(&blob),
-			FnConv::Brotli => compress_brotli(&blob),
+			FnConv::UnZstd => decompress_zstd(&blob),
+			FnConv::Gzip(None) => compress_gzip(&blob),
+			FnConv::Gzip(Some(level)) => compress_gzip_level(&blob, *level),
+			FnConv::Brotli(None) => compress_brotli(&blob),
+			FnConv::Brotli(Some(quality)) => compress_brotli_quality(&blob, *quality),
+			FnConv::Zstd(None) => compress_zstd(&blob),
+			FnConv::Zstd(Some(level)) => compress_zstd_level(&blob, *level),
+			FnConv::Reencode(format, quality) => image2blob_quality(&blob2image(&blob, *format)?, *format, Some(*quality)),
 		}
 	}
 }
@@ -61,6 +84,18 @@ impl TileConverter {
 		dst_comp: &TileCompression,
 		force_recompress: bool,
 	) -> Result<TileConverter> {
+		TileConverter::new_tile_recompressor_with_level(src_comp, dst_comp, force_recompress, None)
+	}
+
+	/// Like `new_tile_recompressor`, but compresses with a specific compression level.
+	/// If a level is given, tiles are always recompressed, unless `dst_comp` is uncompressed.
+	pub fn new_tile_recompressor_with_level(
+		src_comp: &TileCompression,
+		dst_comp: &TileCompression,
+		force_recompress: bool,
+		level: Option<u32>,
+	) -> Result<TileConverter> {
+		let force_recompress = force_recompress || level.is_some();
 		let mut converter = TileConverter::new_empty();
 
 		// Push the necessary conversion functions to the converter pipeline.
@@ -70,19 +105,41 @@ impl TileConverter {
 				Uncompressed => {}
 				Gzip => converter.push(FnConv::UnGzip),
 				Brotli => converter.push(FnConv::UnBrotli),
+				Zstd => converter.push(FnConv::UnZstd),
 			}
 			match dst_comp {
 				Uncompressed => {}
-				Gzip => converter.push(FnConv::Gzip),
-				Brotli => converter.push(FnConv::Brotli),
+				Gzip => converter.push(FnConv::Gzip(level)),
+				Brotli => converter.push(FnConv::Brotli(level)),
+				Zstd => converter.push(FnConv::Zstd(level)),
 			}
 		};
 
 		Ok(converter)
 	}
 
+	/// Creates a new `DataConverter` that decompresses images, encodes them again with `quality`
+	/// and compresses them with an optional compression level.
+	pub fn new_tile_reencoder(
+		src_comp: &TileCompression,
+		dst_comp: &TileCompression,
+		format: TileFormat,
+		quality: u8,
+		level: Option<u32>,
+	) -> TileConverter {
+		let mut converter = TileConverter::new_decompressor(src_comp);
+		converter.push(FnConv::Reencode(format, quality));
+		match dst_comp {
+			TileCompression::Uncompressed => {}
+			TileCompression::Gzip => converter.push(FnConv::Gzip(level)),
+			TileCompression::Brotli => converter.push(FnConv::Brotli(level)),
+			TileCompression::Zstd => converter.push(FnConv::Zstd(level)),
+		}
+		converter
+	}
+
 	/// Constructs a new `DataConverter` instance that decompresses data using the specified compression algorithm.
-	/// The `src_comp` parameter specifies the compression algorithm to use: `Compression::Uncompressed`, `Compression::Gzip`, or `Compression::Brotli`.
+	/// The `src_comp` parameter specifies the compression algorithm to use: `Compression::Uncompressed`, `Compression::Gzip`, `Compression::Brotli` or `Compression::Zstd`.
 	pub fn new_decompressor(src_comp: &TileCompression) -> TileConverter {
 		use TileCompression::*;
 		let mut converter = TileConverter::new_empty();
@@ -94,6 +151,8 @@ impl TileConverter {
 			Gzip => converter.push(FnConv::UnGzip),
 			// If brotli, add the brotli decompression function to the pipeline
 			Brotli => converter.push(FnConv::UnBrotli),
+			// If zstd, add the zstd decompression function to the pipeline
+			Zstd => converter.push(FnConv::UnZstd),
 		}
 
 		converter
@@ -165,6 +224,38 @@ mod tests {
 		assert!(data_converter.is_empty());
 	}
 
+	#[test]
+	fn new_tile_recompressor_with_level() -> Result<()> {
+		use TileCompression::*;
+		let converter = TileConverter::new_tile_recompressor_with_level(&Brotli, &Brotli, false, Some(5))?;
+		assert_eq!(converter.as_string(), "unbrotli,brotli:5");
+		let converter = TileConverter::new_tile_recompressor_with_level(&Uncompressed, &Gzip, false, Some(3))?;
+		assert_eq!(converter.as_string(), "gzip:3");
+
+		let blob = Blob::from("tile ".repeat(100));
+		assert_eq!(decompress_gzip(&converter.process_blob(blob.clone())?)?, blob);
+		Ok(())
+	}
+
+	#[test]
+	fn new_tile_reencoder() -> Result<()> {
+		use versatiles_image::helper::{create_image_rgb, image2blob};
+		let converter = TileConverter::new_tile_reencoder(
+			&TileCompression::Gzip,
+			&TileCompression::Uncompressed,
+			TileFormat::WEBP,
+			30,
+			None,
+		);
+		assert_eq!(converter.as_string(), "ungzip,webp:30");
+
+		let blob = image2blob(&create_image_rgb(), TileFormat::WEBP)?;
+		let result = converter.process_blob(compress_gzip(&blob)?)?;
+		assert!(result.len() < blob.len());
+		assert_eq!(blob2image(&result, TileFormat::WEBP)?.width(), 256);
+		Ok(())
+	}
+
 	#[test]
 	fn new_tile_recompressor() {
 		fn test(
@@ -194,7 +285,7 @@ mod tests {
 		}
 
 		use TileCompression::*;
-		let compressions = vec![Uncompressed, Gzip, Brotli];
+		let compressions = vec![Uncompressed, Gzip, Brotli, Zstd];
 		let forcing = vec![false, true];
 
 		for c_in in &compressions {
@@ -205,6 +296,7 @@ mod tests {
 					if !force {
 						s = s.replace("ungzip,gzip", "");
 						s = s.replace("unbrotli,brotli", "");
+						s = s.replace("unzstd,zstd", "");
 					}
 					s = s.replace(",,", ",");
 					s = s.strip_prefix(',').unwrap_or(&s).to_string();
@@ -225,6 +317,7 @@ mod tests {
 				Uncompressed => "",
 				Gzip => "ungzip",
 				Brotli => "unbrotli",
+				Zstd => "unzstd",
 			}
 		}
 
@@ -233,6 +326,7 @@ mod tests {
 				Uncompressed => "",
 				Gzip => "gzip",
 				Brotli => "brotli",
+				Zstd => "zstd",
 			}
 		}
 	}
@@ -0,0 +1,264 @@
+//! Statistics of all tiles of a container, e.g. to find the zoom levels or layers that bloat a tileset.
+//!
+//! Every tile is read and decompressed. Per zoom level, the sizes, the compression ratio and the number of
+//! empty tiles are counted. Vector tiles are decoded to count features and vertices per layer.
+//!
+//! ```no_run
+//! use versatiles_container::{get_reader, scan_tiles};
+//! use anyhow::Result;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let reader = get_reader("berlin.mbtiles").await?;
+//!     let statistics = scan_tiles(&*reader).await?;
+//!     for level in statistics.levels.iter() {
+//!         println!("z{}: {:.0} bytes per tile", level.level, level.get_size_avg());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use versatiles_core::{
+	progress::get_progress_bar,
+	types::{Blob, TileCompression, TileFormat, TilesReaderTrait},
+	utils::decompress,
+};
+use versatiles_geometry::vector_tile::VectorTile;
+
+use super::is_blank_tile;
+
+/// Statistics of the tiles of one zoom level.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelStatistics {
+	pub level: u8,
+	pub tile_count: u64,
+	/// tiles without features or with only transparent pixels
+	pub empty_count: u64,
+	/// size of the smallest tile in bytes
+	pub size_min: u64,
+	/// size of the biggest tile in bytes
+	pub size_max: u64,
+	/// sum of the tile sizes in bytes
+	pub size_sum: u64,
+	/// sum of the tile sizes in bytes after decompression
+	pub uncompressed_sum: u64,
+}
+
+impl LevelStatistics {
+	/// Returns the average tile size in bytes.
+	pub fn get_size_avg(&self) -> f64 {
+		if self.tile_count == 0 {
+			return 0.0;
+		}
+		self.size_sum as f64 / self.tile_count as f64
+	}
+
+	/// Returns the compressed size relative to the uncompressed size, e.g. `0.25` if compression saves 75%.
+	pub fn get_compression_ratio(&self) -> f64 {
+		if self.uncompressed_sum == 0 {
+			return 1.0;
+		}
+		self.size_sum as f64 / self.uncompressed_sum as f64
+	}
+
+	fn add(&mut self, size: u64, tile: &TileSummary) {
+		if self.tile_count == 0 || size < self.size_min {
+			self.size_min = size;
+		}
+		self.size_max = self.size_max.max(size);
+		self.tile_count += 1;
+		self.size_sum += size;
+		self.uncompressed_sum += tile.uncompressed_size;
+		if tile.is_empty {
+			self.empty_count += 1;
+		}
+	}
+}
+
+/// Statistics of one layer of vector tiles.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayerStatistics {
+	/// tiles containing this layer
+	pub tile_count: u64,
+	pub feature_count: u64,
+	/// points of all geometries, see `VectorTileFeature::count_vertices`
+	pub vertex_count: u64,
+}
+
+/// Statistics of all tiles, see [`scan_tiles`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileStatistics {
+	/// one entry per zoom level that contains tiles, ordered by level
+	pub levels: Vec<LevelStatistics>,
+	/// layers of vector tiles by name, empty for raster tiles
+	pub layers: BTreeMap<String, LayerStatistics>,
+}
+
+/// What is known about a single tile after decoding it.
+struct TileSummary {
+	uncompressed_size: u64,
+	is_empty: bool,
+	/// name, feature count and vertex count of every layer
+	layers: Vec<(String, u64, u64)>,
+}
+
+impl TileSummary {
+	fn new(blob: Blob, tile_format: TileFormat, tile_compression: TileCompression) -> Result<TileSummary> {
+		let blob = decompress(blob, &tile_compression)?;
+		let mut summary = TileSummary {
+			uncompressed_size: blob.len(),
+			is_empty: false,
+			layers: Vec::new(),
+		};
+		match tile_format {
+			TileFormat::PBF => {
+				let tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+				summary.is_empty = tile.is_featureless();
+				for layer in tile.layers {
+					let mut vertex_count = 0;
+					for feature in layer.features.iter() {
+						vertex_count += feature.count_vertices()?;
+					}
+					summary
+						.layers
+						.push((layer.name, layer.features.len() as u64, vertex_count));
+				}
+			}
+			_ => summary.is_empty = is_blank_tile(&blob, tile_format)?,
+		}
+		Ok(summary)
+	}
+}
+
+/// Reads every tile of `reader` and collects statistics per zoom level and, for vector tiles, per layer.
+/// The tiles are decoded in parallel.
+pub async fn scan_tiles(reader: &dyn TilesReaderTrait) -> Result<TileStatistics> {
+	let parameters = reader.get_parameters();
+	let tile_format = parameters.tile_format;
+	let tile_compression = parameters.tile_compression;
+	let pyramid = &parameters.bbox_pyramid;
+
+	let mut statistics = TileStatistics::default();
+	let mut progress = get_progress_bar("scanning tiles", pyramid.count_tiles());
+
+	for bbox in pyramid.iter_levels() {
+		let mut level = LevelStatistics {
+			level: bbox.level,
+			..Default::default()
+		};
+
+		let mut summaries = reader
+			.get_bbox_tile_stream(bbox.clone())
+			.await
+			.stream
+			.map(|(coord, blob)| {
+				tokio::task::spawn_blocking(move || {
+					let size = blob.len();
+					let summary = TileSummary::new(blob, tile_format, tile_compression)
+						.with_context(|| format!("Failed to scan tile {coord:?}"));
+					(size, summary)
+				})
+			})
+			.buffer_unordered(num_cpus::get());
+
+		while let Some(result) = summaries.next().await {
+			let (size, summary) = result?;
+			let summary = summary?;
+			progress.inc(1);
+			level.add(size, &summary);
+			for (name, feature_count, vertex_count) in summary.layers {
+				let layer = statistics.layers.entry(name).or_default();
+				layer.tile_count += 1;
+				layer.feature_count += feature_count;
+				layer.vertex_count += vertex_count;
+			}
+		}
+
+		if level.tile_count > 0 {
+			statistics.levels.push(level);
+		}
+	}
+
+	progress.finish();
+
+	Ok(statistics)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{get_reader, MockTilesReader};
+	use versatiles_core::types::{TileBBoxPyramid, TilesReaderParameters};
+
+	#[tokio::test]
+	async fn scan_vector_tiles() -> Result<()> {
+		let reader = get_reader("../testdata/berlin.mbtiles").await?;
+		let statistics = scan_tiles(reader.as_ref()).await?;
+
+		assert_eq!(statistics.levels.len(), 15);
+		let level = &statistics.levels[14];
+		assert_eq!(level.level, 14);
+		assert!(level.size_min <= level.size_max);
+		assert!(level.get_size_avg() >= level.size_min as f64);
+		assert!(level.get_size_avg() <= level.size_max as f64);
+		assert!(level.get_compression_ratio() < 1.0);
+
+		let tile_count: u64 = statistics.levels.iter().map(|l| l.tile_count).sum();
+		let buildings = statistics.layers.get("buildings").unwrap();
+		assert!(buildings.tile_count <= tile_count);
+		assert!(buildings.feature_count > buildings.tile_count);
+		assert!(buildings.vertex_count > buildings.feature_count * 3);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn scan_raster_tiles() -> Result<()> {
+		let reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PNG,
+			TileCompression::Uncompressed,
+			TileBBoxPyramid::new_full(2),
+		))?;
+		let statistics = scan_tiles(&reader).await?;
+
+		assert_eq!(
+			statistics.levels.iter().map(|l| l.tile_count).collect::<Vec<u64>>(),
+			[1, 4, 16]
+		);
+		assert_eq!(statistics.levels[2].get_compression_ratio(), 1.0);
+		assert!(statistics.layers.is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn level_statistics() {
+		let mut level = LevelStatistics::default();
+		assert_eq!(level.get_size_avg(), 0.0);
+		assert_eq!(level.get_compression_ratio(), 1.0);
+
+		for (size, uncompressed_size, is_empty) in [(30, 100, false), (10, 60, true), (20, 40, false)] {
+			let summary = TileSummary {
+				uncompressed_size,
+				is_empty,
+				layers: Vec::new(),
+			};
+			level.add(size, &summary);
+		}
+		assert_eq!(
+			level,
+			LevelStatistics {
+				level: 0,
+				tile_count: 3,
+				empty_count: 1,
+				size_min: 10,
+				size_max: 30,
+				size_sum: 60,
+				uncompressed_sum: 200,
+			}
+		);
+		assert_eq!(level.get_size_avg(), 20.0);
+		assert_eq!(level.get_compression_ratio(), 0.3);
+	}
+}
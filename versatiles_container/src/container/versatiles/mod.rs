@@ -40,9 +40,18 @@
 //! ```
 
 mod types;
+pub use types::ChecksumAlgorithm;
 
 mod reader;
-pub use reader::VersaTilesReader;
+pub use reader::{VerifyReport, VersaTilesReader};
 
+// writing needs files and signing keys, which are not available in WebAssembly
+#[cfg(not(target_arch = "wasm32"))]
+mod updater;
+#[cfg(not(target_arch = "wasm32"))]
+pub use updater::{UpdateReport, VersaTilesUpdater};
+
+#[cfg(not(target_arch = "wasm32"))]
 mod writer;
-pub use writer::VersaTilesWriter;
+#[cfg(not(target_arch = "wasm32"))]
+pub use writer::{VersaTilesWriter, VersaTilesWriterOptions};
@@ -45,15 +45,38 @@
 //! }
 //! ```
 
-use super::types::{BlockDefinition, BlockIndex, FileHeader, TileIndex};
-use anyhow::{Context, Result};
+use super::types::{
+	BlockDefinition, BlockIndex, ChecksumAlgorithm, FileHeader, IntegritySection, TileIndex, HEADER_LENGTH,
+};
+use anyhow::{bail, ensure, Context, Result};
 use async_trait::async_trait;
 use futures::{lock::Mutex, stream::StreamExt};
 use log::trace;
-use std::{fmt::Debug, ops::Shr, path::Path, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, ops::Shr, path::Path, sync::Arc};
 #[cfg(feature = "cli")]
 use versatiles_core::utils::PrettyPrint;
-use versatiles_core::{io::*, tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_core::{io::*, progress::get_progress_bar, tilejson::TileJSON, types::*, utils::decompress};
+
+/// Blocks are read in chunks of this size while verifying them.
+const VERIFY_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Result of [`VersaTilesReader::verify`]
+#[derive(Debug)]
+pub struct VerifyReport {
+	pub algorithm: ChecksumAlgorithm,
+	/// Ed25519 public key of the signer, if the file is signed with a valid signature
+	pub signer: Option<Vec<u8>>,
+	pub checked_blocks: usize,
+	pub checked_bytes: u64,
+	/// Descriptions of everything that doesn't match, empty if the file is intact
+	pub errors: Vec<String>,
+}
+
+impl VerifyReport {
+	pub fn is_valid(&self) -> bool {
+		self.errors.is_empty()
+	}
+}
 
 /// `VersaTilesReader` is responsible for reading tile data from a `versatiles` container.
 pub struct VersaTilesReader {
@@ -134,7 +157,7 @@ impl VersaTilesReader {
 	/// # Errors
 	///
 	/// Returns an error if the tile index cannot be retrieved.
-	async fn get_block_tile_index(&self, block: &BlockDefinition) -> Result<Arc<TileIndex>> {
+	pub(super) async fn get_block_tile_index(&self, block: &BlockDefinition) -> Result<Arc<TileIndex>> {
 		let block_coord = block.get_coord3();
 
 		let mut cache = self.tile_index_cache.lock().await;
@@ -144,14 +167,122 @@ impl VersaTilesReader {
 		} else {
 			let blob = self.reader.read_range(block.get_index_range()).await?;
 			let mut tile_index = TileIndex::from_brotli_blob(blob)?;
-			tile_index.add_offset(block.get_tiles_range().offset);
+			if self.header.shared_tiles {
+				tile_index.add_offset_shared(block.get_tiles_range().offset)?;
+			} else {
+				tile_index.add_offset(block.get_tiles_range().offset)?;
+			}
 
-			assert_eq!(tile_index.len(), block.count_tiles() as usize);
+			ensure!(
+				tile_index.len() as u64 == block.count_tiles(),
+				"tile index of block {:?} has {} entries, but the block contains {} tiles",
+				block_coord,
+				tile_index.len(),
+				block.count_tiles()
+			);
 
 			cache.add(*block_coord, Arc::new(tile_index))
 		})
 	}
 
+	/// Returns the definitions of all blocks, e.g. to copy them into another container.
+	pub(super) fn get_block_index(&self) -> &BlockIndex {
+		&self.block_index
+	}
+
+	/// Checks all parts of the file against the checksums written by the writer, and checks the signature.
+	///
+	/// If a `public_key` is given, the file must be signed with the matching private key.
+	/// Returns an error if the file has no checksums. Mismatches are collected in [`VerifyReport::errors`].
+	pub async fn verify(&self, public_key: Option<&[u8]>) -> Result<VerifyReport> {
+		let offset = self.header.blocks_range.offset + self.header.blocks_range.length;
+		let Some(integrity) = IntegritySection::from_reader(&self.reader, offset).await? else {
+			bail!("the file contains no checksums, add them with \"versatiles convert --checksum\"");
+		};
+		let algorithm = integrity.algorithm;
+		let mut errors = Vec::new();
+
+		let signer = match integrity.verify_signature() {
+			Ok(()) => integrity.signature.as_ref().map(|s| s.public_key.clone()),
+			Err(error) => {
+				if integrity.signature.is_some() || public_key.is_some() {
+					errors.push(error.to_string());
+				}
+				None
+			}
+		};
+		if let (Some(expected), Some(signer)) = (public_key, &signer) {
+			if expected != signer.as_slice() {
+				errors.push(format!(
+					"the file is signed by the key {}, not by the expected key",
+					Blob::from(signer.clone()).as_hex()
+				));
+			}
+		}
+
+		let mut checked_bytes = 0;
+		let mut check_range = async |name: &str, range: &ByteRange, expected: &[u8]| -> Result<()> {
+			let blob = self.reader.read_range(range).await?;
+			checked_bytes += range.length;
+			if algorithm.checksum(blob.as_slice()) != expected {
+				errors.push(format!("{name} is corrupted"));
+			}
+			Ok(())
+		};
+		check_range("header", &ByteRange::new(0, HEADER_LENGTH), &integrity.header).await?;
+		check_range("meta data", &self.header.meta_range, &integrity.meta).await?;
+		check_range("block index", &self.header.blocks_range, &integrity.block_index).await?;
+
+		let mut checksums: HashMap<TileCoord3, &Vec<u8>> = integrity
+			.blocks
+			.iter()
+			.map(|(coord, checksum)| (*coord, checksum))
+			.collect();
+		let total_bytes = self.get_index_size() + self.get_tiles_size();
+		let mut progress = get_progress_bar("verifying blocks", total_bytes);
+		for block in self.block_index.iter() {
+			let coord = block.get_coord3();
+			let Some(expected) = checksums.remove(coord) else {
+				errors.push(format!("block {} has no checksum", coord.as_json()));
+				continue;
+			};
+
+			// tiles and tile index of a block are stored consecutively
+			let start = block.get_tiles_range().offset;
+			let end = start + block.get_tiles_range().length + block.get_index_range().length;
+			let mut hasher = algorithm.hasher();
+			let mut position = start;
+			while position < end {
+				let length = (end - position).min(VERIFY_CHUNK_SIZE);
+				hasher.update(
+					self
+						.reader
+						.read_range(&ByteRange::new(position, length))
+						.await?
+						.as_slice(),
+				);
+				position += length;
+				progress.inc(length);
+			}
+			checked_bytes += end - start;
+			if &hasher.finish() != expected {
+				errors.push(format!("block {} is corrupted", coord.as_json()));
+			}
+		}
+		progress.finish();
+		for coord in checksums.keys() {
+			errors.push(format!("block {} is missing", coord.as_json()));
+		}
+
+		Ok(VerifyReport {
+			algorithm,
+			signer,
+			checked_blocks: self.block_index.len(),
+			checked_bytes,
+			errors,
+		})
+	}
+
 	/// Retrieves the size of the index.
 	fn get_index_size(&self) -> u64 {
 		self.block_index.iter().map(|b| b.get_index_range().length).sum()
@@ -187,6 +318,25 @@ impl TilesReaderTrait for VersaTilesReader {
 		self.parameters.tile_compression = tile_compression;
 	}
 
+	/// Counts the tiles inside `bbox` using the tile indexes of the overlapping blocks.
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		let (mut count, mut bytes) = (0, 0);
+		for block in self.block_index.iter() {
+			let block_bbox = block.get_global_bbox();
+			if block_bbox.level != bbox.level || !block_bbox.overlaps_bbox(bbox)? {
+				continue;
+			}
+			let tile_index = self.get_block_tile_index(block).await?;
+			for (index, range) in tile_index.iter().enumerate() {
+				if range.length > 0 && bbox.contains3(&block_bbox.get_coord3_by_index(index as u32)?) {
+					count += 1;
+					bytes += range.length;
+				}
+			}
+		}
+		Ok(Some((count, bytes)))
+	}
+
 	/// Gets tile data for a given coordinate.
 	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
 		// Calculate block coordinate
@@ -356,7 +506,7 @@ impl TilesReaderTrait for VersaTilesReader {
 								let end = start + range.length;
 								let tile_range = (start as usize)..(end as usize);
 
-								let blob = Blob::from(big_blob.get_range(tile_range));
+								let blob = big_blob.slice(tile_range);
 
 								assert!(bbox.contains3(&coord), "outer_bbox {bbox:?} does not contain {coord:?}");
 
@@ -396,6 +546,7 @@ impl TilesReaderTrait for VersaTilesReader {
 	// deep probe of container tiles
 	#[cfg(feature = "cli")]
 	async fn probe_tiles(&mut self, print: &PrettyPrint) -> Result<()> {
+		use futures::stream;
 		use versatiles_core::progress::get_progress_bar;
 
 		#[derive(Debug)]
@@ -415,8 +566,19 @@ impl TilesReaderTrait for VersaTilesReader {
 		let block_index = self.block_index.clone();
 		let mut progress = get_progress_bar("scanning blocks", block_index.len() as u64);
 
-		for block in block_index.iter() {
-			let tile_index = self.get_block_tile_index(block).await?;
+		// read the tile indexes of several blocks at once and decompress them on blocking threads
+		async fn read_tile_index(reader: &DataReader, block: BlockDefinition) -> Result<(BlockDefinition, TileIndex)> {
+			let blob = reader.read_range(block.get_index_range()).await?;
+			let tile_index = tokio::task::spawn_blocking(move || TileIndex::from_brotli_blob(blob)).await??;
+			Ok((block, tile_index))
+		}
+		let blocks: Vec<BlockDefinition> = block_index.iter().cloned().collect();
+		let mut tile_indexes = stream::iter(blocks)
+			.map(|block| read_tile_index(&self.reader, block))
+			.buffer_unordered(num_cpus::get());
+
+		while let Some(result) = tile_indexes.next().await {
+			let (block, tile_index) = result?;
 			for (index, tile_range) in tile_index.iter().enumerate() {
 				let size = tile_range.length;
 
@@ -436,7 +598,8 @@ impl TilesReaderTrait for VersaTilesReader {
 					y: coord.y,
 					z: coord.z,
 				});
-				biggest_tiles.sort_by(|a, b| b.size.cmp(&a.size));
+				// blocks are scanned in any order, so sort ties by coordinates
+				biggest_tiles.sort_by_key(|t| (std::cmp::Reverse(t.size), t.z, t.y, t.x));
 				while biggest_tiles.len() > 10 {
 					biggest_tiles.pop();
 				}
@@ -480,7 +643,9 @@ impl PartialEq for VersaTilesReader {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::{make_test_file, MockTilesReader, TilesWriterTrait, VersaTilesWriter, MOCK_BYTES_PBF};
+	use crate::{
+		make_test_file, MockTilesReader, TilesWriterTrait, VersaTilesWriter, VersaTilesWriterOptions, MOCK_BYTES_PBF,
+	};
 	use versatiles_core::{assert_wildcard, io::DataWriterBlob, utils::decompress_gzip};
 
 	#[tokio::test]
@@ -531,6 +696,195 @@ mod tests {
 		Ok(())
 	}
 
+	async fn write_with_options(options: &VersaTilesWriterOptions) -> Result<Vec<u8>> {
+		let mut reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+			TileFormat::PBF,
+			TileCompression::Gzip,
+			TileBBoxPyramid::new_full(4),
+		))?;
+		let mut data_writer = DataWriterBlob::new()?;
+		VersaTilesWriter::write_to_writer_with_options(&mut reader, &mut data_writer, options).await?;
+		Ok(data_writer.into_blob().into_vec())
+	}
+
+	async fn verify(data: Vec<u8>, public_key: Option<&[u8]>) -> Result<VerifyReport> {
+		let reader = VersaTilesReader::open_reader(Box::new(DataReaderBlob::from(data))).await?;
+		reader.verify(public_key).await
+	}
+
+	#[tokio::test]
+	async fn verify_checksums() -> Result<()> {
+		// files without checksums can't be verified
+		let data = write_with_options(&VersaTilesWriterOptions::default()).await?;
+		assert_eq!(
+			verify(data, None).await.unwrap_err().to_string(),
+			"the file contains no checksums, add them with \"versatiles convert --checksum\""
+		);
+
+		for algorithm in [ChecksumAlgorithm::Xxh64, ChecksumAlgorithm::Sha256] {
+			let options = VersaTilesWriterOptions {
+				checksum: Some(algorithm),
+				..Default::default()
+			};
+			let data = write_with_options(&options).await?;
+
+			let report = verify(data.clone(), None).await?;
+			assert!(report.is_valid(), "{:?}", report.errors);
+			assert_eq!(report.algorithm, algorithm);
+			assert_eq!(report.checked_blocks, 5);
+			assert_eq!(report.signer, None);
+
+			// the file can still be read
+			let reader = VersaTilesReader::open_reader(Box::new(DataReaderBlob::from(data.clone()))).await?;
+			assert!(reader.get_tile_data(&TileCoord3::new(15, 1, 4)?).await?.is_some());
+
+			// flip a bit in the first block after the meta data
+			let mut corrupted = data.clone();
+			corrupted[200] ^= 1;
+			let report = verify(corrupted, None).await?;
+			assert_eq!(report.errors.len(), 1);
+			assert_wildcard!(&report.errors[0], "block {x:0,y:0,z:*} is corrupted");
+
+			// a key is expected, but the file is not signed
+			let report = verify(data, Some(&[0; 32])).await?;
+			assert_eq!(report.errors, ["the file is not signed"]);
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn verify_signature() -> Result<()> {
+		use ring::{
+			rand::SystemRandom,
+			signature::{Ed25519KeyPair, KeyPair},
+		};
+
+		let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+		let public_key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+			.unwrap()
+			.public_key()
+			.as_ref()
+			.to_vec();
+		let options = VersaTilesWriterOptions {
+			signing_key: Some(pkcs8.as_ref().to_vec()),
+			..Default::default()
+		};
+		let data = write_with_options(&options).await?;
+
+		let report = verify(data.clone(), Some(&public_key)).await?;
+		assert!(report.is_valid(), "{:?}", report.errors);
+		assert_eq!(report.algorithm, ChecksumAlgorithm::Sha256);
+		assert_eq!(report.signer.as_ref(), Some(&public_key));
+
+		let report = verify(data.clone(), Some(&[0; 32])).await?;
+		assert_eq!(report.errors.len(), 1);
+		assert!(report.errors[0].starts_with("the file is signed by the key "));
+
+		// recalculated checksums don't match the signature
+		let mut tampered = data.clone();
+		let length = tampered.len();
+		tampered[length - 100] ^= 1;
+		let report = verify(tampered, None).await?;
+		assert!(report.errors.contains(&String::from("the signature is invalid")));
+
+		// signatures need SHA-256
+		let options = VersaTilesWriterOptions {
+			checksum: Some(ChecksumAlgorithm::Xxh64),
+			signing_key: Some(pkcs8.as_ref().to_vec()),
+			..Default::default()
+		};
+		assert_eq!(
+			write_with_options(&options).await.unwrap_err().to_string(),
+			"signed files must use SHA-256 checksums"
+		);
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn parallel_blocks() -> Result<()> {
+		let temp_dir = assert_fs::TempDir::new()?;
+		let mut pyramid = TileBBoxPyramid::new_full(4);
+		pyramid.set_level_bbox(TileBBox::new(9, 250, 250, 260, 260)?);
+		let (temp_path, pyramid) = (temp_dir.path(), &pyramid);
+		let write = |workers: Option<usize>| async move {
+			let options = VersaTilesWriterOptions {
+				checksum: Some(ChecksumAlgorithm::Xxh64),
+				temp_dir: Some(temp_path.to_path_buf()),
+				workers,
+				..Default::default()
+			};
+			let mut reader = MockTilesReader::new_mock(TilesReaderParameters::new(
+				TileFormat::PBF,
+				TileCompression::Gzip,
+				pyramid.clone(),
+			))?;
+			let mut data_writer = DataWriterBlob::new()?;
+			VersaTilesWriter::write_to_writer_with_options(&mut reader, &mut data_writer, &options).await?;
+			anyhow::Ok(data_writer.into_blob().into_vec())
+		};
+
+		// the blocks are written by several workers, but the result is always the same
+		let data = write(None).await?;
+		assert_eq!(data, write(Some(4)).await?);
+		assert_eq!(data, write(Some(1)).await?);
+		assert_eq!(std::fs::read_dir(temp_dir.path())?.count(), 0);
+
+		let reader = VersaTilesReader::open_reader(Box::new(DataReaderBlob::from(data.clone()))).await?;
+		assert_eq!(reader.block_index.len(), 9);
+		let tiles = reader
+			.get_bbox_tile_stream(TileBBox::new(9, 250, 250, 260, 260)?)
+			.await
+			.collect()
+			.await;
+		assert_eq!(tiles.len(), 121);
+		for (_, tile) in tiles {
+			assert_eq!(decompress_gzip(&tile)?.as_slice(), MOCK_BYTES_PBF);
+		}
+
+		let report = verify(data, None).await?;
+		assert!(report.is_valid(), "{:?}", report.errors);
+		assert_eq!(report.checked_blocks, 9);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn deduplicate() -> Result<()> {
+		// all mock tiles are identical, so only one tile is stored instead of one per block
+		let options = VersaTilesWriterOptions {
+			checksum: Some(ChecksumAlgorithm::Xxh64),
+			deduplicate: true,
+			..Default::default()
+		};
+		let data = write_with_options(&options).await?;
+		let reader = VersaTilesReader::open_reader(Box::new(DataReaderBlob::from(data.clone()))).await?;
+		assert!(reader.header.shared_tiles);
+		assert_eq!(reader.block_index.len(), 5);
+		assert_eq!(reader.get_tiles_size(), 77);
+
+		for coord in [(0, 0, 0), (1, 0, 1), (3, 2, 2), (15, 1, 4)] {
+			let tile = reader
+				.get_tile_data(&TileCoord3::new(coord.0, coord.1, coord.2)?)
+				.await?;
+			assert_eq!(tile.unwrap().len(), 77, "{coord:?}");
+		}
+		let tiles = reader
+			.get_bbox_tile_stream(TileBBox::new_full(4)?)
+			.await
+			.collect()
+			.await;
+		assert_eq!(tiles.len(), 256);
+
+		// without the marker in the header, the negative offsets are rejected instead of being misread
+		let mut unmarked = data.clone();
+		unmarked[0..14].copy_from_slice(b"versatiles_v02");
+		let reader = VersaTilesReader::open_reader(Box::new(DataReaderBlob::from(unmarked))).await?;
+		assert!(reader.get_tile_data(&TileCoord3::new(15, 1, 4)?).await.is_err());
+
+		let report = verify(data, None).await?;
+		assert!(report.is_valid(), "{:?}", report.errors);
+		Ok(())
+	}
+
 	#[tokio::test]
 	#[cfg(feature = "cli")]
 	async fn probe() -> Result<()> {
@@ -4,7 +4,7 @@
 //!
 //! The `BlockDefinition` struct contains metadata about the tile block, including its coordinates, bounding box, and byte ranges for tiles and index data.
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 use std::{fmt, ops::Div};
 use versatiles_core::{io::*, types::*};
 
@@ -64,6 +64,13 @@ impl BlockDefinition {
 		let x = reader.read_u32()?;
 		let y = reader.read_u32()?;
 
+		ensure!(z <= 31, "block definition is defective: z ({z}) must be <= 31");
+		let max_block = ((1u64 << z) - 1) >> 8;
+		ensure!(
+			x as u64 <= max_block && y as u64 <= max_block,
+			"block definition is defective: block {x}/{y} does not exist at zoom level {z}"
+		);
+
 		let x_min = reader.read_u8()? as u32;
 		let y_min = reader.read_u8()? as u32;
 		let x_max = reader.read_u8()? as u32;
@@ -75,8 +82,12 @@ impl BlockDefinition {
 		let tiles_length = reader.read_u64()?;
 		let index_length = reader.read_u32()? as u64;
 
+		let index_offset = offset
+			.checked_add(tiles_length)
+			.ok_or_else(|| anyhow!("block definition is defective: tiles range {offset} + {tiles_length} overflows"))?;
+
 		let tiles_range = ByteRange::new(offset, tiles_length);
-		let index_range = ByteRange::new(offset + tiles_length, index_length);
+		let index_range = ByteRange::new(index_offset, index_length);
 
 		let global_bbox = TileBBox::new(z, x_min + x * 256, y_min + y * 256, x_max + x * 256, y_max + y * 256)?;
 
@@ -132,12 +143,19 @@ impl BlockDefinition {
 		writer.write_u8(self.tiles_coverage.y_max as u8)?;
 
 		ensure!(
-			self.tiles_range.offset + self.tiles_range.length == self.index_range.offset,
+			self.tiles_range.offset.checked_add(self.tiles_range.length) == Some(self.index_range.offset),
 			"tiles_range and index_range do not match"
 		);
 
 		writer.write_u64(self.tiles_range.offset)?;
 		writer.write_u64(self.tiles_range.length)?;
+		ensure!(
+			self.index_range.length <= u32::MAX as u64,
+			"tile index of block {:?} is {} bytes long, but at most {} bytes are supported",
+			self.offset,
+			self.index_range.length,
+			u32::MAX
+		);
 		writer.write_u32(self.index_range.length as u32)?;
 
 		Ok(writer.into_blob())
@@ -271,4 +289,49 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn large_offsets_round_trip() -> Result<()> {
+		let mut def = BlockDefinition::new(&TileBBox::new(17, 131000, 131000, 131071, 131071)?);
+		def.tiles_range = ByteRange::new(1 << 45, (1 << 40) + 7);
+		def.index_range = ByteRange::new((1 << 45) + (1 << 40) + 7, u32::MAX as u64);
+
+		let def2 = BlockDefinition::from_blob(&def.as_blob()?)?;
+		assert_eq!(def, def2);
+		assert_eq!(def2.get_coord3(), &TileCoord3::new(511, 511, 17)?);
+
+		Ok(())
+	}
+
+	#[test]
+	fn too_large_index_fails() -> Result<()> {
+		let mut def = BlockDefinition::new(&TileBBox::new(12, 300, 400, 320, 450)?);
+		def.index_range = ByteRange::new(0, u32::MAX as u64 + 1);
+		assert!(def.as_blob().is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn defective_blobs_fail() -> Result<()> {
+		let def = BlockDefinition::new(&TileBBox::new(12, 300, 400, 320, 450)?);
+
+		// tiles range that wraps around u64
+		let mut bytes = def.as_blob()?.into_vec();
+		bytes[13..21].copy_from_slice(&(u64::MAX - 3).to_be_bytes());
+		bytes[21..29].copy_from_slice(&4u64.to_be_bytes());
+		assert!(BlockDefinition::from_blob(&Blob::from(bytes)).is_err());
+
+		// block coordinates outside of the zoom level
+		let mut bytes = def.as_blob()?.into_vec();
+		bytes[1..5].copy_from_slice(&16u32.to_be_bytes());
+		assert!(BlockDefinition::from_blob(&Blob::from(bytes)).is_err());
+
+		// invalid zoom level
+		let mut bytes = def.as_blob()?.into_vec();
+		bytes[0] = 40;
+		assert!(BlockDefinition::from_blob(&Blob::from(bytes)).is_err());
+
+		Ok(())
+	}
 }
@@ -6,7 +6,7 @@
 
 use super::BlockDefinition;
 use anyhow::{ensure, Result};
-use std::{collections::HashMap, ops::Div};
+use std::ops::Div;
 use versatiles_core::{io::*, types::*, utils::*};
 
 const BLOCK_INDEX_LENGTH: u64 = 33;
@@ -14,7 +14,7 @@ const BLOCK_INDEX_LENGTH: u64 = 33;
 /// A struct representing an index of blocks within a tile set.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BlockIndex {
-	lookup: HashMap<TileCoord3, BlockDefinition>,
+	lookup: TileMap<BlockDefinition>,
 }
 
 impl BlockIndex {
@@ -23,7 +23,7 @@ impl BlockIndex {
 	/// # Returns
 	/// A new empty `BlockIndex`.
 	pub fn new_empty() -> Self {
-		Self { lookup: HashMap::new() }
+		Self { lookup: TileMap::new() }
 	}
 
 	/// Creates a `BlockIndex` from a binary blob.
@@ -79,7 +79,8 @@ impl BlockIndex {
 	/// # Arguments
 	/// * `block` - The block to add.
 	pub fn add_block(&mut self, block: BlockDefinition) {
-		self.lookup.insert(*block.get_coord3(), block);
+		let coord = *block.get_coord3();
+		self.lookup.insert(&coord, block);
 	}
 
 	/// Converts the `BlockIndex` to a binary blob.
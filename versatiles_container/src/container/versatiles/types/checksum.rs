@@ -0,0 +1,258 @@
+//! This module defines the checksum algorithms that can be used to protect the blocks of a versatiles file.
+//!
+//! XXH64 is fast and detects corruption, SHA-256 is slower, but can also be used to detect tampering
+//! in combination with a signature.
+
+use anyhow::{bail, Result};
+use sha2::{Digest, Sha256};
+
+/// Algorithm of the checksums of a versatiles file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+	Xxh64,
+	Sha256,
+}
+
+impl ChecksumAlgorithm {
+	/// Returns the value used in the file.
+	pub fn as_u8(&self) -> u8 {
+		match self {
+			ChecksumAlgorithm::Xxh64 => 1,
+			ChecksumAlgorithm::Sha256 => 2,
+		}
+	}
+
+	/// Parses the value used in the file.
+	pub fn from_u8(value: u8) -> Result<Self> {
+		Ok(match value {
+			1 => ChecksumAlgorithm::Xxh64,
+			2 => ChecksumAlgorithm::Sha256,
+			_ => bail!("unknown checksum algorithm: {value}"),
+		})
+	}
+
+	/// Parses the name of the algorithm, e.g. "xxhash" or "sha256".
+	pub fn parse_str(value: &str) -> Result<Self> {
+		Ok(match value.to_lowercase().as_str() {
+			"xxhash" | "xxh64" => ChecksumAlgorithm::Xxh64,
+			"sha256" | "sha-256" => ChecksumAlgorithm::Sha256,
+			_ => bail!("unknown checksum algorithm \"{value}\", use \"xxhash\" or \"sha256\""),
+		})
+	}
+
+	pub fn as_str(&self) -> &str {
+		match self {
+			ChecksumAlgorithm::Xxh64 => "xxh64",
+			ChecksumAlgorithm::Sha256 => "sha256",
+		}
+	}
+
+	/// Length of a checksum in bytes.
+	pub fn output_length(&self) -> usize {
+		match self {
+			ChecksumAlgorithm::Xxh64 => 8,
+			ChecksumAlgorithm::Sha256 => 32,
+		}
+	}
+
+	/// Starts a new checksum.
+	pub fn hasher(&self) -> Hasher {
+		match self {
+			ChecksumAlgorithm::Xxh64 => Hasher::Xxh64(Xxh64::new(0)),
+			ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+		}
+	}
+
+	/// Calculates the checksum of `data`.
+	pub fn checksum(&self, data: &[u8]) -> Vec<u8> {
+		let mut hasher = self.hasher();
+		hasher.update(data);
+		hasher.finish()
+	}
+}
+
+/// Calculates a checksum incrementally, so that data can be hashed while it is written or read.
+pub enum Hasher {
+	Xxh64(Xxh64),
+	Sha256(Sha256),
+}
+
+impl Hasher {
+	pub fn update(&mut self, data: &[u8]) {
+		match self {
+			Hasher::Xxh64(hasher) => hasher.update(data),
+			Hasher::Sha256(hasher) => hasher.update(data),
+		}
+	}
+
+	/// Returns the checksum, XXH64 in big endian byte order.
+	pub fn finish(self) -> Vec<u8> {
+		match self {
+			Hasher::Xxh64(hasher) => hasher.finish().to_be_bytes().to_vec(),
+			Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+		}
+	}
+}
+
+const PRIME1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME5: u64 = 0x27D4_EB2F_1656_67C5;
+
+/// Streaming implementation of XXH64, see <https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md>
+pub struct Xxh64 {
+	seed: u64,
+	accumulators: [u64; 4],
+	buffer: [u8; 32],
+	buffer_length: usize,
+	total_length: u64,
+}
+
+impl Xxh64 {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			seed,
+			accumulators: [
+				seed.wrapping_add(PRIME1).wrapping_add(PRIME2),
+				seed.wrapping_add(PRIME2),
+				seed,
+				seed.wrapping_sub(PRIME1),
+			],
+			buffer: [0; 32],
+			buffer_length: 0,
+			total_length: 0,
+		}
+	}
+
+	pub fn update(&mut self, mut data: &[u8]) {
+		self.total_length += data.len() as u64;
+
+		if self.buffer_length > 0 {
+			let length = data.len().min(32 - self.buffer_length);
+			self.buffer[self.buffer_length..self.buffer_length + length].copy_from_slice(&data[..length]);
+			self.buffer_length += length;
+			data = &data[length..];
+			if self.buffer_length < 32 {
+				return;
+			}
+			let buffer = self.buffer;
+			self.process_stripe(&buffer);
+			self.buffer_length = 0;
+		}
+
+		let mut stripes = data.chunks_exact(32);
+		for stripe in stripes.by_ref() {
+			self.process_stripe(stripe);
+		}
+		let rest = stripes.remainder();
+		self.buffer[..rest.len()].copy_from_slice(rest);
+		self.buffer_length = rest.len();
+	}
+
+	fn process_stripe(&mut self, stripe: &[u8]) {
+		for (accumulator, lane) in self.accumulators.iter_mut().zip(stripe.chunks_exact(8)) {
+			*accumulator = round(*accumulator, read_u64(lane));
+		}
+	}
+
+	pub fn finish(&self) -> u64 {
+		let mut hash = if self.total_length >= 32 {
+			let [a1, a2, a3, a4] = self.accumulators;
+			let mut hash = a1
+				.rotate_left(1)
+				.wrapping_add(a2.rotate_left(7))
+				.wrapping_add(a3.rotate_left(12))
+				.wrapping_add(a4.rotate_left(18));
+			for accumulator in self.accumulators {
+				hash = (hash ^ round(0, accumulator)).wrapping_mul(PRIME1).wrapping_add(PRIME4);
+			}
+			hash
+		} else {
+			self.seed.wrapping_add(PRIME5)
+		};
+		hash = hash.wrapping_add(self.total_length);
+
+		let mut rest = &self.buffer[..self.buffer_length];
+		while rest.len() >= 8 {
+			hash ^= round(0, read_u64(rest));
+			hash = hash.rotate_left(27).wrapping_mul(PRIME1).wrapping_add(PRIME4);
+			rest = &rest[8..];
+		}
+		if rest.len() >= 4 {
+			hash ^= (u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64).wrapping_mul(PRIME1);
+			hash = hash.rotate_left(23).wrapping_mul(PRIME2).wrapping_add(PRIME3);
+			rest = &rest[4..];
+		}
+		for byte in rest {
+			hash ^= (*byte as u64).wrapping_mul(PRIME5);
+			hash = hash.rotate_left(11).wrapping_mul(PRIME1);
+		}
+
+		hash ^= hash >> 33;
+		hash = hash.wrapping_mul(PRIME2);
+		hash ^= hash >> 29;
+		hash = hash.wrapping_mul(PRIME3);
+		hash ^= hash >> 32;
+		hash
+	}
+}
+
+fn round(accumulator: u64, lane: u64) -> u64 {
+	accumulator
+		.wrapping_add(lane.wrapping_mul(PRIME2))
+		.rotate_left(31)
+		.wrapping_mul(PRIME1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+	u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn xxh64(data: &[u8]) -> u64 {
+		let mut hasher = Xxh64::new(0);
+		hasher.update(data);
+		hasher.finish()
+	}
+
+	#[test]
+	fn xxh64_reference_values() {
+		assert_eq!(xxh64(b""), 0xEF46_DB37_51D8_E999);
+		assert_eq!(xxh64(b"a"), 0xD24E_C4F1_A98C_6E5B);
+		assert_eq!(xxh64(b"abc"), 0x44BC_2CF5_AD77_0999);
+	}
+
+	#[test]
+	fn xxh64_streaming() {
+		let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+		let expected = xxh64(&data);
+		for chunk_size in [1, 5, 31, 32, 33, 100] {
+			let mut hasher = Xxh64::new(0);
+			for chunk in data.chunks(chunk_size) {
+				hasher.update(chunk);
+			}
+			assert_eq!(hasher.finish(), expected, "chunk size {chunk_size}");
+		}
+	}
+
+	#[test]
+	fn algorithms() -> Result<()> {
+		assert_eq!(ChecksumAlgorithm::parse_str("xxhash")?, ChecksumAlgorithm::Xxh64);
+		assert_eq!(ChecksumAlgorithm::parse_str("SHA256")?, ChecksumAlgorithm::Sha256);
+		assert!(ChecksumAlgorithm::parse_str("md5").is_err());
+
+		for algorithm in [ChecksumAlgorithm::Xxh64, ChecksumAlgorithm::Sha256] {
+			assert_eq!(ChecksumAlgorithm::from_u8(algorithm.as_u8())?, algorithm);
+			assert_eq!(algorithm.checksum(b"versatiles").len(), algorithm.output_length());
+		}
+		assert_eq!(
+			ChecksumAlgorithm::Sha256.checksum(b"abc")[..4],
+			[0xba, 0x78, 0x16, 0xbf]
+		);
+		Ok(())
+	}
+}
@@ -7,7 +7,12 @@
 use anyhow::{bail, ensure, Result};
 use versatiles_core::{io::*, types::*};
 
-const HEADER_LENGTH: u64 = 66;
+pub const HEADER_LENGTH: u64 = 66;
+/// Magic word of files, whose blocks only contain their own tiles
+const MAGIC_WORD: &[u8] = b"versatiles_v02";
+/// Magic word of files with tiles shared across blocks. Older readers don't know it, so they refuse these files
+/// instead of misreading the negative tile offsets.
+const MAGIC_WORD_SHARED_TILES: &[u8] = b"versatiles_v03";
 const BBOX_SCALE: f64 = 10000000.0;
 
 /// A struct representing the header of a versatiles file.
@@ -19,6 +24,8 @@ pub struct FileHeader {
 	pub compression: TileCompression,
 	pub meta_range: ByteRange,
 	pub blocks_range: ByteRange,
+	/// Tile indexes may point to tiles of earlier blocks, see `TileIndex::add_offset_shared`.
+	pub shared_tiles: bool,
 }
 
 impl FileHeader {
@@ -46,6 +53,10 @@ impl FileHeader {
 		);
 
 		bbox.check()?;
+		ensure!(
+			!bbox.crosses_antimeridian(),
+			"bbox {bbox:?} must not cross the antimeridian"
+		);
 
 		Ok(FileHeader {
 			zoom_range,
@@ -54,6 +65,7 @@ impl FileHeader {
 			compression: *compression,
 			meta_range: ByteRange::empty(),
 			blocks_range: ByteRange::empty(),
+			shared_tiles: false,
 		})
 	}
 
@@ -79,7 +91,11 @@ impl FileHeader {
 		use TileFormat::*;
 
 		let mut writer = ValueWriterBlob::new_be();
-		writer.write_slice(b"versatiles_v02")?;
+		writer.write_slice(if self.shared_tiles {
+			MAGIC_WORD_SHARED_TILES
+		} else {
+			MAGIC_WORD
+		})?;
 
 		// tile type
 		writer.write_u8(match self.tile_format {
@@ -90,6 +106,7 @@ impl FileHeader {
 			WEBP => 0x12,
 			AVIF => 0x13,
 			SVG => 0x14,
+			JXL => 0x15,
 
 			PBF => 0x20,
 			GEOJSON => 0x21,
@@ -102,6 +119,8 @@ impl FileHeader {
 			Uncompressed => 0,
 			Gzip => 1,
 			Brotli => 2,
+			// extension of the specification
+			Zstd => 3,
 		})?;
 
 		writer.write_u8(self.zoom_range[0])?;
@@ -142,8 +161,10 @@ impl FileHeader {
 
 		let mut reader = ValueReaderSlice::new_be(blob.as_slice());
 		let magic_word = reader.read_string(14)?;
-		if &magic_word != "versatiles_v02" {
-			bail!("'{blob:?}' is not a valid versatiles header. A header should start with 'versatiles_v02'");
+		let shared_tiles = match magic_word.as_bytes() {
+			MAGIC_WORD => false,
+			MAGIC_WORD_SHARED_TILES => true,
+			_ => bail!("'{blob:?}' is not a valid versatiles header. A header should start with 'versatiles_v02'"),
 		};
 
 		let tile_format = match reader.read_u8()? {
@@ -154,6 +175,7 @@ impl FileHeader {
 			0x12 => WEBP,
 			0x13 => AVIF,
 			0x14 => SVG,
+			0x15 => JXL,
 
 			0x20 => PBF,
 			0x21 => GEOJSON,
@@ -166,6 +188,7 @@ impl FileHeader {
 			0 => Uncompressed,
 			1 => Gzip,
 			2 => Brotli,
+			3 => Zstd,
 			value => bail!("unknown compression value: {value}"),
 		};
 
@@ -188,6 +211,7 @@ impl FileHeader {
 			compression,
 			meta_range,
 			blocks_range,
+			shared_tiles,
 		})
 	}
 }
@@ -278,6 +302,18 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn shared_tiles() -> Result<()> {
+		let mut header = FileHeader::new(&TileFormat::PBF, &Gzip, [0, 0], &GeoBBox(0.0, 0.0, 0.0, 0.0))?;
+		assert!(!header.shared_tiles);
+		header.shared_tiles = true;
+
+		let blob = header.to_blob()?;
+		assert_eq!(&blob.as_slice()[0..14], b"versatiles_v03");
+		assert_eq!(FileHeader::from_blob(&blob)?, header);
+		Ok(())
+	}
+
 	#[test]
 	fn new_file_header_with_invalid_params() {
 		let tf = TileFormat::PNG;
@@ -307,7 +343,7 @@ mod tests {
 		let zoom_range = [0, 0];
 		let bbox = GeoBBox(0.0, 0.0, 0.0, 0.0);
 
-		let tile_formats = vec![BIN, PNG, JPG, WEBP, AVIF, SVG, PBF, GEOJSON, TOPOJSON, JSON];
+		let tile_formats = vec![BIN, PNG, JPG, WEBP, AVIF, SVG, JXL, PBF, GEOJSON, TOPOJSON, JSON];
 
 		for tile_format in tile_formats {
 			let header = FileHeader::new(&tile_format, &compression, zoom_range, &bbox).unwrap();
@@ -325,7 +361,7 @@ mod tests {
 		let zoom_range = [0, 0];
 		let bbox = GeoBBox(0.0, 0.0, 0.0, 0.0);
 
-		let compressions = vec![Uncompressed, Gzip, Brotli];
+		let compressions = vec![Uncompressed, Gzip, Brotli, Zstd];
 
 		for compression in compressions {
 			let header = FileHeader::new(&tile_format, &compression, zoom_range, &bbox).unwrap();
@@ -345,18 +381,21 @@ mod tests {
 
 	#[test]
 	fn invalid_magic_word() {
-		let mut invalid_blob = Blob::from(vec![0; HEADER_LENGTH as usize]);
-		invalid_blob.as_mut_slice()[0..14].copy_from_slice(b"invalid_header");
+		let mut bytes = vec![0; HEADER_LENGTH as usize];
+		bytes[0..14].copy_from_slice(b"invalid_header");
+		let invalid_blob = Blob::from(bytes);
 		assert!(FileHeader::from_blob(&invalid_blob).is_err());
 	}
 
 	#[test]
 	fn unknown_tile_format() {
-		let mut invalid_blob = FileHeader::new(&TileFormat::PNG, &Gzip, [0, 0], &GeoBBox(0.0, 0.0, 0.0, 0.0))
+		let mut bytes = FileHeader::new(&TileFormat::PNG, &Gzip, [0, 0], &GeoBBox(0.0, 0.0, 0.0, 0.0))
 			.unwrap()
 			.to_blob()
-			.unwrap();
-		invalid_blob.as_mut_slice()[14] = 0xFF; // Set an unknown tile format value
+			.unwrap()
+			.into_vec();
+		bytes[14] = 0xFF; // Set an unknown tile format value
+		let invalid_blob = Blob::from(bytes);
 
 		let result = catch_unwind(|| {
 			FileHeader::from_blob(&invalid_blob).unwrap();
@@ -367,11 +406,13 @@ mod tests {
 
 	#[test]
 	fn unknown_compression() {
-		let mut invalid_blob = FileHeader::new(&TileFormat::PNG, &Gzip, [0, 0], &GeoBBox(0.0, 0.0, 0.0, 0.0))
+		let mut bytes = FileHeader::new(&TileFormat::PNG, &Gzip, [0, 0], &GeoBBox(0.0, 0.0, 0.0, 0.0))
 			.unwrap()
 			.to_blob()
-			.unwrap();
-		invalid_blob.as_mut_slice()[15] = 0xFF; // Set an unknown compression value
+			.unwrap()
+			.into_vec();
+		bytes[15] = 0xFF; // Set an unknown compression value
+		let invalid_blob = Blob::from(bytes);
 
 		let result = catch_unwind(|| {
 			FileHeader::from_blob(&invalid_blob).unwrap();
@@ -0,0 +1,256 @@
+//! This module defines the `IntegritySection` struct, which holds the checksums and the optional signature of a versatiles file.
+//!
+//! The section is optional and stored directly after the block index, so readers that don't know it can ignore it.
+//! It starts with a preamble of 20 bytes:
+//! - magic word "versatiles_sum" (14 bytes)
+//! - checksum algorithm (u8): 1 = XXH64, 2 = SHA-256
+//! - flags (u8): bit 0 is set, if the section is signed
+//! - length of the following body (u32)
+//!
+//! The body contains the checksums of the header, the meta data and the block index, followed by the number of blocks (u32)
+//! and for every block its coordinate (z: u8, x: u32, y: u32) and the checksum of its tiles and tile index.
+//! Signed sections end with the Ed25519 public key (32 bytes) and the signature (64 bytes) of everything before.
+
+use super::ChecksumAlgorithm;
+use anyhow::{bail, ensure, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use versatiles_core::{io::*, types::*};
+
+const MAGIC: &[u8; 14] = b"versatiles_sum";
+const PREAMBLE_LENGTH: u64 = 20;
+const PUBLIC_KEY_LENGTH: usize = 32;
+const SIGNATURE_LENGTH: usize = 64;
+
+/// Ed25519 signature of an integrity section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntegritySignature {
+	pub public_key: Vec<u8>,
+	pub signature: Vec<u8>,
+}
+
+/// Checksums of all parts of a versatiles file, optionally signed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntegritySection {
+	pub algorithm: ChecksumAlgorithm,
+	pub header: Vec<u8>,
+	pub meta: Vec<u8>,
+	pub block_index: Vec<u8>,
+	/// checksum of the tiles and the tile index of every block
+	pub blocks: Vec<(TileCoord3, Vec<u8>)>,
+	pub signature: Option<IntegritySignature>,
+}
+
+impl IntegritySection {
+	/// Creates an empty section. The checksums of header, meta data and block index have to be set before writing.
+	pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+		Self {
+			algorithm,
+			header: Vec::new(),
+			meta: Vec::new(),
+			block_index: Vec::new(),
+			blocks: Vec::new(),
+			signature: None,
+		}
+	}
+
+	/// Reads the section that starts at `offset`. Returns `None` if the file has no integrity section.
+	pub async fn from_reader(reader: &DataReader, offset: u64) -> Result<Option<IntegritySection>> {
+		let Ok(preamble) = reader.read_range(&ByteRange::new(offset, PREAMBLE_LENGTH)).await else {
+			return Ok(None);
+		};
+		if !preamble.as_slice().starts_with(MAGIC) {
+			return Ok(None);
+		}
+		let body_length = ValueReaderSlice::new_be(&preamble.as_slice()[16..]).read_u32()? as u64;
+		let body = reader
+			.read_range(&ByteRange::new(offset + PREAMBLE_LENGTH, body_length))
+			.await?;
+
+		let mut blob = preamble.into_vec();
+		blob.extend_from_slice(body.as_slice());
+		Ok(Some(IntegritySection::from_blob(&Blob::from(blob))?))
+	}
+
+	/// Parses a section, including its preamble.
+	pub fn from_blob(blob: &Blob) -> Result<IntegritySection> {
+		let mut reader = ValueReaderSlice::new_be(blob.as_slice());
+		ensure!(
+			reader.read_blob(MAGIC.len() as u64)?.as_slice() == MAGIC,
+			"integrity section must start with \"versatiles_sum\""
+		);
+		let algorithm = ChecksumAlgorithm::from_u8(reader.read_u8()?)?;
+		let flags = reader.read_u8()?;
+		let body_length = reader.read_u32()? as u64;
+		ensure!(
+			body_length == blob.len() - PREAMBLE_LENGTH,
+			"integrity section should be {} bytes long, but is {} bytes long",
+			body_length + PREAMBLE_LENGTH,
+			blob.len()
+		);
+
+		let length = algorithm.output_length() as u64;
+		let mut section = IntegritySection::new(algorithm);
+		section.header = reader.read_blob(length)?.into_vec();
+		section.meta = reader.read_blob(length)?.into_vec();
+		section.block_index = reader.read_blob(length)?.into_vec();
+
+		let count = reader.read_u32()?;
+		for _ in 0..count {
+			let z = reader.read_u8()?;
+			let x = reader.read_u32()?;
+			let y = reader.read_u32()?;
+			section
+				.blocks
+				.push((TileCoord3::new(x, y, z)?, reader.read_blob(length)?.into_vec()));
+		}
+
+		match flags {
+			0 => {}
+			1 => {
+				section.signature = Some(IntegritySignature {
+					public_key: reader.read_blob(PUBLIC_KEY_LENGTH as u64)?.into_vec(),
+					signature: reader.read_blob(SIGNATURE_LENGTH as u64)?.into_vec(),
+				})
+			}
+			_ => bail!("unknown integrity section flags: {flags}"),
+		}
+		ensure!(
+			!reader.has_remaining(),
+			"integrity section has unexpected trailing bytes"
+		);
+
+		Ok(section)
+	}
+
+	/// Converts the section to a blob. If a `key_pair` is given, the section is signed with it.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn to_blob(&self, key_pair: Option<&Ed25519KeyPair>) -> Result<Blob> {
+		let Some(key_pair) = key_pair else {
+			let mut section = self.clone();
+			section.signature = None;
+			return section.signed_data();
+		};
+
+		let public_key = key_pair.public_key().as_ref().to_vec();
+		let mut section = self.clone();
+		section.signature = Some(IntegritySignature {
+			public_key: public_key.clone(),
+			signature: Vec::new(),
+		});
+		let mut blob = section.signed_data()?.into_vec();
+		let signature = key_pair.sign(&blob);
+		blob.extend_from_slice(&public_key);
+		blob.extend_from_slice(signature.as_ref());
+		Ok(Blob::from(blob))
+	}
+
+	/// Checks the signature. Returns an error if the section is not signed or the signature is invalid.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn verify_signature(&self) -> Result<()> {
+		let Some(signature) = &self.signature else {
+			bail!("the file is not signed");
+		};
+		UnparsedPublicKey::new(&ED25519, &signature.public_key)
+			.verify(self.signed_data()?.as_slice(), &signature.signature)
+			.map_err(|_| anyhow::anyhow!("the signature is invalid"))
+	}
+
+	/// Ed25519 is not available in WebAssembly, so checksums can be verified there, but signatures can't.
+	#[cfg(target_arch = "wasm32")]
+	pub fn verify_signature(&self) -> Result<()> {
+		bail!("signatures can't be checked in WebAssembly")
+	}
+
+	/// Everything that is covered by the signature: the preamble and the body without public key and signature.
+	#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+	fn signed_data(&self) -> Result<Blob> {
+		let length = self.algorithm.output_length();
+		for checksum in [&self.header, &self.meta, &self.block_index]
+			.into_iter()
+			.chain(self.blocks.iter().map(|(_, checksum)| checksum))
+		{
+			ensure!(checksum.len() == length, "checksum must be {length} bytes long");
+		}
+
+		let mut body_length = (3 * length + 4 + self.blocks.len() * (9 + length)) as u32;
+		if self.signature.is_some() {
+			body_length += (PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH) as u32;
+		}
+
+		let mut writer = ValueWriterBlob::new_be();
+		writer.write_slice(MAGIC)?;
+		writer.write_u8(self.algorithm.as_u8())?;
+		writer.write_u8(self.signature.is_some() as u8)?;
+		writer.write_u32(body_length)?;
+		writer.write_slice(&self.header)?;
+		writer.write_slice(&self.meta)?;
+		writer.write_slice(&self.block_index)?;
+		writer.write_u32(self.blocks.len() as u32)?;
+		for (coord, checksum) in self.blocks.iter() {
+			writer.write_u8(coord.z)?;
+			writer.write_u32(coord.x)?;
+			writer.write_u32(coord.y)?;
+			writer.write_slice(checksum)?;
+		}
+		Ok(writer.into_blob())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ring::rand::SystemRandom;
+
+	fn example(algorithm: ChecksumAlgorithm) -> Result<IntegritySection> {
+		let mut section = IntegritySection::new(algorithm);
+		section.header = algorithm.checksum(b"header");
+		section.meta = algorithm.checksum(b"meta");
+		section.block_index = algorithm.checksum(b"block index");
+		section.blocks = vec![
+			(TileCoord3::new(0, 0, 3)?, algorithm.checksum(b"block 1")),
+			(TileCoord3::new(1, 2, 14)?, algorithm.checksum(b"block 2")),
+		];
+		Ok(section)
+	}
+
+	#[test]
+	fn conversion() -> Result<()> {
+		for algorithm in [ChecksumAlgorithm::Xxh64, ChecksumAlgorithm::Sha256] {
+			let section = example(algorithm)?;
+			let blob = section.to_blob(None)?;
+			assert_eq!(
+				blob.len(),
+				PREAMBLE_LENGTH + 3 * algorithm.output_length() as u64 + 4 + 2 * (9 + algorithm.output_length() as u64)
+			);
+			assert_eq!(IntegritySection::from_blob(&blob)?, section);
+			assert!(section.verify_signature().is_err());
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn signature() -> Result<()> {
+		let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+		let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+		let section = example(ChecksumAlgorithm::Sha256)?;
+		let blob = section.to_blob(Some(&key_pair))?;
+		let parsed = IntegritySection::from_blob(&blob)?;
+		assert_eq!(parsed.blocks, section.blocks);
+		parsed.verify_signature()?;
+		assert_eq!(
+			parsed.signature.as_ref().unwrap().public_key,
+			key_pair.public_key().as_ref()
+		);
+
+		// a changed checksum breaks the signature
+		let mut tampered = parsed.clone();
+		tampered.blocks[1].1[0] ^= 1;
+		assert_eq!(
+			tampered.verify_signature().unwrap_err().to_string(),
+			"the signature is invalid"
+		);
+		Ok(())
+	}
+}
@@ -9,8 +9,10 @@
 //! # Types
 //!
 //! - `BlockDefinition`: Defines a block within the tile container, including its offset, coverage, and byte ranges.
+//! - `ChecksumAlgorithm`: The algorithm of the optional checksums, XXH64 or SHA-256.
 //! - `BlockIndex`: Manages a collection of `BlockDefinition`s, allowing for efficient lookups and conversions.
 //! - `FileHeader`: Represents the header of a `versatiles` file, containing metadata about the tile format, compression, and ranges.
+//! - `IntegritySection`: The optional checksums of all parts of the file, with an optional signature.
 //! - `TileIndex`: Manages the byte ranges of individual tiles within the container, allowing for efficient access and modifications.
 
 mod block_definition;
@@ -19,8 +21,14 @@ pub use block_definition::BlockDefinition;
 mod block_index;
 pub use block_index::BlockIndex;
 
+mod checksum;
+pub use checksum::{ChecksumAlgorithm, Hasher};
+
 mod file_header;
-pub use file_header::FileHeader;
+pub use file_header::{FileHeader, HEADER_LENGTH};
+
+mod integrity;
+pub use integrity::IntegritySection;
 
 mod tile_index;
 pub use tile_index::TileIndex;
@@ -4,7 +4,7 @@
 //!
 //! The `TileIndex` struct is used to manage the byte ranges of tiles within a versatiles file. It provides methods to create, manipulate, and convert the index to and from binary blobs.
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 use std::ops::Div;
 use versatiles_core::{io::*, types::*, utils::*};
 
@@ -75,10 +75,18 @@ impl TileIndex {
 	/// Converts the `TileIndex` to a binary blob.
 	///
 	/// # Errors
-	/// Returns an error if the conversion fails.
+	/// Returns an error if the conversion fails or if a tile is larger than `u32::MAX` bytes,
+	/// since the index stores tile lengths as 32-bit values.
 	pub fn as_blob(&self) -> Result<Blob> {
 		let mut writer = ValueWriterBlob::new_be();
 		for range in &self.index {
+			ensure!(
+				range.length <= u32::MAX as u64,
+				"tile at offset {} is {} bytes long, but the tile index supports at most {} bytes per tile",
+				range.offset,
+				range.length,
+				u32::MAX
+			);
 			writer.write_u64(range.offset)?;
 			writer.write_u32(range.length as u32)?;
 		}
@@ -119,8 +127,40 @@ impl TileIndex {
 	///
 	/// # Arguments
 	/// * `offset` - The offset to add to each byte range.
-	pub fn add_offset(&mut self, offset: u64) {
-		self.index.iter_mut().for_each(|r| r.offset += offset);
+	///
+	/// # Errors
+	/// Returns an error if any resulting offset would overflow `u64`.
+	pub fn add_offset(&mut self, offset: u64) -> Result<()> {
+		for range in self.index.iter_mut() {
+			range.offset = range
+				.offset
+				.checked_add(offset)
+				.ok_or_else(|| anyhow!("tile offset {} + {offset} overflows", range.offset))?;
+		}
+		Ok(())
+	}
+
+	/// Adds an offset to all byte ranges in the index of a file with shared tiles.
+	///
+	/// Offsets of tiles that are shared with earlier blocks, written with deduplication, are negative
+	/// (two's complement), so they wrap around to a position in front of `offset`.
+	///
+	/// # Arguments
+	/// * `offset` - The offset to add to each byte range.
+	///
+	/// # Errors
+	/// Returns an error if the end of any resulting range would overflow `u64`.
+	pub fn add_offset_shared(&mut self, offset: u64) -> Result<()> {
+		for range in self.index.iter_mut() {
+			let new_offset = range.offset.wrapping_add(offset);
+			ensure!(
+				new_offset.checked_add(range.length).is_some(),
+				"tile offset {} + {offset} overflows",
+				range.offset
+			);
+			range.offset = new_offset;
+		}
+		Ok(())
 	}
 }
 
@@ -140,7 +180,7 @@ mod tests {
 			assert_eq!(index.get(i as usize), &ByteRange::new(i * i, i));
 		}
 
-		index.add_offset(18);
+		index.add_offset(18).unwrap();
 
 		for (index, range) in index.iter().enumerate() {
 			let i = index as u64;
@@ -159,4 +199,66 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn conversion_with_offsets_beyond_4_gib() -> Result<()> {
+		// simple xorshift, so the "random" ranges are reproducible
+		let mut state = 0x2545_f491_4f6c_dd1du64;
+		let mut next = || {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state
+		};
+
+		let mut index1 = TileIndex::new_empty(1000);
+		for i in 0..1000 {
+			let offset = next() >> 8;
+			let length = next() % (u32::MAX as u64 + 1);
+			index1.set(i, ByteRange::new(offset, length));
+		}
+		index1.set(0, ByteRange::new(u64::MAX - 10, u32::MAX as u64));
+
+		let index2 = TileIndex::from_blob(index1.as_blob()?)?;
+		assert_eq!(index1, index2);
+
+		Ok(())
+	}
+
+	#[test]
+	fn too_large_tile_fails() {
+		let mut index = TileIndex::new_empty(2);
+		index.set(1, ByteRange::new(1 << 40, u32::MAX as u64 + 1));
+		let error = index.as_blob().unwrap_err().to_string();
+		assert!(error.contains("4294967296 bytes long"), "{error}");
+	}
+
+	#[test]
+	fn add_offset_overflow_fails() {
+		let mut index = TileIndex::new_empty(2);
+		index.set(1, ByteRange::new(u64::MAX - 5, 1));
+		assert!(index.add_offset(5).is_ok());
+		assert!(index.add_offset(1).is_err());
+
+		let mut index = TileIndex::new_empty(2);
+		index.set(1, ByteRange::new(u64::MAX - 10, 5));
+		assert!(index.add_offset_shared(5).is_ok());
+		assert!(index.add_offset_shared(1).is_err());
+	}
+
+	#[test]
+	fn add_negative_offset() -> Result<()> {
+		let mut index = TileIndex::new_empty(2);
+		index.set(0, ByteRange::new(7, 3));
+		index.set(1, ByteRange::new(40u64.wrapping_neg(), 3));
+		index.add_offset_shared(100)?;
+		assert_eq!(index.get(0), &ByteRange::new(107, 3));
+		assert_eq!(index.get(1), &ByteRange::new(60, 3));
+
+		// files without shared tiles must not have negative offsets
+		let mut index = TileIndex::new_empty(1);
+		index.set(0, ByteRange::new(40u64.wrapping_neg(), 3));
+		assert!(index.add_offset(100).is_err());
+		Ok(())
+	}
 }
@@ -0,0 +1,302 @@
+//! Updates `*.versatiles` containers with the tiles of another container, e.g. a small regional update of a planet.
+//!
+//! Instead of writing all tiles again, blocks that contain no tiles of the update are copied byte-for-byte from
+//! the old container. Only blocks overlapping the update are written again, with the tiles of the update replacing
+//! the old tiles.
+//!
+//! ```no_run
+//! use versatiles_container::{get_reader, VersaTilesUpdater};
+//! use std::path::Path;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let diff = get_reader("berlin.versatiles").await?;
+//! let report = VersaTilesUpdater::update_path(
+//!     Path::new("planet.versatiles"),
+//!     diff,
+//!     Path::new("/data/planet_updated.versatiles"),
+//! )
+//! .await?;
+//! println!("copied {} blocks, wrote {} blocks", report.copied_blocks, report.written_blocks);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+	types::{BlockDefinition, BlockIndex, FileHeader},
+	VersaTilesReader, VersaTilesWriter,
+};
+use crate::{MergePrefer, TilesMergeParameters, TilesMergeReader};
+use anyhow::{anyhow, ensure, Result};
+use log::trace;
+use std::{fs::File, path::Path, sync::Mutex};
+use versatiles_core::{
+	io::{DataWriterFile, DataWriterTrait},
+	progress::get_progress_bar,
+	types::*,
+	utils::{ensure_not_being_written, FileLock},
+};
+
+/// Result of [`VersaTilesUpdater::update_path`]
+#[derive(Debug, Default, PartialEq)]
+pub struct UpdateReport {
+	/// Number of blocks copied byte-for-byte from the old container
+	pub copied_blocks: usize,
+	/// Sum of the tiles and tile indexes of the copied blocks
+	pub copied_bytes: u64,
+	/// Number of blocks containing tiles of the update, that were written again
+	pub written_blocks: usize,
+}
+
+/// A struct for updating a VersaTiles container.
+pub struct VersaTilesUpdater {}
+
+impl VersaTilesUpdater {
+	/// Writes the tiles of the container `base` and of `diff` to `output`. Tiles of `diff` replace those of `base`.
+	///
+	/// Both must have the same tile format and compression, so that unchanged blocks can be copied.
+	/// The output has no checksums, even if `base` has them.
+	/// Like [`write_to_filename`](crate::write_to_filename), `output` is locked while it is written, and `base` must
+	/// not be locked by another writer.
+	pub async fn update_path(base: &Path, diff: Box<dyn TilesReaderTrait + '_>, output: &Path) -> Result<UpdateReport> {
+		if output.exists() {
+			ensure!(
+				base.canonicalize()? != output.canonicalize()?,
+				"the updated container must be written to a new file"
+			);
+		}
+
+		ensure_not_being_written(base)?;
+		let _lock = FileLock::lock_for_writing(output)?;
+
+		let base_reader = VersaTilesReader::open_path(base).await?;
+		let base_file = File::open(base)?;
+		let diff_pyramid = diff.get_parameters().bbox_pyramid.clone();
+		trace!("update - diff bbox_pyramid: {diff_pyramid:#}");
+
+		let merged = TilesMergeReader::new(
+			vec![Box::new(VersaTilesReader::open_path(base).await?), diff],
+			TilesMergeParameters {
+				prefer: MergePrefer::Last,
+				require_same_format: true,
+			},
+		)?;
+		let parameters = merged.get_parameters().clone();
+		let bbox_pyramid = &parameters.bbox_pyramid;
+
+		let mut header = FileHeader::new(
+			&parameters.tile_format,
+			&parameters.tile_compression,
+			[
+				bbox_pyramid.get_zoom_min().ok_or(anyhow!("invalid minzoom"))?,
+				bbox_pyramid.get_zoom_max().ok_or(anyhow!("invalid maxzoom"))?,
+			],
+			&bbox_pyramid.get_geo_bbox().ok_or(anyhow!("invalid geo bounding box"))?,
+		)?;
+
+		let mut writer = DataWriterFile::from_path(output)?;
+		writer.append(&header.to_blob()?)?;
+		header.meta_range = VersaTilesWriter::write_meta(&merged, &mut writer, None).await?;
+
+		let blocks: Vec<BlockDefinition> = bbox_pyramid
+			.iter_levels()
+			.flat_map(|level_bbox| {
+				level_bbox
+					.iter_bbox_grid(256)
+					.map(|bbox_block| BlockDefinition::new(&bbox_block))
+			})
+			.collect();
+		let progress = Mutex::new(get_progress_bar(
+			"updating tiles",
+			blocks.iter().map(|block| block.count_tiles()).sum::<u64>(),
+		));
+
+		let mut block_index = BlockIndex::new_empty();
+		let mut report = UpdateReport::default();
+
+		for mut block in blocks.into_iter() {
+			let changed = diff_pyramid
+				.get_level_bbox(block.get_z())
+				.overlaps_bbox(block.get_global_bbox())?;
+
+			if let (false, Some(base_block)) = (changed, base_reader.get_block_index().get_block(block.get_coord3())) {
+				if Self::is_self_contained(&base_reader, base_block).await? {
+					let tiles_range = *base_block.get_tiles_range();
+					let index_length = base_block.get_index_range().length;
+
+					// tiles and tile index of a block are stored consecutively, and the tile index is relative to the tiles
+					let range = writer.append_from_file(
+						&base_file,
+						&ByteRange::new(tiles_range.offset, tiles_range.length + index_length),
+					)?;
+
+					let mut block = base_block.clone();
+					block.set_tiles_range(ByteRange::new(range.offset, tiles_range.length));
+					block.set_index_range(ByteRange::new(range.offset + tiles_range.length, index_length));
+					block_index.add_block(block);
+
+					report.copied_blocks += 1;
+					report.copied_bytes += range.length;
+					progress.lock().unwrap().inc(base_block.count_tiles());
+					continue;
+				}
+			}
+
+			let (tiles_range, index_range, _) =
+				VersaTilesWriter::write_block(&block, &merged, &mut writer, &progress, None, None).await?;
+			if tiles_range.length + index_range.length == 0 {
+				continue;
+			}
+			block.set_tiles_range(tiles_range);
+			block.set_index_range(index_range);
+			block_index.add_block(block);
+			report.written_blocks += 1;
+		}
+
+		progress.into_inner().unwrap().finish();
+
+		header.blocks_range = writer.append(&block_index.as_brotli_blob()?)?;
+		writer.write_start(&header.to_blob()?)?;
+
+		Ok(report)
+	}
+
+	/// Checks that all tiles of the block are stored inside of it.
+	/// Tiles shared with earlier blocks by deduplication would point to wrong positions after copying the block.
+	async fn is_self_contained(reader: &VersaTilesReader, block: &BlockDefinition) -> Result<bool> {
+		let range = block.get_tiles_range();
+		let tile_index = reader.get_block_tile_index(block).await?;
+		let self_contained = tile_index.iter().all(|tile| {
+			tile.length == 0 || (tile.offset >= range.offset && tile.offset + tile.length <= range.offset + range.length)
+		});
+		Ok(self_contained)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MockTilesReader, VersaTilesWriterOptions};
+	use assert_fs::TempDir;
+	use versatiles_core::utils::decompress_gzip;
+
+	fn mock_reader(pyramid: TileBBoxPyramid, tile_format: TileFormat) -> Result<Box<dyn TilesReaderTrait>> {
+		Ok(Box::new(MockTilesReader::new_mock(TilesReaderParameters::new(
+			tile_format,
+			TileCompression::Gzip,
+			pyramid,
+		))?))
+	}
+
+	async fn write_base(dir: &TempDir, options: &VersaTilesWriterOptions) -> Result<std::path::PathBuf> {
+		let path = dir.path().join("base.versatiles");
+		// one block for each of the zoom levels 0-4 and two blocks at zoom level 9
+		let mut pyramid = TileBBoxPyramid::new_full(4);
+		pyramid.include_bbox(&TileBBox::new(9, 0, 0, 300, 0)?);
+		let mut reader = mock_reader(pyramid, TileFormat::PBF)?;
+		VersaTilesWriter::write_to_path_with_options(&mut *reader, &path, options).await?;
+		Ok(path)
+	}
+
+	#[tokio::test]
+	async fn update() -> Result<()> {
+		let dir = TempDir::new()?;
+		let base = write_base(&dir, &VersaTilesWriterOptions::default()).await?;
+		let output = dir.path().join("output.versatiles");
+
+		// tiles in one of the blocks of zoom level 9 and in a new zoom level
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.include_bbox(&TileBBox::new(9, 300, 0, 301, 1)?);
+		pyramid.include_bbox(&TileBBox::new(10, 0, 0, 1, 1)?);
+		let diff = mock_reader(pyramid, TileFormat::PBF)?;
+
+		let report = VersaTilesUpdater::update_path(&base, diff, &output).await?;
+		// the diff changes one block of zoom level 9 and adds one of zoom level 10
+		assert_eq!(report.copied_blocks, 5 + 1);
+		assert_eq!(report.written_blocks, 2);
+
+		let reader = VersaTilesReader::open_path(&output).await?;
+		assert_eq!(
+			format!("{:?}", reader.get_parameters().bbox_pyramid.get_level_bbox(10)),
+			"10: [0,0,1,1] (4)"
+		);
+		for coord in [(0, 0, 0), (15, 1, 4), (100, 0, 9), (301, 1, 9), (1, 1, 10)] {
+			let tile = reader
+				.get_tile_data(&TileCoord3::new(coord.0, coord.1, coord.2)?)
+				.await?;
+			assert!(!decompress_gzip(&tile.unwrap())?.is_empty(), "{coord:?}");
+		}
+		assert!(reader.get_tile_data(&TileCoord3::new(2, 2, 10)?).await?.is_none());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn update_deduplicated() -> Result<()> {
+		// blocks sharing tiles with other blocks are written again, instead of being copied
+		let dir = TempDir::new()?;
+		let options = VersaTilesWriterOptions {
+			deduplicate: true,
+			..Default::default()
+		};
+		let base = write_base(&dir, &options).await?;
+		let output = dir.path().join("output.versatiles");
+
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.include_bbox(&TileBBox::new(9, 0, 0, 0, 0)?);
+		let diff = mock_reader(pyramid, TileFormat::PBF)?;
+		let report = VersaTilesUpdater::update_path(&base, diff, &output).await?;
+		assert_eq!(report.copied_blocks, 1);
+		assert_eq!(report.written_blocks, 6);
+
+		let reader = VersaTilesReader::open_path(&output).await?;
+		assert!(reader.get_tile_data(&TileCoord3::new(300, 0, 9)?).await?.is_some());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn update_errors() -> Result<()> {
+		let dir = TempDir::new()?;
+		let base = write_base(&dir, &VersaTilesWriterOptions::default()).await?;
+
+		let diff = mock_reader(TileBBoxPyramid::new_full(1), TileFormat::PNG)?;
+		let output = dir.path().join("output.versatiles");
+		let error = VersaTilesUpdater::update_path(&base, diff, &output).await.unwrap_err();
+		assert!(error.to_string().starts_with("tile format of"), "{error}");
+
+		let diff = mock_reader(TileBBoxPyramid::new_full(1), TileFormat::PBF)?;
+		assert_eq!(
+			VersaTilesUpdater::update_path(&base, diff, &base)
+				.await
+				.unwrap_err()
+				.to_string(),
+			"the updated container must be written to a new file"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn refuse_file_being_written() -> Result<()> {
+		let dir = TempDir::new()?;
+		let base = write_base(&dir, &VersaTilesWriterOptions::default()).await?;
+		let output = dir.path().join("output.versatiles");
+		let diff = || mock_reader(TileBBoxPyramid::new_full(1), TileFormat::PBF);
+
+		let lock = FileLock::lock_for_writing(&base)?;
+		let error = VersaTilesUpdater::update_path(&base, diff()?, &output)
+			.await
+			.unwrap_err();
+		assert!(error.to_string().contains("is being written by PID"), "{error}");
+		assert!(!output.exists());
+		drop(lock);
+
+		let lock = FileLock::lock_for_writing(&output)?;
+		let error = VersaTilesUpdater::update_path(&base, diff()?, &output)
+			.await
+			.unwrap_err();
+		assert!(error.to_string().contains("is being written by PID"), "{error}");
+		drop(lock);
+
+		VersaTilesUpdater::update_path(&base, diff()?, &output).await?;
+		assert!(!dir.path().join("output.versatiles.lock").exists());
+		Ok(())
+	}
+}
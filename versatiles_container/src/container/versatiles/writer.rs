@@ -25,24 +25,128 @@
 //!     Ok(())
 //! }
 //! ```
-
-use super::types::{BlockDefinition, BlockIndex, FileHeader, TileIndex};
-use crate::TilesWriterTrait;
-use anyhow::{anyhow, Result};
+//!
+//! Blocks are written in parallel by several workers, each into its own temporary file. Every worker reads the tiles
+//! of its block and hands them to a blocking thread, that appends them to the file, hashes them and compresses the
+//! tile index. Afterwards the blocks are appended to the container in their original order, on Linux with
+//! `copy_file_range`, which shares the data on file systems supporting reflinks. Deduplication across blocks needs
+//! the offsets of all earlier tiles, so deduplicated containers are written block by block.
+
+use super::types::{BlockDefinition, BlockIndex, ChecksumAlgorithm, FileHeader, Hasher, IntegritySection, TileIndex};
+use crate::{TilesWriterTrait, WRITE_QUEUE_SIZE};
+use anyhow::{anyhow, ensure, Result};
 use async_trait::async_trait;
+use futures::{future::try_join_all, StreamExt};
 use log::{debug, trace};
-use std::collections::HashMap;
-use versatiles_core::{io::DataWriterTrait, progress::*, types::*, utils::compress};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use sha2::{Digest, Sha256};
+use std::{
+	collections::HashMap,
+	fs::{self, File},
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex,
+	},
+};
+use versatiles_core::{
+	io::{DataWriterFile, DataWriterTrait},
+	progress::*,
+	types::*,
+	utils::{compress, get_temp_dir},
+};
+
+/// Number of tiles a worker may read ahead of the thread writing them into its temporary file.
+const TEMP_WRITE_QUEUE_SIZE: usize = 256;
 
 /// A struct for writing tiles to a VersaTiles container.
 pub struct VersaTilesWriter {}
 
+/// Options for writing a VersaTiles container.
+#[derive(Clone, Default)]
+pub struct VersaTilesWriterOptions {
+	/// Adds checksums of the header, the meta data and every block, so that corrupted files can be detected.
+	pub checksum: Option<ChecksumAlgorithm>,
+	/// Ed25519 key in PKCS#8 (DER) format, used to sign the checksums, so that tampered files can be detected.
+	/// Signed files always use SHA-256 checksums.
+	pub signing_key: Option<Vec<u8>>,
+	/// Stores identical tiles only once across the whole container, e.g. empty ocean tiles, instead of once per block.
+	/// Tiles shared with earlier blocks have negative offsets in the tile index, so these files are marked as
+	/// `versatiles_v03` and older readers refuse them.
+	pub deduplicate: bool,
+	/// Allows zstd compressed tiles. Zstd is an extension of the specification, so older readers can't open these files.
+	pub allow_zstd: bool,
+	/// Directory of the temporary files of the parallel block writers.
+	/// Defaults to the directory of the container, or to the directory set with `set_temp_dir`.
+	pub temp_dir: Option<PathBuf>,
+	/// Number of blocks written at the same time. Defaults to the number of CPUs.
+	pub workers: Option<usize>,
+}
+
+impl VersaTilesWriterOptions {
+	/// Returns the Ed25519 public key that belongs to `signing_key`, e.g. to publish it for verification.
+	pub fn get_public_key(&self) -> Result<Option<Vec<u8>>> {
+		Ok(self
+			.get_key_pair()?
+			.map(|key_pair| key_pair.public_key().as_ref().to_vec()))
+	}
+
+	fn get_key_pair(&self) -> Result<Option<Ed25519KeyPair>> {
+		let Some(key) = &self.signing_key else {
+			return Ok(None);
+		};
+		Ed25519KeyPair::from_pkcs8_maybe_unchecked(key)
+			.map(Some)
+			.map_err(|e| anyhow!("invalid Ed25519 signing key, expected PKCS#8 in DER format: {e}"))
+	}
+}
+
 #[async_trait]
 impl TilesWriterTrait for VersaTilesWriter {
 	/// Convert tiles from the TilesReader and write them to the writer.
 	async fn write_to_writer(reader: &mut dyn TilesReaderTrait, writer: &mut dyn DataWriterTrait) -> Result<()> {
+		Self::write_to_writer_with_options(reader, writer, &VersaTilesWriterOptions::default()).await
+	}
+}
+
+impl VersaTilesWriter {
+	/// Write tile data from a reader to a specified path, e.g. with checksums.
+	pub async fn write_to_path_with_options(
+		reader: &mut dyn TilesReaderTrait,
+		path: &Path,
+		options: &VersaTilesWriterOptions,
+	) -> Result<()> {
+		let mut options = options.clone();
+		if options.temp_dir.is_none() {
+			// temporary files on the same file system can be reflinked into the container
+			options.temp_dir = path.parent().map(Path::to_path_buf);
+		}
+		Self::write_to_writer_with_options(reader, &mut DataWriterFile::from_path(path)?, &options).await
+	}
+
+	/// Write tile data from a reader to a writer, e.g. with checksums.
+	pub async fn write_to_writer_with_options(
+		reader: &mut dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		options: &VersaTilesWriterOptions,
+	) -> Result<()> {
+		let key_pair = options.get_key_pair()?;
+		let algorithm = match (options.checksum, &key_pair) {
+			(None, Some(_)) => Some(ChecksumAlgorithm::Sha256),
+			(algorithm, _) => algorithm,
+		};
+		ensure!(
+			key_pair.is_none() || algorithm == Some(ChecksumAlgorithm::Sha256),
+			"signed files must use SHA-256 checksums"
+		);
+		let mut integrity = algorithm.map(IntegritySection::new);
+
 		// Finalize the configuration
 		let parameters = reader.get_parameters();
+		ensure!(
+			parameters.tile_compression != TileCompression::Zstd || options.allow_zstd,
+			"zstd compression is an extension of the VersaTiles specification and must be allowed explicitly"
+		);
 		trace!("convert_from - reader.parameters: {parameters:?}");
 
 		// Get the bounding box pyramid
@@ -59,6 +163,7 @@ impl TilesWriterTrait for VersaTilesWriter {
 			],
 			&bbox_pyramid.get_geo_bbox().ok_or(anyhow!("invalid geo bounding box"))?,
 		)?;
+		header.shared_tiles = options.deduplicate;
 
 		// Convert the header to a blob and write it
 		let blob: Blob = header.to_blob()?;
@@ -66,33 +171,55 @@ impl TilesWriterTrait for VersaTilesWriter {
 		writer.append(&blob)?;
 
 		trace!("write meta");
-		header.meta_range = Self::write_meta(reader, writer).await?;
+		header.meta_range = Self::write_meta(reader, writer, integrity.as_mut()).await?;
 
 		trace!("write blocks");
-		header.blocks_range = Self::write_blocks(reader, writer).await?;
+		header.blocks_range = Self::write_blocks(reader, writer, integrity.as_mut(), options).await?;
 
 		trace!("update header");
 		let blob: Blob = header.to_blob()?;
+
+		if let Some(mut integrity) = integrity {
+			// the integrity section follows directly after the block index
+			trace!("write integrity section");
+			integrity.header = integrity.algorithm.checksum(blob.as_slice());
+			writer.append(&integrity.to_blob(key_pair.as_ref())?)?;
+		}
+
 		writer.write_start(&blob)?;
 
 		Ok(())
 	}
-}
 
-impl VersaTilesWriter {
 	/// Write metadata to the writer.
-	async fn write_meta(reader: &dyn TilesReaderTrait, writer: &mut dyn DataWriterTrait) -> Result<ByteRange> {
+	pub(super) async fn write_meta(
+		reader: &dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		integrity: Option<&mut IntegritySection>,
+	) -> Result<ByteRange> {
 		let meta: Blob = reader.get_tilejson().into();
 		let compressed = compress(meta, &reader.get_parameters().tile_compression)?;
 
+		if let Some(integrity) = integrity {
+			integrity.meta = integrity.algorithm.checksum(compressed.as_slice());
+		}
+
 		writer.append(&compressed)
 	}
 
 	/// Write blocks to the writer.
-	async fn write_blocks(reader: &mut dyn TilesReaderTrait, writer: &mut dyn DataWriterTrait) -> Result<ByteRange> {
+	async fn write_blocks(
+		reader: &mut dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		mut integrity: Option<&mut IntegritySection>,
+		options: &VersaTilesWriterOptions,
+	) -> Result<ByteRange> {
 		let pyramid = reader.get_parameters().bbox_pyramid.clone();
 
 		if pyramid.is_empty() {
+			if let Some(integrity) = integrity {
+				integrity.block_index = integrity.algorithm.checksum(&[]);
+			}
 			return Ok(ByteRange::empty());
 		}
 
@@ -107,97 +234,356 @@ impl VersaTilesWriter {
 			.collect();
 
 		// Initialize progress bar
-		let mut progress = get_progress_bar(
+		let progress = Mutex::new(get_progress_bar(
 			"converting tiles",
 			blocks.iter().map(|block| block.count_tiles()).sum::<u64>(),
-		);
+		));
+
+		let algorithm = integrity.as_ref().map(|integrity| integrity.algorithm);
+		let written_blocks = if options.deduplicate {
+			Self::write_blocks_sequential(blocks, reader, writer, &progress, algorithm).await?
+		} else {
+			let temp_dir = options.temp_dir.clone().unwrap_or_else(get_temp_dir);
+			let workers = options.workers.unwrap_or_else(num_cpus::get);
+			Self::write_blocks_parallel(blocks, reader, writer, &progress, algorithm, &temp_dir, workers).await?
+		};
 
 		// Create the block index
 		let mut block_index = BlockIndex::new_empty();
-		let mut tiles_count = 0;
+		for (block, checksum) in written_blocks {
+			if let (Some(integrity), Some(checksum)) = (integrity.as_mut(), checksum) {
+				integrity.blocks.push((*block.get_coord3(), checksum));
+			}
+			block_index.add_block(block);
+		}
+
+		// Finish updating progress and write the block index
+		progress.into_inner().unwrap().finish();
+
+		let blob = block_index.as_brotli_blob()?;
+		if let Some(integrity) = integrity {
+			integrity.block_index = integrity.algorithm.checksum(blob.as_slice());
+		}
+		let range = writer.append(&blob)?;
+
+		Ok(range)
+	}
+
+	/// Write blocks one after another, deduplicating tiles across all blocks.
+	/// Returns the non-empty blocks with their ranges, and their checksums if an `algorithm` is given.
+	async fn write_blocks_sequential(
+		blocks: Vec<BlockDefinition>,
+		reader: &dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		progress: &Mutex<Box<dyn ProgressTrait>>,
+		algorithm: Option<ChecksumAlgorithm>,
+	) -> Result<Vec<(BlockDefinition, Option<Vec<u8>>)>> {
+		// absolute byte ranges of all tiles written so far, by the hash of their content
+		let mut shared_tiles: HashMap<[u8; 32], ByteRange> = HashMap::new();
+		let mut written_blocks = Vec::new();
 
-		// Iterate through blocks and write them
 		for mut block in blocks.into_iter() {
-			let (tiles_range, index_range) = Self::write_block(&block, reader, writer, &mut progress).await?;
+			let (tiles_range, index_range, checksum) =
+				Self::write_block(&block, reader, writer, progress, algorithm, Some(&mut shared_tiles)).await?;
 
 			if tiles_range.length + index_range.length == 0 {
 				// Block is empty, continue with the next block
 				continue;
 			}
 
-			tiles_count += block.count_tiles();
-			progress.set_position(tiles_count);
-
-			// Update the block with the tile and index range and add it to the block index
 			block.set_tiles_range(tiles_range);
 			block.set_index_range(index_range);
-			block_index.add_block(block);
+			written_blocks.push((block, checksum));
 		}
 
-		// Finish updating progress and write the block index
-		progress.finish();
+		Ok(written_blocks)
+	}
 
-		let range = writer.append(&block_index.as_brotli_blob()?)?;
+	/// Write blocks in parallel, each of the `workers` into its own temporary file in `temp_dir`.
+	/// The blocks are then appended to the writer in their original order, so the result is the same as writing them
+	/// one after another.
+	/// Returns the non-empty blocks with their ranges, and their checksums if an `algorithm` is given.
+	async fn write_blocks_parallel(
+		blocks: Vec<BlockDefinition>,
+		reader: &dyn TilesReaderTrait,
+		writer: &mut dyn DataWriterTrait,
+		progress: &Mutex<Box<dyn ProgressTrait>>,
+		algorithm: Option<ChecksumAlgorithm>,
+		temp_dir: &Path,
+		workers: usize,
+	) -> Result<Vec<(BlockDefinition, Option<Vec<u8>>)>> {
+		let worker_count = workers.clamp(1, blocks.len().max(1));
+		let temp_files = (0..worker_count)
+			.map(|_| TempBlockFile::new(temp_dir))
+			.collect::<Vec<_>>();
+		trace!("write {} blocks with {worker_count} workers", blocks.len());
+
+		// Every worker takes the next block, until all blocks are written
+		let next_block = &AtomicUsize::new(0);
+		let blocks = &blocks;
+		let workers = temp_files.iter().map(|temp_file| async move {
+			let mut temp_writer = DataWriterFile::from_path(&temp_file.path)?;
+			let mut results = Vec::new();
+			loop {
+				let index = next_block.fetch_add(1, Ordering::Relaxed);
+				let Some(block) = blocks.get(index) else {
+					break;
+				};
+				let result;
+				(temp_writer, result) = Self::write_block_to_file(block, reader, temp_writer, progress, algorithm).await?;
+				results.push((index, result));
+			}
+			Ok::<_, anyhow::Error>(results)
+		});
+		let results = try_join_all(workers).await?;
+
+		// Restore the original order of the blocks, remembering which worker has written them
+		let mut results = results
+			.into_iter()
+			.enumerate()
+			.flat_map(|(worker, results)| results.into_iter().map(move |result| (worker, result)))
+			.collect::<Vec<_>>();
+		results.sort_by_key(|(_, (index, _))| *index);
+
+		let files = temp_files
+			.iter()
+			.map(|temp_file| File::open(&temp_file.path))
+			.collect::<std::io::Result<Vec<_>>>()?;
+
+		let mut written_blocks = Vec::new();
+		for (worker, (index, (tiles_range, index_range, checksum))) in results {
+			if tiles_range.length + index_range.length == 0 {
+				// Block is empty, continue with the next block
+				continue;
+			}
 
-		Ok(range)
+			// the tile index follows the tiles and is relative to them, so both can be copied together
+			let range = writer.append_from_file(
+				&files[worker],
+				&ByteRange::new(tiles_range.offset, tiles_range.length + index_range.length),
+			)?;
+
+			let mut block = blocks[index].clone();
+			block.set_tiles_range(ByteRange::new(range.offset, tiles_range.length));
+			block.set_index_range(ByteRange::new(range.offset + tiles_range.length, index_range.length));
+			written_blocks.push((block, checksum));
+		}
+
+		Ok(written_blocks)
 	}
 
 	/// Write a single block to the writer.
-	async fn write_block(
+	/// Returns the ranges of tiles and tile index, and their checksum if an `algorithm` is given.
+	///
+	/// If `shared_tiles` is given, tiles are deduplicated across blocks instead of only small tiles within the block.
+	pub(super) async fn write_block(
 		block: &BlockDefinition,
-		reader: &mut dyn TilesReaderTrait,
+		reader: &dyn TilesReaderTrait,
 		writer: &mut dyn DataWriterTrait,
-		progress: &mut Box<dyn ProgressTrait>,
-	) -> Result<(ByteRange, ByteRange)> {
+		progress: &Mutex<Box<dyn ProgressTrait>>,
+		algorithm: Option<ChecksumAlgorithm>,
+		mut shared_tiles: Option<&mut HashMap<[u8; 32], ByteRange>>,
+	) -> Result<(ByteRange, ByteRange, Option<Vec<u8>>)> {
 		// Log the start of the block
 		debug!("start block {:?}", block);
 
-		// Get the initial writer position
-		let offset0 = writer.get_position()?;
-
-		// Prepare the necessary data structures
 		let bbox = &block.get_global_bbox().clone();
-
-		let mut tile_index = TileIndex::new_empty(bbox.count_tiles() as usize);
-		let mut tile_hash_lookup: HashMap<Vec<u8>, ByteRange> = HashMap::new();
+		let mut serializer = BlockSerializer::new(bbox, writer, algorithm)?;
+		let mut tiles_count = 0;
 
 		// Get the tile stream
 		let tile_stream: TileStream = reader.get_bbox_tile_stream(bbox.clone()).await;
 
 		// Iterate through the blobs and process them
 		tile_stream
-			.for_each_sync(|(coord, blob)| {
-				progress.inc(1);
+			.for_each_queued(WRITE_QUEUE_SIZE, |(coord, blob)| {
+				progress.lock().unwrap().inc(1);
+				tiles_count += 1;
+				serializer.add_tile(writer, &coord, blob, shared_tiles.as_deref_mut())
+			})
+			.await?;
 
-				let index = bbox.get_tile_index2(&coord.as_coord2()).unwrap();
+		// Finish the block and write the index
+		debug!("finish block and write index {:?}", block);
+		progress
+			.lock()
+			.unwrap()
+			.inc(block.count_tiles().saturating_sub(tiles_count));
 
-				let mut save_hash = false;
-				if blob.len() < 1000 {
-					if let Some(range) = tile_hash_lookup.get(blob.as_slice()) {
-						tile_index.set(index, *range);
-						return;
-					}
-					save_hash = true;
-				}
+		serializer.finish(writer)
+	}
 
-				let mut range = writer.append(&blob).unwrap();
-				range.shift_backward(offset0);
+	/// Write a single block to the temporary file of a worker.
+	/// The tiles are read here, but written, hashed and indexed on a blocking thread, so that several workers
+	/// serialize their blocks at the same time.
+	/// Returns the file for the next block, together with the result of `write_block`.
+	async fn write_block_to_file(
+		block: &BlockDefinition,
+		reader: &dyn TilesReaderTrait,
+		mut writer: DataWriterFile,
+		progress: &Mutex<Box<dyn ProgressTrait>>,
+		algorithm: Option<ChecksumAlgorithm>,
+	) -> Result<(DataWriterFile, (ByteRange, ByteRange, Option<Vec<u8>>))> {
+		debug!("start block {:?}", block);
 
-				tile_index.set(index, range);
+		let bbox = block.get_global_bbox().clone();
+		let (sender, mut receiver) = tokio::sync::mpsc::channel::<(TileCoord3, Blob)>(TEMP_WRITE_QUEUE_SIZE);
 
-				if save_hash {
-					tile_hash_lookup.insert(blob.into_vec(), range);
+		let serialize_bbox = bbox.clone();
+		let serialize = tokio::task::spawn_blocking(move || {
+			let result = (|| {
+				let mut serializer = BlockSerializer::new(&serialize_bbox, &mut writer, algorithm)?;
+				while let Some((coord, blob)) = receiver.blocking_recv() {
+					serializer.add_tile(&mut writer, &coord, blob, None)?;
 				}
-			})
-			.await;
+				serializer.finish(&mut writer)
+			})();
+			result.map(|result| (writer, result))
+		});
+
+		let mut tiles_count = 0;
+		let mut tile_stream = reader.get_bbox_tile_stream(bbox).await.stream;
+		while let Some(entry) = tile_stream.next().await {
+			progress.lock().unwrap().inc(1);
+			tiles_count += 1;
+			if sender.send(entry).await.is_err() {
+				// the thread has stopped because of an error, which is returned below
+				break;
+			}
+		}
+		drop(sender);
+		let result = serialize.await??;
 
-		// Finish the block and write the index
 		debug!("finish block and write index {:?}", block);
+		progress
+			.lock()
+			.unwrap()
+			.inc(block.count_tiles().saturating_sub(tiles_count));
 
+		Ok(result)
+	}
+}
+
+/// Appends the tiles of a block to a writer and builds its tile index.
+struct BlockSerializer<'a> {
+	bbox: &'a TileBBox,
+	/// position of the first tile of the block
+	offset0: u64,
+	tile_index: TileIndex,
+	/// small tiles written in this block, to store identical ones only once
+	tile_hash_lookup: HashMap<Vec<u8>, ByteRange>,
+	hasher: Option<Hasher>,
+}
+
+impl<'a> BlockSerializer<'a> {
+	fn new(
+		bbox: &'a TileBBox,
+		writer: &mut dyn DataWriterTrait,
+		algorithm: Option<ChecksumAlgorithm>,
+	) -> Result<BlockSerializer<'a>> {
+		Ok(BlockSerializer {
+			bbox,
+			offset0: writer.get_position()?,
+			tile_index: TileIndex::new_empty(bbox.count_tiles() as usize),
+			tile_hash_lookup: HashMap::new(),
+			hasher: algorithm.map(|algorithm| algorithm.hasher()),
+		})
+	}
+
+	/// Appends a tile, unless an identical tile has already been written.
+	/// If `shared_tiles` is given, tiles are deduplicated across blocks instead of only small tiles within the block.
+	fn add_tile(
+		&mut self,
+		writer: &mut dyn DataWriterTrait,
+		coord: &TileCoord3,
+		blob: Blob,
+		shared_tiles: Option<&mut HashMap<[u8; 32], ByteRange>>,
+	) -> Result<()> {
+		let index = self.bbox.get_tile_index2(&coord.as_coord2())?;
+
+		if let Some(shared_tiles) = shared_tiles {
+			let hash: [u8; 32] = Sha256::digest(blob.as_slice()).into();
+			let range = match shared_tiles.get(&hash) {
+				Some(range) => *range,
+				None => {
+					let range = writer.append(&blob)?;
+					if let Some(hasher) = self.hasher.as_mut() {
+						hasher.update(blob.as_slice());
+					}
+					shared_tiles.insert(hash, range);
+					range
+				}
+			};
+			// tiles of earlier blocks get a negative offset, see `TileIndex::add_offset_shared`
+			self.tile_index.set(
+				index,
+				ByteRange::new(range.offset.wrapping_sub(self.offset0), range.length),
+			);
+			return Ok(());
+		}
+
+		let mut save_hash = false;
+		if blob.len() < 1000 {
+			if let Some(range) = self.tile_hash_lookup.get(blob.as_slice()) {
+				self.tile_index.set(index, *range);
+				return Ok(());
+			}
+			save_hash = true;
+		}
+
+		let mut range = writer.append(&blob)?;
+		if let Some(hasher) = self.hasher.as_mut() {
+			hasher.update(blob.as_slice());
+		}
+		range.shift_backward(self.offset0);
+
+		self.tile_index.set(index, range);
+
+		if save_hash {
+			self.tile_hash_lookup.insert(blob.into_vec(), range);
+		}
+		Ok(())
+	}
+
+	/// Appends the tile index.
+	/// Returns the ranges of tiles and tile index, and their checksum if the serializer has a hasher.
+	fn finish(self, writer: &mut dyn DataWriterTrait) -> Result<(ByteRange, ByteRange, Option<Vec<u8>>)> {
 		// Get the final writer position
 		let offset1 = writer.get_position()?;
-		let index_range = writer.append(&tile_index.as_brotli_blob()?)?;
+		let index_blob = self.tile_index.as_brotli_blob()?;
+		let index_range = writer.append(&index_blob)?;
+		let checksum = self.hasher.map(|mut hasher| {
+			hasher.update(index_blob.as_slice());
+			hasher.finish()
+		});
+
+		Ok((
+			ByteRange::new(self.offset0, offset1 - self.offset0),
+			index_range,
+			checksum,
+		))
+	}
+}
+
+/// Temporary file of a worker writing blocks. The file is removed when dropped.
+struct TempBlockFile {
+	path: PathBuf,
+}
+
+impl TempBlockFile {
+	fn new(dir: &Path) -> TempBlockFile {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+		let name = format!(
+			".versatiles-blocks-{}-{}.tmp",
+			std::process::id(),
+			COUNTER.fetch_add(1, Ordering::Relaxed)
+		);
+		TempBlockFile { path: dir.join(name) }
+	}
+}
 
-		Ok((ByteRange::new(offset0, offset1 - offset0), index_range))
+impl Drop for TempBlockFile {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
 	}
 }
@@ -9,6 +9,9 @@ use async_trait::async_trait;
 use std::path::Path;
 use versatiles_core::{io::*, types::TilesReaderTrait};
 
+/// Number of tiles that writers read ahead while writing, see `TileStream::for_each_queued`.
+pub(crate) const WRITE_QUEUE_SIZE: usize = 1024;
+
 /// Trait defining the behavior of a tile writer.
 #[async_trait]
 pub trait TilesWriterTrait: Send {
@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use futures::executor::block_on;
+use versatiles_core::{
+	io::{DataReaderBlob, DataReaderTrait},
+	types::{Blob, ByteRange},
+};
+
+const DATA_SIZE: u64 = 16 * 1024 * 1024;
+const TILE_SIZE: u64 = 64 * 1024;
+
+fn ranges() -> impl Iterator<Item = ByteRange> {
+	(0..DATA_SIZE / TILE_SIZE).map(|i| ByteRange::new(i * TILE_SIZE, TILE_SIZE))
+}
+
+fn bench_read_range(c: &mut Criterion) {
+	let blob = Blob::from(vec![b'a'; DATA_SIZE as usize]);
+	c.bench_function("Blob read_range", |b| {
+		b.iter(|| {
+			for range in ranges() {
+				black_box(blob.read_range(&range).unwrap());
+			}
+		})
+	});
+}
+
+fn bench_data_reader_blob(c: &mut Criterion) {
+	let reader = DataReaderBlob::from(Blob::from(vec![b'a'; DATA_SIZE as usize]));
+	c.bench_function("DataReaderBlob read_range", |b| {
+		b.iter(|| {
+			for range in ranges() {
+				black_box(block_on(reader.read_range(&range)).unwrap());
+			}
+		})
+	});
+}
+
+criterion_group!(
+	name = benches;
+	config = Criterion::default().sample_size(20);
+	targets = bench_read_range, bench_data_reader_blob
+);
+criterion_main!(benches);
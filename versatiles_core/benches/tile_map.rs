@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use versatiles_core::types::{TileCoord3, TileMap};
+
+// all tiles of zoom levels 0-10, about 1.4 million, e.g. the index of a planet extract
+fn coords() -> impl Iterator<Item = TileCoord3> {
+	(0..=10u8).flat_map(|z| {
+		let size = 1u32 << z;
+		(0..size).flat_map(move |y| (0..size).map(move |x| TileCoord3::new(x, y, z).unwrap()))
+	})
+}
+
+fn bench_insert(c: &mut Criterion) {
+	let mut group = c.benchmark_group("insert");
+	group.bench_function("HashMap<TileCoord3>", |b| {
+		b.iter(|| {
+			let mut map = HashMap::new();
+			for coord in coords() {
+				map.insert(coord, coord.x);
+			}
+			black_box(map)
+		})
+	});
+	group.bench_function("TileMap", |b| {
+		b.iter(|| {
+			let mut map = TileMap::new();
+			for coord in coords() {
+				map.insert(&coord, coord.x);
+			}
+			black_box(map)
+		})
+	});
+	group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion) {
+	let mut group = c.benchmark_group("lookup");
+	let hash_map: HashMap<TileCoord3, u32> = coords().map(|c| (c, c.x)).collect();
+	group.bench_function("HashMap<TileCoord3>", |b| {
+		b.iter(|| {
+			let mut sum = 0u64;
+			for coord in coords() {
+				sum += *hash_map.get(&coord).unwrap() as u64;
+			}
+			black_box(sum)
+		})
+	});
+	let tile_map: TileMap<u32> = coords().map(|c| (c, c.x)).collect();
+	group.bench_function("TileMap", |b| {
+		b.iter(|| {
+			let mut sum = 0u64;
+			for coord in coords() {
+				sum += *tile_map.get(&coord).unwrap() as u64;
+			}
+			black_box(sum)
+		})
+	});
+	group.finish();
+}
+
+criterion_group!(
+	name = benches;
+	config = Criterion::default().sample_size(10);
+	targets = bench_insert, bench_lookup
+);
+criterion_main!(benches);
@@ -42,18 +42,18 @@ use std::io::{Cursor, Read};
 /// A struct that provides reading capabilities from an in-memory blob of data.
 #[derive(Debug)]
 pub struct DataReaderBlob {
-	blob: Cursor<Vec<u8>>,
+	blob: Cursor<Blob>,
 }
 
 impl DataReaderBlob {
 	/// Returns the length of the data in the reader.
 	pub fn len(&self) -> usize {
-		self.blob.get_ref().len()
+		self.blob.get_ref().len() as usize
 	}
 
 	/// Checks if the reader is empty.
 	pub fn is_empty(&self) -> bool {
-		self.blob.get_ref().len() == 0
+		self.blob.get_ref().is_empty()
 	}
 }
 
@@ -73,11 +73,11 @@ impl DataReaderTrait for DataReaderBlob {
 		let end = (range.offset + range.length) as usize;
 		let blob = self.blob.get_ref();
 		ensure!(
-			end <= blob.len(),
+			end <= blob.len() as usize,
 			"end of range ({start}..{end}) is outside blob ({})",
 			blob.len()
 		);
-		Ok(Blob::from(&blob[start..end]))
+		Ok(blob.slice(start..end))
 	}
 
 	/// Reads all the data from the reader.
@@ -86,7 +86,7 @@ impl DataReaderTrait for DataReaderBlob {
 	///
 	/// * A Result containing a Blob with all the data or an error.
 	async fn read_all(&self) -> Result<Blob> {
-		Ok(Blob::from(self.blob.get_ref()))
+		Ok(self.blob.get_ref().clone())
 	}
 
 	/// Gets the name of the data source.
@@ -156,7 +156,7 @@ impl From<Blob> for DataReaderBlob {
 	/// * A new DataReaderBlob.
 	fn from(value: Blob) -> Self {
 		DataReaderBlob {
-			blob: Cursor::new(value.into_vec()),
+			blob: Cursor::new(value),
 		}
 	}
 }
@@ -173,7 +173,7 @@ impl From<Vec<u8>> for DataReaderBlob {
 	/// * A new DataReaderBlob.
 	fn from(value: Vec<u8>) -> Self {
 		DataReaderBlob {
-			blob: Cursor::new(value),
+			blob: Cursor::new(Blob::from(value)),
 		}
 	}
 }
@@ -0,0 +1,154 @@
+//! This module provides functionality for reading data with an injected range fetcher.
+//!
+//! # Overview
+//!
+//! The `DataReaderFetch` struct reads data with an implementation of `RangeFetchTrait`, which only has to
+//! request byte ranges, e.g. with the `fetch` API of a browser or web worker. This allows reading
+//! `.versatiles` and `.pmtiles` files in environments without a native HTTP client, like `wasm32-unknown-unknown`.
+//!
+//! `DataReaderTrait` requires `Send` futures. Browsers run a web worker on a single thread, so the
+//! futures of the `fetch` API can be wrapped, e.g. with `send_wrapper::SendWrapper`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use versatiles_core::{io::{DataReaderFetch, DataReaderTrait, RangeFetchTrait}, types::{Blob, ByteRange}};
+//! use anyhow::Result;
+//! use async_trait::async_trait;
+//!
+//! #[derive(Debug)]
+//! struct StaticFetch(Vec<u8>);
+//!
+//! #[async_trait]
+//! impl RangeFetchTrait for StaticFetch {
+//!     async fn fetch_range(&self, range: &ByteRange) -> Result<Blob> {
+//!         Ok(Blob::from(self.0[range.as_range_usize()].to_vec()))
+//!     }
+//!     async fn fetch_all(&self) -> Result<Blob> {
+//!         Ok(Blob::from(self.0.clone()))
+//!     }
+//!     fn get_name(&self) -> &str {
+//!         "static"
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let reader = DataReaderFetch::new(StaticFetch(vec![1, 2, 3, 4, 5]));
+//!     assert_eq!(reader.read_range(&ByteRange::new(1, 3)).await?.as_slice(), &[2, 3, 4]);
+//!     Ok(())
+//! }
+//! ```
+
+use super::DataReaderTrait;
+use crate::types::{Blob, ByteRange};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use std::fmt::Debug;
+
+/// A trait for requesting byte ranges of a remote file, e.g. with HTTP range requests.
+#[async_trait]
+pub trait RangeFetchTrait: Debug + Send + Sync {
+	/// Fetches exactly the bytes of `range`.
+	async fn fetch_range(&self, range: &ByteRange) -> Result<Blob>;
+
+	/// Fetches the whole file.
+	async fn fetch_all(&self) -> Result<Blob>;
+
+	/// Gets the name of the file, e.g. its URL.
+	fn get_name(&self) -> &str;
+}
+
+/// A struct that provides reading capabilities with an injected `RangeFetchTrait`.
+#[derive(Debug)]
+pub struct DataReaderFetch {
+	fetch: Box<dyn RangeFetchTrait>,
+}
+
+impl DataReaderFetch {
+	/// Creates a boxed `DataReaderFetch` that reads with `fetch`.
+	pub fn new(fetch: impl RangeFetchTrait + 'static) -> Box<DataReaderFetch> {
+		Box::new(DataReaderFetch { fetch: Box::new(fetch) })
+	}
+}
+
+#[async_trait]
+impl DataReaderTrait for DataReaderFetch {
+	/// Reads a specific range of bytes with the fetcher.
+	///
+	/// # Arguments
+	///
+	/// * `range` - A ByteRange struct specifying the offset and length of the range to read.
+	///
+	/// # Returns
+	///
+	/// * A Result containing a Blob with the read data or an error.
+	async fn read_range(&self, range: &ByteRange) -> Result<Blob> {
+		if range.length == 0 {
+			return Ok(Blob::new_empty());
+		}
+		let blob = self.fetch.fetch_range(range).await?;
+		ensure!(
+			blob.len() == range.length,
+			"fetching {range:?} of {} returned {} bytes",
+			self.fetch.get_name(),
+			blob.len()
+		);
+		Ok(blob)
+	}
+
+	/// Reads all the data with the fetcher.
+	///
+	/// # Returns
+	///
+	/// * A Result containing a Blob with all the data or an error.
+	async fn read_all(&self) -> Result<Blob> {
+		self.fetch.fetch_all().await
+	}
+
+	/// Gets the name of the data source.
+	///
+	/// # Returns
+	///
+	/// * A string slice representing the name of the data source.
+	fn get_name(&self) -> &str {
+		self.fetch.get_name()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Returns the bytes 0 to 9.
+	#[derive(Debug)]
+	struct MockFetch;
+
+	#[async_trait]
+	impl RangeFetchTrait for MockFetch {
+		async fn fetch_range(&self, range: &ByteRange) -> Result<Blob> {
+			let end = (range.offset + range.length).min(10);
+			Ok(Blob::from((range.offset as u8..end as u8).collect::<Vec<u8>>()))
+		}
+		async fn fetch_all(&self) -> Result<Blob> {
+			Ok(Blob::from((0..10).collect::<Vec<u8>>()))
+		}
+		fn get_name(&self) -> &str {
+			"mock"
+		}
+	}
+
+	#[tokio::test]
+	async fn read_range() -> Result<()> {
+		let reader = DataReaderFetch::new(MockFetch);
+		assert_eq!(reader.get_name(), "mock");
+		assert_eq!(reader.read_range(&ByteRange::new(2, 3)).await?.as_slice(), &[2, 3, 4]);
+		assert_eq!(reader.read_range(&ByteRange::new(5, 0)).await?.len(), 0);
+		assert_eq!(reader.read_all().await?.len(), 10);
+		assert_eq!(
+			reader.read_range(&ByteRange::new(8, 4)).await.unwrap_err().to_string(),
+			"fetching ByteRange[8,4] of mock returned 2 bytes"
+		);
+		Ok(())
+	}
+}
@@ -6,6 +6,16 @@
 //! `DataReaderTrait` to provide asynchronous reading capabilities. The module ensures the URL has
 //! a valid scheme (`http` or `https`) and uses the `reqwest` library to handle HTTP requests.
 //!
+//! Failed requests (network errors, `429` and `5xx` responses) are retried with exponential backoff.
+//! Small reads are extended to aligned chunks of [`HttpReadOptions::prefetch_size`] bytes, which are cached,
+//! so that reading many adjacent small ranges, e.g. the tiles of a remote `.versatiles` file, needs only few requests.
+//! Adjacent missing chunks are coalesced into a single request.
+//!
+//! Responses are verified: `Content-Range` must match the requested range, `Content-Length` must match
+//! `Content-Range`, and the total file size must not change between requests. Bodies that end early are
+//! completed by requesting only the missing bytes. If the server ignores the `Range` header and sends the
+//! whole file, it is downloaded once into a temporary file, see [`HttpReadOptions::download_fallback`].
+//!
 //! # Examples
 //!
 //! ```rust
@@ -27,14 +37,86 @@
 //! }
 //! ```
 
-use super::DataReaderTrait;
-use crate::types::{Blob, ByteRange};
-use anyhow::{bail, Result};
+use super::{DataReaderFile, DataReaderTrait};
+use crate::{
+	metrics::counters,
+	types::{Blob, ByteRange, LimitedCache},
+};
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
 use lazy_static::lazy_static;
+use log::warn;
 use regex::{Regex, RegexBuilder};
 use reqwest::{Client, Method, Request, StatusCode, Url};
-use std::{ops::Deref, str, time::Duration};
+use std::{
+	fs::File,
+	io::Write,
+	mem::size_of,
+	path::{Path, PathBuf},
+	str,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex, OnceLock,
+	},
+	time::Duration,
+};
+use tokio::sync::OnceCell;
+
+/// Options for reading from an HTTP(S) endpoint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpReadOptions {
+	/// Number of retries after a request failed with a network error, `429` or `5xx`. Default: 3
+	pub max_retries: u32,
+	/// Delay before the first retry, doubled for every further retry. Default: 500 ms
+	pub initial_backoff: Duration,
+	/// Reads smaller than this are extended to aligned chunks of this size, which are cached.
+	/// `0` disables prefetching. Default: 64 KiB
+	pub prefetch_size: u64,
+	/// Maximum number of bytes of cached chunks. Default: 16 MiB
+	pub cache_size: u64,
+	/// If the server ignores range requests, download the whole file into a temporary file and read from it.
+	/// Otherwise such servers are an error. Default: `true`
+	pub download_fallback: bool,
+}
+
+impl Default for HttpReadOptions {
+	fn default() -> Self {
+		HttpReadOptions {
+			max_retries: 3,
+			initial_backoff: Duration::from_millis(500),
+			prefetch_size: 64 * 1024,
+			cache_size: 16 * 1024 * 1024,
+			download_fallback: true,
+		}
+	}
+}
+
+/// Result of a single request.
+enum Attempt {
+	Done(Blob),
+	/// the response ended early, the rest of the range has to be requested again
+	Truncated(Blob, anyhow::Error),
+	/// the request failed, but might succeed if it is repeated
+	Retry(anyhow::Error),
+	/// the server ignored the `Range` header and responded with the whole file
+	RangeIgnored,
+}
+
+/// The whole file, downloaded because the server ignores range requests. The file is removed when dropped.
+#[derive(Debug)]
+struct Download {
+	path: PathBuf,
+	reader: Box<DataReaderFile>,
+	size: u64,
+}
+
+impl Drop for Download {
+	fn drop(&mut self) {
+		if let Err(error) = std::fs::remove_file(&self.path) {
+			warn!("could not remove temporary file {:?}: {error}", self.path);
+		}
+	}
+}
 
 /// A struct that provides reading capabilities from an HTTP(S) endpoint.
 #[derive(Debug)]
@@ -42,10 +124,17 @@ pub struct DataReaderHttp {
 	client: Client,
 	name: String,
 	url: Url,
+	options: HttpReadOptions,
+	/// prefetched chunks by their index
+	chunks: Mutex<LimitedCache<u64, Blob>>,
+	/// total size of the file, as reported by the first response
+	file_size: OnceLock<u64>,
+	/// the whole file, if the server ignores range requests
+	download: OnceCell<Download>,
 }
 
 impl DataReaderHttp {
-	/// Creates a `DataReaderHttp` from a URL.
+	/// Creates a `DataReaderHttp` from a URL, using the default [`HttpReadOptions`].
 	///
 	/// # Arguments
 	///
@@ -55,6 +144,20 @@ impl DataReaderHttp {
 	///
 	/// * A Result containing a boxed `DataReaderHttp` or an error.
 	pub fn from_url(url: Url) -> Result<Box<DataReaderHttp>> {
+		Self::from_url_with_options(url, HttpReadOptions::default())
+	}
+
+	/// Creates a `DataReaderHttp` from a URL.
+	///
+	/// # Arguments
+	///
+	/// * `url` - The URL of the HTTP(S) endpoint.
+	/// * `options` - Retry and prefetch options.
+	///
+	/// # Returns
+	///
+	/// * A Result containing a boxed `DataReaderHttp` or an error.
+	pub fn from_url_with_options(url: Url, options: HttpReadOptions) -> Result<Box<DataReaderHttp>> {
 		match url.scheme() {
 			"http" | "https" => (),
 			_ => bail!("url has wrong scheme {url}"),
@@ -67,39 +170,96 @@ impl DataReaderHttp {
 			.use_rustls_tls()
 			.build()?;
 
+		let max_chunks = (options.cache_size / options.prefetch_size.max(1)).max(1) as usize;
+		let chunks = LimitedCache::with_maximum_size(max_chunks * (size_of::<u64>() + size_of::<Blob>()));
+
 		Ok(Box::new(DataReaderHttp {
 			client,
 			name: url.to_string(),
 			url,
+			options,
+			chunks: Mutex::new(chunks),
+			file_size: OnceLock::new(),
+			download: OnceCell::new(),
 		}))
 	}
-}
 
-#[async_trait]
-impl DataReaderTrait for DataReaderHttp {
-	/// Reads a specific range of bytes from the HTTP(S) endpoint.
+	/// Requests a range, retrying failed requests with exponential backoff.
+	/// If a response ends early, only the missing bytes are requested again.
 	///
-	/// # Arguments
-	///
-	/// * `range` - A ByteRange struct specifying the offset and length of the range to read.
-	///
-	/// # Returns
-	///
-	/// * A Result containing a Blob with the read data or an error.
-	async fn read_range(&self, range: &ByteRange) -> Result<Blob> {
+	/// The response may end early, e.g. at the end of the file, but must contain at least `min_length` bytes.
+	async fn fetch(&self, range: &ByteRange, min_length: u64) -> Result<Blob> {
+		let mut backoff = self.options.initial_backoff;
+		let mut attempt = 0;
+		let mut data: Vec<u8> = Vec::new();
+		loop {
+			let received = data.len() as u64;
+			let remaining = ByteRange::new(range.offset + received, range.length - received);
+			let error = match self
+				.fetch_once(&remaining, min_length.saturating_sub(received).max(1))
+				.await?
+			{
+				Attempt::Done(blob) if data.is_empty() => return Ok(blob),
+				Attempt::Done(blob) => {
+					data.extend_from_slice(blob.as_slice());
+					return Ok(Blob::from(data));
+				}
+				Attempt::Truncated(blob, error) => {
+					if !blob.is_empty() {
+						// the server makes progress, so don't give up
+						data.extend_from_slice(blob.as_slice());
+						attempt = 0;
+						backoff = self.options.initial_backoff;
+					}
+					error
+				}
+				Attempt::Retry(error) => error,
+				Attempt::RangeIgnored => return self.read_downloaded(range, min_length).await,
+			};
+
+			if attempt >= self.options.max_retries {
+				return Err(error.context(format!(
+					"range request {range:?} to {} failed after {} attempts",
+					self.url,
+					attempt + 1
+				)));
+			}
+			warn!("range request {remaining:?} to {} failed, retrying: {error}", self.url);
+			counters().http_retries.inc();
+			tokio::time::sleep(backoff).await;
+			backoff *= 2;
+			attempt += 1;
+		}
+	}
+
+	async fn fetch_once(&self, range: &ByteRange, min_length: u64) -> Result<Attempt> {
+		if self.download.initialized() {
+			return Ok(Attempt::RangeIgnored);
+		}
+
 		let mut request = Request::new(Method::GET, self.url.clone());
 		let request_range: String = format!("bytes={}-{}", range.offset, range.length + range.offset - 1);
 		request.headers_mut().append("range", request_range.parse()?);
 
-		let response = self.client.execute(request).await?;
+		counters().http_requests.inc();
+		let mut response = match self.client.execute(request).await {
+			Ok(response) => response,
+			Err(error) => return Ok(Attempt::Retry(error.into())),
+		};
 
-		if response.status() != StatusCode::PARTIAL_CONTENT {
-			let status_code = response.status();
+		let status_code = response.status();
+		if status_code == StatusCode::TOO_MANY_REQUESTS || status_code.is_server_error() {
+			return Ok(Attempt::Retry(anyhow!("server responded with {status_code}")));
+		}
+		if status_code == StatusCode::OK && self.options.download_fallback {
+			return Ok(Attempt::RangeIgnored);
+		}
+		if status_code != StatusCode::PARTIAL_CONTENT {
 			bail!("expected 206 as a response to a range request. instead we got {status_code}");
 		}
 
-		let content_range: &str = match response.headers().get("content-range") {
-			Some(header_value) => header_value.to_str()?,
+		let content_range: String = match response.headers().get("content-range") {
+			Some(header_value) => header_value.to_str()?.to_string(),
 			None => bail!(
 				"content-range is not set for range request {range:?} to url {}",
 				self.url
@@ -107,7 +267,7 @@ impl DataReaderTrait for DataReaderHttp {
 		};
 
 		lazy_static! {
-			static ref RE_RANGE: Regex = RegexBuilder::new(r"^bytes (\d+)-(\d+)/\d+$")
+			static ref RE_RANGE: Regex = RegexBuilder::new(r"^bytes (\d+)-(\d+)/(\d+|\*)$")
 				.case_insensitive(true)
 				.build()
 				.unwrap();
@@ -115,9 +275,18 @@ impl DataReaderTrait for DataReaderHttp {
 
 		let content_range_start: u64;
 		let content_range_end: u64;
-		if let Some(captures) = RE_RANGE.captures(content_range) {
+		if let Some(captures) = RE_RANGE.captures(&content_range) {
 			content_range_start = captures.get(1).unwrap().as_str().parse::<u64>()?;
 			content_range_end = captures.get(2).unwrap().as_str().parse::<u64>()?;
+			if let Ok(file_size) = captures.get(3).unwrap().as_str().parse::<u64>() {
+				let first_size = *self.file_size.get_or_init(|| file_size);
+				if file_size != first_size {
+					bail!(
+						"size of {} changed from {first_size} to {file_size} bytes while reading",
+						self.url
+					);
+				}
+			}
 		} else {
 			bail!("format of content-range response is invalid: {content_range}");
 		}
@@ -126,13 +295,191 @@ impl DataReaderTrait for DataReaderHttp {
 			bail!("content-range-start {content_range_start} is not start of range {range:?}");
 		}
 
-		if content_range_end != range.offset + range.length - 1 {
+		if content_range_end < range.offset + min_length - 1 || content_range_end > range.offset + range.length - 1 {
 			bail!("content-range-end {content_range_end} is not end of range {range:?}");
 		}
 
-		let bytes = response.bytes().await?;
+		let expected_length = content_range_end - content_range_start + 1;
+		if let Some(content_length) = response.content_length() {
+			if content_length != expected_length {
+				bail!("content-length {content_length} does not match content-range {content_range}");
+			}
+		}
+
+		let mut data = Vec::with_capacity(expected_length as usize);
+		loop {
+			match response.chunk().await {
+				Ok(Some(bytes)) => {
+					counters().http_bytes.add(bytes.len() as u64);
+					data.extend_from_slice(&bytes);
+				}
+				Ok(None) => break,
+				Err(error) => return Ok(Attempt::Truncated(Blob::from(data), error.into())),
+			}
+		}
+
+		let length = data.len() as u64;
+		if length > expected_length {
+			bail!("received {length} bytes, but content-range is {content_range}");
+		}
+		if length < expected_length {
+			let error = anyhow!("response ended after {length} of {expected_length} bytes");
+			return Ok(Attempt::Truncated(Blob::from(data), error));
+		}
+		Ok(Attempt::Done(Blob::from(data)))
+	}
+
+	/// Reads a range from the downloaded file, downloading it first if necessary.
+	///
+	/// Like [`Self::fetch`], the result may end early at the end of the file, but must contain at least `min_length` bytes.
+	async fn read_downloaded(&self, range: &ByteRange, min_length: u64) -> Result<Blob> {
+		let download = self.download.get_or_try_init(|| self.download_file()).await?;
+		let length = range.length.min(download.size.saturating_sub(range.offset));
+		if length < min_length {
+			bail!("range {range:?} is beyond the end of {}", self.url);
+		}
+		download.reader.read_range(&ByteRange::new(range.offset, length)).await
+	}
 
-		Ok(Blob::from(bytes.deref()))
+	/// Downloads the whole file into a temporary file, retrying failed downloads.
+	async fn download_file(&self) -> Result<Download> {
+		static COUNTER: AtomicU64 = AtomicU64::new(0);
+		let path = std::env::temp_dir().join(format!(
+			"versatiles-download-{}-{}",
+			std::process::id(),
+			COUNTER.fetch_add(1, Ordering::Relaxed)
+		));
+		warn!(
+			"{} ignores range requests, downloading the whole file to {path:?}",
+			self.url
+		);
+
+		let mut backoff = self.options.initial_backoff;
+		let mut attempt = 0;
+		loop {
+			match self.download_once(&path).await {
+				Ok(size) => {
+					return Ok(Download {
+						reader: DataReaderFile::open(&path)?,
+						path,
+						size,
+					})
+				}
+				Err(error) => {
+					let _ = std::fs::remove_file(&path);
+					if attempt >= self.options.max_retries {
+						return Err(error.context(format!(
+							"download of {} failed after {} attempts",
+							self.url,
+							attempt + 1
+						)));
+					}
+					warn!("download of {} failed, retrying: {error}", self.url);
+					counters().http_retries.inc();
+					tokio::time::sleep(backoff).await;
+					backoff *= 2;
+					attempt += 1;
+				}
+			}
+		}
+	}
+
+	/// Downloads the whole file to `path` and returns its size.
+	async fn download_once(&self, path: &Path) -> Result<u64> {
+		counters().http_requests.inc();
+		let mut response = self.client.get(self.url.clone()).send().await?;
+		let status_code = response.status();
+		if status_code != StatusCode::OK {
+			bail!("expected 200 as a response to a download. instead we got {status_code}");
+		}
+
+		let content_length = response.content_length();
+		let mut file = File::create(path)?;
+		let mut size = 0;
+		while let Some(bytes) = response.chunk().await? {
+			counters().http_bytes.add(bytes.len() as u64);
+			file.write_all(&bytes)?;
+			size += bytes.len() as u64;
+		}
+		file.flush()?;
+
+		if let Some(content_length) = content_length {
+			if size != content_length {
+				bail!("download ended after {size} of {content_length} bytes");
+			}
+		}
+		Ok(size)
+	}
+
+	/// Reads a small range from cached chunks, fetching the missing chunks in a single request.
+	async fn read_range_prefetched(&self, range: &ByteRange) -> Result<Blob> {
+		let size = self.options.prefetch_size;
+		let first = range.offset / size;
+		let last = (range.offset + range.length - 1) / size;
+
+		let mut chunks: Vec<Option<Blob>> = {
+			let mut cache = self.chunks.lock().unwrap();
+			(first..=last).map(|index| cache.get(&index)).collect()
+		};
+
+		if let (Some(a), Some(b)) = (
+			chunks.iter().position(Option::is_none),
+			chunks.iter().rposition(Option::is_none),
+		) {
+			counters().http_cache_misses.inc();
+			let fetch_range = ByteRange::new((first + a as u64) * size, (b - a + 1) as u64 * size);
+			// the requested bytes must be included, the rest of the last chunk may be missing at the end of the file
+			let min_length =
+				(range.offset + range.length).min(fetch_range.offset + fetch_range.length) - fetch_range.offset;
+			let blob = self.fetch(&fetch_range, min_length).await?;
+
+			let mut cache = self.chunks.lock().unwrap();
+			for (i, start) in (0..blob.len()).step_by(size as usize).enumerate() {
+				let chunk = blob.slice(start as usize..(start + size).min(blob.len()) as usize);
+				chunks[a + i] = Some(cache.add(first + a as u64 + i as u64, chunk));
+			}
+		} else {
+			counters().http_cache_hits.inc();
+		}
+
+		let mut data = Vec::with_capacity(range.length as usize);
+		let mut offset = first * size;
+		for chunk in chunks {
+			let chunk = chunk.ok_or_else(|| anyhow!("range {range:?} is beyond the end of {}", self.url))?;
+			let start = range.offset.max(offset) - offset;
+			let end = (range.offset + range.length).min(offset + chunk.len()).max(offset) - offset;
+			if start < end {
+				data.extend_from_slice(chunk.get_range(start as usize..end as usize));
+			}
+			offset += size;
+		}
+		if data.len() as u64 != range.length {
+			bail!("range {range:?} is beyond the end of {}", self.url);
+		}
+		Ok(Blob::from(data))
+	}
+}
+
+#[async_trait]
+impl DataReaderTrait for DataReaderHttp {
+	/// Reads a specific range of bytes from the HTTP(S) endpoint.
+	///
+	/// # Arguments
+	///
+	/// * `range` - A ByteRange struct specifying the offset and length of the range to read.
+	///
+	/// # Returns
+	///
+	/// * A Result containing a Blob with the read data or an error.
+	async fn read_range(&self, range: &ByteRange) -> Result<Blob> {
+		if range.length == 0 {
+			return Ok(Blob::new_empty());
+		}
+		if range.length < self.options.prefetch_size {
+			self.read_range_prefetched(range).await
+		} else {
+			self.fetch(range, range.length).await
+		}
 	}
 
 	/// Reads all the data from the HTTP(S) endpoint.
@@ -157,6 +504,223 @@ impl DataReaderTrait for DataReaderHttp {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	/// How the test server misbehaves
+	#[derive(Clone, Copy, PartialEq)]
+	enum Failure {
+		/// respond with 503
+		Unavailable,
+		/// send only the first half of the body
+		Truncate,
+		/// send a wrong content-length
+		WrongLength,
+		/// ignore the range header and send the whole file
+		IgnoreRange,
+	}
+
+	/// A minimal HTTP server answering range requests for `data`. The first `failures` requests get a 503.
+	/// Returns the url and the list of requested ranges.
+	async fn start_server(data: Vec<u8>, failures: usize) -> Result<(Url, Arc<Mutex<Vec<String>>>)> {
+		start_failing_server(data, failures, Failure::Unavailable).await
+	}
+
+	/// Like [`start_server`], but the first `failures` requests fail as defined by `failure`.
+	/// Requests without a range are listed as "-".
+	async fn start_failing_server(
+		data: Vec<u8>,
+		failures: usize,
+		failure: Failure,
+	) -> Result<(Url, Arc<Mutex<Vec<String>>>)> {
+		let listener = TcpListener::bind("127.0.0.1:0").await?;
+		let url = Url::parse(&format!("http://127.0.0.1:{}/file", listener.local_addr()?.port()))?;
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let counter = Arc::new(AtomicUsize::new(0));
+		let data = Arc::new(data);
+
+		let requests_clone = requests.clone();
+		tokio::spawn(async move {
+			loop {
+				let (mut socket, _) = listener.accept().await.unwrap();
+				let (requests, counter, data) = (requests_clone.clone(), counter.clone(), data.clone());
+				tokio::spawn(async move {
+					let mut buffer = Vec::new();
+					let mut chunk = [0u8; 1024];
+					while !buffer.ends_with(b"\r\n\r\n") {
+						let n = socket.read(&mut chunk).await.unwrap();
+						if n == 0 {
+							return;
+						}
+						buffer.extend_from_slice(&chunk[..n]);
+					}
+					let request = String::from_utf8_lossy(&buffer).to_lowercase();
+					let range = request
+						.lines()
+						.find_map(|line| line.strip_prefix("range: bytes="))
+						.unwrap_or("-")
+						.to_string();
+					requests.lock().unwrap().push(range.clone());
+
+					let fail = counter.fetch_add(1, Ordering::SeqCst) < failures;
+					let response = if fail && failure == Failure::Unavailable {
+						b"HTTP/1.1 503 X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_vec()
+					} else if range == "-" || (fail && failure == Failure::IgnoreRange) {
+						let mut response = format!(
+							"HTTP/1.1 200 X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+							data.len()
+						)
+						.into_bytes();
+						response.extend_from_slice(&data);
+						response
+					} else {
+						let (start, end) = range.split_once('-').unwrap();
+						let start: usize = start.parse().unwrap();
+						let end = end.parse::<usize>().unwrap().min(data.len() - 1);
+						let mut length = end + 1 - start;
+						let mut body = &data[start..=end];
+						if fail && failure == Failure::WrongLength {
+							length -= 1;
+						}
+						if fail && failure == Failure::Truncate {
+							body = &body[..body.len() / 2];
+						}
+						let mut response = format!(
+							"HTTP/1.1 206 X\r\ncontent-length: {length}\r\ncontent-range: bytes {start}-{end}/{}\r\nconnection: close\r\n\r\n",
+							data.len()
+						)
+						.into_bytes();
+						response.extend_from_slice(body);
+						response
+					};
+					socket.write_all(&response).await.unwrap();
+				});
+			}
+		});
+
+		Ok((url, requests))
+	}
+
+	fn test_data() -> Vec<u8> {
+		(0..1000u32).map(|i| (i % 251) as u8).collect()
+	}
+
+	fn options(prefetch_size: u64) -> HttpReadOptions {
+		HttpReadOptions {
+			initial_backoff: Duration::from_millis(1),
+			prefetch_size,
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn prefetch_and_coalesce() -> Result<()> {
+		let data = test_data();
+		let (url, requests) = start_server(data.clone(), 0).await?;
+		let reader = DataReaderHttp::from_url_with_options(url, options(100))?;
+
+		// reads inside the same chunks are served from the cache
+		for offset in [10, 20, 150, 190] {
+			let blob = reader.read_range(&ByteRange::new(offset, 5)).await?;
+			assert_eq!(blob.as_slice(), &data[offset as usize..offset as usize + 5]);
+		}
+		assert_eq!(*requests.lock().unwrap(), vec!["0-99", "100-199"]);
+
+		// a read across two missing chunks needs a single request
+		let blob = reader.read_range(&ByteRange::new(290, 20)).await?;
+		assert_eq!(blob.as_slice(), &data[290..310]);
+		// the last chunk ends early at the end of the file
+		let blob = reader.read_range(&ByteRange::new(990, 10)).await?;
+		assert_eq!(blob.as_slice(), &data[990..1000]);
+		// large reads are not cached
+		let blob = reader.read_range(&ByteRange::new(0, 500)).await?;
+		assert_eq!(blob.as_slice(), &data[0..500]);
+		assert_eq!(
+			*requests.lock().unwrap(),
+			vec!["0-99", "100-199", "200-399", "900-999", "0-499"]
+		);
+
+		assert!(reader.read_range(&ByteRange::new(995, 10)).await.is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn retry() -> Result<()> {
+		let data = test_data();
+		let (url, requests) = start_server(data.clone(), 2).await?;
+		let reader = DataReaderHttp::from_url_with_options(url, options(0))?;
+		let blob = reader.read_range(&ByteRange::new(7, 8)).await?;
+		assert_eq!(blob.as_slice(), &data[7..15]);
+		assert_eq!(requests.lock().unwrap().len(), 3);
+
+		let (url, _) = start_server(data, 10).await?;
+		let reader = DataReaderHttp::from_url_with_options(url, options(0))?;
+		let error = reader.read_range(&ByteRange::new(7, 8)).await.unwrap_err();
+		assert!(error.to_string().contains("failed after 4 attempts"), "{error}");
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn resume_truncated() -> Result<()> {
+		let data = test_data();
+		let (url, requests) = start_failing_server(data.clone(), 2, Failure::Truncate).await?;
+		let reader = DataReaderHttp::from_url_with_options(url, options(0))?;
+		let blob = reader.read_range(&ByteRange::new(100, 400)).await?;
+		assert_eq!(blob.as_slice(), &data[100..500]);
+		// only the missing bytes are requested again
+		assert_eq!(*requests.lock().unwrap(), vec!["100-499", "300-499", "400-499"]);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn wrong_content_length() -> Result<()> {
+		let (url, _) = start_failing_server(test_data(), 1, Failure::WrongLength).await?;
+		let reader = DataReaderHttp::from_url_with_options(url, options(0))?;
+		let error = reader.read_range(&ByteRange::new(100, 400)).await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"content-length 399 does not match content-range bytes 100-499/1000"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn download_fallback() -> Result<()> {
+		let data = test_data();
+		let (url, requests) = start_failing_server(data.clone(), 1, Failure::IgnoreRange).await?;
+		let reader = DataReaderHttp::from_url_with_options(url.clone(), options(100))?;
+		for offset in [10, 500, 990] {
+			let blob = reader.read_range(&ByteRange::new(offset, 10)).await?;
+			assert_eq!(blob.as_slice(), &data[offset as usize..offset as usize + 10]);
+		}
+		assert!(reader.read_range(&ByteRange::new(995, 10)).await.is_err());
+		// the file is downloaded once, all other reads use the temporary file
+		assert_eq!(*requests.lock().unwrap(), vec!["0-99", "-"]);
+
+		let path = reader.download.get().unwrap().path.clone();
+		assert!(path.exists());
+		drop(reader);
+		assert!(!path.exists());
+
+		let (url, _) = start_failing_server(data, 1, Failure::IgnoreRange).await?;
+		let options = HttpReadOptions {
+			download_fallback: false,
+			..options(0)
+		};
+		let reader = DataReaderHttp::from_url_with_options(url, options)?;
+		let error = reader.read_range(&ByteRange::new(7, 8)).await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"expected 206 as a response to a range request. instead we got 200 OK"
+		);
+		Ok(())
+	}
 
 	// Test the 'new' method for valid and invalid URLs
 	#[test]
@@ -58,6 +58,10 @@
 
 use crate::types::{Blob, ByteRange};
 use anyhow::Result;
+use std::{
+	fs::File,
+	io::{Read, Seek, SeekFrom},
+};
 
 /// A trait for writing data to various destinations.
 ///
@@ -66,6 +70,9 @@ use anyhow::Result;
 /// - `write_start`: Writes data from the start of the writer.
 /// - `get_position`: Gets the current write position.
 /// - `set_position`: Sets the write position.
+///
+/// # Provided Methods
+/// - `append_from_file`: Appends a byte range of a file.
 pub trait DataWriterTrait: Send {
 	/// Appends data to the writer.
 	///
@@ -106,4 +113,23 @@ pub trait DataWriterTrait: Send {
 	///
 	/// * A Result indicating success or an error.
 	fn set_position(&mut self, position: u64) -> Result<()>;
+
+	/// Appends a byte range of a file, e.g. of a temporary file.
+	///
+	/// The default implementation reads the range into memory. Writers of files can copy it more efficiently.
+	///
+	/// # Arguments
+	///
+	/// * `file` - The file to read from.
+	/// * `range` - The byte range to copy.
+	///
+	/// # Returns
+	///
+	/// * A Result containing the `ByteRange` of the appended data, or an error.
+	fn append_from_file(&mut self, mut file: &File, range: &ByteRange) -> Result<ByteRange> {
+		file.seek(SeekFrom::Start(range.offset))?;
+		let mut buffer = vec![0; range.length as usize];
+		file.read_exact(&mut buffer)?;
+		self.append(&Blob::from(buffer))
+	}
 }
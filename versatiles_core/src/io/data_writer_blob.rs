@@ -227,6 +227,20 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_append_from_file() -> Result<()> {
+		let file = assert_fs::NamedTempFile::new("data.bin")?;
+		std::fs::write(file.path(), [1, 2, 3, 4, 5, 6])?;
+
+		let mut writer = DataWriterBlob::new()?;
+		writer.append(&Blob::from(vec![9]))?;
+		let range = writer.append_from_file(&std::fs::File::open(file.path())?, &ByteRange::new(2, 3))?;
+		assert_eq!(range, ByteRange::new(1, 3));
+		assert_eq!(writer.as_slice(), &[9, 3, 4, 5]);
+
+		Ok(())
+	}
+
 	#[test]
 	fn test_as_slice() -> Result<()> {
 		let mut writer = DataWriterBlob::new()?;
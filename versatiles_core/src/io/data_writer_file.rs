@@ -38,7 +38,7 @@ use anyhow::{ensure, Result};
 use async_trait::async_trait;
 use std::{
 	fs::File,
-	io::{BufWriter, Seek, SeekFrom, Write},
+	io::{copy, BufWriter, Read, Seek, SeekFrom, Write},
 	path::Path,
 };
 
@@ -123,4 +123,25 @@ impl DataWriterTrait for DataWriterFile {
 		self.writer.seek(SeekFrom::Start(position))?;
 		Ok(())
 	}
+
+	/// Appends a byte range of another file, without reading it into memory.
+	///
+	/// On Linux the data is copied by the kernel, e.g. with `copy_file_range`, which can even share the data
+	/// on file systems supporting reflinks.
+	///
+	/// # Returns
+	///
+	/// * A Result containing the `ByteRange` of the appended data, or an error.
+	fn append_from_file(&mut self, mut file: &File, range: &ByteRange) -> Result<ByteRange> {
+		self.writer.flush()?;
+		let pos = self.writer.stream_position()?;
+		file.seek(SeekFrom::Start(range.offset))?;
+		let length = copy(&mut file.take(range.length), self.writer.get_mut())?;
+		ensure!(
+			length == range.length,
+			"only {length} of {} bytes could be copied",
+			range.length
+		);
+		Ok(ByteRange::new(pos, length))
+	}
 }
@@ -3,8 +3,8 @@
 //! # Overview
 //!
 //! The module provides a unified interface for importing all the necessary components for reading and writing data
-//! in various formats and from various sources. It includes readers and writers for blobs, files, HTTP sources (if enabled),
-//! and more. The value readers and writers support different byte orders and offer functionality for handling various data types.
+//! in various formats and from various sources. It includes readers and writers for blobs, files, HTTP sources (with the feature `http`),
+//! injected range fetchers (with the feature `wasm`), and more. The value readers and writers support different byte orders and offer functionality for handling various data types.
 //!
 //! # Examples
 //!
@@ -19,7 +19,10 @@
 
 mod data_reader;
 mod data_reader_blob;
+#[cfg(feature = "wasm")]
+mod data_reader_fetch;
 mod data_reader_file;
+#[cfg(all(feature = "http", not(target_arch = "wasm32")))]
 mod data_reader_http;
 mod data_writer;
 mod data_writer_blob;
@@ -34,7 +37,10 @@ mod value_writer_file;
 
 pub use data_reader::*;
 pub use data_reader_blob::*;
+#[cfg(feature = "wasm")]
+pub use data_reader_fetch::*;
 pub use data_reader_file::*;
+#[cfg(all(feature = "http", not(target_arch = "wasm32")))]
 pub use data_reader_http::*;
 pub use data_writer::*;
 pub use data_writer_blob::*;
@@ -116,9 +116,9 @@ pub trait ValueReader<'a, E: ByteOrder + 'a> {
 	}
 
 	fn read_blob(&mut self, length: u64) -> Result<Blob> {
-		let mut blob = Blob::new_sized(length as usize);
-		self.get_reader().read_exact(blob.as_mut_slice())?;
-		Ok(blob)
+		let mut vec = vec![0u8; length as usize];
+		self.get_reader().read_exact(&mut vec)?;
+		Ok(Blob::from(vec))
 	}
 
 	fn read_string(&mut self, length: u64) -> Result<String> {
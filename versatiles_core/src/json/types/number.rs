@@ -12,6 +12,12 @@ impl From<u8> for JsonValue {
 	}
 }
 
+impl From<u32> for JsonValue {
+	fn from(input: u32) -> Self {
+		JsonValue::Number(input as f64)
+	}
+}
+
 impl From<i32> for JsonValue {
 	fn from(input: i32) -> Self {
 		JsonValue::Number(input as f64)
@@ -4,6 +4,7 @@ pub mod byte_iterator;
 pub mod io;
 pub mod json;
 pub mod macros;
+pub mod metrics;
 pub mod progress;
 pub mod tilejson;
 pub mod types;
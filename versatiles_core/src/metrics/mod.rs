@@ -0,0 +1,190 @@
+//! Process-wide counters of readers, caches, the server and pipelines.
+//!
+//! Applications embedding VersaTiles can call [`metrics`] to get a typed snapshot of all counters
+//! and report the numbers in their own telemetry.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use versatiles_core::metrics::{counters, metrics};
+//!
+//! counters().server_requests.inc();
+//! let snapshot = metrics();
+//! assert!(snapshot.server_requests >= 1);
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A monotonically increasing counter.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+	const fn new() -> Self {
+		Counter(AtomicU64::new(0))
+	}
+
+	/// Increases the counter by one.
+	pub fn inc(&self) {
+		self.add(1);
+	}
+
+	/// Increases the counter by `value`.
+	pub fn add(&self, value: u64) {
+		self.0.fetch_add(value, Ordering::Relaxed);
+	}
+
+	/// Returns the current value.
+	pub fn get(&self) -> u64 {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// A value that can go up and down, e.g. the number of items in a queue.
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicU64);
+
+impl Gauge {
+	const fn new() -> Self {
+		Gauge(AtomicU64::new(0))
+	}
+
+	/// Increases the gauge by one.
+	pub fn inc(&self) {
+		self.0.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Decreases the gauge by one.
+	pub fn dec(&self) {
+		self.0.fetch_sub(1, Ordering::Relaxed);
+	}
+
+	/// Returns the current value.
+	pub fn get(&self) -> u64 {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// All counters of the process. Use [`counters`] to update them and [`metrics`] to read them.
+#[derive(Debug, Default)]
+pub struct Counters {
+	/// HTTP range requests sent by `DataReaderHttp`, including retries
+	pub http_requests: Counter,
+	/// HTTP range requests that were repeated after an error
+	pub http_retries: Counter,
+	/// bytes received by `DataReaderHttp`
+	pub http_bytes: Counter,
+	/// reads of `DataReaderHttp` served from prefetched chunks
+	pub http_cache_hits: Counter,
+	/// reads of `DataReaderHttp` that had to fetch chunks
+	pub http_cache_misses: Counter,
+	/// tile requests handled by the server
+	pub server_requests: Counter,
+	/// tile requests answered with 404
+	pub server_not_found: Counter,
+	/// tile requests answered with 400
+	pub server_errors: Counter,
+	/// tile requests answered from the tile cache of the server
+	pub server_cache_hits: Counter,
+	/// tile requests that were not found in the tile cache of the server
+	pub server_cache_misses: Counter,
+	/// tiles produced by pipelines
+	pub pipeline_tiles: Counter,
+	/// tiles waiting in the queues between reading and writing, see `TileStream::for_each_queued`
+	pub write_queue_depth: Gauge,
+	/// times reading had to wait, because a writer was slower and its queue was full
+	pub write_queue_waits: Counter,
+}
+
+static COUNTERS: Counters = Counters {
+	http_requests: Counter::new(),
+	http_retries: Counter::new(),
+	http_bytes: Counter::new(),
+	http_cache_hits: Counter::new(),
+	http_cache_misses: Counter::new(),
+	server_requests: Counter::new(),
+	server_not_found: Counter::new(),
+	server_errors: Counter::new(),
+	server_cache_hits: Counter::new(),
+	server_cache_misses: Counter::new(),
+	pipeline_tiles: Counter::new(),
+	write_queue_depth: Gauge::new(),
+	write_queue_waits: Counter::new(),
+};
+
+/// Returns the counters of the process, e.g. to increase them.
+pub fn counters() -> &'static Counters {
+	&COUNTERS
+}
+
+/// A snapshot of all counters. See [`Counters`] for the meaning of the fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MetricsSnapshot {
+	pub http_requests: u64,
+	pub http_retries: u64,
+	pub http_bytes: u64,
+	pub http_cache_hits: u64,
+	pub http_cache_misses: u64,
+	pub server_requests: u64,
+	pub server_not_found: u64,
+	pub server_errors: u64,
+	pub server_cache_hits: u64,
+	pub server_cache_misses: u64,
+	pub pipeline_tiles: u64,
+	pub write_queue_depth: u64,
+	pub write_queue_waits: u64,
+}
+
+/// Returns the current values of all counters.
+pub fn metrics() -> MetricsSnapshot {
+	let c = counters();
+	MetricsSnapshot {
+		http_requests: c.http_requests.get(),
+		http_retries: c.http_retries.get(),
+		http_bytes: c.http_bytes.get(),
+		http_cache_hits: c.http_cache_hits.get(),
+		http_cache_misses: c.http_cache_misses.get(),
+		server_requests: c.server_requests.get(),
+		server_not_found: c.server_not_found.get(),
+		server_errors: c.server_errors.get(),
+		server_cache_hits: c.server_cache_hits.get(),
+		server_cache_misses: c.server_cache_misses.get(),
+		pipeline_tiles: c.pipeline_tiles.get(),
+		write_queue_depth: c.write_queue_depth.get(),
+		write_queue_waits: c.write_queue_waits.get(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counter() {
+		let counter = Counter::new();
+		counter.inc();
+		counter.add(41);
+		assert_eq!(counter.get(), 42);
+	}
+
+	#[test]
+	fn gauge() {
+		let gauge = Gauge::new();
+		gauge.inc();
+		gauge.inc();
+		gauge.dec();
+		assert_eq!(gauge.get(), 1);
+	}
+
+	#[test]
+	fn snapshot() {
+		let before = metrics();
+		counters().pipeline_tiles.add(3);
+		counters().http_bytes.add(100);
+		let after = metrics();
+		// other tests may run in parallel, so the counters can grow even more
+		assert!(after.pipeline_tiles >= before.pipeline_tiles + 3);
+		assert!(after.http_bytes >= before.http_bytes + 100);
+	}
+}
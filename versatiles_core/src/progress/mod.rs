@@ -5,7 +5,9 @@
 //!
 //! The module conditionally includes different progress indicator implementations based on the
 //! build configuration. By default, it provides a no-op progress drain. If the "full" feature is
-//! enabled, it includes a terminal-based progress bar. The `ProgressTrait` trait defines the
+//! enabled, it includes a terminal-based progress bar. With [`set_json_output`], progress is written
+//! as NDJSON events instead, e.g. for log pipelines. [`with_progress_output`] redirects these events
+//! for a single task, e.g. a conversion job running inside the server. The `ProgressTrait` trait defines the
 //! common interface for all progress indicators, and the `get_progress_bar` function provides
 //! a convenient way to create an instance of a progress indicator.
 //!
@@ -28,6 +30,55 @@ mod progress_bar;
 #[cfg(any(feature = "test", not(feature = "cli")))]
 mod progress_dummy;
 
+mod progress_json;
+pub use progress_json::unix_millis;
+
+use std::{
+	future::Future,
+	io::Write,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Creates a writer for the NDJSON events of every progress indicator of a task.
+pub type ProgressOutput = Arc<dyn Fn() -> Box<dyn Write + Send + Sync> + Send + Sync>;
+
+tokio::task_local! {
+	static TASK_OUTPUT: ProgressOutput;
+}
+
+/// Runs `future` so that all progress indicators created inside it write NDJSON events to writers created
+/// by `output`, regardless of the global output settings.
+///
+/// The redirection only applies to the task running `future`, not to tasks spawned by it.
+pub async fn with_progress_output<F: Future>(output: ProgressOutput, future: F) -> F::Output {
+	TASK_OUTPUT.scope(output, future).await
+}
+
+/// Makes all progress indicators created afterwards write NDJSON events to stderr instead of drawing a progress bar.
+pub fn set_json_output(enabled: bool) {
+	JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if machine-readable NDJSON output was requested with [`set_json_output`].
+pub fn is_json_output() -> bool {
+	JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Prints a status message to stderr.
+/// With JSON output it is logged as an info event instead, so that stderr stays valid NDJSON.
+pub fn print_status(message: &str) {
+	if is_json_output() {
+		log::info!("{message}");
+	} else {
+		eprintln!("{message}");
+	}
+}
+
 /// Factory function to create a progress bar or a no-op progress drain based on the build configuration.
 ///
 /// # Arguments
@@ -39,6 +90,18 @@ mod progress_dummy;
 ///
 /// A boxed implementation of `ProgressTrait`.
 pub fn get_progress_bar(message: &str, max_value: u64) -> Box<dyn ProgressTrait> {
+	if let Ok(output) = TASK_OUTPUT.try_with(|output| output.clone()) {
+		let mut progress = progress_json::ProgressJson::with_output(output());
+		progress.init(message, max_value);
+		return Box::new(progress);
+	}
+
+	if is_json_output() {
+		let mut progress = progress_json::ProgressJson::new();
+		progress.init(message, max_value);
+		return Box::new(progress);
+	}
+
 	#[cfg(all(not(feature = "test"), feature = "cli"))]
 	let mut progress = progress_bar::ProgressBar::new();
 	#[cfg(any(feature = "test", not(feature = "cli")))]
@@ -49,3 +112,46 @@ pub fn get_progress_bar(message: &str, max_value: u64) -> Box<dyn ProgressTrait>
 
 mod traits;
 pub use traits::ProgressTrait;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Mutex;
+
+	#[derive(Clone, Default)]
+	struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+	impl Write for Buffer {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_with_progress_output() {
+		let buffer = Buffer::default();
+		let output = {
+			let buffer = buffer.clone();
+			Arc::new(move || Box::new(buffer.clone()) as Box<dyn Write + Send + Sync>)
+		};
+
+		with_progress_output(output, async {
+			let mut progress = get_progress_bar("job", 10);
+			progress.finish();
+		})
+		.await;
+
+		// outside of the scope, nothing is redirected
+		get_progress_bar("other", 10).finish();
+
+		let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+		let lines: Vec<&str> = text.lines().collect();
+		assert_eq!(lines.len(), 2);
+		assert!(lines[0].contains("\"event\":\"start\""));
+		assert!(lines[1].contains("\"event\":\"finish\""));
+		assert!(lines.iter().all(|line| line.contains("\"message\":\"job\"")));
+	}
+}
@@ -0,0 +1,186 @@
+//! This module provides the `ProgressJson` struct, a progress indicator that emits NDJSON events.
+//!
+//! # Overview
+//!
+//! Instead of drawing a progress bar, `ProgressJson` writes one JSON object per line to stderr,
+//! so that log pipelines can parse the progress of long-running tasks:
+//!
+//! ```text
+//! {"event":"start","message":"converting tiles","position":0,"time":1735689600000,"total":1000,"type":"progress"}
+//! {"elapsed":1.002,"event":"update","message":"converting tiles","position":312,"time":1735689601002,"total":1000,"type":"progress"}
+//! ```
+//!
+//! Updates are emitted at most once per second, start and finish events always.
+
+use super::ProgressTrait;
+use crate::json::JsonObject;
+use std::{
+	io::Write,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A progress indicator that writes NDJSON events.
+pub struct ProgressJson {
+	message: String,
+	position: u64,
+	max_value: u64,
+	start: Instant,
+	last_update: Instant,
+	finished: bool,
+	output: Box<dyn Write + Send + Sync>,
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+pub fn unix_millis() -> f64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_millis() as f64)
+		.unwrap_or_default()
+}
+
+impl ProgressJson {
+	/// Creates a progress indicator that writes its events to `output` instead of stderr.
+	pub fn with_output(output: Box<dyn Write + Send + Sync>) -> Self {
+		let now = Instant::now();
+		ProgressJson {
+			message: String::new(),
+			position: 0,
+			max_value: 0,
+			start: now,
+			last_update: now,
+			finished: false,
+			output,
+		}
+	}
+
+	fn emit(&mut self, event: &str) {
+		let mut object = JsonObject::default();
+		object.set("type", "progress");
+		object.set("event", event);
+		object.set("time", unix_millis());
+		object.set("message", self.message.as_str());
+		object.set("position", self.position as f64);
+		object.set("total", self.max_value as f64);
+		if event != "start" {
+			object.set("elapsed", (self.start.elapsed().as_millis() as f64) / 1000.0);
+		}
+		// progress output must never abort the task
+		let _ = writeln!(self.output, "{}", object.stringify());
+		self.last_update = Instant::now();
+	}
+
+	fn update(&mut self) {
+		if !self.finished && self.last_update.elapsed() >= UPDATE_INTERVAL {
+			self.emit("update");
+		}
+	}
+}
+
+impl ProgressTrait for ProgressJson {
+	fn new() -> Self {
+		Self::with_output(Box::new(std::io::stderr()))
+	}
+
+	fn init(&mut self, message: &str, max_value: u64) {
+		self.message = message.to_string();
+		self.max_value = max_value;
+		self.start = Instant::now();
+		self.emit("start");
+	}
+
+	fn set_max_value(&mut self, max_value: u64) {
+		self.max_value = max_value;
+		self.update();
+	}
+
+	fn set_position(&mut self, value: u64) {
+		self.position = value;
+		self.update();
+	}
+
+	fn inc(&mut self, value: u64) {
+		self.position += value;
+		self.update();
+	}
+
+	fn finish(&mut self) {
+		if !self.finished {
+			self.position = self.max_value;
+			self.emit("finish");
+			self.finished = true;
+		}
+	}
+
+	fn remove(&mut self) {
+		self.finish();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::json::JsonValue;
+	use std::sync::{Arc, Mutex};
+
+	#[derive(Clone, Default)]
+	struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+	impl Write for Buffer {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	impl Buffer {
+		fn events(&self) -> Vec<JsonObject> {
+			String::from_utf8(self.0.lock().unwrap().clone())
+				.unwrap()
+				.lines()
+				.map(|line| JsonObject::parse_str(line).unwrap())
+				.collect()
+		}
+	}
+
+	#[test]
+	fn test_events() {
+		let buffer = Buffer::default();
+		let mut progress = ProgressJson::with_output(Box::new(buffer.clone()));
+		progress.init("converting tiles", 100);
+		progress.inc(10);
+		progress.set_position(50);
+		progress.finish();
+		progress.remove();
+
+		let events = buffer.events();
+		// updates are throttled, so only start and finish are written
+		assert_eq!(events.len(), 2);
+
+		let get = |index: usize, key: &str| events[index].get(key).unwrap().clone();
+		assert_eq!(get(0, "type"), JsonValue::from("progress"));
+		assert_eq!(get(0, "event"), JsonValue::from("start"));
+		assert_eq!(get(0, "message"), JsonValue::from("converting tiles"));
+		assert_eq!(get(0, "total"), JsonValue::from(100.0));
+		assert_eq!(get(1, "event"), JsonValue::from("finish"));
+		assert_eq!(get(1, "position"), JsonValue::from(100.0));
+		assert!(events[1].get("elapsed").is_some());
+	}
+
+	#[test]
+	fn test_update() {
+		let buffer = Buffer::default();
+		let mut progress = ProgressJson::with_output(Box::new(buffer.clone()));
+		progress.init("test", 10);
+		progress.last_update -= UPDATE_INTERVAL;
+		progress.inc(3);
+
+		let events = buffer.events();
+		assert_eq!(events.len(), 2);
+		assert_eq!(events[1].get("event"), Some(&JsonValue::from("update")));
+		assert_eq!(events[1].get("position"), Some(&JsonValue::from(3.0)));
+	}
+}
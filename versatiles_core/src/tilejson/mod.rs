@@ -39,7 +39,7 @@ use anyhow::{anyhow, ensure, Result};
 use regex::Regex;
 use std::fmt::Debug;
 use value::TileJsonValues;
-use vector_layer::VectorLayers;
+pub use vector_layer::{VectorLayer, VectorLayers};
 
 /// A struct representing a TileJSON object.
 ///
@@ -179,6 +179,11 @@ impl TileJSON {
 		self.values.get_str(key)
 	}
 
+	/// Retrieves a list of strings from `self.values` by `key`, if present and a list.
+	pub fn get_list(&self, key: &str) -> Option<&[String]> {
+		self.values.get_list(key)
+	}
+
 	/// Inserts or updates a byte (`u8`) value in `self.values`.
 	pub fn set_byte(&mut self, key: &str, value: u8) -> Result<()> {
 		self.values.insert(key, &JsonValue::from(value))
@@ -194,6 +199,28 @@ impl TileJSON {
 		self.values.insert(key, &JsonValue::from(value))
 	}
 
+	/// Returns the order of the tile rows declared by the key `"scheme"`, defaulting to [`TileScheme::Xyz`].
+	///
+	/// # Errors
+	/// Returns an error if the value is neither `"xyz"` nor `"tms"`.
+	pub fn get_scheme(&self) -> Result<TileScheme> {
+		self
+			.values
+			.get_str("scheme")
+			.map_or(Ok(TileScheme::Xyz), TileScheme::parse_str)
+	}
+
+	/// Declares the order of the tile rows. XYZ is the default, so the key `"scheme"` is removed.
+	pub fn set_scheme(&mut self, scheme: TileScheme) {
+		match scheme {
+			TileScheme::Xyz => self.values.remove("scheme"),
+			TileScheme::Tms => self
+				.values
+				.insert("scheme", &JsonValue::from("tms"))
+				.expect("should have inserted a string"),
+		}
+	}
+
 	/// Parses and sets vector layers from a [`JsonValue`].
 	///
 	/// # Errors
@@ -602,6 +629,23 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn should_get_and_set_scheme() -> Result<()> {
+		let mut tj = TileJSON::try_from(r#"{"tilejson":"3.0.0","scheme":"tms"}"#)?;
+		assert_eq!(tj.get_scheme()?, TileScheme::Tms);
+
+		tj.set_scheme(TileScheme::Xyz);
+		assert_eq!(tj.get_scheme()?, TileScheme::Xyz);
+		assert_eq!(tj.as_string(), r#"{"tilejson":"3.0.0"}"#);
+
+		tj.set_scheme(TileScheme::Tms);
+		assert_eq!(tj.as_string(), r#"{"scheme":"tms","tilejson":"3.0.0"}"#);
+
+		let tj = TileJSON::try_from(r#"{"tilejson":"3.0.0","scheme":"zxy"}"#)?;
+		assert!(tj.get_scheme().is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn should_debug_print_as_json() {
 		let tj = TileJSON::default();
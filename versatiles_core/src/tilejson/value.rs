@@ -22,6 +22,11 @@ impl TileJsonValues {
 		Ok(())
 	}
 
+	/// Removes the value of `key`, if present.
+	pub fn remove(&mut self, key: &str) {
+		self.0.remove(key);
+	}
+
 	/// Returns a reference to the inner `str` value if this key exists as a string variant,
 	/// otherwise returns `None`.
 	///
@@ -38,6 +43,11 @@ impl TileJsonValues {
 		self.0.get(key).and_then(|v| v.get_str().map(ToOwned::to_owned))
 	}
 
+	/// Returns the list of strings if this key exists as a list variant, otherwise returns `None`.
+	pub fn get_list(&self, key: &str) -> Option<&[String]> {
+		self.0.get(key).and_then(|v| v.get_list())
+	}
+
 	/// Returns a `u8` if this key exists as a byte variant, otherwise returns `None`.
 	pub fn get_byte(&self, key: &str) -> Option<u8> {
 		self.0.get(key).and_then(|v| v.get_byte())
@@ -126,6 +136,14 @@ impl TileJsonValue {
 		}
 	}
 
+	/// Returns `Some(&[String])` if the value is a list, or `None` otherwise.
+	pub fn get_list(&self) -> Option<&[String]> {
+		match self {
+			TileJsonValue::List(l) => Some(l),
+			_ => None,
+		}
+	}
+
 	/// Returns `Some(u8)` if the value is a byte, or `None` otherwise.
 	pub fn get_byte(&self) -> Option<u8> {
 		match self {
@@ -238,6 +256,8 @@ mod tests {
 			TileJsonValue::List(list) => assert_eq!(list, &["field1", "field2"]),
 			_ => panic!("Expected a list"),
 		}
+		assert_eq!(tv.get_list("fields").unwrap(), ["field1", "field2"]);
+		assert!(tv.get_list("tilejson").is_none());
 		Ok(())
 	}
 
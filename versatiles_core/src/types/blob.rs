@@ -1,12 +1,14 @@
-//! This module provides the [`Blob`] struct, a wrapper around [`Vec<u8>`] that provides additional methods
+//! This module provides the [`Blob`] struct, a wrapper around [`Bytes`] that provides additional methods
 //! for working with byte data.
 //!
 //! # Overview
 //!
-//! The [`Blob`] struct is a simple wrapper around a `Vec<u8>` that provides methods for creating, accessing,
-//! and manipulating byte data. It includes various utility methods for common operations on byte slices,
+//! The [`Blob`] struct is a simple wrapper around reference counted [`Bytes`] that provides methods for creating,
+//! accessing, and manipulating byte data. It includes various utility methods for common operations on byte slices,
 //! such as creating slices, reading ranges, and converting to and from different types.
 //!
+//! Cloning a [`Blob`] and taking a range of it with [`Blob::slice`] or [`Blob::read_range`] does not copy the data.
+//!
 //! # Examples
 //!
 //! ```rust
@@ -27,10 +29,11 @@
 
 use super::ByteRange;
 use anyhow::{bail, Result};
+use bytes::Bytes;
 use std::fmt::Debug;
 use std::ops::Range;
 
-/// A simple wrapper around [`Vec<u8>`] that provides additional methods for working with byte data.
+/// A simple wrapper around [`Bytes`] that provides additional methods for working with byte data.
 ///
 /// # Examples
 ///
@@ -48,7 +51,7 @@ use std::ops::Range;
 /// assert_eq!(blob2.as_str(), "ABC");
 /// ```
 #[derive(Clone, PartialEq, Eq)]
-pub struct Blob(Vec<u8>);
+pub struct Blob(Bytes);
 
 #[allow(dead_code)]
 impl Blob {
@@ -64,7 +67,7 @@ impl Blob {
 	/// assert!(empty_blob.is_empty());
 	/// ```
 	pub fn new_empty() -> Blob {
-		Blob(Vec::new())
+		Blob(Bytes::new())
 	}
 
 	/// Creates a `Blob` with the specified size, filled with zeros.
@@ -87,7 +90,7 @@ impl Blob {
 	/// assert_eq!(blob.as_slice(), &[0, 0, 0, 0, 0]);
 	/// ```
 	pub fn new_sized(length: usize) -> Blob {
-		Blob(vec![0u8; length])
+		Blob(Bytes::from(vec![0u8; length]))
 	}
 
 	/// Returns a byte slice from the specified `range`.
@@ -117,7 +120,25 @@ impl Blob {
 		&self.0[range]
 	}
 
-	/// Returns a new [`Blob`] containing the bytes in the specified [`ByteRange`].
+	/// Returns a new [`Blob`] sharing the bytes in the specified `range`, without copying them.
+	///
+	/// # Panics
+	///
+	/// Panics if the specified range is out of bounds.
+	///
+	/// # Examples
+	///
+	/// ```rust
+	/// use versatiles_core::types::Blob;
+	///
+	/// let blob = Blob::from("abcdef");
+	/// assert_eq!(blob.slice(1..4).as_str(), "bcd");
+	/// ```
+	pub fn slice(&self, range: Range<usize>) -> Blob {
+		Blob(self.0.slice(range))
+	}
+
+	/// Returns a new [`Blob`] sharing the bytes in the specified [`ByteRange`], without copying them.
 	///
 	/// # Arguments
 	///
@@ -145,7 +166,7 @@ impl Blob {
 		if range.offset + range.length > self.0.len() as u64 {
 			bail!("read outside range")
 		}
-		Ok(Blob::from(&self.0[range.as_range_usize()]))
+		Ok(Blob(self.0.slice(range.as_range_usize())))
 	}
 
 	/// Returns a reference to the underlying byte slice.
@@ -162,23 +183,7 @@ impl Blob {
 		self.0.as_ref()
 	}
 
-	/// Returns a mutable reference to the underlying byte slice.
-	///
-	/// # Examples
-	///
-	/// ```rust
-	/// use versatiles_core::types::Blob;
-	///
-	/// let mut blob = Blob::from("abc");
-	/// let slice = blob.as_mut_slice();
-	/// slice[0] = b'z';
-	/// assert_eq!(blob.as_str(), "zbc");
-	/// ```
-	pub fn as_mut_slice(&mut self) -> &mut [u8] {
-		self.0.as_mut()
-	}
-
-	/// Consumes this [`Blob`] and returns the underlying `Vec<u8>`.
+	/// Consumes this [`Blob`] and returns the bytes as `Vec<u8>`. Copies the data, if it is shared.
 	///
 	/// # Examples
 	///
@@ -190,6 +195,11 @@ impl Blob {
 	/// assert_eq!(vec, vec![1, 2, 3]);
 	/// ```
 	pub fn into_vec(self) -> Vec<u8> {
+		Vec::from(self.0)
+	}
+
+	/// Consumes this [`Blob`] and returns the underlying [`Bytes`].
+	pub fn into_bytes(self) -> Bytes {
 		self.0
 	}
 
@@ -227,7 +237,7 @@ impl Blob {
 	/// assert_eq!(s, "Hello");
 	/// ```
 	pub fn into_string(self) -> String {
-		String::from_utf8(self.0).expect("Blob content was not valid UTF-8")
+		String::from_utf8(self.into_vec()).expect("Blob content was not valid UTF-8")
 	}
 
 	/// Returns a hexadecimal string representation of the underlying bytes, with each byte separated by a space.
@@ -291,7 +301,7 @@ impl From<Vec<u8>> for Blob {
 	/// assert_eq!(blob.len(), 3);
 	/// ```
 	fn from(item: Vec<u8>) -> Self {
-		Blob(item)
+		Blob(Bytes::from(item))
 	}
 }
 
@@ -308,7 +318,7 @@ impl From<&Vec<u8>> for Blob {
 	/// assert_eq!(blob.len(), 3);
 	/// ```
 	fn from(item: &Vec<u8>) -> Self {
-		Blob(item.clone())
+		Blob(Bytes::copy_from_slice(item))
 	}
 }
 
@@ -325,7 +335,7 @@ impl From<&[u8]> for Blob {
 	/// assert_eq!(blob.len(), 3);
 	/// ```
 	fn from(item: &[u8]) -> Self {
-		Blob(item.to_vec())
+		Blob(Bytes::copy_from_slice(item))
 	}
 }
 
@@ -341,7 +351,7 @@ impl<const N: usize> From<&[u8; N]> for Blob {
 	/// assert_eq!(blob.len(), 3);
 	/// ```
 	fn from(item: &[u8; N]) -> Self {
-		Blob(item.to_vec())
+		Blob(Bytes::copy_from_slice(item))
 	}
 }
 
@@ -357,7 +367,7 @@ impl From<&str> for Blob {
 	/// assert_eq!(blob.len(), 13);
 	/// ```
 	fn from(item: &str) -> Self {
-		Blob(item.as_bytes().to_vec())
+		Blob(Bytes::copy_from_slice(item.as_bytes()))
 	}
 }
 
@@ -374,7 +384,7 @@ impl From<&String> for Blob {
 	/// assert_eq!(blob.as_str(), "Example");
 	/// ```
 	fn from(item: &String) -> Self {
-		Blob(item.as_bytes().to_vec())
+		Blob(Bytes::copy_from_slice(item.as_bytes()))
 	}
 }
 
@@ -391,7 +401,7 @@ impl From<String> for Blob {
 	/// assert_eq!(blob.as_str(), "Data");
 	/// ```
 	fn from(item: String) -> Self {
-		Blob(item.into_bytes())
+		Blob(Bytes::from(item))
 	}
 }
 
@@ -417,9 +427,18 @@ impl Default for Blob {
 	}
 }
 
-// Allow `Blob` to be used in multithreaded scenarios
-unsafe impl Send for Blob {}
-unsafe impl Sync for Blob {}
+impl From<Bytes> for Blob {
+	/// Converts [`Bytes`] into a [`Blob`], without copying the data.
+	fn from(item: Bytes) -> Self {
+		Blob(item)
+	}
+}
+
+impl AsRef<[u8]> for Blob {
+	fn as_ref(&self) -> &[u8] {
+		self.0.as_ref()
+	}
+}
 
 #[cfg(test)]
 mod tests {
@@ -645,10 +664,17 @@ mod tests {
 	}
 
 	#[test]
-	fn test_as_mut_slice() {
-		let mut blob = Blob::from("abc");
-		let slice = blob.as_mut_slice();
-		slice[0] = b'z';
-		assert_eq!(blob.as_str(), "zbc");
+	fn test_slice_does_not_copy() -> Result<()> {
+		let blob = Blob::from(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+		let slice = blob.slice(2..5);
+		assert_eq!(slice.as_slice(), &[2, 3, 4]);
+		assert_eq!(slice.as_slice().as_ptr(), blob.as_slice()[2..].as_ptr());
+
+		let range = blob.read_range(&ByteRange::new(6, 2))?;
+		assert_eq!(range.as_slice(), &[6, 7]);
+		assert_eq!(range.as_slice().as_ptr(), blob.as_slice()[6..].as_ptr());
+
+		assert_eq!(blob.clone().into_bytes().as_ptr(), blob.as_slice().as_ptr());
+		Ok(())
 	}
 }
@@ -9,9 +9,10 @@ use std::fmt::Debug;
 /// - `min_y` (south) is in `[-90.0, 90.0]`
 /// - `max_x` (east) is in `[-180.0, 180.0]`
 /// - `max_y` (north) is in `[-90.0, 90.0]`
-/// - `west <= east`
 /// - `south <= north`
 ///
+/// If `west > east`, the bounding box crosses the antimeridian, e.g. `[177, -19, -178, -16]` for Fiji.
+///
 /// These constraints can be verified using the [`check`](GeoBBox::check) method.
 #[derive(Clone, Copy, PartialEq)]
 pub struct GeoBBox(pub f64, pub f64, pub f64, pub f64);
@@ -121,14 +122,59 @@ impl GeoBBox {
 		format!("{},{},{},{}", self.0, self.1, self.2, self.3)
 	}
 
+	/// Returns `true` if the bounding box crosses the antimeridian, i.e. if `west > east`.
+	///
+	/// # Examples
+	/// ```
+	/// use versatiles_core::types::GeoBBox;
+	///
+	/// assert!(GeoBBox::new(177.0, -19.0, -178.0, -16.0).crosses_antimeridian());
+	/// assert!(!GeoBBox::new(-10.0, -5.0, 10.0, 5.0).crosses_antimeridian());
+	/// ```
+	pub fn crosses_antimeridian(&self) -> bool {
+		self.0 > self.2
+	}
+
+	/// Splits a bounding box crossing the antimeridian into a western part ending at 180°
+	/// and an eastern part starting at -180°. Other bounding boxes are returned unchanged.
+	///
+	/// # Examples
+	/// ```
+	/// use versatiles_core::types::GeoBBox;
+	///
+	/// let parts = GeoBBox::new(177.0, -19.0, -178.0, -16.0).split_antimeridian();
+	/// assert_eq!(
+	///     parts,
+	///     vec![GeoBBox::new(177.0, -19.0, 180.0, -16.0), GeoBBox::new(-180.0, -19.0, -178.0, -16.0)]
+	/// );
+	/// ```
+	pub fn split_antimeridian(&self) -> Vec<GeoBBox> {
+		if self.crosses_antimeridian() {
+			vec![
+				GeoBBox(self.0, self.1, 180.0, self.3),
+				GeoBBox(-180.0, self.1, self.2, self.3),
+			]
+		} else {
+			vec![*self]
+		}
+	}
+
+	/// Returns the longitude range as `(west, east)`, with `east` shifted by 360° if the
+	/// bounding box crosses the antimeridian, so that `west <= east`.
+	fn unwrapped_x(&self) -> (f64, f64) {
+		if self.crosses_antimeridian() {
+			(self.0, self.2 + 360.0)
+		} else {
+			(self.0, self.2)
+		}
+	}
+
 	/// Expands the current bounding box in place so that it includes the area
 	/// covered by `other`.
 	///
-	/// This is equivalent to:
-	/// - `min_x` = `min(self.min_x, other.min_x)`
-	/// - `min_y` = `min(self.min_y, other.min_y)`
-	/// - `max_x` = `max(self.max_x, other.max_x)`
-	/// - `max_y` = `max(self.max_y, other.max_y)`
+	/// Latitudes are extended to `min(south)` and `max(north)`. Longitudes are extended to
+	/// the smallest range covering both boxes, which crosses the antimeridian if that is shorter,
+	/// e.g. extending `[170, …, 175, …]` by `[-175, …, -170, …]` results in `[170, …, -170, …]`.
 	///
 	/// # Examples
 	/// ```
@@ -141,9 +187,24 @@ impl GeoBBox {
 	/// assert_eq!(bbox1.as_tuple(), (-12.0, -5.0, 10.0, 6.0));
 	/// ```
 	pub fn extend(&mut self, other: &GeoBBox) {
-		self.0 = self.0.min(other.0); // min_x
+		let (a0, a1) = self.unwrapped_x();
+		let (b0, b1) = other.unwrapped_x();
+
+		// try `other` shifted by one turn in both directions and keep the smallest range
+		let (mut x0, mut x1) = (a0.min(b0), a1.max(b1));
+		for shift in [-360.0, 360.0] {
+			let (c0, c1) = (a0.min(b0 + shift), a1.max(b1 + shift));
+			if c1 - c0 < x1 - x0 {
+				(x0, x1) = (c0, c1);
+			}
+		}
+
+		if x1 - x0 >= 360.0 {
+			(x0, x1) = (-180.0, 180.0);
+		}
+		self.0 = if x0 < -180.0 { x0 + 360.0 } else { x0 }; // min_x
 		self.1 = self.1.min(other.1); // min_y
-		self.2 = self.2.max(other.2); // max_x
+		self.2 = if x1 > 180.0 { x1 - 360.0 } else { x1 }; // max_x
 		self.3 = self.3.max(other.3); // max_y
 	}
 
@@ -159,11 +220,8 @@ impl GeoBBox {
 	/// Intersects the current bounding box in place so that it includes *only*
 	/// the overlapping area covered by both `self` and `other`.
 	///
-	/// This is equivalent to:
-	/// - `min_x` = `min(self.min_x, other.min_x)`
-	/// - `min_y` = `min(self.min_y, other.min_y)`
-	/// - `max_x` = `max(self.max_x, other.max_x)`
-	/// - `max_y` = `max(self.max_y, other.max_y)`
+	/// Latitudes are limited to `max(south)` and `min(north)`. Bounding boxes crossing the antimeridian
+	/// are intersected part by part. If the boxes don't overlap in longitude, the result has a width of zero.
 	///
 	/// # Examples
 	/// ```
@@ -176,9 +234,27 @@ impl GeoBBox {
 	/// assert_eq!(bbox1.as_tuple(), (-8.0, -4.0, 10.0, 4.0));
 	/// ```
 	pub fn intersect(&mut self, other: &GeoBBox) {
-		self.0 = self.0.max(other.0); // min_x
+		let mut result: Option<GeoBBox> = None;
+		for a in self.split_antimeridian() {
+			for b in other.split_antimeridian() {
+				if a.0.max(b.0) > a.2.min(b.2) {
+					continue;
+				}
+				let part = GeoBBox(a.0.max(b.0), self.1, a.2.min(b.2), self.3);
+				match result.as_mut() {
+					Some(r) => r.extend(&part),
+					None => result = Some(part),
+				}
+			}
+		}
+
+		let (x_min, x_max) = match result {
+			Some(r) => (r.0, r.2),
+			None => (self.0.max(other.0), self.0.max(other.0)),
+		};
+		self.0 = x_min; // min_x
 		self.1 = self.1.max(other.1); // min_y
-		self.2 = self.2.min(other.2); // max_x
+		self.2 = x_max; // max_x
 		self.3 = self.3.min(other.3); // max_y
 	}
 
@@ -203,10 +279,9 @@ impl GeoBBox {
 	}
 
 	/// Validates that the bounding box is within the typical lat/lon ranges,
-	/// and that the coordinates are in increasing order:
+	/// and that the latitudes are in increasing order:
 	/// - `min_x >= -180.0`
 	/// - `min_y >= -90.0`
-	/// - `min_x <= max_x`
 	/// - `min_y <= max_y`
 	/// - `max_x <= 180.0`
 	/// - `max_y <= 90.0`
@@ -231,7 +306,6 @@ impl GeoBBox {
 		ensure!(self.1 >= -90., "y_min ({}) must be >= -90", self.1);
 		ensure!(self.2 <= 180., "x_max ({}) must be <= 180", self.2);
 		ensure!(self.3 <= 90., "y_max ({}) must be <= 90", self.3);
-		ensure!(self.0 <= self.2, "x_min ({}) must be <= x_max ({})", self.0, self.2);
 		ensure!(self.1 <= self.3, "y_min ({}) must be <= y_max ({})", self.1, self.3);
 		Ok(())
 	}
@@ -421,12 +495,86 @@ mod tests {
 		let bbox = GeoBBox::new(-10.0, -5.0, 10.0, 95.0);
 		assert!(bbox.check().is_err(), "Expected error for north > 90");
 
-		// West > East
-		let bbox = GeoBBox::new(10.0, -5.0, -10.0, 5.0);
-		assert!(bbox.check().is_err(), "Expected error for west > east");
-
 		// South > North
 		let bbox = GeoBBox::new(-10.0, 6.0, 10.0, 5.0);
 		assert!(bbox.check().is_err(), "Expected error for south > north");
 	}
+
+	#[test]
+	fn test_check_antimeridian() -> Result<()> {
+		// West > East crosses the antimeridian
+		let bbox = GeoBBox::new(177.0, -19.0, -178.0, -16.0);
+		bbox.check()?;
+		assert!(bbox.crosses_antimeridian());
+		Ok(())
+	}
+
+	#[test]
+	fn test_split_antimeridian() {
+		let bbox = GeoBBox::new(-10.0, -5.0, 10.0, 5.0);
+		assert_eq!(bbox.split_antimeridian(), vec![bbox]);
+
+		let parts = GeoBBox::new(177.0, -19.0, -178.0, -16.0).split_antimeridian();
+		assert_eq!(
+			parts,
+			vec![
+				GeoBBox::new(177.0, -19.0, 180.0, -16.0),
+				GeoBBox::new(-180.0, -19.0, -178.0, -16.0)
+			]
+		);
+	}
+
+	#[test]
+	fn test_extend_antimeridian() {
+		let extended = |a: [f64; 4], b: [f64; 4]| GeoBBox::from(&a).extended(&GeoBBox::from(&b)).as_array();
+
+		// the shorter way crosses the antimeridian
+		assert_eq!(
+			extended([170.0, -5.0, 175.0, 5.0], [-175.0, -6.0, -170.0, 4.0]),
+			[170.0, -6.0, -170.0, 5.0]
+		);
+		// the shorter way doesn't
+		assert_eq!(
+			extended([-170.0, -5.0, -160.0, 5.0], [10.0, -5.0, 20.0, 5.0]),
+			[-170.0, -5.0, 20.0, 5.0]
+		);
+		// both parts of a split box
+		assert_eq!(
+			extended([177.0, -19.0, 180.0, -16.0], [-180.0, -19.0, -178.0, -16.0]),
+			[177.0, -19.0, -178.0, -16.0]
+		);
+		// crossing and non-crossing
+		assert_eq!(
+			extended([177.0, -19.0, -178.0, -16.0], [-179.0, -20.0, -170.0, -17.0]),
+			[177.0, -20.0, -170.0, -16.0]
+		);
+		// covering the whole world
+		assert_eq!(
+			extended([0.0, -5.0, -10.0, 5.0], [-20.0, -5.0, 10.0, 5.0]),
+			[-180.0, -5.0, 180.0, 5.0]
+		);
+	}
+
+	#[test]
+	fn test_intersect_antimeridian() {
+		let intersected = |a: [f64; 4], b: [f64; 4]| GeoBBox::from(&a).intersected(&GeoBBox::from(&b)).as_array();
+
+		let fiji = [177.0, -19.0, -178.0, -16.0];
+		assert_eq!(intersected([-180.0, -85.0, 180.0, 85.0], fiji), fiji);
+		assert_eq!(intersected(fiji, [-180.0, -85.0, 180.0, 85.0]), fiji);
+		assert_eq!(
+			intersected(fiji, [178.0, -20.0, -179.0, -17.0]),
+			[178.0, -19.0, -179.0, -17.0]
+		);
+		assert_eq!(
+			intersected(fiji, [0.0, -20.0, 179.0, 0.0]),
+			[177.0, -19.0, 179.0, -16.0]
+		);
+		// no overlap in longitude
+		assert_eq!(intersected(fiji, [0.0, -20.0, 10.0, 0.0]), [177.0, -19.0, 177.0, -16.0]);
+		assert_eq!(
+			intersected([0.0, -5.0, 10.0, 5.0], [20.0, -5.0, 30.0, 5.0]),
+			[20.0, -5.0, 20.0, 5.0]
+		);
+	}
 }
@@ -18,6 +18,9 @@ pub use limited_cache::*;
 mod probe_depth;
 pub use probe_depth::*;
 
+mod read_hints;
+pub use read_hints::*;
+
 mod tile_bbox;
 pub use tile_bbox::*;
 
@@ -33,6 +36,15 @@ pub use tile_coords::*;
 mod tile_format;
 pub use tile_format::*;
 
+mod tile_map;
+pub use tile_map::*;
+
+mod tile_scheme;
+pub use tile_scheme::*;
+
+mod tile_selection;
+pub use tile_selection::*;
+
 mod tile_stream;
 pub use tile_stream::*;
 
@@ -0,0 +1,82 @@
+use super::{TileBBoxPyramid, TileCoord3};
+
+/// Hints that tell a `TilesReader` which tiles will be requested.
+///
+/// Readers can use them to prune their index while opening a container, e.g. by skipping zoom level
+/// directories, instead of reading everything and filtering afterwards.
+/// Hints are only an optimization: a reader may ignore them, and callers must still filter the tiles.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReadHints {
+	/// Only tiles inside this pyramid will be requested.
+	pub bbox_pyramid: Option<TileBBoxPyramid>,
+}
+
+impl ReadHints {
+	/// Create hints restricting the reader to a bounding box pyramid.
+	pub fn from_bbox_pyramid(bbox_pyramid: TileBBoxPyramid) -> ReadHints {
+		ReadHints {
+			bbox_pyramid: Some(bbox_pyramid),
+		}
+	}
+
+	/// Returns `true` if no tile of this zoom level will be requested.
+	pub fn skips_level(&self, level: u8) -> bool {
+		match &self.bbox_pyramid {
+			Some(pyramid) => pyramid.get_level_bbox(level).is_empty(),
+			None => false,
+		}
+	}
+
+	/// Returns `true` if tiles of this zoom level and column might be requested.
+	pub fn contains_column(&self, level: u8, x: u32) -> bool {
+		match &self.bbox_pyramid {
+			Some(pyramid) => {
+				let bbox = pyramid.get_level_bbox(level);
+				!bbox.is_empty() && bbox.x_min <= x && x <= bbox.x_max
+			}
+			None => true,
+		}
+	}
+
+	/// Returns `true` if this tile might be requested.
+	pub fn contains_coord(&self, coord: &TileCoord3) -> bool {
+		match &self.bbox_pyramid {
+			Some(pyramid) => pyramid.contains_coord(coord),
+			None => true,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::TileBBox;
+
+	#[test]
+	fn test_default() {
+		let hints = ReadHints::default();
+		assert!(!hints.skips_level(5));
+		assert!(hints.contains_column(5, 3));
+		assert!(hints.contains_coord(&TileCoord3::new(3, 4, 5).unwrap()));
+	}
+
+	#[test]
+	fn test_bbox_pyramid() {
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		pyramid.set_level_bbox(TileBBox::new(4, 2, 3, 5, 6).unwrap());
+		let hints = ReadHints::from_bbox_pyramid(pyramid);
+
+		assert!(hints.skips_level(3));
+		assert!(!hints.skips_level(4));
+
+		assert!(!hints.contains_column(4, 1));
+		assert!(hints.contains_column(4, 2));
+		assert!(hints.contains_column(4, 5));
+		assert!(!hints.contains_column(4, 6));
+		assert!(!hints.contains_column(3, 2));
+
+		assert!(hints.contains_coord(&TileCoord3::new(2, 3, 4).unwrap()));
+		assert!(!hints.contains_coord(&TileCoord3::new(2, 2, 4).unwrap()));
+		assert!(!hints.contains_coord(&TileCoord3::new(2, 3, 5).unwrap()));
+	}
+}
@@ -8,7 +8,8 @@
 //! This is particularly useful in mapping applications where tile management is essential.
 
 use super::{GeoBBox, TileBBoxPyramid, TileCoord2, TileCoord3};
-use anyhow::{ensure, Result};
+use crate::json::JsonValue;
+use anyhow::{ensure, Context, Result};
 use itertools::Itertools;
 use std::{
 	fmt,
@@ -91,6 +92,23 @@ impl TileBBox {
 		Ok(bbox)
 	}
 
+	/// Creates a `TileBBox` from a JSON object like `{"level":3,"x_min":1,"y_min":2,"x_max":3,"y_max":4}`,
+	/// as written by [`TileBBox::as_json_value`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if a key is missing or the values are not a valid bounding box.
+	pub fn from_json_value(json: &JsonValue) -> Result<TileBBox> {
+		let object = json.as_object()?;
+		let get = |key: &str| -> Result<u32> {
+			object
+				.get_number::<u32>(key)?
+				.with_context(|| format!("bbox is missing \"{key}\""))
+		};
+		let level = object.get_number::<u8>("level")?.context("bbox is missing \"level\"")?;
+		TileBBox::new(level, get("x_min")?, get("y_min")?, get("x_max")?, get("y_max")?)
+	}
+
 	/// Creates a `TileBBox` covering the entire range of tiles at the specified zoom level.
 	///
 	/// # Arguments
@@ -149,10 +167,15 @@ impl TileBBox {
 	/// # Errors
 	///
 	/// - If the geographical coordinates are invalid.
+	/// - If the bounding box crosses the antimeridian. Use [`GeoBBox::split_antimeridian`] to convert each part.
 	/// - If the converted tile coordinates are out of bounds.
 	pub fn from_geo(level: u8, bbox: &GeoBBox) -> Result<TileBBox> {
 		ensure!(level <= 31, "level ({level}) must be <= 31");
 		bbox.check()?; // Validate GeoBBox
+		ensure!(
+			!bbox.crosses_antimeridian(),
+			"bbox {bbox:?} crosses the antimeridian, so it cannot be converted to a single tile bbox"
+		);
 
 		// Convert geographical coordinates to tile coordinates
 		let p_min = TileCoord2::from_geo(bbox.0, bbox.3, level, false)?;
@@ -505,6 +528,17 @@ impl TileBBox {
 	// Coordinate Transformations
 	// -------------------------------------------------------------------------
 
+	/// Returns the bounding box as a JSON object with the keys `level`, `x_min`, `y_min`, `x_max` and `y_max`.
+	pub fn as_json_value(&self) -> JsonValue {
+		JsonValue::from(vec![
+			("level", self.level as u32),
+			("x_min", self.x_min),
+			("y_min", self.y_min),
+			("x_max", self.x_max),
+			("y_max", self.y_max),
+		])
+	}
+
 	/// Converts the bounding box to geographical coordinates (`GeoBBox`).
 	///
 	/// # Returns
@@ -701,11 +735,11 @@ impl TileBBox {
 			));
 		}
 
-		let x = coord.x - self.x_min;
-		let y = coord.y - self.y_min;
-		let index = y * (self.x_max + 1 - self.x_min) + x;
+		let x = (coord.x - self.x_min) as u64;
+		let y = (coord.y - self.y_min) as u64;
+		let index = y * (self.width() as u64) + x;
 
-		Ok(index as usize)
+		usize::try_from(index).map_err(|_| anyhow::anyhow!("tile index {index} does not fit into usize"))
 	}
 
 	/// Retrieves the 0-based index of a `TileCoord3` within the bounding box.
@@ -727,11 +761,11 @@ impl TileBBox {
 			));
 		}
 
-		let x = coord.x - self.x_min;
-		let y = coord.y - self.y_min;
-		let index = y * (self.x_max + 1 - self.x_min) + x;
+		let x = (coord.x - self.x_min) as u64;
+		let y = (coord.y - self.y_min) as u64;
+		let index = y * (self.width() as u64) + x;
 
-		Ok(index as usize)
+		usize::try_from(index).map_err(|_| anyhow::anyhow!("tile index {index} does not fit into usize"))
 	}
 
 	/// Retrieves the `TileCoord2` at a specific index within the bounding box.
@@ -745,7 +779,7 @@ impl TileBBox {
 	/// * `Ok(TileCoord2)` if the index is within bounds.
 	/// * `Err(anyhow::Error)` if the index is out of bounds.
 	pub fn get_coord2_by_index(&self, index: u32) -> Result<TileCoord2> {
-		ensure!(index < self.count_tiles() as u32, "index out of bounds");
+		ensure!((index as u64) < self.count_tiles(), "index out of bounds");
 
 		let width = self.width();
 		Ok(TileCoord2::new(
@@ -765,7 +799,7 @@ impl TileBBox {
 	/// * `Ok(TileCoord3)` if the index is within bounds.
 	/// * `Err(anyhow::Error)` if the index is out of bounds.
 	pub fn get_coord3_by_index(&self, index: u32) -> Result<TileCoord3> {
-		ensure!(index < self.count_tiles() as u32, "index {index} out of bounds");
+		ensure!((index as u64) < self.count_tiles(), "index {index} out of bounds");
 
 		let width = self.width();
 		let x = index.rem(width) + self.x_min;
@@ -1397,6 +1431,26 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn should_handle_more_than_u32_max_tiles() -> Result<()> {
+		// z17 covers 2^34 tiles, which does not fit into u32
+		let bbox = TileBBox::new_full(17)?;
+		assert_eq!(bbox.count_tiles(), 1u64 << 34);
+
+		let last = TileCoord3::new(131071, 131071, 17)?;
+		assert_eq!(bbox.get_tile_index3(&last)?, (1usize << 34) - 1);
+		assert_eq!(bbox.get_tile_index2(&last.as_coord2())?, (1usize << 34) - 1);
+
+		// indexes addressable by u32 must not be rejected due to a truncated tile count
+		assert_eq!(bbox.get_coord3_by_index(u32::MAX)?, TileCoord3::new(131071, 32767, 17)?);
+		assert_eq!(bbox.get_coord2_by_index(131072)?, TileCoord2::new(0, 1));
+
+		// full z31 pyramid level still fits into u64
+		assert_eq!(TileBBox::new_full(31)?.count_tiles(), 1u64 << 62);
+
+		Ok(())
+	}
+
 	#[test]
 	fn should_convert_to_geo_bbox_correctly() -> Result<()> {
 		let bbox = TileBBox::new(4, 5, 10, 7, 12)?;
@@ -1582,4 +1636,30 @@ mod tests {
 		assert_eq!(grids, expected_grids);
 		Ok(())
 	}
+
+	#[test]
+	fn should_convert_to_and_from_json() -> Result<()> {
+		let bbox = TileBBox::new(4, 5, 6, 7, 8)?;
+		let json = bbox.as_json_value();
+		assert_eq!(
+			json.stringify(),
+			"{\"level\":4,\"x_max\":7,\"x_min\":5,\"y_max\":8,\"y_min\":6}"
+		);
+		assert_eq!(TileBBox::from_json_value(&json)?, bbox);
+
+		let error = |json: &str| {
+			TileBBox::from_json_value(&JsonValue::parse_str(json).unwrap())
+				.unwrap_err()
+				.to_string()
+		};
+		assert_eq!(
+			error("{\"level\":4,\"x_min\":5,\"y_min\":6,\"x_max\":7}"),
+			"bbox is missing \"y_max\""
+		);
+		assert_eq!(
+			error("{\"level\":2,\"x_min\":0,\"y_min\":0,\"x_max\":7,\"y_max\":0}"),
+			"x_max (7) must be <= max (3)"
+		);
+		Ok(())
+	}
 }
@@ -2,6 +2,8 @@
 //! across multiple zoom levels. It provides methods to create, manipulate, and query these bounding boxes.
 
 use super::{GeoBBox, GeoCenter, TileBBox, TileCoord3};
+use crate::json::{JsonArray, JsonValue};
+use anyhow::{ensure, Result};
 use std::array::from_fn;
 use std::fmt;
 
@@ -78,23 +80,52 @@ impl TileBBoxPyramid {
 	///
 	/// A new `TileBBoxPyramid` populated with bounding boxes derived from `bbox`.
 	/// Levels outside the given range remain empty.
+	///
+	/// A `bbox` crossing the antimeridian covers the full width of each level, since a single
+	/// tile bbox cannot wrap around. Use one pyramid per [`GeoBBox::split_antimeridian`] part instead.
 	pub fn from_geo_bbox(zoom_level_min: u8, zoom_level_max: u8, bbox: &GeoBBox) -> TileBBoxPyramid {
 		let mut pyramid = TileBBoxPyramid::new_empty();
 		for z in zoom_level_min..=zoom_level_max {
-			pyramid.set_level_bbox(TileBBox::from_geo(z, bbox).unwrap());
+			pyramid.set_level_bbox(tile_bbox_from_geo(z, bbox));
 		}
 		pyramid
 	}
 
+	/// Creates a `TileBBoxPyramid` from a JSON array of bounding boxes, as written by
+	/// [`TileBBoxPyramid::as_json_value`]. Missing zoom levels remain empty.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the JSON is not an array of valid bounding boxes, or if a zoom level occurs twice.
+	pub fn from_json_value(json: &JsonValue) -> Result<TileBBoxPyramid> {
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		for entry in json.as_array()?.0.iter() {
+			let bbox = TileBBox::from_json_value(entry)?;
+			ensure!(
+				pyramid.get_level_bbox(bbox.level).is_empty(),
+				"zoom level {} is defined twice",
+				bbox.level
+			);
+			pyramid.set_level_bbox(bbox);
+		}
+		Ok(pyramid)
+	}
+
+	/// Returns the non-empty levels as a JSON array of bounding boxes (see [`TileBBox::as_json_value`]).
+	pub fn as_json_value(&self) -> JsonValue {
+		JsonValue::Array(JsonArray(self.iter_levels().map(|bbox| bbox.as_json_value()).collect()))
+	}
+
 	/// Intersects each bounding box in the pyramid with the bounding box derived from the provided [`GeoBBox`].
 	///
 	/// # Arguments
 	///
-	/// * `geo_bbox` - The geographical bounding box to intersect with.
+	/// * `geo_bbox` - The geographical bounding box to intersect with. If it crosses the antimeridian,
+	///   the full width of each level is kept (see [`TileBBoxPyramid::from_geo_bbox`]).
 	pub fn intersect_geo_bbox(&mut self, geo_bbox: &GeoBBox) {
 		for (z, tile_bbox) in self.level_bbox.iter_mut().enumerate() {
 			tile_bbox
-				.intersect_bbox(&TileBBox::from_geo(z as u8, geo_bbox).unwrap())
+				.intersect_bbox(&tile_bbox_from_geo(z as u8, geo_bbox))
 				.unwrap();
 		}
 	}
@@ -251,6 +282,61 @@ impl TileBBoxPyramid {
 		self.level_bbox.iter().map(|bbox| bbox.count_tiles()).sum()
 	}
 
+	/// Counts the tiles of every non-empty zoom level, as `(level, tile_count)` in ascending order.
+	pub fn count_tiles_per_level(&self) -> Vec<(u8, u64)> {
+		self
+			.iter_levels()
+			.map(|bbox| (bbox.level, bbox.count_tiles()))
+			.collect()
+	}
+
+	/// Returns the intersection with another pyramid, without changing this one. See [`TileBBoxPyramid::intersect`].
+	pub fn get_intersection(&self, other_bbox_pyramid: &TileBBoxPyramid) -> TileBBoxPyramid {
+		let mut pyramid = self.clone();
+		pyramid.intersect(other_bbox_pyramid);
+		pyramid
+	}
+
+	/// Estimates the size of all tiles, if every tile has `bytes_per_tile` bytes on average.
+	pub fn estimate_bytes(&self, bytes_per_tile: u64) -> u64 {
+		self.count_tiles().saturating_mul(bytes_per_tile)
+	}
+
+	/// Returns the highest zoom level, so that the tiles of all levels up to it fit into `max_bytes`,
+	/// if every tile has `bytes_per_tile` bytes on average, e.g. to check quotas before a conversion.
+	///
+	/// Returns `None` if not even the lowest zoom level fits, or the pyramid is empty.
+	pub fn get_zoom_max_for_budget(&self, max_bytes: u64, bytes_per_tile: u64) -> Option<u8> {
+		let mut bytes: u64 = 0;
+		let mut zoom_max = None;
+		for (level, tile_count) in self.count_tiles_per_level() {
+			bytes = bytes.saturating_add(tile_count.saturating_mul(bytes_per_tile));
+			if bytes > max_bytes {
+				break;
+			}
+			zoom_max = Some(level);
+		}
+		zoom_max
+	}
+
+	/// Returns a compact, human-readable summary with the number of tiles per level,
+	/// e.g. `"z0-2: 21 tiles (z0: 1, z1: 4, z2: 16)"`, or `"empty"`.
+	pub fn get_summary(&self) -> String {
+		let (Some(zoom_min), Some(zoom_max)) = (self.get_zoom_min(), self.get_zoom_max()) else {
+			return String::from("empty");
+		};
+		let levels: Vec<String> = self
+			.count_tiles_per_level()
+			.iter()
+			.map(|(level, tile_count)| format!("z{level}: {tile_count}"))
+			.collect();
+		format!(
+			"z{zoom_min}-{zoom_max}: {} tiles ({})",
+			self.count_tiles(),
+			levels.join(", ")
+		)
+	}
+
 	/// Checks if **all** bounding boxes in this pyramid are empty.
 	pub fn is_empty(&self) -> bool {
 		self.level_bbox.iter().all(|bbox| bbox.is_empty())
@@ -290,6 +376,17 @@ impl TileBBoxPyramid {
 	}
 }
 
+/// Converts a [`GeoBBox`] to a [`TileBBox`], covering all parts of a bbox crossing the antimeridian.
+fn tile_bbox_from_geo(level: u8, geo_bbox: &GeoBBox) -> TileBBox {
+	let mut tile_bbox = TileBBox::new_empty(level).unwrap();
+	for part in geo_bbox.split_antimeridian() {
+		tile_bbox
+			.include_bbox(&TileBBox::from_geo(level, &part).unwrap())
+			.unwrap();
+	}
+	tile_bbox
+}
+
 impl fmt::Debug for TileBBoxPyramid {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		// Debug: show only non-empty levels
@@ -374,6 +471,25 @@ mod tests {
 		assert!(pyramid1.is_full(8));
 	}
 
+	#[test]
+	fn test_statistics() {
+		let pyramid = TileBBoxPyramid::new_full(2);
+		assert_eq!(pyramid.count_tiles_per_level(), vec![(0, 1), (1, 4), (2, 16)]);
+		assert_eq!(pyramid.get_summary(), "z0-2: 21 tiles (z0: 1, z1: 4, z2: 16)");
+		assert_eq!(TileBBoxPyramid::new_empty().get_summary(), "empty");
+
+		let berlin = TileBBoxPyramid::from_geo_bbox(1, 5, &GeoBBox(13.0, 52.0, 14.0, 53.0));
+		let intersection = pyramid.get_intersection(&berlin);
+		assert_eq!(intersection.get_summary(), "z1-2: 2 tiles (z1: 1, z2: 1)");
+		assert_eq!(pyramid.count_tiles(), 21);
+
+		assert_eq!(pyramid.estimate_bytes(1000), 21000);
+		assert_eq!(pyramid.get_zoom_max_for_budget(21000, 1000), Some(2));
+		assert_eq!(pyramid.get_zoom_max_for_budget(20999, 1000), Some(1));
+		assert_eq!(pyramid.get_zoom_max_for_budget(999, 1000), None);
+		assert_eq!(TileBBoxPyramid::new_full(31).estimate_bytes(u64::MAX), u64::MAX);
+	}
+
 	#[test]
 	fn test_limit_by_geo_bbox() {
 		let mut pyramid = TileBBoxPyramid::new_full(8);
@@ -506,6 +622,17 @@ mod tests {
 		assert!(pyramid.get_level_bbox(6).is_empty());
 	}
 
+	#[test]
+	fn test_geo_bbox_antimeridian() {
+		let fiji = GeoBBox(177.0, -19.0, -178.0, -16.0);
+		let pyramid = TileBBoxPyramid::from_geo_bbox(3, 3, &fiji);
+		assert_eq!(pyramid.to_string(), "[3: [0,4,7,4] (8)]");
+
+		let mut pyramid = TileBBoxPyramid::new_full(3);
+		pyramid.intersect_geo_bbox(&fiji);
+		assert_eq!(pyramid.get_level_bbox(3), &TileBBox::new(3, 0, 4, 7, 4).unwrap());
+	}
+
 	#[test]
 	fn test_add_border2() {
 		let mut pyramid = TileBBoxPyramid::new_empty();
@@ -674,4 +801,29 @@ mod tests {
 		let maybe_center = p.get_geo_center();
 		assert!(maybe_center.is_some());
 	}
+
+	#[test]
+	fn test_json_roundtrip() -> Result<()> {
+		let pyramid = TileBBoxPyramid::from_geo_bbox(3, 5, &GeoBBox(13.0, 52.0, 14.0, 53.0));
+		let json = pyramid.as_json_value().stringify();
+		assert_eq!(
+			json,
+			"[{\"level\":3,\"x_max\":4,\"x_min\":4,\"y_max\":2,\"y_min\":2},{\"level\":4,\"x_max\":8,\"x_min\":8,\"y_max\":5,\"y_min\":5},{\"level\":5,\"x_max\":17,\"x_min\":17,\"y_max\":10,\"y_min\":10}]"
+		);
+		assert_eq!(
+			TileBBoxPyramid::from_json_value(&JsonValue::parse_str(&json)?)?,
+			pyramid
+		);
+
+		assert!(TileBBoxPyramid::from_json_value(&JsonValue::parse_str("[]")?)?.is_empty());
+
+		let twice = "[{\"level\":1,\"x_min\":0,\"y_min\":0,\"x_max\":1,\"y_max\":1},{\"level\":1,\"x_min\":0,\"y_min\":0,\"x_max\":0,\"y_max\":0}]";
+		assert_eq!(
+			TileBBoxPyramid::from_json_value(&JsonValue::parse_str(twice)?)
+				.unwrap_err()
+				.to_string(),
+			"zoom level 1 is defined twice"
+		);
+		Ok(())
+	}
 }
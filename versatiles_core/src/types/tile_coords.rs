@@ -22,7 +22,7 @@
 //! let geo = coord3.as_geo();
 //! ```
 
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 use std::{
 	f64::consts::PI as PI32,
 	fmt::{self, Debug},
@@ -31,6 +31,9 @@ use std::{
 
 use super::GeoBBox;
 
+/// The northern and southern limit of Web Mercator in degrees.
+const MAX_MERCATOR_LAT: f64 = 85.051_128_779_806_6;
+
 #[derive(Eq, PartialEq, Clone, Hash)]
 pub struct TileCoord2 {
 	pub x: u32,
@@ -111,23 +114,39 @@ impl TileCoord3 {
 	}
 
 	pub fn as_geo(&self) -> [f64; 2] {
-		let zoom: f64 = 2.0f64.powi(self.z as i32);
+		self.pixel_to_lonlat([0.0, 0.0], 1.0)
+	}
 
+	pub fn as_geo_bbox(&self) -> GeoBBox {
+		let [x_min, y_max] = self.pixel_to_lonlat([0.0, 0.0], 1.0);
+		let [x_max, y_min] = self.pixel_to_lonlat([1.0, 1.0], 1.0);
+		GeoBBox(x_min, y_max, x_max, y_min)
+	}
+
+	/// Converts a pixel position within this tile to longitude and latitude (Web Mercator).
+	///
+	/// The tile covers the pixels `0..tile_size` in both directions, with y pointing south,
+	/// so `tile_size` is e.g. 256 for raster tiles or the extent of a vector tile layer.
+	/// Positions outside the tile are allowed and map to the neighbouring tiles.
+	pub fn pixel_to_lonlat(&self, pixel: [f64; 2], tile_size: f64) -> [f64; 2] {
+		let zoom: f64 = 2.0f64.powi(self.z as i32);
+		let x = (self.x as f64 + pixel[0] / tile_size) / zoom;
+		let y = (self.y as f64 + pixel[1] / tile_size) / zoom;
 		[
-			((self.x as f64) / zoom - 0.5) * 360.0,
-			((PI32 * (1.0 - 2.0 * (self.y as f64) / zoom)).exp().atan() / PI32 - 0.25) * 360.0,
+			(x - 0.5) * 360.0,
+			((PI32 * (1.0 - 2.0 * y)).exp().atan() / PI32 - 0.25) * 360.0,
 		]
 	}
 
-	pub fn as_geo_bbox(&self) -> GeoBBox {
+	/// Converts longitude and latitude to a pixel position within this tile, the inverse of [`Self::pixel_to_lonlat`].
+	///
+	/// Latitudes beyond the limits of Web Mercator (±85.0511°) are clamped.
+	pub fn lonlat_to_pixel(&self, lonlat: [f64; 2], tile_size: f64) -> [f64; 2] {
 		let zoom: f64 = 2.0f64.powi(self.z as i32);
-
-		GeoBBox(
-			((self.x as f64) / zoom - 0.5) * 360.0,
-			((PI32 * (1.0 - 2.0 * (self.y as f64) / zoom)).exp().atan() / PI32 - 0.25) * 360.0,
-			(((self.x + 1) as f64) / zoom - 0.5) * 360.0,
-			((PI32 * (1.0 - 2.0 * ((self.y + 1) as f64) / zoom)).exp().atan() / PI32 - 0.25) * 360.0,
-		)
+		let lat = lonlat[1].clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+		let x = (lonlat[0] / 360.0 + 0.5) * zoom;
+		let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI32) / 2.0 * zoom;
+		[(x - self.x as f64) * tile_size, (y - self.y as f64) * tile_size]
 	}
 
 	pub fn as_coord2(&self) -> TileCoord2 {
@@ -151,6 +170,22 @@ impl TileCoord3 {
 		let offset = (size * size - 1) / 3;
 		offset + size * self.y as u64 + self.x as u64
 	}
+
+	/// Inverse of [`get_sort_index`](Self::get_sort_index).
+	pub fn from_sort_index(mut index: u64) -> Result<TileCoord3> {
+		for z in 0..=31u8 {
+			let size = 2u64.pow(z as u32);
+			if index < size * size {
+				return Ok(TileCoord3 {
+					x: (index % size) as u32,
+					y: (index / size) as u32,
+					z,
+				});
+			}
+			index -= size * size;
+		}
+		bail!("sort index is too large")
+	}
 }
 
 impl Debug for TileCoord3 {
@@ -263,6 +298,39 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn tilecoord3_pixel_to_lonlat() {
+		let coord = TileCoord3::new(3, 4, 5).unwrap();
+		assert_eq!(coord.pixel_to_lonlat([0.0, 0.0], 256.0), coord.as_geo());
+		assert_eq!(
+			coord.pixel_to_lonlat([4096.0, 4096.0], 4096.0),
+			TileCoord3::new(4, 5, 5).unwrap().as_geo()
+		);
+		assert_eq!(
+			TileCoord3::new(0, 0, 0).unwrap().pixel_to_lonlat([128.0, 128.0], 256.0),
+			[0.0, 0.0]
+		);
+
+		for pixel in [[0.0, 0.0], [12.5, 200.0], [-30.0, 300.0], [255.0, 1.0]] {
+			let lonlat = coord.pixel_to_lonlat(pixel, 256.0);
+			let result = coord.lonlat_to_pixel(lonlat, 256.0);
+			assert!((result[0] - pixel[0]).abs() < 1e-6, "{result:?} != {pixel:?}");
+			assert!((result[1] - pixel[1]).abs() < 1e-6, "{result:?} != {pixel:?}");
+		}
+	}
+
+	#[test]
+	fn tilecoord3_lonlat_to_pixel() {
+		let coord = TileCoord3::new(0, 0, 0).unwrap();
+		assert_eq!(coord.lonlat_to_pixel([0.0, 0.0], 256.0), [128.0, 128.0]);
+		assert_eq!(coord.lonlat_to_pixel([-180.0, 90.0], 4096.0)[0], 0.0);
+		assert!(coord.lonlat_to_pixel([-180.0, 90.0], 4096.0)[1].abs() < 1e-6);
+		assert!((coord.lonlat_to_pixel([180.0, -90.0], 4096.0)[1] - 4096.0).abs() < 1e-6);
+
+		let coord = TileCoord3::new(1, 1, 1).unwrap();
+		assert_eq!(coord.lonlat_to_pixel([0.0, 0.0], 512.0), [0.0, 0.0]);
+	}
+
 	#[test]
 	fn tilecoord3_as_coord2() {
 		let coord = TileCoord3::new(3, 4, 5).unwrap();
@@ -280,6 +348,19 @@ mod tests {
 	fn tilecoord3_get_sort_index() {
 		let coord = TileCoord3::new(3, 4, 5).unwrap();
 		assert_eq!(coord.get_sort_index(), 472);
+		assert_eq!(TileCoord3::from_sort_index(472).unwrap(), coord);
+
+		for coord in [
+			(0, 0, 0),
+			(1, 1, 1),
+			(0, 0, 2),
+			(123, 456, 10),
+			(2147483647, 2147483647, 31),
+		] {
+			let coord = TileCoord3::new(coord.0, coord.1, coord.2).unwrap();
+			assert_eq!(TileCoord3::from_sort_index(coord.get_sort_index()).unwrap(), coord);
+		}
+		assert!(TileCoord3::from_sort_index(u64::MAX).is_err());
 	}
 
 	#[test]
@@ -3,7 +3,7 @@
 //! extracting the format from a filename.
 //!
 //! The `TileFormat` enum supports a variety of tile formats such as `AVIF`, `BIN`, `GEOJSON`, `JPG`,
-//! `JSON`, `PBF`, `PNG`, `SVG`, `TOPOJSON`, and `WEBP`. Each variant provides its canonical file extension
+//! `JSON`, `JXL`, `PBF`, `PNG`, `SVG`, `TOPOJSON`, and `WEBP`. Each variant provides its canonical file extension
 //! and can be derived from a filename or string representation.
 //!
 //! # Examples
@@ -43,6 +43,7 @@ use std::fmt::{Display, Formatter};
 /// - `GEOJSON` - GeoJSON vector data
 /// - `JPG` - JPEG image format (including `.jpeg`)
 /// - `JSON` - Generic JSON data
+/// - `JXL` - JPEG XL image format
 /// - `PBF` - Mapbox Vector Tile in Protocol Buffer format
 /// - `PNG` - PNG image format
 /// - `SVG` - SVG image format
@@ -57,6 +58,7 @@ pub enum TileFormat {
 	GEOJSON,
 	JPG,
 	JSON,
+	JXL,
 	PBF,
 	PNG,
 	SVG,
@@ -80,6 +82,7 @@ impl TileFormat {
 			TileFormat::GEOJSON => "geojson",
 			TileFormat::JPG => "jpg",
 			TileFormat::JSON => "json",
+			TileFormat::JXL => "jxl",
 			TileFormat::PBF => "pbf",
 			TileFormat::PNG => "png",
 			TileFormat::SVG => "svg",
@@ -100,7 +103,12 @@ impl TileFormat {
 	/// ```
 	pub fn as_type_str(&self) -> &str {
 		match self {
-			TileFormat::AVIF | TileFormat::JPG | TileFormat::PNG | TileFormat::SVG | TileFormat::WEBP => "image",
+			TileFormat::AVIF
+			| TileFormat::JPG
+			| TileFormat::JXL
+			| TileFormat::PNG
+			| TileFormat::SVG
+			| TileFormat::WEBP => "image",
 			TileFormat::BIN | TileFormat::JSON => "unknown",
 			TileFormat::GEOJSON | TileFormat::PBF | TileFormat::TOPOJSON => "vector",
 		}
@@ -123,6 +131,7 @@ impl TileFormat {
 			TileFormat::JPG => "image/jpeg",
 			TileFormat::WEBP => "image/webp",
 			TileFormat::AVIF => "image/avif",
+			TileFormat::JXL => "image/jxl",
 			TileFormat::SVG => "image/svg+xml",
 			TileFormat::PBF => "application/x-protobuf",
 			TileFormat::GEOJSON => "application/geo+json",
@@ -146,6 +155,7 @@ impl TileFormat {
 			TileFormat::GEOJSON => ".geojson",
 			TileFormat::JPG => ".jpg",
 			TileFormat::JSON => ".json",
+			TileFormat::JXL => ".jxl",
 			TileFormat::PBF => ".pbf",
 			TileFormat::PNG => ".png",
 			TileFormat::SVG => ".svg",
@@ -188,6 +198,7 @@ impl TileFormat {
 				".geojson" => TileFormat::GEOJSON,
 				".jpg" | ".jpeg" => TileFormat::JPG,
 				".json" => TileFormat::JSON,
+				".jxl" => TileFormat::JXL,
 				".pbf" => TileFormat::PBF,
 				".png" => TileFormat::PNG,
 				".svg" => TileFormat::SVG,
@@ -232,6 +243,7 @@ impl TileFormat {
 			"geojson" => TileFormat::GEOJSON,
 			"jpeg" | "jpg" => TileFormat::JPG,
 			"json" => TileFormat::JSON,
+			"jxl" => TileFormat::JXL,
 			"pbf" => TileFormat::PBF,
 			"png" => TileFormat::PNG,
 			"svg" => TileFormat::SVG,
@@ -261,6 +273,7 @@ mod tests {
             (TileFormat::GEOJSON, ".geojson"),
             (TileFormat::JPG, ".jpg"),
             (TileFormat::JSON, ".json"),
+            (TileFormat::JXL, ".jxl"),
             (TileFormat::PBF, ".pbf"),
             (TileFormat::PNG, ".png"),
             (TileFormat::SVG, ".svg"),
@@ -413,6 +426,10 @@ mod tests {
 				input: ".json",
 				expected: Some(TileFormat::JSON),
 			},
+			Case {
+				input: "JXL",
+				expected: Some(TileFormat::JXL),
+			},
 			Case {
 				input: " pbf ",
 				expected: Some(TileFormat::PBF),
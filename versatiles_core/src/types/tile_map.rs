@@ -0,0 +1,213 @@
+//! A fast map from tile coordinates to values.
+//!
+//! Tile coordinates are packed into a single `u64` (see [`TileCoord3::get_sort_index`]) and hashed
+//! with FxHash, which is a lot faster than hashing the three fields of a [`TileCoord3`] with SipHash.
+//! Use it for large lookup tables, e.g. the tile index of a container or deduplication tables.
+
+use super::TileCoord3;
+use rustc_hash::FxHashMap;
+use std::{collections::hash_map, fmt};
+
+/// A hash map with tile coordinates as keys.
+///
+/// # Examples
+/// ```
+/// use versatiles_core::types::{TileCoord3, TileMap};
+///
+/// let mut map = TileMap::new();
+/// map.insert(&TileCoord3::new(1, 2, 3).unwrap(), "tile");
+/// assert_eq!(map.get(&TileCoord3::new(1, 2, 3).unwrap()), Some(&"tile"));
+/// assert_eq!(map.get(&TileCoord3::new(2, 1, 3).unwrap()), None);
+/// ```
+#[derive(Clone, PartialEq)]
+pub struct TileMap<T> {
+	map: FxHashMap<u64, T>,
+}
+
+impl<T> TileMap<T> {
+	/// Creates an empty map.
+	pub fn new() -> TileMap<T> {
+		TileMap {
+			map: FxHashMap::default(),
+		}
+	}
+
+	/// Creates an empty map with space for at least `capacity` tiles.
+	pub fn with_capacity(capacity: usize) -> TileMap<T> {
+		TileMap {
+			map: FxHashMap::with_capacity_and_hasher(capacity, Default::default()),
+		}
+	}
+
+	/// Returns the number of tiles.
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the map contains no tiles.
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Inserts a value for a tile, returning the previous value, if any.
+	///
+	/// The coordinate must be valid (`x` and `y` < 2^`z`), otherwise it may collide with another tile.
+	pub fn insert(&mut self, coord: &TileCoord3, value: T) -> Option<T> {
+		self.map.insert(coord.get_sort_index(), value)
+	}
+
+	/// Returns the value of a tile.
+	pub fn get(&self, coord: &TileCoord3) -> Option<&T> {
+		self.map.get(&coord.get_sort_index())
+	}
+
+	/// Returns a mutable reference to the value of a tile.
+	pub fn get_mut(&mut self, coord: &TileCoord3) -> Option<&mut T> {
+		self.map.get_mut(&coord.get_sort_index())
+	}
+
+	/// Returns the entry of a tile for in-place manipulation.
+	pub fn entry(&mut self, coord: &TileCoord3) -> hash_map::Entry<'_, u64, T> {
+		self.map.entry(coord.get_sort_index())
+	}
+
+	/// Returns `true` if the map contains a value for the tile.
+	pub fn contains(&self, coord: &TileCoord3) -> bool {
+		self.map.contains_key(&coord.get_sort_index())
+	}
+
+	/// Removes a tile, returning its value, if any.
+	pub fn remove(&mut self, coord: &TileCoord3) -> Option<T> {
+		self.map.remove(&coord.get_sort_index())
+	}
+
+	/// Keeps only the tiles for which `f` returns `true`.
+	pub fn retain(&mut self, mut f: impl FnMut(&TileCoord3, &mut T) -> bool) {
+		self
+			.map
+			.retain(|index, value| f(&TileCoord3::from_sort_index(*index).unwrap(), value))
+	}
+
+	/// Removes all tiles.
+	pub fn clear(&mut self) {
+		self.map.clear()
+	}
+
+	/// Iterates over all tiles and their values in arbitrary order.
+	pub fn iter(&self) -> impl Iterator<Item = (TileCoord3, &T)> {
+		self
+			.map
+			.iter()
+			.map(|(index, value)| (TileCoord3::from_sort_index(*index).unwrap(), value))
+	}
+
+	/// Iterates over all tiles in arbitrary order.
+	pub fn coords(&self) -> impl Iterator<Item = TileCoord3> + '_ {
+		self
+			.map
+			.keys()
+			.map(|index| TileCoord3::from_sort_index(*index).unwrap())
+	}
+
+	/// Iterates over all values in arbitrary order.
+	pub fn values(&self) -> impl Iterator<Item = &T> {
+		self.map.values()
+	}
+
+	/// Returns all tiles and their values, sorted by zoom level, row and column.
+	pub fn into_sorted_vec(self) -> Vec<(TileCoord3, T)> {
+		let mut entries: Vec<(u64, T)> = self.map.into_iter().collect();
+		entries.sort_unstable_by_key(|(index, _)| *index);
+		entries
+			.into_iter()
+			.map(|(index, value)| (TileCoord3::from_sort_index(index).unwrap(), value))
+			.collect()
+	}
+}
+
+impl<T> Default for TileMap<T> {
+	fn default() -> Self {
+		TileMap::new()
+	}
+}
+
+impl<T: Eq> Eq for TileMap<T> {}
+
+impl<T> FromIterator<(TileCoord3, T)> for TileMap<T> {
+	fn from_iter<I: IntoIterator<Item = (TileCoord3, T)>>(iter: I) -> Self {
+		let mut map = TileMap::new();
+		map.extend(iter);
+		map
+	}
+}
+
+impl<T> Extend<(TileCoord3, T)> for TileMap<T> {
+	fn extend<I: IntoIterator<Item = (TileCoord3, T)>>(&mut self, iter: I) {
+		for (coord, value) in iter {
+			self.insert(&coord, value);
+		}
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for TileMap<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn coord(x: u32, y: u32, z: u8) -> TileCoord3 {
+		TileCoord3::new(x, y, z).unwrap()
+	}
+
+	#[test]
+	fn insert_get_remove() {
+		let mut map = TileMap::new();
+		assert!(map.is_empty());
+		assert_eq!(map.insert(&coord(1, 2, 3), 1), None);
+		assert_eq!(map.insert(&coord(2, 1, 3), 2), None);
+		assert_eq!(map.insert(&coord(1, 2, 3), 3), Some(1));
+		assert_eq!(map.len(), 2);
+
+		assert_eq!(map.get(&coord(1, 2, 3)), Some(&3));
+		assert_eq!(map.get(&coord(1, 2, 4)), None);
+		*map.get_mut(&coord(2, 1, 3)).unwrap() += 10;
+		*map.entry(&coord(0, 0, 0)).or_insert(0) += 5;
+		assert!(map.contains(&coord(0, 0, 0)));
+
+		assert_eq!(map.remove(&coord(2, 1, 3)), Some(12));
+		assert_eq!(map.remove(&coord(2, 1, 3)), None);
+		map.insert(&coord(0, 0, 1), 7);
+		map.retain(|c, v| c.z == 0 || *v > 10);
+		assert_eq!(map.len(), 1);
+		map.clear();
+		assert!(map.is_empty());
+
+		map.insert(&coord(1, 2, 3), 3);
+		assert_eq!(format!("{map:?}"), "{TileCoord3(1, 2, 3): 3}");
+	}
+
+	#[test]
+	fn iterate() {
+		let coords = [coord(3, 0, 2), coord(0, 0, 0), coord(5, 7, 31), coord(1, 0, 1)];
+		let map: TileMap<usize> = coords.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+
+		let mut iterated: Vec<(TileCoord3, usize)> = map.iter().map(|(c, v)| (c, *v)).collect();
+		iterated.sort_by_key(|(c, _)| c.get_sort_index());
+		assert_eq!(iterated, map.clone().into_sorted_vec());
+		assert_eq!(
+			iterated,
+			vec![
+				(coord(0, 0, 0), 1),
+				(coord(1, 0, 1), 3),
+				(coord(3, 0, 2), 0),
+				(coord(5, 7, 31), 2)
+			]
+		);
+		assert_eq!(map.coords().count(), 4);
+		assert_eq!(map.values().sum::<usize>(), 6);
+	}
+}
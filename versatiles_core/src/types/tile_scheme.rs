@@ -0,0 +1,94 @@
+//! This module defines the `TileScheme` enum, the order of the tile rows in paths and URLs.
+//!
+//! All readers and writers use XYZ coordinates internally. The scheme is only applied where tiles
+//! are addressed from the outside, e.g. in the paths of a tar file or in server URLs, so that the
+//! y coordinate is flipped exactly once.
+//!
+//! # Examples
+//!
+//! ```
+//! use versatiles_core::types::{TileCoord3, TileScheme};
+//!
+//! let scheme = TileScheme::parse_str("tms").unwrap();
+//! assert_eq!(scheme.as_str(), "tms");
+//!
+//! let mut coord = TileCoord3::new(1, 2, 3).unwrap();
+//! scheme.apply(&mut coord);
+//! assert_eq!(coord, TileCoord3::new(1, 5, 3).unwrap());
+//! ```
+
+use super::TileCoord3;
+use crate::utils::TransformCoord;
+use anyhow::{bail, Result};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::fmt::Display;
+
+/// Order of the tile rows
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileScheme {
+	/// rows are counted from the north, like in most web maps
+	#[default]
+	Xyz,
+	/// rows are counted from the south, like in TMS and MBTiles
+	Tms,
+}
+
+impl TileScheme {
+	pub fn as_str(&self) -> &str {
+		match self {
+			TileScheme::Xyz => "xyz",
+			TileScheme::Tms => "tms",
+		}
+	}
+
+	/// Parses the value of the TileJSON key "scheme".
+	pub fn parse_str(value: &str) -> Result<TileScheme> {
+		Ok(match value.trim().to_lowercase().as_str() {
+			"xyz" => TileScheme::Xyz,
+			"tms" => TileScheme::Tms,
+			_ => bail!("unknown tile scheme '{value}', expected 'xyz' or 'tms'"),
+		})
+	}
+
+	/// Converts between XYZ and this scheme. The conversion is its own inverse.
+	pub fn apply(&self, coord: &mut TileCoord3) {
+		if *self == TileScheme::Tms {
+			coord.flip_y();
+		}
+	}
+}
+
+impl Display for TileScheme {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse() {
+		assert_eq!(TileScheme::parse_str("xyz").unwrap(), TileScheme::Xyz);
+		assert_eq!(TileScheme::parse_str(" TMS").unwrap(), TileScheme::Tms);
+		assert!(TileScheme::parse_str("zxy").is_err());
+		assert_eq!(TileScheme::default().to_string(), "xyz");
+	}
+
+	#[test]
+	fn apply_twice() -> Result<()> {
+		let coord = TileCoord3::new(3, 1, 4)?;
+		let mut flipped = coord;
+		TileScheme::Tms.apply(&mut flipped);
+		assert_eq!(flipped, TileCoord3::new(3, 14, 4)?);
+		TileScheme::Tms.apply(&mut flipped);
+		assert_eq!(flipped, coord);
+
+		TileScheme::Xyz.apply(&mut flipped);
+		assert_eq!(flipped, coord);
+		Ok(())
+	}
+}
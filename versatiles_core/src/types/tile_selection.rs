@@ -0,0 +1,136 @@
+use super::{GeoBBox, TileBBoxPyramid, TileCoord3};
+
+/// A selection of tiles that cannot be described by a single bounding box per zoom level: the union of
+/// several bounding box pyramids, minus excluded areas. E.g. "Europe without Russia".
+///
+/// Tiles are excluded only if they lie completely inside an excluded area, so no tile that also covers
+/// a selected area gets lost.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileSelection {
+	/// Tiles inside any of these pyramids are selected. If empty, all tiles are selected.
+	pub include: Vec<TileBBoxPyramid>,
+	/// Tiles completely inside any of these areas are removed from the selection. Areas may cross the antimeridian.
+	pub exclude: Vec<GeoBBox>,
+}
+
+impl TileSelection {
+	/// Returns the smallest pyramid containing all selected tiles, or `None` if all tiles are included.
+	pub fn get_bbox_pyramid(&self) -> Option<TileBBoxPyramid> {
+		if self.include.is_empty() {
+			return None;
+		}
+		let mut pyramid = TileBBoxPyramid::new_empty();
+		for include in self.include.iter() {
+			pyramid.include_bbox_pyramid(include);
+		}
+		Some(pyramid)
+	}
+
+	/// Returns the geographic extent of all included pyramids, or `None` if all tiles are included.
+	///
+	/// Pyramids on both sides of the antimeridian, e.g. from a split `--bbox`, result in a bbox crossing it.
+	pub fn get_geo_bbox(&self) -> Option<GeoBBox> {
+		self
+			.include
+			.iter()
+			.filter_map(|pyramid| pyramid.get_geo_bbox())
+			.reduce(|a, b| a.extended(&b))
+	}
+
+	/// Returns `true` if the selection is not just a single bounding box pyramid, so every tile has to be checked.
+	pub fn needs_filter(&self) -> bool {
+		self.include.len() > 1 || !self.exclude.is_empty()
+	}
+
+	/// Returns `true` if the tile is selected.
+	pub fn contains_coord(&self, coord: &TileCoord3) -> bool {
+		if !self.include.is_empty() && !self.include.iter().any(|p| p.contains_coord(coord)) {
+			return false;
+		}
+		if self.exclude.is_empty() {
+			return true;
+		}
+		let tile = coord.as_geo_bbox();
+		!self
+			.exclude
+			.iter()
+			.flat_map(|e| e.split_antimeridian())
+			.any(|e| e.0 <= tile.0 && e.1 <= tile.1 && tile.2 <= e.2 && tile.3 <= e.3)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn coord(x: u32, y: u32, z: u8) -> TileCoord3 {
+		TileCoord3::new(x, y, z).unwrap()
+	}
+
+	#[test]
+	fn test_union() {
+		let selection = TileSelection {
+			include: vec![
+				TileBBoxPyramid::from_geo_bbox(2, 2, &GeoBBox(-180.0, 0.0, -90.0, 85.0)),
+				TileBBoxPyramid::from_geo_bbox(2, 2, &GeoBBox(90.0, -85.0, 180.0, 0.0)),
+			],
+			exclude: vec![],
+		};
+		assert!(selection.needs_filter());
+		assert_eq!(selection.get_bbox_pyramid().unwrap().to_string(), "[2: [0,0,3,3] (16)]");
+		assert!(selection.contains_coord(&coord(0, 0, 2)));
+		assert!(selection.contains_coord(&coord(3, 3, 2)));
+		assert!(!selection.contains_coord(&coord(3, 0, 2)));
+		assert!(!selection.contains_coord(&coord(0, 0, 3)));
+	}
+
+	#[test]
+	fn test_exclude() {
+		let selection = TileSelection {
+			include: vec![],
+			exclude: vec![GeoBBox(0.0, -85.0, 180.0, 85.0)],
+		};
+		assert!(selection.needs_filter());
+		assert_eq!(selection.get_bbox_pyramid(), None);
+		assert!(selection.contains_coord(&coord(1, 1, 2)));
+		assert!(!selection.contains_coord(&coord(2, 1, 2)));
+		// only partially covered by the excluded area
+		assert!(selection.contains_coord(&coord(0, 0, 0)));
+	}
+
+	#[test]
+	fn test_antimeridian() {
+		let fiji = GeoBBox(177.0, -19.0, -178.0, -16.0);
+		let selection = TileSelection {
+			include: fiji
+				.split_antimeridian()
+				.iter()
+				.map(|part| TileBBoxPyramid::from_geo_bbox(3, 3, part))
+				.collect(),
+			exclude: vec![],
+		};
+		assert!(selection.contains_coord(&coord(0, 4, 3)));
+		assert!(selection.contains_coord(&coord(7, 4, 3)));
+		assert!(!selection.contains_coord(&coord(3, 4, 3)));
+		assert_eq!(
+			selection.get_geo_bbox().unwrap().as_array(),
+			[135.0, -40.97989806962013, -135.0, 0.0]
+		);
+
+		let selection = TileSelection {
+			include: vec![],
+			exclude: vec![GeoBBox(90.0, -85.0, -90.0, 85.0)],
+		};
+		assert_eq!(selection.get_geo_bbox(), None);
+		assert!(!selection.contains_coord(&coord(0, 1, 2)));
+		assert!(!selection.contains_coord(&coord(3, 1, 2)));
+		assert!(selection.contains_coord(&coord(1, 1, 2)));
+	}
+
+	#[test]
+	fn test_default() {
+		let selection = TileSelection::default();
+		assert!(!selection.needs_filter());
+		assert!(selection.contains_coord(&coord(5, 6, 7)));
+	}
+}
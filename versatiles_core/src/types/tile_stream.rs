@@ -8,13 +8,27 @@
 //! - **Buffering**: Collect or process data in configurable batches.
 //! - **Synchronous and Asynchronous Callbacks**: Choose between sync and async processing steps.
 
-use crate::types::{Blob, TileCoord3};
+use crate::{
+	metrics::counters,
+	types::{Blob, TileCoord3},
+	utils::get_write_queue_memory,
+};
+use anyhow::Result;
 use futures::{
+	channel::mpsc,
 	future::ready,
+	join,
 	stream::{self, BoxStream},
-	Future, Stream, StreamExt,
+	Future, SinkExt, Stream, StreamExt,
+};
+use std::{
+	pin::Pin,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
 };
-use std::{pin::Pin, sync::Arc};
+use tokio::sync::Notify;
 
 /// A wrapper that encapsulates a stream of `(TileCoord3, Blob)` tuples.
 ///
@@ -338,6 +352,113 @@ impl<'a> TileStream<'a> {
 		}
 	}
 
+	/// Calls `callback` for each item, while the stream is read ahead into a queue of at most `queue_size` items.
+	///
+	/// Use it for writers: while `callback` writes a tile, e.g. to a slow network file system, the parallel
+	/// transformations of the stream can go on. The queue is bounded, so if `callback` is slower than the stream,
+	/// reading waits and memory stays flat. Besides the number of items, the queue holds at most
+	/// [`get_write_queue_memory`] bytes of tiles, but always at least one tile.
+	/// The queue depth and the waits are counted in [`crate::metrics`].
+	///
+	/// Consumes the stream. Stops at the first error of `callback` and returns it.
+	///
+	/// # Examples
+	/// ```
+	/// # use versatiles_core::types::{TileCoord3, Blob, TileStream};
+	/// # async fn test() -> anyhow::Result<()> {
+	/// let stream = TileStream::from_vec(vec![
+	///     (TileCoord3::new(0,0,0).unwrap(), Blob::from("data0")),
+	///     (TileCoord3::new(1,1,1).unwrap(), Blob::from("data1")),
+	/// ]);
+	///
+	/// let mut sizes = Vec::new();
+	/// stream.for_each_queued(16, |(_coord, blob)| {
+	///     sizes.push(blob.len());
+	///     Ok(())
+	/// }).await?;
+	/// assert_eq!(sizes, vec![5, 5]);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn for_each_queued<F>(self, queue_size: usize, callback: F) -> Result<()>
+	where
+		F: FnMut((TileCoord3, Blob)) -> Result<()>,
+	{
+		self
+			.for_each_queued_bounded(queue_size, get_write_queue_memory() as u64, callback)
+			.await
+	}
+
+	/// Like [`Self::for_each_queued`], with a queue of at most `max_bytes` bytes of tiles.
+	async fn for_each_queued_bounded<F>(self, queue_size: usize, max_bytes: u64, mut callback: F) -> Result<()>
+	where
+		F: FnMut((TileCoord3, Blob)) -> Result<()>,
+	{
+		let counters = counters();
+		// the capacity of the channel is its buffer plus one slot for the sender
+		let (mut sender, mut receiver) = mpsc::channel(queue_size.max(1) - 1);
+		let mut stream = self.stream;
+		let queued_bytes = &AtomicU64::new(0);
+		// the writer signals that it has taken tiles out of the queue
+		let dequeued = &Notify::new();
+
+		let read = async move {
+			while let Some(item) = stream.next().await {
+				let size = item.1.len();
+				if queued_bytes.load(Ordering::Relaxed) + size > max_bytes {
+					counters.write_queue_waits.inc();
+					loop {
+						// created before the check, so that a signal in between is not lost
+						let notified = dequeued.notified();
+						let queued = queued_bytes.load(Ordering::Relaxed);
+						if queued == 0 || queued + size <= max_bytes {
+							break;
+						}
+						notified.await;
+					}
+				}
+				queued_bytes.fetch_add(size, Ordering::Relaxed);
+				// count the item before sending it, because the writer may receive it right away
+				counters.write_queue_depth.inc();
+				let item = match sender.try_send(item) {
+					Ok(()) => continue,
+					Err(error) if error.is_full() => error.into_inner(),
+					Err(_) => {
+						// the writer has stopped
+						counters.write_queue_depth.dec();
+						return;
+					}
+				};
+				counters.write_queue_waits.inc();
+				if sender.send(item).await.is_err() {
+					counters.write_queue_depth.dec();
+					return;
+				}
+			}
+		};
+
+		let write = async move {
+			while let Some(item) = receiver.next().await {
+				counters.write_queue_depth.dec();
+				queued_bytes.fetch_sub(item.1.len(), Ordering::Relaxed);
+				dequeued.notify_one();
+				if let Err(error) = callback(item) {
+					// empty the queue, so that the depth stays correct and a waiting reader stops
+					receiver.close();
+					while let Some(item) = receiver.next().await {
+						counters.write_queue_depth.dec();
+						queued_bytes.fetch_sub(item.1.len(), Ordering::Relaxed);
+					}
+					dequeued.notify_one();
+					return Err(error);
+				}
+			}
+			Ok(())
+		};
+
+		join!(read, write).1
+	}
+
 	// -------------------------------------------------------------------------
 	// Parallel Transformations
 	// -------------------------------------------------------------------------
@@ -459,6 +580,29 @@ impl<'a> TileStream<'a> {
 		TileStream { stream: s }
 	}
 
+	/// Keeps only the items whose coordinate satisfies `callback`.
+	///
+	/// # Examples
+	/// ```
+	/// # use versatiles_core::types::{TileCoord3, Blob, TileStream};
+	/// # async fn test() {
+	/// let stream = TileStream::from_vec(vec![
+	///     (TileCoord3::new(0,0,0).unwrap(), Blob::from("data0")),
+	///     (TileCoord3::new(1,1,1).unwrap(), Blob::from("data1")),
+	/// ]);
+	///
+	/// let items = stream.filter_coord(|coord| coord.z > 0).collect().await;
+	/// assert_eq!(items.len(), 1);
+	/// # }
+	/// ```
+	pub fn filter_coord<F>(self, mut callback: F) -> Self
+	where
+		F: FnMut(&TileCoord3) -> bool + Send + 'a,
+	{
+		let s = self.stream.filter(move |(coord, _)| ready(callback(coord))).boxed();
+		TileStream { stream: s }
+	}
+
 	// -------------------------------------------------------------------------
 	// Utility
 	// -------------------------------------------------------------------------
@@ -545,6 +689,18 @@ mod tests {
 		assert_eq!(blob.as_str(), "data");
 	}
 
+	#[tokio::test]
+	async fn should_filter_coord() {
+		let original = TileStream::from_vec(vec![
+			(TileCoord3::new(0, 0, 1).unwrap(), Blob::from("tile0")),
+			(TileCoord3::new(1, 0, 1).unwrap(), Blob::from("tile1")),
+		]);
+
+		let items = original.filter_coord(|coord| coord.x > 0).collect().await;
+		assert_eq!(items.len(), 1);
+		assert_eq!(items[0].1.as_str(), "tile1");
+	}
+
 	#[tokio::test]
 	async fn should_count_items_with_drain_and_count() {
 		let tile_data = vec![
@@ -580,6 +736,94 @@ mod tests {
 		assert_eq!(results, vec![2, 1]);
 	}
 
+	#[tokio::test]
+	async fn should_write_queued_items() {
+		let tile_data: Vec<(TileCoord3, Blob)> = (0..100)
+			.map(|x| (TileCoord3::new(x, 0, 7).unwrap(), Blob::from(format!("tile{x}"))))
+			.collect();
+
+		let mut written = Vec::new();
+		TileStream::from_vec(tile_data.clone())
+			.for_each_queued(4, |item| {
+				written.push(item);
+				Ok(())
+			})
+			.await
+			.unwrap();
+		assert_eq!(written, tile_data);
+	}
+
+	#[tokio::test]
+	async fn should_limit_queued_bytes() {
+		let read = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+		let read_clone = read.clone();
+		let stream = stream::iter((0..100).map(|x| (TileCoord3::new(x, 0, 7).unwrap(), Blob::from("0123456789"))))
+			.inspect(move |_| {
+				read_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+			})
+			.boxed();
+
+		let mut max_ahead = 0;
+		let mut written = 0;
+		TileStream::from_stream(stream)
+			.for_each_queued_bounded(100, 25, |_| {
+				written += 1;
+				max_ahead = max_ahead.max(read.load(std::sync::atomic::Ordering::Relaxed) - written);
+				Ok(())
+			})
+			.await
+			.unwrap();
+		assert_eq!(written, 100);
+		// two tiles of 10 bytes in the queue, and one waiting to be queued
+		assert!(max_ahead <= 3, "{max_ahead}");
+
+		// tiles larger than the queue are written one by one
+		let mut written = 0;
+		TileStream::from_vec(vec![(TileCoord3::new(0, 0, 0).unwrap(), Blob::from("0123456789")); 3])
+			.for_each_queued_bounded(100, 5, |_| {
+				written += 1;
+				Ok(())
+			})
+			.await
+			.unwrap();
+		assert_eq!(written, 3);
+	}
+
+	#[tokio::test]
+	async fn should_stop_writing_queued_items_at_error() {
+		let tile_data: Vec<(TileCoord3, Blob)> = (0..100)
+			.map(|x| (TileCoord3::new(x, 0, 7).unwrap(), Blob::from("tile")))
+			.collect();
+
+		let mut count = 0;
+		let error = TileStream::from_vec(tile_data)
+			.for_each_queued(4, |(coord, _)| {
+				count += 1;
+				anyhow::ensure!(coord.x < 10, "disk full");
+				Ok(())
+			})
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "disk full");
+		assert_eq!(count, 11);
+	}
+
+	#[tokio::test]
+	async fn should_stop_writing_full_queue_at_error() {
+		let tile_data: Vec<(TileCoord3, Blob)> = (0..100)
+			.map(|x| (TileCoord3::new(x, 0, 7).unwrap(), Blob::from("0123456789")))
+			.collect();
+
+		let error = TileStream::from_vec(tile_data)
+			.for_each_queued_bounded(100, 25, |(coord, _)| {
+				anyhow::ensure!(coord.x < 10, "disk full");
+				Ok(())
+			})
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "disk full");
+	}
+
 	#[tokio::test]
 	async fn should_do_parallel_blob_mapping() {
 		let tile_data = vec![
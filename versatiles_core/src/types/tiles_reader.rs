@@ -1,6 +1,6 @@
 #[cfg(feature = "cli")]
 use super::ProbeDepth;
-use super::{Blob, TileBBox, TileCompression, TileCoord3, TileStream, TilesReaderParameters};
+use super::{Blob, ReadHints, TileBBox, TileCompression, TileCoord3, TileStream, TilesReaderParameters};
 use crate::tilejson::TileJSON;
 #[cfg(feature = "cli")]
 use crate::utils::PrettyPrint;
@@ -10,6 +10,11 @@ use futures::lock::Mutex;
 use std::{fmt::Debug, sync::Arc};
 
 /// Trait defining the behavior of a tile reader.
+///
+/// Readers are `Send + Sync`, so they can be shared between tasks. They don't have to be `'static`:
+/// streams returned by [`get_bbox_tile_stream`](TilesReaderTrait::get_bbox_tile_stream) borrow the reader,
+/// and a mutable reference to a reader is a reader itself, so a borrowed reader can be wrapped, e.g. by a
+/// converter, with `(&mut reader).boxed()` instead of moving it.
 #[async_trait]
 pub trait TilesReaderTrait: Debug + Send + Sync + Unpin {
 	/// Get the name of the reader source, e.g., the filename.
@@ -27,6 +32,16 @@ pub trait TilesReaderTrait: Debug + Send + Sync + Unpin {
 	/// Get the metadata, always uncompressed.
 	fn get_tilejson(&self) -> &TileJSON;
 
+	/// Tell the reader which tiles will be requested, so that it can drop everything else from its index.
+	/// Readers that don't keep an index ignore the hints.
+	fn set_read_hints(&mut self, _hints: &ReadHints) {}
+
+	/// Counts the tiles inside `bbox` and sums up their sizes in bytes, using only the index of the container,
+	/// without reading any tiles. Returns `(tile_count, tile_bytes)`, or `None` if the container has no such index.
+	async fn count_bbox_tiles(&self, _bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		Ok(None)
+	}
+
 	/// Get tile data for the given coordinate, always compressed and formatted.
 	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>>;
 
@@ -51,9 +66,13 @@ pub trait TilesReaderTrait: Debug + Send + Sync + Unpin {
 	/// probe container
 	#[cfg(feature = "cli")]
 	async fn probe(&mut self, level: ProbeDepth) -> Result<()> {
-		use ProbeDepth::*;
+		self.probe_with_print(level, &mut PrettyPrint::new()).await
+	}
 
-		let mut print = PrettyPrint::new();
+	/// probe container and write the report to `print`
+	#[cfg(feature = "cli")]
+	async fn probe_with_print(&mut self, level: ProbeDepth, print: &mut PrettyPrint) -> Result<()> {
+		use ProbeDepth::*;
 
 		let cat = print.get_category("meta_data").await;
 		cat.add_key_value("name", self.get_source_name()).await;
@@ -92,6 +111,9 @@ pub trait TilesReaderTrait: Debug + Send + Sync + Unpin {
 		print
 			.add_key_value("bbox", &format!("{:?}", parameters.bbox_pyramid.get_geo_bbox()))
 			.await;
+		print
+			.add_key_value("tiles", &parameters.bbox_pyramid.get_summary())
+			.await;
 		print
 			.add_key_value("tile compression", &parameters.tile_compression)
 			.await;
@@ -126,14 +148,74 @@ pub trait TilesReaderTrait: Debug + Send + Sync + Unpin {
 		Ok(())
 	}
 
-	fn boxed(self) -> Box<dyn TilesReaderTrait>
+	fn boxed<'a>(self) -> Box<dyn TilesReaderTrait + 'a>
 	where
-		Self: Sized + 'static,
+		Self: Sized + 'a,
 	{
 		Box::new(self)
 	}
 }
 
+/// Forwards all calls to the borrowed reader.
+#[async_trait]
+impl<T: TilesReaderTrait + ?Sized> TilesReaderTrait for &mut T {
+	fn get_source_name(&self) -> &str {
+		(**self).get_source_name()
+	}
+
+	fn get_container_name(&self) -> &str {
+		(**self).get_container_name()
+	}
+
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		(**self).get_parameters()
+	}
+
+	fn override_compression(&mut self, tile_compression: TileCompression) {
+		(**self).override_compression(tile_compression)
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		(**self).get_tilejson()
+	}
+
+	fn set_read_hints(&mut self, hints: &ReadHints) {
+		(**self).set_read_hints(hints)
+	}
+
+	async fn count_bbox_tiles(&self, bbox: &TileBBox) -> Result<Option<(u64, u64)>> {
+		(**self).count_bbox_tiles(bbox).await
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		(**self).get_tile_data(coord).await
+	}
+
+	async fn get_bbox_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		(**self).get_bbox_tile_stream(bbox).await
+	}
+
+	#[cfg(feature = "cli")]
+	async fn probe_parameters(&mut self, print: &mut PrettyPrint) -> Result<()> {
+		(**self).probe_parameters(print).await
+	}
+
+	#[cfg(feature = "cli")]
+	async fn probe_container(&mut self, print: &PrettyPrint) -> Result<()> {
+		(**self).probe_container(print).await
+	}
+
+	#[cfg(feature = "cli")]
+	async fn probe_tiles(&mut self, print: &PrettyPrint) -> Result<()> {
+		(**self).probe_tiles(print).await
+	}
+
+	#[cfg(feature = "cli")]
+	async fn probe_tile_contents(&mut self, print: &PrettyPrint) -> Result<()> {
+		(**self).probe_tile_contents(print).await
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -237,6 +319,20 @@ mod tests {
 		Ok(())
 	}
 
+	#[tokio::test]
+	async fn test_borrowed_reader() -> Result<()> {
+		let mut reader = TestReader::new_dummy();
+		{
+			let mut borrowed: Box<dyn TilesReaderTrait + '_> = (&mut reader).boxed();
+			assert_eq!(borrowed.get_source_name(), "dummy");
+			borrowed.override_compression(TileCompression::Brotli);
+			let stream = borrowed.get_bbox_tile_stream(TileBBox::new(1, 0, 0, 1, 1)?).await;
+			assert_eq!(stream.drain_and_count().await, 4);
+		}
+		assert_eq!(reader.get_parameters().tile_compression, TileCompression::Brotli);
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn test_get_bbox_tile_stream() -> Result<()> {
 		let reader = TestReader::new_dummy();
@@ -1,7 +1,10 @@
 use super::{TileBBoxPyramid, TileCompression, TileFormat};
+use crate::json::{JsonObject, JsonValue};
+use anyhow::{Context, Result};
 
 /// Parameters for configuring a `TilesReader`.
 #[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub struct TilesReaderParameters {
 	pub bbox_pyramid: TileBBoxPyramid,
 	pub tile_compression: TileCompression,
@@ -22,6 +25,31 @@ impl TilesReaderParameters {
 		}
 	}
 
+	/// Creates `TilesReaderParameters` from a JSON object, as written by [`TilesReaderParameters::as_json_value`].
+	pub fn from_json_value(json: &JsonValue) -> Result<TilesReaderParameters> {
+		let object = json.as_object()?;
+		let get = |key: &str| {
+			object
+				.get(key)
+				.with_context(|| format!("parameters are missing \"{key}\""))
+		};
+		Ok(TilesReaderParameters {
+			tile_format: TileFormat::parse_str(get("tile_format")?.as_str()?)?,
+			tile_compression: TileCompression::parse_str(get("tile_compression")?.as_str()?)?,
+			bbox_pyramid: TileBBoxPyramid::from_json_value(get("bbox_pyramid")?)?,
+		})
+	}
+
+	/// Returns the parameters as a JSON object, so they can be stored and passed to other tools, e.g.
+	/// `{"bbox_pyramid":[…],"tile_compression":"gzip","tile_format":"pbf"}`.
+	pub fn as_json_value(&self) -> JsonValue {
+		let mut object = JsonObject::default();
+		object.set("bbox_pyramid", self.bbox_pyramid.as_json_value());
+		object.set("tile_compression", self.tile_compression.as_str());
+		object.set("tile_format", self.tile_format.as_str());
+		JsonValue::Object(object)
+	}
+
 	#[cfg(test)]
 	#[allow(dead_code)]
 	pub fn new_full(tile_format: TileFormat, tile_compression: TileCompression) -> TilesReaderParameters {
@@ -61,4 +89,25 @@ mod tests {
 		assert_eq!(params.tile_compression, tile_compression);
 		assert_eq!(params.bbox_pyramid, TileBBoxPyramid::new_full(31));
 	}
+
+	#[test]
+	fn test_json_roundtrip() -> Result<()> {
+		let params = TilesReaderParameters::new(TileFormat::PBF, TileCompression::Brotli, TileBBoxPyramid::new_full(1));
+		let json = params.as_json_value().stringify();
+		assert_eq!(
+			json,
+			"{\"bbox_pyramid\":[{\"level\":0,\"x_max\":0,\"x_min\":0,\"y_max\":0,\"y_min\":0},{\"level\":1,\"x_max\":1,\"x_min\":0,\"y_max\":1,\"y_min\":0}],\"tile_compression\":\"brotli\",\"tile_format\":\"pbf\"}"
+		);
+		assert_eq!(
+			TilesReaderParameters::from_json_value(&JsonValue::parse_str(&json)?)?,
+			params
+		);
+
+		let json = JsonValue::parse_str("{\"tile_format\":\"png\",\"tile_compression\":\"none\"}")?;
+		assert_eq!(
+			TilesReaderParameters::from_json_value(&json).unwrap_err().to_string(),
+			"parameters are missing \"bbox_pyramid\""
+		);
+		Ok(())
+	}
 }
@@ -1,11 +1,11 @@
 //! # Compression Module
 //!
 //! This module provides functionalities to compress and decompress data blobs
-//! using various compression algorithms such as Gzip and Brotli. It also allows
+//! using various compression algorithms such as Gzip, Brotli and Zstandard. It also allows
 //! optimizing compression based on target preferences and handling recompression.
 //!
 //! ## Features
-//! - Compress and decompress data using Gzip and Brotli.
+//! - Compress and decompress data using Gzip, Brotli and Zstandard.
 //! - Optimize compression based on target settings.
 //! - Recompress data from one compression format to another.
 //!
@@ -23,7 +23,7 @@
 #![allow(dead_code)]
 
 use crate::types::{Blob, TileCompression};
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use brotli::{enc::BrotliEncoderParams, BrotliCompress, BrotliDecompress};
 use enumset::EnumSet;
 use flate2::bufread::{GzDecoder, GzEncoder};
@@ -33,7 +33,7 @@ use std::{
 };
 
 /// Represents the target compression settings.
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct TargetCompression {
 	/// Set of allowed compression algorithms.
 	compressions: EnumSet<TileCompression>,
@@ -178,6 +178,25 @@ pub fn optimize_compression(
 	input_compression: &TileCompression,
 	target: &TargetCompression,
 ) -> Result<(Blob, TileCompression)> {
+	let output_compression = select_compression(input_compression, target)?;
+	if output_compression == *input_compression {
+		return Ok((blob, output_compression));
+	}
+	let blob = decompress(blob, input_compression).context("Failed to decompress blob")?;
+	let blob = compress(blob, &output_compression).context("Failed to compress blob")?;
+	Ok((blob, output_compression))
+}
+
+/// Selects the compression that [`optimize_compression`] converts a blob to.
+///
+/// This allows callers to run the decompression and compression steps on their own,
+/// e.g. to measure them separately.
+///
+/// # Errors
+///
+/// * If no compression algorithms are allowed in the target.
+/// * If 'Uncompressed' is not included in the allowed compressions.
+pub fn select_compression(input_compression: &TileCompression, target: &TargetCompression) -> Result<TileCompression> {
 	if target.compressions.is_empty() {
 		bail!("At least one compression algorithm must be allowed");
 	}
@@ -187,56 +206,62 @@ pub fn optimize_compression(
 	}
 
 	use CompressionGoal::*;
+	use TileCompression::*;
 
 	// If the target is not seeking the best compression and the current compression is allowed,
 	// retain the current compression.
 	if target.compression_goal != UseBestCompression && target.compressions.contains(*input_compression) {
-		return Ok((blob, *input_compression));
+		return Ok(*input_compression);
 	}
 
-	match input_compression {
-		TileCompression::Uncompressed => {
-			if target.compression_goal != IsIncompressible {
-				if target.compressions.contains(TileCompression::Brotli) {
-					return Ok((compress_brotli(&blob)?, TileCompression::Brotli));
-				}
-
-				if target.compressions.contains(TileCompression::Gzip) {
-					return Ok((compress_gzip(&blob)?, TileCompression::Gzip));
-				}
+	// Brotli compresses best, followed by Zstandard and Gzip.
+	let compressible = target.compression_goal != IsIncompressible;
+	Ok(match input_compression {
+		Uncompressed => {
+			if compressible && target.compressions.contains(Brotli) {
+				Brotli
+			} else if compressible && target.compressions.contains(Zstd) {
+				Zstd
+			} else if compressible && target.compressions.contains(Gzip) {
+				Gzip
+			} else {
+				Uncompressed
 			}
-
-			Ok((blob, TileCompression::Uncompressed))
 		}
-		TileCompression::Gzip => {
-			if target.compression_goal != IsIncompressible && target.compressions.contains(TileCompression::Brotli) {
-				let decompressed = decompress_gzip(&blob).context("Failed to decompress Gzip blob")?;
-				let compressed_brotli = compress_brotli(&decompressed).context("Failed to compress Brotli blob")?;
-				return Ok((compressed_brotli, TileCompression::Brotli));
+		Gzip => {
+			if compressible && target.compressions.contains(Brotli) {
+				Brotli
+			} else if compressible && target.compressions.contains(Zstd) {
+				Zstd
+			} else if target.compressions.contains(Gzip) {
+				Gzip
+			} else {
+				Uncompressed
 			}
-
-			if target.compressions.contains(TileCompression::Gzip) {
-				return Ok((blob, TileCompression::Gzip));
-			}
-
-			// Fallback to Uncompressed if Gzip is not allowed
-			let decompressed = decompress_gzip(&blob).context("Failed to decompress Gzip blob")?;
-			Ok((decompressed, TileCompression::Uncompressed))
 		}
-		TileCompression::Brotli => {
-			if target.compressions.contains(TileCompression::Brotli) {
-				return Ok((blob, TileCompression::Brotli));
+		Brotli => {
+			if target.compressions.contains(Brotli) {
+				Brotli
+			} else if compressible && target.compressions.contains(Zstd) {
+				Zstd
+			} else if compressible && target.compressions.contains(Gzip) {
+				Gzip
+			} else {
+				Uncompressed
 			}
-			let decompressed = decompress_brotli(&blob).context("Failed to decompress Brotli blob")?;
-
-			if target.compression_goal != IsIncompressible && target.compressions.contains(TileCompression::Gzip) {
-				let compressed_gzip = compress_gzip(&decompressed).context("Failed to compress Gzip blob")?;
-				return Ok((compressed_gzip, TileCompression::Gzip));
+		}
+		Zstd => {
+			if compressible && target.compressions.contains(Brotli) {
+				Brotli
+			} else if target.compressions.contains(Zstd) {
+				Zstd
+			} else if compressible && target.compressions.contains(Gzip) {
+				Gzip
+			} else {
+				Uncompressed
 			}
-
-			Ok((decompressed, TileCompression::Uncompressed))
 		}
-	}
+	})
 }
 
 /// Recompresses a data blob from one compression algorithm to another.
@@ -293,6 +318,7 @@ pub fn compress(blob: Blob, compression: &TileCompression) -> Result<Blob> {
 		TileCompression::Uncompressed => Ok(blob),
 		TileCompression::Gzip => compress_gzip(&blob),
 		TileCompression::Brotli => compress_brotli(&blob),
+		TileCompression::Zstd => compress_zstd(&blob),
 	}
 }
 
@@ -316,6 +342,7 @@ pub fn decompress(blob: Blob, compression: &TileCompression) -> Result<Blob> {
 		TileCompression::Uncompressed => Ok(blob),
 		TileCompression::Gzip => decompress_gzip(&blob),
 		TileCompression::Brotli => decompress_brotli(&blob),
+		TileCompression::Zstd => decompress_zstd(&blob),
 	}
 }
 
@@ -334,7 +361,30 @@ pub fn decompress(blob: Blob, compression: &TileCompression) -> Result<Blob> {
 ///
 /// * If the Gzip compression process fails.
 pub fn compress_gzip(blob: &Blob) -> Result<Blob> {
-	let mut encoder = GzEncoder::new(blob.as_slice(), flate2::Compression::best());
+	compress_gzip_level(blob, 9)
+}
+
+/// Compresses data using Gzip with a specific compression level.
+///
+/// # Arguments
+///
+/// * `blob` - The data blob to compress.
+/// * `level` - The compression level, from `0` (fastest) to `9` (smallest).
+///
+/// # Returns
+///
+/// * `Ok(Blob)` containing the Gzip-compressed data.
+/// * `Err(anyhow::Error)` if compression fails.
+///
+/// # Errors
+///
+/// * If the level is invalid or the Gzip compression process fails.
+pub fn compress_gzip_level(blob: &Blob, level: u32) -> Result<Blob> {
+	ensure!(
+		level <= 9,
+		"gzip compression level must be between 0 and 9, but is {level}"
+	);
+	let mut encoder = GzEncoder::new(blob.as_slice(), flate2::Compression::new(level));
 	let mut compressed_data = Vec::new();
 	encoder
 		.read_to_end(&mut compressed_data)
@@ -380,9 +430,32 @@ pub fn decompress_gzip(blob: &Blob) -> Result<Blob> {
 ///
 /// * If the Brotli compression process fails.
 pub fn compress_brotli(blob: &Blob) -> Result<Blob> {
+	compress_brotli_quality(blob, 10)
+}
+
+/// Compresses data using Brotli with a specific quality.
+///
+/// # Arguments
+///
+/// * `blob` - The data blob to compress.
+/// * `quality` - The quality, from `0` (fastest) to `11` (smallest).
+///
+/// # Returns
+///
+/// * `Ok(Blob)` containing the Brotli-compressed data.
+/// * `Err(anyhow::Error)` if compression fails.
+///
+/// # Errors
+///
+/// * If the quality is invalid or the Brotli compression process fails.
+pub fn compress_brotli_quality(blob: &Blob, quality: u32) -> Result<Blob> {
+	ensure!(
+		quality <= 11,
+		"brotli quality must be between 0 and 11, but is {quality}"
+	);
 	let params = BrotliEncoderParams {
-		quality: 10, // Highest quality
-		lgwin: 19,   // Window size
+		quality: quality as i32,
+		lgwin: 19, // Window size
 		size_hint: blob.len() as usize,
 		..Default::default()
 	};
@@ -442,6 +515,89 @@ pub fn decompress_brotli(blob: &Blob) -> Result<Blob> {
 	Ok(Blob::from(decompressed_data))
 }
 
+/// Compresses data using Zstandard.
+///
+/// # Arguments
+///
+/// * `blob` - The data blob to compress.
+///
+/// # Returns
+///
+/// * `Ok(Blob)` containing the Zstandard-compressed data.
+/// * `Err(anyhow::Error)` if compression fails.
+///
+/// # Errors
+///
+/// * If the Zstandard compression process fails.
+pub fn compress_zstd(blob: &Blob) -> Result<Blob> {
+	compress_zstd_level(blob, 19)
+}
+
+/// Compresses data using Zstandard with a specific compression level.
+///
+/// # Arguments
+///
+/// * `blob` - The data blob to compress.
+/// * `level` - The compression level, from `1` (fastest) to `22` (smallest).
+///
+/// # Returns
+///
+/// * `Ok(Blob)` containing the Zstandard-compressed data.
+/// * `Err(anyhow::Error)` if compression fails.
+///
+/// # Errors
+///
+/// * If the level is invalid or the Zstandard compression process fails.
+pub fn compress_zstd_level(blob: &Blob, level: u32) -> Result<Blob> {
+	ensure!(
+		(1..=22).contains(&level),
+		"zstd compression level must be between 1 and 22, but is {level}"
+	);
+	let compressed_data =
+		zstd_compress(blob.as_slice(), level as i32).context("Failed to compress data using Zstandard")?;
+	Ok(Blob::from(compressed_data))
+}
+
+/// Decompresses data that was compressed using Zstandard.
+///
+/// # Arguments
+///
+/// * `blob` - The Zstandard-compressed data blob.
+///
+/// # Returns
+///
+/// * `Ok(Blob)` containing the decompressed data.
+/// * `Err(anyhow::Error)` if decompression fails.
+///
+/// # Errors
+///
+/// * If the Zstandard decompression process fails.
+pub fn decompress_zstd(blob: &Blob) -> Result<Blob> {
+	let decompressed_data = zstd_decompress(blob.as_slice()).context("Failed to decompress data using Zstandard")?;
+	Ok(Blob::from(decompressed_data))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn zstd_compress(data: &[u8], level: i32) -> Result<Vec<u8>> {
+	Ok(zstd::bulk::compress(data, level)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn zstd_decompress(data: &[u8]) -> Result<Vec<u8>> {
+	Ok(zstd::stream::decode_all(data)?)
+}
+
+// zstd is a C library, that can't be built for WebAssembly
+#[cfg(target_arch = "wasm32")]
+fn zstd_compress(_data: &[u8], _level: i32) -> Result<Vec<u8>> {
+	bail!("zstd is not available in WebAssembly")
+}
+
+#[cfg(target_arch = "wasm32")]
+fn zstd_decompress(_data: &[u8]) -> Result<Vec<u8>> {
+	bail!("zstd is not available in WebAssembly")
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -473,6 +629,26 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn should_compress_with_levels() -> Result<()> {
+		let data = Blob::from("versatiles ".repeat(1000));
+		for level in [0, 6, 9] {
+			assert_eq!(decompress_gzip(&compress_gzip_level(&data, level)?)?, data);
+		}
+		for quality in [0, 6, 11] {
+			assert_eq!(decompress_brotli(&compress_brotli_quality(&data, quality)?)?, data);
+		}
+		assert!(compress_gzip_level(&data, 0)?.len() > compress_gzip_level(&data, 9)?.len());
+		for level in [1, 10, 22] {
+			assert_eq!(decompress_zstd(&compress_zstd_level(&data, level)?)?, data);
+		}
+		assert!(compress_gzip_level(&data, 10).is_err());
+		assert!(compress_brotli_quality(&data, 12).is_err());
+		assert!(compress_zstd_level(&data, 0).is_err());
+		assert!(compress_zstd_level(&data, 23).is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn should_compress_and_decompress_brotli_fast_correctly() -> Result<()> {
 		let data = generate_test_data(10_000);
@@ -491,12 +667,23 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn should_compress_and_decompress_zstd_correctly() -> Result<()> {
+		let data = generate_test_data(100_000);
+		let compressed = compress_zstd(&data)?;
+		let decompressed = decompress_zstd(&compressed)?;
+		assert_eq!(data, decompressed, "Zstandard compression and decompression failed");
+		assert!(decompress_zstd(&data).is_err());
+		Ok(())
+	}
+
 	#[test]
 	/// Tests the `optimize_compression` function across various compression scenarios.
 	fn should_optimize_compression_correctly() -> Result<()> {
 		let original_blob = generate_test_data(100);
 		let gzip_blob = compress_gzip(&original_blob)?;
 		let brotli_blob = compress_brotli(&original_blob)?;
+		let zstd_blob = compress_zstd(&original_blob)?;
 
 		let test_case = |input_compression: TileCompression,
 		                 allowed_compressions: EnumSet<TileCompression>,
@@ -511,12 +698,15 @@ mod tests {
 				TileCompression::Uncompressed => original_blob.clone(),
 				TileCompression::Gzip => gzip_blob.clone(),
 				TileCompression::Brotli => brotli_blob.clone(),
+				TileCompression::Zstd => zstd_blob.clone(),
 			};
 			let expected_blob = match expected_compression {
 				TileCompression::Uncompressed => original_blob.clone(),
 				TileCompression::Gzip => gzip_blob.clone(),
 				TileCompression::Brotli => brotli_blob.clone(),
+				TileCompression::Zstd => zstd_blob.clone(),
 			};
+			assert_eq!(select_compression(&input_compression, &target)?, expected_compression);
 			let (result_blob, result_compression) = optimize_compression(input_blob, &input_compression, &target)?;
 			assert_eq!(
 				result_compression, expected_compression,
@@ -533,11 +723,13 @@ mod tests {
 		let uncompressed = TileCompression::Uncompressed;
 		let gzip = TileCompression::Gzip;
 		let brotli = TileCompression::Brotli;
+		let zstd = TileCompression::Zstd;
 
 		let allowed_uncompressed = enum_set!(TileCompression::Uncompressed);
 		let allowed_gzip = enum_set!(TileCompression::Uncompressed | TileCompression::Gzip);
 		let allowed_brotli = enum_set!(TileCompression::Uncompressed | TileCompression::Brotli);
-		let allowed_all = enum_set!(TileCompression::Uncompressed | TileCompression::Gzip | TileCompression::Brotli);
+		let allowed_zstd = enum_set!(TileCompression::Uncompressed | TileCompression::Gzip | TileCompression::Zstd);
+		let allowed_all = EnumSet::all();
 
 		use CompressionGoal::*;
 
@@ -545,17 +737,25 @@ mod tests {
 		test_case(uncompressed, allowed_all, UseBestCompression, brotli)?;
 		test_case(gzip, allowed_all, UseBestCompression, brotli)?;
 		test_case(brotli, allowed_all, UseBestCompression, brotli)?;
+		test_case(zstd, allowed_all, UseBestCompression, brotli)?;
+		test_case(uncompressed, allowed_zstd, UseBestCompression, zstd)?;
+		test_case(gzip, allowed_zstd, UseBestCompression, zstd)?;
+		test_case(brotli, allowed_zstd, UseBestCompression, zstd)?;
+		test_case(zstd, allowed_gzip, UseBestCompression, gzip)?;
 
 		// Test using fast compression
 		test_case(uncompressed, allowed_all, UseFastCompression, uncompressed)?;
 		test_case(gzip, allowed_gzip, UseFastCompression, gzip)?;
 		test_case(gzip, allowed_brotli, UseFastCompression, brotli)?;
 		test_case(brotli, allowed_all, UseFastCompression, brotli)?;
+		test_case(zstd, allowed_all, UseFastCompression, zstd)?;
+		test_case(zstd, allowed_brotli, UseFastCompression, brotli)?;
 
 		// Test treating data as incompressible
 		test_case(uncompressed, allowed_uncompressed, IsIncompressible, uncompressed)?;
 		test_case(gzip, allowed_gzip, IsIncompressible, gzip)?;
 		test_case(brotli, allowed_brotli, IsIncompressible, brotli)?;
+		test_case(zstd, allowed_brotli, IsIncompressible, uncompressed)?;
 
 		Ok(())
 	}
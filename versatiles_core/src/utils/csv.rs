@@ -1,7 +1,69 @@
 use crate::byte_iterator::ByteIterator;
-use anyhow::{bail, Error, Result};
+use anyhow::{bail, Result};
 use std::io::BufRead;
 
+/// Separators that are recognized by [`detect_csv_separator`].
+const SEPARATOR_CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Code points of the bytes 0x80..0x9F in Windows-1252, all other bytes are identical to ISO-8859-1.
+const WINDOWS_1252: [char; 32] = [
+	'€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}', '\u{90}', '‘', '’',
+	'“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Decodes a field as UTF-8, falling back to Windows-1252, which is still common in CSV exports of spreadsheets.
+fn decode_field(bytes: Vec<u8>) -> String {
+	match String::from_utf8(bytes) {
+		Ok(text) => text,
+		Err(err) => err
+			.into_bytes()
+			.into_iter()
+			.map(|b| match b {
+				0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+				_ => b as char,
+			})
+			.collect(),
+	}
+}
+
+/// Guesses the separator of a CSV file by looking at its beginning, without consuming the reader.
+///
+/// The separator is the candidate (`,`, `;`, tab or `|`) that occurs equally often in every sampled line,
+/// ignoring quoted fields. Defaults to `,`.
+pub fn detect_csv_separator(reader: &mut impl BufRead) -> Result<u8> {
+	let sample = reader.fill_buf()?;
+
+	// count the candidates outside of quotes, per line
+	let mut lines: Vec<[usize; 4]> = vec![[0; 4]];
+	let mut in_quotes = false;
+	for byte in sample.iter() {
+		match byte {
+			b'"' => in_quotes = !in_quotes,
+			b'\n' if !in_quotes => {
+				if lines.len() >= 20 {
+					break;
+				}
+				lines.push([0; 4]);
+			}
+			_ if !in_quotes => {
+				if let Some(index) = SEPARATOR_CANDIDATES.iter().position(|c| c == byte) {
+					lines.last_mut().unwrap()[index] += 1;
+				}
+			}
+			_ => {}
+		}
+	}
+	// the last line might be incomplete or empty
+	if lines.len() > 1 {
+		lines.pop();
+	}
+
+	let best = (0..SEPARATOR_CANDIDATES.len())
+		.filter(|&i| lines[0][i] > 0 && lines.iter().all(|line| line[i] == lines[0][i]))
+		.max_by_key(|&i| lines[0][i]);
+	Ok(best.map_or(b',', |i| SEPARATOR_CANDIDATES[i]))
+}
+
 fn parse_quoted_csv_string(iter: &mut ByteIterator) -> Result<String> {
 	if iter.expect_next_byte()? != b'"' {
 		bail!(iter.format_error("expected '\"' while parsing a string"));
@@ -15,7 +77,7 @@ fn parse_quoted_csv_string(iter: &mut ByteIterator) -> Result<String> {
 					bytes.push(b'"');
 					iter.advance();
 				}
-				_ => return String::from_utf8(bytes).map_err(Error::from),
+				_ => return Ok(decode_field(bytes)),
 			},
 			Some(c) => bytes.push(c),
 			None => bail!("unexpected end of file"),
@@ -31,8 +93,8 @@ fn parse_simple_csv_string(iter: &mut ByteIterator, separator: u8) -> Result<Str
 	let mut bytes: Vec<u8> = Vec::new();
 	loop {
 		match iter.peek() {
-			Some(s) if s == separator => return String::from_utf8(bytes).map_err(Error::from),
-			Some(b'\r') | Some(b'\n') | None => return String::from_utf8(bytes).map_err(Error::from),
+			Some(s) if s == separator => return Ok(decode_field(bytes)),
+			Some(b'\r') | Some(b'\n') | None => return Ok(decode_field(bytes)),
 			Some(c) => {
 				bytes.push(c);
 				iter.advance();
@@ -82,7 +144,11 @@ fn read_csv_fields<'a>(
 						return Some(Ok((fields, iter.position())));
 					}
 					Some(e) if e == separator => break,
-					Some(_) => panic!(),
+					Some(_) => {
+						return Some(Err(
+							iter.format_error("expected separator or end of line after a quoted field"),
+						))
+					}
 				}
 			}
 		}
@@ -100,7 +166,15 @@ pub fn read_csv_iter<'a>(
 	let mut option_len: Option<usize> = None;
 
 	Ok(iter.map(move |entry| {
-		entry.and_then(|(fields, byte_pos)| {
+		entry.and_then(|(mut fields, byte_pos)| {
+			if line_pos == 0 {
+				// remove the byte order mark, that some editors add to UTF-8 files
+				if let Some(first) = fields.first_mut() {
+					if let Some(stripped) = first.strip_prefix('\u{feff}') {
+						*first = stripped.to_string();
+					}
+				}
+			}
 			if let Some(len) = option_len {
 				if fields.len() != len {
 					bail!("At byte {byte_pos}: line {line_pos} has different number of fields");
@@ -201,6 +275,51 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_read_csv_fields_multiline_and_invalid_quotes() {
+		let reader = Cursor::new("name,note\n\"John\",\"line 1\nline 2\"\nJane,\"\"");
+		let iter = read_csv_fields(reader, b',').unwrap();
+		assert_eq!(
+			check(iter),
+			vec![vec!["name", "note"], vec!["John", "line 1\nline 2"], vec!["Jane", ""]]
+		);
+
+		let reader = Cursor::new("name,note\n\"John\"x,1");
+		let mut iter = read_csv_fields(reader, b',').unwrap();
+		iter.next().unwrap().unwrap();
+		assert!(iter.next().unwrap().is_err());
+	}
+
+	#[test]
+	fn test_encoding() {
+		// "Müller" and "€" in Windows-1252, with a UTF-8 byte order mark in the header
+		let mut data = b"\xEF\xBB\xBFname;price\n".to_vec();
+		data.extend_from_slice(b"M\xFCller;5\x80\nStra\xC3\x9Fe;1");
+		let iter = read_csv_iter(Cursor::new(data), b';').unwrap();
+		let rows: Vec<Vec<String>> = iter.map(|e| e.unwrap().0).collect();
+		assert_eq!(
+			rows,
+			vec![vec!["name", "price"], vec!["Müller", "5€"], vec!["Straße", "1"]]
+		);
+	}
+
+	#[test]
+	fn test_detect_csv_separator() {
+		let detect = |text: &str| detect_csv_separator(&mut Cursor::new(text)).unwrap() as char;
+		assert_eq!(detect("a,b,c\n1,2,3\n"), ',');
+		assert_eq!(detect("a;b;c\n1,5;2;3\n4;5,5;6"), ';');
+		assert_eq!(detect("a\tb\n1\t2"), '\t');
+		assert_eq!(detect("a|b\n\"x|y\"|2\n"), '|');
+		assert_eq!(detect("\"a;b\",c\n1,2"), ',');
+		assert_eq!(detect("single column\nvalue"), ',');
+		assert_eq!(detect(""), ',');
+
+		// the reader is not consumed
+		let mut reader = Cursor::new("a;b");
+		detect_csv_separator(&mut reader).unwrap();
+		assert_eq!(reader.position(), 0);
+	}
+
 	#[test]
 	fn test_read_csv_fields_different_separator() {
 		let mut reader = Cursor::new("name|age\nJohn Doe|30\nJane Doe|29");
@@ -0,0 +1,195 @@
+//! Cooperative locking of files that are being written, e.g. when a server and a converter share a directory.
+//!
+//! A writer holds an exclusive advisory lock (flock on Unix, LockFileEx on Windows) on a lock file next to the
+//! container, e.g. "berlin.versatiles.lock", that contains its process ID. Readers refuse files whose lock file
+//! is locked, instead of reading an incomplete container. The operating system releases the lock when the writer
+//! exits, so lock files left behind by crashed processes are ignored. [`set_ignore_file_locks`] disables the checks.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use versatiles_core::utils::{ensure_not_being_written, FileLock};
+//!
+//! let path = std::env::temp_dir().join("versatiles-lock-example.versatiles");
+//! let lock = FileLock::lock_for_writing(&path).unwrap();
+//! assert!(ensure_not_being_written(&path).is_err());
+//! drop(lock);
+//! assert!(ensure_not_being_written(&path).is_ok());
+//! ```
+
+use anyhow::{Context, Result};
+use std::{
+	ffi::OsString,
+	fs::{self, File, OpenOptions, TryLockError},
+	io::{Read, Seek, Write},
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+static IGNORE_FILE_LOCKS: AtomicBool = AtomicBool::new(false);
+
+/// Ignores the locks of other processes, e.g. to read a file that is known to be complete.
+pub fn set_ignore_file_locks(ignore: bool) {
+	IGNORE_FILE_LOCKS.store(ignore, Ordering::Relaxed);
+}
+
+/// Returns an error, if another process is writing the file at `path`.
+pub fn ensure_not_being_written(path: &Path) -> Result<()> {
+	check_not_being_written(path, IGNORE_FILE_LOCKS.load(Ordering::Relaxed))
+}
+
+/// Exclusive lock of a file that is being written. The lock is released and the lock file removed when dropped.
+#[derive(Debug)]
+pub struct FileLock {
+	file: Option<File>,
+	path: PathBuf,
+}
+
+impl FileLock {
+	/// Locks the file at `path` for writing. Fails if another process is writing it.
+	pub fn lock_for_writing(path: &Path) -> Result<FileLock> {
+		lock(path, IGNORE_FILE_LOCKS.load(Ordering::Relaxed))
+	}
+}
+
+impl Drop for FileLock {
+	fn drop(&mut self) {
+		if self.file.is_none() {
+			// the lock file belongs to another process
+			return;
+		}
+		// removing the lock file while it is still locked avoids a race with other writers,
+		// but Windows can only remove it after it is closed
+		let removed = fs::remove_file(&self.path).is_ok();
+		drop(self.file.take());
+		if !removed {
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+	let mut name = OsString::from(path.as_os_str());
+	name.push(".lock");
+	PathBuf::from(name)
+}
+
+fn being_written_error(path: &Path, mut file: &File) -> anyhow::Error {
+	let mut pid = String::new();
+	if file.read_to_string(&mut pid).is_err() || pid.trim().is_empty() {
+		pid = String::from("unknown");
+	}
+	anyhow::anyhow!(
+		"file {path:?} is being written by PID {}, wait until it is finished or use --force to ignore the lock",
+		pid.trim()
+	)
+}
+
+fn check_not_being_written(path: &Path, force: bool) -> Result<()> {
+	let Ok(file) = File::open(lock_path(path)) else {
+		return Ok(());
+	};
+	match file.try_lock_shared() {
+		Ok(()) => Ok(()),
+		Err(TryLockError::WouldBlock) if force => {
+			log::warn!("ignoring the lock of {path:?}, it might be incomplete");
+			Ok(())
+		}
+		Err(TryLockError::WouldBlock) => Err(being_written_error(path, &file)),
+		Err(TryLockError::Error(error)) => {
+			log::debug!("can not check the lock of {path:?}: {error}");
+			Ok(())
+		}
+	}
+}
+
+fn lock(path: &Path, force: bool) -> Result<FileLock> {
+	let lock_path = lock_path(path);
+	let mut file = OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.open(&lock_path)
+		.with_context(|| format!("creating lock file {lock_path:?}"))?;
+
+	match file.try_lock() {
+		Ok(()) => {}
+		Err(TryLockError::WouldBlock) if force => {
+			log::warn!("ignoring the lock of {path:?}, another process is writing it");
+			return Ok(FileLock {
+				file: None,
+				path: lock_path,
+			});
+		}
+		Err(TryLockError::WouldBlock) => return Err(being_written_error(path, &file)),
+		Err(TryLockError::Error(error)) => {
+			log::debug!("can not lock {path:?}: {error}");
+		}
+	}
+
+	file.set_len(0)?;
+	file.rewind()?;
+	write!(file, "{}", std::process::id())?;
+	file.flush()?;
+	Ok(FileLock {
+		file: Some(file),
+		path: lock_path,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+
+	#[test]
+	fn lock_and_unlock() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("tiles.versatiles");
+
+		check_not_being_written(&path, false)?;
+		let file_lock = lock(&path, false)?;
+		assert_eq!(fs::read_to_string(lock_path(&path))?, std::process::id().to_string());
+
+		let message = format!(
+			"file {path:?} is being written by PID {}, wait until it is finished or use --force to ignore the lock",
+			std::process::id()
+		);
+		assert_eq!(check_not_being_written(&path, false).unwrap_err().to_string(), message);
+		assert_eq!(lock(&path, false).unwrap_err().to_string(), message);
+
+		drop(file_lock);
+		assert!(!lock_path(&path).exists());
+		check_not_being_written(&path, false)?;
+		Ok(())
+	}
+
+	#[test]
+	fn ignore_stale_lock_file() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("tiles.pmtiles");
+		fs::write(lock_path(&path), "123456")?;
+
+		check_not_being_written(&path, false)?;
+		let file_lock = lock(&path, false)?;
+		assert_eq!(fs::read_to_string(lock_path(&path))?, std::process::id().to_string());
+		drop(file_lock);
+		Ok(())
+	}
+
+	#[test]
+	fn force() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("tiles.mbtiles");
+		let file_lock = lock(&path, false)?;
+
+		check_not_being_written(&path, true)?;
+		let forced_lock = lock(&path, true)?;
+		assert!(forced_lock.file.is_none());
+		drop(forced_lock);
+		assert!(lock_path(&path).exists());
+		drop(file_lock);
+		Ok(())
+	}
+}
@@ -0,0 +1,79 @@
+//! Memory budget of conversions.
+//!
+//! Readers, write queues and writers buffer tiles and index entries. [`set_max_memory`] limits them for
+//! the whole process, so that large conversions run on small machines. The budget is split up:
+//! - a quarter for tiles that readers load at once, shared by one reader per CPU, see [`get_read_chunk_size`],
+//! - a quarter for tiles waiting in the queues of writers, see [`get_write_queue_memory`],
+//! - the rest for the indexes of writers, e.g. the directory entries of PMTiles.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use versatiles_core::utils::{get_max_memory, get_read_chunk_size, set_max_memory};
+//!
+//! set_max_memory(64 * 1024 * 1024);
+//! assert_eq!(get_max_memory(), 64 * 1024 * 1024);
+//!
+//! // a level with 1 million tiles of 50 KB has to be read in chunks of some hundred tiles
+//! let size = get_read_chunk_size(1_000_000, 50_000_000_000).unwrap();
+//! assert!(size * size < 1000);
+//! # set_max_memory(versatiles_core::utils::DEFAULT_MAX_MEMORY);
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default memory budget: 1 GiB.
+pub const DEFAULT_MAX_MEMORY: usize = 1 << 30;
+
+static MAX_MEMORY: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_MEMORY);
+
+/// Sets the memory budget in bytes for all conversions started afterwards.
+pub fn set_max_memory(bytes: usize) {
+	MAX_MEMORY.store(bytes, Ordering::Relaxed);
+}
+
+/// Returns the memory budget in bytes, see [`set_max_memory`].
+pub fn get_max_memory() -> usize {
+	MAX_MEMORY.load(Ordering::Relaxed)
+}
+
+/// Returns the number of bytes that a single reader may load at once.
+pub fn get_read_memory() -> usize {
+	get_max_memory() / 4 / num_cpus::get()
+}
+
+/// Returns the number of bytes of tiles that may wait in the queue of a writer.
+pub fn get_write_queue_memory() -> usize {
+	get_max_memory() / 4
+}
+
+/// Returns the side length of square chunks, if a reader can't load `tile_count` tiles of `bytes` in total at once.
+/// Each chunk contains tiles of about [`get_read_memory`] bytes, but at least one tile.
+/// Returns `None`, if all tiles fit into the budget.
+pub fn get_read_chunk_size(tile_count: u64, bytes: u64) -> Option<u32> {
+	let budget = get_read_memory() as u64;
+	if bytes <= budget || tile_count == 0 {
+		return None;
+	}
+	let tiles_per_chunk = (budget as f64 * tile_count as f64 / bytes as f64).max(1.0);
+	Some((tiles_per_chunk.sqrt().floor() as u32).clamp(1, 1 << 16))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn read_chunk_size() {
+		let budget = get_read_memory() as u64;
+		assert_eq!(get_read_chunk_size(0, 0), None);
+		assert_eq!(get_read_chunk_size(1000, budget), None);
+
+		// 100 tiles per chunk
+		assert_eq!(get_read_chunk_size(10_000, budget * 100), Some(10));
+		// 10 tiles per chunk
+		assert_eq!(get_read_chunk_size(10_000, budget * 1000), Some(3));
+		// tiles larger than the budget are read one by one
+		assert_eq!(get_read_chunk_size(10, budget * 100), Some(1));
+	}
+}
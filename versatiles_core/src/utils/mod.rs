@@ -1,11 +1,19 @@
 mod compression;
 mod csv;
+mod file_lock;
+mod max_memory;
 #[cfg(feature = "cli")]
 mod pretty_print;
+mod temp_dir;
 mod transform_coord;
+mod zoom_range;
 
 pub use compression::*;
 pub use csv::*;
+pub use file_lock::*;
+pub use max_memory::*;
 #[cfg(feature = "cli")]
 pub use pretty_print::*;
+pub use temp_dir::*;
 pub use transform_coord::*;
+pub use zoom_range::*;
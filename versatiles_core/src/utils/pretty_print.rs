@@ -8,8 +8,8 @@ struct PrettyPrinter {
 	indention: String,
 	#[cfg(not(any(test, feature = "test")))]
 	output: Arc<Mutex<Box<dyn Write + Send>>>,
-	#[cfg(any(test, feature = "test"))]
-	output: Arc<Mutex<Vec<u8>>>,
+	/// everything written so far, e.g. to cache a report
+	buffer: Arc<Mutex<Vec<u8>>>,
 }
 
 impl PrettyPrinter {
@@ -23,13 +23,18 @@ impl PrettyPrinter {
 			#[cfg(not(any(test, feature = "test")))]
 			output: Arc::new(Mutex::new(Box::new(stderr()))),
 
-			#[cfg(any(test, feature = "test"))]
-			output: Arc::new(Mutex::new(Vec::new())),
+			buffer: Arc::new(Mutex::new(Vec::new())),
 		}
 	}
 
 	async fn write(&self, text: String) {
+		#[cfg(not(any(test, feature = "test")))]
 		self.output.lock().await.write_all(text.as_bytes()).unwrap();
+		self.buffer.lock().await.write_all(text.as_bytes()).unwrap();
+	}
+
+	async fn get_output(&self) -> String {
+		String::from_utf8_lossy(&self.buffer.lock().await).to_string()
 	}
 
 	#[cfg(any(test, feature = "test"))]
@@ -41,7 +46,7 @@ impl PrettyPrinter {
 			static ref RE_COLORS: Regex = RegexBuilder::new("\u{001b}\\[[0-9;]*m").build().unwrap();
 		}
 
-		let text: String = String::from_utf8(self.output.lock().await.to_vec()).unwrap();
+		let text: String = self.get_output().await;
 		RE_COLORS.replace_all(&text, "").to_string()
 	}
 }
@@ -98,6 +103,11 @@ impl PrettyPrint {
 			.await;
 	}
 
+	/// Returns everything printed so far, including color codes, e.g. to print it again later.
+	pub async fn get_output(&self) -> String {
+		self.printer.get_output().await
+	}
+
 	#[cfg(any(test, feature = "test"))]
 	pub async fn as_string(&self) -> String {
 		self.printer.as_string().await
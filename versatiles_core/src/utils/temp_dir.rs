@@ -0,0 +1,144 @@
+//! Directory of temporary files, and checks of the free disk space.
+//!
+//! Some writers and downloads need temporary files, e.g. the sorted entries of huge PMTiles files.
+//! By default they are stored in the temporary directory of the system, which is often too small on
+//! build servers. [`set_temp_dir`] changes the directory for the whole process.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use versatiles_core::utils::{get_available_space, get_temp_dir, set_temp_dir};
+//!
+//! set_temp_dir(Some(std::env::current_dir().unwrap())).unwrap();
+//! let path = get_temp_dir().join("versatiles-example.tmp");
+//! let available = get_available_space(&path).unwrap();
+//! set_temp_dir(None).unwrap();
+//! ```
+
+use anyhow::{ensure, Result};
+use std::{
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+static TEMP_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Sets the directory of all temporary files created afterwards. `None` restores the temporary directory of the system.
+pub fn set_temp_dir(dir: Option<PathBuf>) -> Result<()> {
+	store_temp_dir(&TEMP_DIR, dir)
+}
+
+/// Returns the directory for temporary files, see [`set_temp_dir`].
+pub fn get_temp_dir() -> PathBuf {
+	load_temp_dir(&TEMP_DIR)
+}
+
+fn store_temp_dir(slot: &Mutex<Option<PathBuf>>, dir: Option<PathBuf>) -> Result<()> {
+	if let Some(dir) = &dir {
+		ensure!(dir.is_dir(), "temporary directory {dir:?} does not exist");
+	}
+	*slot.lock().unwrap() = dir;
+	Ok(())
+}
+
+fn load_temp_dir(slot: &Mutex<Option<PathBuf>>) -> PathBuf {
+	slot.lock().unwrap().clone().unwrap_or_else(std::env::temp_dir)
+}
+
+/// Returns the free disk space in bytes for a file at `path`, which does not have to exist yet.
+/// Returns `None` if the space can not be determined on this platform.
+pub fn get_available_space(path: &Path) -> Result<Option<u64>> {
+	// the nearest existing folder is on the same file system
+	let mut dir = path;
+	while !dir.is_dir() {
+		match dir.parent() {
+			Some(parent) if !parent.as_os_str().is_empty() => dir = parent,
+			_ => {
+				dir = Path::new(".");
+				break;
+			}
+		}
+	}
+	available_space_of_dir(dir)
+}
+
+#[cfg(unix)]
+fn available_space_of_dir(dir: &Path) -> Result<Option<u64>> {
+	use anyhow::Context;
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let c_path = CString::new(dir.as_os_str().as_bytes())?;
+	// SAFETY: `statvfs` is plain old data, and is filled by the call below
+	let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+	// SAFETY: `c_path` is a valid C string and `stat` lives until the call returns
+	if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+		return Err(std::io::Error::last_os_error()).with_context(|| format!("reading the free disk space of {dir:?}"));
+	}
+	#[allow(clippy::useless_conversion)]
+	Ok(Some(u64::from(stat.f_bavail) * u64::from(stat.f_frsize)))
+}
+
+#[cfg(not(unix))]
+fn available_space_of_dir(_dir: &Path) -> Result<Option<u64>> {
+	Ok(None)
+}
+
+/// Fails if there are less than `needed` bytes of free disk space for a file at `path`.
+/// `purpose` describes the file in the error message, e.g. "the output".
+pub fn ensure_available_space(path: &Path, needed: u64, purpose: &str) -> Result<()> {
+	if let Some(available) = get_available_space(path)? {
+		let mb = |bytes: u64| bytes.div_ceil(1 << 20);
+		ensure!(
+			needed <= available,
+			"not enough disk space for {purpose}: about {} MB are needed, but only {} MB are available for {path:?}",
+			mb(needed),
+			mb(available)
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// uses its own slot, because other tests read the process-wide directory in parallel
+	#[test]
+	fn temp_dir() -> Result<()> {
+		let slot = Mutex::new(None);
+		assert_eq!(load_temp_dir(&slot), std::env::temp_dir());
+
+		let dir = std::env::current_dir()?;
+		store_temp_dir(&slot, Some(dir.clone()))?;
+		assert_eq!(load_temp_dir(&slot), dir);
+
+		assert!(store_temp_dir(&slot, Some(dir.join("does_not_exist")))
+			.unwrap_err()
+			.to_string()
+			.ends_with("does_not_exist\" does not exist"));
+		assert_eq!(load_temp_dir(&slot), dir);
+
+		store_temp_dir(&slot, None)?;
+		assert_eq!(load_temp_dir(&slot), std::env::temp_dir());
+		Ok(())
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn available_space() -> Result<()> {
+		let dir = std::env::current_dir()?;
+		let available = get_available_space(&dir.join("missing/folder/file.versatiles"))?.unwrap();
+		assert!(available > 0);
+		assert!(get_available_space(Path::new("relative.versatiles"))?.is_some());
+
+		ensure_available_space(&dir, 1, "the test")?;
+		let error = ensure_available_space(&dir, u64::MAX, "the test")
+			.unwrap_err()
+			.to_string();
+		assert!(
+			error.starts_with("not enough disk space for the test: about 17592186044416 MB are needed, but only"),
+			"{error}"
+		);
+		Ok(())
+	}
+}
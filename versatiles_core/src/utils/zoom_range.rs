@@ -0,0 +1,43 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::ops::RangeInclusive;
+
+/// Parses a zoom range: a single zoom level (`5`), a closed range (`0-8`) or an open range (`13-` or `-4`).
+pub fn parse_zoom_range(text: &str) -> Result<RangeInclusive<u8>> {
+	let parse = |s: &str, default: u8| -> Result<u8> {
+		if s.is_empty() {
+			return Ok(default);
+		}
+		let zoom = s
+			.parse::<u8>()
+			.with_context(|| format!("zoom level {s:?} is not a number"))?;
+		ensure!(zoom <= 31, "zoom level must be between 0 and 31, but is {zoom}");
+		Ok(zoom)
+	};
+	let range = match text.split_once('-') {
+		Some((min, max)) => parse(min, 0)?..=parse(max, 31)?,
+		None => {
+			if text.is_empty() {
+				bail!("zoom range is missing");
+			}
+			let zoom = parse(text, 0)?;
+			zoom..=zoom
+		}
+	};
+	ensure!(!range.is_empty(), "zoom range {text:?} is empty");
+	Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_zoom_range() {
+		assert_eq!(parse_zoom_range("5").unwrap(), 5..=5);
+		assert_eq!(parse_zoom_range("0-8").unwrap(), 0..=8);
+		assert_eq!(parse_zoom_range("13-").unwrap(), 13..=31);
+		assert_eq!(parse_zoom_range("-4").unwrap(), 0..=4);
+		assert!(parse_zoom_range("").is_err());
+		assert!(parse_zoom_range("8-2").is_err());
+	}
+}
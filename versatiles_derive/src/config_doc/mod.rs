@@ -0,0 +1,3 @@
+mod schema_struct;
+
+pub use schema_struct::schema_struct;
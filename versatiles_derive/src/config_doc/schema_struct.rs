@@ -0,0 +1,204 @@
+use crate::decode_vpl::extract_comment;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, DataStruct, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Token, Type};
+
+pub fn schema_struct(input: DeriveInput, data_struct: DataStruct) -> TokenStream {
+	let name = input.ident;
+
+	let doc_struct = input
+		.attrs
+		.iter()
+		.filter_map(extract_comment)
+		.collect::<Vec<String>>()
+		.join(" ");
+	let serde_struct = SerdeAttributes::from_attrs(&input.attrs);
+
+	let fields = if let Fields::Named(fields_named) = data_struct.fields {
+		fields_named.named
+	} else {
+		panic!("ConfigDoc can only be derived for structs with named fields");
+	};
+
+	let mut properties: Vec<TokenStream> = Vec::new();
+	let mut required: Vec<String> = Vec::new();
+
+	for field in fields {
+		let serde_field = SerdeAttributes::from_attrs(&field.attrs);
+		let key = serde_field
+			.rename
+			.unwrap_or_else(|| field.ident.as_ref().expect("could not get field_name").to_string());
+
+		let comment = field
+			.attrs
+			.iter()
+			.filter_map(extract_comment)
+			.collect::<Vec<String>>()
+			.join(" ");
+
+		let (inner_type, optional) = match generic_argument(&field.ty, "Option") {
+			Some(inner_type) => (inner_type, true),
+			None => (&field.ty, false),
+		};
+		if !optional && !serde_field.default && !serde_struct.default {
+			required.push(key.clone());
+		}
+
+		let schema = match get_values(&field.attrs) {
+			Some(values) => quote! {
+				versatiles_core::json::JsonValue::from(vec![
+					("type", versatiles_core::json::JsonValue::from("string")),
+					("enum", versatiles_core::json::JsonValue::from(vec![#(#values),*])),
+				])
+			},
+			None => type_schema(inner_type),
+		};
+
+		let description = (!comment.is_empty()).then(|| quote! { schema.set("description", #comment); });
+		properties.push(quote! {
+			let mut schema = #schema.to_object().unwrap();
+			#description
+			properties.set(#key, versatiles_core::json::JsonValue::Object(schema));
+		});
+	}
+
+	let description = (!doc_struct.is_empty()).then(|| quote! { schema.set("description", #doc_struct); });
+	let required = (!required.is_empty()).then(|| {
+		quote! {
+			let required: Vec<&str> = vec![#(#required),*];
+			schema.set("required", versatiles_core::json::JsonValue::from(required));
+		}
+	});
+	let additional_properties = serde_struct
+		.deny_unknown_fields
+		.then(|| quote! { schema.set("additionalProperties", false); });
+
+	quote! {
+		impl #name {
+			/// Returns the JSON Schema of this struct.
+			pub fn get_json_schema() -> versatiles_core::json::JsonValue {
+				let mut properties = versatiles_core::json::JsonObject::default();
+				#(
+					{ #properties }
+				)*
+
+				let mut schema = versatiles_core::json::JsonObject::default();
+				schema.set("type", "object");
+				#description
+				schema.set("properties", versatiles_core::json::JsonValue::Object(properties));
+				#required
+				#additional_properties
+				versatiles_core::json::JsonValue::Object(schema)
+			}
+		}
+	}
+}
+
+/// Returns the code that generates the schema of a type.
+fn type_schema(ty: &Type) -> TokenStream {
+	if let Some(item_type) = generic_argument(ty, "Vec") {
+		let items = type_schema(item_type);
+		return quote! {
+			versatiles_core::json::JsonValue::from(vec![
+				("type", versatiles_core::json::JsonValue::from("array")),
+				("items", #items),
+			])
+		};
+	}
+
+	let type_str = quote!(#ty).to_string().replace(' ', "");
+	let (json_type, minimum, maximum): (&str, Option<u32>, Option<u32>) = match type_str.as_str() {
+		"String" => ("string", None, None),
+		"bool" => ("boolean", None, None),
+		"u8" => ("integer", Some(0), Some(u8::MAX as u32)),
+		"u16" => ("integer", Some(0), Some(u16::MAX as u32)),
+		"u32" => ("integer", Some(0), Some(u32::MAX)),
+		"u64" | "usize" => ("integer", Some(0), None),
+		"i8" | "i16" | "i32" | "i64" => ("integer", None, None),
+		"f32" | "f64" => ("number", None, None),
+		"JsonObject" => ("object", None, None),
+		_ => return quote! { <#ty>::get_json_schema() },
+	};
+	let minimum = minimum.map(|v| quote! { schema.set("minimum", #v); });
+	let maximum = maximum.map(|v| quote! { schema.set("maximum", #v); });
+	quote! {
+		{
+			let mut schema = versatiles_core::json::JsonObject::default();
+			schema.set("type", #json_type);
+			#minimum
+			#maximum
+			versatiles_core::json::JsonValue::Object(schema)
+		}
+	}
+}
+
+/// Returns `T` if `ty` is `wrapper<T>`.
+fn generic_argument<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+	let Type::Path(type_path) = ty else {
+		return None;
+	};
+	let segment = type_path.path.segments.last()?;
+	if segment.ident != wrapper {
+		return None;
+	}
+	let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+		return None;
+	};
+	match arguments.args.first()? {
+		GenericArgument::Type(inner_type) => Some(inner_type),
+		_ => None,
+	}
+}
+
+/// Parses `#[config_doc(values = "a, b")]`.
+fn get_values(attrs: &[Attribute]) -> Option<Vec<String>> {
+	let mut values = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("config_doc")) {
+		attr
+			.parse_nested_meta(|meta| {
+				if meta.path.is_ident("values") {
+					let list: LitStr = meta.value()?.parse()?;
+					values = Some(list.value().split(',').map(|v| v.trim().to_string()).collect());
+					Ok(())
+				} else {
+					Err(meta.error("unknown config_doc attribute, expected \"values\""))
+				}
+			})
+			.unwrap();
+	}
+	values
+}
+
+/// The serde attributes that change the schema.
+#[derive(Default)]
+struct SerdeAttributes {
+	default: bool,
+	deny_unknown_fields: bool,
+	rename: Option<String>,
+}
+
+impl SerdeAttributes {
+	fn from_attrs(attrs: &[Attribute]) -> Self {
+		let mut result = SerdeAttributes::default();
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+			attr
+				.parse_nested_meta(|meta| {
+					if meta.path.is_ident("default") {
+						result.default = true;
+					} else if meta.path.is_ident("deny_unknown_fields") {
+						result.deny_unknown_fields = true;
+					} else if meta.path.is_ident("rename") {
+						result.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+						return Ok(());
+					}
+					// skip the values of all other attributes, e.g. `deserialize_with = "..."`
+					if meta.input.peek(Token![=]) {
+						meta.value()?.parse::<syn::Expr>()?;
+					}
+					Ok(())
+				})
+				.unwrap();
+		}
+		result
+	}
+}
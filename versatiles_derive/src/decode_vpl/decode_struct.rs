@@ -65,10 +65,18 @@ pub fn decode_struct(input: DeriveInput, data_struct: DataStruct) -> TokenStream
 					format!("* *`{field_str}`: u8 *{comment}"),
 					quote! { #field_name: node.get_property_number_req::<u8>(#field_str)? },
 				),
+				"u32" => (
+					format!("* **`{field_str}`: u32 (required)**{comment}"),
+					quote! { #field_name: node.get_property_number_req::<u32>(#field_str)? },
+				),
 				"[f64;4]" => (
 					format!("* **`{field_str}`: [f64,f64,f64,f64] (required)**{comment}"),
 					quote! { #field_name: node.get_property_number_array4_req::<f64>(#field_str)? },
 				),
+				"Vec<String>" => (
+					format!("* *`{field_str}`: [String] (optional, default: [])*{comment}"),
+					quote! { #field_name: node.get_property_string_array(#field_str) },
+				),
 				"Option<String>" => (
 					format!("* *`{field_str}`: String (optional)*{comment}"),
 					quote! { #field_name: node.get_property_string(#field_str)? },
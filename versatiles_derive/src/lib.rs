@@ -1,5 +1,7 @@
+mod config_doc;
 mod decode_vpl;
 
+use config_doc::schema_struct;
 use decode_vpl::decode_struct;
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, Data, DeriveInput};
@@ -15,3 +17,20 @@ pub fn decode_vpl(input: TokenStream) -> TokenStream {
 
 	TokenStream::from(expanded)
 }
+
+/// Documents a configuration struct as JSON Schema, generating `get_json_schema()`.
+///
+/// Doc comments become descriptions, and the serde attributes `default`, `rename` and `deny_unknown_fields`
+/// are respected. Fields that are deserialized from a fixed set of strings can list them with
+/// `#[config_doc(values = "xyz, tms")]`.
+#[proc_macro_derive(ConfigDoc, attributes(config_doc))]
+pub fn config_doc(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+
+	let expanded = match input.data.clone() {
+		Data::Struct(data_struct) => schema_struct(input, data_struct),
+		_ => panic!("ConfigDoc can only be derived for structs, but: {:?}", input.data),
+	};
+
+	TokenStream::from(expanded)
+}
@@ -0,0 +1,468 @@
+//! Clipping of geometries to rectangles, e.g. tiles, and to convex polygons.
+//!
+//! Lines are clipped segment by segment (Liang–Barsky for rectangles, Cyrus–Beck for convex polygons),
+//! rings with the Sutherland–Hodgman algorithm.
+
+use super::*;
+use crate::math::area_ring;
+
+/// Clips a line string to the rectangle `[x_min, y_min, x_max, y_max]`.
+///
+/// A line that leaves and re-enters the rectangle is split into several parts.
+pub fn clip_line_string(line: &Coordinates1, bbox: &[f64; 4]) -> Coordinates2 {
+	clip_segments(line, |a, b| clip_segment(a, b, bbox))
+}
+
+/// Clips a line string to a convex polygon, given as closed ring in any orientation.
+///
+/// A line that leaves and re-enters the polygon is split into several parts.
+pub fn clip_line_string_convex(line: &Coordinates1, clip: &Coordinates1) -> Coordinates2 {
+	let sign = ring_orientation(clip);
+	clip_segments(line, |a, b| clip_segment_convex(a, b, clip, sign))
+}
+
+/// Joins the clipped segments of a line to line strings.
+fn clip_segments(
+	line: &Coordinates1,
+	clip_segment: impl Fn(Coordinates0, Coordinates0) -> Option<(Coordinates0, Coordinates0)>,
+) -> Coordinates2 {
+	let mut parts = Vec::new();
+	let mut part: Coordinates1 = Vec::new();
+
+	for segment in line.windows(2) {
+		if let Some((a, b)) = clip_segment(segment[0], segment[1]) {
+			if part.last() != Some(&a) {
+				if part.len() >= 2 {
+					parts.push(part);
+				}
+				part = vec![a];
+			}
+			part.push(b);
+			if b != segment[1] {
+				// segment leaves the clip area
+				parts.push(part);
+				part = Vec::new();
+			}
+		}
+	}
+
+	if part.len() >= 2 {
+		parts.push(part);
+	}
+	parts
+}
+
+/// Clips a closed ring to the rectangle `[x_min, y_min, x_max, y_max]` using the Sutherland–Hodgman algorithm.
+///
+/// The result is closed again. It is empty if fewer than 4 points remain.
+pub fn clip_ring(ring: &Coordinates1, bbox: &[f64; 4]) -> Coordinates1 {
+	let mut points: Coordinates1 = ring.clone();
+	if points.first() == points.last() {
+		points.pop();
+	}
+
+	// (axis, limit, keep values below the limit)
+	let edges = [
+		(0, bbox[0], false),
+		(0, bbox[2], true),
+		(1, bbox[1], false),
+		(1, bbox[3], true),
+	];
+	for (axis, limit, below) in edges {
+		if points.is_empty() {
+			break;
+		}
+		let inside = |p: &Coordinates0| if below { p[axis] <= limit } else { p[axis] >= limit };
+		let intersect = |a: &Coordinates0, b: &Coordinates0| {
+			let t = (limit - a[axis]) / (b[axis] - a[axis]);
+			let mut p = [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+			p[axis] = limit;
+			p
+		};
+
+		let mut output = Vec::with_capacity(points.len() + 4);
+		let mut prev = *points.last().unwrap();
+		for p in points.iter() {
+			match (inside(p), inside(&prev)) {
+				(true, true) => output.push(*p),
+				(true, false) => {
+					output.push(intersect(&prev, p));
+					output.push(*p);
+				}
+				(false, true) => output.push(intersect(&prev, p)),
+				(false, false) => (),
+			}
+			prev = *p;
+		}
+		points = output;
+	}
+
+	points.dedup();
+	if points.len() < 3 {
+		return Vec::new();
+	}
+	points.push(points[0]);
+	points
+}
+
+/// Clips a closed ring to a convex polygon, given as closed ring in any orientation, using the Sutherland–Hodgman algorithm.
+///
+/// The result is closed again. It is empty if fewer than 4 points remain.
+pub fn clip_ring_convex(ring: &Coordinates1, clip: &Coordinates1) -> Coordinates1 {
+	let mut points: Coordinates1 = ring.clone();
+	if points.first() == points.last() {
+		points.pop();
+	}
+
+	let sign = ring_orientation(clip);
+	for edge in clip.windows(2) {
+		if points.is_empty() {
+			break;
+		}
+		let (c0, c1) = (&edge[0], &edge[1]);
+		if c0 == c1 {
+			continue;
+		}
+		let side = |p: &Coordinates0| orientation(c0, c1, p) * sign;
+		let intersect = |a: &Coordinates0, b: &Coordinates0| {
+			let (sa, sb) = (side(a), side(b));
+			let t = sa / (sa - sb);
+			[a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+		};
+
+		let mut output = Vec::with_capacity(points.len() + 4);
+		let mut prev = *points.last().unwrap();
+		for p in points.iter() {
+			match (side(p) >= 0.0, side(&prev) >= 0.0) {
+				(true, true) => output.push(*p),
+				(true, false) => {
+					output.push(intersect(&prev, p));
+					output.push(*p);
+				}
+				(false, true) => output.push(intersect(&prev, p)),
+				(false, false) => (),
+			}
+			prev = *p;
+		}
+		points = output;
+	}
+
+	points.dedup();
+	if points.len() < 3 {
+		return Vec::new();
+	}
+	points.push(points[0]);
+	points
+}
+
+/// Clips a geometry to the rectangle `[x_min, y_min, x_max, y_max]`.
+///
+/// The result is always a multi geometry, or `None` if nothing remains. Polygon rings are reoriented,
+/// so that outer rings are clockwise and holes counter-clockwise, as required by vector tiles.
+pub fn clip_geometry(geometry: &Geometry, bbox: &[f64; 4]) -> Option<Geometry> {
+	clip_multi_geometry(
+		geometry,
+		|p| p[0] >= bbox[0] && p[0] <= bbox[2] && p[1] >= bbox[1] && p[1] <= bbox[3],
+		|line| clip_line_string(line, bbox),
+		|ring| clip_ring(ring, bbox),
+	)
+}
+
+/// Clips a geometry to a convex polygon, given as closed ring in any orientation.
+///
+/// Like [`clip_geometry`], the result is always a multi geometry with reoriented polygon rings, or `None` if nothing remains.
+pub fn clip_geometry_convex(geometry: &Geometry, clip: &Coordinates1) -> Option<Geometry> {
+	clip_multi_geometry(
+		geometry,
+		|p| locate_point_in_ring(clip, p) != PointLocation::Outside,
+		|line| clip_line_string_convex(line, clip),
+		|ring| clip_ring_convex(ring, clip),
+	)
+}
+
+fn clip_multi_geometry(
+	geometry: &Geometry,
+	contains_point: impl Fn(&Coordinates0) -> bool,
+	clip_line_string: impl Fn(&Coordinates1) -> Coordinates2,
+	clip_ring: impl Fn(&Coordinates1) -> Coordinates1,
+) -> Option<Geometry> {
+	match geometry.clone().into_multi() {
+		Geometry::MultiPoint(g) => {
+			let points: Coordinates1 = g.0.into_iter().filter(|p| contains_point(p)).collect();
+			(!points.is_empty()).then(|| Geometry::new_multi_point(points))
+		}
+		Geometry::MultiLineString(g) => {
+			let lines: Coordinates2 = g.0.iter().flat_map(&clip_line_string).collect();
+			(!lines.is_empty()).then(|| Geometry::new_multi_line_string(lines))
+		}
+		Geometry::MultiPolygon(g) => {
+			let mut polygons = Vec::new();
+			for polygon in g.0.iter() {
+				let mut rings: Coordinates2 = Vec::new();
+				for (index, ring) in polygon.iter().enumerate() {
+					let mut ring = clip_ring(ring);
+					if ring.is_empty() {
+						if index == 0 {
+							break;
+						}
+						continue;
+					}
+					if (area_ring(&ring) > 0.0) != (index == 0) {
+						ring.reverse();
+					}
+					rings.push(ring);
+				}
+				if !rings.is_empty() {
+					polygons.push(rings);
+				}
+			}
+			(!polygons.is_empty()).then(|| Geometry::new_multi_polygon(polygons))
+		}
+		_ => unreachable!("geometries are converted to multi geometries"),
+	}
+}
+
+/// Liang–Barsky line clipping of a single segment.
+pub(super) fn clip_segment(a: Coordinates0, b: Coordinates0, bbox: &[f64; 4]) -> Option<(Coordinates0, Coordinates0)> {
+	let d = [b[0] - a[0], b[1] - a[1]];
+	let (mut t0, mut t1) = (0.0f64, 1.0f64);
+
+	for (p, q) in [
+		(-d[0], a[0] - bbox[0]),
+		(d[0], bbox[2] - a[0]),
+		(-d[1], a[1] - bbox[1]),
+		(d[1], bbox[3] - a[1]),
+	] {
+		if p == 0.0 {
+			if q < 0.0 {
+				return None;
+			}
+		} else {
+			let r = q / p;
+			if p < 0.0 {
+				t0 = t0.max(r);
+			} else {
+				t1 = t1.min(r);
+			}
+		}
+	}
+
+	if t0 > t1 {
+		return None;
+	}
+
+	let at = |t: f64| {
+		if t == 0.0 {
+			a
+		} else if t == 1.0 {
+			b
+		} else {
+			[a[0] + d[0] * t, a[1] + d[1] * t]
+		}
+	};
+	Some((at(t0), at(t1)))
+}
+
+/// Cyrus–Beck line clipping of a single segment to a convex polygon. `sign` is the orientation of the polygon.
+fn clip_segment_convex(
+	a: Coordinates0,
+	b: Coordinates0,
+	clip: &Coordinates1,
+	sign: f64,
+) -> Option<(Coordinates0, Coordinates0)> {
+	let (mut t0, mut t1) = (0.0f64, 1.0f64);
+
+	for edge in clip.windows(2) {
+		if edge[0] == edge[1] {
+			continue;
+		}
+		// signed distances of a and b to the edge, positive inside
+		let sa = orientation(&edge[0], &edge[1], &a) * sign;
+		let sb = orientation(&edge[0], &edge[1], &b) * sign;
+		if sa == sb {
+			if sa < 0.0 {
+				return None;
+			}
+		} else {
+			let r = sa / (sa - sb);
+			if sb < sa {
+				t1 = t1.min(r);
+			} else {
+				t0 = t0.max(r);
+			}
+		}
+	}
+
+	if t0 > t1 {
+		return None;
+	}
+
+	let at = |t: f64| {
+		if t == 0.0 {
+			a
+		} else if t == 1.0 {
+			b
+		} else {
+			[a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+		}
+	};
+	Some((at(t0), at(t1)))
+}
+
+/// Returns 1 for counter-clockwise rings (in a y-up coordinate system) and -1 for clockwise rings,
+/// so that multiplied with [`orientation`] the inside of the ring is positive.
+fn ring_orientation(ring: &Coordinates1) -> f64 {
+	let sum: f64 = ring.windows(2).map(|w| w[0][0] * w[1][1] - w[1][0] * w[0][1]).sum();
+	if sum < 0.0 {
+		-1.0
+	} else {
+		1.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const BBOX: [f64; 4] = [0.0, 0.0, 10.0, 10.0];
+
+	#[test]
+	fn line_inside() {
+		let line = vec![[1.0, 1.0], [5.0, 5.0], [9.0, 1.0]];
+		assert_eq!(clip_line_string(&line, &BBOX), vec![line]);
+	}
+
+	#[test]
+	fn line_crossing() {
+		let line = vec![[-5.0, 5.0], [5.0, 5.0], [5.0, 15.0]];
+		assert_eq!(
+			clip_line_string(&line, &BBOX),
+			vec![vec![[0.0, 5.0], [5.0, 5.0], [5.0, 10.0]]]
+		);
+	}
+
+	#[test]
+	fn line_reentering() {
+		let line = vec![[2.0, 5.0], [2.0, 15.0], [8.0, 15.0], [8.0, 5.0]];
+		assert_eq!(
+			clip_line_string(&line, &BBOX),
+			vec![vec![[2.0, 5.0], [2.0, 10.0]], vec![[8.0, 10.0], [8.0, 5.0]]]
+		);
+	}
+
+	#[test]
+	fn line_outside() {
+		let line = vec![[-5.0, -5.0], [-1.0, 20.0]];
+		assert!(clip_line_string(&line, &BBOX).is_empty());
+	}
+
+	#[test]
+	fn ring_overlapping() {
+		let ring = vec![[5.0, 5.0], [15.0, 5.0], [15.0, 15.0], [5.0, 15.0], [5.0, 5.0]];
+		assert_eq!(
+			clip_ring(&ring, &BBOX),
+			vec![[5.0, 10.0], [5.0, 5.0], [10.0, 5.0], [10.0, 10.0], [5.0, 10.0]]
+		);
+	}
+
+	#[test]
+	fn geometry() {
+		let points = Geometry::new_multi_point(vec![[5.0, 5.0], [15.0, 5.0]]);
+		assert_eq!(
+			clip_geometry(&points, &BBOX),
+			Some(Geometry::new_multi_point(vec![[5.0, 5.0]]))
+		);
+
+		let polygon = Geometry::new_polygon(vec![vec![
+			[5.0, 5.0],
+			[5.0, 15.0],
+			[15.0, 15.0],
+			[15.0, 5.0],
+			[5.0, 5.0],
+		]]);
+		let Some(Geometry::MultiPolygon(clipped)) = clip_geometry(&polygon, &BBOX) else {
+			panic!("expected a multi polygon")
+		};
+		assert!(area_ring(&clipped.0[0][0]) > 0.0);
+
+		assert_eq!(
+			clip_geometry(&Geometry::new_line_string(vec![[-5.0, -5.0], [-1.0, 20.0]]), &BBOX),
+			None
+		);
+	}
+
+	#[test]
+	fn ring_outside() {
+		let ring = vec![[15.0, 5.0], [25.0, 5.0], [25.0, 15.0], [15.0, 5.0]];
+		assert!(clip_ring(&ring, &BBOX).is_empty());
+	}
+
+	// a diamond around (5,5), in clockwise order
+	fn diamond() -> Coordinates1 {
+		vec![[5.0, 0.0], [0.0, 5.0], [5.0, 10.0], [10.0, 5.0], [5.0, 0.0]]
+	}
+
+	#[test]
+	fn line_convex() {
+		let line = vec![[-5.0, 5.0], [15.0, 5.0]];
+		assert_eq!(
+			clip_line_string_convex(&line, &diamond()),
+			vec![vec![[0.0, 5.0], [10.0, 5.0]]]
+		);
+
+		let line = vec![[1.0, 5.0], [5.0, 5.0], [5.0, 15.0], [6.0, 15.0], [6.0, 5.0]];
+		assert_eq!(
+			clip_line_string_convex(&line, &diamond()),
+			vec![vec![[1.0, 5.0], [5.0, 5.0], [5.0, 10.0]], vec![[6.0, 9.0], [6.0, 5.0]]]
+		);
+
+		let line = vec![[0.0, 0.0], [1.0, 1.0]];
+		assert!(clip_line_string_convex(&line, &diamond()).is_empty());
+
+		// same results in both orientations
+		let mut reversed = diamond();
+		reversed.reverse();
+		let line = vec![[-5.0, 5.0], [15.0, 5.0]];
+		assert_eq!(
+			clip_line_string_convex(&line, &reversed),
+			clip_line_string_convex(&line, &diamond())
+		);
+	}
+
+	#[test]
+	fn ring_convex() {
+		let ring = vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]];
+		let clipped = clip_ring_convex(&ring, &diamond());
+		assert_eq!(clipped.len(), 5);
+		assert_eq!(area_ring(&clipped).abs(), 100.0);
+
+		// the rectangle special case gives the same result
+		let ring = vec![[5.0, 5.0], [15.0, 5.0], [15.0, 15.0], [5.0, 15.0], [5.0, 5.0]];
+		let square = vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]];
+		let clipped = clip_ring_convex(&ring, &square);
+		assert_eq!(area_ring(&clipped), area_ring(&clip_ring(&ring, &BBOX)));
+
+		let ring = vec![[15.0, 5.0], [25.0, 5.0], [25.0, 15.0], [15.0, 5.0]];
+		assert!(clip_ring_convex(&ring, &diamond()).is_empty());
+	}
+
+	#[test]
+	fn geometry_convex() {
+		let points = Geometry::new_multi_point(vec![[5.0, 5.0], [1.0, 1.0], [10.0, 5.0]]);
+		assert_eq!(
+			clip_geometry_convex(&points, &diamond()),
+			Some(Geometry::new_multi_point(vec![[5.0, 5.0], [10.0, 5.0]]))
+		);
+
+		let polygon = Geometry::new_polygon(vec![
+			vec![[0.0, 0.0], [0.0, 10.0], [10.0, 10.0], [10.0, 0.0], [0.0, 0.0]],
+			vec![[4.0, 4.0], [6.0, 4.0], [6.0, 6.0], [4.0, 6.0], [4.0, 4.0]],
+		]);
+		let Some(Geometry::MultiPolygon(clipped)) = clip_geometry_convex(&polygon, &diamond()) else {
+			panic!("expected a multi polygon")
+		};
+		assert_eq!(clipped.0[0].len(), 2);
+		assert!(area_ring(&clipped.0[0][0]) > 0.0);
+		assert!(area_ring(&clipped.0[0][1]) < 0.0);
+	}
+}
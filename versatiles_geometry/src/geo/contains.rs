@@ -0,0 +1,208 @@
+//! Point-in-polygon tests.
+//!
+//! The winding number is calculated with orientation tests instead of ray intersections, so points on vertices
+//! and horizontal edges are handled consistently and points on the boundary are detected. For integer coordinates
+//! below 2^25, like vector tile coordinates, the orientation test is exact.
+
+use super::*;
+
+/// Location of a point relative to a ring or polygon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointLocation {
+	Inside,
+	Boundary,
+	Outside,
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`. Positive if `p` lies left of the line from `a` to `b`,
+/// negative if it lies right of it and zero if the three points are collinear.
+pub fn orientation(a: &Coordinates0, b: &Coordinates0, p: &Coordinates0) -> f64 {
+	(b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Locates a point relative to a ring. The ring may be open or closed and have any orientation.
+pub fn locate_point_in_ring(ring: &Coordinates1, point: &Coordinates0) -> PointLocation {
+	if ring.len() < 3 {
+		return PointLocation::Outside;
+	}
+
+	let mut winding = 0i32;
+	let mut a = ring.last().unwrap();
+	for b in ring.iter() {
+		let o = orientation(a, b, point);
+		if o == 0.0
+			&& a[0].min(b[0]) <= point[0]
+			&& point[0] <= a[0].max(b[0])
+			&& a[1].min(b[1]) <= point[1]
+			&& point[1] <= a[1].max(b[1])
+		{
+			return PointLocation::Boundary;
+		}
+		if a[1] <= point[1] {
+			if b[1] > point[1] && o > 0.0 {
+				winding += 1;
+			}
+		} else if b[1] <= point[1] && o < 0.0 {
+			winding -= 1;
+		}
+		a = b;
+	}
+
+	if winding == 0 {
+		PointLocation::Outside
+	} else {
+		PointLocation::Inside
+	}
+}
+
+/// Locates a point relative to a polygon, whose first ring is the outer ring and all others are holes.
+pub fn locate_point_in_polygon(polygon: &Coordinates2, point: &Coordinates0) -> PointLocation {
+	let Some(outer) = polygon.first() else {
+		return PointLocation::Outside;
+	};
+	match locate_point_in_ring(outer, point) {
+		PointLocation::Inside => {}
+		location => return location,
+	}
+	for hole in polygon.iter().skip(1) {
+		match locate_point_in_ring(hole, point) {
+			PointLocation::Inside => return PointLocation::Outside,
+			PointLocation::Boundary => return PointLocation::Boundary,
+			PointLocation::Outside => {}
+		}
+	}
+	PointLocation::Inside
+}
+
+/// Locates a point relative to a multi polygon. A point on the boundary of one polygon and inside another is inside.
+pub fn locate_point_in_multi_polygon(multi_polygon: &Coordinates3, point: &Coordinates0) -> PointLocation {
+	let mut result = PointLocation::Outside;
+	for polygon in multi_polygon {
+		match locate_point_in_polygon(polygon, point) {
+			PointLocation::Inside => return PointLocation::Inside,
+			PointLocation::Boundary => result = PointLocation::Boundary,
+			PointLocation::Outside => {}
+		}
+	}
+	result
+}
+
+impl PolygonGeometry {
+	/// Returns `true` if the point lies inside the polygon or on its boundary.
+	pub fn contains_point(&self, point: &Coordinates0) -> bool {
+		locate_point_in_polygon(&self.0, point) != PointLocation::Outside
+	}
+}
+
+impl MultiPolygonGeometry {
+	/// Returns `true` if the point lies inside one of the polygons or on its boundary.
+	pub fn contains_point(&self, point: &Coordinates0) -> bool {
+		locate_point_in_multi_polygon(&self.0, point) != PointLocation::Outside
+	}
+}
+
+impl Geometry {
+	/// Returns `true` if the point lies inside the (multi) polygon or on its boundary.
+	/// Points and lines contain no area, so `false` is returned for them.
+	pub fn contains_point(&self, point: &Coordinates0) -> bool {
+		match self {
+			Geometry::Polygon(g) => g.contains_point(point),
+			Geometry::MultiPolygon(g) => g.contains_point(point),
+			_ => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use PointLocation::*;
+
+	fn square(x0: f64, y0: f64, x1: f64, y1: f64) -> Coordinates1 {
+		vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1], [x0, y0]]
+	}
+
+	#[test]
+	fn test_orientation() {
+		assert_eq!(orientation(&[0.0, 0.0], &[1.0, 0.0], &[0.0, 1.0]), 1.0);
+		assert_eq!(orientation(&[0.0, 0.0], &[1.0, 0.0], &[0.0, -1.0]), -1.0);
+		assert_eq!(orientation(&[0.0, 0.0], &[1.0, 1.0], &[5.0, 5.0]), 0.0);
+	}
+
+	#[test]
+	fn test_ring() {
+		let ring = square(0.0, 0.0, 4.0, 4.0);
+		assert_eq!(locate_point_in_ring(&ring, &[2.0, 2.0]), Inside);
+		assert_eq!(locate_point_in_ring(&ring, &[5.0, 2.0]), Outside);
+		assert_eq!(locate_point_in_ring(&ring, &[4.0, 2.0]), Boundary);
+		assert_eq!(locate_point_in_ring(&ring, &[2.0, 0.0]), Boundary);
+		assert_eq!(locate_point_in_ring(&ring, &[4.0, 4.0]), Boundary);
+		// on the extension of an edge
+		assert_eq!(locate_point_in_ring(&ring, &[6.0, 0.0]), Outside);
+		assert_eq!(locate_point_in_ring(&ring, &[-2.0, 4.0]), Outside);
+
+		// open ring in the other orientation
+		let mut ring = ring;
+		ring.pop();
+		ring.reverse();
+		assert_eq!(locate_point_in_ring(&ring, &[2.0, 2.0]), Inside);
+		assert_eq!(locate_point_in_ring(&ring, &[-1.0, 2.0]), Outside);
+
+		assert_eq!(
+			locate_point_in_ring(&vec![[0.0, 0.0], [1.0, 1.0]], &[0.0, 0.0]),
+			Outside
+		);
+	}
+
+	#[test]
+	fn test_ray_through_vertices() {
+		// the horizontal ray from the points touches the vertices (2,2) and (6,2) and passes through (10,2)
+		let ring = vec![
+			[0.0, 0.0],
+			[2.0, 2.0],
+			[4.0, 0.0],
+			[6.0, 2.0],
+			[8.0, 0.0],
+			[10.0, 2.0],
+			[8.0, 4.0],
+			[0.0, 4.0],
+			[0.0, 0.0],
+		];
+		assert_eq!(locate_point_in_ring(&ring, &[-1.0, 2.0]), Outside);
+		assert_eq!(locate_point_in_ring(&ring, &[1.0, 2.0]), Inside);
+		assert_eq!(locate_point_in_ring(&ring, &[4.0, 2.0]), Inside);
+		assert_eq!(locate_point_in_ring(&ring, &[9.0, 2.0]), Inside);
+		assert_eq!(locate_point_in_ring(&ring, &[11.0, 2.0]), Outside);
+		assert_eq!(locate_point_in_ring(&ring, &[6.0, 2.0]), Boundary);
+		assert_eq!(locate_point_in_ring(&ring, &[4.0, 4.0]), Boundary);
+	}
+
+	#[test]
+	fn test_polygon_with_hole() {
+		let polygon = PolygonGeometry(vec![square(0.0, 0.0, 10.0, 10.0), square(3.0, 3.0, 6.0, 6.0)]);
+		assert_eq!(locate_point_in_polygon(&polygon.0, &[1.0, 1.0]), Inside);
+		assert_eq!(locate_point_in_polygon(&polygon.0, &[4.0, 4.0]), Outside);
+		assert_eq!(locate_point_in_polygon(&polygon.0, &[3.0, 4.0]), Boundary);
+		assert_eq!(locate_point_in_polygon(&vec![], &[3.0, 4.0]), Outside);
+		assert!(polygon.contains_point(&[1.0, 1.0]));
+		assert!(polygon.contains_point(&[3.0, 4.0]));
+		assert!(!polygon.contains_point(&[4.0, 4.0]));
+	}
+
+	#[test]
+	fn test_multi_polygon() {
+		let multi_polygon = MultiPolygonGeometry(vec![
+			vec![square(0.0, 0.0, 2.0, 2.0)],
+			vec![square(2.0, 0.0, 4.0, 2.0)],
+			vec![square(10.0, 0.0, 12.0, 2.0)],
+		]);
+		assert_eq!(locate_point_in_multi_polygon(&multi_polygon.0, &[11.0, 1.0]), Inside);
+		assert_eq!(locate_point_in_multi_polygon(&multi_polygon.0, &[2.0, 1.0]), Boundary);
+		assert_eq!(locate_point_in_multi_polygon(&multi_polygon.0, &[6.0, 1.0]), Outside);
+
+		let geometry = Geometry::MultiPolygon(multi_polygon);
+		assert!(geometry.contains_point(&[1.0, 1.0]));
+		assert!(!geometry.contains_point(&[6.0, 1.0]));
+		assert!(!Geometry::new_point([1.0, 1.0]).contains_point(&[1.0, 1.0]));
+	}
+}
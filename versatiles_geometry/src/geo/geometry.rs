@@ -54,6 +54,18 @@ impl Geometry {
 		}
 	}
 
+	/// Applies `f` to every coordinate of the geometry.
+	pub fn transform(&mut self, f: impl Fn(&mut [f64; 2])) {
+		match self {
+			Geometry::Point(g) => f(&mut g.0),
+			Geometry::LineString(g) => g.0.iter_mut().for_each(f),
+			Geometry::MultiPoint(g) => g.0.iter_mut().for_each(f),
+			Geometry::Polygon(g) => g.0.iter_mut().flatten().for_each(f),
+			Geometry::MultiLineString(g) => g.0.iter_mut().flatten().for_each(f),
+			Geometry::MultiPolygon(g) => g.0.iter_mut().flatten().flatten().for_each(f),
+		}
+	}
+
 	pub fn new_example() -> Self {
 		Self::new_multi_polygon(vec![
 			vec![
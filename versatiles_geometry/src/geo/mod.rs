@@ -1,15 +1,21 @@
 #![allow(clippy::module_inception)]
 
+mod clip;
 mod collection;
+mod contains;
 mod feature;
 mod geometry;
+mod polygon_filter;
 mod properties;
 mod types;
 mod value;
 
+pub use clip::*;
 pub use collection::*;
+pub use contains::*;
 pub use feature::*;
 pub use geometry::*;
+pub use polygon_filter::*;
 pub use properties::*;
 pub use types::*;
 pub use value::*;
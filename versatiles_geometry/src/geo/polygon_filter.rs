@@ -0,0 +1,244 @@
+//! Selection of tiles by polygons, e.g. the outline of a country, instead of a rectangular bounding box.
+//!
+//! Tiles are compared with the polygons in longitude and latitude. A tile is selected if its interior overlaps the
+//! interior of a polygon, so tiles touching a polygon only at their border are not selected.
+//!
+//! Tiles whose parent lies completely inside or outside the polygons are decided by the parent, so only tiles
+//! along the boundary of the polygons are compared with their edges.
+
+use super::*;
+use crate::geojson::read_geojson;
+use anyhow::{bail, Context, Result};
+use std::{collections::HashMap, fmt::Debug, fs::File, io::BufReader, path::Path, sync::Mutex};
+use versatiles_core::types::{GeoBBox, TileCoord3};
+
+/// Relation of a tile to the polygons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Relation {
+	Inside,
+	Boundary,
+	Outside,
+}
+
+/// Selects the tiles overlapping a multi polygon.
+pub struct PolygonFilter {
+	polygons: Vec<(Coordinates2, [f64; 4])>,
+	bbox: [f64; 4],
+	cache: Mutex<HashMap<TileCoord3, Relation>>,
+}
+
+impl PolygonFilter {
+	/// Creates a filter from (multi) polygons. Other geometries are ignored.
+	pub fn new(geometries: Vec<Geometry>) -> Result<PolygonFilter> {
+		let mut polygons = Vec::new();
+		for geometry in geometries {
+			match geometry {
+				Geometry::Polygon(g) => polygons.push(g.0),
+				Geometry::MultiPolygon(g) => polygons.extend(g.0),
+				_ => {}
+			}
+		}
+		polygons.retain(|polygon| polygon.first().is_some_and(|ring| ring.len() >= 3));
+		if polygons.is_empty() {
+			bail!("no polygons found");
+		}
+
+		let polygons: Vec<(Coordinates2, [f64; 4])> = polygons
+			.into_iter()
+			.map(|polygon| {
+				let bbox = ring_bbox(&polygon[0]);
+				(polygon, bbox)
+			})
+			.collect();
+		let bbox = polygons
+			.iter()
+			.map(|(_, bbox)| *bbox)
+			.reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+			.unwrap();
+
+		Ok(PolygonFilter {
+			polygons,
+			bbox,
+			cache: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Creates a filter from all polygons of a GeoJSON feature collection.
+	pub fn from_geojson_file(path: &Path) -> Result<PolygonFilter> {
+		let file = File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+		let collection = read_geojson(BufReader::new(file)).with_context(|| format!("Failed to parse {path:?}"))?;
+		PolygonFilter::new(collection.features.into_iter().map(|f| f.geometry).collect())
+			.with_context(|| format!("Failed to read polygons from {path:?}"))
+	}
+
+	/// Returns the bounding box of all polygons, e.g. to skip most tiles before testing them.
+	pub fn get_geo_bbox(&self) -> GeoBBox {
+		GeoBBox(self.bbox[0], self.bbox[1], self.bbox[2], self.bbox[3])
+	}
+
+	/// Returns `true` if the tile overlaps one of the polygons.
+	pub fn contains_coord(&self, coord: &TileCoord3) -> bool {
+		self.get_relation(coord) != Relation::Outside
+	}
+
+	fn get_relation(&self, coord: &TileCoord3) -> Relation {
+		if let Some(relation) = self.cache.lock().unwrap().get(coord) {
+			return *relation;
+		}
+
+		let relation = if coord.z == 0 {
+			self.calc_relation(coord)
+		} else {
+			let parent = TileCoord3 {
+				x: coord.x / 2,
+				y: coord.y / 2,
+				z: coord.z - 1,
+			};
+			match self.get_relation(&parent) {
+				Relation::Boundary => self.calc_relation(coord),
+				relation => return relation,
+			}
+		};
+
+		// only tiles whose parent lies on the boundary are cached, all others are decided by an ancestor
+		self.cache.lock().unwrap().insert(*coord, relation);
+		relation
+	}
+
+	fn calc_relation(&self, coord: &TileCoord3) -> Relation {
+		let [west, north] = coord.pixel_to_lonlat([0.0, 0.0], 1.0);
+		let [east, south] = coord.pixel_to_lonlat([1.0, 1.0], 1.0);
+		let rect = [west, south, east, north];
+		if !overlaps(&rect, &self.bbox) {
+			return Relation::Outside;
+		}
+
+		let polygons: Vec<&Coordinates2> = self
+			.polygons
+			.iter()
+			.filter(|(_, bbox)| overlaps(&rect, bbox))
+			.map(|(polygon, _)| polygon)
+			.collect();
+
+		// an edge through the interior of the tile means that the tile is partly inside
+		for polygon in polygons.iter() {
+			for ring in polygon.iter() {
+				if ring_crosses_rect(ring, &rect) {
+					return Relation::Boundary;
+				}
+			}
+		}
+
+		// otherwise the tile is completely inside or outside, like its center
+		let center = [(west + east) / 2.0, (south + north) / 2.0];
+		if polygons
+			.iter()
+			.any(|polygon| locate_point_in_polygon(polygon, &center) == PointLocation::Inside)
+		{
+			Relation::Inside
+		} else {
+			Relation::Outside
+		}
+	}
+}
+
+impl Debug for PolygonFilter {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("PolygonFilter")
+			.field("polygons", &self.polygons.len())
+			.field("bbox", &self.bbox)
+			.finish()
+	}
+}
+
+fn ring_bbox(ring: &Coordinates1) -> [f64; 4] {
+	ring.iter().fold(
+		[f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+		|b, p| [b[0].min(p[0]), b[1].min(p[1]), b[2].max(p[0]), b[3].max(p[1])],
+	)
+}
+
+fn overlaps(a: &[f64; 4], b: &[f64; 4]) -> bool {
+	a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+/// Checks if an edge of the ring passes through the interior of the rectangle, not only along its border.
+fn ring_crosses_rect(ring: &Coordinates1, rect: &[f64; 4]) -> bool {
+	let Some(mut a) = ring.last() else {
+		return false;
+	};
+	for b in ring.iter() {
+		if let Some((p, q)) = clip_segment(*a, *b, rect) {
+			// the clipped segment lies inside the rectangle, so its midpoint is inside, unless it runs along the border
+			let mid = [(p[0] + q[0]) / 2.0, (p[1] + q[1]) / 2.0];
+			if p != q && rect[0] < mid[0] && mid[0] < rect[2] && rect[1] < mid[1] && mid[1] < rect[3] {
+				return true;
+			}
+		}
+		a = b;
+	}
+	false
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn square(x0: f64, y0: f64, x1: f64, y1: f64) -> Coordinates1 {
+		vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1], [x0, y0]]
+	}
+
+	fn coord(x: u32, y: u32, z: u8) -> TileCoord3 {
+		TileCoord3::new(x, y, z).unwrap()
+	}
+
+	#[test]
+	fn contains_coord() -> Result<()> {
+		// a square with a hole, covering the tiles 2..=5 of zoom level 3 in both directions
+		let filter = PolygonFilter::new(vec![Geometry::new_polygon(vec![
+			square(-90.0, -66.5, 90.0, 66.5),
+			square(-40.0, -30.0, 40.0, 30.0),
+		])])?;
+
+		assert!(filter.contains_coord(&coord(0, 0, 0)));
+		assert!(filter.contains_coord(&coord(2, 2, 3)));
+		assert!(filter.contains_coord(&coord(5, 5, 3)));
+		assert!(!filter.contains_coord(&coord(1, 1, 3)));
+		assert!(!filter.contains_coord(&coord(6, 3, 3)));
+
+		// inside of the hole
+		assert!(!filter.contains_coord(&coord(31, 31, 6)));
+		assert!(filter.contains_coord(&coord(18, 18, 6)));
+
+		// deeper tiles are decided by their parents
+		assert!(filter.contains_coord(&coord(2 << 10, 3 << 10, 13)));
+		assert!(!filter.contains_coord(&coord(1 << 10, 3 << 10, 13)));
+		assert_eq!(filter.get_geo_bbox().as_array(), [-90.0, -66.5, 90.0, 66.5]);
+		Ok(())
+	}
+
+	#[test]
+	fn touching_tiles() -> Result<()> {
+		// the polygon touches the tile 2/1/1 only at its bottom right corner
+		let filter = PolygonFilter::new(vec![Geometry::new_polygon(vec![vec![
+			[0.0, 0.0],
+			[45.0, 0.0],
+			[0.0, -40.0],
+			[0.0, 0.0],
+		]])])?;
+		assert!(!filter.contains_coord(&coord(1, 1, 2)));
+		assert!(filter.contains_coord(&coord(2, 2, 2)));
+		assert!(!filter.contains_coord(&coord(1, 2, 2)));
+		Ok(())
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(
+			PolygonFilter::new(vec![Geometry::new_point([1.0, 2.0])])
+				.unwrap_err()
+				.to_string(),
+			"no polygons found"
+		);
+	}
+}
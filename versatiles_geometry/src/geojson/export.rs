@@ -0,0 +1,134 @@
+use super::feature_to_json;
+use crate::{vector_tile::VectorTile, GeoFeature};
+use anyhow::{ensure, Context, Result};
+use std::{f64::consts::PI, io::Write};
+use versatiles_core::{
+	json::JsonValue,
+	types::{TileCoord3, TileFormat, TileMap, TilesReaderTrait},
+	utils::decompress,
+};
+
+/// Options for [`export_ndgeojson`]
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+	/// Zoom level to export. Defaults to the highest zoom level of the container.
+	pub zoom: Option<u8>,
+	/// Names of the layers to export. All layers are exported if empty.
+	pub layers: Vec<String>,
+}
+
+/// Decodes all vector tiles of one zoom level and writes their features as newline-delimited GeoJSON.
+///
+/// Coordinates are reprojected to WGS84. Every feature gets the name of its layer as the foreign
+/// member `"layer"`. Features that cross tile borders are written once per tile.
+/// Returns the number of written features.
+pub async fn export_ndgeojson(
+	reader: &dyn TilesReaderTrait,
+	options: &ExportOptions,
+	writer: &mut impl Write,
+) -> Result<u64> {
+	let parameters = reader.get_parameters();
+	ensure!(
+		parameters.tile_format == TileFormat::PBF,
+		"only vector tiles can be exported, but the tile format is {:?}",
+		parameters.tile_format
+	);
+
+	let Some(zoom) = options.zoom.or_else(|| parameters.bbox_pyramid.get_zoom_max()) else {
+		return Ok(0);
+	};
+
+	let mut count = 0;
+	let level_bbox = parameters.bbox_pyramid.get_level_bbox(zoom);
+	for bbox in level_bbox.iter_bbox_grid(256) {
+		let tiles: TileMap<_> = reader
+			.get_bbox_tile_stream(bbox)
+			.await
+			.collect()
+			.await
+			.into_iter()
+			.collect();
+		for (coord, blob) in tiles.into_sorted_vec() {
+			let blob = decompress(blob, &parameters.tile_compression)?;
+			let tile = VectorTile::from_blob(&blob).with_context(|| format!("Failed to decode tile {coord:?}"))?;
+			for (layer_name, feature) in tile_to_wgs84_features(&tile, &coord, &options.layers)? {
+				let mut object = feature_to_json(&feature);
+				object.set("layer", JsonValue::from(layer_name));
+				writeln!(writer, "{}", object.stringify())?;
+				count += 1;
+			}
+		}
+	}
+	writer.flush()?;
+
+	Ok(count)
+}
+
+/// Decodes the features of a vector tile and reprojects them from tile pixels to WGS84.
+/// Returns the layer name and feature. Only the given `layers` are decoded, or all layers if empty.
+pub fn tile_to_wgs84_features(
+	tile: &VectorTile,
+	coord: &TileCoord3,
+	layers: &[String],
+) -> Result<Vec<(String, GeoFeature)>> {
+	let mut result = Vec::new();
+	for layer in tile.layers.iter() {
+		if !layers.is_empty() && !layers.contains(&layer.name) {
+			continue;
+		}
+
+		let size = 2.0f64.powi(coord.z as i32);
+		let extent = layer.extent as f64;
+		for mut feature in layer.to_features()? {
+			feature.geometry.transform(|p| {
+				let x = (coord.x as f64 + p[0] / extent) / size;
+				let y = (coord.y as f64 + p[1] / extent) / size;
+				p[0] = x * 360.0 - 180.0;
+				p[1] = (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees();
+			});
+			result.push((layer.name.clone(), feature));
+		}
+	}
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{vector_tile::VectorTileLayer, Geometry};
+	use versatiles_core::types::Blob;
+
+	#[test]
+	fn test_tile_to_wgs84_features() -> Result<()> {
+		let features = vec![GeoFeature::new(Geometry::new_line_string(vec![[0, 0], [4096, 4096]]))];
+		let layer = VectorTileLayer::from_features(String::from("roads"), features, 4096, 2)?;
+		let tile = VectorTile::new(vec![layer]);
+
+		let features = tile_to_wgs84_features(&tile, &TileCoord3::new(1, 1, 1)?, &[])?;
+		assert_eq!(features.len(), 1);
+		assert_eq!(features[0].0, "roads");
+		let Geometry::MultiLineString(line) = &features[0].1.geometry else {
+			panic!("expected a line");
+		};
+		let line = &line.0[0];
+		assert_eq!(line[0][0], 0.0);
+		assert!(line[0][1].abs() < 1e-9);
+		assert_eq!(line[1][0], 180.0);
+		assert!((line[1][1] + 85.0511288).abs() < 1e-7);
+
+		assert!(tile_to_wgs84_features(&tile, &TileCoord3::new(1, 1, 1)?, &[String::from("water")])?.is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn test_layer_filter() -> Result<()> {
+		let tile = VectorTile::from_blob(&Blob::from(std::fs::read("../testdata/shortbread-tile.pbf")?))?;
+		let coord = TileCoord3::new(8800, 5373, 14)?;
+		let all = tile_to_wgs84_features(&tile, &coord, &[])?;
+		let streets = tile_to_wgs84_features(&tile, &coord, &[String::from("streets")])?;
+		assert!(!streets.is_empty());
+		assert!(streets.len() < all.len());
+		assert!(streets.iter().all(|(name, _)| name == "streets"));
+		Ok(())
+	}
+}
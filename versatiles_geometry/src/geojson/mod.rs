@@ -1,5 +1,11 @@
+mod export;
+mod outline;
 mod parse;
 mod read;
+mod write;
 
+pub use export::*;
+pub use outline::*;
 pub use parse::*;
 pub use read::*;
+pub use write::*;
@@ -0,0 +1,119 @@
+use super::feature_to_json;
+use crate::{math::dissolve_polygons, Coordinates3, GeoFeature, Geometry};
+use anyhow::Result;
+use versatiles_core::{
+	json::{JsonObject, JsonValue},
+	types::{TileCoord2, TileCoord3, TilesReaderTrait},
+};
+
+/// Returns the area covered by the tiles `coords` of zoom level `z` in WGS84.
+///
+/// Areas without tiles that are enclosed by tiles become holes, so gaps in the data stay visible.
+/// Outer rings are counter-clockwise and holes clockwise, as recommended by RFC 7946.
+pub fn tile_outline(coords: &[TileCoord2], z: u8) -> Coordinates3 {
+	let squares: Coordinates3 = coords
+		.iter()
+		.map(|c| {
+			let (x, y) = (c.x as f64, c.y as f64);
+			vec![vec![[x, y], [x + 1.0, y], [x + 1.0, y + 1.0], [x, y + 1.0], [x, y]]]
+		})
+		.collect();
+
+	// pixels of the first tile with a size of 1 are tile coordinates
+	let origin = TileCoord3 { x: 0, y: 0, z };
+	let mut polygons = dissolve_polygons(&squares);
+	for ring in polygons.iter_mut().flatten() {
+		for p in ring.iter_mut() {
+			*p = origin.pixel_to_lonlat(*p, 1.0);
+		}
+		// the y axis of tiles points down, so projecting flips the orientation
+		ring.reverse();
+	}
+	polygons
+}
+
+/// Returns one feature per zoom level with the outline of its tiles, see [`tile_outline`].
+///
+/// The features have the properties `zoom`, `tiles` (number of tiles) and `holes` (number of holes).
+/// Only the given zoom levels are outlined, or all zoom levels if `zooms` is empty.
+pub async fn tile_outline_features(reader: &dyn TilesReaderTrait, zooms: &[u8]) -> Result<Vec<GeoFeature>> {
+	let mut features = Vec::new();
+	for level_bbox in reader.get_parameters().bbox_pyramid.iter_levels() {
+		if level_bbox.is_empty() || (!zooms.is_empty() && !zooms.contains(&level_bbox.level)) {
+			continue;
+		}
+
+		let mut coords = Vec::new();
+		for bbox in level_bbox.iter_bbox_grid(256) {
+			reader
+				.get_bbox_tile_stream(bbox)
+				.await
+				.for_each_sync(|(coord, _)| coords.push(coord.as_coord2()))
+				.await;
+		}
+		if coords.is_empty() {
+			continue;
+		}
+
+		let outline = tile_outline(&coords, level_bbox.level);
+		let holes = outline.iter().map(|polygon| polygon.len() as u32 - 1).sum::<u32>();
+		let mut feature = GeoFeature::new(Geometry::new_multi_polygon(outline));
+		feature.set_property(String::from("zoom"), level_bbox.level as u32);
+		feature.set_property(String::from("tiles"), coords.len() as u32);
+		feature.set_property(String::from("holes"), holes);
+		features.push(feature);
+	}
+	Ok(features)
+}
+
+/// Returns the features as a GeoJSON `FeatureCollection`.
+pub fn features_to_feature_collection(features: &[GeoFeature]) -> JsonObject {
+	let mut object = JsonObject::default();
+	object.set("type", "FeatureCollection");
+	object.set(
+		"features",
+		features
+			.iter()
+			.map(|feature| JsonValue::Object(feature_to_json(feature)))
+			.collect::<Vec<_>>(),
+	);
+	object
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::area_ring;
+
+	#[test]
+	fn test_tile_outline() {
+		// 3x3 tiles of zoom level 3 without the center tile, and a separate tile
+		let mut coords = Vec::new();
+		for y in 0..3 {
+			for x in 0..3 {
+				if x != 1 || y != 1 {
+					coords.push(TileCoord2::new(x, y));
+				}
+			}
+		}
+		coords.push(TileCoord2::new(5, 5));
+
+		let outline = tile_outline(&coords, 3);
+		assert_eq!(outline.len(), 2);
+		assert_eq!(outline[0].len() + outline[1].len(), 3);
+		let (ring, hole) = if outline[0].len() == 2 {
+			(&outline[0][0], &outline[0][1])
+		} else {
+			(&outline[1][0], &outline[1][1])
+		};
+		assert!(area_ring(ring) > 0.0);
+		assert!(area_ring(hole) < 0.0);
+		for p in ring.iter() {
+			assert!([-180.0, -45.0].contains(&p[0]), "{p:?}");
+		}
+		for p in hole.iter() {
+			assert!([-135.0, -90.0].contains(&p[0]), "{p:?}");
+			assert!((66.5..79.2).contains(&p[1]), "{p:?}");
+		}
+	}
+}
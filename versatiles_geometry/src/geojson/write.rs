@@ -0,0 +1,111 @@
+use crate::{GeoFeature, GeoProperties, GeoValue, Geometry};
+use versatiles_core::json::{JsonArray, JsonObject, JsonValue};
+
+/// Converts a feature into a GeoJSON `Feature` object.
+pub fn feature_to_json(feature: &GeoFeature) -> JsonObject {
+	let mut object = JsonObject::default();
+	object.set("type", "Feature");
+	if let Some(id) = &feature.id {
+		object.set("id", value_to_json(id));
+	}
+	object.set("geometry", JsonValue::Object(geometry_to_json(&feature.geometry)));
+	object.set("properties", JsonValue::Object(properties_to_json(&feature.properties)));
+	object
+}
+
+/// Converts a geometry into a GeoJSON geometry object.
+/// Coordinates are rounded to 7 decimal places, which is about 1 cm.
+pub fn geometry_to_json(geometry: &Geometry) -> JsonObject {
+	let coordinates = match geometry {
+		Geometry::Point(g) => coordinates0_to_json(&g.0),
+		Geometry::LineString(g) => coordinates1_to_json(&g.0),
+		Geometry::Polygon(g) => array(g.0.iter().map(|r| coordinates1_to_json(r))),
+		Geometry::MultiPoint(g) => coordinates1_to_json(&g.0),
+		Geometry::MultiLineString(g) => array(g.0.iter().map(|l| coordinates1_to_json(l))),
+		Geometry::MultiPolygon(g) => array(g.0.iter().map(|p| array(p.iter().map(|r| coordinates1_to_json(r))))),
+	};
+
+	let mut object = JsonObject::default();
+	object.set("type", geometry.get_type_name());
+	object.set("coordinates", coordinates);
+	object
+}
+
+fn properties_to_json(properties: &GeoProperties) -> JsonObject {
+	let mut object = JsonObject::default();
+	for (key, value) in properties.iter() {
+		object.set(key, value_to_json(value));
+	}
+	object
+}
+
+fn value_to_json(value: &GeoValue) -> JsonValue {
+	match value {
+		GeoValue::Bool(v) => JsonValue::Boolean(*v),
+		GeoValue::Double(v) => JsonValue::Number(*v),
+		// go through the shortest decimal representation, so that 0.1f32 doesn't become 0.10000000149011612
+		GeoValue::Float(v) => JsonValue::Number(v.to_string().parse().unwrap_or(*v as f64)),
+		GeoValue::Int(v) => JsonValue::Number(*v as f64),
+		GeoValue::Null => JsonValue::Null,
+		GeoValue::String(v) => JsonValue::from(v),
+		GeoValue::UInt(v) => JsonValue::Number(*v as f64),
+	}
+}
+
+fn coordinates0_to_json(point: &[f64; 2]) -> JsonValue {
+	array(point.iter().map(|v| JsonValue::Number((v * 1e7).round() / 1e7)))
+}
+
+fn coordinates1_to_json(points: &[[f64; 2]]) -> JsonValue {
+	array(points.iter().map(coordinates0_to_json))
+}
+
+fn array(values: impl Iterator<Item = JsonValue>) -> JsonValue {
+	JsonValue::Array(JsonArray(values.collect()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parse_geojson_feature;
+	use std::io::Cursor;
+	use versatiles_core::byte_iterator::ByteIterator;
+
+	#[test]
+	fn test_geometry_to_json() {
+		let json = |geometry: Geometry| geometry_to_json(&geometry).stringify();
+		assert_eq!(
+			json(Geometry::new_point([13.123456789, 52.5])),
+			"{\"coordinates\":[13.1234568,52.5],\"type\":\"Point\"}"
+		);
+		assert_eq!(
+			json(Geometry::new_line_string(vec![[1, 2], [3, 4]])),
+			"{\"coordinates\":[[1,2],[3,4]],\"type\":\"LineString\"}"
+		);
+		assert_eq!(
+			json(Geometry::new_multi_polygon(vec![vec![vec![
+				[0, 0],
+				[1, 0],
+				[0, 1],
+				[0, 0]
+			]]])),
+			"{\"coordinates\":[[[[0,0],[1,0],[0,1],[0,0]]]],\"type\":\"MultiPolygon\"}"
+		);
+	}
+
+	#[test]
+	fn test_feature_to_json() {
+		let mut feature = GeoFeature::new_example();
+		feature.set_property(String::from("ratio"), 0.1f32);
+		let json = feature_to_json(&feature).stringify();
+		assert!(json.starts_with("{\"geometry\":{\"coordinates\":[[[[0,0],[5,0],[2.5,4],[0,0]],"));
+		assert!(json.ends_with(
+			"\"id\":13,\"properties\":{\"is_nice\":true,\"name\":\"Nice\",\"population\":348085,\"ratio\":0.1},\"type\":\"Feature\"}"
+		));
+
+		// the output can be read again
+		let parsed = parse_geojson_feature(&mut ByteIterator::from_reader(Cursor::new(json), true)).unwrap();
+		assert_eq!(parsed.geometry, feature.geometry);
+		assert_eq!(parsed.properties.get("name"), Some(&GeoValue::from("Nice")));
+	}
+}
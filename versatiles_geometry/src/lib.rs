@@ -1,4 +1,4 @@
-mod geo;
+pub mod geo;
 pub mod geojson;
 pub mod math;
 pub mod osm;
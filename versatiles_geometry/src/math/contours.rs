@@ -0,0 +1,157 @@
+use crate::geo::*;
+use std::collections::{HashMap, HashSet};
+
+/// Traces the contour lines of `level` through a grid of `width` × `height` values, given row by row,
+/// with the marching squares algorithm.
+///
+/// The coordinates are in grid units: value `(x, y)` is at point `[x, y]`. Closed contours are returned
+/// as rings, whose first and last point are equal. Cells with a `NaN` corner are skipped.
+pub fn contour_lines(values: &[f64], width: usize, height: usize, level: f64) -> Vec<Coordinates1> {
+	assert_eq!(values.len(), width * height, "grid must contain width × height values");
+	if width < 2 || height < 2 {
+		return Vec::new();
+	}
+
+	let value = |x: usize, y: usize| values[y * width + x];
+	let inside = |v: f64| v >= level;
+
+	// ids of the cell edges: horizontal edges first, then vertical edges
+	let h_edge = |x: usize, y: usize| y * (width - 1) + x;
+	let v_edge = |x: usize, y: usize| height * (width - 1) + y * width + x;
+
+	let mut points: HashMap<usize, Coordinates0> = HashMap::new();
+	let mut crossing = |edge: usize, p0: [usize; 2], v0: f64, p1: [usize; 2], v1: f64| -> Option<usize> {
+		if inside(v0) == inside(v1) {
+			return None;
+		}
+		points.entry(edge).or_insert_with(|| {
+			let t = (level - v0) / (v1 - v0);
+			[
+				p0[0] as f64 + t * (p1[0] as f64 - p0[0] as f64),
+				p0[1] as f64 + t * (p1[1] as f64 - p0[1] as f64),
+			]
+		});
+		Some(edge)
+	};
+
+	let mut segments: Vec<(usize, usize)> = Vec::new();
+	for y in 0..height - 1 {
+		for x in 0..width - 1 {
+			let (tl, tr, br, bl) = (value(x, y), value(x + 1, y), value(x + 1, y + 1), value(x, y + 1));
+			if [tl, tr, br, bl].iter().any(|v| v.is_nan()) {
+				continue;
+			}
+
+			let top = crossing(h_edge(x, y), [x, y], tl, [x + 1, y], tr);
+			let right = crossing(v_edge(x + 1, y), [x + 1, y], tr, [x + 1, y + 1], br);
+			let bottom = crossing(h_edge(x, y + 1), [x, y + 1], bl, [x + 1, y + 1], br);
+			let left = crossing(v_edge(x, y), [x, y], tl, [x, y + 1], bl);
+
+			match (top, right, bottom, left) {
+				(Some(t), Some(r), Some(b), Some(l)) => {
+					// saddle: the value in the center decides which corners are connected
+					let center = (tl + tr + br + bl) / 4.0;
+					if inside(center) == inside(tl) {
+						segments.push((t, r));
+						segments.push((b, l));
+					} else {
+						segments.push((t, l));
+						segments.push((r, b));
+					}
+				}
+				_ => {
+					let crossed: Vec<usize> = [top, right, bottom, left].into_iter().flatten().collect();
+					if let [a, b] = crossed[..] {
+						segments.push((a, b));
+					}
+				}
+			}
+		}
+	}
+
+	let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+	for (a, b) in segments {
+		neighbors.entry(a).or_default().push(b);
+		neighbors.entry(b).or_default().push(a);
+	}
+
+	// start with the open lines, which end at the grid border or at cells without data
+	let mut starts: Vec<usize> = neighbors
+		.iter()
+		.filter(|(_, n)| n.len() == 1)
+		.map(|(e, _)| *e)
+		.collect();
+	starts.sort_unstable();
+	let mut rings: Vec<usize> = neighbors.keys().copied().collect();
+	rings.sort_unstable();
+	starts.extend(rings);
+
+	let mut visited: HashSet<usize> = HashSet::new();
+	let mut lines = Vec::new();
+	for start in starts {
+		if visited.contains(&start) {
+			continue;
+		}
+		let mut line = vec![points[&start]];
+		visited.insert(start);
+		let mut edge = start;
+		while let Some(next) = neighbors[&edge].iter().find(|e| !visited.contains(e)) {
+			line.push(points[next]);
+			visited.insert(*next);
+			edge = *next;
+		}
+		if edge != start && neighbors[&edge].contains(&start) && neighbors[&start].len() == 2 {
+			line.push(points[&start]);
+		}
+		if line.len() >= 2 {
+			lines.push(line);
+		}
+	}
+	lines
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_hill() {
+		// a single peak in the center of a 5×5 grid
+		#[rustfmt::skip]
+		let values = [
+			0.0, 0.0, 0.0, 0.0, 0.0,
+			0.0, 1.0, 1.0, 1.0, 0.0,
+			0.0, 1.0, 4.0, 1.0, 0.0,
+			0.0, 1.0, 1.0, 1.0, 0.0,
+			0.0, 0.0, 0.0, 0.0, 0.0,
+		];
+		let lines = contour_lines(&values, 5, 5, 2.0);
+		assert_eq!(lines.len(), 1);
+		let ring = &lines[0];
+		assert_eq!(ring.len(), 5);
+		assert_eq!(ring.first(), ring.last());
+		for point in ring {
+			let distance = (point[0] - 2.0).abs() + (point[1] - 2.0).abs();
+			assert!((distance - 2.0 / 3.0).abs() < 1e-9, "{point:?}");
+		}
+
+		assert!(contour_lines(&values, 5, 5, 5.0).is_empty());
+	}
+
+	#[test]
+	fn test_slope() {
+		// values increase from left to right, so the contour is a vertical line
+		let values: Vec<f64> = (0..12).map(|i| (i % 4) as f64).collect();
+		let lines = contour_lines(&values, 4, 3, 1.5);
+		assert_eq!(lines, vec![vec![[1.5, 0.0], [1.5, 1.0], [1.5, 2.0]]]);
+	}
+
+	#[test]
+	fn test_saddle_and_nan() {
+		let values = [1.0, 0.0, 0.0, 1.0];
+		assert_eq!(contour_lines(&values, 2, 2, 0.5).len(), 2);
+
+		let values = [1.0, 0.0, f64::NAN, 1.0];
+		assert!(contour_lines(&values, 2, 2, 0.5).is_empty());
+	}
+}
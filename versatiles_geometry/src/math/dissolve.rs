@@ -0,0 +1,201 @@
+use super::area_ring;
+use crate::geo::*;
+use std::collections::HashMap;
+
+type PointKey = (u64, u64);
+
+fn point_key(p: &Coordinates0) -> PointKey {
+	// adding 0.0 turns -0.0 into 0.0
+	((p[0] + 0.0).to_bits(), (p[1] + 0.0).to_bits())
+}
+
+/// Merges adjacent polygons by removing the edges they share.
+///
+/// Polygons are merged if their boundaries contain the same edge in opposite directions, as is the case
+/// for neighbouring polygons of a vector tile that share their boundary in the source data. Overlapping
+/// polygons are not merged. Outer rings of the result are clockwise and holes counter-clockwise.
+pub fn dissolve_polygons(polygons: &Coordinates3) -> Coordinates3 {
+	let mut points: HashMap<PointKey, Coordinates0> = HashMap::new();
+	let mut edges: HashMap<(PointKey, PointKey), usize> = HashMap::new();
+	let mut starts: Vec<PointKey> = Vec::new();
+
+	for polygon in polygons.iter() {
+		for (index, ring) in polygon.iter().enumerate() {
+			let mut ring = ring.clone();
+			if (area_ring(&ring) > 0.0) != (index == 0) {
+				ring.reverse();
+			}
+			for segment in ring.windows(2) {
+				let a = point_key(&segment[0]);
+				let b = point_key(&segment[1]);
+				if a == b {
+					continue;
+				}
+				// a shared edge appears in both polygons, but in opposite directions
+				if let Some(count) = edges.get_mut(&(b, a)).filter(|count| **count > 0) {
+					*count -= 1;
+					continue;
+				}
+				points.insert(a, segment[0]);
+				points.insert(b, segment[1]);
+				*edges.entry((a, b)).or_default() += 1;
+				starts.push(a);
+			}
+		}
+	}
+
+	let mut outgoing: HashMap<PointKey, Vec<PointKey>> = HashMap::new();
+	for start in starts.iter() {
+		outgoing.entry(*start).or_default();
+	}
+	for ((a, b), count) in edges.into_iter() {
+		for _ in 0..count {
+			outgoing.get_mut(&a).unwrap().push(b);
+		}
+	}
+	// sort the edges, so that the result does not depend on the order of the hash map
+	for targets in outgoing.values_mut() {
+		targets.sort_unstable();
+	}
+
+	let mut outer_rings: Vec<(Coordinates1, f64)> = Vec::new();
+	let mut holes: Vec<Coordinates1> = Vec::new();
+
+	for start in starts {
+		while let Some(next) = outgoing.get_mut(&start).unwrap().pop() {
+			let mut ring = vec![points[&start]];
+			let mut current = next;
+			loop {
+				ring.push(points[&current]);
+				if current == start {
+					break;
+				}
+				match outgoing.get_mut(&current).and_then(|targets| targets.pop()) {
+					Some(next) => current = next,
+					None => break,
+				}
+			}
+			if current != start {
+				// broken ring, e.g. because of overlapping polygons
+				continue;
+			}
+
+			let ring = remove_collinear_points(ring);
+			if ring.is_empty() {
+				continue;
+			}
+			let area = area_ring(&ring);
+			if area > 0.0 {
+				outer_rings.push((ring, area));
+			} else if area < 0.0 {
+				holes.push(ring);
+			}
+		}
+	}
+
+	let mut result: Coordinates3 = outer_rings.iter().map(|(ring, _)| vec![ring.clone()]).collect();
+	for hole in holes {
+		let p = [(hole[0][0] + hole[1][0]) / 2.0, (hole[0][1] + hole[1][1]) / 2.0];
+		let parent = outer_rings
+			.iter()
+			.enumerate()
+			.filter(|(_, (ring, _))| locate_point_in_ring(ring, &p) == PointLocation::Inside)
+			.min_by(|a, b| a.1 .1.total_cmp(&b.1 .1));
+		if let Some((index, _)) = parent {
+			result[index].push(hole);
+		}
+	}
+	result
+}
+
+/// Removes points that lie on a straight line between their neighbours, e.g. the former end points of shared edges.
+fn remove_collinear_points(mut ring: Coordinates1) -> Coordinates1 {
+	fn is_collinear(a: &Coordinates0, b: &Coordinates0, c: &Coordinates0) -> bool {
+		(b[0] - a[0]) * (c[1] - a[1]) == (b[1] - a[1]) * (c[0] - a[0])
+	}
+
+	ring.pop();
+	let mut result: Coordinates1 = Vec::with_capacity(ring.len());
+	for p in ring {
+		while result.len() >= 2 && is_collinear(&result[result.len() - 2], &result[result.len() - 1], &p) {
+			result.pop();
+		}
+		result.push(p);
+	}
+	while result.len() >= 3 && is_collinear(&result[result.len() - 2], &result[result.len() - 1], &result[0]) {
+		result.pop();
+	}
+	while result.len() >= 3 && is_collinear(&result[result.len() - 1], &result[0], &result[1]) {
+		result.remove(0);
+	}
+
+	if result.len() < 3 {
+		return Vec::new();
+	}
+	result.push(result[0]);
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn square(x: f64, y: f64) -> Coordinates2 {
+		vec![vec![[x, y], [x + 1.0, y], [x + 1.0, y + 1.0], [x, y + 1.0], [x, y]]]
+	}
+
+	#[test]
+	fn merges_adjacent_squares() {
+		let result = dissolve_polygons(&vec![square(0.0, 0.0), square(1.0, 0.0)]);
+		assert_eq!(result.len(), 1);
+		assert_eq!(result[0].len(), 1);
+		assert_eq!(result[0][0].len(), 5);
+		assert_eq!(area_ring(&result[0][0]), 4.0);
+	}
+
+	#[test]
+	fn keeps_separate_polygons() {
+		let result = dissolve_polygons(&vec![square(0.0, 0.0), square(2.0, 0.0)]);
+		assert_eq!(result, vec![square(0.0, 0.0), square(2.0, 0.0)]);
+	}
+
+	#[test]
+	fn reorients_rings() {
+		let mut reversed = square(1.0, 0.0);
+		reversed[0].reverse();
+		let result = dissolve_polygons(&vec![square(0.0, 0.0), reversed]);
+		assert_eq!(result.len(), 1);
+		assert_eq!(area_ring(&result[0][0]), 4.0);
+	}
+
+	#[test]
+	fn creates_holes() {
+		// 3x3 squares without the center
+		let mut polygons = Vec::new();
+		for y in 0..3 {
+			for x in 0..3 {
+				if x != 1 || y != 1 {
+					polygons.push(square(x as f64, y as f64));
+				}
+			}
+		}
+		let result = dissolve_polygons(&polygons);
+		assert_eq!(result.len(), 1);
+		assert_eq!(result[0].len(), 2);
+		assert_eq!(area_ring(&result[0][0]), 18.0);
+		assert_eq!(area_ring(&result[0][1]), -2.0);
+	}
+
+	#[test]
+	fn keeps_existing_holes() {
+		let polygon = vec![
+			vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]],
+			vec![[1.0, 1.0], [1.0, 2.0], [2.0, 2.0], [2.0, 1.0], [1.0, 1.0]],
+		];
+		let result = dissolve_polygons(&vec![polygon, square(4.0, 0.0)]);
+		assert_eq!(result.len(), 1);
+		assert_eq!(result[0].len(), 2);
+		assert_eq!(area_ring(&result[0][0]), 34.0);
+		assert_eq!(area_ring(&result[0][1]), -2.0);
+	}
+}
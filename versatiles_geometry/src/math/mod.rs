@@ -1,2 +1,8 @@
 mod area;
 pub use area::*;
+mod contours;
+pub use contours::*;
+mod dissolve;
+pub use dissolve::*;
+mod simplify;
+pub use simplify::*;
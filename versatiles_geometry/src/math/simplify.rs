@@ -0,0 +1,245 @@
+use crate::geo::*;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// Algorithm used by [`simplify_geometry`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SimplifyAlgorithm {
+	/// Removes points closer than the tolerance to the simplified line, see [`simplify_line_string`].
+	#[default]
+	DouglasPeucker,
+	/// Removes points forming a triangle smaller than the tolerance squared, see [`simplify_line_string_visvalingam`].
+	Visvalingam,
+}
+
+/// Simplifies a line string with the Douglas–Peucker algorithm.
+///
+/// Points closer than `tolerance` to the simplified line are removed. First and last point are always kept.
+pub fn simplify_line_string(line: &Coordinates1, tolerance: f64) -> Coordinates1 {
+	if line.len() <= 2 || tolerance <= 0.0 {
+		return line.clone();
+	}
+
+	let mut keep = vec![false; line.len()];
+	keep[0] = true;
+	keep[line.len() - 1] = true;
+
+	let mut stack = vec![(0, line.len() - 1)];
+	while let Some((first, last)) = stack.pop() {
+		let mut max_distance = 0.0;
+		let mut index = first;
+		for i in first + 1..last {
+			let distance = segment_distance_squared(&line[i], &line[first], &line[last]);
+			if distance > max_distance {
+				max_distance = distance;
+				index = i;
+			}
+		}
+		if max_distance > tolerance * tolerance {
+			keep[index] = true;
+			stack.push((first, index));
+			stack.push((index, last));
+		}
+	}
+
+	line
+		.iter()
+		.zip(keep)
+		.filter_map(|(p, k)| if k { Some(*p) } else { None })
+		.collect()
+}
+
+/// Simplifies a closed ring. The result is empty if the ring collapses to fewer than 4 points.
+pub fn simplify_ring(ring: &Coordinates1, tolerance: f64) -> Coordinates1 {
+	let ring = simplify_line_string(ring, tolerance);
+	if ring.len() < 4 {
+		Vec::new()
+	} else {
+		ring
+	}
+}
+
+/// Simplifies a line string with the Visvalingam–Whyatt algorithm.
+///
+/// Repeatedly removes the point that forms the smallest triangle with its neighbours, until all triangles are at least
+/// `tolerance`² in size. This keeps the overall shape better than Douglas–Peucker. First and last point are always kept.
+pub fn simplify_line_string_visvalingam(line: &Coordinates1, tolerance: f64) -> Coordinates1 {
+	let n = line.len();
+	if n <= 2 || tolerance <= 0.0 {
+		return line.clone();
+	}
+
+	let min_area = tolerance * tolerance;
+	let triangle_area = |a: usize, b: usize, c: usize| -> f64 {
+		let (a, b, c) = (line[a], line[b], line[c]);
+		((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.0
+	};
+
+	let mut prev: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+	let mut next: Vec<usize> = (1..=n).collect();
+	let mut removed = vec![false; n];
+	let mut areas: Vec<f64> = (0..n)
+		.map(|i| {
+			if i == 0 || i == n - 1 {
+				f64::INFINITY
+			} else {
+				triangle_area(i - 1, i, i + 1)
+			}
+		})
+		.collect();
+
+	// areas are never negative, so their bit patterns are ordered like the values
+	let mut heap: BinaryHeap<_> = (1..n - 1).map(|i| Reverse((areas[i].to_bits(), i))).collect();
+
+	while let Some(Reverse((bits, i))) = heap.pop() {
+		let area = f64::from_bits(bits);
+		if removed[i] || area != areas[i] {
+			// outdated entry
+			continue;
+		}
+		if area >= min_area {
+			break;
+		}
+		removed[i] = true;
+		let (p, q) = (prev[i], next[i]);
+		next[p] = q;
+		prev[q] = p;
+		for j in [p, q] {
+			if j != 0 && j != n - 1 {
+				areas[j] = triangle_area(prev[j], j, next[j]);
+				heap.push(Reverse((areas[j].to_bits(), j)));
+			}
+		}
+	}
+
+	line
+		.iter()
+		.zip(removed)
+		.filter_map(|(p, r)| if r { None } else { Some(*p) })
+		.collect()
+}
+
+/// Simplifies all lines and rings of a geometry. Points are not changed.
+///
+/// Rings that collapse are removed, as well as polygons whose outer ring collapses.
+/// Returns `None` if nothing remains.
+pub fn simplify_geometry(geometry: &Geometry, tolerance: f64, algorithm: SimplifyAlgorithm) -> Option<Geometry> {
+	let simplify_line = |line: &Coordinates1| match algorithm {
+		SimplifyAlgorithm::DouglasPeucker => simplify_line_string(line, tolerance),
+		SimplifyAlgorithm::Visvalingam => simplify_line_string_visvalingam(line, tolerance),
+	};
+	let simplify_polygon = |polygon: &Coordinates2| -> Option<Coordinates2> {
+		let mut rings = polygon.iter().map(|ring| {
+			let ring = simplify_line(ring);
+			if ring.len() < 4 {
+				Vec::new()
+			} else {
+				ring
+			}
+		});
+		let outer = rings.next().filter(|ring| !ring.is_empty())?;
+		Some(
+			std::iter::once(outer)
+				.chain(rings.filter(|ring| !ring.is_empty()))
+				.collect(),
+		)
+	};
+
+	match geometry {
+		Geometry::Point(_) | Geometry::MultiPoint(_) => Some(geometry.clone()),
+		Geometry::LineString(g) => Some(Geometry::new_line_string(simplify_line(&g.0))),
+		Geometry::MultiLineString(g) => Some(Geometry::new_multi_line_string(g.0.iter().map(simplify_line).collect())),
+		Geometry::Polygon(g) => simplify_polygon(&g.0).map(Geometry::new_polygon),
+		Geometry::MultiPolygon(g) => {
+			let polygons: Coordinates3 = g.0.iter().filter_map(simplify_polygon).collect();
+			if polygons.is_empty() {
+				None
+			} else {
+				Some(Geometry::new_multi_polygon(polygons))
+			}
+		}
+	}
+}
+
+/// Squared distance between point `p` and the segment `a`–`b`.
+fn segment_distance_squared(p: &Coordinates0, a: &Coordinates0, b: &Coordinates0) -> f64 {
+	let (mut x, mut y) = (a[0], a[1]);
+	let (dx, dy) = (b[0] - x, b[1] - y);
+
+	if dx != 0.0 || dy != 0.0 {
+		let t = ((p[0] - x) * dx + (p[1] - y) * dy) / (dx * dx + dy * dy);
+		if t > 1.0 {
+			x = b[0];
+			y = b[1];
+		} else if t > 0.0 {
+			x += dx * t;
+			y += dy * t;
+		}
+	}
+
+	(p[0] - x).powi(2) + (p[1] - y).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn simplify_line() {
+		let line = vec![[0.0, 0.0], [1.0, 0.1], [2.0, -0.1], [3.0, 5.0], [4.0, 6.0], [5.0, 7.0]];
+		assert_eq!(simplify_line_string(&line, 0.0), line);
+		assert_eq!(
+			simplify_line_string(&line, 0.5),
+			vec![[0.0, 0.0], [2.0, -0.1], [3.0, 5.0], [5.0, 7.0]]
+		);
+		assert_eq!(simplify_line_string(&line, 10.0), vec![[0.0, 0.0], [5.0, 7.0]]);
+	}
+
+	#[test]
+	fn simplify_line_visvalingam() {
+		let line = vec![[0.0, 0.0], [1.0, 0.1], [2.0, -0.1], [3.0, 5.0], [4.0, 6.0], [5.0, 7.0]];
+		assert_eq!(simplify_line_string_visvalingam(&line, 0.0), line);
+		// the triangles at [1.0, 0.1] and [4.0, 6.0] are smaller than 0.5² = 0.25
+		assert_eq!(
+			simplify_line_string_visvalingam(&line, 0.5),
+			vec![[0.0, 0.0], [2.0, -0.1], [3.0, 5.0], [5.0, 7.0]]
+		);
+		assert_eq!(
+			simplify_line_string_visvalingam(&line, 10.0),
+			vec![[0.0, 0.0], [5.0, 7.0]]
+		);
+	}
+
+	#[test]
+	fn simplify_geometries() {
+		use SimplifyAlgorithm::*;
+		let square = |size: f64| vec![[0.0, 0.0], [size, 0.0], [size, size], [0.0, size], [0.0, 0.0]];
+
+		let point = Geometry::new_point([1.0, 2.0]);
+		assert_eq!(simplify_geometry(&point, 10.0, DouglasPeucker), Some(point));
+
+		// the small hole collapses, the polygon remains
+		let polygon = Geometry::new_polygon(vec![square(100.0), square(1.0)]);
+		assert_eq!(
+			simplify_geometry(&polygon, 2.0, Visvalingam),
+			Some(Geometry::new_polygon(vec![square(100.0)]))
+		);
+
+		// small polygons are removed completely
+		let multi_polygon = Geometry::new_multi_polygon(vec![vec![square(1.0)], vec![square(100.0)]]);
+		assert_eq!(
+			simplify_geometry(&multi_polygon, 2.0, DouglasPeucker),
+			Some(Geometry::new_multi_polygon(vec![vec![square(100.0)]]))
+		);
+		assert_eq!(
+			simplify_geometry(&Geometry::new_polygon(vec![square(1.0)]), 2.0, Visvalingam),
+			None
+		);
+	}
+
+	#[test]
+	fn simplify_small_ring() {
+		let ring = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]];
+		assert_eq!(simplify_ring(&ring, 0.1), ring);
+		assert!(simplify_ring(&ring, 2.0).is_empty());
+	}
+}
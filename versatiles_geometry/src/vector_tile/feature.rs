@@ -205,6 +205,30 @@ impl VectorTileFeature {
 		}
 	}
 
+	/// Counts the points of the encoded geometry without decoding it. Closing points of polygon rings
+	/// are not encoded, so they are not counted.
+	pub fn count_vertices(&self) -> Result<u64> {
+		let mut reader = ValueReaderSlice::new_le(self.geom_data.as_slice());
+		let mut count = 0;
+		while reader.has_remaining() {
+			let value = reader
+				.read_varint()
+				.context("Failed to read varint for geometry command")?;
+			match value & 0x7 {
+				1 | 2 => {
+					for _ in 0..value >> 3 {
+						reader.read_svarint().context("Failed to read x coordinate")?;
+						reader.read_svarint().context("Failed to read y coordinate")?;
+						count += 1;
+					}
+				}
+				7 => {}
+				command => bail!("Unknown command {}", command),
+			}
+		}
+		Ok(count)
+	}
+
 	pub fn decode_properties(&self, layer: &VectorTileLayer) -> Result<GeoProperties> {
 		layer.decode_tag_ids(&self.tag_ids)
 	}
@@ -342,6 +366,32 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn count_vertices() -> Result<()> {
+		let count = |geometry: Geometry| {
+			VectorTileFeature::from_geometry(None, vec![], geometry)
+				.unwrap()
+				.count_vertices()
+				.unwrap()
+		};
+		assert_eq!(count(Geometry::new_point([1, 2])), 1);
+		assert_eq!(count(Geometry::new_line_string(vec![[0, 1], [0, 3], [2, 3]])), 3);
+		assert_eq!(
+			count(Geometry::new_polygon(vec![
+				vec![[0, 0], [3, 0], [3, 3], [0, 3], [0, 0]],
+				vec![[1, 1], [1, 2], [2, 2], [1, 1]],
+			])),
+			7
+		);
+
+		let feature = VectorTileFeature {
+			geom_data: Blob::from(vec![4]),
+			..Default::default()
+		};
+		assert_eq!(feature.count_vertices().unwrap_err().to_string(), "Unknown command 4");
+		Ok(())
+	}
+
 	#[test]
 	fn point_geometry_round_trip() -> Result<()> {
 		let geometry = Geometry::new_point([1, 2]);
@@ -1,7 +1,11 @@
 #![allow(dead_code)]
 
 use crate::{
-	vector_tile::{feature::VectorTileFeature, property_manager::PropertyManager, value::GeoValuePBF},
+	vector_tile::{
+		feature::VectorTileFeature,
+		property_manager::{PropertyManager, VTLPMap},
+		value::GeoValuePBF,
+	},
 	GeoFeature, GeoProperties, GeoValue,
 };
 use anyhow::{anyhow, bail, Context, Result};
@@ -260,6 +264,32 @@ impl VectorTileLayer {
 		})
 	}
 
+	/// Renames property keys without decoding the features. `rename` returns the new name of a key,
+	/// or `None` to keep it. Fails if two keys would have the same name.
+	pub fn rename_keys<F>(&mut self, rename: F) -> Result<()>
+	where
+		F: Fn(&str) -> Option<String>,
+	{
+		let keys = &self.property_manager.key;
+		let list: Vec<String> = keys
+			.iter()
+			.map(|key| rename(key).unwrap_or_else(|| key.clone()))
+			.collect();
+		let renamed = VTLPMap::new(list);
+		if renamed.map.len() != renamed.list.len() {
+			let mut names = renamed.list.clone();
+			names.sort();
+			let duplicate = names.windows(2).find(|w| w[0] == w[1]).map(|w| w[0].clone());
+			bail!(
+				"renaming the keys of layer '{}' results in the key '{}' twice",
+				self.name,
+				duplicate.unwrap_or_default()
+			);
+		}
+		self.property_manager.key = renamed;
+		Ok(())
+	}
+
 	#[cfg(test)]
 	pub fn new_example() -> Self {
 		VectorTileLayer::from_features(String::from("layer1"), vec![GeoFeature::new_example()], 4096, 1).unwrap()
@@ -355,4 +385,22 @@ mod tests {
 		assert_eq!(layer.version, 1);
 		Ok(())
 	}
+
+	#[test]
+	fn test_rename_keys() -> Result<()> {
+		let mut layer = VectorTileLayer::new_example();
+		layer.rename_keys(|key| (key == "population").then(|| String::from("pop")))?;
+		assert_eq!(layer.property_manager.key.list, vec!["is_nice", "name", "pop"]);
+		assert_eq!(
+			format!("{:?}", layer.to_features()?[0].properties),
+			"{\"is_nice\": Bool(true), \"name\": String(\"Nice\"), \"pop\": UInt(348085)}"
+		);
+
+		let error = layer.rename_keys(|_| Some(String::from("x"))).unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"renaming the keys of layer 'layer1' results in the key 'x' twice"
+		);
+		Ok(())
+	}
 }
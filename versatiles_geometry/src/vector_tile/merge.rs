@@ -0,0 +1,253 @@
+use super::{VectorTile, VectorTileFeature, VectorTileLayer};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+
+/// What happens if both tiles contain a layer with the same name.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LayerCollision {
+	/// Appends the features to the existing layer.
+	#[default]
+	Merge,
+	/// Adds the layer with a new name, e.g. `water_2`.
+	Rename,
+	/// Keeps the existing layer and ignores the new one.
+	KeepFirst,
+	/// Replaces the existing layer by the new one.
+	Replace,
+}
+
+/// What happens to merged features whose id is already used by a feature of the same layer.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DuplicateIds {
+	/// Keeps the duplicated ids.
+	#[default]
+	Keep,
+	/// Removes the id of the merged feature.
+	RemoveId,
+	/// Drops the merged feature.
+	DropFeature,
+}
+
+/// Options for [`VectorTile::merge`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeOptions {
+	pub layer_collision: LayerCollision,
+	pub duplicate_ids: DuplicateIds,
+	/// Rescales the geometries of all layers to this extent. By default, merged features are rescaled to the
+	/// extent of the existing layer and other layers keep their extent.
+	pub extent: Option<u32>,
+}
+
+impl VectorTile {
+	/// Merges the layers of `other` into this tile.
+	///
+	/// Features of layers with different extents are rescaled, so they cover the same area.
+	pub fn merge(&mut self, other: VectorTile, options: &MergeOptions) -> Result<()> {
+		if let Some(extent) = options.extent {
+			for layer in self.layers.iter_mut() {
+				rescale_layer(layer, extent)?;
+			}
+		}
+
+		for mut layer in other.layers {
+			if let Some(extent) = options.extent {
+				rescale_layer(&mut layer, extent)?;
+			}
+
+			let Some(index) = self.layers.iter().position(|l| l.name == layer.name) else {
+				self.layers.push(layer);
+				continue;
+			};
+
+			match options.layer_collision {
+				LayerCollision::Merge => {
+					let existing = &mut self.layers[index];
+					rescale_layer(&mut layer, existing.extent)?;
+					remove_duplicate_ids(existing, &mut layer, options.duplicate_ids);
+					existing
+						.add_from_layer(layer)
+						.with_context(|| format!("Failed to merge layer {:?}", existing.name))?;
+				}
+				LayerCollision::Rename => {
+					layer.name = (2..)
+						.map(|i| format!("{}_{i}", layer.name))
+						.find(|name| !self.layers.iter().any(|l| &l.name == name))
+						.unwrap();
+					self.layers.push(layer);
+				}
+				LayerCollision::KeepFirst => {}
+				LayerCollision::Replace => self.layers[index] = layer,
+			}
+		}
+		Ok(())
+	}
+}
+
+fn remove_duplicate_ids(existing: &VectorTileLayer, layer: &mut VectorTileLayer, duplicate_ids: DuplicateIds) {
+	if duplicate_ids == DuplicateIds::Keep {
+		return;
+	}
+	let mut ids: HashSet<u64> = existing.features.iter().filter_map(|f| f.id).collect();
+	layer.features.retain_mut(|feature| {
+		let Some(id) = feature.id else {
+			return true;
+		};
+		if ids.insert(id) {
+			return true;
+		}
+		match duplicate_ids {
+			DuplicateIds::Keep => true,
+			DuplicateIds::RemoveId => {
+				feature.id = None;
+				true
+			}
+			DuplicateIds::DropFeature => false,
+		}
+	});
+}
+
+fn rescale_layer(layer: &mut VectorTileLayer, extent: u32) -> Result<()> {
+	if layer.extent == extent {
+		return Ok(());
+	}
+	let factor = extent as f64 / layer.extent as f64;
+	for feature in layer.features.iter_mut() {
+		let mut geometry = feature.to_geometry()?;
+		geometry.transform(|p| {
+			p[0] *= factor;
+			p[1] *= factor;
+		});
+		*feature = VectorTileFeature::from_geometry(feature.id, std::mem::take(&mut feature.tag_ids), geometry)?;
+	}
+	layer.extent = extent;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GeoFeature, GeoProperties, GeoValue, Geometry};
+
+	fn layer(name: &str, extent: u32, ids: &[u64]) -> VectorTileLayer {
+		let features = ids
+			.iter()
+			.map(|id| {
+				let mut feature = GeoFeature::new(Geometry::new_line_string(vec![[0.0, 0.0], [256.0, 512.0]]));
+				feature.set_id(GeoValue::from(*id));
+				feature.properties = GeoProperties::from(vec![("name", GeoValue::from(format!("{name}{id}")))]);
+				feature
+			})
+			.collect();
+		VectorTileLayer::from_features(String::from(name), features, extent, 2).unwrap()
+	}
+
+	fn names(tile: &VectorTile) -> Vec<String> {
+		tile.layers.iter().map(|l| l.name.clone()).collect()
+	}
+
+	fn ids(layer: &VectorTileLayer) -> Vec<Option<u64>> {
+		layer.features.iter().map(|f| f.id).collect()
+	}
+
+	#[test]
+	fn merges_layers() -> Result<()> {
+		let mut tile = VectorTile::new(vec![layer("water", 4096, &[1, 2])]);
+		let other = VectorTile::new(vec![layer("roads", 4096, &[1]), layer("water", 4096, &[2, 3])]);
+		tile.merge(other, &MergeOptions::default())?;
+
+		assert_eq!(names(&tile), vec!["water", "roads"]);
+		assert_eq!(ids(&tile.layers[0]), vec![Some(1), Some(2), Some(2), Some(3)]);
+		let properties: Vec<String> = tile.layers[0]
+			.to_features()?
+			.iter()
+			.map(|f| f.properties.get("name").unwrap().to_string())
+			.collect();
+		assert_eq!(properties, vec!["water1", "water2", "water2", "water3"]);
+		Ok(())
+	}
+
+	#[test]
+	fn handles_layer_collisions() -> Result<()> {
+		let run = |layer_collision: LayerCollision| -> Result<VectorTile> {
+			let mut tile = VectorTile::new(vec![layer("water", 4096, &[1])]);
+			let other = VectorTile::new(vec![layer("water", 4096, &[5, 6]), layer("water_2", 4096, &[7])]);
+			let options = MergeOptions {
+				layer_collision,
+				..Default::default()
+			};
+			tile.merge(other, &options)?;
+			Ok(tile)
+		};
+
+		let tile = run(LayerCollision::Rename)?;
+		assert_eq!(names(&tile), vec!["water", "water_2", "water_2_2"]);
+		assert_eq!(ids(&tile.layers[1]), vec![Some(5), Some(6)]);
+
+		let tile = run(LayerCollision::KeepFirst)?;
+		assert_eq!(ids(&tile.layers[0]), vec![Some(1)]);
+
+		let tile = run(LayerCollision::Replace)?;
+		assert_eq!(ids(&tile.layers[0]), vec![Some(5), Some(6)]);
+		Ok(())
+	}
+
+	#[test]
+	fn handles_duplicate_ids() -> Result<()> {
+		let run = |duplicate_ids: DuplicateIds| -> Result<Vec<Option<u64>>> {
+			let mut tile = VectorTile::new(vec![layer("water", 4096, &[1, 2])]);
+			let other = VectorTile::new(vec![layer("water", 4096, &[2, 3, 3])]);
+			let options = MergeOptions {
+				duplicate_ids,
+				..Default::default()
+			};
+			tile.merge(other, &options)?;
+			Ok(ids(&tile.layers[0]))
+		};
+
+		assert_eq!(
+			run(DuplicateIds::Keep)?,
+			vec![Some(1), Some(2), Some(2), Some(3), Some(3)]
+		);
+		assert_eq!(
+			run(DuplicateIds::RemoveId)?,
+			vec![Some(1), Some(2), None, Some(3), None]
+		);
+		assert_eq!(run(DuplicateIds::DropFeature)?, vec![Some(1), Some(2), Some(3)]);
+		Ok(())
+	}
+
+	#[test]
+	fn normalizes_extents() -> Result<()> {
+		let end_point = |layer: &VectorTileLayer| -> Result<Vec<[f64; 2]>> {
+			Ok(layer
+				.to_features()?
+				.into_iter()
+				.map(|f| match f.geometry {
+					Geometry::LineString(g) => g.0[1],
+					Geometry::MultiLineString(g) => g.0[0][1],
+					_ => panic!("unexpected geometry"),
+				})
+				.collect())
+		};
+
+		let mut tile = VectorTile::new(vec![layer("water", 4096, &[1])]);
+		tile.merge(
+			VectorTile::new(vec![layer("water", 512, &[2])]),
+			&MergeOptions::default(),
+		)?;
+		assert_eq!(tile.layers[0].extent, 4096);
+		assert_eq!(end_point(&tile.layers[0])?, vec![[256.0, 512.0], [2048.0, 4096.0]]);
+
+		let mut tile = VectorTile::new(vec![layer("water", 4096, &[1])]);
+		let options = MergeOptions {
+			extent: Some(1024),
+			..Default::default()
+		};
+		tile.merge(VectorTile::new(vec![layer("roads", 512, &[2])]), &options)?;
+		assert_eq!(tile.layers[0].extent, 1024);
+		assert_eq!(tile.layers[1].extent, 1024);
+		assert_eq!(end_point(&tile.layers[0])?, vec![[64.0, 128.0]]);
+		assert_eq!(end_point(&tile.layers[1])?, vec![[512.0, 1024.0]]);
+		Ok(())
+	}
+}
@@ -1,9 +1,13 @@
 mod feature;
 mod geometry_type;
 mod layer;
+mod merge;
+mod overzoom;
 mod property_manager;
 mod tile;
 mod value;
 
+pub use feature::VectorTileFeature;
 pub use layer::VectorTileLayer;
+pub use merge::{DuplicateIds, LayerCollision, MergeOptions};
 pub use tile::VectorTile;
@@ -0,0 +1,118 @@
+use super::{VectorTile, VectorTileLayer};
+use crate::geo::clip_geometry;
+use anyhow::{ensure, Result};
+
+impl VectorTile {
+	/// Cuts out the part of this tile that is covered by a tile `level_diff` zoom levels deeper,
+	/// and scales it up to the full extent.
+	///
+	/// `x` and `y` are the position of the deeper tile inside this tile, from 0 to `2^level_diff - 1`.
+	/// `buffer` is the margin around the deeper tile that is kept, as a fraction of the tile size,
+	/// so that lines and polygons don't end exactly at the tile border.
+	/// Layers without remaining features are dropped.
+	pub fn overzoom(&self, level_diff: u8, x: u32, y: u32, buffer: f64) -> Result<VectorTile> {
+		ensure!(level_diff < 32, "level_diff must be smaller than 32");
+		let scale = 2u64.pow(level_diff as u32) as f64;
+		ensure!(
+			(x as f64) < scale && (y as f64) < scale,
+			"position ({x}, {y}) is outside of the tile"
+		);
+
+		let mut layers = Vec::new();
+		for layer in self.layers.iter() {
+			let size = layer.extent as f64 / scale;
+			let offset = [x as f64 * size, y as f64 * size];
+			let margin = buffer * size;
+			let bbox = [
+				offset[0] - margin,
+				offset[1] - margin,
+				offset[0] + size + margin,
+				offset[1] + size + margin,
+			];
+
+			let features: Vec<_> = layer
+				.to_features()?
+				.into_iter()
+				.filter_map(|mut feature| {
+					let mut geometry = clip_geometry(&feature.geometry, &bbox)?;
+					geometry.transform(|p| {
+						p[0] = (p[0] - offset[0]) * scale;
+						p[1] = (p[1] - offset[1]) * scale;
+					});
+					feature.geometry = geometry;
+					Some(feature)
+				})
+				.collect();
+
+			if !features.is_empty() {
+				layers.push(VectorTileLayer::from_features(
+					layer.name.clone(),
+					features,
+					layer.extent,
+					layer.version,
+				)?);
+			}
+		}
+		Ok(VectorTile::new(layers))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GeoFeature, Geometry};
+
+	fn make_tile() -> Result<VectorTile> {
+		let features = vec![
+			GeoFeature::new(Geometry::new_line_string(vec![[0.0, 1000.0], [4096.0, 1000.0]])),
+			GeoFeature::new(Geometry::new_point([3000.0, 3000.0])),
+		];
+		let layer = VectorTileLayer::from_features(String::from("layer"), features, 4096, 2)?;
+		Ok(VectorTile::new(vec![layer]))
+	}
+
+	fn geometries(tile: &VectorTile) -> Result<Vec<Geometry>> {
+		Ok(tile.layers[0].to_features()?.into_iter().map(|f| f.geometry).collect())
+	}
+
+	#[test]
+	fn overzoom_top_left() -> Result<()> {
+		let tile = make_tile()?.overzoom(1, 0, 0, 0.0)?;
+		assert_eq!(
+			geometries(&tile)?,
+			vec![Geometry::new_multi_line_string(vec![vec![
+				[0.0, 2000.0],
+				[4096.0, 2000.0]
+			]])]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn overzoom_bottom_right() -> Result<()> {
+		let tile = make_tile()?.overzoom(1, 1, 1, 0.0)?;
+		assert_eq!(
+			geometries(&tile)?,
+			vec![Geometry::new_multi_point(vec![[1904.0, 1904.0]])]
+		);
+
+		// two levels deeper, the line is still in the buffer of the tile below it
+		let tile = make_tile()?.overzoom(2, 1, 1, 1.0 / 32.0)?;
+		assert_eq!(
+			geometries(&tile)?,
+			vec![Geometry::new_multi_line_string(vec![vec![
+				[-128.0, -96.0],
+				[4224.0, -96.0]
+			]])]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn overzoom_drops_empty_layers() -> Result<()> {
+		let tile = make_tile()?.overzoom(2, 0, 3, 0.0)?;
+		assert!(tile.layers.is_empty());
+		assert!(make_tile()?.overzoom(1, 2, 0, 0.0).is_err());
+		Ok(())
+	}
+}
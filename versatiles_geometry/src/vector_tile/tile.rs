@@ -14,6 +14,11 @@ impl VectorTile {
 		VectorTile { layers }
 	}
 
+	/// Returns `true` if no layer contains a feature, e.g. a tile that only carries empty layers.
+	pub fn is_featureless(&self) -> bool {
+		self.layers.iter().all(|layer| layer.features.is_empty())
+	}
+
 	pub fn from_blob(blob: &Blob) -> Result<VectorTile> {
 		let mut reader = ValueReaderSlice::new_le(blob.as_slice());
 
@@ -77,4 +82,12 @@ mod tests {
 		assert_eq!(tile1, tile2);
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn is_featureless() -> Result<()> {
+		assert!(!get_tile().await?.is_featureless());
+		assert!(VectorTile::default().is_featureless());
+		assert!(VectorTile::new(vec![VectorTileLayer::new_standard("empty")]).is_featureless());
+		Ok(())
+	}
 }
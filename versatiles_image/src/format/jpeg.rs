@@ -7,8 +7,13 @@ use versatiles_core::types::Blob;
 const JPEG_QUALITY: u8 = 95;
 
 pub fn image2blob(image: &DynamicImage) -> Result<Blob> {
+	image2blob_quality(image, JPEG_QUALITY)
+}
+
+/// Encodes the image with a quality from 0 (smallest) to 100 (best).
+pub fn image2blob_quality(image: &DynamicImage, quality: u8) -> Result<Blob> {
 	let mut buffer: Vec<u8> = Vec::new();
-	JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY).write_image(
+	JpegEncoder::new_with_quality(&mut buffer, quality.clamp(1, 100)).write_image(
 		image.as_bytes(),
 		image.width(),
 		image.height(),
@@ -34,7 +39,11 @@ mod tests {
 		compare_images(blob2image(&image2blob(&image1)?)?, image1, 0);
 
 		let image2 = create_image_rgb();
-		compare_images(blob2image(&image2blob(&image2)?)?, image2, 4);
+		compare_images(blob2image(&image2blob(&image2)?)?, image2.clone(), 4);
+
+		let low = image2blob_quality(&image2, 20)?;
+		assert!(low.len() < image2blob(&image2)?.len());
+		compare_images(blob2image(&low)?, image2, 40);
 
 		Ok(())
 	}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, bail, Result};
+use image::{ColorType, DynamicImage};
+use jxl_oxide::integration::JxlDecoder;
+use versatiles_core::types::Blob;
+use zune_core::{bit_depth::BitDepth, colorspace::ColorSpace, options::EncoderOptions};
+use zune_jpegxl::JxlSimpleEncoder;
+
+/// Encodes the image losslessly.
+pub fn image2blob(image: &DynamicImage) -> Result<Blob> {
+	let (colorspace, depth) = match image.color() {
+		ColorType::L8 => (ColorSpace::Luma, BitDepth::Eight),
+		ColorType::La8 => (ColorSpace::LumaA, BitDepth::Eight),
+		ColorType::Rgb8 => (ColorSpace::RGB, BitDepth::Eight),
+		ColorType::Rgba8 => (ColorSpace::RGBA, BitDepth::Eight),
+		ColorType::L16 => (ColorSpace::Luma, BitDepth::Sixteen),
+		ColorType::La16 => (ColorSpace::LumaA, BitDepth::Sixteen),
+		ColorType::Rgb16 => (ColorSpace::RGB, BitDepth::Sixteen),
+		ColorType::Rgba16 => (ColorSpace::RGBA, BitDepth::Sixteen),
+		_ => bail!("currently only 8 and 16 bit images are supported for JPEG XL encoding"),
+	};
+	let options = EncoderOptions::new(image.width() as usize, image.height() as usize, colorspace, depth);
+
+	let mut buffer: Vec<u8> = Vec::new();
+	JxlSimpleEncoder::new(image.as_bytes(), options)
+		.encode(&mut buffer)
+		.map_err(|e| anyhow!("can't encode JPEG XL: {e:?}"))?;
+	Ok(Blob::from(buffer))
+}
+
+pub fn blob2image(blob: &Blob) -> Result<DynamicImage> {
+	Ok(DynamicImage::from_decoder(JxlDecoder::new(blob.as_slice())?)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::helper::{compare_images, create_image_grey, create_image_greya, create_image_rgb, create_image_rgba};
+
+	/// Test lossless JPEG XL encoding and decoding for grayscale, grayscale with alpha, RGB, and RGBA images
+	#[test]
+	fn jxl() -> Result<()> {
+		for image in [
+			create_image_grey(),
+			create_image_greya(),
+			create_image_rgb(),
+			create_image_rgba(),
+		] {
+			let decoded = blob2image(&image2blob(&image)?)?;
+			assert_eq!(decoded.color(), image.color());
+			compare_images(decoded, image, 0);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn jxl_16bit() -> Result<()> {
+		let image = DynamicImage::ImageRgb16(create_image_rgb().to_rgb16());
+		let decoded = blob2image(&image2blob(&image)?)?;
+		assert_eq!(decoded.color(), ColorType::Rgb16);
+		compare_images(decoded, image, 0);
+		Ok(())
+	}
+
+	#[test]
+	fn jxl_errors() {
+		let image = DynamicImage::ImageRgb32F(create_image_rgb().to_rgb32f());
+		assert_eq!(
+			image2blob(&image).unwrap_err().to_string(),
+			"currently only 8 and 16 bit images are supported for JPEG XL encoding"
+		);
+		assert!(blob2image(&Blob::from(vec![0u8, 1, 2, 3])).is_err());
+	}
+}
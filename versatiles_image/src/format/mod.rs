@@ -1,4 +1,10 @@
+#[cfg(feature = "codecs")]
 pub mod jpeg;
+#[cfg(feature = "jxl")]
+pub mod jxl;
+#[cfg(feature = "codecs")]
 pub mod png;
+#[cfg(feature = "codecs")]
 pub mod webp;
+#[cfg(feature = "codecs")]
 pub mod webp_lossless;
@@ -3,14 +3,19 @@ use image::DynamicImage;
 use versatiles_core::types::Blob;
 use webp::{Decoder, Encoder};
 
-const WEBP_QUALITY: f32 = 95.0;
+const WEBP_QUALITY: u8 = 95;
 
 pub fn image2blob(image: &DynamicImage) -> Result<Blob> {
+	image2blob_quality(image, WEBP_QUALITY)
+}
+
+/// Encodes the image with a quality from 0 (smallest) to 100 (best).
+pub fn image2blob_quality(image: &DynamicImage, quality: u8) -> Result<Blob> {
 	match image.color() {
 		image::ColorType::Rgb8 | image::ColorType::Rgba8 => Ok(Blob::from(
 			Encoder::from_image(image)
 				.map_err(|e| anyhow::Error::msg(e.to_owned()))?
-				.encode(WEBP_QUALITY)
+				.encode(quality.min(100) as f32)
 				.to_vec(),
 		)),
 		_ => bail!("currently only 8 bit RGB/RGBA is supported for WebP lossy encoding"),
@@ -43,7 +48,10 @@ mod tests {
 		compare_images(blob2image(&image2blob(&image3)?)?, image3, 4);
 
 		let image4 = create_image_rgba();
-		compare_images(blob2image(&image2blob(&image4)?)?, image4, 6);
+		compare_images(blob2image(&image2blob(&image4)?)?, image4.clone(), 6);
+
+		let low = image2blob_quality(&image4, 20)?;
+		assert!(low.len() < image2blob(&image4)?.len());
 
 		Ok(())
 	}
@@ -1,8 +1,21 @@
+#[cfg(feature = "jxl")]
+use crate::jxl;
+#[cfg(feature = "codecs")]
 use crate::{jpeg, png, webp};
-use anyhow::Result;
-use image::{DynamicImage, GrayAlphaImage, GrayImage, Luma, LumaA, Rgb, RgbImage, Rgba, RgbaImage};
+use anyhow::{bail, ensure, Result};
+use image::{
+	imageops::FilterType, DynamicImage, GrayAlphaImage, GrayImage, Luma, LumaA, Rgb, RgbImage, Rgba, RgbaImage,
+};
 use versatiles_core::types::{Blob, TileFormat};
 
+/// Error message for builds without the feature `codecs`, e.g. a minimal command line tool.
+#[cfg(not(feature = "codecs"))]
+const NO_CODECS: &str = "this build does not include image codecs, so raster tiles can not be encoded or decoded";
+
+/// Error message for builds without the feature `jxl`.
+#[cfg(not(feature = "jxl"))]
+const NO_JXL: &str = "this build does not include the JPEG XL codec, enable the feature \"jxl\"";
+
 /// Generate a DynamicImage with RGBA colors
 pub fn create_image_rgba() -> DynamicImage {
 	DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 256, |x, y| -> Rgba<u8> {
@@ -65,34 +78,211 @@ pub fn compare_images(image1: DynamicImage, image2: DynamicImage, max_allowed_di
 	);
 }
 
+#[cfg_attr(not(any(feature = "codecs", feature = "jxl")), allow(unused_variables))]
 pub fn image2blob(image: &DynamicImage, format: TileFormat) -> Result<Blob> {
 	use TileFormat::*;
 	match format {
 		AVIF => todo!(),
 		BIN => todo!(),
 		GEOJSON => todo!(),
+		#[cfg(feature = "codecs")]
 		JPG => jpeg::image2blob(image),
 		JSON => todo!(),
+		#[cfg(feature = "jxl")]
+		JXL => jxl::image2blob(image),
+		#[cfg(not(feature = "jxl"))]
+		JXL => bail!(NO_JXL),
 		PBF => todo!(),
+		#[cfg(feature = "codecs")]
 		PNG => png::image2blob(image, true),
 		SVG => todo!(),
 		TOPOJSON => todo!(),
+		#[cfg(feature = "codecs")]
 		WEBP => webp::image2blob(image),
+		#[cfg(not(feature = "codecs"))]
+		JPG | PNG | WEBP => bail!(NO_CODECS),
 	}
 }
 
+#[cfg_attr(not(any(feature = "codecs", feature = "jxl")), allow(unused_variables))]
 pub fn image2blob_fast(image: &DynamicImage, format: TileFormat) -> Result<Blob> {
 	use TileFormat::*;
 	match format {
 		AVIF => todo!(),
 		BIN => todo!(),
 		GEOJSON => todo!(),
+		#[cfg(feature = "codecs")]
 		JPG => jpeg::image2blob(image),
 		JSON => todo!(),
+		#[cfg(feature = "jxl")]
+		JXL => jxl::image2blob(image),
+		#[cfg(not(feature = "jxl"))]
+		JXL => bail!(NO_JXL),
 		PBF => todo!(),
+		#[cfg(feature = "codecs")]
 		PNG => png::image2blob(image, false),
 		SVG => todo!(),
 		TOPOJSON => todo!(),
+		#[cfg(feature = "codecs")]
 		WEBP => webp::image2blob(image),
+		#[cfg(not(feature = "codecs"))]
+		JPG | PNG | WEBP => bail!(NO_CODECS),
+	}
+}
+
+/// Like [`image2blob`], but encodes JPEG and WebP images with a quality from 0 (smallest) to 100 (best).
+/// Other formats are lossless and ignore the quality.
+pub fn image2blob_quality(image: &DynamicImage, format: TileFormat, quality: Option<u8>) -> Result<Blob> {
+	match (format, quality) {
+		#[cfg(feature = "codecs")]
+		(TileFormat::JPG, Some(quality)) => jpeg::image2blob_quality(image, quality),
+		#[cfg(feature = "codecs")]
+		(TileFormat::WEBP, Some(quality)) => webp::image2blob_quality(image, quality),
+		_ => image2blob(image, format),
+	}
+}
+
+#[cfg_attr(not(any(feature = "codecs", feature = "jxl")), allow(unused_variables))]
+pub fn blob2image(blob: &Blob, format: TileFormat) -> Result<DynamicImage> {
+	use TileFormat::*;
+	match format {
+		#[cfg(feature = "codecs")]
+		JPG => jpeg::blob2image(blob),
+		#[cfg(feature = "codecs")]
+		PNG => png::blob2image(blob),
+		#[cfg(feature = "codecs")]
+		WEBP => webp::blob2image(blob),
+		#[cfg(not(feature = "codecs"))]
+		JPG | PNG | WEBP => bail!(NO_CODECS),
+		#[cfg(feature = "jxl")]
+		JXL => jxl::blob2image(blob),
+		#[cfg(not(feature = "jxl"))]
+		JXL => bail!(NO_JXL),
+		_ => bail!("tile format '{}' can not be decoded as an image", format.as_str()),
+	}
+}
+
+/// Cuts out the part of `image` that is covered by a tile `level_diff` zoom levels deeper,
+/// and scales it up to the size of `image`.
+///
+/// `x` and `y` are the position of the deeper tile inside this tile, from 0 to `2^level_diff - 1`.
+pub fn overzoom_image(image: &DynamicImage, level_diff: u8, x: u32, y: u32) -> Result<DynamicImage> {
+	ensure!(level_diff < 32, "level_diff must be smaller than 32");
+	let scale = 1u32 << level_diff;
+	ensure!(x < scale && y < scale, "position ({x}, {y}) is outside of the tile");
+	let (width, height) = (image.width() / scale, image.height() / scale);
+	ensure!(
+		width > 0 && height > 0,
+		"image is too small to be overzoomed by {level_diff} levels"
+	);
+	Ok(image.crop_imm(x * width, y * height, width, height).resize_exact(
+		image.width(),
+		image.height(),
+		FilterType::Triangle,
+	))
+}
+
+/// Returns `true` if all pixels of `image` are fully transparent. Images without alpha channel are never transparent.
+pub fn is_image_transparent(image: &DynamicImage) -> bool {
+	if !image.color().has_alpha() {
+		return false;
+	}
+	image.to_rgba8().pixels().all(|pixel| pixel.0[3] == 0)
+}
+
+/// Returns `true` if no pixel of `image` is transparent, e.g. to decide whether it hides all tiles below it.
+/// Images without alpha channel are always opaque.
+pub fn is_image_opaque(image: &DynamicImage) -> bool {
+	if !image.color().has_alpha() {
+		return true;
+	}
+	image.to_rgba8().pixels().all(|pixel| pixel.0[3] == 255)
+}
+
+/// Resizes `image` by `scale`, e.g. a 256 pixel tile with scale 1.5 to 384 pixels.
+///
+/// Used for clients that can not scale tiles themselves and need them in the pixel ratio of the screen.
+pub fn scale_image(image: &DynamicImage, scale: f64) -> Result<DynamicImage> {
+	ensure!(scale.is_finite() && scale > 0.0, "scale must be a positive number");
+	let width = (image.width() as f64 * scale).round() as u32;
+	let height = (image.height() as f64 * scale).round() as u32;
+	ensure!(width > 0 && height > 0, "image is too small to be scaled by {scale}");
+	Ok(image.resize_exact(width, height, FilterType::CatmullRom))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::GenericImageView;
+
+	#[test]
+	fn test_overzoom_image() -> Result<()> {
+		let image = create_image_rgba();
+		let result = overzoom_image(&image, 1, 1, 0)?;
+		assert_eq!(result.dimensions(), (256, 256));
+
+		// pixel (100, 100) of the result shows pixel (178, 50) of the original
+		let pixel = result.get_pixel(100, 100).0;
+		let expected = [178u8, 77, 50, 205];
+		for (a, b) in pixel.iter().zip(expected.iter()) {
+			assert!(a.abs_diff(*b) <= 1, "{pixel:?} != {expected:?}");
+		}
+
+		assert!(overzoom_image(&image, 1, 2, 0).is_err());
+		assert!(overzoom_image(&image, 9, 0, 0).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_is_image_transparent() {
+		assert!(!is_image_transparent(&create_image_rgb()));
+		assert!(!is_image_transparent(&create_image_rgba()));
+		assert!(is_image_transparent(&DynamicImage::ImageRgba8(RgbaImage::new(4, 4))));
+		assert!(is_image_transparent(&DynamicImage::ImageLumaA8(GrayAlphaImage::new(
+			4, 4
+		))));
+		assert!(!is_image_transparent(&DynamicImage::ImageRgb8(RgbImage::new(4, 4))));
+	}
+
+	#[test]
+	fn test_is_image_opaque() {
+		assert!(is_image_opaque(&create_image_rgb()));
+		assert!(is_image_opaque(&DynamicImage::ImageRgb8(RgbImage::new(4, 4))));
+		assert!(!is_image_opaque(&create_image_rgba()));
+		assert!(!is_image_opaque(&DynamicImage::ImageRgba8(RgbaImage::new(4, 4))));
+		assert!(is_image_opaque(&DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+			4,
+			4,
+			image::Rgba([10, 20, 30, 255])
+		))));
+	}
+
+	#[test]
+	fn test_scale_image() -> Result<()> {
+		let image = create_image_rgb();
+		assert_eq!(scale_image(&image, 1.5)?.dimensions(), (384, 384));
+		assert_eq!(scale_image(&image, 0.5)?.dimensions(), (128, 128));
+
+		// the gradient is kept
+		let pixel = scale_image(&image, 2.0)?.get_pixel(201, 101).0;
+		for (a, b) in pixel.iter().zip([100u8, 155, 50, 255].iter()) {
+			assert!(a.abs_diff(*b) <= 1, "{pixel:?}");
+		}
+
+		assert!(scale_image(&image, 0.0).is_err());
+		assert!(scale_image(&image, f64::NAN).is_err());
+		assert!(scale_image(&image, 0.001).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_jxl() -> Result<()> {
+		let image = create_image_rgba();
+		let result = image2blob(&image, TileFormat::JXL);
+		#[cfg(feature = "jxl")]
+		compare_images(blob2image(&result?, TileFormat::JXL)?, image, 0);
+		#[cfg(not(feature = "jxl"))]
+		assert_eq!(result.unwrap_err().to_string(), NO_JXL);
+		Ok(())
 	}
 }
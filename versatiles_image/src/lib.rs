@@ -1,4 +1,10 @@
+// JPEG, PNG and WebP, and optionally JPEG XL
+#[cfg(any(feature = "codecs", feature = "jxl"))]
 mod format;
+#[cfg(any(feature = "codecs", feature = "jxl"))]
 pub use format::*;
 
 pub mod helper;
+mod quality;
+pub use quality::*;
+pub mod terrain;
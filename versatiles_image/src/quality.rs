@@ -0,0 +1,109 @@
+//! Image quality per zoom range.
+//!
+//! Tiles of low zoom levels are few and often requested, tiles of high zoom levels are many. So a single
+//! quality either wastes space at high zoom levels or looks poor at low ones, e.g. `0-9:90,10-:75` for WebP.
+
+use anyhow::{ensure, Context, Result};
+use std::{fmt, ops::RangeInclusive};
+use versatiles_core::{types::TileFormat, utils::parse_zoom_range};
+
+/// Qualities from 0 (smallest) to 100 (best) for ranges of zoom levels.
+/// Zoom levels without a range keep their tiles unchanged.
+#[derive(Clone, Default, PartialEq)]
+pub struct ImageQuality {
+	ranges: Vec<(RangeInclusive<u8>, u8)>,
+}
+
+impl ImageQuality {
+	/// Parses a comma separated list of `zoom_range:quality` items, e.g. `0-9:90,10-:75`.
+	///
+	/// A zoom range is a single zoom level (`5`), a closed range (`0-8`) or an open range (`13-` or `-4`).
+	/// An item without a zoom range, e.g. `80`, applies to all zoom levels. If ranges overlap, the first one wins.
+	pub fn parse_str(text: &str) -> Result<ImageQuality> {
+		let mut ranges = Vec::new();
+		for item in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+			let (zooms, quality) = match item.split_once(':') {
+				Some((zooms, quality)) => (parse_zoom_range(zooms.trim())?, quality.trim()),
+				None => (0..=31, item),
+			};
+			let quality = quality
+				.parse::<u8>()
+				.with_context(|| format!("quality {quality:?} is not a number"))?;
+			ensure!(quality <= 100, "quality must be between 0 and 100, but is {quality}");
+			ranges.push((zooms, quality));
+		}
+		Ok(ImageQuality { ranges })
+	}
+
+	/// Returns `true` if no qualities are defined.
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	/// Returns the quality for a zoom level, or `None` if tiles of this zoom level are not encoded again.
+	pub fn get_quality(&self, zoom: u8) -> Option<u8> {
+		self
+			.ranges
+			.iter()
+			.find(|(zooms, _)| zooms.contains(&zoom))
+			.map(|(_, quality)| *quality)
+	}
+
+	/// Checks that images of this format can be encoded with a quality.
+	pub fn check(&self, format: TileFormat) -> Result<()> {
+		ensure!(
+			self.is_empty() || matches!(format, TileFormat::JPG | TileFormat::WEBP),
+			"quality is only supported for jpg and webp tiles, not for {}",
+			format.as_str()
+		);
+		Ok(())
+	}
+}
+
+impl fmt::Debug for ImageQuality {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let items: Vec<String> = self
+			.ranges
+			.iter()
+			.map(|(zooms, quality)| format!("{}-{}:{quality}", zooms.start(), zooms.end()))
+			.collect();
+		f.write_str(&items.join(","))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse() -> Result<()> {
+		let quality = ImageQuality::parse_str("0-9:90, 10-:75")?;
+		assert_eq!(format!("{quality:?}"), "0-9:90,10-31:75");
+		assert_eq!(quality.get_quality(9), Some(90));
+		assert_eq!(quality.get_quality(10), Some(75));
+
+		let quality = ImageQuality::parse_str("3:50,80")?;
+		assert_eq!(quality.get_quality(3), Some(50));
+		assert_eq!(quality.get_quality(4), Some(80));
+
+		assert!(ImageQuality::parse_str("")?.is_empty());
+
+		let error = |text: &str| ImageQuality::parse_str(text).unwrap_err().to_string();
+		assert_eq!(error("0-9:high"), "quality \"high\" is not a number");
+		assert_eq!(error("120"), "quality must be between 0 and 100, but is 120");
+		assert_eq!(error("9-3:50"), "zoom range \"9-3\" is empty");
+		Ok(())
+	}
+
+	#[test]
+	fn test_check() -> Result<()> {
+		let quality = ImageQuality::parse_str("80")?;
+		assert!(quality.check(TileFormat::WEBP).is_ok());
+		assert_eq!(
+			quality.check(TileFormat::PNG).unwrap_err().to_string(),
+			"quality is only supported for jpg and webp tiles, not for png"
+		);
+		assert!(ImageQuality::default().check(TileFormat::PNG).is_ok());
+		Ok(())
+	}
+}
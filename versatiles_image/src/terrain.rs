@@ -0,0 +1,109 @@
+//! Decoding and encoding of elevation data stored in the color channels of raster tiles.
+
+use anyhow::{bail, Result};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// The way elevations are encoded in the RGB channels of a terrain tile.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TerrainEncoding {
+	/// Mapbox Terrain-RGB: `elevation = -10000 + (R * 65536 + G * 256 + B) * 0.1`
+	#[default]
+	Mapbox,
+	/// Terrarium: `elevation = R * 256 + G + B / 256 - 32768`
+	Terrarium,
+}
+
+impl TerrainEncoding {
+	/// Parses "mapbox" or "terrarium".
+	pub fn parse(name: &str) -> Result<TerrainEncoding> {
+		Ok(match name {
+			"mapbox" => TerrainEncoding::Mapbox,
+			"terrarium" => TerrainEncoding::Terrarium,
+			_ => bail!("unknown terrain encoding {name:?}, use \"mapbox\" or \"terrarium\""),
+		})
+	}
+
+	/// Returns the elevation in meters encoded by a pixel.
+	pub fn decode(&self, [r, g, b]: [u8; 3]) -> f64 {
+		let (r, g, b) = (r as f64, g as f64, b as f64);
+		match self {
+			TerrainEncoding::Mapbox => -10000.0 + (r * 65536.0 + g * 256.0 + b) * 0.1,
+			TerrainEncoding::Terrarium => r * 256.0 + g + b / 256.0 - 32768.0,
+		}
+	}
+
+	/// Returns the pixel encoding `elevation`, rounded to the precision of the encoding.
+	pub fn encode(&self, elevation: f64) -> [u8; 3] {
+		let value = match self {
+			TerrainEncoding::Mapbox => ((elevation + 10000.0) * 10.0).round(),
+			TerrainEncoding::Terrarium => ((elevation + 32768.0) * 256.0).round(),
+		}
+		.clamp(0.0, 16_777_215.0) as u32;
+		[(value >> 16) as u8, (value >> 8) as u8, value as u8]
+	}
+}
+
+/// Decodes the elevations of a terrain tile, row by row. Transparent pixels have no data and become `NaN`.
+pub fn image2elevations(image: &DynamicImage, encoding: TerrainEncoding) -> Vec<f64> {
+	image
+		.to_rgba8()
+		.pixels()
+		.map(|Rgba([r, g, b, a])| {
+			if *a == 0 {
+				f64::NAN
+			} else {
+				encoding.decode([*r, *g, *b])
+			}
+		})
+		.collect()
+}
+
+/// Encodes elevations, given row by row, as a terrain tile. `NaN` becomes a transparent pixel.
+pub fn elevations2image(elevations: &[f64], width: u32, height: u32, encoding: TerrainEncoding) -> DynamicImage {
+	DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+		let elevation = elevations[(y * width + x) as usize];
+		if elevation.is_nan() {
+			Rgba([0, 0, 0, 0])
+		} else {
+			let [r, g, b] = encoding.encode(elevation);
+			Rgba([r, g, b, 255])
+		}
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_decode_encode() {
+		let mapbox = TerrainEncoding::Mapbox;
+		assert_eq!(mapbox.decode([1, 134, 160]), 0.0);
+		assert_eq!(mapbox.encode(0.0), [1, 134, 160]);
+		assert!((mapbox.decode(mapbox.encode(8848.8)) - 8848.8).abs() < 1e-6);
+
+		let terrarium = TerrainEncoding::Terrarium;
+		assert_eq!(terrarium.decode([128, 0, 0]), 0.0);
+		assert_eq!(terrarium.encode(-11.5), [127, 244, 128]);
+		assert_eq!(terrarium.decode(terrarium.encode(8848.5)), 8848.5);
+
+		assert_eq!(TerrainEncoding::parse("terrarium").unwrap(), terrarium);
+		assert!(TerrainEncoding::parse("rgb").is_err());
+	}
+
+	#[test]
+	fn test_image() {
+		let elevations = vec![0.0, 100.5, f64::NAN, -20.0];
+		let image = elevations2image(&elevations, 2, 2, TerrainEncoding::Mapbox);
+		let result = image2elevations(&image, TerrainEncoding::Mapbox);
+		assert!(result[2].is_nan());
+		for i in [0, 1, 3] {
+			assert!(
+				(result[i] - elevations[i]).abs() < 1e-6,
+				"{} != {}",
+				result[i],
+				elevations[i]
+			);
+		}
+	}
+}
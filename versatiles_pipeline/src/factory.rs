@@ -1,25 +1,30 @@
 use crate::{
 	helpers::mock_vector_source::MockVectorSource,
-	operations::{get_read_operation_factories, get_transform_operation_factories},
+	operations::{get_read_operation_factories, get_transform_operation_factories, to_container},
 	traits::{OperationTrait, ReadOperationFactoryTrait, TransformOperationFactoryTrait},
 	vpl::{parse_vpl, VPLNode, VPLPipeline},
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use futures::future::BoxFuture;
 use itertools::Itertools;
 use std::{
 	collections::HashMap,
 	path::{Path, PathBuf},
+	sync::Mutex,
 };
-use versatiles_core::types::TilesReaderTrait;
+use versatiles_core::types::{TileCompression, TilesReaderTrait};
 
 type Callback = Box<dyn Fn(String) -> BoxFuture<'static, Result<Box<dyn TilesReaderTrait>>>>;
+type WriteCallback =
+	Box<dyn Fn(Box<dyn OperationTrait>, String, Option<TileCompression>) -> BoxFuture<'static, Result<()>>>;
 
 pub struct PipelineFactory {
 	read_ops: HashMap<String, Box<dyn ReadOperationFactoryTrait>>,
 	tran_ops: HashMap<String, Box<dyn TransformOperationFactoryTrait>>,
 	dir: PathBuf,
 	create_reader: Callback,
+	write_container: Option<WriteCallback>,
+	sinks: Mutex<Vec<to_container::Sink>>,
 }
 
 impl PipelineFactory {
@@ -29,9 +34,16 @@ impl PipelineFactory {
 			tran_ops: HashMap::new(),
 			dir: dir.to_path_buf(),
 			create_reader,
+			write_container: None,
+			sinks: Mutex::new(Vec::new()),
 		}
 	}
 
+	/// Sets the callback that writes the tiles of a `to_container` sink into a container.
+	pub fn set_writer(&mut self, write_container: WriteCallback) {
+		self.write_container = Some(write_container);
+	}
+
 	pub fn default(dir: &Path, create_reader: Callback) -> Self {
 		let mut factory = PipelineFactory::new(dir, create_reader);
 
@@ -73,7 +85,40 @@ impl PipelineFactory {
 
 	pub async fn operation_from_vpl(&self, text: &str) -> Result<Box<dyn OperationTrait>> {
 		let pipeline = parse_vpl(text)?;
-		self.build_pipeline(pipeline).await
+		let operation = self.build_pipeline(pipeline).await?;
+		ensure!(
+			std::mem::take(&mut *self.sinks.lock().unwrap()).is_empty(),
+			"'{}' can only be used when running a pipeline, not when reading from it",
+			to_container::TAG_NAME
+		);
+		Ok(operation)
+	}
+
+	/// Runs a pipeline that ends with a `to_container` sink, by writing all its sinks in the order of the VPL.
+	pub async fn run_vpl(&self, text: &str) -> Result<()> {
+		let pipeline = parse_vpl(text)?;
+		ensure!(
+			pipeline
+				.pipeline
+				.last()
+				.is_some_and(|node| node.name == to_container::TAG_NAME),
+			"pipeline must end with a '{}' operation",
+			to_container::TAG_NAME
+		);
+		let Some(write_container) = &self.write_container else {
+			bail!("this pipeline factory can not write containers");
+		};
+
+		self.build_pipeline(pipeline).await?;
+		let sinks = std::mem::take(&mut *self.sinks.lock().unwrap());
+
+		for sink in sinks {
+			log::info!("write {:?}", sink.filename);
+			let filename = self.resolve_filename(&sink.filename);
+			write_container(sink.operation, filename, sink.compression).await?;
+		}
+
+		Ok(())
 	}
 
 	pub async fn build_pipeline(&self, pipeline: VPLPipeline) -> Result<Box<dyn OperationTrait>> {
@@ -82,7 +127,13 @@ impl PipelineFactory {
 		let mut vpl_operation = self.read_operation_from_node(head).await?;
 
 		for node in tail {
-			vpl_operation = self.tran_operation_from_node(node, vpl_operation).await?;
+			if node.name == to_container::TAG_NAME {
+				let (sink, operation) = to_container::build(&node, vpl_operation)?;
+				self.sinks.lock().unwrap().push(sink);
+				vpl_operation = operation;
+			} else {
+				vpl_operation = self.tran_operation_from_node(node, vpl_operation).await?;
+			}
 		}
 
 		Ok(vpl_operation)
@@ -135,6 +186,8 @@ impl PipelineFactory {
 				.sorted_by_key(|f| f.get_tag_name())
 				.map(|f| format!("\n## {}\n{}\n", f.get_tag_name(), f.get_docs()))
 				.join(""),
+			String::from("---\n# SINK operations"),
+			format!("\n## {}\n{}\n", to_container::TAG_NAME, to_container::get_docs()),
 		]
 		.join("\n")
 	}
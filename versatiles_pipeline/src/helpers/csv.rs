@@ -1,53 +1,153 @@
-use anyhow::{bail, Context, Result};
-use std::{io::BufReader, path::Path};
-use versatiles_core::{progress::get_progress_bar, utils::read_csv_iter};
+use anyhow::{bail, ensure, Context, Result};
+use std::{collections::HashMap, io::BufReader, path::Path};
+use versatiles_core::{
+	progress::get_progress_bar,
+	utils::{detect_csv_separator, read_csv_iter},
+};
 use versatiles_geometry::{GeoProperties, GeoValue};
 
-/// Reads a CSV file from the given path and returns a vector of `GeoProperties`.
-///
-/// # Arguments
-///
-/// * `path` - A reference to the path of the CSV file.
-///
-/// # Returns
+/// Type of a CSV column.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvType {
+	/// Guess the type of every value, e.g. "12" is a number and "true" a boolean.
+	#[default]
+	Auto,
+	String,
+	Int,
+	Float,
+	Bool,
+}
+
+impl CsvType {
+	fn parse_str(value: &str) -> Result<CsvType> {
+		Ok(match value.trim() {
+			"auto" => CsvType::Auto,
+			"string" => CsvType::String,
+			"int" => CsvType::Int,
+			"float" => CsvType::Float,
+			"bool" => CsvType::Bool,
+			_ => bail!("unknown column type \"{value}\", expected \"auto\", \"string\", \"int\", \"float\" or \"bool\""),
+		})
+	}
+
+	/// Converts a value. Empty values of typed columns return `None`, so the property is left out.
+	fn convert(&self, value: String) -> Result<Option<GeoValue>> {
+		if value.is_empty() && !matches!(self, CsvType::Auto | CsvType::String) {
+			return Ok(None);
+		}
+		let trimmed = value.trim();
+		Ok(Some(match self {
+			CsvType::Auto => GeoValue::parse_str(&value),
+			CsvType::String => GeoValue::String(value),
+			CsvType::Int => GeoValue::Int(
+				trimmed
+					.parse()
+					.with_context(|| format!("\"{value}\" is not an integer"))?,
+			),
+			CsvType::Float => GeoValue::Double(
+				trimmed
+					.parse()
+					.with_context(|| format!("\"{value}\" is not a number"))?,
+			),
+			CsvType::Bool => GeoValue::Bool(match trimmed.to_lowercase().as_str() {
+				"true" | "1" | "yes" => true,
+				"false" | "0" | "no" => false,
+				_ => bail!("\"{value}\" is not a boolean"),
+			}),
+		}))
+	}
+}
+
+/// Options for reading CSV files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsvOptions {
+	/// Separator of the fields, detected automatically if `None`.
+	pub separator: Option<u8>,
+	/// Types of the columns by name. Other columns are `CsvType::Auto`.
+	pub column_types: HashMap<String, CsvType>,
+}
+
+impl CsvOptions {
+	/// Parses the options as used in VPL, e.g. `separator=";"` and `column_types="zip:string,population:int"`.
+	/// The separator can also be "tab" or "auto".
+	pub fn parse(separator: Option<&str>, column_types: Option<&str>) -> Result<CsvOptions> {
+		let separator = match separator {
+			None | Some("auto") => None,
+			Some("tab") | Some("\t") => Some(b'\t'),
+			Some(value) => {
+				ensure!(
+					value.len() == 1,
+					"separator must be a single character, \"tab\" or \"auto\", but is \"{value}\""
+				);
+				Some(value.as_bytes()[0])
+			}
+		};
+
+		let mut types = HashMap::new();
+		for entry in column_types.unwrap_or("").split(',').filter(|e| !e.trim().is_empty()) {
+			let (name, column_type) = entry
+				.rsplit_once(':')
+				.with_context(|| format!("column type \"{entry}\" must have the format \"name:type\""))?;
+			types.insert(name.trim().to_string(), CsvType::parse_str(column_type)?);
+		}
+
+		Ok(CsvOptions {
+			separator,
+			column_types: types,
+		})
+	}
+}
+
+/// Reads the rows of a CSV file one after the other, so files larger than memory can be processed.
 ///
-/// * `Result<Vec<GeoProperties>>` - A vector of `GeoProperties` or an error if the file could not be read.
-pub async fn read_csv_file(path: &Path) -> Result<Vec<GeoProperties>> {
+/// The first row is the header. Fields can be quoted and span multiple lines. Fields are decoded as UTF-8,
+/// or as Windows-1252 if they are not valid UTF-8.
+pub fn read_csv_rows(path: &Path, options: &CsvOptions) -> Result<impl Iterator<Item = Result<GeoProperties>>> {
 	let file = std::fs::File::open(path).with_context(|| format!("Failed to open file at path: {:?}", path))?;
 
 	let size = file.metadata()?.len();
 	let mut progress = get_progress_bar("read csv", size);
 
-	let reader = BufReader::new(file);
-
-	let mut errors = vec![];
-	let mut iter = read_csv_iter(reader, b',')?;
-	let header: Vec<String> = iter.next().unwrap()?.0;
-	let data: Vec<GeoProperties> = iter
-		.filter_map(|e| {
-			e.map(|(fields, _line_pos, byte_pos)| {
-				progress.set_position(byte_pos as u64);
-
-				GeoProperties::from_iter(
-					fields
-						.into_iter()
-						.enumerate()
-						.map(|(col, value)| (header[col].clone(), GeoValue::parse_str(&value))),
-				)
-			})
-			.map_err(|e| errors.push(e))
-			.ok()
-		})
-		.collect::<Vec<_>>();
-
-	progress.finish();
+	let mut reader = BufReader::new(file);
+	let separator = match options.separator {
+		Some(separator) => separator,
+		None => detect_csv_separator(&mut reader)?,
+	};
 
-	if !errors.is_empty() {
-		println!("{:?}", errors);
-		bail!("found {} error(s) while reading csv", errors.len());
+	let mut iter = read_csv_iter(reader, separator)?;
+	let header: Vec<String> = match iter.next() {
+		Some(header) => header?.0,
+		None => vec![],
+	};
+	let types: Vec<CsvType> = header
+		.iter()
+		.map(|name| options.column_types.get(name).copied().unwrap_or_default())
+		.collect();
+	for name in options.column_types.keys() {
+		ensure!(
+			header.contains(name),
+			"column \"{name}\" of the column types is not in the CSV header"
+		);
 	}
 
-	Ok(data)
+	Ok(iter.map(move |entry| {
+		let (fields, line_pos, byte_pos) = entry?;
+		progress.set_position(byte_pos as u64);
+		if byte_pos as u64 >= size {
+			progress.finish();
+		}
+
+		let mut properties = GeoProperties::new();
+		for (col, value) in fields.into_iter().enumerate() {
+			let value = types[col]
+				.convert(value)
+				.with_context(|| format!("in line {}, column \"{}\"", line_pos, header[col]))?;
+			if let Some(value) = value {
+				properties.insert(header[col].clone(), value);
+			}
+		}
+		Ok(properties)
+	}))
 }
 
 #[cfg(test)]
@@ -66,11 +166,15 @@ mod tests {
 		Ok(temp_file)
 	}
 
-	#[tokio::test]
-	async fn test_read_csv_file() -> Result<()> {
+	fn read_csv_file(path: &Path) -> Result<Vec<GeoProperties>> {
+		read_csv_rows(path, &CsvOptions::default())?.collect()
+	}
+
+	#[test]
+	fn test_read_csv_file() -> Result<()> {
 		let file_path =
 			make_temp_csv("name,age,city\nJohn Doe,30,New York\nJane Smith,25,Los Angeles\nAlice Johnson,28,Chicago")?;
-		let data = read_csv_file(file_path.path()).await?;
+		let data = read_csv_file(file_path.path())?;
 
 		assert_eq!(data.len(), 3);
 
@@ -92,19 +196,19 @@ mod tests {
 		Ok(())
 	}
 
-	#[tokio::test]
-	async fn test_read_empty_csv_file() -> Result<()> {
+	#[test]
+	fn test_read_empty_csv_file() -> Result<()> {
 		let file_path = make_temp_csv("name,age,city")?;
-		let data = read_csv_file(file_path.path()).await?;
+		let data = read_csv_file(file_path.path())?;
 		assert!(data.is_empty());
 		Ok(())
 	}
 
-	#[tokio::test]
-	async fn test_read_csv_file_missing_values() -> Result<()> {
+	#[test]
+	fn test_read_csv_file_missing_values() -> Result<()> {
 		let file_path = make_temp_csv("name,age,city\nJohn Doe,,New York\n,25,Los Angeles\nAlice Johnson,28,")?;
 
-		let data = read_csv_file(file_path.path()).await?;
+		let data = read_csv_file(file_path.path())?;
 
 		assert_eq!(data.len(), 3);
 
@@ -126,10 +230,57 @@ mod tests {
 		Ok(())
 	}
 
-	#[tokio::test]
-	async fn test_read_csv_file_incorrect_path() {
+	#[test]
+	fn test_read_csv_file_incorrect_path() {
 		let path = Path::new("non_existent.csv");
-		let result = read_csv_file(path).await;
+		let result = read_csv_file(path);
 		assert!(result.is_err());
 	}
+
+	#[test]
+	fn test_read_csv_rows_with_options() -> Result<()> {
+		let file_path = make_temp_csv("id;zip;population;share;capital\n1;01067;556780;0,5;yes\n2;;;;no")?;
+		let options = CsvOptions::parse(None, Some("zip:string, population:int,capital:bool"))?;
+		let rows = read_csv_rows(file_path.path(), &options)?.collect::<Result<Vec<_>>>()?;
+
+		assert_eq!(rows.len(), 2);
+		assert_eq!(rows[0].get("id").unwrap(), &GeoValue::from(1));
+		assert_eq!(rows[0].get("zip").unwrap(), &GeoValue::from("01067"));
+		assert_eq!(rows[0].get("population").unwrap(), &GeoValue::Int(556780));
+		assert_eq!(rows[0].get("share").unwrap(), &GeoValue::from("0,5"));
+		assert_eq!(rows[0].get("capital").unwrap(), &GeoValue::Bool(true));
+
+		// empty values of typed columns are left out
+		assert_eq!(rows[1].get("zip").unwrap(), &GeoValue::from(""));
+		assert!(rows[1].get("population").is_none());
+		assert_eq!(rows[1].get("capital").unwrap(), &GeoValue::Bool(false));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_read_csv_rows_errors() -> Result<()> {
+		let file_path = make_temp_csv("name,age\nJohn,thirty")?;
+		let options = CsvOptions::parse(Some(","), Some("age:int"))?;
+		let error = read_csv_rows(file_path.path(), &options)?.next().unwrap().unwrap_err();
+		assert_eq!(
+			format!("{error:#}"),
+			"in line 2, column \"age\": \"thirty\" is not an integer: invalid digit found in string"
+		);
+
+		let options = CsvOptions::parse(None, Some("size:int"))?;
+		assert!(read_csv_rows(file_path.path(), &options).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_csv_options() -> Result<()> {
+		assert_eq!(CsvOptions::parse(None, None)?, CsvOptions::default());
+		assert_eq!(CsvOptions::parse(Some("tab"), None)?.separator, Some(b'\t'));
+		assert_eq!(CsvOptions::parse(Some(";"), None)?.separator, Some(b';'));
+		assert!(CsvOptions::parse(Some(";;"), None).is_err());
+		assert!(CsvOptions::parse(None, Some("a")).is_err());
+		assert!(CsvOptions::parse(None, Some("a:date")).is_err());
+		Ok(())
+	}
 }
@@ -1,4 +1,6 @@
 mod csv;
 pub mod mock_vector_source;
+mod sqlite;
 
 pub use csv::*;
+pub use sqlite::*;
@@ -0,0 +1,230 @@
+use anyhow::{ensure, Context, Result};
+use log::warn;
+use r2d2::Pool;
+use r2d2_sqlite::{
+	rusqlite::{
+		params_from_iter,
+		types::{Value, ValueRef},
+		OpenFlags,
+	},
+	SqliteConnectionManager,
+};
+use std::{collections::HashMap, fmt::Debug, path::Path};
+use versatiles_geometry::{GeoProperties, GeoValue};
+
+/// Maximum number of keys per query, below the SQLite limit of host parameters.
+const BATCH_SIZE: usize = 500;
+
+/// Returns true if the file is a SQLite database or GeoPackage, judging by its extension.
+pub fn is_sqlite_path(path: &Path) -> bool {
+	matches!(
+		path.extension().and_then(|e| e.to_str()),
+		Some("sqlite" | "sqlite3" | "db" | "gpkg")
+	)
+}
+
+/// A table of a SQLite database or GeoPackage, whose rows are looked up by a key column.
+///
+/// Rows are only read when they are requested, so tables larger than memory can be used.
+/// The key column should be indexed, otherwise every lookup scans the whole table.
+pub struct SqliteTable {
+	pool: Pool<SqliteConnectionManager>,
+	table: String,
+	key_column: String,
+	columns: Vec<String>,
+}
+
+impl SqliteTable {
+	/// Opens the database read-only and checks that the table and the key column exist.
+	pub fn open(path: &Path, table: &str, key_column: &str) -> Result<SqliteTable> {
+		ensure!(path.exists(), "file {path:?} does not exist");
+
+		let manager = SqliteConnectionManager::file(path).with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY);
+		let pool = Pool::builder().max_size(10).build(manager)?;
+
+		let conn = pool.get()?;
+		let columns = conn
+			.prepare(&format!("SELECT * FROM {} LIMIT 0", quote(table)))
+			.with_context(|| format!("Failed to read table \"{table}\" from {path:?}"))?
+			.column_names()
+			.into_iter()
+			.map(String::from)
+			.collect::<Vec<String>>();
+		ensure!(
+			columns.iter().any(|c| c == key_column),
+			"column \"{key_column}\" not found in table \"{table}\", available columns are: {}",
+			columns.join(", ")
+		);
+
+		let plan: String = conn.query_row(
+			&format!(
+				"EXPLAIN QUERY PLAN SELECT * FROM {} WHERE {} = ?",
+				quote(table),
+				quote(key_column)
+			),
+			[0],
+			|row| row.get("detail"),
+		)?;
+		if plan.starts_with("SCAN") {
+			warn!(
+				"column \"{key_column}\" of table \"{table}\" has no index, lookups will be slow. Create one with: CREATE INDEX {} ON {} ({})",
+				quote(&format!("{table}_{key_column}")),
+				quote(table),
+				quote(key_column)
+			);
+		}
+		drop(conn);
+
+		Ok(SqliteTable {
+			pool,
+			table: table.to_string(),
+			key_column: key_column.to_string(),
+			columns,
+		})
+	}
+
+	/// Names of all columns of the table, including the key column.
+	pub fn get_columns(&self) -> &[String] {
+		&self.columns
+	}
+
+	/// Reads the rows of the given keys. Returns the found rows by the string representation of their key.
+	/// `NULL` values are left out.
+	pub fn get_rows(&self, keys: &[GeoValue]) -> Result<HashMap<String, GeoProperties>> {
+		let mut rows = HashMap::new();
+		if keys.is_empty() {
+			return Ok(rows);
+		}
+
+		let conn = self.pool.get()?;
+		for chunk in keys.chunks(BATCH_SIZE) {
+			let sql = format!(
+				"SELECT * FROM {} WHERE {} IN ({})",
+				quote(&self.table),
+				quote(&self.key_column),
+				vec!["?"; chunk.len()].join(",")
+			);
+			let mut stmt = conn.prepare_cached(&sql)?;
+			let mut result = stmt.query(params_from_iter(chunk.iter().map(to_sql_value)))?;
+			while let Some(row) = result.next()? {
+				let mut properties = GeoProperties::new();
+				for (index, name) in self.columns.iter().enumerate() {
+					if let Some(value) = to_geo_value(row.get_ref(index)?) {
+						properties.insert(name.clone(), value);
+					}
+				}
+				if let Some(key) = properties.get(&self.key_column) {
+					rows.insert(key.to_string(), properties);
+				}
+			}
+		}
+		Ok(rows)
+	}
+}
+
+impl Debug for SqliteTable {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("SqliteTable")
+			.field("table", &self.table)
+			.field("key_column", &self.key_column)
+			.field("columns", &self.columns)
+			.finish()
+	}
+}
+
+fn quote(identifier: &str) -> String {
+	format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn to_sql_value(value: &GeoValue) -> Value {
+	match value {
+		GeoValue::Bool(v) => Value::Integer(*v as i64),
+		GeoValue::Double(v) => Value::Real(*v),
+		GeoValue::Float(v) => Value::Real(*v as f64),
+		GeoValue::Int(v) => Value::Integer(*v),
+		GeoValue::Null => Value::Null,
+		GeoValue::String(v) => Value::Text(v.clone()),
+		GeoValue::UInt(v) => Value::Integer(*v as i64),
+	}
+}
+
+/// Converts a SQLite value like CSV values are converted, so non-negative integers become `UInt`.
+fn to_geo_value(value: ValueRef) -> Option<GeoValue> {
+	match value {
+		ValueRef::Null | ValueRef::Blob(_) => None,
+		ValueRef::Integer(v) if v >= 0 => Some(GeoValue::UInt(v as u64)),
+		ValueRef::Integer(v) => Some(GeoValue::Int(v)),
+		ValueRef::Real(v) => Some(GeoValue::Double(v)),
+		ValueRef::Text(v) => Some(GeoValue::String(String::from_utf8_lossy(v).to_string())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::NamedTempFile;
+	use r2d2_sqlite::rusqlite::Connection;
+
+	/// Creates a database with a table "cities" of `count` rows: id, name and population (NULL for every 10th row).
+	fn make_temp_db(count: u64, index: bool) -> Result<NamedTempFile> {
+		let file = NamedTempFile::new("test.sqlite")?;
+		let conn = Connection::open(file.path())?;
+		conn.execute_batch("CREATE TABLE cities (id INTEGER, name TEXT, population INTEGER)")?;
+		if index {
+			conn.execute_batch("CREATE INDEX cities_id ON cities (id)")?;
+		}
+		let mut stmt = conn.prepare("INSERT INTO cities VALUES (?, ?, ?)")?;
+		for id in 0..count {
+			let population = if id % 10 == 0 { None } else { Some(id * 1000) };
+			stmt.execute((id, format!("city {id}"), population))?;
+		}
+		Ok(file)
+	}
+
+	#[test]
+	fn test_is_sqlite_path() {
+		assert!(is_sqlite_path(Path::new("data/attributes.sqlite")));
+		assert!(is_sqlite_path(Path::new("attributes.gpkg")));
+		assert!(!is_sqlite_path(Path::new("attributes.csv")));
+		assert!(!is_sqlite_path(Path::new("sqlite")));
+	}
+
+	#[test]
+	fn test_open() -> Result<()> {
+		let file = make_temp_db(10, true)?;
+		let table = SqliteTable::open(file.path(), "cities", "id")?;
+		assert_eq!(table.get_columns(), ["id", "name", "population"]);
+
+		let error = SqliteTable::open(file.path(), "cities", "zip").unwrap_err().to_string();
+		assert_eq!(
+			error,
+			"column \"zip\" not found in table \"cities\", available columns are: id, name, population"
+		);
+		assert!(SqliteTable::open(file.path(), "towns", "id").is_err());
+		assert!(SqliteTable::open(Path::new("missing.sqlite"), "cities", "id").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_rows() -> Result<()> {
+		let file = make_temp_db(2000, false)?;
+		let table = SqliteTable::open(file.path(), "cities", "id")?;
+
+		let rows = table.get_rows(&[GeoValue::from(3u64), GeoValue::from("10"), GeoValue::from(5000u64)])?;
+		assert_eq!(rows.len(), 2);
+		assert_eq!(
+			format!("{:?}", rows["3"]),
+			"{\"id\": UInt(3), \"name\": String(\"city 3\"), \"population\": UInt(3000)}"
+		);
+		assert_eq!(
+			format!("{:?}", rows["10"]),
+			"{\"id\": UInt(10), \"name\": String(\"city 10\")}"
+		);
+
+		// more keys than fit into one query
+		let keys: Vec<GeoValue> = (0..1500u64).map(GeoValue::from).collect();
+		assert_eq!(table.get_rows(&keys)?.len(), 1500);
+		assert!(table.get_rows(&[])?.is_empty());
+		Ok(())
+	}
+}
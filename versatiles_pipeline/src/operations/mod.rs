@@ -1,5 +1,7 @@
 mod read;
+mod sink;
 mod transform;
 
 pub use read::*;
+pub use sink::*;
 pub use transform::*;
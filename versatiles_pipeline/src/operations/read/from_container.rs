@@ -11,12 +11,16 @@ struct Args {
 	/// The filename of the tile container. This is relative to the path of the VPL file.
 	/// For example: `filename="world.versatiles"`.
 	filename: String,
+	/// Coordinate reference system of the tile grid and the vector tile geometries, e.g. `crs="EPSG:4326"`.
+	/// It is stored as "crs" in the metadata, so that `vector_reproject` can convert the tiles. Defaults to the value in the container's metadata.
+	crs: Option<String>,
 }
 
 #[derive(Debug)]
 struct Operation {
 	parameters: TilesReaderParameters,
 	reader: Box<dyn TilesReaderTrait>,
+	tilejson: TileJSON,
 }
 
 impl ReadOperationTrait for Operation {
@@ -29,7 +33,16 @@ impl ReadOperationTrait for Operation {
 			let reader = factory.get_reader(&factory.resolve_filename(&args.filename)).await?;
 			let parameters = reader.get_parameters().clone();
 
-			Ok(Box::new(Self { parameters, reader }) as Box<dyn OperationTrait>)
+			let mut tilejson = reader.get_tilejson().clone();
+			if let Some(crs) = &args.crs {
+				tilejson.set_string("crs", crs)?;
+			}
+
+			Ok(Box::new(Self {
+				parameters,
+				reader,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
 		})
 	}
 }
@@ -41,7 +54,7 @@ impl OperationTrait for Operation {
 	}
 
 	fn get_tilejson(&self) -> &TileJSON {
-		self.reader.get_tilejson()
+		&self.tilejson
 	}
 
 	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
@@ -106,4 +119,14 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn test_crs() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=\"test.mbtiles\" crs=\"EPSG:4326\"")
+			.await?;
+		assert_eq!(operation.get_tilejson().get_str("crs"), Some("EPSG:4326"));
+		Ok(())
+	}
 }
@@ -0,0 +1,442 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{collections::BTreeMap, fmt::Debug, fs::File, sync::Arc};
+use versatiles_core::{
+	tilejson::{TileJSON, VectorLayer},
+	types::*,
+};
+use versatiles_geometry::{
+	geo::clip_geometry,
+	math::{simplify_line_string, simplify_ring},
+	read_geojson,
+	vector_tile::{VectorTile, VectorTileLayer},
+	Coordinates1, GeoFeature, Geometry,
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Generates vector tiles from a GeoJSON file. All features are written into a single layer.
+struct Args {
+	/// The filename of the GeoJSON file. This is relative to the path of the VPL file.
+	/// For example: `filename="parcels.geojson"`.
+	filename: String,
+	/// Name of the layer. Defaults to the filename without extension.
+	layer_name: Option<String>,
+	/// Minimal zoom level of the generated tiles (default: 0).
+	min_zoom: Option<u8>,
+	/// Maximal zoom level of the generated tiles, at most 31 (default: 14).
+	max_zoom: Option<u8>,
+	/// Tile extent, i.e. the resolution of the tile coordinates, between 512 and 8192 (default: 4096).
+	extent: Option<u32>,
+	/// Size of the buffer around each tile in pixels of a 256 pixel tile (default: 5). Features are clipped to the tile plus buffer.
+	buffer: Option<u32>,
+	/// Simplification tolerance in pixels of a 256 pixel tile (default: 1). Use 0 to disable simplification.
+	/// It applies to the single layer of this source. For a different tolerance per layer, combine several
+	/// `from_geojson` sources with `from_vectortiles_merged`.
+	tolerance: Option<f32>,
+}
+
+/// A feature projected to Web Mercator, normalized to [0, 1].
+#[derive(Debug)]
+struct MercatorFeature {
+	bbox: [f64; 4],
+	feature: GeoFeature,
+}
+
+#[derive(Debug)]
+struct Tiler {
+	buffer: f64,
+	extent: u32,
+	features: Vec<MercatorFeature>,
+	layer_name: String,
+	tolerance: f64,
+}
+
+impl Tiler {
+	fn build_tile(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let extent = self.extent as f64;
+		let scale = 2f64.powi(coord.z as i32);
+		let pixel = extent / 256.0;
+
+		// tile + buffer in normalized coordinates
+		let b = self.buffer * pixel / extent / scale;
+		let bbox_norm = [
+			coord.x as f64 / scale - b,
+			coord.y as f64 / scale - b,
+			(coord.x + 1) as f64 / scale + b,
+			(coord.y + 1) as f64 / scale + b,
+		];
+		let bbox_tile = [
+			-self.buffer * pixel,
+			-self.buffer * pixel,
+			extent + self.buffer * pixel,
+			extent + self.buffer * pixel,
+		];
+		let tolerance = self.tolerance * pixel;
+
+		let project = |c: &[f64; 2]| -> [f64; 2] {
+			[
+				(c[0] * scale - coord.x as f64) * extent,
+				(c[1] * scale - coord.y as f64) * extent,
+			]
+		};
+		let project_line = |line: &Coordinates1| -> Coordinates1 { line.iter().map(project).collect() };
+
+		let mut features = Vec::new();
+		for f in self.features.iter() {
+			if f.bbox[0] > bbox_norm[2] || f.bbox[2] < bbox_norm[0] || f.bbox[1] > bbox_norm[3] || f.bbox[3] < bbox_norm[1]
+			{
+				continue;
+			}
+
+			let geometry = match &f.feature.geometry {
+				Geometry::MultiPoint(g) => Geometry::new_multi_point(g.0.iter().map(project).collect()),
+				Geometry::MultiLineString(g) => Geometry::new_multi_line_string(
+					g.0.iter()
+						.map(|line| simplify_line_string(&project_line(line), tolerance))
+						.collect(),
+				),
+				Geometry::MultiPolygon(g) => Geometry::new_multi_polygon(
+					g.0.iter()
+						.map(|polygon| {
+							polygon
+								.iter()
+								.map(|ring| simplify_ring(&project_line(ring), tolerance))
+								.collect()
+						})
+						.collect(),
+				),
+				_ => unreachable!("geometries are converted to multi geometries"),
+			};
+
+			if let Some(geometry) = clip_geometry(&geometry, &bbox_tile) {
+				let mut feature = GeoFeature::new(geometry);
+				feature.id = f.feature.id.clone();
+				feature.properties = f.feature.properties.clone();
+				features.push(feature);
+			}
+		}
+
+		if features.is_empty() {
+			return Ok(None);
+		}
+
+		let layer = VectorTileLayer::from_features(self.layer_name.clone(), features, self.extent, 2)?;
+		Ok(Some(VectorTile::new(vec![layer]).to_blob()?))
+	}
+}
+
+/// Projects WGS84 coordinates to Web Mercator, normalized to [0, 1] with y pointing south.
+fn to_mercator(c: &[f64; 2]) -> [f64; 2] {
+	TileCoord3 { x: 0, y: 0, z: 0 }.lonlat_to_pixel(*c, 1.0)
+}
+
+fn project_feature(mut feature: GeoFeature) -> MercatorFeature {
+	let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+	let mut project = |c: &mut [f64; 2]| {
+		*c = to_mercator(c);
+		bbox = [
+			bbox[0].min(c[0]),
+			bbox[1].min(c[1]),
+			bbox[2].max(c[0]),
+			bbox[3].max(c[1]),
+		];
+	};
+
+	feature.geometry = feature.geometry.into_multi();
+	match &mut feature.geometry {
+		Geometry::MultiPoint(g) => g.0.iter_mut().for_each(&mut project),
+		Geometry::MultiLineString(g) => g.0.iter_mut().flatten().for_each(&mut project),
+		Geometry::MultiPolygon(g) => g.0.iter_mut().flatten().flatten().for_each(&mut project),
+		_ => unreachable!("geometries are converted to multi geometries"),
+	}
+
+	MercatorFeature { bbox, feature }
+}
+
+#[derive(Debug)]
+struct Operation {
+	parameters: TilesReaderParameters,
+	tiler: Arc<Tiler>,
+	tilejson: TileJSON,
+}
+
+impl ReadOperationTrait for Operation {
+	fn build(vpl_node: VPLNode, factory: &PipelineFactory) -> BoxFuture<'_, Result<Box<dyn OperationTrait>>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let extent = args.extent.unwrap_or(4096);
+			ensure!((512..=8192).contains(&extent), "extent must be between 512 and 8192");
+			let tolerance = args.tolerance.unwrap_or(1.0);
+			ensure!(tolerance >= 0.0, "tolerance must not be negative");
+			let min_zoom = args.min_zoom.unwrap_or(0);
+			let max_zoom = args.max_zoom.unwrap_or(14);
+			ensure!(min_zoom <= max_zoom, "min_zoom must not be greater than max_zoom");
+			ensure!(max_zoom <= 31, "max_zoom must not be greater than 31");
+
+			let path = factory.resolve_path(&args.filename);
+			let collection = read_geojson(File::open(&path).with_context(|| format!("opening {path:?}"))?)
+				.with_context(|| format!("parsing GeoJSON {path:?}"))?;
+
+			let layer_name = args.layer_name.unwrap_or_else(|| {
+				path
+					.file_stem()
+					.map_or(String::from("geojson"), |s| s.to_string_lossy().to_string())
+			});
+
+			let mut fields = BTreeMap::new();
+			for feature in collection.features.iter() {
+				for (key, _) in feature.properties.iter() {
+					fields.entry(key.clone()).or_insert_with(String::new);
+				}
+			}
+
+			let features: Vec<MercatorFeature> = collection.features.into_iter().map(project_feature).collect();
+
+			let mut bbox_pyramid = TileBBoxPyramid::new_empty();
+			if !features.is_empty() {
+				let bbox = features.iter().fold([f64::MAX, f64::MAX, f64::MIN, f64::MIN], |b, f| {
+					[
+						b[0].min(f.bbox[0]),
+						b[1].min(f.bbox[1]),
+						b[2].max(f.bbox[2]),
+						b[3].max(f.bbox[3]),
+					]
+				});
+				for z in min_zoom..=max_zoom {
+					let max = (1u32 << z) - 1;
+					let scale = 2f64.powi(z as i32);
+					let to_tile = |v: f64| ((v * scale).floor().max(0.0) as u32).min(max);
+					bbox_pyramid.set_level_bbox(TileBBox::new(
+						z,
+						to_tile(bbox[0]),
+						to_tile(bbox[1]),
+						to_tile(bbox[2]),
+						to_tile(bbox[3]),
+					)?);
+				}
+			}
+
+			let parameters = TilesReaderParameters::new(TileFormat::PBF, TileCompression::Uncompressed, bbox_pyramid);
+
+			let mut tilejson = TileJSON::default();
+			tilejson.update_from_pyramid(&parameters.bbox_pyramid);
+			tilejson.vector_layers.0.insert(
+				layer_name.clone(),
+				VectorLayer {
+					fields,
+					description: None,
+					minzoom: Some(min_zoom),
+					maxzoom: Some(max_zoom),
+				},
+			);
+
+			let tiler = Arc::new(Tiler {
+				buffer: args.buffer.unwrap_or(5) as f64,
+				extent,
+				features,
+				layer_name,
+				tolerance: tolerance as f64,
+			});
+
+			Ok(Box::new(Self {
+				parameters,
+				tiler,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if !self.parameters.bbox_pyramid.contains_coord(coord) {
+			return Ok(None);
+		}
+		self.tiler.build_tile(coord)
+	}
+
+	async fn get_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		bbox.intersect_pyramid(&self.parameters.bbox_pyramid).unwrap();
+		let tiler = self.tiler.clone();
+		TileStream::from_coord_iter_parallel(bbox.into_iter_coords(), move |c| tiler.build_tile(&c).ok().flatten())
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"from_geojson"
+	}
+}
+
+#[async_trait]
+impl ReadOperationFactoryTrait for Factory {
+	async fn build<'a>(&self, vpl_node: VPLNode, factory: &'a PipelineFactory) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+
+	const GEOJSON: &str = r#"{"type":"FeatureCollection","features":[
+		{"type":"Feature","properties":{"name":"square"},"geometry":{"type":"Polygon","coordinates":[[[13.0,52.0],[14.0,52.0],[14.0,53.0],[13.0,53.0],[13.0,52.0]]]}},
+		{"type":"Feature","properties":{"name":"line"},"geometry":{"type":"LineString","coordinates":[[13.0,52.0],[13.5,52.2],[14.0,52.1]]}},
+		{"type":"Feature","properties":{"name":"point"},"geometry":{"type":"Point","coordinates":[13.4,52.5]}}
+	]}"#;
+
+	async fn get_operation(dir: &TempDir, args: &str) -> Result<Box<dyn OperationTrait>> {
+		let path = dir.path().join("shapes.geojson");
+		std::fs::write(&path, GEOJSON)?;
+		PipelineFactory::new_dummy()
+			.operation_from_vpl(&format!("from_geojson filename=\"{}\" {args}", path.display()))
+			.await
+	}
+
+	fn decode(blob: &Blob) -> Result<VectorTileLayer> {
+		Ok(VectorTile::from_blob(blob)?.layers.pop().unwrap())
+	}
+
+	#[tokio::test]
+	async fn test_parameters() -> Result<()> {
+		let dir = TempDir::new()?;
+		let operation = get_operation(&dir, "max_zoom=8").await?;
+
+		let parameters = operation.get_parameters();
+		assert_eq!(parameters.tile_format, TileFormat::PBF);
+		assert_eq!(parameters.bbox_pyramid.get_zoom_min(), Some(0));
+		assert_eq!(parameters.bbox_pyramid.get_zoom_max(), Some(8));
+		assert_eq!(
+			parameters.bbox_pyramid.get_level_bbox(8),
+			&TileBBox::new(8, 137, 83, 137, 84)?
+		);
+
+		let tilejson = operation.get_tilejson().as_string();
+		assert!(
+			tilejson.contains(r#""vector_layers":[{"fields":{"name":""},"id":"shapes","maxzoom":8,"minzoom":0}]"#),
+			"{tilejson}"
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_tiles() -> Result<()> {
+		let dir = TempDir::new()?;
+		let operation = get_operation(&dir, "layer_name=\"parcels\" max_zoom=10 extent=512 buffer=0").await?;
+
+		let layer = decode(&operation.get_tile_data(&TileCoord3::new(550, 337, 10)?).await?.unwrap())?;
+		assert_eq!(layer.name, "parcels");
+		assert_eq!(layer.extent, 512);
+		assert_eq!(layer.features.len(), 2);
+
+		// the square covers the whole tile
+		let feature = layer.features[0].to_feature(&layer)?;
+		assert_eq!(
+			format!("{:?}", feature.geometry),
+			"MultiPolygon([[[[0.0, 0.0], [512.0, 0.0], [512.0, 512.0], [0.0, 512.0], [0.0, 0.0]]]])"
+		);
+
+		assert!(operation.get_tile_data(&TileCoord3::new(0, 0, 8)?).await?.is_none());
+
+		let count = operation
+			.get_tile_stream(TileBBox::new_full(6)?)
+			.await
+			.drain_and_count()
+			.await;
+		assert_eq!(count, 2);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_buffer() -> Result<()> {
+		let dir = TempDir::new()?;
+		let extent_of = |layer: VectorTileLayer| -> Result<f64> {
+			let feature = layer.features[0].to_feature(&layer)?;
+			let Geometry::MultiPolygon(g) = feature.geometry else {
+				panic!()
+			};
+			Ok(g.0[0][0].iter().map(|p| p[0]).fold(f64::MIN, f64::max))
+		};
+
+		let coord = TileCoord3::new(550, 337, 10)?;
+		let operation = get_operation(&dir, "max_zoom=10 extent=1024 buffer=0").await?;
+		assert_eq!(
+			extent_of(decode(&operation.get_tile_data(&coord).await?.unwrap())?)?,
+			1024.0
+		);
+
+		let operation = get_operation(&dir, "max_zoom=10 extent=1024 buffer=16").await?;
+		assert_eq!(
+			extent_of(decode(&operation.get_tile_data(&coord).await?.unwrap())?)?,
+			1088.0
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() -> Result<()> {
+		let dir = TempDir::new()?;
+		let error = get_operation(&dir, "extent=100").await.unwrap_err();
+		assert_eq!(error.to_string(), "extent must be between 512 and 8192");
+
+		let error = get_operation(&dir, "min_zoom=5 max_zoom=3").await.unwrap_err();
+		assert_eq!(error.to_string(), "min_zoom must not be greater than max_zoom");
+
+		let error = get_operation(&dir, "max_zoom=32").await.unwrap_err();
+		assert_eq!(error.to_string(), "max_zoom must not be greater than 31");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_tolerance_per_layer() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("shapes.geojson");
+		std::fs::write(&path, GEOJSON)?;
+		let operation = PipelineFactory::new_dummy()
+			.operation_from_vpl(&format!(
+				"from_vectortiles_merged [ from_geojson filename=\"{0}\" layer_name=\"exact\" max_zoom=4 tolerance=0, from_geojson filename=\"{0}\" layer_name=\"simple\" max_zoom=4 tolerance=64 ]",
+				path.display()
+			))
+			.await?;
+
+		let tile = VectorTile::from_blob(&operation.get_tile_data(&TileCoord3::new(8, 5, 4)?).await?.unwrap())?;
+		let count_points = |name: &str| -> Result<usize> {
+			let layer = tile.layers.iter().find(|layer| layer.name == name).unwrap();
+			for feature in layer.features.iter() {
+				if let Geometry::MultiLineString(g) = feature.to_feature(layer)?.geometry {
+					return Ok(g.0[0].len());
+				}
+			}
+			panic!("no line in layer {name}")
+		};
+		assert_eq!(count_points("exact")?, 3);
+		assert_eq!(count_points("simple")?, 2);
+
+		Ok(())
+	}
+}
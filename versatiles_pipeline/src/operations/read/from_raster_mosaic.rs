@@ -0,0 +1,470 @@
+use crate::{
+	traits::*,
+	vpl::{VPLNode, VPLPipeline},
+	PipelineFactory,
+};
+use anyhow::{bail, ensure, Result};
+use async_trait::async_trait;
+use futures::future::{join_all, BoxFuture};
+use imageproc::image::{DynamicImage, RgbaImage};
+use std::cmp::Ordering;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_image::helper::{blob2image, image2blob, is_image_opaque, is_image_transparent};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Combines overlapping raster scenes into one mosaic. Transparent pixels of a scene show the scenes below.
+struct Args {
+	/// All scenes must have the same raster tile format.
+	sources: Vec<VPLPipeline>,
+	/// Which scene is on top: "first" (default) uses the order of `sources`, "latest" puts the scene with the latest date in the meta data on top, "min_cloud" the scene with the lowest cloud cover.
+	rule: Option<String>,
+	/// Meta data key used by the rule (default: "datetime" for "latest", "cloud_cover" for "min_cloud"). Scenes without this key are put at the bottom.
+	key: Option<String>,
+	/// Width in pixels of the transition at the edges of a scene, where it is blended with the scenes below (default: 0). Edges are detected by transparent pixels within a tile.
+	feather: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum MosaicRule {
+	First,
+	Latest(String),
+	MinValue(String),
+}
+
+impl MosaicRule {
+	fn parse(rule: Option<&str>, key: Option<&str>) -> Result<MosaicRule> {
+		Ok(match rule.unwrap_or("first") {
+			"first" => {
+				ensure!(key.is_none(), "`key` can not be used with rule \"first\"");
+				MosaicRule::First
+			}
+			"latest" => MosaicRule::Latest(key.unwrap_or("datetime").to_string()),
+			"min_cloud" => MosaicRule::MinValue(key.unwrap_or("cloud_cover").to_string()),
+			rule => bail!("unknown rule \"{rule}\", expected \"first\", \"latest\" or \"min_cloud\""),
+		})
+	}
+
+	/// Sorts the scenes, so that the top scene comes first. Scenes without a value keep their order at the bottom.
+	fn sort(&self, sources: &mut [Box<dyn OperationTrait>]) {
+		fn compare<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Ordering {
+			match (a, b) {
+				(Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+				(Some(_), None) => Ordering::Less,
+				(None, Some(_)) => Ordering::Greater,
+				(None, None) => Ordering::Equal,
+			}
+		}
+		match self {
+			MosaicRule::First => {}
+			MosaicRule::Latest(key) => sources.sort_by(|a, b| {
+				let get = |s: &dyn OperationTrait| s.get_tilejson().get_string(key).map(std::cmp::Reverse);
+				compare(get(a.as_ref()), get(b.as_ref()))
+			}),
+			MosaicRule::MinValue(key) => sources.sort_by(|a, b| {
+				let get = |s: &dyn OperationTrait| get_number(s.get_tilejson(), key);
+				compare(get(a.as_ref()), get(b.as_ref()))
+			}),
+		}
+	}
+}
+
+fn get_number(tilejson: &TileJSON, key: &str) -> Option<f64> {
+	match tilejson.values.get_byte(key) {
+		Some(byte) => Some(byte as f64),
+		None => tilejson.get_str(key)?.trim().parse().ok(),
+	}
+}
+
+/// Returns for every pixel how much it contributes to the mosaic: the opacity, reduced towards transparent pixels.
+fn get_weights(image: &RgbaImage, feather: u32) -> Vec<f32> {
+	let (width, height) = (image.width() as usize, image.height() as usize);
+	let alpha: Vec<f32> = image.pixels().map(|p| p.0[3] as f32 / 255.0).collect();
+	if feather == 0 {
+		return alpha;
+	}
+
+	// chamfer distance to the nearest transparent pixel
+	let mut distance: Vec<f32> = alpha.iter().map(|a| if *a == 0.0 { 0.0 } else { f32::MAX }).collect();
+	let diagonal = std::f32::consts::SQRT_2;
+	let relax = |distance: &mut Vec<f32>, x: usize, y: usize, dx: isize, dy: isize, step: f32| {
+		let (nx, ny) = (x as isize + dx, y as isize + dy);
+		if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+			return;
+		}
+		let value = distance[ny as usize * width + nx as usize] + step;
+		let index = y * width + x;
+		if value < distance[index] {
+			distance[index] = value;
+		}
+	};
+	for y in 0..height {
+		for x in 0..width {
+			relax(&mut distance, x, y, -1, 0, 1.0);
+			relax(&mut distance, x, y, 0, -1, 1.0);
+			relax(&mut distance, x, y, -1, -1, diagonal);
+			relax(&mut distance, x, y, 1, -1, diagonal);
+		}
+	}
+	for y in (0..height).rev() {
+		for x in (0..width).rev() {
+			relax(&mut distance, x, y, 1, 0, 1.0);
+			relax(&mut distance, x, y, 0, 1, 1.0);
+			relax(&mut distance, x, y, 1, 1, diagonal);
+			relax(&mut distance, x, y, -1, 1, diagonal);
+		}
+	}
+
+	alpha
+		.into_iter()
+		.zip(distance)
+		.map(|(a, d)| a * (d / feather as f32).min(1.0))
+		.collect()
+}
+
+/// Blends the scenes, top scene first. Colors are weighted by the feathered weights, while the opacity of the
+/// mosaic stays the same, so the outer edges of the mosaic don't fade out.
+fn blend_images(images: &[RgbaImage], feather: u32) -> Result<RgbaImage> {
+	let (width, height) = images[0].dimensions();
+	for image in images.iter() {
+		ensure!(
+			image.dimensions() == (width, height),
+			"all scenes must have the same tile size"
+		);
+	}
+
+	let size = (width * height) as usize;
+	let mut color = vec![[0f32; 3]; size];
+	let mut weight = vec![0f32; size];
+	let mut alpha = vec![0f32; size];
+
+	for image in images.iter().rev() {
+		let weights = get_weights(image, feather);
+		for (i, pixel) in image.pixels().enumerate() {
+			let w = weights[i];
+			for (value, channel) in color[i].iter_mut().zip(pixel.0) {
+				*value = channel as f32 * w + *value * (1.0 - w);
+			}
+			weight[i] = w + weight[i] * (1.0 - w);
+			let a = pixel.0[3] as f32 / 255.0;
+			alpha[i] = a + alpha[i] * (1.0 - a);
+		}
+	}
+
+	let mut result = RgbaImage::new(width, height);
+	for (i, pixel) in result.pixels_mut().enumerate() {
+		if weight[i] > 0.0 {
+			for (channel, value) in pixel.0.iter_mut().zip(color[i]) {
+				*channel = (value / weight[i]).round().clamp(0.0, 255.0) as u8;
+			}
+		}
+		pixel.0[3] = (alpha[i] * 255.0).round() as u8;
+	}
+	Ok(result)
+}
+
+/// Combines the tiles of all scenes, top scene first.
+fn mosaic_tiles(blobs: Vec<Blob>, format: TileFormat, feather: u32) -> Result<Blob> {
+	if blobs.len() == 1 {
+		return Ok(blobs.into_iter().next().unwrap());
+	}
+
+	let images = blobs
+		.iter()
+		.map(|blob| blob2image(blob, format))
+		.collect::<Result<Vec<DynamicImage>>>()?;
+	if is_image_opaque(&images[0]) {
+		// the top scene covers the whole tile
+		return Ok(blobs.into_iter().next().unwrap());
+	}
+
+	// blank scenes don't contribute to the mosaic
+	let blank: Vec<bool> = images.iter().map(is_image_transparent).collect();
+	if blank.iter().all(|b| *b) {
+		return Ok(blobs.into_iter().next().unwrap());
+	}
+	let mut scenes: Vec<(Blob, DynamicImage)> = blobs
+		.into_iter()
+		.zip(images)
+		.zip(blank)
+		.filter_map(|(scene, blank)| (!blank).then_some(scene))
+		.collect();
+	if scenes.len() == 1 {
+		return Ok(scenes.pop().unwrap().0);
+	}
+
+	let images: Vec<RgbaImage> = scenes.into_iter().map(|(_, image)| image.to_rgba8()).collect();
+	let image = DynamicImage::ImageRgba8(blend_images(&images, feather)?);
+	if format == TileFormat::JPG {
+		image2blob(&DynamicImage::ImageRgb8(image.to_rgb8()), format)
+	} else {
+		image2blob(&image, format)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	feather: u32,
+	parameters: TilesReaderParameters,
+	sources: Vec<Box<dyn OperationTrait>>,
+	tilejson: TileJSON,
+}
+
+impl ReadOperationTrait for Operation {
+	fn build(
+		vpl_node: VPLNode,
+		factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+			let mut sources = join_all(args.sources.into_iter().map(|c| factory.build_pipeline(c)))
+				.await
+				.into_iter()
+				.collect::<Result<Vec<_>>>()?;
+
+			ensure!(sources.len() > 1, "must have at least two sources");
+
+			MosaicRule::parse(args.rule.as_deref(), args.key.as_deref())?.sort(&mut sources);
+
+			let mut meta = TileJSON::default();
+			let parameters = sources.first().unwrap().get_parameters();
+			let mut pyramid = parameters.bbox_pyramid.clone();
+			let tile_format = parameters.tile_format;
+			ensure!(
+				matches!(tile_format, TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP),
+				"sources must be raster tiles"
+			);
+
+			for source in sources.iter() {
+				meta.merge(source.get_tilejson())?;
+
+				let parameters = source.get_parameters();
+				pyramid.include_bbox_pyramid(&parameters.bbox_pyramid);
+				ensure!(
+					parameters.tile_format == tile_format,
+					"all sources must have the same tile format"
+				);
+			}
+
+			let parameters = TilesReaderParameters::new(tile_format, TileCompression::Uncompressed, pyramid);
+
+			Ok(Box::new(Self {
+				feather: args.feather.unwrap_or(0),
+				tilejson: meta,
+				parameters,
+				sources,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let mut blobs: Vec<Blob> = Vec::new();
+		for source in self.sources.iter() {
+			if let Some(blob) = source.get_tile_data(coord).await? {
+				blobs.push(decompress(blob, &source.get_parameters().tile_compression)?);
+			}
+		}
+		if blobs.is_empty() {
+			return Ok(None);
+		}
+		Ok(Some(mosaic_tiles(blobs, self.parameters.tile_format, self.feather)?))
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let bboxes: Vec<TileBBox> = bbox.clone().iter_bbox_grid(32).collect();
+		let format = self.parameters.tile_format;
+		let feather = self.feather;
+
+		TileStream::from_stream_iter(bboxes.into_iter().map(move |bbox| async move {
+			let mut tiles: Vec<Vec<Blob>> = Vec::new();
+			tiles.resize(bbox.count_tiles() as usize, vec![]);
+
+			for source in self.sources.iter() {
+				source
+					.get_tile_stream(bbox.clone())
+					.await
+					.for_each_sync(|(coord, mut blob)| {
+						let index = bbox.get_tile_index3(&coord).unwrap();
+						blob = decompress(blob, &source.get_parameters().tile_compression).unwrap();
+						tiles[index].push(blob);
+					})
+					.await;
+			}
+
+			TileStream::from_vec(
+				tiles
+					.into_iter()
+					.enumerate()
+					.filter_map(|(i, v)| {
+						if v.is_empty() {
+							None
+						} else {
+							let blob = mosaic_tiles(v, format, feather).unwrap();
+							Some((bbox.get_coord3_by_index(i as u32).unwrap(), blob))
+						}
+					})
+					.collect(),
+			)
+		}))
+		.await
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"from_raster_mosaic"
+	}
+}
+
+#[async_trait]
+impl ReadOperationFactoryTrait for Factory {
+	async fn build<'a>(&self, vpl_node: VPLNode, factory: &'a PipelineFactory) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use imageproc::image::Rgba;
+
+	fn image(color: [u8; 4], opaque: impl Fn(u32, u32) -> bool) -> RgbaImage {
+		RgbaImage::from_fn(
+			16,
+			16,
+			|x, y| {
+				if opaque(x, y) {
+					Rgba(color)
+				} else {
+					Rgba([0, 0, 0, 0])
+				}
+			},
+		)
+	}
+
+	#[test]
+	fn test_rule() {
+		let parse = |rule: Option<&str>, key: Option<&str>| MosaicRule::parse(rule, key).map_err(|e| e.to_string());
+		assert_eq!(parse(None, None), Ok(MosaicRule::First));
+		assert_eq!(parse(Some("latest"), None), Ok(MosaicRule::Latest("datetime".into())));
+		assert_eq!(
+			parse(Some("min_cloud"), Some("clouds")),
+			Ok(MosaicRule::MinValue("clouds".into()))
+		);
+		assert_eq!(
+			parse(Some("newest"), None),
+			Err("unknown rule \"newest\", expected \"first\", \"latest\" or \"min_cloud\"".into())
+		);
+		assert!(parse(Some("first"), Some("datetime")).is_err());
+	}
+
+	#[test]
+	fn test_get_number() -> Result<()> {
+		let tilejson = TileJSON::try_from(r#"{"cloud_cover":"12.5","clouds":3,"name":"x"}"#)?;
+		assert_eq!(get_number(&tilejson, "cloud_cover"), Some(12.5));
+		assert_eq!(get_number(&tilejson, "clouds"), Some(3.0));
+		assert_eq!(get_number(&tilejson, "name"), None);
+		assert_eq!(get_number(&tilejson, "missing"), None);
+		Ok(())
+	}
+
+	#[test]
+	fn test_blend_without_feather() -> Result<()> {
+		let top = image([255, 0, 0, 255], |x, _| x < 8);
+		let bottom = image([0, 0, 255, 255], |_, _| true);
+		let result = blend_images(&[top, bottom], 0)?;
+		assert_eq!(result.get_pixel(7, 5).0, [255, 0, 0, 255]);
+		assert_eq!(result.get_pixel(8, 5).0, [0, 0, 255, 255]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_blend_with_feather() -> Result<()> {
+		let top = image([255, 0, 0, 255], |x, _| x < 8);
+		let bottom = image([0, 0, 255, 255], |_, _| true);
+		let result = blend_images(&[top.clone(), bottom], 4)?;
+		let red: Vec<u8> = (0..9).map(|x| result.get_pixel(x, 5).0[0]).collect();
+		assert_eq!(red, [255, 255, 255, 255, 255, 191, 128, 64, 0]);
+		assert!((0..16).all(|x| result.get_pixel(x, 5).0[3] == 255));
+
+		// without scenes below, the feathered edge keeps its color and opacity
+		let result = blend_images(&[top], 4)?;
+		assert_eq!(result.get_pixel(7, 5).0, [255, 0, 0, 255]);
+		assert_eq!(result.get_pixel(8, 5).0, [0, 0, 0, 0]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_blend_size_mismatch() {
+		let small = RgbaImage::new(8, 8);
+		let big = RgbaImage::new(16, 16);
+		assert_eq!(
+			blend_images(&[small, big], 0).unwrap_err().to_string(),
+			"all scenes must have the same tile size"
+		);
+	}
+
+	#[test]
+	fn test_mosaic_tiles_skips_hidden_and_blank_scenes() -> Result<()> {
+		let png = |image: RgbaImage| image2blob(&DynamicImage::ImageRgba8(image), TileFormat::PNG).unwrap();
+		let opaque = png(image([255, 0, 0, 255], |_, _| true));
+		let half = png(image([0, 255, 0, 255], |x, _| x < 8));
+		let blank = png(image([0, 0, 0, 0], |_, _| false));
+
+		// an opaque top scene hides all scenes below
+		let result = mosaic_tiles(vec![opaque.clone(), half.clone()], TileFormat::PNG, 0)?;
+		assert_eq!(result, opaque);
+
+		// blank scenes are skipped
+		let result = mosaic_tiles(vec![blank.clone(), half.clone(), blank.clone()], TileFormat::PNG, 0)?;
+		assert_eq!(result, half);
+		let result = mosaic_tiles(vec![blank.clone(), blank.clone()], TileFormat::PNG, 0)?;
+		assert_eq!(result, blank);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl(
+				r#"from_raster_mosaic rule="latest" feather=8 [ from_debug format="png", from_debug format="png" ]"#,
+			)
+			.await?;
+		let parameters = operation.get_parameters();
+		assert_eq!(parameters.tile_format, TileFormat::PNG);
+		assert_eq!(parameters.tile_compression, TileCompression::Uncompressed);
+
+		let coord = TileCoord3::new(1, 2, 3)?;
+		let blob = operation.get_tile_data(&coord).await?.unwrap();
+		assert_eq!(blob2image(&blob, TileFormat::PNG)?.width(), 512);
+
+		let bbox = TileBBox::new(3, 0, 0, 1, 1)?;
+		assert_eq!(operation.get_tile_stream(bbox).await.collect().await.len(), 4);
+
+		let error = factory
+			.operation_from_vpl(r#"from_raster_mosaic [ from_debug format="pbf", from_debug format="pbf" ]"#)
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "sources must be raster tiles");
+		Ok(())
+	}
+}
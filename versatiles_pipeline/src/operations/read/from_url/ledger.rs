@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use std::{
+	fs,
+	io::ErrorKind,
+	path::{Path, PathBuf},
+};
+use versatiles_core::types::{Blob, TileCoord3};
+
+/// Remembers every tile that was fetched successfully, so that a second run doesn't request it again.
+///
+/// Each tile is stored as `{z}/{x}/{y}` in the ledger directory. Tiles that the server doesn't have
+/// are stored as empty files. Files are written to a temporary name first and then renamed,
+/// so an interrupted run never leaves a truncated tile behind.
+#[derive(Debug)]
+pub struct Ledger {
+	dir: PathBuf,
+}
+
+impl Ledger {
+	pub fn new(dir: &Path) -> Result<Ledger> {
+		fs::create_dir_all(dir).with_context(|| format!("Failed to create ledger directory {dir:?}"))?;
+		Ok(Ledger { dir: dir.to_path_buf() })
+	}
+
+	fn get_path(&self, coord: &TileCoord3) -> PathBuf {
+		self
+			.dir
+			.join(coord.z.to_string())
+			.join(coord.x.to_string())
+			.join(coord.y.to_string())
+	}
+
+	/// Returns `None` if the tile has not been fetched yet, or `Some(tile)` if it has.
+	pub fn get(&self, coord: &TileCoord3) -> Result<Option<Option<Blob>>> {
+		let path = self.get_path(coord);
+		match fs::read(&path) {
+			Ok(data) if data.is_empty() => Ok(Some(None)),
+			Ok(data) => Ok(Some(Some(Blob::from(data)))),
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e).with_context(|| format!("Failed to read ledger entry {path:?}")),
+		}
+	}
+
+	/// Records a fetched tile, or `None` if the server has no tile at this coordinate.
+	pub fn set(&self, coord: &TileCoord3, tile: Option<&Blob>) -> Result<()> {
+		let path = self.get_path(coord);
+		fs::create_dir_all(path.parent().unwrap())?;
+		let temp_path = path.with_extension("tmp");
+		fs::write(&temp_path, tile.map(|blob| blob.as_slice()).unwrap_or_default())
+			.with_context(|| format!("Failed to write ledger entry {temp_path:?}"))?;
+		fs::rename(&temp_path, &path)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+
+	#[test]
+	fn test_ledger() -> Result<()> {
+		let dir = TempDir::new()?;
+		let ledger = Ledger::new(&dir.path().join("ledger"))?;
+		let coord1 = TileCoord3::new(1, 2, 3)?;
+		let coord2 = TileCoord3::new(2, 1, 3)?;
+
+		assert_eq!(ledger.get(&coord1)?, None);
+		ledger.set(&coord1, Some(&Blob::from("tile")))?;
+		ledger.set(&coord2, None)?;
+		assert_eq!(ledger.get(&coord1)?, Some(Some(Blob::from("tile"))));
+		assert_eq!(ledger.get(&coord2)?, Some(None));
+
+		// a new ledger on the same directory knows all previous tiles
+		let ledger = Ledger::new(&dir.path().join("ledger"))?;
+		assert_eq!(ledger.get(&coord1)?, Some(Some(Blob::from("tile"))));
+		assert!(dir.path().join("ledger/3/1/2").exists());
+		Ok(())
+	}
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+use tokio::{
+	sync::Mutex,
+	time::{sleep_until, Instant},
+};
+
+/// Spaces out the requests to one upstream server.
+///
+/// Every request reserves the next free time slot. A pause, e.g. requested by a `Retry-After` header,
+/// pushes back all slots, so that every pending request waits.
+#[derive(Debug)]
+pub struct RateLimiter {
+	interval: Duration,
+	next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+	/// Creates a limiter for `max_rps` requests per second. A value of 0 disables the limit.
+	pub fn new(max_rps: f32) -> RateLimiter {
+		let interval = if max_rps > 0.0 {
+			Duration::from_secs_f32(1.0 / max_rps)
+		} else {
+			Duration::ZERO
+		};
+		RateLimiter {
+			interval,
+			next_slot: Mutex::new(Instant::now()),
+		}
+	}
+
+	/// Waits until the next request may be sent.
+	pub async fn wait(&self) {
+		let slot = {
+			let mut next_slot = self.next_slot.lock().await;
+			let slot = (*next_slot).max(Instant::now());
+			*next_slot = slot + self.interval;
+			slot
+		};
+		sleep_until(slot).await;
+	}
+
+	/// Delays all following requests by at least `duration`.
+	pub async fn pause(&self, duration: Duration) {
+		let mut next_slot = self.next_slot.lock().await;
+		*next_slot = (*next_slot).max(Instant::now() + duration);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_wait() {
+		let limiter = RateLimiter::new(50.0);
+		let start = Instant::now();
+		for _ in 0..6 {
+			limiter.wait().await;
+		}
+		// the first request is sent immediately, the other five are spaced by 20ms
+		assert!(start.elapsed() >= Duration::from_millis(100));
+	}
+
+	#[tokio::test]
+	async fn test_pause() {
+		let limiter = RateLimiter::new(0.0);
+		let start = Instant::now();
+		limiter.wait().await;
+		assert!(start.elapsed() < Duration::from_millis(50));
+		limiter.pause(Duration::from_millis(100)).await;
+		limiter.wait().await;
+		assert!(start.elapsed() >= Duration::from_millis(100));
+	}
+}
@@ -0,0 +1,427 @@
+mod ledger;
+mod limiter;
+
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{anyhow, bail, ensure, Result};
+use async_trait::async_trait;
+use futures::{future::BoxFuture, stream, StreamExt};
+use ledger::Ledger;
+use limiter::RateLimiter;
+use reqwest::{header::HeaderMap, Client, StatusCode};
+use std::{fmt::Debug, sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+use versatiles_core::{tilejson::TileJSON, types::*};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Reads tiles from a web server, e.g. `from_url url="https://example.org/tiles/{z}/{x}/{y}.png" format="png"`.
+/// Requests are throttled to protect the upstream server: a server responding with status 429 or 503 is given a break
+/// (as long as requested by a `Retry-After` header), other server errors are retried with an exponential backoff.
+struct Args {
+	/// URL template of the tiles, containing the placeholders `{z}`, `{x}` and `{y}`.
+	url: String,
+	/// tile format: "pbf", "jpg", "png", "webp", …
+	format: String,
+	/// compression of the tiles as delivered by the server: "none", "gzip", "brotli" or "zstd". Defaults to "none".
+	compression: Option<String>,
+	/// minimum zoom level. Defaults to 0.
+	min_zoom: Option<u8>,
+	/// maximum zoom level. Defaults to 14.
+	max_zoom: Option<u8>,
+	/// bounding box of the tiles: [west, south, east, north]. Defaults to the whole world.
+	bbox: Option<[f64; 4]>,
+	/// maximum number of requests per second. Defaults to 10, use 0 to disable the limit.
+	max_rps: Option<f32>,
+	/// maximum number of parallel requests. Defaults to 4.
+	max_concurrency: Option<u8>,
+	/// how often a failed request is retried. Defaults to 3.
+	max_retries: Option<u8>,
+	/// directory, relative to the VPL file, that records all fetched tiles.
+	/// Running the pipeline again, e.g. after a failed conversion, reads the recorded tiles instead of requesting them again.
+	ledger: Option<String>,
+}
+
+/// Fetches single tiles, taking care of rate limiting, retries and the ledger.
+#[derive(Debug)]
+struct Fetcher {
+	client: Client,
+	url_template: String,
+	limiter: RateLimiter,
+	semaphore: Semaphore,
+	max_retries: u8,
+	ledger: Option<Ledger>,
+}
+
+impl Fetcher {
+	fn get_url(&self, coord: &TileCoord3) -> String {
+		self
+			.url_template
+			.replace("{z}", &coord.z.to_string())
+			.replace("{x}", &coord.x.to_string())
+			.replace("{y}", &coord.y.to_string())
+	}
+
+	async fn fetch(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if let Some(ledger) = &self.ledger {
+			if let Some(tile) = ledger.get(coord)? {
+				return Ok(tile);
+			}
+		}
+
+		let tile = {
+			let _permit = self.semaphore.acquire().await?;
+			self.request(&self.get_url(coord)).await?
+		};
+
+		if let Some(ledger) = &self.ledger {
+			ledger.set(coord, tile.as_ref())?;
+		}
+		Ok(tile)
+	}
+
+	async fn request(&self, url: &str) -> Result<Option<Blob>> {
+		let mut attempt: u8 = 0;
+		loop {
+			self.limiter.wait().await;
+
+			let (error, retry_after) = match self.client.get(url).send().await {
+				Ok(response) => match response.status() {
+					StatusCode::OK => {
+						let blob = Blob::from(response.bytes().await?);
+						return Ok(if blob.is_empty() { None } else { Some(blob) });
+					}
+					StatusCode::NO_CONTENT | StatusCode::NOT_FOUND => return Ok(None),
+					status @ (StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) => {
+						(anyhow!("status {status}"), parse_retry_after(response.headers()))
+					}
+					status if status.is_server_error() => (anyhow!("status {status}"), None),
+					status => bail!("request to {url} failed with status {status}"),
+				},
+				Err(error) => (anyhow!(error), None),
+			};
+
+			if attempt >= self.max_retries {
+				return Err(error.context(format!("request to {url} failed after {} attempts", attempt + 1)));
+			}
+
+			let delay = retry_after.unwrap_or_else(|| Duration::from_secs(1 << attempt.min(6)));
+			log::warn!("request to {url} failed with {error}, retrying in {delay:?}");
+			self.limiter.pause(delay).await;
+			attempt += 1;
+		}
+	}
+}
+
+/// Parses a `Retry-After` header given in seconds. HTTP dates are not supported and fall back to the default backoff.
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+	let value = headers.get("retry-after")?.to_str().ok()?;
+	value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+#[derive(Debug)]
+struct Operation {
+	fetcher: Arc<Fetcher>,
+	max_concurrency: usize,
+	parameters: TilesReaderParameters,
+	tilejson: TileJSON,
+}
+
+impl ReadOperationTrait for Operation {
+	fn build(vpl_node: VPLNode, factory: &PipelineFactory) -> BoxFuture<'_, Result<Box<dyn OperationTrait>>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+			ensure!(
+				args.url.starts_with("http://") || args.url.starts_with("https://"),
+				"url must start with http:// or https://, but is {:?}",
+				args.url
+			);
+			for placeholder in ["{z}", "{x}", "{y}"] {
+				ensure!(args.url.contains(placeholder), "url must contain {placeholder}");
+			}
+
+			let tile_format = TileFormat::parse_str(&args.format)?;
+			let tile_compression = match &args.compression {
+				Some(compression) => TileCompression::parse_str(compression)?,
+				None => TileCompression::Uncompressed,
+			};
+
+			let min_zoom = args.min_zoom.unwrap_or(0);
+			let max_zoom = args.max_zoom.unwrap_or(14);
+			ensure!(min_zoom <= max_zoom, "min_zoom must not be greater than max_zoom");
+			let bbox = GeoBBox::from(&args.bbox.unwrap_or([-180.0, -85.0511, 180.0, 85.0511]));
+			let bbox_pyramid = TileBBoxPyramid::from_geo_bbox(min_zoom, max_zoom, &bbox);
+
+			let max_concurrency = args.max_concurrency.unwrap_or(4).max(1) as usize;
+			let ledger = match &args.ledger {
+				Some(dir) => Some(Ledger::new(&factory.resolve_path(dir))?),
+				None => None,
+			};
+
+			let fetcher = Fetcher {
+				client: Client::builder().use_rustls_tls().build()?,
+				url_template: args.url,
+				limiter: RateLimiter::new(args.max_rps.unwrap_or(10.0)),
+				semaphore: Semaphore::new(max_concurrency),
+				max_retries: args.max_retries.unwrap_or(3),
+				ledger,
+			};
+
+			let mut tilejson = TileJSON::default();
+			tilejson.update_from_pyramid(&bbox_pyramid);
+
+			Ok(Box::new(Self {
+				fetcher: Arc::new(fetcher),
+				max_concurrency,
+				parameters: TilesReaderParameters::new(tile_format, tile_compression, bbox_pyramid),
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if !self.parameters.bbox_pyramid.contains_coord(coord) {
+			return Ok(None);
+		}
+		self.fetcher.fetch(coord).await
+	}
+
+	async fn get_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		if bbox.intersect_pyramid(&self.parameters.bbox_pyramid).is_err() || bbox.is_empty() {
+			return TileStream::new_empty();
+		}
+
+		let fetcher = self.fetcher.clone();
+		let coords: Vec<TileCoord3> = bbox.into_iter_coords().collect();
+		TileStream::from_stream(
+			stream::iter(coords)
+				.map(move |coord| {
+					let fetcher = fetcher.clone();
+					async move { (coord, fetcher.fetch(&coord).await) }
+				})
+				.buffer_unordered(self.max_concurrency)
+				.filter_map(|(coord, result)| async move {
+					match result {
+						Ok(tile) => tile.map(|blob| (coord, blob)),
+						Err(error) => {
+							log::error!("failed to fetch tile {coord:?}: {error:?}");
+							None
+						}
+					}
+				})
+				.boxed(),
+		)
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"from_url"
+	}
+}
+
+#[async_trait]
+impl ReadOperationFactoryTrait for Factory {
+	async fn build<'a>(&self, vpl_node: VPLNode, factory: &'a PipelineFactory) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex,
+	};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	type Handler = dyn Fn(&str, usize) -> (u16, Vec<(&'static str, String)>, String) + Send + Sync;
+
+	/// A minimal HTTP server that answers every request with `handler(path, request_number)`.
+	struct TestServer {
+		port: u16,
+		requests: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl TestServer {
+		async fn start(handler: Box<Handler>) -> Result<TestServer> {
+			let listener = TcpListener::bind("127.0.0.1:0").await?;
+			let port = listener.local_addr()?.port();
+			let requests = Arc::new(Mutex::new(Vec::new()));
+			let counter = Arc::new(AtomicUsize::new(0));
+			let handler = Arc::new(handler);
+
+			let requests_clone = requests.clone();
+			tokio::spawn(async move {
+				loop {
+					let (mut socket, _) = listener.accept().await.unwrap();
+					let requests = requests_clone.clone();
+					let counter = counter.clone();
+					let handler = handler.clone();
+					tokio::spawn(async move {
+						let mut buffer = Vec::new();
+						let mut chunk = [0u8; 1024];
+						while !buffer.ends_with(b"\r\n\r\n") {
+							let n = socket.read(&mut chunk).await.unwrap();
+							if n == 0 {
+								return;
+							}
+							buffer.extend_from_slice(&chunk[..n]);
+						}
+						let request = String::from_utf8_lossy(&buffer);
+						let path = request.split(' ').nth(1).unwrap().to_string();
+						requests.lock().unwrap().push(path.clone());
+
+						let (status, headers, body) = handler(&path, counter.fetch_add(1, Ordering::SeqCst));
+						let mut response = format!("HTTP/1.1 {status} X\r\ncontent-length: {}\r\n", body.len());
+						for (key, value) in headers {
+							response.push_str(&format!("{key}: {value}\r\n"));
+						}
+						response.push_str("connection: close\r\n\r\n");
+						response.push_str(&body);
+						socket.write_all(response.as_bytes()).await.unwrap();
+					});
+				}
+			});
+
+			Ok(TestServer { port, requests })
+		}
+
+		fn url(&self) -> String {
+			format!("http://127.0.0.1:{}/{{z}}/{{x}}/{{y}}.pbf", self.port)
+		}
+
+		fn requests(&self) -> Vec<String> {
+			let mut requests = self.requests.lock().unwrap().clone();
+			requests.sort();
+			requests
+		}
+	}
+
+	async fn build(args: &str) -> Result<Box<dyn OperationTrait>> {
+		PipelineFactory::new_dummy()
+			.operation_from_vpl(&format!("from_url format=\"pbf\" max_rps=0 {args}"))
+			.await
+	}
+
+	#[tokio::test]
+	async fn test_stream_and_ledger() -> Result<()> {
+		let server = TestServer::start(Box::new(|path, _| match path {
+			"/1/1/1.pbf" => (404, vec![], String::new()),
+			_ => (200, vec![], format!("tile {path}")),
+		}))
+		.await?;
+		let dir = assert_fs::TempDir::new()?;
+		let args = format!(
+			"url=\"{}\" max_zoom=1 ledger=\"{}\"",
+			server.url(),
+			dir.path().join("ledger").to_str().unwrap()
+		);
+
+		let get_tiles = || async {
+			let operation = build(&args).await?;
+			let mut tiles = operation
+				.get_tile_stream(TileBBox::new_full(1)?)
+				.await
+				.collect()
+				.await
+				.into_iter()
+				.map(|(coord, blob)| format!("{coord:?}: {}", blob.as_str()))
+				.collect::<Vec<_>>();
+			tiles.sort();
+			Ok::<_, anyhow::Error>(tiles)
+		};
+
+		let expected = [
+			"TileCoord3(0, 0, 1): tile /1/0/0.pbf",
+			"TileCoord3(0, 1, 1): tile /1/0/1.pbf",
+			"TileCoord3(1, 0, 1): tile /1/1/0.pbf",
+		];
+		assert_eq!(get_tiles().await?, expected);
+		assert_eq!(
+			server.requests(),
+			["/1/0/0.pbf", "/1/0/1.pbf", "/1/1/0.pbf", "/1/1/1.pbf"]
+		);
+
+		// the second run reads everything, including the missing tile, from the ledger
+		assert_eq!(get_tiles().await?, expected);
+		assert_eq!(server.requests().len(), 4);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_retry_after() -> Result<()> {
+		let server = TestServer::start(Box::new(|_, n| match n {
+			0 => (429, vec![("retry-after", String::from("0"))], String::new()),
+			1 => (503, vec![("retry-after", String::from("0"))], String::new()),
+			_ => (200, vec![], String::from("tile")),
+		}))
+		.await?;
+
+		let operation = build(&format!("url=\"{}\"", server.url())).await?;
+		let blob = operation.get_tile_data(&TileCoord3::new(1, 2, 3)?).await?.unwrap();
+		assert_eq!(blob.as_str(), "tile");
+		assert_eq!(server.requests(), ["/3/1/2.pbf", "/3/1/2.pbf", "/3/1/2.pbf"]);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() -> Result<()> {
+		let server = TestServer::start(Box::new(|path, _| match path {
+			"/0/0/0.pbf" => (500, vec![], String::new()),
+			_ => (403, vec![], String::new()),
+		}))
+		.await?;
+
+		let operation = build(&format!("url=\"{}\" max_retries=0", server.url())).await?;
+		let error = operation.get_tile_data(&TileCoord3::new(0, 0, 0)?).await.unwrap_err();
+		assert!(
+			error.to_string().ends_with("0/0/0.pbf failed after 1 attempts"),
+			"{error}"
+		);
+		let error = operation.get_tile_data(&TileCoord3::new(0, 0, 1)?).await.unwrap_err();
+		assert!(
+			error.to_string().ends_with("failed with status 403 Forbidden"),
+			"{error}"
+		);
+
+		// tiles outside the zoom range are never requested
+		assert_eq!(operation.get_tile_data(&TileCoord3::new(0, 0, 15)?).await?, None);
+		assert_eq!(server.requests().len(), 2);
+
+		assert!(build("url=\"ftp://example.org/{z}/{x}/{y}\"").await.is_err());
+		assert!(build("url=\"https://example.org/{z}/{x}\"").await.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_retry_after() {
+		let mut headers = HeaderMap::new();
+		assert_eq!(parse_retry_after(&headers), None);
+		headers.insert("retry-after", "120".parse().unwrap());
+		assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(120)));
+		headers.insert("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+		assert_eq!(parse_retry_after(&headers), None);
+	}
+}
@@ -6,9 +6,8 @@ use crate::{
 use anyhow::{ensure, Result};
 use async_trait::async_trait;
 use futures::future::{join_all, BoxFuture};
-use std::collections::HashMap;
 use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
-use versatiles_geometry::vector_tile::{VectorTile, VectorTileLayer};
+use versatiles_geometry::vector_tile::{MergeOptions, VectorTile};
 
 #[derive(versatiles_derive::VPLDecode, Clone, Debug)]
 /// Merges multiple vector tile sources. Each layer will contain all features from the same layer of all sources.
@@ -25,18 +24,12 @@ struct Operation {
 }
 
 fn merge_tiles(blobs: Vec<Blob>) -> Result<Blob> {
-	let mut layers = HashMap::<String, VectorTileLayer>::new();
+	let options = MergeOptions::default();
+	let mut tile = VectorTile::new(vec![]);
 	for blob in blobs.into_iter() {
-		let tile = VectorTile::from_blob(&blob)?;
-		for new_layer in tile.layers {
-			if let Some(layer) = layers.get_mut(&new_layer.name) {
-				layer.add_from_layer(new_layer)?;
-			} else {
-				layers.insert(new_layer.name.clone(), new_layer);
-			}
-		}
+		tile.merge(VectorTile::from_blob(&blob)?, &options)?;
 	}
-	VectorTile::new(layers.into_values().collect()).to_blob()
+	tile.to_blob()
 }
 
 impl ReadOperationTrait for Operation {
@@ -167,6 +160,7 @@ mod tests {
 	use crate::helpers::mock_vector_source::{arrange_tiles, MockVectorSource};
 	use itertools::Itertools;
 	use std::{ops::BitXor, path::Path};
+	use versatiles_geometry::vector_tile::VectorTileLayer;
 
 	pub fn check_tile(blob: &Blob, coord: &TileCoord3) -> String {
 		use versatiles_geometry::GeoValue;
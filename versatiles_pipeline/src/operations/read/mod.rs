@@ -2,14 +2,20 @@ use crate::traits::ReadOperationFactoryTrait;
 
 mod from_container;
 pub mod from_debug;
+mod from_geojson;
 mod from_overlayed;
+mod from_raster_mosaic;
+mod from_url;
 mod from_vectortiles_merged;
 
 pub fn get_read_operation_factories() -> Vec<Box<dyn ReadOperationFactoryTrait>> {
 	vec![
 		Box::new(from_container::Factory {}),
 		Box::new(from_debug::Factory {}),
+		Box::new(from_geojson::Factory {}),
 		Box::new(from_overlayed::Factory {}),
+		Box::new(from_raster_mosaic::Factory {}),
+		Box::new(from_url::Factory {}),
 		Box::new(from_vectortiles_merged::Factory {}),
 	]
 }
@@ -0,0 +1 @@
+pub mod to_container;
@@ -0,0 +1,159 @@
+use crate::{traits::*, vpl::VPLNode};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use versatiles_core::{tilejson::TileJSON, types::*};
+
+pub const TAG_NAME: &str = "to_container";
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Writes the tiles of the pipeline up to this point into a tile container and passes them on unchanged.
+/// A pipeline can contain several sinks, e.g. to write the original and a simplified version of the same source:
+/// `from_container filename="in.mbtiles" | to_container filename="raw.versatiles" | vector_simplify | to_container filename="small.versatiles"`.
+/// Sinks are written one after another, in the order in which they appear in the VPL.
+struct Args {
+	/// The filename of the tile container. This is relative to the path of the VPL file.
+	/// For example: `filename="world.versatiles"`.
+	filename: String,
+	/// Compression of the written tiles: "br", "gzip", "zstd" or "none". Defaults to the compression of the pipeline.
+	compression: Option<String>,
+}
+
+/// A container that has to be written when the pipeline is run.
+#[derive(Debug)]
+pub struct Sink {
+	pub filename: String,
+	pub compression: Option<TileCompression>,
+	pub operation: Box<dyn OperationTrait>,
+}
+
+/// Builds a sink for `source`. Returns the sink and an operation that passes the tiles of `source` on.
+pub fn build(vpl_node: &VPLNode, source: Box<dyn OperationTrait>) -> Result<(Sink, Box<dyn OperationTrait>)> {
+	let args = Args::from_vpl_node(vpl_node)?;
+	let compression = args
+		.compression
+		.as_deref()
+		.map(TileCompression::parse_str)
+		.transpose()?;
+	let source = SharedOperation(Arc::from(source));
+
+	Ok((
+		Sink {
+			filename: args.filename,
+			compression,
+			operation: Box::new(source.clone()),
+		},
+		Box::new(source),
+	))
+}
+
+pub fn get_docs() -> String {
+	Args::get_docs()
+}
+
+/// Lets the sink and the following operations use the same source.
+#[derive(Clone, Debug)]
+struct SharedOperation(Arc<dyn OperationTrait>);
+
+#[async_trait]
+impl OperationTrait for SharedOperation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		self.0.get_parameters()
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		self.0.get_tilejson()
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		self.0.get_tile_data(coord).await
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		self.0.get_tile_stream(bbox).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::PipelineFactory;
+	use anyhow::Result;
+	use futures::future::BoxFuture;
+	use std::sync::{Arc, Mutex};
+	use versatiles_core::types::{TileBBox, TileCompression};
+
+	type Written = Arc<Mutex<Vec<(String, Option<TileCompression>, usize)>>>;
+
+	fn factory() -> (PipelineFactory, Written) {
+		let written: Written = Arc::default();
+		let mut factory = PipelineFactory::new_dummy();
+		let list = written.clone();
+		factory.set_writer(Box::new(
+			move |operation, filename, compression| -> BoxFuture<Result<()>> {
+				let list = list.clone();
+				Box::pin(async move {
+					let tiles = operation.get_tile_stream(TileBBox::new_full(2)?).await.collect().await;
+					list.lock().unwrap().push((filename, compression, tiles.len()));
+					Ok(())
+				})
+			},
+		));
+		(factory, written)
+	}
+
+	#[tokio::test]
+	async fn test_run_sinks() -> Result<()> {
+		let (factory, written) = factory();
+		factory
+			.run_vpl(
+				"from_container filename=dummy | to_container filename=all.versatiles | filter_zoom max=1 | to_container filename=low.mbtiles compression=br",
+			)
+			.await?;
+		assert_eq!(
+			*written.lock().unwrap(),
+			vec![
+				(String::from("all.versatiles"), None, 16),
+				(String::from("low.mbtiles"), Some(TileCompression::Brotli), 0),
+			]
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() -> Result<()> {
+		let (factory, _) = factory();
+		let error = |vpl: &'static str| {
+			let factory = &factory;
+			async move { factory.run_vpl(vpl).await.unwrap_err().to_string() }
+		};
+		assert_eq!(
+			error("from_container filename=dummy").await,
+			"pipeline must end with a 'to_container' operation"
+		);
+		assert!(error("from_container filename=dummy | to_container")
+			.await
+			.contains("filename"));
+		assert_eq!(
+			error("from_container filename=dummy | to_container filename=a compression=zip").await,
+			"Unknown tile compression. Expected brotli, gzip, zstd or none"
+		);
+
+		assert!(PipelineFactory::new_dummy()
+			.run_vpl("from_container filename=dummy | to_container filename=a")
+			.await
+			.is_err());
+		assert_eq!(
+			factory
+				.operation_from_vpl("from_container filename=dummy | to_container filename=a")
+				.await
+				.unwrap_err()
+				.to_string(),
+			"'to_container' can only be used when running a pipeline, not when reading from it"
+		);
+		assert!(factory
+			.operation_from_vpl("from_container filename=dummy")
+			.await
+			.is_ok());
+		Ok(())
+	}
+}
@@ -0,0 +1,178 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{fmt::Debug, sync::Arc};
+use versatiles_core::{tilejson::TileJSON, types::*};
+use versatiles_geometry::PolygonFilter;
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Filter tiles by the polygons of a GeoJSON file, e.g. the outline of a country.
+/// Only tiles overlapping a polygon are kept.
+struct Args {
+	/// The filename of the GeoJSON file. This is relative to the path of the VPL file.
+	/// For example: `filename="germany.geojson"`.
+	filename: String,
+}
+
+#[derive(Debug)]
+struct Operation {
+	parameters: TilesReaderParameters,
+	polygon_filter: Arc<PolygonFilter>,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+			let polygon_filter = PolygonFilter::from_geojson_file(&factory.resolve_path(&args.filename))?;
+
+			let mut parameters = source.get_parameters().clone();
+			parameters
+				.bbox_pyramid
+				.intersect_geo_bbox(&polygon_filter.get_geo_bbox());
+
+			let mut tilejson = source.get_tilejson().clone();
+			tilejson.update_from_pyramid(&parameters.bbox_pyramid);
+
+			Ok(Box::new(Self {
+				parameters,
+				polygon_filter: Arc::new(polygon_filter),
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if self.parameters.bbox_pyramid.contains_coord(coord) && self.polygon_filter.contains_coord(coord) {
+			self.source.get_tile_data(coord).await
+		} else {
+			Ok(None)
+		}
+	}
+
+	async fn get_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		bbox.intersect_pyramid(&self.parameters.bbox_pyramid).unwrap();
+		let polygon_filter = self.polygon_filter.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.filter_coord(move |coord| polygon_filter.contains_coord(coord))
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"filter_polygon"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+
+	// a triangle covering the tiles of zoom level 1, except the south east
+	const GEOJSON: &str = r#"{"type":"FeatureCollection","features":[
+		{"type":"Feature","properties":{},"geometry":{"type":"Polygon","coordinates":[[[-80,60],[80,60],[-80,-60],[-80,60]]]}}
+	]}"#;
+
+	async fn get_operation(dir: &TempDir) -> Result<Box<dyn OperationTrait>> {
+		let path = dir.path().join("triangle.geojson");
+		std::fs::write(&path, GEOJSON)?;
+		PipelineFactory::new_dummy()
+			.operation_from_vpl(&format!(
+				"from_debug format=pbf | filter_polygon filename=\"{}\"",
+				path.display()
+			))
+			.await
+	}
+
+	#[tokio::test]
+	async fn test_get_tile_data() -> Result<()> {
+		let dir = TempDir::new()?;
+		let operation = get_operation(&dir).await?;
+
+		assert!(operation.get_tile_data(&TileCoord3::new(0, 0, 1)?).await?.is_some());
+		assert!(operation.get_tile_data(&TileCoord3::new(1, 0, 1)?).await?.is_some());
+		assert!(operation.get_tile_data(&TileCoord3::new(0, 1, 1)?).await?.is_some());
+		assert!(operation.get_tile_data(&TileCoord3::new(1, 1, 1)?).await?.is_none());
+
+		// outside of the bounding box of the triangle
+		assert!(operation.get_tile_data(&TileCoord3::new(0, 0, 3)?).await?.is_none());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_get_tile_stream() -> Result<()> {
+		let dir = TempDir::new()?;
+		let operation = get_operation(&dir).await?;
+
+		let mut coords: Vec<TileCoord3> = operation
+			.get_tile_stream(TileBBox::new_full(1)?)
+			.await
+			.collect()
+			.await
+			.into_iter()
+			.map(|(coord, _)| coord)
+			.collect();
+		coords.sort_by_key(|c| c.get_sort_index());
+		assert_eq!(
+			coords,
+			vec![
+				TileCoord3::new(0, 0, 1)?,
+				TileCoord3::new(1, 0, 1)?,
+				TileCoord3::new(0, 1, 1)?
+			]
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_missing_file() {
+		let error = PipelineFactory::new_dummy()
+			.operation_from_vpl("from_debug format=pbf | filter_polygon filename=\"missing.geojson\"")
+			.await
+			.unwrap_err();
+		assert!(format!("{error:#}").contains("Failed to open"), "{error:#}");
+	}
+}
@@ -1,13 +1,45 @@
 use crate::traits::TransformOperationFactoryTrait;
 
 mod filter_bbox;
+mod filter_polygon;
 mod filter_zoom;
+mod overzoom;
+mod raster_contours;
+mod raster_format;
+mod raster_retile;
+mod raster_watermark;
+mod vector_dissolve;
+mod vector_expand_keys;
+mod vector_limit_features;
+mod vector_merge_layers;
+mod vector_minify_keys;
+mod vector_rename_layers;
+mod vector_reproject;
+mod vector_simplify;
+mod vector_translate;
 mod vectortiles_update_properties;
+mod watchdog;
 
 pub fn get_transform_operation_factories() -> Vec<Box<dyn TransformOperationFactoryTrait>> {
 	vec![
 		Box::new(filter_bbox::Factory {}),
+		Box::new(filter_polygon::Factory {}),
 		Box::new(filter_zoom::Factory {}),
+		Box::new(overzoom::Factory {}),
+		Box::new(raster_contours::Factory {}),
+		Box::new(raster_format::Factory {}),
+		Box::new(raster_retile::Factory {}),
+		Box::new(raster_watermark::Factory {}),
+		Box::new(vector_dissolve::Factory {}),
+		Box::new(vector_expand_keys::Factory {}),
+		Box::new(vector_limit_features::Factory {}),
+		Box::new(vector_merge_layers::Factory {}),
+		Box::new(vector_minify_keys::Factory {}),
+		Box::new(vector_rename_layers::Factory {}),
+		Box::new(vector_reproject::Factory {}),
+		Box::new(vector_simplify::Factory {}),
+		Box::new(vector_translate::Factory {}),
 		Box::new(vectortiles_update_properties::Factory {}),
+		Box::new(watchdog::Factory {}),
 	]
 }
@@ -0,0 +1,248 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::fmt::Debug;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_geometry::vector_tile::VectorTile;
+use versatiles_image::helper::{blob2image, image2blob, overzoom_image};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Adds zoom levels beyond the maximum zoom level of the source, e.g. for tools that need z15+ tiles.
+/// Each new tile is cut out of its ancestor at the maximum zoom level of the source and scaled up:
+/// raster tiles are cropped and resized, vector tiles are clipped and their geometries scaled.
+struct Args {
+	/// The new maximum zoom level.
+	max_zoom: u8,
+	/// Margin around vector tiles that is kept, in pixels of a 256 pixel tile (default: 4).
+	buffer: Option<f32>,
+}
+
+#[derive(Debug)]
+struct Operation {
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	source_compression: TileCompression,
+	/// maximum zoom level of the source
+	source_zoom: u8,
+	buffer: f64,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+			ensure!(args.max_zoom <= 30, "max_zoom must not be greater than 30");
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(
+				matches!(
+					parameters.tile_format,
+					TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP | TileFormat::PBF
+				),
+				"source must be raster or vector tiles"
+			);
+			let source_zoom = parameters
+				.bbox_pyramid
+				.get_zoom_max()
+				.context("source must not be empty")?;
+
+			let source_bbox = parameters.bbox_pyramid.get_level_bbox(source_zoom).clone();
+			for level in source_zoom + 1..=args.max_zoom {
+				let scale = 1u32 << (level - source_zoom);
+				parameters.bbox_pyramid.set_level_bbox(TileBBox::new(
+					level,
+					source_bbox.x_min * scale,
+					source_bbox.y_min * scale,
+					(source_bbox.x_max + 1) * scale - 1,
+					(source_bbox.y_max + 1) * scale - 1,
+				)?);
+			}
+
+			let source_compression = parameters.tile_compression;
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let mut tilejson = source.get_tilejson().clone();
+			tilejson.set_byte("maxzoom", args.max_zoom.max(source_zoom))?;
+
+			Ok(Box::new(Self {
+				parameters,
+				source,
+				source_compression,
+				source_zoom,
+				buffer: args.buffer.unwrap_or(4.0) as f64 / 256.0,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+
+	/// Cuts the tile `coord` out of the source tile `blob`.
+	fn overzoom(&self, blob: Blob, coord: &TileCoord3) -> Result<Blob> {
+		let blob = decompress(blob, &self.source_compression)?;
+		if coord.z <= self.source_zoom {
+			return Ok(blob);
+		}
+
+		let level_diff = coord.z - self.source_zoom;
+		let mask = (1u32 << level_diff) - 1;
+		let (x, y) = (coord.x & mask, coord.y & mask);
+
+		let format = self.parameters.tile_format;
+		Ok(match format {
+			TileFormat::PBF => VectorTile::from_blob(&blob)?
+				.overzoom(level_diff, x, y, self.buffer)?
+				.to_blob()?,
+			TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP => {
+				image2blob(&overzoom_image(&blob2image(&blob, format)?, level_diff, x, y)?, format)?
+			}
+			_ => bail!("tile format {} can not be overzoomed", format.as_str()),
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if !self.parameters.bbox_pyramid.contains_coord(coord) {
+			return Ok(None);
+		}
+		let level_diff = coord.z.saturating_sub(self.source_zoom);
+		let source_coord = TileCoord3::new(coord.x >> level_diff, coord.y >> level_diff, coord.z - level_diff)?;
+		let Some(blob) = self.source.get_tile_data(&source_coord).await? else {
+			return Ok(None);
+		};
+		Ok(Some(self.overzoom(blob, coord)?))
+	}
+
+	async fn get_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		if bbox.level <= self.source_zoom {
+			let source_compression = self.source_compression;
+			return self
+				.source
+				.get_tile_stream(bbox)
+				.await
+				.map_blob_parallel(move |blob| decompress(blob, &source_compression).unwrap());
+		}
+
+		bbox.intersect_pyramid(&self.parameters.bbox_pyramid).unwrap();
+		if bbox.is_empty() {
+			return TileStream::new_empty();
+		}
+
+		let level_diff = bbox.level - self.source_zoom;
+		let bboxes: Vec<TileBBox> = bbox.iter_bbox_grid(64).collect();
+
+		TileStream::from_stream_iter(bboxes.into_iter().map(move |bbox| async move {
+			let source_bbox = TileBBox::new(
+				self.source_zoom,
+				bbox.x_min >> level_diff,
+				bbox.y_min >> level_diff,
+				bbox.x_max >> level_diff,
+				bbox.y_max >> level_diff,
+			)
+			.unwrap();
+			let source_tiles = self.source.get_tile_stream(source_bbox).await.collect().await;
+
+			let scale = 1u32 << level_diff;
+			let mut tiles = Vec::new();
+			for (source_coord, blob) in source_tiles {
+				for y in 0..scale {
+					for x in 0..scale {
+						let coord =
+							TileCoord3::new(source_coord.x * scale + x, source_coord.y * scale + y, bbox.level).unwrap();
+						if bbox.contains3(&coord) {
+							tiles.push((coord, self.overzoom(blob.clone(), &coord).unwrap()));
+						}
+					}
+				}
+			}
+			TileStream::from_vec(tiles)
+		}))
+		.await
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"overzoom"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use imageproc::image::GenericImageView;
+
+	#[tokio::test]
+	async fn test_raster() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_debug format=png | filter_zoom max=3 | overzoom max_zoom=5")
+			.await?;
+
+		let parameters = operation.get_parameters();
+		assert_eq!(parameters.tile_compression, TileCompression::Uncompressed);
+		assert_eq!(parameters.bbox_pyramid.get_zoom_max(), Some(5));
+		assert_eq!(operation.get_tilejson().values.get_byte("maxzoom"), Some(5));
+
+		let blob = operation.get_tile_data(&TileCoord3::new(20, 10, 5)?).await?.unwrap();
+		assert_eq!(blob2image(&blob, TileFormat::PNG)?.dimensions(), (512, 512));
+
+		let tiles = operation.get_tile_stream(TileBBox::new_full(5)?).await.collect().await;
+		assert_eq!(tiles.len(), 1024);
+		let tiles = operation.get_tile_stream(TileBBox::new_full(2)?).await.collect().await;
+		assert_eq!(tiles.len(), 16);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_vector() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | overzoom max_zoom=10")
+			.await?;
+
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 10)?).await?.unwrap();
+		VectorTile::from_blob(&blob)?;
+		assert!(operation.get_tile_data(&TileCoord3::new(0, 0, 11)?).await?.is_none());
+
+		assert!(factory
+			.operation_from_vpl("from_container filename=dummy | overzoom")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
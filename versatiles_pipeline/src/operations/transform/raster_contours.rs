@@ -0,0 +1,331 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{collections::BTreeMap, sync::Arc};
+use versatiles_core::{
+	tilejson::{TileJSON, VectorLayer},
+	types::*,
+	utils::decompress,
+};
+use versatiles_geometry::{
+	math::contour_lines,
+	vector_tile::{VectorTile, VectorTileLayer},
+	GeoFeature, Geometry,
+};
+use versatiles_image::{
+	helper::blob2image,
+	terrain::{image2elevations, TerrainEncoding},
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Generates vector tiles with contour lines from terrain-RGB raster tiles.
+/// The outer pixels of a tile are stretched to the tile border, so the contour lines of neighbouring tiles meet at the border.
+struct Args {
+	/// Elevation difference between two contour lines in meters (default: 10).
+	interval: Option<f32>,
+	/// Every contour line at a multiple of this elevation gets the property `index=true`, e.g. to draw it thicker. By default no line is an index line.
+	index_interval: Option<f32>,
+	/// Name of the property containing the elevation (default: "ele").
+	elevation_property: Option<String>,
+	/// Name of the vector layer (default: "contours").
+	layer: Option<String>,
+	/// Encoding of the elevations: "mapbox" (default) or "terrarium".
+	encoding: Option<String>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	interval: f64,
+	index_interval: Option<f64>,
+	elevation_property: String,
+	layer: String,
+	encoding: TerrainEncoding,
+	tile_format: TileFormat,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn from_args(args: Args, tile_format: TileFormat, tile_compression: TileCompression) -> Result<Runner> {
+		let interval = args.interval.unwrap_or(10.0) as f64;
+		ensure!(interval > 0.0, "interval must be positive");
+		let index_interval = args.index_interval.map(|v| v as f64);
+		if let Some(index_interval) = index_interval {
+			ensure!(index_interval > 0.0, "index_interval must be positive");
+		}
+		Ok(Runner {
+			interval,
+			index_interval,
+			elevation_property: args.elevation_property.unwrap_or(String::from("ele")),
+			layer: args.layer.unwrap_or(String::from("contours")),
+			encoding: TerrainEncoding::parse(args.encoding.as_deref().unwrap_or("mapbox"))?,
+			tile_format,
+			tile_compression,
+		})
+	}
+
+	fn is_index(&self, elevation: f64) -> bool {
+		self.index_interval.is_some_and(|index_interval| {
+			let ratio = elevation / index_interval;
+			(ratio - ratio.round()).abs() < 1e-9
+		})
+	}
+
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let image = blob2image(&decompress(blob, &self.tile_compression)?, self.tile_format)?;
+		let (width, height) = (image.width() as usize, image.height() as usize);
+		let elevations = image2elevations(&image, self.encoding);
+
+		let (min, max) = elevations
+			.iter()
+			.filter(|v| !v.is_nan())
+			.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+				(min.min(*v), max.max(*v))
+			});
+		if min > max {
+			return Ok(None);
+		}
+
+		// the outer pixels are stretched to the tile border
+		let extent = 4096u32;
+		let scale_x = extent as f64 / (width.max(2) - 1) as f64;
+		let scale_y = extent as f64 / (height.max(2) - 1) as f64;
+
+		let mut features = Vec::new();
+		let mut step = (min / self.interval).ceil() as i64;
+		while step as f64 * self.interval <= max {
+			let elevation = step as f64 * self.interval;
+			step += 1;
+
+			let lines: Vec<Vec<[f64; 2]>> = contour_lines(&elevations, width, height, elevation)
+				.into_iter()
+				.map(|line| line.into_iter().map(|[x, y]| [x * scale_x, y * scale_y]).collect())
+				.collect();
+			if lines.is_empty() {
+				continue;
+			}
+
+			let mut feature = GeoFeature::new(Geometry::new_multi_line_string(lines));
+			if elevation.fract() == 0.0 {
+				feature.set_property(self.elevation_property.clone(), elevation as i64);
+			} else {
+				feature.set_property(self.elevation_property.clone(), elevation);
+			}
+			if self.index_interval.is_some() {
+				feature.set_property(String::from("index"), self.is_index(elevation));
+			}
+			features.push(feature);
+		}
+		if features.is_empty() {
+			return Ok(None);
+		}
+
+		let layer = VectorTileLayer::from_features(self.layer.clone(), features, extent, 1)?;
+		Ok(Some(
+			VectorTile::new(vec![layer])
+				.to_blob()
+				.context("Failed to convert VectorTile to Blob")?,
+		))
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let source_parameters = source.get_parameters();
+			ensure!(
+				matches!(source_parameters.tile_format, TileFormat::PNG | TileFormat::WEBP),
+				"source must be lossless raster tiles (png or webp)"
+			);
+
+			let runner = Runner::from_args(args, source_parameters.tile_format, source_parameters.tile_compression)?;
+			let parameters = TilesReaderParameters::new(
+				TileFormat::PBF,
+				TileCompression::Uncompressed,
+				source_parameters.bbox_pyramid.clone(),
+			);
+
+			let mut fields = BTreeMap::new();
+			fields.insert(runner.elevation_property.clone(), String::from("Number"));
+			if runner.index_interval.is_some() {
+				fields.insert(String::from("index"), String::from("Boolean"));
+			}
+			let mut tilejson = source.get_tilejson().clone();
+			tilejson.vector_layers.0.insert(
+				runner.layer.clone(),
+				VectorLayer {
+					fields,
+					description: None,
+					minzoom: parameters.bbox_pyramid.get_zoom_min(),
+					maxzoom: parameters.bbox_pyramid.get_zoom_max(),
+				},
+			);
+
+			Ok(Box::new(Self {
+				runner: Arc::new(runner),
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		match self.source.get_tile_data(coord).await? {
+			Some(blob) => self.runner.run(blob),
+			None => Ok(None),
+		}
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"raster_contours"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::GeoValue;
+	use versatiles_image::{helper::image2blob, terrain::elevations2image};
+
+	fn runner(args: &str) -> Result<Runner> {
+		let args = Args::from_vpl_node(&VPLNode::from_str(&format!("raster_contours {args}"))?)?;
+		Runner::from_args(args, TileFormat::PNG, TileCompression::Uncompressed)
+	}
+
+	/// A terrain tile of 64×64 pixels, rising from 0 m on the left to 126 m on the right.
+	fn make_tile(encoding: TerrainEncoding) -> Result<Blob> {
+		let elevations: Vec<f64> = (0..64 * 64).map(|i| (i % 64) as f64 * 2.0).collect();
+		image2blob(&elevations2image(&elevations, 64, 64, encoding), TileFormat::PNG)
+	}
+
+	fn get_features(blob: Blob) -> Result<Vec<GeoFeature>> {
+		let tile = VectorTile::from_blob(&blob)?;
+		assert_eq!(tile.layers.len(), 1);
+		tile.layers[0].to_features()
+	}
+
+	#[test]
+	fn test_contours() -> Result<()> {
+		let runner = runner("interval=25 index_interval=50 elevation_property=\"height\"")?;
+		let features = get_features(runner.run(make_tile(TerrainEncoding::Mapbox)?)?.unwrap())?;
+
+		let values: Vec<String> = features
+			.iter()
+			.map(|f| format!("{:?},{:?}", f.properties.get("height"), f.properties.get("index")))
+			.collect();
+		assert_eq!(
+			values,
+			[
+				"Some(Int(25)),Some(Bool(false))",
+				"Some(Int(50)),Some(Bool(true))",
+				"Some(Int(75)),Some(Bool(false))",
+				"Some(Int(100)),Some(Bool(true))",
+				"Some(Int(125)),Some(Bool(false))",
+			]
+		);
+
+		// the line at 50 m is vertical and spans the whole tile
+		let Geometry::MultiLineString(lines) = features[1].geometry.clone().into_multi() else {
+			panic!("expected lines");
+		};
+		let xs: Vec<f64> = lines.0[0].iter().map(|p| p[0]).collect();
+		let ys: Vec<f64> = lines.0[0].iter().map(|p| p[1]).collect();
+		assert!(xs.iter().all(|x| (x - 25.0 * 4096.0 / 63.0).abs() < 1.0), "{xs:?}");
+		assert_eq!(ys.iter().cloned().fold(f64::INFINITY, f64::min), 0.0);
+		assert_eq!(ys.iter().cloned().fold(0.0, f64::max), 4096.0);
+		Ok(())
+	}
+
+	#[test]
+	fn test_options() -> Result<()> {
+		let runner = runner("encoding=\"terrarium\" interval=100 layer=\"iso\"")?;
+		let tile = VectorTile::from_blob(&runner.run(make_tile(TerrainEncoding::Terrarium)?)?.unwrap())?;
+		assert_eq!(tile.layers[0].name, "iso");
+		let features = tile.layers[0].to_features()?;
+		assert_eq!(features.len(), 1);
+		assert_eq!(features[0].properties.get("ele"), Some(&GeoValue::from(100i64)));
+		assert_eq!(features[0].properties.get("index"), None);
+
+		// a flat tile without contour lines is skipped
+		let flat = elevations2image(&[5.0; 16], 4, 4, TerrainEncoding::Mapbox);
+		assert!(runner.run(image2blob(&flat, TileFormat::PNG)?)?.is_none());
+
+		assert!(self::runner("interval=0").is_err());
+		assert!(self::runner("encoding=\"rgb\"").is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_debug format=png | raster_contours interval=50")
+			.await?;
+		assert_eq!(operation.get_parameters().tile_format, TileFormat::PBF);
+		assert_eq!(
+			operation.get_tilejson().as_string(),
+			r#"{"tilejson":"3.0.0","vector_layers":[{"fields":{"ele":"Number"},"id":"contours","maxzoom":31,"minzoom":0}]}"#
+		);
+
+		let error = factory
+			.operation_from_vpl("from_debug format=pbf | raster_contours")
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "source must be lossless raster tiles (png or webp)");
+		Ok(())
+	}
+}
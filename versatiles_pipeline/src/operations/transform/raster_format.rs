@@ -0,0 +1,245 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use imageproc::image::DynamicImage;
+use std::sync::Arc;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_image::{
+	helper::{blob2image, image2blob_quality},
+	ImageQuality,
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Encodes raster tiles again, in a different image format or with a different quality per zoom level.
+struct Args {
+	/// The new image format: "png", "jpg", "webp" or "jxl" (default: the format of the source). "jxl" is encoded losslessly and requires the feature "jxl".
+	format: Option<String>,
+	/// Quality from 0 (smallest) to 100 (best) per zoom range, e.g. `quality="0-9:90,10-:75"`, or a single quality for all zoom levels, e.g. `quality=80`. Only for "jpg" and "webp". Zoom levels without a quality use the default quality.
+	quality: Option<String>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	source_format: TileFormat,
+	source_compression: TileCompression,
+	format: TileFormat,
+	quality: ImageQuality,
+}
+
+impl Runner {
+	fn run(&self, blob: Blob, zoom: u8) -> Result<Blob> {
+		let image = blob2image(&decompress(blob, &self.source_compression)?, self.source_format)?;
+		// JPEG has no alpha channel and WebP supports only 8 bit RGB and RGBA
+		let image = match (self.format, image.color().has_alpha()) {
+			(TileFormat::JPG, _) | (TileFormat::WEBP, false) => DynamicImage::ImageRgb8(image.to_rgb8()),
+			(TileFormat::WEBP, true) => DynamicImage::ImageRgba8(image.to_rgba8()),
+			_ => image,
+		};
+		image2blob_quality(&image, self.format, self.quality.get_quality(zoom))
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			let is_raster = |format: TileFormat| {
+				matches!(
+					format,
+					TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP | TileFormat::JXL
+				)
+			};
+			ensure!(is_raster(parameters.tile_format), "source must be raster tiles");
+
+			let format = match &args.format {
+				Some(name) => {
+					let format = TileFormat::parse_str(name)?;
+					ensure!(
+						is_raster(format),
+						"format must be \"png\", \"jpg\", \"webp\" or \"jxl\""
+					);
+					format
+				}
+				None => parameters.tile_format,
+			};
+			ensure!(
+				cfg!(feature = "jxl") || (parameters.tile_format != TileFormat::JXL && format != TileFormat::JXL),
+				"JPEG XL tiles require the feature \"jxl\""
+			);
+			let quality = ImageQuality::parse_str(args.quality.as_deref().unwrap_or(""))?;
+			quality.check(format)?;
+
+			let runner = Arc::new(Runner {
+				source_format: parameters.tile_format,
+				source_compression: parameters.tile_compression,
+				format,
+				quality,
+			});
+			parameters.tile_format = format;
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				tilejson: source.get_tilejson().clone(),
+				source,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		Ok(match self.source.get_tile_data(coord).await? {
+			Some(blob) => Some(self.runner.run(blob, coord.z)?),
+			None => None,
+		})
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		let zoom = bbox.level;
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.map_blob_parallel(move |blob| runner.run(blob, zoom).unwrap())
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"raster_format"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	async fn get_tile_size(vpl: &str, z: u8) -> Result<usize> {
+		let operation = PipelineFactory::new_dummy().operation_from_vpl(vpl).await?;
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, z)?).await?.unwrap();
+		let format = operation.get_parameters().tile_format;
+		assert!(blob2image(&blob, format).is_ok());
+		Ok(blob.len() as usize)
+	}
+
+	#[tokio::test]
+	async fn test_quality_per_zoom() -> Result<()> {
+		let vpl = "from_debug format=png | raster_format format=webp quality=\"0-2:95,3-:20\"";
+		let high = get_tile_size(vpl, 2).await?;
+		let low = get_tile_size(vpl, 3).await?;
+		assert!(low < high, "{low} >= {high}");
+
+		let operation = PipelineFactory::new_dummy().operation_from_vpl(vpl).await?;
+		assert_eq!(operation.get_parameters().tile_format, TileFormat::WEBP);
+		let tiles = operation.get_tile_stream(TileBBox::new_full(1)?).await.collect().await;
+		assert_eq!(tiles.len(), 4);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_format() -> Result<()> {
+		get_tile_size("from_debug format=webp | raster_format format=jpg quality=50", 4).await?;
+		get_tile_size("from_debug format=png | raster_format format=png", 4).await?;
+		Ok(())
+	}
+
+	#[cfg(feature = "jxl")]
+	#[tokio::test]
+	async fn test_format_jxl() -> Result<()> {
+		let vpl = "from_debug format=png | raster_format format=jxl";
+		get_tile_size(vpl, 4).await?;
+
+		// lossless, so converting back yields the same pixels
+		let factory = PipelineFactory::new_dummy();
+		let coord = TileCoord3::new(3, 4, 5)?;
+		let png = factory.operation_from_vpl("from_debug format=png").await?;
+		let png = png.get_tile_data(&coord).await?.unwrap();
+		let jxl = factory.operation_from_vpl(vpl).await?;
+		let jxl = jxl.get_tile_data(&coord).await?.unwrap();
+		assert_eq!(
+			blob2image(&jxl, TileFormat::JXL)?.to_rgba8(),
+			blob2image(&png, TileFormat::PNG)?.to_rgba8()
+		);
+		Ok(())
+	}
+
+	#[cfg(not(feature = "jxl"))]
+	#[tokio::test]
+	async fn test_format_jxl() -> Result<()> {
+		let error = PipelineFactory::new_dummy()
+			.operation_from_vpl("from_debug format=png | raster_format format=jxl")
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "JPEG XL tiles require the feature \"jxl\"");
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() {
+		let factory = PipelineFactory::new_dummy();
+		let error = |vpl: &str| {
+			let factory = &factory;
+			let vpl = vpl.to_string();
+			async move { factory.operation_from_vpl(&vpl).await.unwrap_err().to_string() }
+		};
+		assert_eq!(
+			error("from_debug format=png | raster_format quality=80").await,
+			"quality is only supported for jpg and webp tiles, not for png"
+		);
+		assert_eq!(
+			error("from_debug format=png | raster_format format=pbf").await,
+			"format must be \"png\", \"jpg\", \"webp\" or \"jxl\""
+		);
+		assert_eq!(
+			error("from_debug format=pbf | raster_format format=png").await,
+			"source must be raster tiles"
+		);
+	}
+}
@@ -0,0 +1,389 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use imageproc::image::{imageops, DynamicImage, GenericImageView, RgbaImage};
+use std::fmt::Debug;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_image::helper::{blob2image, image2blob};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Changes the size of raster tiles by moving them one zoom level up or down, e.g. to turn a source of 512px tiles into 256px tiles.
+struct Args {
+	/// Either "split" (default) or "merge". "split" cuts every tile into four tiles of half the size at the next zoom level. "merge" combines four tiles into one tile of double size at the previous zoom level.
+	mode: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+	Split,
+	Merge,
+}
+
+impl Mode {
+	/// Returns the bbox of source tiles needed to build the tiles of `bbox`.
+	fn source_bbox(&self, bbox: &TileBBox) -> Result<TileBBox> {
+		match self {
+			Mode::Split => {
+				ensure!(bbox.level > 0, "there is no source level for level 0");
+				TileBBox::new(
+					bbox.level - 1,
+					bbox.x_min / 2,
+					bbox.y_min / 2,
+					bbox.x_max / 2,
+					bbox.y_max / 2,
+				)
+			}
+			Mode::Merge => TileBBox::new(
+				bbox.level + 1,
+				bbox.x_min * 2,
+				bbox.y_min * 2,
+				bbox.x_max * 2 + 1,
+				bbox.y_max * 2 + 1,
+			),
+		}
+	}
+
+	/// Returns the bbox covered by the tiles of the source `bbox` after retiling.
+	fn target_bbox(&self, bbox: &TileBBox) -> Result<TileBBox> {
+		match self {
+			Mode::Split => TileBBox::new(
+				bbox.level + 1,
+				bbox.x_min * 2,
+				bbox.y_min * 2,
+				bbox.x_max * 2 + 1,
+				bbox.y_max * 2 + 1,
+			),
+			Mode::Merge => TileBBox::new(
+				bbox.level - 1,
+				bbox.x_min / 2,
+				bbox.y_min / 2,
+				bbox.x_max / 2,
+				bbox.y_max / 2,
+			),
+		}
+	}
+
+	fn target_pyramid(&self, pyramid: &TileBBoxPyramid) -> Result<TileBBoxPyramid> {
+		let mut result = TileBBoxPyramid::new_empty();
+		for bbox in pyramid.iter_levels() {
+			let skip = match self {
+				Mode::Split => bbox.level >= 31,
+				Mode::Merge => bbox.level == 0,
+			};
+			if !skip {
+				result.set_level_bbox(self.target_bbox(bbox)?);
+			}
+		}
+		Ok(result)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	mode: Mode,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	source_compression: TileCompression,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+			let mode = match args.mode.as_deref().unwrap_or("split") {
+				"split" => Mode::Split,
+				"merge" => Mode::Merge,
+				m => bail!("unknown mode \"{m}\", expected \"split\" or \"merge\""),
+			};
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(
+				matches!(
+					parameters.tile_format,
+					TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP
+				),
+				"source must be raster tiles"
+			);
+
+			let source_compression = parameters.tile_compression;
+			parameters.tile_compression = TileCompression::Uncompressed;
+			parameters.bbox_pyramid = mode.target_pyramid(&parameters.bbox_pyramid)?;
+
+			let mut tilejson = source.get_tilejson().clone();
+			if let Some(z) = parameters.bbox_pyramid.get_zoom_min() {
+				tilejson.set_byte("minzoom", z)?;
+			}
+			if let Some(z) = parameters.bbox_pyramid.get_zoom_max() {
+				tilejson.set_byte("maxzoom", z)?;
+			}
+
+			Ok(Box::new(Self {
+				mode,
+				parameters,
+				source,
+				source_compression,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+
+	/// Builds the tiles of `bbox` from the given source tiles.
+	fn retile(&self, bbox: &TileBBox, source_tiles: Vec<(TileCoord3, Blob)>) -> Result<Vec<(TileCoord3, Blob)>> {
+		let format = self.parameters.tile_format;
+		let mut result = Vec::new();
+
+		match self.mode {
+			Mode::Split => {
+				for (coord, blob) in source_tiles {
+					let image = blob2image(&decompress(blob, &self.source_compression)?, format)?;
+					let (width, height) = (image.width() / 2, image.height() / 2);
+					for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+						let target = TileCoord3::new(coord.x * 2 + dx, coord.y * 2 + dy, coord.z + 1)?;
+						if bbox.contains3(&target) {
+							let tile = image.crop_imm(dx * width, dy * height, width, height);
+							result.push((target, image2blob(&tile, format)?));
+						}
+					}
+				}
+			}
+			Mode::Merge => {
+				let mut images: Vec<Vec<(TileCoord3, DynamicImage)>> = vec![vec![]; bbox.count_tiles() as usize];
+				for (coord, blob) in source_tiles {
+					let target = TileCoord3::new(coord.x / 2, coord.y / 2, coord.z - 1)?;
+					if bbox.contains3(&target) {
+						let image = blob2image(&decompress(blob, &self.source_compression)?, format)?;
+						images[bbox.get_tile_index3(&target)?].push((coord, image));
+					}
+				}
+
+				for (index, children) in images.into_iter().enumerate() {
+					let Some((_, first)) = children.first() else {
+						continue;
+					};
+					let (width, height) = first.dimensions();
+					let mut canvas = RgbaImage::new(width * 2, height * 2);
+					for (coord, image) in children.iter() {
+						let x = (coord.x % 2) * width;
+						let y = (coord.y % 2) * height;
+						imageops::replace(&mut canvas, &image.to_rgba8(), x as i64, y as i64);
+					}
+
+					let tile = if format == TileFormat::JPG {
+						DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+					} else {
+						DynamicImage::ImageRgba8(canvas)
+					};
+					result.push((bbox.get_coord3_by_index(index as u32)?, image2blob(&tile, format)?));
+				}
+			}
+		}
+
+		Ok(result)
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if !self.parameters.bbox_pyramid.contains_coord(coord) {
+			return Ok(None);
+		}
+
+		let bbox = TileBBox::new(coord.z, coord.x, coord.y, coord.x, coord.y)?;
+		let source_tiles = self
+			.source
+			.get_tile_stream(self.mode.source_bbox(&bbox)?)
+			.await
+			.collect()
+			.await;
+
+		Ok(self.retile(&bbox, source_tiles)?.pop().map(|(_, blob)| blob))
+	}
+
+	async fn get_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		bbox.intersect_pyramid(&self.parameters.bbox_pyramid).unwrap();
+		if bbox.is_empty() {
+			return TileStream::new_empty();
+		}
+
+		let bboxes: Vec<TileBBox> = bbox.iter_bbox_grid(32).collect();
+
+		TileStream::from_stream_iter(bboxes.into_iter().map(move |bbox| async move {
+			let source_tiles = self
+				.source
+				.get_tile_stream(self.mode.source_bbox(&bbox).unwrap())
+				.await
+				.collect()
+				.await;
+
+			TileStream::from_vec(self.retile(&bbox, source_tiles).unwrap())
+		}))
+		.await
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"raster_retile"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	async fn get_image(operation: &dyn OperationTrait, x: u32, y: u32, z: u8) -> Result<DynamicImage> {
+		let blob = operation.get_tile_data(&TileCoord3::new(x, y, z)?).await?.unwrap();
+		blob2image(&blob, operation.get_parameters().tile_format)
+	}
+
+	#[tokio::test]
+	async fn test_split() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_debug format=png | raster_retile")
+			.await?;
+
+		let parameters = operation.get_parameters();
+		assert_eq!(parameters.tile_compression, TileCompression::Uncompressed);
+		assert_eq!(parameters.bbox_pyramid.get_zoom_min(), Some(1));
+		assert_eq!(parameters.bbox_pyramid.get_zoom_max(), Some(31));
+		assert_eq!(operation.get_tilejson().values.get_byte("minzoom"), Some(1));
+
+		assert!(operation.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.is_none());
+
+		let image = get_image(operation.as_ref(), 3, 2, 3).await?;
+		assert_eq!(image.dimensions(), (256, 256));
+
+		let source = blob2image(
+			&factory
+				.operation_from_vpl("from_debug format=png")
+				.await?
+				.get_tile_data(&TileCoord3::new(1, 1, 2)?)
+				.await?
+				.unwrap(),
+			TileFormat::PNG,
+		)?;
+		assert_eq!(image.to_rgba8(), source.crop_imm(256, 0, 256, 256).to_rgba8());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_merge() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_debug format=png | raster_retile mode=merge")
+			.await?;
+
+		let parameters = operation.get_parameters();
+		assert_eq!(parameters.bbox_pyramid.get_zoom_min(), Some(0));
+		assert_eq!(parameters.bbox_pyramid.get_zoom_max(), Some(30));
+
+		let image = get_image(operation.as_ref(), 1, 0, 1).await?;
+		assert_eq!(image.dimensions(), (1024, 1024));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_split_and_merge() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let original = factory.operation_from_vpl("from_debug format=png").await?;
+		let operation = factory
+			.operation_from_vpl("from_debug format=png | raster_retile | raster_retile mode=merge")
+			.await?;
+
+		for coord in [(0, 0, 1), (1, 2, 2), (5, 3, 3)] {
+			let expected = get_image(original.as_ref(), coord.0, coord.1, coord.2).await?;
+			let image = get_image(operation.as_ref(), coord.0, coord.1, coord.2).await?;
+			assert_eq!(image.to_rgb8(), expected.to_rgb8());
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_tile_stream() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_debug format=png | raster_retile")
+			.await?;
+
+		let mut coords: Vec<TileCoord3> = operation
+			.get_tile_stream(TileBBox::new(3, 1, 2, 2, 3)?)
+			.await
+			.collect()
+			.await
+			.into_iter()
+			.map(|(coord, _)| coord)
+			.collect();
+		coords.sort_by_key(|c| (c.y, c.x));
+
+		assert_eq!(
+			coords,
+			vec![
+				TileCoord3::new(1, 2, 3)?,
+				TileCoord3::new(2, 2, 3)?,
+				TileCoord3::new(1, 3, 3)?,
+				TileCoord3::new(2, 3, 3)?,
+			]
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() {
+		let factory = PipelineFactory::new_dummy();
+
+		let error = factory
+			.operation_from_vpl("from_debug format=pbf | raster_retile")
+			.await
+			.unwrap_err();
+		assert_eq!(error.to_string(), "source must be raster tiles");
+
+		let error = factory
+			.operation_from_vpl("from_debug format=png | raster_retile mode=shrink")
+			.await
+			.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"unknown mode \"shrink\", expected \"split\" or \"merge\""
+		);
+	}
+}
@@ -0,0 +1,373 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use ab_glyph::{Font, FontArc, PxScale};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use imageproc::{
+	drawing::{draw_text_mut, text_size},
+	image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage},
+};
+use std::{fmt::Debug, sync::Arc};
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_image::helper::{blob2image, image2blob};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Draws an attribution text or a logo onto every raster tile, e.g. when a license requires it for derived products.
+struct Args {
+	/// The text to draw, e.g. `text="© Example"`. Either `text` or `image` must be set.
+	text: Option<String>,
+	/// TrueType or OpenType font file for the text, relative to the VPL file. Required if the text contains characters other than digits and "xyz:".
+	font: Option<String>,
+	/// Height of the text in pixels (default: 12).
+	font_size: Option<f32>,
+	/// Color of the text as hex value (default: "FFFFFF").
+	color: Option<String>,
+	/// Image file of a logo (PNG, JPEG or WebP), relative to the VPL file. For example: `image="logo.png"`.
+	image: Option<String>,
+	/// Where to draw the watermark: "top-left", "top-right", "bottom-left", "bottom-right" (default) or "center".
+	position: Option<String>,
+	/// Distance to the tile border in pixels (default: 4).
+	margin: Option<u8>,
+	/// Opacity of the watermark, between 0 and 1 (default: 0.7).
+	opacity: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Position {
+	TopLeft,
+	TopRight,
+	BottomLeft,
+	BottomRight,
+	Center,
+}
+
+impl Position {
+	fn parse(value: &str) -> Result<Position> {
+		Ok(match value {
+			"top-left" => Position::TopLeft,
+			"top-right" => Position::TopRight,
+			"bottom-left" => Position::BottomLeft,
+			"bottom-right" => Position::BottomRight,
+			"center" => Position::Center,
+			_ => bail!(
+				"unknown position \"{value}\", expected \"top-left\", \"top-right\", \"bottom-left\", \"bottom-right\" or \"center\""
+			),
+		})
+	}
+
+	/// Returns the top left corner of a watermark of `size` on a tile of `tile_size`.
+	fn offset(&self, tile_size: (u32, u32), size: (u32, u32), margin: u32) -> (i64, i64) {
+		let (tw, th) = (tile_size.0 as i64, tile_size.1 as i64);
+		let (w, h) = (size.0 as i64, size.1 as i64);
+		let m = margin as i64;
+		match self {
+			Position::TopLeft => (m, m),
+			Position::TopRight => (tw - w - m, m),
+			Position::BottomLeft => (m, th - h - m),
+			Position::BottomRight => (tw - w - m, th - h - m),
+			Position::Center => ((tw - w) / 2, (th - h) / 2),
+		}
+	}
+}
+
+fn parse_color(value: &str) -> Result<[u8; 3]> {
+	let hex = value.trim_start_matches('#');
+	ensure!(
+		hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+		"color \"{value}\" must be a hex value like \"FFFFFF\""
+	);
+	let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+	Ok([channel(0), channel(2), channel(4)])
+}
+
+/// Renders the text into an image with a transparent background.
+fn render_text(text: &str, font: &FontArc, font_size: f32, color: [u8; 3]) -> Result<RgbaImage> {
+	if let Some(c) = text.chars().find(|c| !c.is_whitespace() && font.glyph_id(*c).0 == 0) {
+		bail!("the font does not contain the character '{c}', please set a different `font`");
+	}
+
+	let scale = PxScale::from(font_size);
+	let (width, height) = text_size(scale, font, text);
+	ensure!(width > 0 && height > 0, "text \"{text}\" is empty");
+
+	// draw the coverage of the glyphs, then use it as alpha channel
+	let mut mask = GrayImage::new(width + 2, height + 2);
+	draw_text_mut(&mut mask, Luma([255]), 1, 1, scale, font, text);
+
+	let [r, g, b] = color;
+	Ok(RgbaImage::from_fn(mask.width(), mask.height(), |x, y| {
+		Rgba([r, g, b, mask.get_pixel(x, y).0[0]])
+	}))
+}
+
+#[derive(Debug)]
+struct Watermark {
+	image: RgbaImage,
+	position: Position,
+	margin: u32,
+	opacity: f32,
+	format: TileFormat,
+	source_compression: TileCompression,
+}
+
+impl Watermark {
+	fn draw(&self, tile: &mut RgbaImage) {
+		let (x0, y0) = self
+			.position
+			.offset(tile.dimensions(), self.image.dimensions(), self.margin);
+
+		for (x, y, pixel) in self.image.enumerate_pixels() {
+			let (tx, ty) = (x0 + x as i64, y0 + y as i64);
+			if tx < 0 || ty < 0 || tx >= tile.width() as i64 || ty >= tile.height() as i64 {
+				continue;
+			}
+			let alpha = pixel.0[3] as f32 / 255.0 * self.opacity;
+			if alpha <= 0.0 {
+				continue;
+			}
+			let target = tile.get_pixel_mut(tx as u32, ty as u32);
+			for i in 0..3 {
+				target.0[i] = (target.0[i] as f32 * (1.0 - alpha) + pixel.0[i] as f32 * alpha).round() as u8;
+			}
+			target.0[3] = (target.0[3] as f32 + (255.0 - target.0[3] as f32) * alpha).round() as u8;
+		}
+	}
+
+	fn run(&self, blob: Blob) -> Result<Blob> {
+		let image = blob2image(&decompress(blob, &self.source_compression)?, self.format)?;
+		let has_alpha = image.color().has_alpha();
+
+		let mut tile = image.to_rgba8();
+		self.draw(&mut tile);
+
+		let tile = if has_alpha {
+			DynamicImage::ImageRgba8(tile)
+		} else {
+			DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(tile).to_rgb8())
+		};
+		image2blob(&tile, self.format)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+	watermark: Arc<Watermark>,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(
+				matches!(
+					parameters.tile_format,
+					TileFormat::JPG | TileFormat::PNG | TileFormat::WEBP
+				),
+				"source must be raster tiles"
+			);
+
+			let opacity = args.opacity.unwrap_or(0.7);
+			ensure!(
+				(0.0..=1.0).contains(&opacity),
+				"opacity must be between 0 and 1, but is {opacity}"
+			);
+
+			let image = match (&args.text, &args.image) {
+				(Some(text), None) => {
+					let font = match &args.font {
+						Some(filename) => {
+							let path = factory.resolve_path(filename);
+							let data = std::fs::read(&path).with_context(|| format!("reading font {path:?}"))?;
+							FontArc::try_from_vec(data).with_context(|| format!("parsing font {path:?}"))?
+						}
+						None => FontArc::try_from_slice(include_bytes!("../read/from_debug/trim.ttf"))?,
+					};
+					let color = parse_color(args.color.as_deref().unwrap_or("FFFFFF"))?;
+					render_text(text, &font, args.font_size.unwrap_or(12.0), color)?
+				}
+				(None, Some(filename)) => {
+					let mut name = filename.clone();
+					let format = TileFormat::from_filename(&mut name)
+						.with_context(|| format!("unknown image format of {filename:?}"))?;
+					let path = factory.resolve_path(filename);
+					let data = std::fs::read(&path).with_context(|| format!("reading image {path:?}"))?;
+					blob2image(&Blob::from(data), format)?.to_rgba8()
+				}
+				_ => bail!("either `text` or `image` must be set"),
+			};
+
+			let watermark = Watermark {
+				image,
+				position: Position::parse(args.position.as_deref().unwrap_or("bottom-right"))?,
+				margin: args.margin.unwrap_or(4) as u32,
+				opacity,
+				format: parameters.tile_format,
+				source_compression: parameters.tile_compression,
+			};
+
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			Ok(Box::new(Self {
+				parameters,
+				tilejson: source.get_tilejson().clone(),
+				source,
+				watermark: Arc::new(watermark),
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		Ok(match self.source.get_tile_data(coord).await? {
+			Some(blob) => Some(self.watermark.run(blob)?),
+			None => None,
+		})
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let watermark = self.watermark.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.map_blob_parallel(move |blob| watermark.run(blob).unwrap())
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"raster_watermark"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+
+	async fn get_tile(vpl: &str, factory: &PipelineFactory) -> Result<RgbaImage> {
+		let operation = factory.operation_from_vpl(vpl).await?;
+		let coord = TileCoord3::new(0, 0, 3)?;
+		let blob = operation.get_tile_data(&coord).await?.unwrap();
+		Ok(blob2image(&blob, operation.get_parameters().tile_format)?.to_rgba8())
+	}
+
+	#[test]
+	fn test_position() {
+		let offset = |p: &str| Position::parse(p).unwrap().offset((256, 256), (50, 10), 4);
+		assert_eq!(offset("top-left"), (4, 4));
+		assert_eq!(offset("top-right"), (202, 4));
+		assert_eq!(offset("bottom-left"), (4, 242));
+		assert_eq!(offset("bottom-right"), (202, 242));
+		assert_eq!(offset("center"), (103, 123));
+		assert!(Position::parse("left").is_err());
+	}
+
+	#[test]
+	fn test_parse_color() {
+		assert_eq!(parse_color("#FF8000").unwrap(), [255, 128, 0]);
+		assert_eq!(parse_color("00ff00").unwrap(), [0, 255, 0]);
+		assert!(parse_color("red").is_err());
+	}
+
+	#[tokio::test]
+	async fn test_text() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let original = get_tile("from_debug format=png", &factory).await?;
+		let tile = get_tile(
+			"from_debug format=png | raster_watermark text=\"1:2\" color=\"FF0000\" opacity=1 position=\"top-left\" font_size=40",
+			&factory,
+		)
+		.await?;
+
+		assert_eq!(tile.dimensions(), original.dimensions());
+		// the text changed pixels in the top left corner, but not in the bottom right corner
+		let changed = |x0: u32, y0: u32| {
+			(x0..x0 + 40).any(|x| (y0..y0 + 30).any(|y| tile.get_pixel(x, y) != original.get_pixel(x, y)))
+		};
+		assert!(changed(4, 4));
+		assert!(!changed(200, 200));
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_image() -> Result<()> {
+		let dir = TempDir::new()?;
+		let logo = dir.path().join("logo.png");
+		RgbaImage::from_pixel(10, 10, Rgba([0, 0, 255, 255])).save(&logo)?;
+
+		let factory = PipelineFactory::new_dummy();
+		let tile = get_tile(
+			&format!(
+				"from_debug format=png | raster_watermark image=\"{}\" opacity=0.5 margin=0",
+				logo.display()
+			),
+			&factory,
+		)
+		.await?;
+
+		let (w, h) = tile.dimensions();
+		let pixel = tile.get_pixel(w - 1, h - 1).0;
+		// half blue over the light grey background
+		assert!(pixel[0] < 130 && pixel[2] > 230, "{pixel:?}");
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() {
+		let factory = PipelineFactory::new_dummy();
+		let error = |vpl: &str| {
+			let vpl = format!("from_debug format=png | raster_watermark {vpl}");
+			let factory = &factory;
+			async move { factory.operation_from_vpl(&vpl).await.unwrap_err().to_string() }
+		};
+		assert_eq!(error("").await, "either `text` or `image` must be set");
+		assert_eq!(
+			error("text=\"© Example\"").await,
+			"the font does not contain the character '©', please set a different `font`"
+		);
+		assert_eq!(
+			error("text=\"1\" opacity=2").await,
+			"opacity must be between 0 and 1, but is 2"
+		);
+	}
+}
@@ -0,0 +1,307 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{collections::HashMap, sync::Arc};
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_geometry::{
+	math::dissolve_polygons,
+	vector_tile::{VectorTile, VectorTileLayer},
+	GeoFeature, Geometry,
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Merges adjacent polygons of a tile that have the same value of an attribute, e.g. to reduce the number of features of administrative boundaries at low zoom levels.
+/// Merged features keep only the properties that all of them have in common. Tiles are processed independently, so polygons are not merged across tile boundaries.
+struct Args {
+	/// Name of the attribute, e.g. `by="iso_a2"`.
+	by: String,
+	/// Name of the vector layer. By default all layers are dissolved.
+	layer: Option<String>,
+	/// Only tiles up to this zoom level are dissolved, e.g. `max_zoom=6`. Higher zoom levels are passed through unchanged.
+	max_zoom: Option<u8>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	args: Args,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let blob = decompress(blob, &self.tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+
+		for layer in tile.layers.iter_mut() {
+			if self.args.layer.as_ref().is_some_and(|name| name != &layer.name) {
+				continue;
+			}
+			let features = self.dissolve_features(layer.to_features()?);
+			*layer = VectorTileLayer::from_features(layer.name.clone(), features, layer.extent, layer.version)?;
+		}
+
+		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
+	}
+
+	fn is_active(&self, level: u8) -> bool {
+		self.args.max_zoom.is_none_or(|max_zoom| level <= max_zoom)
+	}
+
+	/// Groups the polygons by the value of the attribute and merges every group into a single feature,
+	/// placed at the position of the first feature of the group.
+	fn dissolve_features(&self, features: Vec<GeoFeature>) -> Vec<GeoFeature> {
+		let mut result: Vec<(GeoFeature, usize)> = Vec::new();
+		let mut groups: HashMap<String, usize> = HashMap::new();
+
+		for feature in features {
+			let polygons = match &feature.geometry {
+				Geometry::Polygon(g) => vec![g.0.clone()],
+				Geometry::MultiPolygon(g) => g.0.clone(),
+				_ => {
+					result.push((feature, 1));
+					continue;
+				}
+			};
+			let Some(key) = feature.properties.get(&self.args.by).map(|value| format!("{value:?}")) else {
+				result.push((feature, 1));
+				continue;
+			};
+
+			if let Some(index) = groups.get(&key) {
+				let (group, count) = &mut result[*index];
+				if let Geometry::MultiPolygon(g) = &mut group.geometry {
+					g.0.extend(polygons);
+				}
+				group.properties.0.retain(|k, v| feature.properties.get(k) == Some(v));
+				group.id = None;
+				*count += 1;
+			} else {
+				groups.insert(key, result.len());
+				let mut group = feature;
+				group.geometry = Geometry::new_multi_polygon(polygons);
+				result.push((group, 1));
+			}
+		}
+
+		result
+			.into_iter()
+			.map(|(mut feature, count)| {
+				if count > 1 {
+					if let Geometry::MultiPolygon(g) = &mut feature.geometry {
+						g.0 = dissolve_polygons(&g.0);
+					}
+				}
+				feature
+			})
+			.collect()
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let runner = Arc::new(Runner {
+				args,
+				tile_compression: parameters.tile_compression,
+			});
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let tilejson = source.get_tilejson().clone();
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		let active = runner.is_active(bbox.level);
+		let stream = self.source.get_tile_stream(bbox).await;
+		if active {
+			stream.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+		} else {
+			let tile_compression = runner.tile_compression;
+			stream.map_blob_parallel(move |blob| decompress(blob, &tile_compression).unwrap())
+		}
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(blob) = self.source.get_tile_data(coord).await? else {
+			return Ok(None);
+		};
+		if self.runner.is_active(coord.z) {
+			self.runner.run(blob)
+		} else {
+			Ok(Some(decompress(blob, &self.runner.tile_compression)?))
+		}
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_dissolve"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::{GeoProperties, GeoValue};
+
+	fn square(x: f64, y: f64, country: &str, name: &str) -> GeoFeature {
+		let mut feature = GeoFeature::new(Geometry::new_polygon(vec![vec![
+			[x, y],
+			[x + 10.0, y],
+			[x + 10.0, y + 10.0],
+			[x, y + 10.0],
+			[x, y],
+		]]));
+		feature.properties = GeoProperties::from(vec![
+			("country", GeoValue::from(country)),
+			("name", GeoValue::from(name)),
+		]);
+		feature
+	}
+
+	fn runner(args: &str) -> Runner {
+		Runner {
+			args: Args::from_vpl_node(&VPLNode::from_str(&format!("vector_dissolve {args}")).unwrap()).unwrap(),
+			tile_compression: TileCompression::Uncompressed,
+		}
+	}
+
+	#[test]
+	fn test_dissolve() -> Result<()> {
+		let features = vec![
+			square(0.0, 0.0, "a", "a1"),
+			square(10.0, 0.0, "a", "a2"),
+			square(20.0, 0.0, "b", "b1"),
+			square(30.0, 0.0, "a", "a3"),
+			GeoFeature::new(Geometry::new_line_string(vec![[0.0, 0.0], [5.0, 5.0]])),
+		];
+		let layer = VectorTileLayer::from_features(String::from("boundaries"), features, 4096, 1)?;
+		let blob = VectorTile::new(vec![layer]).to_blob()?;
+
+		let blob = runner("by=country").run(blob)?.unwrap();
+		let features = VectorTile::from_blob(&blob)?.layers[0].to_features()?;
+		assert_eq!(features.len(), 3);
+
+		// "a1" and "a2" are merged, "a3" is not adjacent, so it remains a separate polygon
+		let Geometry::MultiPolygon(g) = &features[0].geometry else {
+			panic!("expected MultiPolygon")
+		};
+		assert_eq!(
+			g.0,
+			vec![
+				vec![vec![[0.0, 0.0], [20.0, 0.0], [20.0, 10.0], [0.0, 10.0], [0.0, 0.0]]],
+				vec![vec![[30.0, 0.0], [40.0, 0.0], [40.0, 10.0], [30.0, 10.0], [30.0, 0.0]]],
+			]
+		);
+		assert_eq!(format!("{:?}", features[0].properties), "{\"country\": String(\"a\")}");
+		assert_eq!(
+			format!("{:?}", features[1].properties),
+			"{\"country\": String(\"b\"), \"name\": String(\"b1\")}"
+		);
+		assert_eq!(features[2].geometry.get_type_name(), "MultiLineString");
+		Ok(())
+	}
+
+	#[test]
+	fn test_layer_and_zoom() -> Result<()> {
+		let layer = |name: &str| {
+			VectorTileLayer::from_features(
+				String::from(name),
+				vec![square(0.0, 0.0, "a", "a1"), square(10.0, 0.0, "a", "a2")],
+				4096,
+				1,
+			)
+			.unwrap()
+		};
+		let blob = VectorTile::new(vec![layer("boundaries"), layer("other")]).to_blob()?;
+
+		let blob = runner("by=country layer=boundaries").run(blob)?.unwrap();
+		let tile = VectorTile::from_blob(&blob)?;
+		assert_eq!(tile.layers[0].features.len(), 1);
+		assert_eq!(tile.layers[1].features.len(), 2);
+
+		let runner = runner("by=country max_zoom=6");
+		assert!(runner.is_active(0));
+		assert!(runner.is_active(6));
+		assert!(!runner.is_active(7));
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | vector_dissolve by=filename max_zoom=3")
+			.await?;
+		assert_eq!(
+			operation.get_parameters().tile_compression,
+			TileCompression::Uncompressed
+		);
+
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.unwrap();
+		assert_eq!(VectorTile::from_blob(&blob)?.layers.len(), 1);
+
+		let tiles = operation.get_tile_stream(TileBBox::new_full(4)?).await.collect().await;
+		assert_eq!(tiles.len(), 256);
+
+		assert!(factory
+			.operation_from_vpl("from_debug format=png | vector_dissolve by=name")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
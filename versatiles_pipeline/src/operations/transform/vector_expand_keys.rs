@@ -0,0 +1,184 @@
+use super::vector_minify_keys::{KeyMapping, MAPPING_KEY};
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use versatiles_core::{tilejson::TileJSON, types::*};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Restores the property keys of vector tiles that were minified by `vector_minify_keys`, using the mapping "minified_keys" in the TileJSON.
+/// The mapping is removed from the TileJSON.
+struct Args {}
+
+#[derive(Debug)]
+struct Runner {
+	mapping: KeyMapping,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn run(&self, blob: Blob) -> Result<Blob> {
+		self.mapping.rename_tile(blob, &self.tile_compression, false)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let mut tilejson = source.get_tilejson().clone();
+			let mapping = KeyMapping::from_tilejson(&tilejson)?
+				.with_context(|| format!("the TileJSON of the source contains no \"{MAPPING_KEY}\""))?;
+			mapping.rename_fields(&mut tilejson.vector_layers, false);
+			tilejson.values.remove(MAPPING_KEY);
+
+			let runner = Arc::new(Runner {
+				mapping,
+				tile_compression: parameters.tile_compression,
+			});
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		Ok(match self.source.get_tile_data(coord).await? {
+			Some(blob) => Some(self.runner.run(blob)?),
+			None => None,
+		})
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.map_blob_parallel(move |blob| runner.run(blob).unwrap())
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_expand_keys"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use versatiles_geometry::vector_tile::VectorTile;
+
+	#[tokio::test]
+	async fn test_round_trip() -> Result<()> {
+		let dir = TempDir::new()?;
+		let path = dir.path().join("places.geojson");
+		std::fs::write(
+			&path,
+			r#"{"type":"FeatureCollection","features":[{"type":"Feature","properties":{"name":"Berlin","population":3800000},"geometry":{"type":"Point","coordinates":[13.4,52.5]}}]}"#,
+		)?;
+		let factory = PipelineFactory::new_dummy();
+		let source = format!("from_geojson filename=\"{}\" max_zoom=4", path.display());
+		let coord = TileCoord3::new(8, 5, 4)?;
+		let get_properties = |vpl: String| {
+			let factory = &factory;
+			async move {
+				let operation = factory.operation_from_vpl(&vpl).await?;
+				let blob = operation.get_tile_data(&coord).await?.unwrap();
+				let properties = &VectorTile::from_blob(&blob)?.layers[0].to_features()?[0].properties;
+				let mut keys: Vec<String> = properties.iter().map(|(key, _)| key.clone()).collect();
+				keys.sort();
+				Ok::<_, anyhow::Error>((keys, operation.get_tilejson().as_string()))
+			}
+		};
+
+		let (keys, tilejson) = get_properties(format!("{source} | vector_minify_keys keep=[name]")).await?;
+		assert_eq!(keys, ["a", "name"]);
+		assert!(tilejson.contains(r#""minified_keys":["a=population"]"#), "{tilejson}");
+
+		let (keys, tilejson) = get_properties(format!(
+			"{source} | vector_minify_keys keep=[name] | vector_expand_keys"
+		))
+		.await?;
+		assert_eq!(keys, ["name", "population"]);
+		assert!(!tilejson.contains("minified_keys"), "{tilejson}");
+		assert!(
+			tilejson.contains(r#""fields":{"name":"","population":""}"#),
+			"{tilejson}"
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() {
+		let factory = PipelineFactory::new_dummy();
+		let error = |vpl: &str| {
+			let factory = &factory;
+			let vpl = vpl.to_string();
+			async move { factory.operation_from_vpl(&vpl).await.unwrap_err().to_string() }
+		};
+		assert_eq!(
+			error("from_container filename=dummy | vector_expand_keys").await,
+			"the TileJSON of the source contains no \"minified_keys\""
+		);
+		assert_eq!(
+			error("from_debug format=png | vector_expand_keys").await,
+			"source must be vector tiles"
+		);
+	}
+}
@@ -0,0 +1,283 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_geometry::{
+	math::area_polygon,
+	vector_tile::{VectorTile, VectorTileFeature, VectorTileLayer},
+	GeoValue, Geometry,
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Limits the number of features per layer in each vector tile, e.g. to keep tiles renderable on low-end devices.
+/// If a layer has too many features, the features with the lowest priority are dropped.
+/// The priority is either the value of an attribute, or the size of the feature: the area of polygons and the length of lines.
+/// To use different limits for different layers or zoom ranges, chain several operations, e.g.
+/// `vector_limit_features max_features=500 layer=pois max_zoom=12 | vector_limit_features max_features=2000 min_zoom=13`.
+struct Args {
+	/// Maximum number of features per layer and tile.
+	max_features: u32,
+	/// Name of the numeric attribute used as priority. Features without this attribute have the lowest priority. By default, the size of the features is used.
+	priority: Option<String>,
+	/// If set, lower values of the priority attribute are more important, e.g. for ranks.
+	reverse: bool,
+	/// Name of the vector layer. By default all layers are limited.
+	layer: Option<String>,
+	/// Only tiles from this zoom level on are limited. Lower zoom levels are passed through unchanged.
+	min_zoom: Option<u8>,
+	/// Only tiles up to this zoom level are limited. Higher zoom levels are passed through unchanged.
+	max_zoom: Option<u8>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	args: Args,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let blob = decompress(blob, &self.tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+
+		for layer in tile.layers.iter_mut() {
+			if self.args.layer.as_ref().is_some_and(|name| name != &layer.name) {
+				continue;
+			}
+			self.limit_layer(layer)?;
+		}
+
+		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
+	}
+
+	/// Drops the features with the lowest priority, keeping the order of the remaining features.
+	fn limit_layer(&self, layer: &mut VectorTileLayer) -> Result<()> {
+		let max_features = self.args.max_features as usize;
+		if layer.features.len() <= max_features {
+			return Ok(());
+		}
+
+		let mut priorities = layer
+			.features
+			.iter()
+			.enumerate()
+			.map(|(index, feature)| Ok((self.get_priority(feature, layer)?, index)))
+			.collect::<Result<Vec<(f64, usize)>>>()?;
+		// sort by descending priority, features with the same priority keep their order
+		priorities.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+		let mut keep = vec![false; layer.features.len()];
+		for (_, index) in priorities.into_iter().take(max_features) {
+			keep[index] = true;
+		}
+		let mut keep = keep.into_iter();
+		layer.features.retain(|_| keep.next().unwrap());
+		Ok(())
+	}
+
+	fn get_priority(&self, feature: &VectorTileFeature, layer: &VectorTileLayer) -> Result<f64> {
+		let Some(key) = &self.args.priority else {
+			return Ok(get_size(&feature.to_geometry()?));
+		};
+		let value = match feature.decode_properties(layer)?.get(key) {
+			Some(GeoValue::Double(v)) => *v,
+			Some(GeoValue::Float(v)) => *v as f64,
+			Some(GeoValue::Int(v)) => *v as f64,
+			Some(GeoValue::UInt(v)) => *v as f64,
+			_ => return Ok(f64::NEG_INFINITY),
+		};
+		Ok(if self.args.reverse { -value } else { value })
+	}
+
+	fn is_active(&self, level: u8) -> bool {
+		(self.args.min_zoom.unwrap_or(0)..=self.args.max_zoom.unwrap_or(31)).contains(&level)
+	}
+}
+
+/// Returns the area of polygons, the length of lines and 0 for points.
+fn get_size(geometry: &Geometry) -> f64 {
+	let length = |line: &Vec<[f64; 2]>| -> f64 {
+		line
+			.windows(2)
+			.map(|w| ((w[1][0] - w[0][0]).powi(2) + (w[1][1] - w[0][1]).powi(2)).sqrt())
+			.sum()
+	};
+	match geometry {
+		Geometry::Point(_) | Geometry::MultiPoint(_) => 0.0,
+		Geometry::LineString(g) => length(&g.0),
+		Geometry::MultiLineString(g) => g.0.iter().map(length).sum(),
+		Geometry::Polygon(g) => area_polygon(&g.0).abs(),
+		Geometry::MultiPolygon(g) => g.0.iter().map(|p| area_polygon(p).abs()).sum(),
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let runner = Arc::new(Runner {
+				args,
+				tile_compression: parameters.tile_compression,
+			});
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let tilejson = source.get_tilejson().clone();
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		let active = runner.is_active(bbox.level);
+		let stream = self.source.get_tile_stream(bbox).await;
+		if active {
+			stream.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+		} else {
+			let tile_compression = runner.tile_compression;
+			stream.map_blob_parallel(move |blob| decompress(blob, &tile_compression).unwrap())
+		}
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(blob) = self.source.get_tile_data(coord).await? else {
+			return Ok(None);
+		};
+		if self.runner.is_active(coord.z) {
+			self.runner.run(blob)
+		} else {
+			Ok(Some(decompress(blob, &self.runner.tile_compression)?))
+		}
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_limit_features"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::{GeoFeature, GeoProperties};
+
+	fn runner(args: &str) -> Result<Runner> {
+		let args = Args::from_vpl_node(&VPLNode::from_str(&format!("vector_limit_features {args}"))?)?;
+		Ok(Runner {
+			args,
+			tile_compression: TileCompression::Uncompressed,
+		})
+	}
+
+	/// Squares of different sizes, with a "rank" attribute.
+	fn make_tile() -> Result<Blob> {
+		let features = [(10.0, 3), (40.0, 1), (20.0, 4), (30.0, 2)]
+			.into_iter()
+			.map(|(size, rank)| {
+				let square = vec![[0.0, 0.0], [size, 0.0], [size, size], [0.0, size], [0.0, 0.0]];
+				let mut feature = GeoFeature::new(Geometry::new_polygon(vec![square]));
+				feature.properties = GeoProperties::from(vec![("rank", GeoValue::from(rank as u32))]);
+				feature
+			})
+			.collect();
+		let layer = VectorTileLayer::from_features(String::from("layer"), features, 4096, 1)?;
+		VectorTile::new(vec![layer]).to_blob()
+	}
+
+	fn get_ranks(blob: &Blob) -> Result<Vec<String>> {
+		Ok(VectorTile::from_blob(blob)?.layers[0]
+			.to_features()?
+			.iter()
+			.map(|f| f.properties.get("rank").unwrap().to_string())
+			.collect())
+	}
+
+	#[test]
+	fn test_limit() -> Result<()> {
+		let blob = make_tile()?;
+		let run = |args: &str| get_ranks(&runner(args)?.run(blob.clone())?.unwrap());
+
+		// the largest squares are kept, in their original order
+		assert_eq!(run("max_features=2")?, vec!["1", "2"]);
+		assert_eq!(run("max_features=3")?, vec!["1", "4", "2"]);
+		assert_eq!(run("max_features=2 priority=rank")?, vec!["3", "4"]);
+		assert_eq!(run("max_features=2 priority=rank reverse=true")?, vec!["1", "2"]);
+		// features without the attribute are dropped first
+		assert_eq!(run("max_features=2 priority=unknown")?.len(), 2);
+		assert_eq!(run("max_features=10")?.len(), 4);
+		assert_eq!(run("max_features=1 layer=other")?.len(), 4);
+		assert!(runner("priority=rank").is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | vector_limit_features max_features=0 min_zoom=2")
+			.await?;
+
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 1)?).await?.unwrap();
+		assert_eq!(VectorTile::from_blob(&blob)?.layers[0].features.len(), 1);
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 2)?).await?.unwrap();
+		assert_eq!(VectorTile::from_blob(&blob)?.layers[0].features.len(), 0);
+
+		assert!(factory
+			.operation_from_vpl("from_debug format=png | vector_limit_features max_features=1")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
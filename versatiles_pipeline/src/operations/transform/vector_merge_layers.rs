@@ -0,0 +1,352 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use versatiles_core::{
+	tilejson::{TileJSON, VectorLayer, VectorLayers},
+	types::*,
+	utils::decompress,
+};
+use versatiles_geometry::vector_tile::{VectorTile, VectorTileLayer};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Merges several layers of each vector tile into one layer, e.g. `landuse_overlay` into `landuse`.
+/// The features are appended in the order of `layers`. The merged layer takes the place of the first of these layers in the tile.
+/// If the tile already contains a layer with the target name, that is not listed in `layers`, its features come first.
+struct Args {
+	/// Names of the layers to merge, e.g. `layers=[landuse,landuse_overlay]`.
+	layers: Vec<String>,
+	/// Name of the merged layer. By default the name of the first layer in `layers`.
+	target: Option<String>,
+	/// Prefixes added to the property names, one per layer in the order of `layers`, so that properties with the same name don't collide. Use `""` for no prefix, e.g. `prefixes=["","overlay_"]`.
+	prefixes: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	args: Args,
+	target: String,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn new(args: Args, tile_compression: TileCompression) -> Result<Runner> {
+		ensure!(!args.layers.is_empty(), "'layers' must not be empty");
+		ensure!(
+			args.prefixes.is_empty() || args.prefixes.len() == args.layers.len(),
+			"'prefixes' must have one entry per layer"
+		);
+		let target = args.target.clone().unwrap_or_else(|| args.layers[0].clone());
+		Ok(Runner {
+			args,
+			target,
+			tile_compression,
+		})
+	}
+
+	fn prefix(&self, index: usize) -> &str {
+		self.args.prefixes.get(index).map_or("", String::as_str)
+	}
+
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let blob = decompress(blob, &self.tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+		self.merge_layers(&mut tile)?;
+		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
+	}
+
+	fn merge_layers(&self, tile: &mut VectorTile) -> Result<()> {
+		let Some(position) = tile
+			.layers
+			.iter()
+			.position(|layer| layer.name == self.target || self.args.layers.contains(&layer.name))
+		else {
+			return Ok(());
+		};
+
+		let mut sources: Vec<Option<VectorTileLayer>> = self.args.layers.iter().map(|_| None).collect();
+		let mut merged: Option<VectorTileLayer> = None;
+		let mut remaining = Vec::with_capacity(tile.layers.len());
+		for layer in tile.layers.drain(..) {
+			if let Some(index) = self.args.layers.iter().position(|name| name == &layer.name) {
+				sources[index] = Some(layer);
+			} else if layer.name == self.target {
+				merged = Some(layer);
+			} else {
+				remaining.push(layer);
+			}
+		}
+
+		for (index, layer) in sources.into_iter().enumerate() {
+			let Some(mut layer) = layer else {
+				continue;
+			};
+			let target =
+				merged.get_or_insert_with(|| VectorTileLayer::new(self.target.clone(), layer.extent, layer.version));
+			ensure!(
+				layer.extent == target.extent,
+				"layer '{}' has the extent {}, but '{}' has {}",
+				layer.name,
+				layer.extent,
+				target.name,
+				target.extent
+			);
+			let prefix = self.prefix(index);
+			if prefix.is_empty() {
+				target.add_from_layer(layer)?;
+			} else {
+				for feature in std::mem::take(&mut layer.features) {
+					let properties = layer
+						.decode_tag_ids(&feature.tag_ids)?
+						.into_iter()
+						.map(|(key, value)| (format!("{prefix}{key}"), value))
+						.collect();
+					target.add_vector_tile_features(feature, properties);
+				}
+			}
+		}
+
+		let mut merged = merged.expect("at least one layer should be merged");
+		merged.name = self.target.clone();
+		remaining.insert(position.min(remaining.len()), merged);
+		tile.layers = remaining;
+		Ok(())
+	}
+
+	/// Merges the descriptions of the layers in the TileJSON the same way as the tiles.
+	fn merge_vector_layers(&self, vector_layers: &mut VectorLayers) {
+		let mut merged: Option<VectorLayer> = vector_layers.0.remove(&self.target);
+		for (index, name) in self.args.layers.iter().enumerate() {
+			let Some(layer) = vector_layers.0.remove(name) else {
+				continue;
+			};
+			let prefix = self.prefix(index);
+			let layer = VectorLayer {
+				fields: layer
+					.fields
+					.into_iter()
+					.map(|(key, value)| (format!("{prefix}{key}"), value))
+					.collect(),
+				..layer
+			};
+			match &mut merged {
+				Some(merged) => merged.merge(&layer),
+				None => merged = Some(layer),
+			}
+		}
+		if let Some(merged) = merged {
+			vector_layers.0.insert(self.target.clone(), merged);
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let runner = Arc::new(Runner::new(args, parameters.tile_compression)?);
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let mut tilejson = source.get_tilejson().clone();
+			runner.merge_vector_layers(&mut tilejson.vector_layers);
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(blob) = self.source.get_tile_data(coord).await? else {
+			return Ok(None);
+		};
+		self.runner.run(blob)
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_merge_layers"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::{GeoFeature, GeoProperties, GeoValue, Geometry};
+
+	fn runner(args: &str) -> Result<Runner> {
+		let args = Args::from_vpl_node(&VPLNode::from_str(&format!("vector_merge_layers {args}"))?)?;
+		Runner::new(args, TileCompression::Uncompressed)
+	}
+
+	/// A tile with the layers "water", "landuse" (2 features), "roads" and "landuse_overlay".
+	fn make_tile() -> Result<VectorTile> {
+		let layer = |name: &str, classes: &[&str]| {
+			let features = classes
+				.iter()
+				.map(|class| {
+					let mut feature = GeoFeature::new(Geometry::new_point([1.0, 2.0]));
+					feature.properties = GeoProperties::from(vec![("class", GeoValue::from(*class))]);
+					feature
+				})
+				.collect();
+			VectorTileLayer::from_features(name.to_string(), features, 4096, 1)
+		};
+		Ok(VectorTile::new(vec![
+			layer("water", &["lake"])?,
+			layer("landuse", &["forest", "farmland"])?,
+			layer("roads", &["primary"])?,
+			layer("landuse_overlay", &["wetland"])?,
+		]))
+	}
+
+	/// Returns the layer names and the properties of their features.
+	fn describe(tile: &VectorTile) -> Result<Vec<String>> {
+		tile
+			.layers
+			.iter()
+			.map(|layer| {
+				let properties = layer
+					.to_features()?
+					.iter()
+					.map(|feature| {
+						let (key, value) = feature.properties.iter().next().unwrap();
+						format!("{key}={value}")
+					})
+					.collect::<Vec<_>>();
+				Ok(format!("{}: {}", layer.name, properties.join(" ")))
+			})
+			.collect()
+	}
+
+	fn merge(args: &str) -> Result<Vec<String>> {
+		let mut tile = make_tile()?;
+		runner(args)?.merge_layers(&mut tile)?;
+		describe(&tile)
+	}
+
+	#[test]
+	fn test_merge() -> Result<()> {
+		assert_eq!(
+			merge("layers=[landuse,landuse_overlay]")?,
+			[
+				"water: class=lake",
+				"landuse: class=forest class=farmland class=wetland",
+				"roads: class=primary"
+			]
+		);
+		assert_eq!(
+			merge("layers=[landuse_overlay,landuse] target=landcover prefixes=[\"overlay_\",\"\"]")?,
+			[
+				"water: class=lake",
+				"landcover: overlay_class=wetland class=forest class=farmland",
+				"roads: class=primary"
+			]
+		);
+		// an existing target layer comes first
+		assert_eq!(
+			merge("layers=landuse_overlay target=water")?,
+			[
+				"water: class=lake class=wetland",
+				"landuse: class=forest class=farmland",
+				"roads: class=primary"
+			]
+		);
+		// tiles without these layers are unchanged
+		assert_eq!(merge("layers=[buildings]")?, describe(&make_tile()?)?);
+
+		assert!(runner("target=landuse").is_err());
+		assert!(runner("layers=[a,b] prefixes=[x]").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_merge_vector_layers() -> Result<()> {
+		let mut vector_layers = VectorLayers::from_json(&versatiles_core::json::parse_json_str(
+			r#"[{"id":"landuse","fields":{"class":"String"}},{"id":"landuse_overlay","fields":{"class":"String"},"minzoom":5},{"id":"roads","fields":{}}]"#,
+		)?)?;
+		runner("layers=[landuse,landuse_overlay] prefixes=[\"\",o_]")?.merge_vector_layers(&mut vector_layers);
+		assert_eq!(
+			vector_layers.as_json_value().stringify(),
+			r#"[{"fields":{"class":"String","o_class":"String"},"id":"landuse","minzoom":5},{"fields":{},"id":"roads"}]"#
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | vector_merge_layers layers=[mock,other] target=merged")
+			.await?;
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 1)?).await?.unwrap();
+		let tile = VectorTile::from_blob(&blob)?;
+		assert_eq!(tile.layers.len(), 1);
+		assert_eq!(tile.layers[0].name, "merged");
+		assert_eq!(tile.layers[0].features.len(), 1);
+
+		assert!(factory
+			.operation_from_vpl("from_debug format=png | vector_merge_layers layers=[a,b]")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,341 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{
+	collections::{BTreeSet, HashMap},
+	sync::Arc,
+};
+use versatiles_core::{
+	tilejson::{TileJSON, VectorLayers},
+	types::*,
+	utils::decompress,
+};
+use versatiles_geometry::vector_tile::VectorTile;
+
+/// Key of the TileJSON entry with the mapping: a list of `"code=key"` items.
+pub(super) const MAPPING_KEY: &str = "minified_keys";
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Renames long property keys of vector tiles to short codes, e.g. `population` to `a`, to shrink tiles with many properties.
+/// The keys are taken from the `vector_layers` of the source. The mapping is stored as "minified_keys" in the TileJSON, e.g. `["a=population"]`, and `vector_layers` lists the codes.
+/// `vector_expand_keys` restores the original keys.
+struct Args {
+	/// Keys that keep their names, e.g. `keep=[name,class]` for keys that clients use without reading the mapping.
+	keep: Vec<String>,
+}
+
+/// Mapping between property keys and their codes.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct KeyMapping {
+	/// code of every renamed key
+	codes: HashMap<String, String>,
+	/// key of every code
+	keys: HashMap<String, String>,
+}
+
+impl KeyMapping {
+	/// Assigns codes to all fields of `vector_layers`, except to `keep` and to keys not longer than their code.
+	fn from_vector_layers(vector_layers: &VectorLayers, keep: &[String]) -> KeyMapping {
+		let fields: BTreeSet<&String> = vector_layers.0.values().flat_map(|layer| layer.fields.keys()).collect();
+		let mut mapping = KeyMapping::default();
+		let mut index = 0;
+		for key in fields.iter().filter(|key| !keep.contains(key)) {
+			// codes must not be confused with keys that are not renamed
+			let code = loop {
+				let code = get_code(index);
+				index += 1;
+				if !fields.contains(&code) && !keep.contains(&code) {
+					break code;
+				}
+			};
+			if code.len() >= key.len() {
+				index -= 1;
+				continue;
+			}
+			mapping.insert(code, key.to_string());
+		}
+		mapping
+	}
+
+	/// Reads the mapping from the TileJSON entry "minified_keys".
+	pub(super) fn from_tilejson(tilejson: &TileJSON) -> Result<Option<KeyMapping>> {
+		let Some(list) = tilejson.get_list(MAPPING_KEY) else {
+			return Ok(None);
+		};
+		let mut mapping = KeyMapping::default();
+		for item in list {
+			let (code, key) = item
+				.split_once('=')
+				.with_context(|| format!("item \"{item}\" of \"{MAPPING_KEY}\" must be \"code=key\""))?;
+			mapping.insert(code.to_string(), key.to_string());
+		}
+		Ok(Some(mapping))
+	}
+
+	fn insert(&mut self, code: String, key: String) {
+		self.codes.insert(key.clone(), code.clone());
+		self.keys.insert(code, key);
+	}
+
+	/// Returns the mapping as TileJSON list, sorted by key.
+	fn as_list(&self) -> Vec<String> {
+		let mut items: Vec<(&String, &String)> = self.codes.iter().collect();
+		items.sort();
+		items.into_iter().map(|(key, code)| format!("{code}={key}")).collect()
+	}
+
+	/// Renames keys to codes if `minify` is set, otherwise codes to keys.
+	pub(super) fn rename(&self, name: &str, minify: bool) -> Option<String> {
+		let map = if minify { &self.codes } else { &self.keys };
+		map.get(name).cloned()
+	}
+
+	/// Renames the fields of `vector_layers` like the keys of the tiles.
+	pub(super) fn rename_fields(&self, vector_layers: &mut VectorLayers, minify: bool) {
+		for layer in vector_layers.0.values_mut() {
+			layer.fields = std::mem::take(&mut layer.fields)
+				.into_iter()
+				.map(|(name, value)| (self.rename(&name, minify).unwrap_or(name), value))
+				.collect();
+		}
+	}
+
+	/// Renames the keys in all layers of a vector tile.
+	pub(super) fn rename_tile(&self, blob: Blob, tile_compression: &TileCompression, minify: bool) -> Result<Blob> {
+		let blob = decompress(blob, tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+		for layer in tile.layers.iter_mut() {
+			layer.rename_keys(|key| self.rename(key, minify))?;
+		}
+		tile.to_blob().context("Failed to convert VectorTile to Blob")
+	}
+}
+
+/// Returns the code with the number `index`: "a" to "z", then "aa", "ab" and so on.
+fn get_code(mut index: usize) -> String {
+	let mut code = Vec::new();
+	loop {
+		code.push(b'a' + (index % 26) as u8);
+		if index < 26 {
+			break;
+		}
+		index = index / 26 - 1;
+	}
+	code.reverse();
+	String::from_utf8(code).unwrap()
+}
+
+#[derive(Debug)]
+struct Runner {
+	mapping: KeyMapping,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn run(&self, blob: Blob) -> Result<Blob> {
+		self.mapping.rename_tile(blob, &self.tile_compression, true)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let mut tilejson = source.get_tilejson().clone();
+			if KeyMapping::from_tilejson(&tilejson)?.is_some() {
+				bail!("the keys of the source are minified already");
+			}
+			let mapping = KeyMapping::from_vector_layers(&tilejson.vector_layers, &args.keep);
+			ensure!(
+				!mapping.codes.is_empty(),
+				"the vector_layers of the source contain no keys to minify"
+			);
+			mapping.rename_fields(&mut tilejson.vector_layers, true);
+			tilejson.set_list(MAPPING_KEY, mapping.as_list())?;
+
+			let runner = Arc::new(Runner {
+				mapping,
+				tile_compression: parameters.tile_compression,
+			});
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		Ok(match self.source.get_tile_data(coord).await? {
+			Some(blob) => Some(self.runner.run(blob)?),
+			None => None,
+		})
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.map_blob_parallel(move |blob| runner.run(blob).unwrap())
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_minify_keys"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_core::json::parse_json_str;
+	use versatiles_geometry::{vector_tile::VectorTileLayer, GeoFeature, GeoProperties, GeoValue, Geometry};
+
+	fn get_vector_layers() -> Result<VectorLayers> {
+		VectorLayers::from_json(&parse_json_str(
+			r#"[{"id":"places","fields":{"name":"String","population":"Number","id":"Number"}},{"id":"roads","fields":{"surface":"String","name":"String"}}]"#,
+		)?)
+	}
+
+	#[test]
+	fn test_get_code() {
+		assert_eq!(get_code(0), "a");
+		assert_eq!(get_code(25), "z");
+		assert_eq!(get_code(26), "aa");
+		assert_eq!(get_code(27), "ab");
+		assert_eq!(get_code(26 * 27), "aaa");
+	}
+
+	#[test]
+	fn test_short_keys() -> Result<()> {
+		let vector_layers =
+			VectorLayers::from_json(&parse_json_str(r#"[{"id":"l","fields":{"a":"","x":"","id":""}}]"#)?)?;
+		// one letter keys are not renamed, and their names are not used as codes
+		assert_eq!(KeyMapping::from_vector_layers(&vector_layers, &[]).as_list(), ["b=id"]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_mapping() -> Result<()> {
+		let mut vector_layers = get_vector_layers()?;
+		let mapping = KeyMapping::from_vector_layers(&vector_layers, &[String::from("name")]);
+		assert_eq!(mapping.as_list(), ["a=id", "b=population", "c=surface"]);
+
+		mapping.rename_fields(&mut vector_layers, true);
+		assert_eq!(
+			vector_layers.as_json_value().stringify(),
+			r#"[{"fields":{"a":"Number","b":"Number","name":"String"},"id":"places"},{"fields":{"c":"String","name":"String"},"id":"roads"}]"#
+		);
+		mapping.rename_fields(&mut vector_layers, false);
+		assert_eq!(vector_layers, get_vector_layers()?);
+
+		let mut tilejson = TileJSON::default();
+		tilejson.set_list(MAPPING_KEY, mapping.as_list())?;
+		assert_eq!(KeyMapping::from_tilejson(&tilejson)?, Some(mapping));
+		assert_eq!(KeyMapping::from_tilejson(&TileJSON::default())?, None);
+
+		tilejson.set_list(MAPPING_KEY, vec![String::from("population")])?;
+		assert_eq!(
+			KeyMapping::from_tilejson(&tilejson).unwrap_err().to_string(),
+			"item \"population\" of \"minified_keys\" must be \"code=key\""
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_rename_tile() -> Result<()> {
+		let mut feature = GeoFeature::new(Geometry::new_point([1.0, 2.0]));
+		feature.properties = GeoProperties::from(vec![
+			("name", GeoValue::from("Berlin")),
+			("population", GeoValue::from(3_800_000)),
+		]);
+		let layer = VectorTileLayer::from_features(String::from("places"), vec![feature], 4096, 1)?;
+		let blob = VectorTile::new(vec![layer]).to_blob()?;
+
+		let mapping = KeyMapping::from_vector_layers(&get_vector_layers()?, &[]);
+		let minified = mapping.rename_tile(blob.clone(), &TileCompression::Uncompressed, true)?;
+		assert!(minified.len() < blob.len());
+		let properties = &VectorTile::from_blob(&minified)?.layers[0].to_features()?[0].properties;
+		assert_eq!(properties.get("c"), Some(&GeoValue::from(3_800_000)));
+		assert_eq!(properties.get("b"), Some(&GeoValue::from("Berlin")));
+
+		let expanded = mapping.rename_tile(minified, &TileCompression::Uncompressed, false)?;
+		assert_eq!(
+			VectorTile::from_blob(&expanded)?.layers[0].to_features()?[0].properties,
+			VectorTile::from_blob(&blob)?.layers[0].to_features()?[0].properties
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() {
+		let factory = PipelineFactory::new_dummy();
+		let error = |vpl: &str| {
+			let factory = &factory;
+			let vpl = vpl.to_string();
+			async move { factory.operation_from_vpl(&vpl).await.unwrap_err().to_string() }
+		};
+		assert_eq!(
+			error("from_container filename=dummy | vector_minify_keys").await,
+			"the vector_layers of the source contain no keys to minify"
+		);
+		assert_eq!(
+			error("from_debug format=png | vector_minify_keys").await,
+			"source must be vector tiles"
+		);
+	}
+}
@@ -0,0 +1,280 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{collections::HashSet, sync::Arc};
+use versatiles_core::{
+	tilejson::{TileJSON, VectorLayers},
+	types::*,
+	utils::decompress,
+};
+use versatiles_geometry::vector_tile::{VectorTile, VectorTileLayer};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Renames the layers of each vector tile and changes their order, which is also the render order in some clients.
+/// Layers are renamed first, so `order` uses the new names.
+/// To combine layers with the same name, use `vector_merge_layers`.
+struct Args {
+	/// Names of the layers to rename, e.g. `layers=[water_name,poi]`.
+	layers: Vec<String>,
+	/// New names, one per layer in the order of `layers`, e.g. `names=[water_label,pois]`.
+	names: Vec<String>,
+	/// Layers that are moved to the beginning of the tile in this order, e.g. `order=[water,landuse,roads]`.
+	/// All other layers follow in their original order.
+	order: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	args: Args,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn new(args: Args, tile_compression: TileCompression) -> Result<Runner> {
+		ensure!(
+			args.layers.len() == args.names.len(),
+			"'names' must have one entry per layer"
+		);
+		ensure!(
+			!args.layers.is_empty() || !args.order.is_empty(),
+			"either 'layers' and 'names' or 'order' must be set"
+		);
+		ensure!(
+			args.names.iter().collect::<HashSet<_>>().len() == args.names.len(),
+			"'names' must not contain duplicates"
+		);
+		ensure!(
+			args.order.iter().collect::<HashSet<_>>().len() == args.order.len(),
+			"'order' must not contain duplicates"
+		);
+		Ok(Runner { args, tile_compression })
+	}
+
+	fn get_new_name<'a>(&'a self, name: &'a str) -> &'a str {
+		match self.args.layers.iter().position(|layer| layer == name) {
+			Some(index) => &self.args.names[index],
+			None => name,
+		}
+	}
+
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let blob = decompress(blob, &self.tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+		self.rename_layers(&mut tile)?;
+		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
+	}
+
+	fn rename_layers(&self, tile: &mut VectorTile) -> Result<()> {
+		let mut names = HashSet::new();
+		for layer in tile.layers.iter_mut() {
+			layer.name = self.get_new_name(&layer.name).to_string();
+			if !names.insert(layer.name.clone()) {
+				bail!(
+					"the tile contains the layer '{}' twice, use vector_merge_layers to combine layers",
+					layer.name
+				);
+			}
+		}
+
+		// the sort is stable, so layers not listed in `order` keep their order
+		let position = |layer: &VectorTileLayer| {
+			self
+				.args
+				.order
+				.iter()
+				.position(|name| name == &layer.name)
+				.unwrap_or(self.args.order.len())
+		};
+		tile.layers.sort_by_key(position);
+		Ok(())
+	}
+
+	/// Renames the descriptions of the layers in the TileJSON the same way as the tiles.
+	fn rename_vector_layers(&self, vector_layers: &mut VectorLayers) -> Result<()> {
+		let mut renamed = VectorLayers::default();
+		for (name, layer) in std::mem::take(&mut vector_layers.0) {
+			let name = self.get_new_name(&name).to_string();
+			if renamed.0.insert(name.clone(), layer).is_some() {
+				bail!("the source contains the layer '{name}' twice, use vector_merge_layers to combine layers");
+			}
+		}
+		*vector_layers = renamed;
+		Ok(())
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let runner = Arc::new(Runner::new(args, parameters.tile_compression)?);
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let mut tilejson = source.get_tilejson().clone();
+			runner.rename_vector_layers(&mut tilejson.vector_layers)?;
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(blob) = self.source.get_tile_data(coord).await? else {
+			return Ok(None);
+		};
+		self.runner.run(blob)
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_rename_layers"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::{GeoFeature, Geometry};
+
+	fn runner(args: &str) -> Result<Runner> {
+		let args = Args::from_vpl_node(&VPLNode::from_str(&format!("vector_rename_layers {args}"))?)?;
+		Runner::new(args, TileCompression::Uncompressed)
+	}
+
+	fn rename(args: &str) -> Result<Vec<String>> {
+		let layer = |name: &str| {
+			VectorTileLayer::from_features(
+				name.to_string(),
+				vec![GeoFeature::new(Geometry::new_point([1.0, 2.0]))],
+				4096,
+				1,
+			)
+		};
+		let mut tile = VectorTile::new(vec![layer("water")?, layer("landuse")?, layer("roads")?, layer("poi")?]);
+		runner(args)?.rename_layers(&mut tile)?;
+		Ok(tile.layers.into_iter().map(|layer| layer.name).collect())
+	}
+
+	#[test]
+	fn test_rename() -> Result<()> {
+		assert_eq!(
+			rename("layers=[poi,water] names=[pois,ocean]")?,
+			["ocean", "landuse", "roads", "pois"]
+		);
+		assert_eq!(rename("order=[roads,poi]")?, ["roads", "poi", "water", "landuse"]);
+		// order uses the new names, unknown layers are ignored
+		assert_eq!(
+			rename("layers=poi names=pois order=[pois,buildings,landuse]")?,
+			["pois", "landuse", "water", "roads"]
+		);
+		// swapping names is allowed
+		assert_eq!(
+			rename("layers=[water,roads] names=[roads,water]")?,
+			["roads", "landuse", "water", "poi"]
+		);
+
+		assert_eq!(
+			rename("layers=poi names=water").unwrap_err().to_string(),
+			"the tile contains the layer 'water' twice, use vector_merge_layers to combine layers"
+		);
+		assert!(runner("layers=[a,b] names=c").is_err());
+		assert!(runner("layers=[a,b] names=[c,c]").is_err());
+		assert!(runner("order=[a,a]").is_err());
+		assert!(runner("").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_rename_vector_layers() -> Result<()> {
+		let mut vector_layers = VectorLayers::from_json(&versatiles_core::json::parse_json_str(
+			r#"[{"id":"poi","fields":{"class":"String"},"minzoom":5},{"id":"roads","fields":{}}]"#,
+		)?)?;
+		runner("layers=[poi] names=[pois] order=[roads]")?.rename_vector_layers(&mut vector_layers)?;
+		assert_eq!(
+			vector_layers.as_json_value().stringify(),
+			r#"[{"fields":{"class":"String"},"id":"pois","minzoom":5},{"fields":{},"id":"roads"}]"#
+		);
+
+		assert!(runner("layers=[pois] names=[roads]")?
+			.rename_vector_layers(&mut vector_layers)
+			.is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | vector_rename_layers layers=mock names=renamed")
+			.await?;
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 1)?).await?.unwrap();
+		let tile = VectorTile::from_blob(&blob)?;
+		assert_eq!(tile.layers.len(), 1);
+		assert_eq!(tile.layers[0].name, "renamed");
+
+		assert!(factory
+			.operation_from_vpl("from_debug format=png | vector_rename_layers order=[a,b]")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,481 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_geometry::{
+	geo::clip_geometry,
+	vector_tile::{VectorTile, VectorTileLayer},
+	GeoFeature, Geometry,
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Converts vector tiles from one tile grid to another, e.g. to serve a dataset tiled in EPSG:4326 to clients expecting Web Mercator tiles.
+/// Supported are "EPSG:3857" (Web Mercator) and "EPSG:4326" (square tiles of equal size in degrees, so that the world covers the upper half of the grid, as written by tippecanoe).
+struct Args {
+	/// CRS of the source tiles. Defaults to "crs" in the metadata of the source, e.g. set by `from_container crs="EPSG:4326"`.
+	from: Option<String>,
+	/// CRS of the resulting tiles (default: "EPSG:3857").
+	to: Option<String>,
+	/// Size of the buffer around each tile in pixels of a 256 pixel tile (default: 5). Features are clipped to the tile plus buffer.
+	buffer: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Grid {
+	WebMercator,
+	Wgs84,
+}
+
+impl Grid {
+	fn parse(crs: &str) -> Result<Grid> {
+		Ok(match crs.to_uppercase().as_str() {
+			"EPSG:3857" | "EPSG:900913" => Grid::WebMercator,
+			"EPSG:4326" => Grid::Wgs84,
+			_ => bail!("unsupported CRS \"{crs}\", expected \"EPSG:3857\" or \"EPSG:4326\""),
+		})
+	}
+
+	fn name(&self) -> &str {
+		match self {
+			Grid::WebMercator => "EPSG:3857",
+			Grid::Wgs84 => "EPSG:4326",
+		}
+	}
+
+	/// Converts a pixel position within the tile `coord` to longitude and latitude, see [`TileCoord3::pixel_to_lonlat`].
+	fn pixel_to_lonlat(&self, coord: &TileCoord3, p: [f64; 2], tile_size: f64) -> [f64; 2] {
+		match self {
+			Grid::WebMercator => coord.pixel_to_lonlat(p, tile_size),
+			Grid::Wgs84 => {
+				let size = 2f64.powi(coord.z as i32);
+				let x = coord.x as f64 + p[0] / tile_size;
+				let y = coord.y as f64 + p[1] / tile_size;
+				[x / size * 360.0 - 180.0, 90.0 - y / size * 360.0]
+			}
+		}
+	}
+
+	/// Converts longitude and latitude to a pixel position within the tile `coord`, see [`TileCoord3::lonlat_to_pixel`].
+	fn lonlat_to_pixel(&self, coord: &TileCoord3, c: [f64; 2], tile_size: f64) -> [f64; 2] {
+		match self {
+			Grid::WebMercator => coord.lonlat_to_pixel(c, tile_size),
+			Grid::Wgs84 => {
+				let size = 2f64.powi(coord.z as i32);
+				let x = (c[0] + 180.0) / 360.0 * size;
+				let y = (90.0 - c[1].clamp(-90.0, 90.0)) / 360.0 * size;
+				[(x - coord.x as f64) * tile_size, (y - coord.y as f64) * tile_size]
+			}
+		}
+	}
+
+	/// Returns the tiles of this grid that cover the tiles `bbox` of the grid `other`, extended by `buffer` tiles.
+	fn covering_bbox(&self, other: &Grid, bbox: &TileBBox, buffer: f64) -> Result<TileBBox> {
+		let level = bbox.level;
+		// pixels of the first tile with a size of 1 are fractional tile coordinates
+		let origin = TileCoord3::new(0, 0, level)?;
+		let project = |x: f64, y: f64| self.lonlat_to_pixel(&origin, other.pixel_to_lonlat(&origin, [x, y], 1.0), 1.0);
+		let min = project(bbox.x_min as f64 - buffer, bbox.y_min as f64 - buffer);
+		let max = project((bbox.x_max + 1) as f64 + buffer, (bbox.y_max + 1) as f64 + buffer);
+
+		let last = 2f64.powi(level as i32) - 1.0;
+		let tile = |v: f64| v.floor().clamp(0.0, last) as u32;
+		// the maximum is an exclusive edge
+		let tile_max = |v: f64| (v.ceil() - 1.0).clamp(0.0, last) as u32;
+		TileBBox::new(level, tile(min[0]), tile(min[1]), tile_max(max[0]), tile_max(max[1]))
+	}
+
+	fn target_pyramid(&self, other: &Grid, pyramid: &TileBBoxPyramid) -> Result<TileBBoxPyramid> {
+		let mut result = TileBBoxPyramid::new_empty();
+		for bbox in pyramid.iter_levels() {
+			result.set_level_bbox(self.covering_bbox(other, bbox, 0.0)?);
+		}
+		Ok(result)
+	}
+}
+
+/// A layer of a source tile, with geometries in longitude and latitude.
+#[derive(Debug)]
+struct Layer {
+	name: String,
+	extent: u32,
+	version: u32,
+	features: Vec<GeoFeature>,
+}
+
+/// Applies `f` to every coordinate of a geometry. The result is always a multi geometry.
+fn map_coordinates(geometry: Geometry, f: impl Fn(&[f64; 2]) -> [f64; 2]) -> Geometry {
+	let mut geometry = geometry.into_multi();
+	let f = |c: &mut [f64; 2]| *c = f(c);
+	match &mut geometry {
+		Geometry::MultiPoint(g) => g.0.iter_mut().for_each(f),
+		Geometry::MultiLineString(g) => g.0.iter_mut().flatten().for_each(f),
+		Geometry::MultiPolygon(g) => g.0.iter_mut().flatten().flatten().for_each(f),
+		_ => unreachable!("geometries are converted to multi geometries"),
+	}
+	geometry
+}
+
+#[derive(Debug)]
+struct Reprojector {
+	from: Grid,
+	to: Grid,
+	/// buffer in pixels of a 256 pixel tile
+	buffer: f64,
+	source_compression: TileCompression,
+}
+
+impl Reprojector {
+	/// Returns the source tiles needed to build the tiles of `bbox`.
+	fn source_bbox(&self, bbox: &TileBBox) -> Result<TileBBox> {
+		self.from.covering_bbox(&self.to, bbox, self.buffer / 256.0)
+	}
+
+	fn decode(&self, coord: &TileCoord3, blob: Blob) -> Result<Vec<Layer>> {
+		let blob = decompress(blob, &self.source_compression)?;
+		let tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+
+		tile
+			.layers
+			.into_iter()
+			.map(|layer| {
+				let extent = layer.extent as f64;
+				let features = layer
+					.to_features()?
+					.into_iter()
+					.map(|mut feature| {
+						feature.geometry =
+							map_coordinates(feature.geometry, |p| self.from.pixel_to_lonlat(coord, *p, extent));
+						feature
+					})
+					.collect();
+				Ok(Layer {
+					name: layer.name,
+					extent: layer.extent,
+					version: layer.version,
+					features,
+				})
+			})
+			.collect()
+	}
+
+	/// Builds the tile `coord` from the decoded source tiles.
+	fn build_tile(&self, coord: &TileCoord3, sources: &[(TileCoord3, Vec<Layer>)]) -> Result<Option<Blob>> {
+		let source_bbox = self.source_bbox(&TileBBox::new(coord.z, coord.x, coord.y, coord.x, coord.y)?)?;
+
+		let mut layers: BTreeMap<&str, (u32, u32, Vec<GeoFeature>)> = BTreeMap::new();
+		for (source_coord, source_layers) in sources.iter() {
+			if !source_bbox.contains3(source_coord) {
+				continue;
+			}
+			for layer in source_layers.iter() {
+				let extent = layer.extent as f64;
+				let buffer = self.buffer * extent / 256.0;
+				let bbox_tile = [-buffer, -buffer, extent + buffer, extent + buffer];
+
+				let entry = layers
+					.entry(&layer.name)
+					.or_insert_with(|| (layer.extent, layer.version, Vec::new()));
+				for feature in layer.features.iter() {
+					let geometry = map_coordinates(feature.geometry.clone(), |c| self.to.lonlat_to_pixel(coord, *c, extent));
+					if let Some(geometry) = clip_geometry(&geometry, &bbox_tile) {
+						let mut clipped = GeoFeature::new(geometry);
+						clipped.id = feature.id.clone();
+						clipped.properties = feature.properties.clone();
+						entry.2.push(clipped);
+					}
+				}
+			}
+		}
+
+		let layers = layers
+			.into_iter()
+			.filter(|(_, (_, _, features))| !features.is_empty())
+			.map(|(name, (extent, version, features))| {
+				VectorTileLayer::from_features(name.to_string(), features, extent, version)
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		if layers.is_empty() {
+			return Ok(None);
+		}
+		Ok(Some(VectorTile::new(layers).to_blob()?))
+	}
+
+	fn build_tiles(&self, bbox: &TileBBox, source_tiles: Vec<(TileCoord3, Blob)>) -> Result<Vec<(TileCoord3, Blob)>> {
+		let sources = source_tiles
+			.into_iter()
+			.map(|(coord, blob)| Ok((coord, self.decode(&coord, blob)?)))
+			.collect::<Result<Vec<_>>>()?;
+
+		let mut result = Vec::new();
+		for coord in bbox.iter_coords() {
+			if let Some(blob) = self.build_tile(&coord, &sources)? {
+				result.push((coord, blob));
+			}
+		}
+		Ok(result)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	parameters: TilesReaderParameters,
+	reprojector: Arc<Reprojector>,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let mut tilejson = source.get_tilejson().clone();
+			let from = match (args.from.as_deref(), tilejson.get_str("crs")) {
+				(Some(crs), _) | (None, Some(crs)) => Grid::parse(crs)?,
+				(None, None) => {
+					bail!("the CRS of the source is unknown, please set \"from\" or the \"crs\" in its metadata")
+				}
+			};
+			let to = Grid::parse(args.to.as_deref().unwrap_or("EPSG:3857"))?;
+			ensure!(from != to, "source is already in {}", to.name());
+
+			let reprojector = Reprojector {
+				from,
+				to,
+				buffer: args.buffer.unwrap_or(5) as f64,
+				source_compression: parameters.tile_compression,
+			};
+
+			parameters.tile_compression = TileCompression::Uncompressed;
+			parameters.bbox_pyramid = to.target_pyramid(&from, &parameters.bbox_pyramid)?;
+			tilejson.set_string("crs", to.name())?;
+
+			Ok(Box::new(Self {
+				parameters,
+				reprojector: Arc::new(reprojector),
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+
+	async fn get_source_tiles(&self, bbox: &TileBBox) -> Result<Vec<(TileCoord3, Blob)>> {
+		let mut source_bbox = self.reprojector.source_bbox(bbox)?;
+		source_bbox.intersect_pyramid(&self.source.get_parameters().bbox_pyramid)?;
+		if source_bbox.is_empty() {
+			return Ok(Vec::new());
+		}
+		Ok(self.source.get_tile_stream(source_bbox).await.collect().await)
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		if !self.parameters.bbox_pyramid.contains_coord(coord) {
+			return Ok(None);
+		}
+
+		let bbox = TileBBox::new(coord.z, coord.x, coord.y, coord.x, coord.y)?;
+		let source_tiles = self.get_source_tiles(&bbox).await?;
+		Ok(self
+			.reprojector
+			.build_tiles(&bbox, source_tiles)?
+			.pop()
+			.map(|(_, blob)| blob))
+	}
+
+	async fn get_tile_stream(&self, mut bbox: TileBBox) -> TileStream {
+		bbox.intersect_pyramid(&self.parameters.bbox_pyramid).unwrap();
+		if bbox.is_empty() {
+			return TileStream::new_empty();
+		}
+
+		let bboxes: Vec<TileBBox> = bbox.iter_bbox_grid(32).collect();
+
+		TileStream::from_stream_iter(bboxes.into_iter().map(move |bbox| async move {
+			let source_tiles = self.get_source_tiles(&bbox).await.unwrap();
+			TileStream::from_vec(self.reprojector.build_tiles(&bbox, source_tiles).unwrap())
+		}))
+		.await
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_reproject"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Vector source in EPSG:4326 at zoom level 1 with a point at 90°E 45°N in tile 1/1/0.
+	#[derive(Debug)]
+	struct Wgs84Source {
+		parameters: TilesReaderParameters,
+		tilejson: TileJSON,
+	}
+
+	impl Wgs84Source {
+		fn new() -> Self {
+			let mut tilejson = TileJSON::default();
+			tilejson.set_string("crs", "EPSG:4326").unwrap();
+			let mut pyramid = TileBBoxPyramid::new_empty();
+			pyramid.set_level_bbox(TileBBox::new(1, 0, 0, 1, 0).unwrap());
+			Wgs84Source {
+				parameters: TilesReaderParameters::new(TileFormat::PBF, TileCompression::Uncompressed, pyramid),
+				tilejson,
+			}
+		}
+	}
+
+	#[async_trait]
+	impl OperationTrait for Wgs84Source {
+		fn get_parameters(&self) -> &TilesReaderParameters {
+			&self.parameters
+		}
+
+		fn get_tilejson(&self) -> &TileJSON {
+			&self.tilejson
+		}
+
+		async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+			if (coord.z, coord.x, coord.y) != (1, 1, 0) {
+				return Ok(None);
+			}
+			let feature = GeoFeature::new(Geometry::new_point([2048.0, 1024.0]));
+			let layer = VectorTileLayer::from_features(String::from("points"), vec![feature], 4096, 2)?;
+			Ok(Some(VectorTile::new(vec![layer]).to_blob()?))
+		}
+
+		async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+			let mut tiles = Vec::new();
+			for coord in bbox.iter_coords() {
+				if let Some(blob) = self.get_tile_data(&coord).await.unwrap() {
+					tiles.push((coord, blob));
+				}
+			}
+			TileStream::from_vec(tiles)
+		}
+	}
+
+	async fn build(args: &str) -> Result<Box<dyn OperationTrait>> {
+		let node = VPLNode::from_str(&format!("vector_reproject {args}"))?;
+		Operation::build(node, Box::new(Wgs84Source::new()), &PipelineFactory::new_dummy()).await
+	}
+
+	fn decode_points(blob: &Blob) -> Vec<Geometry> {
+		let tile = VectorTile::from_blob(blob).unwrap();
+		assert_eq!(tile.layers.len(), 1);
+		assert_eq!(tile.layers[0].name, "points");
+		tile.layers[0]
+			.to_features()
+			.unwrap()
+			.into_iter()
+			.map(|f| f.geometry)
+			.collect()
+	}
+
+	#[test]
+	fn test_grid() {
+		let coord = TileCoord3::new(17, 10, 5).unwrap();
+		for grid in [Grid::WebMercator, Grid::Wgs84] {
+			let p = grid.lonlat_to_pixel(&coord, [13.4, 52.5], 4096.0);
+			let c = grid.pixel_to_lonlat(&coord, p, 4096.0);
+			assert!((c[0] - 13.4).abs() < 1e-9 && (c[1] - 52.5).abs() < 1e-9);
+		}
+
+		let origin = TileCoord3::new(0, 0, 1).unwrap();
+		assert_eq!(Grid::Wgs84.lonlat_to_pixel(&origin, [-180.0, -90.0], 1.0), [0.0, 1.0]);
+
+		let bbox = TileBBox::new(1, 0, 0, 1, 0).unwrap();
+		assert_eq!(
+			Grid::WebMercator.covering_bbox(&Grid::Wgs84, &bbox, 0.0).unwrap(),
+			TileBBox::new(1, 0, 0, 1, 1).unwrap()
+		);
+		let bbox = TileBBox::new(2, 0, 0, 3, 3).unwrap();
+		assert_eq!(
+			Grid::Wgs84.covering_bbox(&Grid::WebMercator, &bbox, 0.0).unwrap(),
+			TileBBox::new(2, 0, 0, 3, 1).unwrap()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_reproject() -> Result<()> {
+		let operation = build("").await?;
+		assert_eq!(operation.get_tilejson().get_str("crs"), Some("EPSG:3857"));
+		assert_eq!(
+			format!("{:?}", operation.get_parameters().bbox_pyramid),
+			"[1: [0,0,1,1] (4)]"
+		);
+
+		let tiles = operation.get_tile_stream(TileBBox::new_full(1)?).await.collect().await;
+		assert_eq!(tiles.len(), 1);
+		assert_eq!(tiles[0].0, TileCoord3::new(1, 0, 1)?);
+
+		// 45°N is at y = 0.7194 of the Web Mercator tile grid at zoom level 1
+		assert_eq!(
+			decode_points(&tiles[0].1),
+			[Geometry::new_multi_point(vec![[2048, 2947]])]
+		);
+
+		let blob = operation.get_tile_data(&TileCoord3::new(1, 0, 1)?).await?.unwrap();
+		assert_eq!(blob, tiles[0].1);
+		assert!(operation.get_tile_data(&TileCoord3::new(1, 1, 1)?).await?.is_none());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_errors() {
+		let error = |args: &str| {
+			let args = args.to_string();
+			async move { build(&args).await.unwrap_err().to_string() }
+		};
+		assert_eq!(
+			error("from=\"EPSG:2056\"").await,
+			"unsupported CRS \"EPSG:2056\", expected \"EPSG:3857\" or \"EPSG:4326\""
+		);
+		assert_eq!(error("to=\"EPSG:4326\"").await, "source is already in EPSG:4326");
+	}
+}
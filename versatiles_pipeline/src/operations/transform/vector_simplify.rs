@@ -0,0 +1,278 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_geometry::{
+	math::{simplify_geometry, SimplifyAlgorithm},
+	vector_tile::{VectorTile, VectorTileLayer},
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Simplifies the lines and polygons of vector tiles, e.g. to shrink overzoomed tiles. Points are not changed,
+/// polygons and holes that collapse are removed.
+/// To use different tolerances for different zoom ranges, chain several operations, e.g.
+/// `vector_simplify tolerance=4 max_zoom=10 | vector_simplify tolerance=1 min_zoom=11`.
+struct Args {
+	/// Tolerance in pixels of a 256 pixel tile (default: 1).
+	tolerance: Option<f32>,
+	/// Simplification algorithm: "douglas_peucker" (default) or "visvalingam".
+	algorithm: Option<String>,
+	/// Name of the vector layer. By default all layers are simplified.
+	layer: Option<String>,
+	/// Only tiles from this zoom level on are simplified. Lower zoom levels are passed through unchanged.
+	min_zoom: Option<u8>,
+	/// Only tiles up to this zoom level are simplified. Higher zoom levels are passed through unchanged.
+	max_zoom: Option<u8>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	tolerance: f64,
+	algorithm: SimplifyAlgorithm,
+	layer: Option<String>,
+	min_zoom: u8,
+	max_zoom: u8,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn from_args(args: Args, tile_compression: TileCompression) -> Result<Runner> {
+		let algorithm = match args.algorithm.as_deref() {
+			None | Some("douglas_peucker") => SimplifyAlgorithm::DouglasPeucker,
+			Some("visvalingam") => SimplifyAlgorithm::Visvalingam,
+			Some(name) => bail!("unknown algorithm {name:?}, use \"douglas_peucker\" or \"visvalingam\""),
+		};
+		let tolerance = args.tolerance.unwrap_or(1.0) as f64;
+		ensure!(tolerance >= 0.0, "tolerance must not be negative");
+		Ok(Runner {
+			tolerance,
+			algorithm,
+			layer: args.layer,
+			min_zoom: args.min_zoom.unwrap_or(0),
+			max_zoom: args.max_zoom.unwrap_or(31),
+			tile_compression,
+		})
+	}
+
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let blob = decompress(blob, &self.tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+
+		for layer in tile.layers.iter_mut() {
+			if self.layer.as_ref().is_some_and(|name| name != &layer.name) {
+				continue;
+			}
+			let tolerance = self.tolerance * layer.extent as f64 / 256.0;
+			let features = layer
+				.to_features()?
+				.into_iter()
+				.filter_map(|mut feature| {
+					feature.geometry = simplify_geometry(&feature.geometry, tolerance, self.algorithm)?;
+					Some(feature)
+				})
+				.collect();
+			*layer = VectorTileLayer::from_features(layer.name.clone(), features, layer.extent, layer.version)?;
+		}
+
+		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
+	}
+
+	fn is_active(&self, level: u8) -> bool {
+		(self.min_zoom..=self.max_zoom).contains(&level)
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let runner = Arc::new(Runner::from_args(args, parameters.tile_compression)?);
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let tilejson = source.get_tilejson().clone();
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		let active = runner.is_active(bbox.level);
+		let stream = self.source.get_tile_stream(bbox).await;
+		if active {
+			stream.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+		} else {
+			let tile_compression = runner.tile_compression;
+			stream.map_blob_parallel(move |blob| decompress(blob, &tile_compression).unwrap())
+		}
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(blob) = self.source.get_tile_data(coord).await? else {
+			return Ok(None);
+		};
+		if self.runner.is_active(coord.z) {
+			self.runner.run(blob)
+		} else {
+			Ok(Some(decompress(blob, &self.runner.tile_compression)?))
+		}
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_simplify"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::{GeoFeature, Geometry};
+
+	fn runner(args: &str) -> Result<Runner> {
+		let args = Args::from_vpl_node(&VPLNode::from_str(&format!("vector_simplify {args}"))?)?;
+		Runner::from_args(args, TileCompression::Uncompressed)
+	}
+
+	/// A wiggly line and a small square, with an extent of 4096, so 1 pixel is 16 units.
+	fn make_tile() -> Result<Blob> {
+		let line: Vec<[f64; 2]> = (0..=100)
+			.map(|i| [i as f64 * 40.0, if i % 2 == 0 { 0.0 } else { 2.0 }])
+			.collect();
+		let square = vec![[0.0, 0.0], [20.0, 0.0], [20.0, 20.0], [0.0, 20.0], [0.0, 0.0]];
+		let features = vec![
+			GeoFeature::new(Geometry::new_line_string(line)),
+			GeoFeature::new(Geometry::new_polygon(vec![square])),
+			GeoFeature::new(Geometry::new_point([5.0, 5.0])),
+		];
+		let layer = VectorTileLayer::from_features(String::from("layer"), features, 4096, 1)?;
+		VectorTile::new(vec![layer]).to_blob()
+	}
+
+	fn get_geometries(blob: &Blob) -> Result<Vec<Geometry>> {
+		Ok(VectorTile::from_blob(blob)?.layers[0]
+			.to_features()?
+			.into_iter()
+			.map(|f| f.geometry)
+			.collect())
+	}
+
+	#[test]
+	fn test_simplify() -> Result<()> {
+		let blob = make_tile()?;
+		assert_eq!(get_geometries(&blob)?.len(), 3);
+
+		for algorithm in ["douglas_peucker", "visvalingam"] {
+			let result = runner(&format!("tolerance=4 algorithm={algorithm}"))?
+				.run(blob.clone())?
+				.unwrap();
+			let geometries = get_geometries(&result)?;
+			// the square is smaller than the tolerance and is removed, the line is straightened
+			assert_eq!(geometries.len(), 2, "{algorithm}");
+			assert_eq!(
+				geometries[0],
+				Geometry::new_multi_line_string(vec![vec![[0.0, 0.0], [4000.0, 0.0]]]),
+				"{algorithm}"
+			);
+			assert_eq!(geometries[1].get_type_name(), "MultiPoint", "{algorithm}");
+			assert!(result.len() < blob.len());
+		}
+
+		// a tolerance of 0.01 pixels keeps everything
+		let result = runner("tolerance=0.01")?.run(blob.clone())?.unwrap();
+		assert_eq!(get_geometries(&result)?, get_geometries(&blob)?);
+
+		assert!(runner("algorithm=unknown").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_layer_and_zoom() -> Result<()> {
+		let blob = make_tile()?;
+		let result = runner("layer=other")?.run(blob.clone())?.unwrap();
+		assert_eq!(get_geometries(&result)?.len(), 3);
+
+		let runner = runner("min_zoom=5 max_zoom=10")?;
+		assert!(!runner.is_active(4));
+		assert!(runner.is_active(5));
+		assert!(runner.is_active(10));
+		assert!(!runner.is_active(11));
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | vector_simplify tolerance=2 max_zoom=3")
+			.await?;
+		assert_eq!(
+			operation.get_parameters().tile_compression,
+			TileCompression::Uncompressed
+		);
+
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.unwrap();
+		assert_eq!(VectorTile::from_blob(&blob)?.layers.len(), 1);
+
+		let tiles = operation.get_tile_stream(TileBBox::new_full(4)?).await.collect().await;
+		assert_eq!(tiles.len(), 256);
+
+		assert!(factory
+			.operation_from_vpl("from_debug format=png | vector_simplify")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
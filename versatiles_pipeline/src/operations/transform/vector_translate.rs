@@ -0,0 +1,269 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
+use versatiles_geometry::{
+	geo::clip_geometry,
+	vector_tile::{VectorTile, VectorTileLayer},
+};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Moves the geometries of a vector layer by a fixed offset in pixels, e.g. to separate overlapping lines of two layers.
+/// Geometries are clipped to the tile plus buffer, so features moved out of the tile are removed.
+/// To move layers in different directions, chain several operations, e.g.
+/// `vector_translate layer=tram dx=-1 | vector_translate layer=bus dx=1`.
+struct Args {
+	/// Name of the vector layer.
+	layer: String,
+	/// Horizontal offset in pixels of a 256 pixel tile, positive values move to the right (default: 0).
+	dx: Option<f32>,
+	/// Vertical offset in pixels of a 256 pixel tile, positive values move down (default: 0).
+	dy: Option<f32>,
+	/// Size of the buffer around each tile in pixels of a 256 pixel tile (default: 5).
+	buffer: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Runner {
+	layer: String,
+	/// offset in pixels of a 256 pixel tile
+	offset: [f64; 2],
+	/// buffer in pixels of a 256 pixel tile
+	buffer: f64,
+	tile_compression: TileCompression,
+}
+
+impl Runner {
+	fn from_args(args: Args, tile_compression: TileCompression) -> Result<Runner> {
+		let offset = [args.dx.unwrap_or(0.0) as f64, args.dy.unwrap_or(0.0) as f64];
+		ensure!(offset.iter().all(|v| v.is_finite()), "dx and dy must be finite numbers");
+		Ok(Runner {
+			layer: args.layer,
+			offset,
+			buffer: args.buffer.unwrap_or(5) as f64,
+			tile_compression,
+		})
+	}
+
+	fn run(&self, blob: Blob) -> Result<Option<Blob>> {
+		let blob = decompress(blob, &self.tile_compression)?;
+		let mut tile = VectorTile::from_blob(&blob).context("Failed to create VectorTile from Blob")?;
+
+		let Some(index) = tile.layers.iter().position(|layer| layer.name == self.layer) else {
+			return Ok(Some(blob));
+		};
+		let layer = &tile.layers[index];
+
+		let scale = layer.extent as f64 / 256.0;
+		let [dx, dy] = self.offset.map(|v| v * scale);
+		let min = -self.buffer * scale;
+		let max = layer.extent as f64 + self.buffer * scale;
+
+		let features = layer
+			.to_features()?
+			.into_iter()
+			.filter_map(|mut feature| {
+				feature.geometry.transform(|c| *c = [c[0] + dx, c[1] + dy]);
+				feature.geometry = clip_geometry(&feature.geometry, &[min, min, max, max])?;
+				Some(feature)
+			})
+			.collect::<Vec<_>>();
+
+		if features.is_empty() {
+			tile.layers.remove(index);
+		} else {
+			tile.layers[index] =
+				VectorTileLayer::from_features(layer.name.clone(), features, layer.extent, layer.version)?;
+		}
+
+		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	runner: Arc<Runner>,
+	parameters: TilesReaderParameters,
+	source: Box<dyn OperationTrait>,
+	tilejson: TileJSON,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		_factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let mut parameters = source.get_parameters().clone();
+			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+			let runner = Arc::new(Runner::from_args(args, parameters.tile_compression)?);
+			parameters.tile_compression = TileCompression::Uncompressed;
+
+			let tilejson = source.get_tilejson().clone();
+
+			Ok(Box::new(Self {
+				runner,
+				parameters,
+				source,
+				tilejson,
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let runner = self.runner.clone();
+		self
+			.source
+			.get_tile_stream(bbox)
+			.await
+			.filter_map_blob_parallel(move |blob| runner.run(blob).unwrap())
+	}
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		let Some(blob) = self.source.get_tile_data(coord).await? else {
+			return Ok(None);
+		};
+		self.runner.run(blob)
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"vector_translate"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use versatiles_geometry::{GeoFeature, Geometry};
+
+	fn runner(args: &str) -> Result<Runner> {
+		let args = Args::from_vpl_node(&VPLNode::from_str(&format!("vector_translate {args}"))?)?;
+		Runner::from_args(args, TileCompression::Uncompressed)
+	}
+
+	/// A horizontal line through the tile and a point near the right edge in the layer "lines",
+	/// and a point in the layer "other", with an extent of 4096, so 1 pixel is 16 units.
+	fn make_tile() -> Result<Blob> {
+		let lines = VectorTileLayer::from_features(
+			String::from("lines"),
+			vec![
+				GeoFeature::new(Geometry::new_line_string(vec![[0.0, 100.0], [4096.0, 100.0]])),
+				GeoFeature::new(Geometry::new_point([4000.0, 2000.0])),
+			],
+			4096,
+			1,
+		)?;
+		let other = VectorTileLayer::from_features(
+			String::from("other"),
+			vec![GeoFeature::new(Geometry::new_point([100.0, 100.0]))],
+			4096,
+			1,
+		)?;
+		VectorTile::new(vec![lines, other]).to_blob()
+	}
+
+	fn get_geometries(blob: &Blob, layer: &str) -> Result<Vec<Geometry>> {
+		let tile = VectorTile::from_blob(blob)?;
+		let Some(layer) = tile.layers.iter().find(|l| l.name == layer) else {
+			return Ok(vec![]);
+		};
+		Ok(layer.to_features()?.into_iter().map(|f| f.geometry).collect())
+	}
+
+	#[test]
+	fn test_translate() -> Result<()> {
+		let blob = make_tile()?;
+
+		let result = runner("layer=lines dx=-2 dy=3")?.run(blob.clone())?.unwrap();
+		assert_eq!(
+			get_geometries(&result, "lines")?,
+			[
+				Geometry::new_multi_line_string(vec![vec![[-32.0, 148.0], [4064.0, 148.0]]]),
+				Geometry::new_multi_point(vec![[3968.0, 2048.0]]),
+			]
+		);
+		// other layers are not changed
+		assert_eq!(get_geometries(&result, "other")?, get_geometries(&blob, "other")?);
+
+		// geometries are clipped at the buffer of 5 pixels, i.e. 80 units
+		let result = runner("layer=lines dx=20")?.run(blob.clone())?.unwrap();
+		assert_eq!(
+			get_geometries(&result, "lines")?,
+			[Geometry::new_multi_line_string(vec![vec![
+				[320.0, 100.0],
+				[4176.0, 100.0]
+			]])]
+		);
+
+		// layers without remaining features are removed
+		let result = runner("layer=lines dy=-200")?.run(blob.clone())?.unwrap();
+		assert_eq!(get_geometries(&result, "lines")?, []);
+		assert_eq!(VectorTile::from_blob(&result)?.layers.len(), 1);
+
+		// tiles without the layer are not changed
+		assert_eq!(runner("layer=unknown dx=1")?.run(blob.clone())?.unwrap(), blob);
+
+		assert!(runner("dx=1").is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_operation() -> Result<()> {
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl("from_container filename=dummy | vector_translate layer=mock dx=1")
+			.await?;
+		assert_eq!(
+			operation.get_parameters().tile_compression,
+			TileCompression::Uncompressed
+		);
+
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.unwrap();
+		assert_eq!(VectorTile::from_blob(&blob)?.layers.len(), 1);
+
+		let tiles = operation.get_tile_stream(TileBBox::new_full(3)?).await.collect().await;
+		assert_eq!(tiles.len(), 64);
+
+		assert!(factory
+			.operation_from_vpl("from_debug format=png | vector_translate layer=mock")
+			.await
+			.is_err());
+		Ok(())
+	}
+}
@@ -1,5 +1,5 @@
 use crate::{
-	helpers::read_csv_file,
+	helpers::{is_sqlite_path, read_csv_rows, CsvOptions, SqliteTable},
 	traits::{OperationFactoryTrait, OperationTrait, TransformOperationFactoryTrait},
 	vpl::VPLNode,
 	PipelineFactory,
@@ -13,14 +13,18 @@ use std::{
 	sync::Arc,
 };
 use versatiles_core::{tilejson::TileJSON, types::*, utils::decompress};
-use versatiles_geometry::{vector_tile::VectorTile, GeoProperties};
+use versatiles_geometry::{vector_tile::VectorTile, GeoProperties, GeoValue};
 
 #[derive(versatiles_derive::VPLDecode, Clone, Debug)]
 /// Updates properties of vector tile features using data from an external source (e.g., CSV file). Matches features based on an ID field.
+/// Large tables can be read from a SQLite database or GeoPackage, where only the rows needed for a tile are looked up.
 struct Args {
-	/// Path to the data source file, e.g., `data_source_path="data.csv"`.
+	/// Path to the data source file, e.g., `data_source_path="data.csv"`. Files ending with `.sqlite`, `.sqlite3`, `.db` or `.gpkg` are read as SQLite databases.
 	data_source_path: String,
 
+	/// Name of the table, if the data source is a SQLite database or GeoPackage, e.g. `table_name="cities"`. The ID field in the data source should be indexed.
+	table_name: Option<String>,
+
 	/// Name of the vector layer to update.
 	layer_name: String,
 
@@ -38,13 +42,28 @@ struct Args {
 
 	/// If set, includes the ID field in the updated properties.
 	include_id: bool,
+
+	/// Separator of the CSV fields, e.g. `separator=";"` or `separator="tab"`. Detected automatically by default.
+	separator: Option<String>,
+
+	/// Types of CSV columns, e.g. `column_types="zip:string,population:int"`. Possible types are "string", "int", "float", "bool" and "auto" (default).
+	column_types: Option<String>,
+}
+
+/// Where the new properties come from
+#[derive(Debug)]
+enum DataSource {
+	/// all rows of a CSV file by their ID
+	Csv(HashMap<String, GeoProperties>),
+	/// a table that is queried for the IDs of every tile
+	Sqlite(SqliteTable),
 }
 
 #[derive(Debug)]
 struct Runner {
 	args: Args,
 	tile_compression: TileCompression,
-	properties_map: HashMap<String, GeoProperties>,
+	data_source: DataSource,
 }
 
 impl Runner {
@@ -59,9 +78,24 @@ impl Runner {
 				continue;
 			}
 
+			let rows;
+			let properties_map = match &self.data_source {
+				DataSource::Csv(map) => map,
+				DataSource::Sqlite(table) => {
+					let mut ids = Vec::<GeoValue>::new();
+					for feature in layer.features.iter() {
+						if let Some(id) = feature.decode_properties(layer)?.get(&self.args.id_field_tiles) {
+							ids.push(id.clone());
+						}
+					}
+					rows = self.fetch_rows(table, &ids)?;
+					&rows
+				}
+			};
+
 			layer.filter_map_properties(|mut prop| {
 				if let Some(id) = prop.get(&self.args.id_field_tiles) {
-					if let Some(new_prop) = self.properties_map.get(&id.to_string()) {
+					if let Some(new_prop) = properties_map.get(&id.to_string()) {
 						if self.args.replace_properties {
 							prop = new_prop.clone();
 						} else {
@@ -82,6 +116,18 @@ impl Runner {
 
 		Ok(Some(tile.to_blob().context("Failed to convert VectorTile to Blob")?))
 	}
+
+	fn fetch_rows(&self, table: &SqliteTable, ids: &[GeoValue]) -> Result<HashMap<String, GeoProperties>> {
+		let mut rows = table
+			.get_rows(ids)
+			.with_context(|| format!("Failed to read rows from '{}'", self.args.data_source_path))?;
+		if !self.args.include_id {
+			for properties in rows.values_mut() {
+				properties.remove(&self.args.id_field_data);
+			}
+		}
+		Ok(rows)
+	}
 }
 
 #[derive(Debug)]
@@ -103,13 +149,32 @@ impl Operation {
 	{
 		Box::pin(async move {
 			let args = Args::from_vpl_node(&vpl_node)?;
-			let data = read_csv_file(&factory.resolve_path(&args.data_source_path))
-				.await
+			let path = factory.resolve_path(&args.data_source_path);
+
+			if is_sqlite_path(&path) {
+				let table_name = args
+					.table_name
+					.as_deref()
+					.ok_or_else(|| anyhow!("'table_name' is required to read from a SQLite database"))?;
+				let table = SqliteTable::open(&path, table_name, &args.id_field_data)
+					.with_context(|| format!("Failed to open SQLite database '{}'", args.data_source_path))?;
+				let keys: BTreeSet<String> = table
+					.get_columns()
+					.iter()
+					.filter(|name| args.include_id || **name != args.id_field_data)
+					.cloned()
+					.collect();
+				return Self::build_with_source(args, DataSource::Sqlite(table), keys, source);
+			}
+
+			let options = CsvOptions::parse(args.separator.as_deref(), args.column_types.as_deref())?;
+			let rows = read_csv_rows(&path, &options)
 				.with_context(|| format!("Failed to read CSV file from '{}'", args.data_source_path))?;
 
-			let properties_map = data
-				.into_iter()
-				.map(|mut properties| {
+			let properties_map = rows
+				.map(|properties| {
+					let mut properties =
+						properties.with_context(|| format!("Failed to read CSV file from '{}'", args.data_source_path))?;
 					let key = properties
 						.get(&args.id_field_data)
 						.ok_or_else(|| anyhow!("Key '{}' not found in CSV data", args.id_field_data))
@@ -128,42 +193,51 @@ impl Operation {
 				.collect::<Result<HashMap<String, GeoProperties>>>()
 				.context("Failed to build properties map from CSV data")?;
 
-			let mut parameters = source.get_parameters().clone();
-			ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+			let keys: BTreeSet<String> = properties_map
+				.values()
+				.flat_map(|properties| properties.iter().map(|(key, _)| key.clone()))
+				.collect();
+			Self::build_with_source(args, DataSource::Csv(properties_map), keys, source)
+		})
+	}
 
-			let mut tilejson = source.get_tilejson().clone();
-			if let Some(layer) = tilejson.vector_layers.0.get_mut(&args.layer_name) {
-				let mut all_keys = BTreeSet::<String>::new();
-				for prop in properties_map.values() {
-					for (k, _) in prop.iter() {
-						if !prop.0.contains_key(k) {
-							all_keys.insert(k.clone());
-						}
-					}
-				}
-				if args.replace_properties {
-					layer.fields.clear();
-				}
-				for key in all_keys {
-					layer.fields.insert(key, "automatically added field".to_string());
-				}
+	/// `keys` are the names of all properties that the data source can add.
+	fn build_with_source(
+		args: Args,
+		data_source: DataSource,
+		keys: BTreeSet<String>,
+		source: Box<dyn OperationTrait>,
+	) -> Result<Box<dyn OperationTrait>> {
+		let mut parameters = source.get_parameters().clone();
+		ensure!(parameters.tile_format == TileFormat::PBF, "source must be vector tiles");
+
+		let mut tilejson = source.get_tilejson().clone();
+		if let Some(layer) = tilejson.vector_layers.0.get_mut(&args.layer_name) {
+			if args.replace_properties {
+				layer.fields.clear();
+			}
+			for key in keys {
+				layer
+					.fields
+					.entry(key)
+					.or_insert_with(|| "automatically added field".to_string());
 			}
+		}
 
-			let runner = Arc::new(Runner {
-				args,
-				properties_map,
-				tile_compression: parameters.tile_compression,
-			});
+		let runner = Arc::new(Runner {
+			args,
+			data_source,
+			tile_compression: parameters.tile_compression,
+		});
 
-			parameters.tile_compression = TileCompression::Uncompressed;
+		parameters.tile_compression = TileCompression::Uncompressed;
 
-			Ok(Box::new(Self {
-				runner,
-				parameters,
-				source,
-				tilejson,
-			}) as Box<dyn OperationTrait>)
-		})
+		Ok(Box::new(Self {
+			runner,
+			parameters,
+			source,
+			tilejson,
+		}) as Box<dyn OperationTrait>)
 	}
 }
 
@@ -243,15 +317,18 @@ mod tests {
 		let runner = Runner {
 			args: Args {
 				data_source_path: "data.csv".to_string(),
+				table_name: None,
 				id_field_tiles: "id".to_string(),
 				id_field_data: "id".to_string(),
 				layer_name: "test_layer".to_string(),
 				replace_properties: false,
 				remove_non_matching: false,
 				include_id: false,
+				separator: None,
+				column_types: None,
 			},
 			tile_compression: TileCompression::Uncompressed,
-			properties_map,
+			data_source: DataSource::Csv(properties_map),
 		};
 
 		let blob = create_sample_vector_tile_blob();
@@ -353,4 +430,41 @@ mod tests {
 		);
 		Ok(())
 	}
+
+	async fn run_sqlite(table_name: &str, include_id: bool) -> Result<String> {
+		let temp_file = NamedTempFile::new("test.sqlite")?;
+		let conn = r2d2_sqlite::rusqlite::Connection::open(&temp_file)?;
+		conn.execute_batch(
+			"CREATE TABLE data (data_id INTEGER PRIMARY KEY, value TEXT, empty TEXT);
+			INSERT INTO data VALUES (0, 'test', NULL), (1, 'other', NULL);",
+		)?;
+		drop(conn);
+
+		let factory = PipelineFactory::new_dummy();
+		let operation = factory
+			.operation_from_vpl(&format!(
+				"from_container filename=dummy | vectortiles_update_properties data_source_path=\"{}\" table_name={table_name} id_field_tiles=x id_field_data=data_id layer_name=mock include_id={include_id}",
+				temp_file.to_str().unwrap().replace("\\", "\\\\")
+			))
+			.await?;
+
+		let blob = operation.get_tile_data(&TileCoord3::new(0, 0, 0)?).await?.unwrap();
+		let tile = VectorTile::from_blob(&blob)?;
+		let properties = tile.layers[0].features[0].decode_properties(&tile.layers[0])?;
+		Ok(format!("{properties:?}"))
+	}
+
+	#[tokio::test]
+	async fn test_run_sqlite() -> Result<()> {
+		assert_eq!(
+			run_sqlite("data", false).await?,
+			"{\"filename\": String(\"dummy\"), \"value\": String(\"test\"), \"x\": UInt(0), \"y\": UInt(0), \"z\": UInt(0)}"
+		);
+		assert_eq!(
+			run_sqlite("data", true).await?,
+			"{\"data_id\": UInt(0), \"filename\": String(\"dummy\"), \"value\": String(\"test\"), \"x\": UInt(0), \"y\": UInt(0), \"z\": UInt(0)}"
+		);
+		assert!(run_sqlite("missing", false).await.is_err());
+		Ok(())
+	}
 }
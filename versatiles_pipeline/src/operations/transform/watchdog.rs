@@ -0,0 +1,420 @@
+use crate::{traits::*, vpl::VPLNode, PipelineFactory};
+use anyhow::{bail, ensure, Result};
+use async_trait::async_trait;
+use futures::{future::BoxFuture, Future, StreamExt};
+use std::{
+	fmt::Debug,
+	fs::File,
+	io::Write,
+	sync::{
+		atomic::{AtomicU8, AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+	time::Duration,
+};
+use tokio::{runtime::Handle, task::spawn_blocking, time::timeout};
+use versatiles_core::{tilejson::TileJSON, types::*};
+
+#[derive(versatiles_derive::VPLDecode, Clone, Debug)]
+/// Guards the tiles of the source against pathological input.
+/// Every tile that takes longer than `timeout`, exceeds `max_size` or fails with an error is skipped and
+/// recorded in the quarantine report, instead of stalling or aborting the whole pipeline.
+///
+/// A tile that timed out can not be stopped: it keeps running on a background thread until it finishes.
+/// To keep these tiles from occupying all threads, the pipeline fails once more than `max_abandoned` of them
+/// are still running.
+struct Args {
+	/// Maximum time in seconds to generate a single tile. Defaults to 60.
+	timeout: Option<f32>,
+	/// Maximum number of timed out tiles, that may still be running in the background. Defaults to 32.
+	max_abandoned: Option<u32>,
+	/// Maximum size of a single tile in bytes. Defaults to no limit.
+	/// This limits the size of the generated tile, not the memory used while generating it.
+	max_size: Option<u32>,
+	/// Optional CSV file, relative to the VPL file, to which the quarantined tiles are written.
+	/// For example: `report="quarantine.csv"`. Quarantined tiles are always logged as warnings.
+	report: Option<String>,
+}
+
+#[derive(Debug)]
+struct Watchdog {
+	timeout: Duration,
+	/// number of timed out tasks that are still running
+	abandoned: Arc<AtomicUsize>,
+	max_abandoned: usize,
+	max_size: Option<u64>,
+	report: Option<Mutex<File>>,
+}
+
+impl Watchdog {
+	/// Runs `task` on a blocking thread, so that even CPU-bound work can be abandoned after the timeout.
+	/// An abandoned task keeps running in the background, but no longer holds up the stream.
+	async fn run<T: Send + 'static>(&self, task: impl Future<Output = Result<T>> + Send + 'static) -> Result<T> {
+		const RUNNING: u8 = 0;
+		const FINISHED: u8 = 1;
+		const ABANDONED: u8 = 2;
+
+		let handle = Handle::current();
+		let state = Arc::new(AtomicU8::new(RUNNING));
+		let (task_state, abandoned) = (state.clone(), self.abandoned.clone());
+		let task = spawn_blocking(move || {
+			let result = handle.block_on(task);
+			if task_state.swap(FINISHED, Ordering::SeqCst) == ABANDONED {
+				abandoned.fetch_sub(1, Ordering::SeqCst);
+			}
+			result
+		});
+
+		match timeout(self.timeout, task).await {
+			Ok(Ok(result)) => result,
+			Ok(Err(error)) => bail!("{error}"),
+			Err(_) => {
+				if state
+					.compare_exchange(RUNNING, ABANDONED, Ordering::SeqCst, Ordering::SeqCst)
+					.is_ok()
+				{
+					self.abandoned.fetch_add(1, Ordering::SeqCst);
+				}
+				bail!("timed out after {}s", self.timeout.as_secs_f32())
+			}
+		}
+	}
+
+	/// Fails if too many timed out tiles are still running, as they would eventually occupy all threads.
+	fn ensure_not_stalled(&self) -> Result<()> {
+		let abandoned = self.abandoned.load(Ordering::SeqCst);
+		ensure!(
+			abandoned <= self.max_abandoned,
+			"{abandoned} timed out tiles are still running, which exceeds max_abandoned={}",
+			self.max_abandoned
+		);
+		Ok(())
+	}
+
+	fn check_size(&self, blob: &Blob) -> Result<()> {
+		if let Some(max_size) = self.max_size {
+			ensure!(
+				blob.len() <= max_size,
+				"size of {} bytes exceeds {max_size} bytes",
+				blob.len()
+			);
+		}
+		Ok(())
+	}
+
+	fn quarantine(&self, coord: &TileCoord3, reason: &str) {
+		log::warn!("quarantined tile {}/{}/{}: {reason}", coord.z, coord.x, coord.y);
+		if let Some(file) = &self.report {
+			let reason = reason.replace('"', "\"\"");
+			let line = format!("{},{},{},\"{reason}\"\n", coord.z, coord.x, coord.y);
+			if let Err(error) = file.lock().unwrap().write_all(line.as_bytes()) {
+				log::error!("could not write quarantine report: {error}");
+			}
+		}
+	}
+
+	async fn get_tile(&self, source: Arc<Box<dyn OperationTrait>>, coord: TileCoord3) -> Result<Option<Blob>> {
+		self.ensure_not_stalled()?;
+		let result = self
+			.run(async move { source.get_tile_data(&coord).await })
+			.await
+			.and_then(|blob| {
+				if let Some(blob) = &blob {
+					self.check_size(blob)?;
+				}
+				Ok(blob)
+			});
+		match result {
+			Ok(blob) => Ok(blob),
+			Err(error) => {
+				self.quarantine(&coord, &error.to_string());
+				Ok(None)
+			}
+		}
+	}
+
+	/// Fetches a block of tiles at once. If that fails or times out, the block is retried tile by tile,
+	/// so that only the offending tiles are quarantined.
+	async fn get_block(
+		self: Arc<Self>,
+		source: Arc<Box<dyn OperationTrait>>,
+		bbox: TileBBox,
+	) -> Result<Vec<(TileCoord3, Blob)>> {
+		self.ensure_not_stalled()?;
+		let (block_source, block_bbox) = (source.clone(), bbox.clone());
+		let block = self
+			.run(async move { Ok(block_source.get_tile_stream(block_bbox).await.collect().await) })
+			.await;
+
+		if let Ok(tiles) = block {
+			return Ok(tiles
+				.into_iter()
+				.filter(|(coord, blob)| match self.check_size(blob) {
+					Ok(()) => true,
+					Err(error) => {
+						self.quarantine(coord, &error.to_string());
+						false
+					}
+				})
+				.collect());
+		}
+
+		futures::stream::iter(bbox.iter_coords())
+			.map(|coord| {
+				let watchdog = self.clone();
+				let source = source.clone();
+				async move { Ok(watchdog.get_tile(source, coord).await?.map(|blob| (coord, blob))) }
+			})
+			.buffer_unordered(num_cpus::get())
+			.collect::<Vec<Result<_>>>()
+			.await
+			.into_iter()
+			.filter_map(Result::transpose)
+			.collect()
+	}
+}
+
+#[derive(Debug)]
+struct Operation {
+	parameters: TilesReaderParameters,
+	source: Arc<Box<dyn OperationTrait>>,
+	tilejson: TileJSON,
+	watchdog: Arc<Watchdog>,
+}
+
+impl Operation {
+	fn build(
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &PipelineFactory,
+	) -> BoxFuture<'_, Result<Box<dyn OperationTrait>, anyhow::Error>>
+	where
+		Self: Sized + OperationTrait,
+	{
+		Box::pin(async move {
+			let args = Args::from_vpl_node(&vpl_node)?;
+
+			let seconds = args.timeout.unwrap_or(60.0);
+			ensure!(
+				seconds > 0.0 && seconds.is_finite(),
+				"timeout must be positive and finite, but is {seconds}"
+			);
+
+			let report = if let Some(filename) = &args.report {
+				let mut file = File::create(factory.resolve_path(filename))?;
+				file.write_all(b"z,x,y,reason\n")?;
+				Some(Mutex::new(file))
+			} else {
+				None
+			};
+
+			Ok(Box::new(Self {
+				parameters: source.get_parameters().clone(),
+				tilejson: source.get_tilejson().clone(),
+				source: Arc::new(source),
+				watchdog: Arc::new(Watchdog {
+					timeout: Duration::from_secs_f32(seconds),
+					abandoned: Arc::new(AtomicUsize::new(0)),
+					max_abandoned: args.max_abandoned.unwrap_or(32) as usize,
+					max_size: args.max_size.map(u64::from),
+					report,
+				}),
+			}) as Box<dyn OperationTrait>)
+		})
+	}
+}
+
+#[async_trait]
+impl OperationTrait for Operation {
+	fn get_parameters(&self) -> &TilesReaderParameters {
+		&self.parameters
+	}
+
+	fn get_tilejson(&self) -> &TileJSON {
+		&self.tilejson
+	}
+
+	async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+		self.watchdog.get_tile(self.source.clone(), *coord).await
+	}
+
+	async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+		let blocks: Vec<TileBBox> = bbox.iter_bbox_grid(32).collect();
+		TileStream::from_stream_iter(blocks.into_iter().map(|block| {
+			let watchdog = self.watchdog.clone();
+			let source = self.source.clone();
+			// like the other operations, panic on errors
+			async move { TileStream::from_vec(watchdog.get_block(source, block).await.unwrap()) }
+		}))
+		.await
+	}
+}
+
+pub struct Factory {}
+
+impl OperationFactoryTrait for Factory {
+	fn get_docs(&self) -> String {
+		Args::get_docs()
+	}
+	fn get_tag_name(&self) -> &str {
+		"watchdog"
+	}
+}
+
+#[async_trait]
+impl TransformOperationFactoryTrait for Factory {
+	async fn build<'a>(
+		&self,
+		vpl_node: VPLNode,
+		source: Box<dyn OperationTrait>,
+		factory: &'a PipelineFactory,
+	) -> Result<Box<dyn OperationTrait>> {
+		Operation::build(vpl_node, source, factory).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use std::{thread::sleep, time::Instant};
+
+	/// Source with tiles up to zoom level 2: 1/1/0 hangs, 1/0/1 fails and 2/*/* are 100 bytes large.
+	#[derive(Debug)]
+	struct PoisonedSource {
+		parameters: TilesReaderParameters,
+		tilejson: TileJSON,
+	}
+
+	impl PoisonedSource {
+		fn new() -> Self {
+			PoisonedSource {
+				parameters: TilesReaderParameters::new(
+					TileFormat::PBF,
+					TileCompression::Uncompressed,
+					TileBBoxPyramid::new_full(2),
+				),
+				tilejson: TileJSON::default(),
+			}
+		}
+	}
+
+	#[async_trait]
+	impl OperationTrait for PoisonedSource {
+		fn get_parameters(&self) -> &TilesReaderParameters {
+			&self.parameters
+		}
+
+		fn get_tilejson(&self) -> &TileJSON {
+			&self.tilejson
+		}
+
+		async fn get_tile_data(&self, coord: &TileCoord3) -> Result<Option<Blob>> {
+			match (coord.z, coord.x, coord.y) {
+				(1, 1, 0) => sleep(Duration::from_millis(1000)),
+				(1, 0, 1) => bail!("invalid geometry"),
+				(2, _, _) => return Ok(Some(Blob::from(vec![0u8; 100]))),
+				_ => {}
+			}
+			Ok(Some(Blob::from(format!("{},{},{}", coord.z, coord.x, coord.y))))
+		}
+
+		async fn get_tile_stream(&self, bbox: TileBBox) -> TileStream {
+			let mut tiles = Vec::new();
+			for coord in bbox.iter_coords() {
+				// like the other operations, panic on errors
+				if let Some(blob) = self.get_tile_data(&coord).await.unwrap() {
+					tiles.push((coord, blob));
+				}
+			}
+			TileStream::from_vec(tiles)
+		}
+	}
+
+	async fn build(args: &str) -> Result<Box<dyn OperationTrait>> {
+		let node = VPLNode::from_str(&format!("watchdog {args}"))?;
+		Operation::build(node, Box::new(PoisonedSource::new()), &PipelineFactory::new_dummy()).await
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_get_tile_data() -> Result<()> {
+		let operation = build("timeout=0.1 max_size=50").await?;
+
+		let tile = |z, x, y| {
+			let operation = &operation;
+			async move {
+				operation
+					.get_tile_data(&TileCoord3::new(x, y, z).unwrap())
+					.await
+					.unwrap()
+			}
+		};
+
+		assert_eq!(tile(1, 0, 0).await.unwrap().as_str(), "1,0,0");
+
+		let start = Instant::now();
+		assert!(tile(1, 1, 0).await.is_none());
+		assert!(start.elapsed() < Duration::from_millis(900));
+
+		assert!(tile(1, 0, 1).await.is_none());
+		assert!(tile(2, 0, 0).await.is_none());
+
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_get_tile_stream() -> Result<()> {
+		let dir = TempDir::new()?;
+		let report = dir.path().join("quarantine.csv");
+		let operation = build(&format!("timeout=0.2 max_size=50 report=\"{}\"", report.display())).await?;
+
+		let mut coords = Vec::new();
+		for z in 0..=2 {
+			let bbox = TileBBox::new_full(z)?;
+			let mut tiles = operation.get_tile_stream(bbox).await.collect().await;
+			coords.extend(tiles.drain(..).map(|(c, _)| (c.z, c.x, c.y)));
+		}
+		coords.sort();
+		assert_eq!(coords, [(0, 0, 0), (1, 0, 0), (1, 1, 1)]);
+
+		let mut lines: Vec<String> = std::fs::read_to_string(&report)?.lines().map(String::from).collect();
+		assert_eq!(lines.remove(0), "z,x,y,reason");
+		lines.sort();
+		assert_eq!(lines.len(), 18);
+		assert_eq!(lines[0], "1,0,1,\"invalid geometry\"");
+		assert_eq!(lines[1], "1,1,0,\"timed out after 0.2s\"");
+		assert_eq!(lines[2], "2,0,0,\"size of 100 bytes exceeds 50 bytes\"");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_invalid_timeout() {
+		assert_eq!(
+			build("timeout=0").await.unwrap_err().to_string(),
+			"timeout must be positive and finite, but is 0"
+		);
+		assert_eq!(
+			build("timeout=inf").await.unwrap_err().to_string(),
+			"timeout must be positive and finite, but is inf"
+		);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_max_abandoned() -> Result<()> {
+		let operation = build("timeout=0.1 max_abandoned=0").await?;
+		let hanging = TileCoord3::new(1, 0, 1)?;
+		let coord = TileCoord3::new(0, 0, 0)?;
+
+		assert!(operation.get_tile_data(&hanging).await?.is_none());
+		assert_eq!(
+			operation.get_tile_data(&coord).await.unwrap_err().to_string(),
+			"1 timed out tiles are still running, which exceeds max_abandoned=0"
+		);
+
+		// once the abandoned tile has finished, tiles are generated again
+		sleep(Duration::from_millis(1000));
+		assert!(operation.get_tile_data(&coord).await?.is_some());
+		Ok(())
+	}
+}
@@ -69,7 +69,14 @@ fn parse_bare_identifier(input: &str) -> IResult<&str, String, VerboseError<&str
 }
 
 fn parse_quoted_string(input: &str) -> IResult<&str, String, VerboseError<&str>> {
-	context("quoted string", delimited(char('\"'), parse_string, cut(char('\"'))))(input)
+	context(
+		"quoted string",
+		delimited(
+			char('\"'),
+			opt(parse_string).map(Option::unwrap_or_default),
+			cut(char('\"')),
+		),
+	)(input)
 }
 
 fn parse_array(input: &str) -> IResult<&str, Vec<String>, VerboseError<&str>> {
@@ -213,6 +220,7 @@ mod tests {
 			parse_quoted_string("\"foo\\\"bar\\\"\""),
 			Ok(("", "foo\"bar\"".to_string()))
 		);
+		assert_eq!(parse_quoted_string("\"\""), Ok(("", "".to_string())));
 		assert!(parse_quoted_string("\"foo").is_err());
 		assert!(parse_quoted_string("foo\"").is_err());
 	}
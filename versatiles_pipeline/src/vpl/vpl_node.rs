@@ -41,6 +41,11 @@ impl VPLNode {
 		self.required(field, self.get_property_string(field))
 	}
 
+	/// Returns all values of an array like `[a,b]`, or of a single value. Missing parameters are empty.
+	pub fn get_property_string_array(&self, field: &str) -> Vec<String> {
+		self.get_property_vec(field).cloned().unwrap_or_default()
+	}
+
 	pub fn get_property_bool_req(&self, field: &str) -> Result<bool> {
 		Ok(self
 			.get_property(field)?
@@ -220,6 +225,15 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_vplnode_get_property_string_array() -> Result<()> {
+		let node = VPLNode::from_str("node key1=[a, \"b c\"] key2=d")?;
+		assert_eq!(node.get_property_string_array("key1"), vec!["a", "b c"]);
+		assert_eq!(node.get_property_string_array("key2"), vec!["d"]);
+		assert!(node.get_property_string_array("key3").is_empty());
+		Ok(())
+	}
+
 	#[test]
 	fn test_vplnode_get_property_bool_req() -> Result<()> {
 		let node = VPLNode {